@@ -15,11 +15,15 @@
 //! The top-level application type.
 
 use std::cell::RefCell;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use crate::clipboard::Clipboard;
 use crate::error::Error;
+use crate::piet::Color;
 use crate::platform::application as platform;
 use crate::util;
 
@@ -30,9 +34,8 @@ use crate::util;
 ///
 /// # Note
 ///
-/// This is currently very limited in its functionality, and is currently
-/// designed to address a single case, which is handling menu commands when
-/// no window is open.
+/// This is currently very limited in its functionality. It handles menu
+/// commands when no window is open, and delivers [`PowerEvent`]s.
 ///
 /// It is possible that this will expand to cover additional functionality
 /// in the future.
@@ -40,6 +43,117 @@ pub trait AppHandler {
     /// Called when a menu item is selected.
     #[allow(unused_variables)]
     fn command(&mut self, id: u32) {}
+
+    /// Called when the system or its displays go to sleep or wake up.
+    ///
+    /// This is supported on Windows and macOS. With GTK, only
+    /// [`PowerEvent::Suspending`] and [`PowerEvent::Resumed`] are delivered,
+    /// and only if logind is running.
+    #[allow(unused_variables)]
+    fn power_event(&mut self, event: PowerEvent) {}
+
+    /// Called when the system switches between battery and external power,
+    /// or when the charge of the battery changes.
+    ///
+    /// This is supported on Windows and macOS, and with GTK if UPower is
+    /// running.
+    #[allow(unused_variables)]
+    fn power_status_changed(&mut self, status: PowerStatus) {}
+
+    /// Called when the user's preferred locales or regional settings change.
+    ///
+    /// This is supported on Windows and macOS.
+    fn locale_changed(&mut self) {}
+}
+
+/// A change in the power state of the system or its displays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerEvent {
+    /// The system is about to go to sleep.
+    ///
+    /// There is only a short time to react to this, so this is the place to
+    /// stop timers and animations, not to do lengthy work.
+    Suspending,
+    /// The system has woken up from sleep.
+    ///
+    /// Network connections may have been dropped while asleep.
+    Resumed,
+    /// The displays have been turned off.
+    DisplayOff,
+    /// The displays have been turned back on.
+    DisplayOn,
+}
+
+/// Where the system gets its power from, as returned by
+/// [`Application::power_status`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PowerStatus {
+    /// Whether the system is running on battery, rather than being plugged in.
+    pub on_battery: bool,
+    /// The charge of the battery in percent, or `None` if there is no
+    /// battery or its charge is unknown.
+    pub battery_percent: Option<u8>,
+}
+
+/// The user's preferences for formatting numbers and dates, as returned by
+/// [`Application::regional_settings`].
+///
+/// Each field is `None` if the platform has no such setting, or if it could
+/// not be read. The conventions of the user's locale apply in that case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RegionalSettings {
+    /// The separator between the whole and the fractional part of a number.
+    pub decimal_separator: Option<String>,
+    /// The separator between groups of digits in large numbers.
+    pub grouping_separator: Option<String>,
+    /// The pattern for short, numeric dates, in which `d`, `M` and `y` stand
+    /// for the day, month and year, such as `dd.MM.yyyy`.
+    pub short_date_pattern: Option<String>,
+    /// The day that weeks start on.
+    pub first_day_of_week: Option<Weekday>,
+}
+
+/// A day of the week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+/// Text editing settings from the user's system configuration.
+///
+/// Each field is `None` if the platform has no such setting, or if it could
+/// not be read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextSettings {
+    /// How long the caret stays visible (and then hidden) when it blinks.
+    ///
+    /// This is `Duration::ZERO` if the caret should not blink.
+    pub caret_blink_interval: Option<Duration>,
+    /// The width of the caret, in display points.
+    pub caret_width: Option<f64>,
+    /// The background color of selected text.
+    pub selection_color: Option<Color>,
+    /// The color of selected text.
+    pub selected_text_color: Option<Color>,
+}
+
+/// Mouse settings from the user's system configuration.
+///
+/// Each field is `None` if the platform has no such setting, or if it could
+/// not be read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MouseSettings {
+    /// The longest time between two clicks for them to count as a double click.
+    pub double_click_interval: Option<Duration>,
+    /// How far the pointer can move between two clicks for them to count as a
+    /// double click, in display points.
+    pub double_click_distance: Option<f64>,
 }
 
 /// The top level application object.
@@ -195,11 +309,47 @@ impl Application {
         self.platform_app.set_menu(menu.into_inner());
     }
 
+    /// Show a count on the application's icon, such as the number of unread
+    /// messages, or remove it with `None`.
+    ///
+    /// On macOS the count is shown as the dock tile's badge, and on Windows as
+    /// an overlay on the taskbar buttons of the application's windows. On GTK
+    /// it is sent to launchers that implement the Unity launcher API, which
+    /// need a `.desktop` file named after the application id. Elsewhere this
+    /// has no effect.
+    pub fn set_badge(&self, count: Option<u64>) {
+        self.platform_app.set_badge(count)
+    }
+
+    /// Add a file to the system's list of recently used documents.
+    ///
+    /// This is what the "Open Recent" menu of the dock on macOS, the jump
+    /// list on Windows, and the recent files of GTK file choosers show.
+    /// Elsewhere this has no effect.
+    ///
+    /// A relative `path` is taken to be relative to the current directory.
+    pub fn add_recent_document(&self, path: impl AsRef<Path>) {
+        self.platform_app
+            .add_recent_document(&absolute_path(path.as_ref()))
+    }
+
     /// Returns a handle to the system clipboard.
     pub fn clipboard(&self) -> Clipboard {
         self.platform_app.clipboard().into()
     }
 
+    /// Returns a handle to the primary selection, on platforms that have one.
+    ///
+    /// On X11 this holds the most recently selected text, which is pasted with
+    /// the middle mouse button. Elsewhere this returns `None`.
+    pub fn primary_selection(&self) -> Option<Clipboard> {
+        #[cfg(all(feature = "x11", target_os = "linux"))]
+        let primary = Some(self.platform_app.primary().into());
+        #[cfg(not(all(feature = "x11", target_os = "linux")))]
+        let primary = None;
+        primary
+    }
+
     /// Returns the current locale string.
     ///
     /// This should a [Unicode language identifier].
@@ -208,4 +358,81 @@ impl Application {
     pub fn get_locale() -> String {
         platform::Application::get_locale()
     }
+
+    /// Returns the user's preferred locales, most preferred first.
+    ///
+    /// These are [Unicode language identifiers], like the one returned by
+    /// [`get_locale`]. The list is empty if the preferences can not be
+    /// determined. When they change, [`AppHandler::locale_changed`] is called.
+    ///
+    /// [Unicode language identifiers]: https://unicode.org/reports/tr35/#Unicode_language_identifier
+    /// [`get_locale`]: Application::get_locale
+    pub fn preferred_locales() -> Vec<String> {
+        platform::Application::preferred_locales()
+    }
+
+    /// Returns the user's preferences for formatting numbers and dates.
+    ///
+    /// When they change, [`AppHandler::locale_changed`] is called.
+    pub fn regional_settings() -> RegionalSettings {
+        platform::Application::regional_settings()
+    }
+
+    /// Returns the user's text editing settings, such as the caret blink rate
+    /// and the selection color.
+    ///
+    /// When these settings change, [`WinHandler::system_settings_changed`] is
+    /// called on every window.
+    ///
+    /// [`WinHandler::system_settings_changed`]: crate::WinHandler::system_settings_changed
+    pub fn get_text_settings() -> TextSettings {
+        platform::Application::get_text_settings()
+    }
+
+    /// Returns the user's mouse settings, such as how fast a double click
+    /// has to be.
+    ///
+    /// When these settings change, [`WinHandler::system_settings_changed`] is
+    /// called on every window, on the platforms that report it.
+    ///
+    /// [`WinHandler::system_settings_changed`]: crate::WinHandler::system_settings_changed
+    pub fn get_mouse_settings() -> MouseSettings {
+        platform::Application::get_mouse_settings()
+    }
+
+    /// Returns whether the system is running on battery, and how much charge
+    /// the battery has left.
+    ///
+    /// If this can not be determined, the system is assumed to be plugged
+    /// in. When the status changes, [`AppHandler::power_status_changed`] is
+    /// called.
+    pub fn power_status() -> PowerStatus {
+        platform::Application::power_status()
+    }
+}
+
+/// Resolve `path` against the current directory, because the platforms
+/// either reject relative paths or would resolve them against a directory of
+/// their own.
+fn absolute_path(path: &Path) -> PathBuf {
+    match env::current_dir() {
+        Ok(dir) => dir.join(path),
+        Err(e) => {
+            tracing::warn!("failed to get the current directory: {}", e);
+            path.to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recent_documents_are_absolute() {
+        let dir = env::current_dir().unwrap();
+        assert_eq!(absolute_path(Path::new("notes.txt")), dir.join("notes.txt"));
+        let absolute = dir.join("letters").join("draft.txt");
+        assert_eq!(absolute_path(&absolute), absolute);
+    }
 }
@@ -13,8 +13,16 @@
 // limitations under the License.
 
 //! Interacting with the system pasteboard/clipboard.
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
 pub use crate::platform::clipboard as platform;
 
+#[cfg(all(feature = "image", feature = "image_png"))]
+use crate::image::{codecs::png::PngEncoder, ColorType};
+#[cfg(all(feature = "image", feature = "image_png"))]
+use crate::piet::{ImageBuf, ImageFormat};
+
 /// A handle to the system clipboard.
 ///
 /// To get access to the global clipboard, call [`Application::clipboard()`].
@@ -25,6 +33,14 @@ pub use crate::platform::clipboard as platform;
 /// Copying and pasting text is simple, using [`Clipboard::put_string`] and
 /// [`Clipboard::get_string`]. If this is all you need, you're in luck.
 ///
+/// # Working with images
+///
+/// With the `image` and `image_png` features, `Clipboard::put_image` and
+/// `Clipboard::get_image` copy and paste [`ImageBuf`]s, in the formats that
+/// other programs on the platform expect.
+///
+/// [`ImageBuf`]: crate::piet::ImageBuf
+///
 /// # Advanced usage
 ///
 /// When working with data more complicated than plaintext, you will generally
@@ -134,9 +150,21 @@ impl Clipboard {
         self.0.put_string(s);
     }
 
+    /// Put a string onto the system clipboard, marked as [sensitive].
+    ///
+    /// [sensitive]: ClipboardFormat::sensitive
+    pub fn put_sensitive_string(&mut self, s: impl AsRef<str>) {
+        self.put_formats(&[ClipboardFormat::from(s.as_ref()).sensitive()]);
+    }
+
     /// Put multi-format data on the system clipboard.
+    ///
+    /// If any of the formats is [sensitive], the platform's hints for
+    /// sensitive data are added.
+    ///
+    /// [sensitive]: ClipboardFormat::sensitive
     pub fn put_formats(&mut self, formats: &[ClipboardFormat]) {
-        self.0.put_formats(formats)
+        self.0.put_formats(&with_sensitive_hints(formats))
     }
 
     /// Get a string from the system clipboard, if one is available.
@@ -161,6 +189,57 @@ impl Clipboard {
         self.0.get_format(format)
     }
 
+    /// Put a list of files on the system clipboard, as a file manager does when
+    /// files are copied.
+    ///
+    /// This uses `CF_HDROP` on Windows, file URLs on macOS, and `text/uri-list`
+    /// on Linux.
+    pub fn put_files(&mut self, paths: &[impl AsRef<Path>]) {
+        let paths = paths.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.0.put_files(&paths)
+    }
+
+    /// Get a list of files from the system clipboard, if there is one.
+    pub fn get_files(&self) -> Option<Vec<PathBuf>> {
+        self.0.get_files()
+    }
+
+    /// Put an image onto the system clipboard.
+    ///
+    /// The image is stored as a PNG, and on Windows also as a `CF_DIB`, which
+    /// is what most Windows programs expect.
+    #[cfg(all(feature = "image", feature = "image_png"))]
+    pub fn put_image(&mut self, image: &ImageBuf) {
+        let mut formats = Vec::new();
+        match encode_png(image) {
+            Ok(png) => formats.push(ClipboardFormat::new(ClipboardFormat::PNG, png)),
+            Err(e) => tracing::warn!("failed to encode image for the clipboard: {}", e),
+        }
+        #[cfg(target_os = "windows")]
+        formats.push(ClipboardFormat::new("CF_DIB", platform::encode_dib(image)));
+        self.put_formats(&formats);
+    }
+
+    /// Get an image from the system clipboard, if one is available.
+    ///
+    /// This reads PNG data, and on Windows also a `CF_DIB`.
+    #[cfg(all(feature = "image", feature = "image_png"))]
+    pub fn get_image(&self) -> Option<ImageBuf> {
+        if let Some(image) = self
+            .get_format(ClipboardFormat::PNG)
+            .and_then(|png| decode_png(&png))
+        {
+            return Some(image);
+        }
+        #[cfg(target_os = "windows")]
+        {
+            if let Some(dib) = self.get_format("CF_DIB") {
+                return platform::decode_dib(&dib);
+            }
+        }
+        None
+    }
+
     /// For debugging: print the resolved identifiers for each type currently
     /// on the clipboard.
     #[doc(hidden)]
@@ -182,6 +261,7 @@ pub type FormatId = &'static str;
 pub struct ClipboardFormat {
     pub(crate) identifier: FormatId,
     pub(crate) data: Vec<u8>,
+    sensitive: bool,
 }
 
 impl ClipboardFormat {
@@ -191,7 +271,32 @@ impl ClipboardFormat {
     /// as the provided format.
     pub fn new(identifier: FormatId, data: impl Into<Vec<u8>>) -> Self {
         let data = data.into();
-        ClipboardFormat { identifier, data }
+        ClipboardFormat {
+            identifier,
+            data,
+            sensitive: false,
+        }
+    }
+
+    /// Mark this data as sensitive, such as a password copied from a password
+    /// manager.
+    ///
+    /// When sensitive data is put on the clipboard, it is tagged so that
+    /// clipboard managers leave it out of their history: with
+    /// `x-kde-passwordManagerHint` on Linux, by disabling clipboard history
+    /// and cloud sync on Windows, and with the `org.nspasteboard.ConcealedType`
+    /// type on macOS. Whether the hints are respected is up to the clipboard
+    /// manager.
+    pub fn sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
+
+    /// Whether this data has been marked as [sensitive].
+    ///
+    /// [sensitive]: ClipboardFormat::sensitive
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
     }
 }
 
@@ -208,6 +313,62 @@ impl From<&str> for ClipboardFormat {
     }
 }
 
+/// Add the platform's hints for sensitive data to `formats`, if any of them
+/// is sensitive.
+fn with_sensitive_hints(formats: &[ClipboardFormat]) -> Cow<'_, [ClipboardFormat]> {
+    if formats.iter().any(ClipboardFormat::is_sensitive) {
+        let mut formats = formats.to_vec();
+        formats.extend(
+            ClipboardFormat::SENSITIVE_HINTS
+                .iter()
+                .map(|(identifier, data)| ClipboardFormat::new(identifier, *data)),
+        );
+        Cow::Owned(formats)
+    } else {
+        Cow::Borrowed(formats)
+    }
+}
+
+#[cfg(all(feature = "image", feature = "image_png"))]
+fn encode_png(image: &ImageBuf) -> Result<Vec<u8>, crate::image::ImageError> {
+    let raw: Vec<u8> = image
+        .pixel_colors()
+        .flatten()
+        .flat_map(|color| {
+            let (r, g, b, a) = color.as_rgba8();
+            vec![r, g, b, a]
+        })
+        .collect();
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).encode(
+        &raw,
+        image.width() as u32,
+        image.height() as u32,
+        ColorType::Rgba8,
+    )?;
+    Ok(png)
+}
+
+#[cfg(all(feature = "image", feature = "image_png"))]
+fn decode_png(png: &[u8]) -> Option<ImageBuf> {
+    match crate::image::load_from_memory_with_format(png, crate::image::ImageFormat::Png) {
+        Ok(decoded) => {
+            let rgba = decoded.into_rgba8();
+            let (width, height) = rgba.dimensions();
+            Some(ImageBuf::from_raw(
+                rgba.into_raw(),
+                ImageFormat::RgbaSeparate,
+                width as usize,
+                height as usize,
+            ))
+        }
+        Err(e) => {
+            tracing::warn!("failed to decode image from the clipboard: {}", e);
+            None
+        }
+    }
+}
+
 impl From<platform::Clipboard> for Clipboard {
     fn from(src: platform::Clipboard) -> Clipboard {
         Clipboard(src)
@@ -220,6 +381,11 @@ cfg_if::cfg_if! {
             pub const PDF: &'static str = "com.adobe.pdf";
             pub const TEXT: &'static str = "public.utf8-plain-text";
             pub const SVG: &'static str = "public.svg-image";
+            pub const PNG: &'static str = "public.png";
+
+            /// Formats added alongside sensitive data; see <http://nspasteboard.org>.
+            pub(crate) const SENSITIVE_HINTS: &'static [(FormatId, &'static [u8])] =
+                &[("org.nspasteboard.ConcealedType", b"")];
         }
     } else {
         impl ClipboardFormat {
@@ -231,8 +397,73 @@ cfg_if::cfg_if! {
                     pub const TEXT: &'static str = "text/plain";
                 }
             }
+            cfg_if::cfg_if! {
+                if #[cfg(target_os = "windows")] {
+                    // the registered format that browsers and office programs use.
+                    pub const PNG: &'static str = "PNG";
+                } else {
+                    pub const PNG: &'static str = "image/png";
+                }
+            }
             pub const PDF: &'static str = "application/pdf";
             pub const SVG: &'static str = "image/svg+xml";
         }
+
+        impl ClipboardFormat {
+            cfg_if::cfg_if! {
+                if #[cfg(target_os = "windows")] {
+                    /// Formats added alongside sensitive data. These are DWORDs; zero
+                    /// means "no".
+                    pub(crate) const SENSITIVE_HINTS: &'static [(FormatId, &'static [u8])] = &[
+                        ("ExcludeClipboardContentFromMonitorProcessing", &[0; 4]),
+                        ("CanIncludeInClipboardHistory", &[0; 4]),
+                        ("CanUploadToCloudClipboard", &[0; 4]),
+                    ];
+                } else if #[cfg(target_os = "linux")] {
+                    /// Formats added alongside sensitive data, understood by KDE's
+                    /// Klipper and a few other clipboard managers.
+                    pub(crate) const SENSITIVE_HINTS: &'static [(FormatId, &'static [u8])] =
+                        &[("x-kde-passwordManagerHint", b"secret")];
+                } else {
+                    pub(crate) const SENSITIVE_HINTS: &'static [(FormatId, &'static [u8])] = &[];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn identifiers(formats: &[ClipboardFormat]) -> Vec<FormatId> {
+        formats.iter().map(|format| format.identifier).collect()
+    }
+
+    #[test]
+    fn sensitive_hints_added() {
+        let formats = [ClipboardFormat::from("hunter2").sensitive()];
+        let formats = with_sensitive_hints(&formats);
+
+        let mut expected = vec![ClipboardFormat::TEXT];
+        expected.extend(ClipboardFormat::SENSITIVE_HINTS.iter().map(|(id, _)| *id));
+        assert_eq!(identifiers(&formats), expected);
+        for (format, (_, data)) in formats[1..].iter().zip(ClipboardFormat::SENSITIVE_HINTS) {
+            assert_eq!(format.data, *data);
+        }
+        #[cfg(target_os = "linux")]
+        assert_eq!(formats[1].identifier, "x-kde-passwordManagerHint");
+    }
+
+    #[test]
+    fn no_hints_for_plain_data() {
+        let formats = [
+            ClipboardFormat::from("hello"),
+            ClipboardFormat::new(ClipboardFormat::SVG, "<svg/>"),
+        ];
+        assert_eq!(
+            identifiers(&with_sensitive_hints(&formats)),
+            [ClipboardFormat::TEXT, ClipboardFormat::SVG]
+        );
     }
 }
@@ -0,0 +1,82 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dragging data out of, and dropping data into, the application.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::kurbo::Point;
+
+type WriteFn = dyn FnMut(&mut dyn Write) -> io::Result<()>;
+
+/// A file that is dragged out of the application, and only written once it
+/// has been dropped.
+///
+/// This lets applications offer files that do not exist yet, such as a
+/// rendered image, without doing the work until the user drops them on the
+/// desktop or in a file manager. Dragging is started with
+/// [`WindowHandle::start_file_drag`].
+///
+/// [`WindowHandle::start_file_drag`]: crate::WindowHandle::start_file_drag
+pub struct FilePromise {
+    file_name: String,
+    write: Box<WriteFn>,
+}
+
+impl FilePromise {
+    /// Create a new `FilePromise` for a file called `file_name`.
+    ///
+    /// When the file is dropped, `write` is called on the main thread to
+    /// write its contents. It may be called more than once, if the file is
+    /// dropped in several places.
+    pub fn new(
+        file_name: impl Into<String>,
+        write: impl FnMut(&mut dyn Write) -> io::Result<()> + 'static,
+    ) -> Self {
+        FilePromise {
+            file_name: file_name.into(),
+            write: Box::new(write),
+        }
+    }
+
+    /// The name of the file, without a directory.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Write the contents of the file to `out`.
+    pub fn write(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        (self.write)(out)
+    }
+}
+
+impl fmt::Debug for FilePromise {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilePromise")
+            .field("file_name", &self.file_name)
+            .finish()
+    }
+}
+
+/// Something that is dragged over a window, such as files from a file manager
+/// or text from another application.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropEvent {
+    /// The position of the mouse, in display points relative to the window.
+    pub pos: Point,
+    /// The formats that the dragged data is available in, such as
+    /// `text/uri-list` or `UTF8_STRING`.
+    pub formats: Vec<String>,
+}
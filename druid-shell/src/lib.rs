@@ -50,6 +50,7 @@ mod application;
 mod clipboard;
 mod common_util;
 mod dialog;
+mod drag;
 mod error;
 mod hotkey;
 mod keyboard;
@@ -63,21 +64,29 @@ mod window;
 
 pub mod text;
 
-pub use application::{AppHandler, Application};
+pub use application::{
+    AppHandler, Application, MouseSettings, PowerEvent, PowerStatus, RegionalSettings,
+    TextSettings, Weekday,
+};
 pub use clipboard::{Clipboard, ClipboardFormat, FormatId};
 pub use common_util::Counter;
 pub use dialog::{FileDialogOptions, FileInfo, FileSpec};
+pub use drag::{DropEvent, FilePromise};
 pub use error::Error;
 pub use hotkey::{HotKey, RawMods, SysMods};
 pub use keyboard::{Code, IntoKey, KbKey, KeyEvent, KeyState, Location, Modifiers};
 pub use menu::Menu;
-pub use mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+pub use mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 pub use region::Region;
-pub use scale::{Scalable, Scale, ScaledArea};
-pub use screen::{Monitor, Screen};
+pub use scale::{Scalable, Scale, ScaledArea, StrokeAlignment};
+pub use screen::{AnchorSide, Monitor, MonitorSelector, Screen};
 pub use window::{
-    FileDialogToken, IdleHandle, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowBuilder,
-    WindowHandle, WindowLevel, WindowState,
+    FileDialogToken, IdleHandle, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler,
+    WindowBuilder, WindowHandle, WindowLevel, WindowState,
 };
+#[cfg(feature = "raw-win-handle")]
+pub use window::{ForeignWindow, RenderSurface};
 
 pub use keyboard_types;
+#[cfg(feature = "raw-win-handle")]
+pub use raw_window_handle;
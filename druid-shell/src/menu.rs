@@ -22,9 +22,12 @@ use crate::platform::menu as platform;
 ///
 /// # Configuring menus
 ///
-/// Currently, a menu and its items cannot be changed once created. If you need
-/// to change anything about a menu (for instance, disabling or selecting items)
-/// you need to create a new menu with the desired properties.
+/// A menu's structure cannot be changed once it has been created. The text and
+/// the state of the items of a window's menu can be changed with
+/// [`WindowHandle::update_menu_item`]; for anything else you need to create a
+/// new menu with the desired properties.
+///
+/// [`WindowHandle::update_menu_item`]: crate::WindowHandle::update_menu_item
 pub struct Menu(platform::Menu);
 
 impl Menu {
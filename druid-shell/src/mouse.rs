@@ -52,16 +52,35 @@ pub struct MouseEvent {
     /// move on scrolling. This polarity is consistent with the
     /// deltaX and deltaY values in a web [WheelEvent].
     ///
+    /// The delta is not changed for modifiers, so turning the wheel with shift
+    /// held down still scrolls vertically; the convention that this scrolls
+    /// horizontally is left to the application. macOS applies it itself.
+    ///
     /// [WheelEvent]: https://w3c.github.io/uievents/#event-type-wheel
     pub wheel_delta: Vec2,
+    /// The kind of device that caused the event.
+    ///
+    /// Platforms that turn touches into mouse events don't always say so, in
+    /// which case this is [`PointerType::Mouse`].
+    pub pointer_type: PointerType,
+}
+
+/// The kind of device that a [`MouseEvent`] came from.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum PointerType {
+    /// A mouse, trackpad or other indirect pointing device.
+    #[default]
+    Mouse,
+    /// A finger on a touchscreen.
+    Touch,
+    /// A pen or stylus.
+    Pen,
 }
 
 /// An indicator of which mouse button was pressed.
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
-#[repr(u8)]
 pub enum MouseButton {
     /// No mouse button.
-    // MUST BE FIRST (== 0)
     None,
     /// Left mouse button.
     Left,
@@ -69,13 +88,36 @@ pub enum MouseButton {
     Right,
     /// Middle mouse button.
     Middle,
-    /// First X button.
+    /// First X button, which is usually the *back* button.
     X1,
-    /// Second X button.
+    /// Second X button, which is usually the *forward* button.
     X2,
+    /// Any other button.
+    ///
+    /// The buttons are numbered as on the web, where the left button is `0`
+    /// and the X buttons are `3` and `4`, so this is always at least `5`.
+    /// These buttons are never part of [`MouseButtons`].
+    Other(u8),
 }
 
 impl MouseButton {
+    /// The button that navigates back, which is [`MouseButton::X1`].
+    pub const BACK: MouseButton = MouseButton::X1;
+    /// The button that navigates forward, which is [`MouseButton::X2`].
+    pub const FORWARD: MouseButton = MouseButton::X2;
+
+    /// The bit of this button in a [`MouseButtons`].
+    fn mask(self) -> u8 {
+        match self {
+            MouseButton::None | MouseButton::Other(_) => 0,
+            MouseButton::Left => 1 << 1,
+            MouseButton::Right => 1 << 2,
+            MouseButton::Middle => 1 << 3,
+            MouseButton::X1 => 1 << 4,
+            MouseButton::X2 => 1 << 5,
+        }
+    }
+
     /// Returns `true` if this is [`MouseButton::Left`].
     ///
     /// [`MouseButton::Left`]: #variant.Left
@@ -133,33 +175,33 @@ impl MouseButtons {
     /// Add the `button` to the set.
     #[inline]
     pub fn insert(&mut self, button: MouseButton) {
-        self.0 |= 1.min(button as u8) << button as u8;
+        self.0 |= button.mask();
     }
 
     /// Remove the `button` from the set.
     #[inline]
     pub fn remove(&mut self, button: MouseButton) {
-        self.0 &= !(1.min(button as u8) << button as u8);
+        self.0 &= !button.mask();
     }
 
     /// Builder-style method for adding the `button` to the set.
     #[inline]
     pub fn with(mut self, button: MouseButton) -> MouseButtons {
-        self.0 |= 1.min(button as u8) << button as u8;
+        self.0 |= button.mask();
         self
     }
 
     /// Builder-style method for removing the `button` from the set.
     #[inline]
     pub fn without(mut self, button: MouseButton) -> MouseButtons {
-        self.0 &= !(1.min(button as u8) << button as u8);
+        self.0 &= !button.mask();
         self
     }
 
     /// Returns `true` if the `button` is in the set.
     #[inline]
     pub fn contains(self, button: MouseButton) -> bool {
-        (self.0 & (1.min(button as u8) << button as u8)) != 0
+        self.0 & button.mask() != 0
     }
 
     /// Returns `true` if the set is empty.
@@ -305,3 +347,27 @@ impl std::fmt::Debug for Cursor {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn button_sets() {
+        let buttons = MouseButtons::new()
+            .with(MouseButton::Left)
+            .with(MouseButton::BACK)
+            .with(MouseButton::FORWARD);
+        assert_eq!(buttons.count(), 3);
+        assert!(buttons.contains(MouseButton::X1));
+        assert!(!buttons.contains(MouseButton::Middle));
+        assert!(!buttons.without(MouseButton::X2).contains(MouseButton::X2));
+
+        // the other buttons don't fit in the set, so they are never part of it
+        let mut buttons = MouseButtons::new();
+        buttons.insert(MouseButton::Other(5));
+        buttons.insert(MouseButton::None);
+        assert!(buttons.is_empty());
+        assert!(!buttons.contains(MouseButton::Other(5)));
+    }
+}
@@ -14,12 +14,27 @@
 
 //! GTK implementation of features at the application scope.
 
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::ptr;
+use std::rc::Rc;
+use std::time::Duration;
+
 use gio::prelude::ApplicationExtManual;
-use gio::{ApplicationExt, ApplicationFlags, Cancellable};
-use gtk::{Application as GtkApplication, GtkApplicationExt};
+use gio::{ApplicationExt, ApplicationFlags, BusType, Cancellable, DBusProxy, DBusProxyFlags};
+use glib::translate::{FromGlibPtrFull, ToGlibPtr};
+use glib::{ObjectExt, Variant};
+use gtk::{
+    Application as GtkApplication, GtkApplicationExt, RecentManagerExt, SettingsExt,
+    StyleContextExt, WidgetExt,
+};
 
-use crate::application::AppHandler;
+use crate::application::{
+    AppHandler, MouseSettings, PowerEvent, PowerStatus, RegionalSettings, TextSettings,
+};
+use crate::piet::Color;
 
+use super::super::shared;
 use super::clipboard::Clipboard;
 use super::error::Error;
 
@@ -60,7 +75,14 @@ impl Application {
         &self.gtk_app
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {
+    pub fn run(self, handler: Option<Box<dyn AppHandler>>) {
+        // The proxies have to stay alive for as long as we want their signals.
+        let _proxies = handler.map(|handler| {
+            let handler = Rc::new(RefCell::new(handler));
+            let sleep = watch_sleep(handler.clone());
+            let power_status = watch_power_status(handler);
+            (sleep, power_status)
+        });
         // TODO: should we pass the command line arguments?
         self.gtk_app.run(&[]);
     }
@@ -77,6 +99,52 @@ impl Application {
         }
     }
 
+    pub fn set_badge(&self, count: Option<u64>) {
+        let (connection, app_id) = match (
+            self.gtk_app.get_dbus_connection(),
+            self.gtk_app.get_application_id(),
+        ) {
+            (Some(connection), Some(app_id)) => (connection, app_id),
+            _ => {
+                tracing::warn!("set_badge: the application is not on the session bus");
+                return;
+            }
+        };
+        let params = match launcher_entry_update(&app_id, count) {
+            Some(params) => params,
+            None => {
+                tracing::warn!("set_badge: invalid application id {}", app_id);
+                return;
+            }
+        };
+        let sent = connection.emit_signal(
+            None,
+            "/",
+            "com.canonical.Unity.LauncherEntry",
+            "Update",
+            Some(&params),
+        );
+        if let Err(e) = sent {
+            tracing::warn!("failed to update the launcher entry: {}", e);
+        }
+    }
+
+    pub fn add_recent_document(&self, path: &Path) {
+        let uri = match glib::filename_to_uri(path, None) {
+            Ok(uri) => uri,
+            Err(e) => {
+                tracing::warn!("add_recent_document: {}", e);
+                return;
+            }
+        };
+        match gtk::RecentManager::get_default() {
+            Some(manager) => {
+                manager.add_item(&uri);
+            }
+            None => tracing::warn!("add_recent_document: no recent manager"),
+        }
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
@@ -84,4 +152,224 @@ impl Application {
     pub fn get_locale() -> String {
         glib::get_language_names()[0].as_str().into()
     }
+
+    pub fn preferred_locales() -> Vec<String> {
+        shared::preferred_locales()
+    }
+
+    pub fn regional_settings() -> RegionalSettings {
+        // TODO: read LC_NUMERIC and LC_TIME
+        RegionalSettings::default()
+    }
+
+    pub fn get_text_settings() -> TextSettings {
+        let settings = match gtk::Settings::get_default() {
+            Some(settings) => settings,
+            None => return TextSettings::default(),
+        };
+        let caret_blink_interval = if settings.get_property_gtk_cursor_blink() {
+            // gtk-cursor-blink-time is the length of a whole on-off cycle.
+            let cycle = settings.get_property_gtk_cursor_blink_time().max(0) as u64;
+            Duration::from_millis(cycle / 2)
+        } else {
+            Duration::ZERO
+        };
+        // the selection colors come from the theme, as seen by a text entry.
+        let lookup = |name| {
+            THEME_ENTRY.with(|entry| {
+                entry
+                    .get_style_context()
+                    .lookup_color(name)
+                    .map(|c| Color::rgba(c.red, c.green, c.blue, c.alpha))
+            })
+        };
+        TextSettings {
+            caret_blink_interval: Some(caret_blink_interval),
+            caret_width: None,
+            selection_color: lookup("theme_selected_bg_color"),
+            selected_text_color: lookup("theme_selected_fg_color"),
+        }
+    }
+
+    pub fn get_mouse_settings() -> MouseSettings {
+        let settings = match gtk::Settings::get_default() {
+            Some(settings) => settings,
+            None => return MouseSettings::default(),
+        };
+        let millis = settings.get_property_gtk_double_click_time();
+        let distance = settings.get_property_gtk_double_click_distance();
+        MouseSettings {
+            double_click_interval: Some(Duration::from_millis(millis.max(0) as u64)),
+            double_click_distance: Some(distance.max(0) as f64),
+        }
+    }
+
+    pub fn power_status() -> PowerStatus {
+        shared::power_status()
+    }
+}
+
+type SharedHandler = Rc<RefCell<Box<dyn AppHandler>>>;
+
+thread_local! {
+    /// A text entry that is never shown, for looking up theme colors.
+    ///
+    /// It is kept around because the settings are read again whenever the
+    /// theme changes, and its style follows the theme.
+    static THEME_ENTRY: gtk::Entry = gtk::Entry::new();
+}
+
+/// Create a proxy for a service on the system bus.
+fn system_proxy(
+    flags: DBusProxyFlags,
+    name: &str,
+    path: &str,
+    interface: &str,
+) -> Option<DBusProxy> {
+    let proxy = DBusProxy::new_for_bus_sync(
+        BusType::System,
+        flags,
+        None,
+        name,
+        path,
+        interface,
+        None as Option<&Cancellable>,
+    );
+    match proxy {
+        Ok(proxy) => Some(proxy),
+        Err(e) => {
+            tracing::warn!("failed to connect to {}: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Deliver logind's notifications about going to sleep and waking up to the
+/// app handler.
+fn watch_sleep(handler: SharedHandler) -> Option<DBusProxy> {
+    let proxy = system_proxy(
+        DBusProxyFlags::DO_NOT_LOAD_PROPERTIES,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    // The arguments are the proxy, the sender, the signal name and its parameters.
+    let connected = proxy.connect_local("g-signal", false, move |args| {
+        let signal = args
+            .get(2)
+            .and_then(|arg| arg.get::<String>().ok().flatten());
+        let params = args
+            .get(3)
+            .and_then(|arg| arg.get::<Variant>().ok().flatten());
+        if let (Some("PrepareForSleep"), Some(params)) = (signal.as_deref(), params) {
+            let event = match sleep_started(&params) {
+                Some(true) => PowerEvent::Suspending,
+                Some(false) => PowerEvent::Resumed,
+                None => return None,
+            };
+            handler.borrow_mut().power_event(event);
+        }
+        None
+    });
+    if let Err(e) = connected {
+        tracing::warn!("failed to watch logind for sleep: {}", e);
+        return None;
+    }
+    Some(proxy)
+}
+
+/// Tell the app handler when UPower reports a change to the power status.
+fn watch_power_status(handler: SharedHandler) -> Option<DBusProxy> {
+    // The display device combines all of the system's batteries. Its state
+    // changes when the power is plugged in or out, and so does its charge.
+    // Properties are only watched if they are loaded.
+    let proxy = system_proxy(
+        DBusProxyFlags::NONE,
+        "org.freedesktop.UPower",
+        "/org/freedesktop/UPower/devices/DisplayDevice",
+        "org.freedesktop.UPower.Device",
+    )?;
+    let last_status = Cell::new(shared::power_status());
+    let connected = proxy.connect_local("g-properties-changed", false, move |_| {
+        let status = shared::power_status();
+        if last_status.replace(status) != status {
+            handler.borrow_mut().power_status_changed(status);
+        }
+        None
+    });
+    if let Err(e) = connected {
+        tracing::warn!("failed to watch UPower for power changes: {}", e);
+        return None;
+    }
+    Some(proxy)
+}
+
+/// The parameters of the Unity launcher API's `Update` signal, which other
+/// docks implement too.
+fn launcher_entry_update(app_id: &str, count: Option<u64>) -> Option<Variant> {
+    parse_variant(&format!(
+        "('application://{}.desktop', {{'count': <int64 {}>, 'count-visible': <{}>}})",
+        app_id,
+        count.unwrap_or(0).min(i64::MAX as u64),
+        count.is_some(),
+    ))
+}
+
+/// Parse a variant from its text format.
+fn parse_variant(text: &str) -> Option<Variant> {
+    // glib-rs has no way to build tuples and dictionaries.
+    unsafe {
+        let ptr = glib_sys::g_variant_parse(
+            ptr::null(),
+            text.to_glib_none().0,
+            ptr::null(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Variant::from_glib_full(ptr))
+        }
+    }
+}
+
+/// The argument of logind's `PrepareForSleep` signal, which is true before
+/// going to sleep and false after waking up.
+fn sleep_started(params: &Variant) -> Option<bool> {
+    if params.type_().to_str() != "(b)" {
+        return None;
+    }
+    // glib-rs has no way to get at the members of a tuple.
+    unsafe {
+        let ptr: *mut glib_sys::GVariant = params.to_glib_none().0;
+        Variant::from_glib_full(glib_sys::g_variant_get_child_value(ptr, 0)).get::<bool>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn launcher_entry_update_params() {
+        let params = launcher_entry_update("org.example.app", Some(5)).unwrap();
+        assert_eq!(params.type_().to_str(), "(sa{sv})");
+        assert_eq!(
+            params.to_string(),
+            "('application://org.example.app.desktop', {'count': <int64 5>, 'count-visible': <true>})"
+        );
+
+        let params = launcher_entry_update("org.example.app", None).unwrap();
+        assert_eq!(
+            params.to_string(),
+            "('application://org.example.app.desktop', {'count': <int64 0>, 'count-visible': <false>})"
+        );
+
+        // a count that doesn't fit an int64 is clamped.
+        let params = launcher_entry_update("org.example.app", Some(u64::MAX)).unwrap();
+        assert!(params
+            .to_string()
+            .contains(&format!("<int64 {}>", i64::MAX)));
+    }
 }
@@ -14,6 +14,8 @@
 
 //! Interactions with the system pasteboard on GTK+.
 
+use std::path::{Path, PathBuf};
+
 use gdk::Atom;
 use gtk::{TargetEntry, TargetFlags};
 
@@ -27,6 +29,9 @@ const CLIPBOARD_TARGETS: [&str; 5] = [
     "text/plain",
 ];
 
+const URI_LIST: &str = "text/uri-list";
+const GNOME_COPIED_FILES: &str = "x-special/gnome-copied-files";
+
 /// The system clipboard.
 #[derive(Debug, Clone)]
 pub struct Clipboard;
@@ -78,6 +83,42 @@ impl Clipboard {
         }
     }
 
+    /// Put a list of files on the system clipboard.
+    pub fn put_files(&mut self, paths: &[&Path]) {
+        let uris = paths
+            .iter()
+            .filter_map(|path| match glib::filename_to_uri(path, None) {
+                Ok(uri) => Some(uri.to_string()),
+                Err(e) => {
+                    tracing::warn!("can't make a URI for {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        // nautilus and friends only paste files from their own format.
+        let gnome = format!("copy\n{}", uris.join("\n"));
+        let uri_list = uris
+            .iter()
+            .map(|uri| format!("{}\r\n", uri))
+            .collect::<String>();
+        self.put_formats(&[
+            ClipboardFormat::new(URI_LIST, uri_list),
+            ClipboardFormat::new(GNOME_COPIED_FILES, gnome),
+        ]);
+    }
+
+    /// Get a list of files from the system clipboard, if there is one.
+    pub fn get_files(&self) -> Option<Vec<PathBuf>> {
+        let uri_list = String::from_utf8(self.get_format(URI_LIST)?).ok()?;
+        let paths = uri_list
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|uri| glib::filename_from_uri(uri.trim()).ok())
+            .map(|(path, _host)| path)
+            .collect();
+        Some(paths)
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         let display = gdk::Display::get_default().unwrap();
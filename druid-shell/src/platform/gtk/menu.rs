@@ -14,6 +14,8 @@
 
 //! GTK implementation of menus.
 
+use std::collections::HashMap;
+
 use gdk::ModifierType;
 use gtk::{
     AccelGroup, GtkMenuExt, GtkMenuItemExt, Menu as GtkMenu, MenuBar as GtkMenuBar,
@@ -84,6 +86,7 @@ impl Menu {
         menu: &mut M,
         handle: &WindowHandle,
         accel_group: &AccelGroup,
+        items: &mut HashMap<u32, GtkMenuItem>,
     ) {
         for item in self.items {
             match item {
@@ -108,10 +111,11 @@ impl Menu {
                     });
 
                     menu.append(&item);
+                    items.insert(id, item);
                 }
                 MenuItem::SubMenu(name, submenu) => {
                     let item = GtkMenuItem::with_label(&name);
-                    item.set_submenu(Some(&submenu.into_gtk_menu(handle, accel_group)));
+                    item.set_submenu(Some(&submenu.build_gtk_menu(handle, accel_group, items)));
 
                     menu.append(&item);
                 }
@@ -126,22 +130,43 @@ impl Menu {
         accel_group: &AccelGroup,
     ) -> GtkMenuBar {
         let mut menu = GtkMenuBar::new();
+        let mut items = HashMap::new();
 
-        self.append_items_to_menu(&mut menu, handle, accel_group);
+        self.append_items_to_menu(&mut menu, handle, accel_group, &mut items);
 
+        // so that the items can be updated later, see `update_item`.
+        if let Some(state) = handle.state.upgrade() {
+            state.menu_items.replace(items);
+        }
         menu
     }
 
     pub fn into_gtk_menu(self, handle: &WindowHandle, accel_group: &AccelGroup) -> GtkMenu {
+        self.build_gtk_menu(handle, accel_group, &mut HashMap::new())
+    }
+
+    fn build_gtk_menu(
+        self,
+        handle: &WindowHandle,
+        accel_group: &AccelGroup,
+        items: &mut HashMap<u32, GtkMenuItem>,
+    ) -> GtkMenu {
         let mut menu = GtkMenu::new();
         menu.set_accel_group(Some(accel_group));
 
-        self.append_items_to_menu(&mut menu, handle, accel_group);
+        self.append_items_to_menu(&mut menu, handle, accel_group, items);
 
         menu
     }
 }
 
+/// Change an item of a menu bar.
+pub(crate) fn update_item(item: &GtkMenuItem, text: &str, enabled: bool) {
+    // TODO: implement selected items
+    item.set_label(&strip_access_key(text));
+    item.set_sensitive(enabled);
+}
+
 fn register_accelerator(item: &GtkMenuItem, accel_group: &AccelGroup, menu_key: HotKey) {
     let gdk_keyval = match &menu_key.key {
         KbKey::Character(text) => text.chars().next().unwrap() as u32,
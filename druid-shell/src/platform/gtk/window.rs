@@ -15,6 +15,7 @@
 //! GTK window creation and management.
 
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::ffi::c_void;
 use std::os::raw::{c_int, c_uint};
@@ -26,7 +27,9 @@ use std::time::Instant;
 
 use anyhow::anyhow;
 use cairo::Surface;
-use gdk::{EventKey, EventMask, ModifierType, ScrollDirection, WindowExt, WindowTypeHint};
+use gdk::{
+    EventKey, EventMask, InputSource, ModifierType, ScrollDirection, WindowExt, WindowTypeHint,
+};
 use gio::ApplicationExt;
 use gtk::prelude::*;
 use gtk::{AccelGroup, ApplicationWindow, DrawingArea, SettingsExt};
@@ -36,25 +39,28 @@ use tracing::{error, warn};
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::piet::{Color, ImageBuf, Piet, PietText, RenderContext};
 
 use crate::common_util::{ClickCounter, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
+use crate::drag::FilePromise;
 use crate::error::Error as ShellError;
+use crate::hotkey::HotKey;
 use crate::keyboard::{KbKey, KeyEvent, KeyState, Modifiers};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::piet::ImageFormat;
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
 use crate::text::{simulate_input, Event};
 use crate::window::{
-    self, FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    self, FileDialogToken, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler,
+    WindowLevel,
 };
 
 use super::application::Application;
 use super::dialog;
 use super::keycodes;
-use super::menu::Menu;
+use super::menu::{self, Menu};
 use super::util;
 
 /// The platform target DPI.
@@ -99,6 +105,39 @@ pub struct WindowHandle {
     marker: std::marker::PhantomData<*const ()>,
 }
 
+/// Embedding foreign windows is not supported, so this can't be created.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) enum ForeignWindow {}
+
+#[cfg(feature = "raw-win-handle")]
+impl ForeignWindow {
+    pub fn set_frame(&self, _frame: Rect) {
+        match *self {}
+    }
+
+    pub fn focus(&self) {
+        match *self {}
+    }
+}
+
+/// Render surfaces are not supported, so this can't be created.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) enum RenderSurface {}
+
+#[cfg(feature = "raw-win-handle")]
+impl RenderSurface {
+    pub fn set_frame(&self, _frame: Rect) {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe impl HasRawWindowHandle for RenderSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        match *self {}
+    }
+}
+
 #[cfg(feature = "raw-win-handle")]
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
@@ -130,6 +169,10 @@ pub(crate) struct WindowBuilder {
     resizable: bool,
     show_titlebar: bool,
     transparent: bool,
+    background_color: Option<Color>,
+    always_on_top: bool,
+    fullscreen: bool,
+    icon: Option<ImageBuf>,
 }
 
 #[derive(Clone)]
@@ -152,6 +195,12 @@ pub(crate) struct WindowState {
     scale: Cell<Scale>,
     area: Cell<ScaledArea>,
     is_transparent: Cell<bool>,
+    /// Styles the background of the window, which shows until we paint.
+    background_css: gtk::CssProvider,
+    /// The steps that the drawing area is resized in, in display points.
+    resize_increments: Cell<Option<Size>>,
+    /// The width to height ratio that the drawing area is kept at.
+    aspect_ratio: Cell<Option<f64>>,
     /// Used to determine whether to honor close requests from the system: we inhibit them unless
     /// this is true, and this gets set to true when our client requests a close.
     closing: Cell<bool>,
@@ -177,6 +226,8 @@ pub(crate) struct WindowState {
     click_counter: ClickCounter,
     active_text_input: Cell<Option<TextFieldToken>>,
     deferred_queue: RefCell<Vec<DeferredOp>>,
+    /// The items of the menu bar, by id.
+    pub(crate) menu_items: RefCell<HashMap<u32, gtk::MenuItem>>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -197,6 +248,10 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             transparent: false,
+            background_color: None,
+            always_on_top: false,
+            fullscreen: false,
+            icon: None,
         }
     }
 
@@ -224,6 +279,10 @@ impl WindowBuilder {
         self.transparent = transparent;
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_position(&mut self, position: Point) {
         self.position = Some(position);
     }
@@ -232,6 +291,18 @@ impl WindowBuilder {
         self.level = Some(level);
     }
 
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.always_on_top = always_on_top;
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+    }
+
+    pub fn set_icon(&mut self, icon: ImageBuf) {
+        self.icon = Some(icon);
+    }
+
     pub fn set_window_state(&mut self, state: window::WindowState) {
         self.state = Some(state);
     }
@@ -264,6 +335,11 @@ impl WindowBuilder {
         }
         window.set_app_paintable(transparent);
 
+        let background_css = gtk::CssProvider::new();
+        window
+            .get_style_context()
+            .add_provider(&background_css, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+
         // Get the scale factor based on the GTK reported DPI
         let scale_factor =
             window.get_display().get_default_screen().get_resolution() / SCALE_TARGET_DPI;
@@ -285,6 +361,9 @@ impl WindowBuilder {
             scale: Cell::new(scale),
             area: Cell::new(area),
             is_transparent: Cell::new(transparent),
+            background_css,
+            resize_increments: Cell::new(None),
+            aspect_ratio: Cell::new(None),
             closing: Cell::new(false),
             drawing_area,
             surface: RefCell::new(None),
@@ -296,8 +375,13 @@ impl WindowBuilder {
             click_counter: ClickCounter::default(),
             active_text_input: Cell::new(None),
             deferred_queue: RefCell::new(Vec::new()),
+            menu_items: RefCell::new(HashMap::new()),
         });
 
+        if let Some(color) = &self.background_color {
+            win_state.set_background_color(color);
+        }
+
         self.app
             .gtk_app()
             .connect_shutdown(clone!(win_state => move |_| {
@@ -314,6 +398,15 @@ impl WindowBuilder {
         if let Some(level) = self.level {
             handle.set_level(level);
         }
+        if self.always_on_top {
+            handle.set_always_on_top(true);
+        }
+        if self.fullscreen {
+            handle.set_fullscreen(true);
+        }
+        if let Some(icon) = &self.icon {
+            handle.set_icon(icon);
+        }
         if let Some(pos) = self.position {
             handle.set_position(pos);
         }
@@ -483,7 +576,8 @@ impl WindowBuilder {
                                     count,
                                     focus: false,
                                     button,
-                                    wheel_delta: Vec2::ZERO
+                                    wheel_delta: Vec2::ZERO,
+                                    pointer_type: get_pointer_type(event),
                                 },
                             );
                         }
@@ -508,7 +602,8 @@ impl WindowBuilder {
                                 count: 0,
                                 focus: false,
                                 button,
-                                wheel_delta: Vec2::ZERO
+                                wheel_delta: Vec2::ZERO,
+                                pointer_type: get_pointer_type(event),
                             },
                         );
                     }
@@ -530,7 +625,8 @@ impl WindowBuilder {
                         count: 0,
                         focus: false,
                         button: MouseButton::None,
-                        wheel_delta: Vec2::ZERO
+                        wheel_delta: Vec2::ZERO,
+                        pointer_type: get_pointer_type(motion),
                     };
 
                     state.with_handler(|h| h.mouse_move(&mouse_event));
@@ -552,7 +648,8 @@ impl WindowBuilder {
                         count: 0,
                         focus: false,
                         button: MouseButton::None,
-                        wheel_delta: Vec2::ZERO
+                        wheel_delta: Vec2::ZERO,
+                        pointer_type: get_pointer_type(crossing),
                     };
 
                     state.with_handler(|h| h.mouse_move(&mouse_event));
@@ -571,24 +668,15 @@ impl WindowBuilder {
 
                     // The magic "120"s are from Microsoft's documentation for WM_MOUSEWHEEL.
                     // They claim that one "tick" on a scroll wheel should be 120 units.
-                    let shift = mods.shift();
                     let wheel_delta = match scroll.get_direction() {
-                        ScrollDirection::Up if shift => Some(Vec2::new(-120.0, 0.0)),
                         ScrollDirection::Up => Some(Vec2::new(0.0, -120.0)),
-                        ScrollDirection::Down if shift => Some(Vec2::new(120.0, 0.0)),
                         ScrollDirection::Down => Some(Vec2::new(0.0, 120.0)),
                         ScrollDirection::Left => Some(Vec2::new(-120.0, 0.0)),
                         ScrollDirection::Right => Some(Vec2::new(120.0, 0.0)),
                         ScrollDirection::Smooth => {
                             //TODO: Look at how gtk's scroll containers implements it
-                            let (mut delta_x, mut delta_y) = scroll.get_delta();
-                            delta_x *= 120.;
-                            delta_y *= 120.;
-                            if shift {
-                                delta_x += delta_y;
-                                delta_y = 0.;
-                            }
-                            Some(Vec2::new(delta_x, delta_y))
+                            let (delta_x, delta_y) = scroll.get_delta();
+                            Some(Vec2::new(delta_x * 120., delta_y * 120.))
                         }
                         e => {
                             warn!(
@@ -607,7 +695,8 @@ impl WindowBuilder {
                             count: 0,
                             focus: false,
                             button: MouseButton::None,
-                            wheel_delta
+                            wheel_delta,
+                            pointer_type: get_pointer_type(scroll),
                         };
 
                         state.with_handler(|h| h.wheel(&mouse_event));
@@ -671,6 +760,26 @@ impl WindowBuilder {
                 Inhibit(true)
             }));
 
+        if let Some(settings) = win_state.drawing_area.get_settings() {
+            settings.connect_property_gtk_cursor_blink_notify(clone!(handle => move |_settings| {
+                if let Some(state) = handle.state.upgrade() {
+                    state.with_handler(|h| h.system_settings_changed());
+                }
+            }));
+            settings.connect_property_gtk_cursor_blink_time_notify(
+                clone!(handle => move |_settings| {
+                    if let Some(state) = handle.state.upgrade() {
+                        state.with_handler(|h| h.system_settings_changed());
+                    }
+                }),
+            );
+            settings.connect_property_gtk_theme_name_notify(clone!(handle => move |_settings| {
+                if let Some(state) = handle.state.upgrade() {
+                    state.with_handler(|h| h.system_settings_changed());
+                }
+            }));
+        }
+
         win_state
             .window
             .connect_delete_event(clone!(handle => move |_widget, _ev| {
@@ -682,6 +791,21 @@ impl WindowBuilder {
                 }
             }));
 
+        win_state
+            .window
+            .connect_window_state_event(clone!(handle => move |_widget, event| {
+                if event.get_changed_mask().contains(gdk::WindowState::ICONIFIED) {
+                    if let Some(state) = handle.state.upgrade() {
+                        if event.get_new_window_state().contains(gdk::WindowState::ICONIFIED) {
+                            state.with_handler(|h| h.suspended());
+                        } else {
+                            state.with_handler(|h| h.resumed());
+                        }
+                    }
+                }
+                Inhibit(false)
+            }));
+
         win_state
             .drawing_area
             .connect_destroy(clone!(handle => move |_widget| {
@@ -714,6 +838,53 @@ impl WindowBuilder {
 }
 
 impl WindowState {
+    fn update_geometry_hints(&self) {
+        let mut hints = gdk::WindowHints::empty();
+        let (mut width_inc, mut height_inc) = (0, 0);
+        if let Some(increments) = self.resize_increments.get() {
+            let increments = increments.to_px(self.scale.get());
+            width_inc = (increments.width.round() as i32).max(1);
+            height_inc = (increments.height.round() as i32).max(1);
+            hints |= gdk::WindowHints::RESIZE_INC;
+        }
+        let aspect = self.aspect_ratio.get().unwrap_or(0.0);
+        if self.aspect_ratio.get().is_some() {
+            hints |= gdk::WindowHints::ASPECT;
+        }
+        let geometry = gdk::Geometry {
+            min_width: 0,
+            min_height: 0,
+            max_width: 0,
+            max_height: 0,
+            base_width: 0,
+            base_height: 0,
+            width_inc,
+            height_inc,
+            min_aspect: aspect,
+            max_aspect: aspect,
+            win_gravity: gdk::Gravity::NorthWest,
+        };
+        self.window
+            .set_geometry_hints(Some(&self.drawing_area), Some(&geometry), hints);
+    }
+
+    fn set_background_color(&self, color: &Color) {
+        if self.is_transparent.get() {
+            return;
+        }
+        let (r, g, b, a) = color.as_rgba8();
+        let css = format!(
+            "window {{ background-color: rgba({}, {}, {}, {}); }}",
+            r,
+            g,
+            b,
+            a as f64 / 255.0
+        );
+        if let Err(e) = self.background_css.load_from_data(css.as_bytes()) {
+            warn!("failed to set the background color: {}", e);
+        }
+    }
+
     #[track_caller]
     fn with_handler<T, F: FnOnce(&mut dyn WinHandler) -> T>(&self, f: F) -> Option<T> {
         if self.invalid.try_borrow_mut().is_err() || self.surface.try_borrow_mut().is_err() {
@@ -913,6 +1084,47 @@ impl WindowHandle {
         self.set_override_redirect(level);
     }
 
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        if let Some(state) = self.state.upgrade() {
+            state.window.set_keep_above(always_on_top);
+        }
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if let Some(state) = self.state.upgrade() {
+            if fullscreen {
+                state.window.fullscreen();
+            } else {
+                state.window.unfullscreen();
+            }
+        }
+    }
+
+    pub fn set_icon(&self, icon: &ImageBuf) {
+        if let Some(state) = self.state.upgrade() {
+            // The pixbuf wants non-premultiplied RGBA, whatever format the image is in.
+            let mut pixels = Vec::with_capacity(icon.width() * icon.height() * 4);
+            for row in icon.pixel_colors() {
+                for color in row {
+                    let (r, g, b, a) = color.as_rgba8();
+                    pixels.extend_from_slice(&[r, g, b, a]);
+                }
+            }
+            let pixbuf = gdk_pixbuf::Pixbuf::from_mut_slice(
+                pixels,
+                gdk_pixbuf::Colorspace::Rgb,
+                true,
+                // bits_per_sample
+                8,
+                icon.width() as i32,
+                icon.height() as i32,
+                // row stride (in bytes)
+                (icon.width() * 4) as i32,
+            );
+            state.window.set_icon(Some(&pixbuf));
+        }
+    }
+
     /// The override-redirect flag tells the window manager not to mess with the window; it should
     /// be set for things like tooltips, dropdowns, etc.
     ///
@@ -1153,6 +1365,19 @@ impl WindowHandle {
             .get())
     }
 
+    #[cfg(feature = "raw-win-handle")]
+    pub unsafe fn embed_foreign_window(
+        &self,
+        _child: RawWindowHandle,
+    ) -> Result<ForeignWindow, ShellError> {
+        Err(anyhow::anyhow!("embedding foreign windows is not supported on gtk").into())
+    }
+
+    #[cfg(feature = "raw-win-handle")]
+    pub fn create_render_surface(&self, _frame: Rect) -> Result<RenderSurface, ShellError> {
+        Err(anyhow::anyhow!("render surfaces are not supported on gtk").into())
+    }
+
     pub fn set_menu(&self, menu: Menu) {
         if let Some(state) = self.state.upgrade() {
             let window = &state.window;
@@ -1176,12 +1401,63 @@ impl WindowHandle {
         }
     }
 
+    pub fn update_menu_item(
+        &self,
+        id: u32,
+        text: &str,
+        _key: Option<&HotKey>,
+        enabled: bool,
+        _selected: bool,
+    ) {
+        if let Some(state) = self.state.upgrade() {
+            if let Some(item) = state.menu_items.borrow().get(&id) {
+                menu::update_item(item, text, enabled);
+            }
+        }
+    }
+
     pub fn show_context_menu(&self, menu: Menu, _pos: Point) {
         if let Some(state) = self.state.upgrade() {
             state.defer(DeferredOp::ContextMenu(menu, self.clone()));
         }
     }
 
+    pub fn start_file_drag(&self, _promise: FilePromise) {
+        // TODO(gtk/file_promise): implement the direct save protocol (XDS)
+        warn!("WindowHandle::start_file_drag is currently unimplemented for gtk.");
+    }
+
+    pub fn announce(&self, _text: &str, _politeness: Politeness) {
+        warn!("WindowHandle::announce is currently unimplemented for gtk.");
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        if let Some(state) = self.state.upgrade() {
+            state.set_background_color(&color);
+        }
+    }
+
+    pub fn set_resize_increments(&self, width: f64, height: f64) {
+        if let Some(state) = self.state.upgrade() {
+            let increments =
+                Some(Size::new(width, height)).filter(|size| size.width > 0.0 || size.height > 0.0);
+            state.resize_increments.set(increments);
+            state.update_geometry_hints();
+        }
+    }
+
+    pub fn set_aspect_ratio(&self, numerator: u32, denominator: u32) {
+        if let Some(state) = self.state.upgrade() {
+            let ratio = if numerator == 0 || denominator == 0 {
+                None
+            } else {
+                Some(numerator as f64 / denominator as f64)
+            };
+            state.aspect_ratio.set(ratio);
+            state.update_geometry_hints();
+        }
+    }
+
     pub fn set_title(&self, title: impl Into<String>) {
         if let Some(state) = self.state.upgrade() {
             state.window.set_title(&(title.into()));
@@ -1286,10 +1562,21 @@ fn get_mouse_button(button: u32) -> Option<MouseButton> {
         // GDK X backend interprets button press events for button 4-7 as scroll events
         8 => Some(MouseButton::X1),
         9 => Some(MouseButton::X2),
+        // the other buttons come after the scroll buttons, so they are numbered from 10
+        10..=260 => Some(MouseButton::Other((button - 5) as u8)),
         _ => None,
     }
 }
 
+/// The kind of device that an event came from.
+fn get_pointer_type(event: &gdk::Event) -> PointerType {
+    match event.get_source_device().map(|device| device.get_source()) {
+        Some(InputSource::Touchscreen) => PointerType::Touch,
+        Some(InputSource::Pen) | Some(InputSource::Eraser) => PointerType::Pen,
+        _ => PointerType::Mouse,
+    }
+}
+
 fn get_mouse_buttons_from_modifiers(modifiers: gdk::ModifierType) -> MouseButtons {
     let mut buttons = MouseButtons::new();
     if modifiers.contains(ModifierType::BUTTON1_MASK) {
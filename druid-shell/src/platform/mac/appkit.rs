@@ -16,16 +16,52 @@
 
 #![allow(clippy::upper_case_acronyms, non_snake_case, non_upper_case_globals)]
 
+use std::ffi::c_void;
+
 use bitflags::bitflags;
 use cocoa::base::id;
-use cocoa::foundation::NSRect;
+use cocoa::foundation::{NSInteger, NSRect};
 use objc::{class, msg_send, sel, sel_impl};
 
 #[link(name = "AppKit", kind = "framework")]
 extern "C" {
     pub static NSRunLoopCommonModes: id;
+    pub static NSAccessibilityAnnouncementRequestedNotification: id;
+    pub static NSAccessibilityAnnouncementKey: id;
+    pub static NSAccessibilityPriorityKey: id;
+    pub static NSPasteboardURLReadingFileURLsOnlyKey: id;
+
+    pub fn NSAccessibilityPostNotificationWithUserInfo(element: id, notification: id, userInfo: id);
+}
+
+// The power source info, list and descriptions are Core Foundation types that
+// are toll-free bridged, so we treat them as objects.
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    pub fn IOPSCopyPowerSourcesInfo() -> id;
+    pub fn IOPSCopyPowerSourcesList(blob: id) -> id;
+    pub fn IOPSGetPowerSourceDescription(blob: id, ps: id) -> id;
+    pub fn IOPSGetProvidingPowerSourceType(snapshot: id) -> id;
+    pub fn IOPSNotificationCreateRunLoopSource(
+        callback: extern "C" fn(*mut c_void),
+        context: *mut c_void,
+    ) -> *mut c_void;
 }
 
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    pub static kCFRunLoopDefaultMode: id;
+
+    pub fn CFRunLoopGetMain() -> *mut c_void;
+    pub fn CFRunLoopAddSource(rl: *mut c_void, source: *mut c_void, mode: id);
+    pub fn CFRunLoopSourceInvalidate(source: *mut c_void);
+    pub fn CFRelease(cf: *const c_void);
+}
+
+// NSAccessibilityPriorityLevel
+pub const NSAccessibilityPriorityMedium: NSInteger = 50;
+pub const NSAccessibilityPriorityHigh: NSInteger = 90;
+
 bitflags! {
     pub struct NSTrackingAreaOptions: i32 {
         const MouseEnteredAndExited = 1;
@@ -18,18 +18,24 @@
 
 use std::cell::RefCell;
 use std::ffi::c_void;
+use std::path::Path;
 use std::rc::Rc;
+use std::time::Duration;
 
-use cocoa::appkit::{NSApp, NSApplication, NSApplicationActivationPolicyRegular};
-use cocoa::base::{id, nil, NO, YES};
-use cocoa::foundation::{NSArray, NSAutoreleasePool};
+use cocoa::appkit::{CGFloat, NSApp, NSApplication, NSApplicationActivationPolicyRegular};
+use cocoa::base::{id, nil, BOOL, NO, YES};
+use cocoa::foundation::{NSArray, NSAutoreleasePool, NSInteger, NSUInteger};
 use lazy_static::lazy_static;
 use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
 
-use crate::application::AppHandler;
+use crate::application::{
+    AppHandler, MouseSettings, PowerEvent, PowerStatus, RegionalSettings, TextSettings, Weekday,
+};
+use crate::piet::Color;
 
+use super::appkit;
 use super::clipboard::Clipboard;
 use super::error::Error;
 use super::menu::Menu;
@@ -37,6 +43,10 @@ use super::util;
 
 static APP_HANDLER_IVAR: &str = "druidAppHandler";
 
+// NSDateFormatterStyle
+const NSDateFormatterNoStyle: NSUInteger = 0;
+const NSDateFormatterShortStyle: NSUInteger = 1;
+
 #[derive(Clone)]
 pub(crate) struct Application {
     ns_app: id,
@@ -66,15 +76,50 @@ impl Application {
             // Initialize the application delegate
             let delegate: id = msg_send![APP_DELEGATE.0, alloc];
             let () = msg_send![delegate, init];
-            let state = DelegateState { handler };
+            let state = DelegateState {
+                handler,
+                power_status: Application::power_status(),
+            };
             let state_ptr = Box::into_raw(Box::new(state));
             (*delegate).set_ivar(APP_HANDLER_IVAR, state_ptr as *mut c_void);
             let () = msg_send![self.ns_app, setDelegate: delegate];
 
+            // Sleep and wake are only reported to the workspace's notification center.
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let workspace_center: id = msg_send![workspace, notificationCenter];
+            for &(name, selector, _) in &power_notifications() {
+                let name = util::make_nsstring(name);
+                let () = msg_send![workspace_center, addObserver: delegate selector: selector name: name object: nil];
+            }
+
+            let center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+            let name = util::make_nsstring("NSCurrentLocaleDidChangeNotification");
+            let () = msg_send![center, addObserver: delegate selector: sel!(localeDidChange:) name: name object: nil];
+
+            // IOKit tells us about any change to the power sources, such as the
+            // estimated time that is left.
+            let power_source = appkit::IOPSNotificationCreateRunLoopSource(
+                power_sources_changed,
+                state_ptr as *mut c_void,
+            );
+            if !power_source.is_null() {
+                appkit::CFRunLoopAddSource(
+                    appkit::CFRunLoopGetMain(),
+                    power_source,
+                    appkit::kCFRunLoopDefaultMode,
+                );
+            }
+
             // Run the main app loop
             self.ns_app.run();
 
             // Clean up the delegate
+            if !power_source.is_null() {
+                appkit::CFRunLoopSourceInvalidate(power_source);
+                appkit::CFRelease(power_source);
+            }
+            let () = msg_send![workspace_center, removeObserver: delegate];
+            let () = msg_send![center, removeObserver: delegate];
             let () = msg_send![self.ns_app, setDelegate: nil];
             Box::from_raw(state_ptr); // Causes it to drop & dealloc automatically
         }
@@ -124,6 +169,32 @@ impl Application {
         }
     }
 
+    pub fn set_badge(&self, count: Option<u64>) {
+        unsafe {
+            let label = match count {
+                Some(count) => util::make_nsstring(&count.to_string()),
+                None => nil,
+            };
+            let dock_tile: id = msg_send![self.ns_app, dockTile];
+            let () = msg_send![dock_tile, setBadgeLabel: label];
+        }
+    }
+
+    pub fn add_recent_document(&self, path: &Path) {
+        let path = match path.to_str() {
+            Some(path) => path,
+            None => {
+                tracing::warn!("add_recent_document: path is not valid UTF-8");
+                return;
+            }
+        };
+        unsafe {
+            let url: id = msg_send![class!(NSURL), fileURLWithPath: util::make_nsstring(path)];
+            let controller: id = msg_send![class!(NSDocumentController), sharedDocumentController];
+            let () = msg_send![controller, noteNewRecentDocumentURL: url];
+        }
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
@@ -140,10 +211,162 @@ impl Application {
             locale
         }
     }
+
+    pub fn preferred_locales() -> Vec<String> {
+        unsafe {
+            let languages: id = msg_send![class!(NSLocale), preferredLanguages];
+            (0..languages.count())
+                .map(|i| util::from_nsstring(languages.objectAtIndex(i)))
+                .collect()
+        }
+    }
+
+    pub fn regional_settings() -> RegionalSettings {
+        unsafe {
+            let locale: id = msg_send![class!(NSLocale), currentLocale];
+            let string = |value: id| {
+                if value == nil {
+                    None
+                } else {
+                    Some(util::from_nsstring(value))
+                }
+            };
+            // The short date style follows the user's customizations.
+            let formatter: id = msg_send![class!(NSDateFormatter), new];
+            let () = msg_send![formatter, setLocale: locale];
+            let () = msg_send![formatter, setDateStyle: NSDateFormatterShortStyle];
+            let () = msg_send![formatter, setTimeStyle: NSDateFormatterNoStyle];
+            let short_date_pattern = string(msg_send![formatter, dateFormat]);
+            let () = msg_send![formatter, release];
+
+            // Days are numbered from 1, which is Sunday.
+            let calendar: id = msg_send![class!(NSCalendar), currentCalendar];
+            let first_weekday: NSUInteger = msg_send![calendar, firstWeekday];
+            let first_day_of_week = match first_weekday {
+                1 => Some(Weekday::Sunday),
+                2 => Some(Weekday::Monday),
+                3 => Some(Weekday::Tuesday),
+                4 => Some(Weekday::Wednesday),
+                5 => Some(Weekday::Thursday),
+                6 => Some(Weekday::Friday),
+                7 => Some(Weekday::Saturday),
+                _ => None,
+            };
+            RegionalSettings {
+                decimal_separator: string(msg_send![locale, decimalSeparator]),
+                grouping_separator: string(msg_send![locale, groupingSeparator]),
+                short_date_pattern,
+                first_day_of_week,
+            }
+        }
+    }
+
+    pub fn get_text_settings() -> TextSettings {
+        unsafe {
+            // AppKit only has a setting for this in the user defaults, and it
+            // is usually not set.
+            let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+            let key = util::make_nsstring("NSTextInsertionPointBlinkPeriodOn");
+            let period: NSInteger = msg_send![defaults, integerForKey: key];
+            let caret_blink_interval = if period > 0 {
+                Some(Duration::from_millis(period as u64))
+            } else {
+                None
+            };
+            TextSettings {
+                caret_blink_interval,
+                caret_width: None,
+                selection_color: srgb_color(msg_send![
+                    class!(NSColor),
+                    selectedTextBackgroundColor
+                ]),
+                selected_text_color: srgb_color(msg_send![class!(NSColor), selectedTextColor]),
+            }
+        }
+    }
+
+    pub fn get_mouse_settings() -> MouseSettings {
+        unsafe {
+            let interval: f64 = msg_send![class!(NSEvent), doubleClickInterval];
+            MouseSettings {
+                double_click_interval: Some(Duration::from_secs_f64(interval.max(0.0))),
+                // AppKit has no setting for this.
+                double_click_distance: None,
+            }
+        }
+    }
+
+    pub fn power_status() -> PowerStatus {
+        unsafe {
+            let info = appkit::IOPSCopyPowerSourcesInfo();
+            if info == nil {
+                return PowerStatus::default();
+            }
+            let source_type = appkit::IOPSGetProvidingPowerSourceType(info);
+            let status = PowerStatus {
+                on_battery: source_type != nil && is_equal_to(source_type, "Battery Power"),
+                battery_percent: battery_percent(info),
+            };
+            let () = msg_send![info, release];
+            status
+        }
+    }
+}
+
+/// The charge of the first internal battery in a snapshot of the power
+/// sources.
+unsafe fn battery_percent(info: id) -> Option<u8> {
+    let sources = appkit::IOPSCopyPowerSourcesList(info);
+    if sources == nil {
+        return None;
+    }
+    let mut percent = None;
+    for i in 0..sources.count() {
+        let description = appkit::IOPSGetPowerSourceDescription(info, sources.objectAtIndex(i));
+        if description == nil {
+            continue;
+        }
+        let get = |key| -> id { msg_send![description, objectForKey: util::make_nsstring(key)] };
+        let kind = get("Type");
+        if kind == nil || !is_equal_to(kind, "InternalBattery") {
+            continue;
+        }
+        let (current, max) = (get("Current Capacity"), get("Max Capacity"));
+        if current != nil && max != nil {
+            let current: NSInteger = msg_send![current, integerValue];
+            let max: NSInteger = msg_send![max, integerValue];
+            if max > 0 {
+                percent = Some((current * 100 / max).max(0).min(100) as u8);
+                break;
+            }
+        }
+    }
+    let () = msg_send![sources, release];
+    percent
+}
+
+unsafe fn is_equal_to(string: id, other: &str) -> bool {
+    let equal: BOOL = msg_send![string, isEqualToString: util::make_nsstring(other)];
+    equal == YES
+}
+
+/// Convert a system color to sRGB, which fails for colors that are
+/// patterns.
+unsafe fn srgb_color(color: id) -> Option<Color> {
+    let space: id = msg_send![class!(NSColorSpace), sRGBColorSpace];
+    let color: id = msg_send![color, colorUsingColorSpace: space];
+    if color == nil {
+        return None;
+    }
+    let (mut r, mut g, mut b, mut a): (CGFloat, CGFloat, CGFloat, CGFloat) = (0., 0., 0., 0.);
+    let () = msg_send![color, getRed: &mut r green: &mut g blue: &mut b alpha: &mut a];
+    Some(Color::rgba(r, g, b, a))
 }
 
 struct DelegateState {
     handler: Option<Box<dyn AppHandler>>,
+    // The power status that was last reported to the handler.
+    power_status: PowerStatus,
 }
 
 impl DelegateState {
@@ -152,6 +375,28 @@ impl DelegateState {
             inner.command(command)
         }
     }
+
+    fn power_event(&mut self, event: PowerEvent) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.power_event(event)
+        }
+    }
+
+    fn locale_changed(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.locale_changed()
+        }
+    }
+
+    fn power_sources_changed(&mut self) {
+        let status = Application::power_status();
+        if status != self.power_status {
+            self.power_status = status;
+            if let Some(inner) = self.handler.as_mut() {
+                inner.power_status_changed(status)
+            }
+        }
+    }
 }
 
 struct AppDelegate(*const Class);
@@ -172,6 +417,18 @@ lazy_static! {
             sel!(handleMenuItem:),
             handle_menu_item as extern "C" fn(&mut Object, Sel, id),
         );
+
+        decl.add_method(
+            sel!(localeDidChange:),
+            locale_did_change as extern "C" fn(&mut Object, Sel, id),
+        );
+
+        for &(_, selector, _) in &power_notifications() {
+            decl.add_method(
+                selector,
+                handle_power_notification as extern "C" fn(&mut Object, Sel, id),
+            );
+        }
         AppDelegate(decl.register())
     };
 }
@@ -195,3 +452,59 @@ extern "C" fn handle_menu_item(this: &mut Object, _: Sel, item: id) {
         (*inner).command(tag as u32);
     }
 }
+
+/// The `NSWorkspace` notifications that are turned into [`PowerEvent`]s, and
+/// the selectors of the delegate that observe them.
+fn power_notifications() -> [(&'static str, Sel, PowerEvent); 4] {
+    [
+        (
+            "NSWorkspaceWillSleepNotification",
+            sel!(workspaceWillSleep:),
+            PowerEvent::Suspending,
+        ),
+        (
+            "NSWorkspaceDidWakeNotification",
+            sel!(workspaceDidWake:),
+            PowerEvent::Resumed,
+        ),
+        (
+            "NSWorkspaceScreensDidSleepNotification",
+            sel!(workspaceScreensDidSleep:),
+            PowerEvent::DisplayOff,
+        ),
+        (
+            "NSWorkspaceScreensDidWakeNotification",
+            sel!(workspaceScreensDidWake:),
+            PowerEvent::DisplayOn,
+        ),
+    ]
+}
+
+extern "C" fn handle_power_notification(this: &mut Object, cmd: Sel, _notification: id) {
+    let event = power_notifications()
+        .iter()
+        .find(|(_, selector, _)| *selector == cmd)
+        .map(|(_, _, event)| *event);
+    if let Some(event) = event {
+        unsafe {
+            let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+            let inner = &mut *(inner as *mut DelegateState);
+            (*inner).power_event(event);
+        }
+    }
+}
+
+extern "C" fn locale_did_change(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).locale_changed();
+    }
+}
+
+extern "C" fn power_sources_changed(context: *mut c_void) {
+    unsafe {
+        let inner = &mut *(context as *mut DelegateState);
+        inner.power_sources_changed();
+    }
+}
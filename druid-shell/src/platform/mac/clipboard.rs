@@ -14,11 +14,14 @@
 
 //! Interactions with the system pasteboard on macOS.
 
+use std::path::{Path, PathBuf};
+
 use cocoa::appkit::NSPasteboardTypeString;
 use cocoa::base::{id, nil, BOOL, YES};
 use cocoa::foundation::{NSArray, NSInteger, NSUInteger};
 use objc::{class, msg_send, sel, sel_impl};
 
+use super::appkit::NSPasteboardURLReadingFileURLsOnlyKey;
 use super::util;
 use crate::clipboard::{ClipboardFormat, FormatId};
 
@@ -70,6 +73,57 @@ impl Clipboard {
         }
     }
 
+    /// Put a list of files on the system clipboard.
+    pub fn put_files(&mut self, paths: &[&Path]) {
+        unsafe {
+            let urls = paths
+                .iter()
+                .filter_map(|path| match path.to_str() {
+                    Some(path) => {
+                        let url: id =
+                            msg_send![class!(NSURL), fileURLWithPath: util::make_nsstring(path)];
+                        Some(url)
+                    }
+                    None => {
+                        tracing::warn!("can't put non-UTF-8 path {:?} on the clipboard", path);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            let array = NSArray::arrayWithObjects(nil, &urls);
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let _: NSInteger = msg_send![pasteboard, clearContents];
+            let result: BOOL = msg_send![pasteboard, writeObjects: array];
+            if result != YES {
+                tracing::warn!("failed to put files on the clipboard");
+            }
+        }
+    }
+
+    /// Get a list of files from the system clipboard, if there is one.
+    pub fn get_files(&self) -> Option<Vec<PathBuf>> {
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let url_class: id = msg_send![class!(NSURL), class];
+            let classes = NSArray::arrayWithObjects(nil, &[url_class]);
+            let yes: id = msg_send![class!(NSNumber), numberWithBool: YES];
+            let options: id = msg_send![class!(NSDictionary),
+                dictionaryWithObject: yes
+                forKey: NSPasteboardURLReadingFileURLsOnlyKey];
+            let urls: id = msg_send![pasteboard, readObjectsForClasses: classes options: options];
+            if urls.is_null() || urls.count() == 0 {
+                return None;
+            }
+            let paths = (0..urls.count())
+                .map(|i| {
+                    let path: id = msg_send![urls.objectAtIndex(i), path];
+                    PathBuf::from(util::from_nsstring(path))
+                })
+                .collect();
+            Some(paths)
+        }
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         unsafe {
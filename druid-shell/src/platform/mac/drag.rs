@@ -0,0 +1,171 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dragging data out of the application, macOS implementation.
+
+#![allow(non_upper_case_globals)]
+
+use std::ffi::c_void;
+use std::fs::File;
+use std::path::Path;
+
+use block::Block;
+use cocoa::appkit::NSApp;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSArray, NSInteger, NSPoint, NSRect, NSSize, NSUInteger};
+use lazy_static::lazy_static;
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+
+use crate::drag::FilePromise;
+
+use super::util::{from_nsstring, make_nsstring};
+
+const NSDragOperationCopy: NSUInteger = 1;
+const NSFileWriteUnknownError: NSInteger = 512;
+
+/// The size of the icon that is shown under the mouse while dragging.
+const DRAG_ICON_SIZE: f64 = 32.0;
+
+const PROMISE_IVAR: &str = "filePromise";
+
+struct DelegateClass(*const Class);
+unsafe impl Sync for DelegateClass {}
+
+lazy_static! {
+    static ref PROMISE_DELEGATE_CLASS: DelegateClass = unsafe {
+        let mut decl = ClassDecl::new("DruidFilePromiseDelegate", class!(NSObject))
+            .expect("File promise delegate class defined");
+        decl.add_ivar::<*mut c_void>(PROMISE_IVAR);
+
+        decl.add_method(
+            sel!(filePromiseProvider:fileNameForType:),
+            file_name_for_type as extern "C" fn(&Object, Sel, id, id) -> id,
+        );
+        decl.add_method(
+            sel!(filePromiseProvider:writePromiseToURL:completionHandler:),
+            write_promise as extern "C" fn(&Object, Sel, id, id, id),
+        );
+        decl.add_method(
+            sel!(operationQueueForFilePromiseProvider:),
+            operation_queue as extern "C" fn(&Object, Sel, id) -> id,
+        );
+        decl.add_method(sel!(dealloc), dealloc as extern "C" fn(&Object, Sel));
+        DelegateClass(decl.register())
+    };
+}
+
+/// Start dragging `promise` out of `view`, from where the mouse is.
+///
+/// This must be called while handling a mouse event.
+pub(crate) unsafe fn start_file_drag(view: id, promise: FilePromise) {
+    let event: id = msg_send![NSApp(), currentEvent];
+    if event == nil {
+        tracing::warn!("start_file_drag called outside of a mouse event");
+        return;
+    }
+    let extension = Path::new(promise.file_name())
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_owned();
+
+    let delegate: id = msg_send![PROMISE_DELEGATE_CLASS.0, new];
+    (*delegate).set_ivar(
+        PROMISE_IVAR,
+        Box::into_raw(Box::new(promise)) as *mut c_void,
+    );
+    let provider: id = msg_send![class!(NSFilePromiseProvider), alloc];
+    let provider: id =
+        msg_send![provider, initWithFileType: make_nsstring("public.data") delegate: delegate];
+    // The provider only has a weak reference to its delegate, so the user
+    // info keeps it alive for as long as the provider is.
+    let () = msg_send![provider, setUserInfo: delegate];
+    let () = msg_send![delegate, release];
+
+    let item: id = msg_send![class!(NSDraggingItem), alloc];
+    let item: id = msg_send![item, initWithPasteboardWriter: provider];
+    let () = msg_send![provider, release];
+    let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+    let icon: id = msg_send![workspace, iconForFileType: make_nsstring(&extension)];
+    let location: NSPoint = msg_send![event, locationInWindow];
+    let location: NSPoint = msg_send![view, convertPoint: location fromView: nil];
+    let frame = NSRect::new(
+        NSPoint::new(
+            location.x - DRAG_ICON_SIZE / 2.0,
+            location.y - DRAG_ICON_SIZE / 2.0,
+        ),
+        NSSize::new(DRAG_ICON_SIZE, DRAG_ICON_SIZE),
+    );
+    let () = msg_send![item, setDraggingFrame: frame contents: icon];
+    let items = NSArray::arrayWithObject(nil, item);
+    let () = msg_send![item, release];
+
+    let _session: id =
+        msg_send![view, beginDraggingSessionWithItems: items event: event source: view];
+}
+
+/// The view's method of `NSDraggingSource`.
+pub(crate) extern "C" fn source_operation_mask(
+    _this: &mut Object,
+    _: Sel,
+    _session: id,
+    _context: NSInteger,
+) -> NSUInteger {
+    NSDragOperationCopy
+}
+
+extern "C" fn file_name_for_type(this: &Object, _: Sel, _provider: id, _file_type: id) -> id {
+    unsafe {
+        let promise: *mut c_void = *this.get_ivar(PROMISE_IVAR);
+        let promise = &*(promise as *mut FilePromise);
+        make_nsstring(promise.file_name())
+    }
+}
+
+extern "C" fn write_promise(this: &Object, _: Sel, _provider: id, url: id, completion: id) {
+    unsafe {
+        let promise: *mut c_void = *this.get_ivar(PROMISE_IVAR);
+        let promise = &mut *(promise as *mut FilePromise);
+        let path: id = msg_send![url, path];
+        let path = from_nsstring(path);
+        let result = File::create(&path).and_then(|mut file| promise.write(&mut file));
+        let error: id = match result {
+            Ok(()) => nil,
+            Err(e) => {
+                tracing::warn!("failed to write dragged file {}: {}", path, e);
+                msg_send![class!(NSError), errorWithDomain: make_nsstring("NSCocoaErrorDomain") code: NSFileWriteUnknownError userInfo: nil]
+            }
+        };
+        let completion = &*(completion as *mut Block<(id,), ()>);
+        completion.call((error,));
+    }
+}
+
+/// The promise is written on the main thread, where the application's data
+/// lives.
+extern "C" fn operation_queue(_this: &Object, _: Sel, _provider: id) -> id {
+    unsafe { msg_send![class!(NSOperationQueue), mainQueue] }
+}
+
+extern "C" fn dealloc(this: &Object, _: Sel) {
+    unsafe {
+        let promise: *mut c_void = *this.get_ivar(PROMISE_IVAR);
+        if !promise.is_null() {
+            Box::from_raw(promise as *mut FilePromise);
+        }
+        let () = msg_send![super(this, class!(NSObject)), dealloc];
+    }
+}
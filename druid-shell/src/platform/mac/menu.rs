@@ -14,8 +14,8 @@
 
 //! macOS implementation of menus.
 
-use cocoa::appkit::{NSEventModifierFlags, NSMenu, NSMenuItem};
-use cocoa::base::{id, nil, NO};
+use cocoa::appkit::{NSApp, NSEventModifierFlags, NSMenu, NSMenuItem};
+use cocoa::base::{id, nil, NO, YES};
 use cocoa::foundation::NSAutoreleasePool;
 use objc::{msg_send, sel, sel_impl};
 
@@ -56,6 +56,43 @@ fn make_menu_item(id: u32, text: &str, key: Option<&HotKey>, enabled: bool, sele
     }
 }
 
+/// Change the item with the given `id` in the application menu.
+///
+/// Items are found by their tag, which is their id.
+pub(crate) fn update_item(id: u32, text: &str, enabled: bool, selected: bool) {
+    unsafe {
+        let main_menu: id = msg_send![NSApp(), mainMenu];
+        let item = find_item(main_menu, id as isize);
+        if item == nil {
+            return;
+        }
+        let () = msg_send![item, setTitle: make_nsstring(&strip_access_key(text))];
+        let () = msg_send![item, setEnabled: if enabled { YES } else { NO }];
+        let () = msg_send![item, setState: if selected { 1_isize } else { 0_isize }];
+    }
+}
+
+/// Find the item with the given tag in `menu` or one of its submenus.
+unsafe fn find_item(menu: id, tag: isize) -> id {
+    if menu == nil {
+        return nil;
+    }
+    let item: id = msg_send![menu, itemWithTag: tag];
+    if item != nil {
+        return item;
+    }
+    let count: isize = msg_send![menu, numberOfItems];
+    for i in 0..count {
+        let child: id = msg_send![menu, itemAtIndex: i];
+        let submenu: id = msg_send![child, submenu];
+        let item = find_item(submenu, tag);
+        if item != nil {
+            return item;
+        }
+    }
+    nil
+}
+
 impl Menu {
     pub fn new() -> Menu {
         unsafe {
@@ -20,6 +20,7 @@ pub mod appkit;
 pub mod application;
 pub mod clipboard;
 pub mod dialog;
+mod drag;
 pub mod error;
 mod keyboard;
 pub mod menu;
@@ -34,7 +34,7 @@ use core_graphics::context::CGContextRef;
 use foreign_types::ForeignTypeRef;
 use lazy_static::lazy_static;
 use objc::declare::ClassDecl;
-use objc::rc::WeakPtr;
+use objc::rc::{StrongPtr, WeakPtr};
 use objc::runtime::{Class, Object, Protocol, Sel};
 use objc::{class, msg_send, sel, sel_impl};
 use tracing::{debug, error, info};
@@ -43,26 +43,33 @@ use tracing::{debug, error, info};
 use raw_window_handle::{macos::MacOSHandle, HasRawWindowHandle, RawWindowHandle};
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::piet::{Color, ImageBuf, Piet, PietText, RenderContext};
 
 use super::appkit::{
-    NSRunLoopCommonModes, NSTrackingArea, NSTrackingAreaOptions, NSView as NSViewExt,
+    NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+    NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityHigh,
+    NSAccessibilityPriorityKey, NSAccessibilityPriorityMedium, NSRunLoopCommonModes,
+    NSTrackingArea, NSTrackingAreaOptions, NSView as NSViewExt,
 };
 use super::application::Application;
 use super::dialog;
+use super::drag;
 use super::keyboard::{make_modifiers, KeyboardState};
-use super::menu::Menu;
+use super::menu::{self, Menu};
 use super::text_input::NSRange;
 use super::util::{assert_main_thread, make_nsstring};
 use crate::common_util::IdleCallback;
 use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
+use crate::drag::FilePromise;
+use crate::hotkey::HotKey;
 use crate::keyboard_types::KeyState;
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::region::Region;
 use crate::scale::Scale;
 use crate::text::{Event, InputHandler};
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel, WindowState,
+    FileDialogToken, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    WindowState,
 };
 use crate::Error;
 
@@ -126,6 +133,10 @@ pub(crate) struct WindowBuilder {
     resizable: bool,
     show_titlebar: bool,
     transparent: bool,
+    background_color: Option<Color>,
+    always_on_top: bool,
+    fullscreen: bool,
+    icon: Option<ImageBuf>,
 }
 
 #[derive(Clone)]
@@ -179,6 +190,10 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             transparent: false,
+            background_color: None,
+            always_on_top: false,
+            fullscreen: false,
+            icon: None,
         }
     }
 
@@ -206,10 +221,26 @@ impl WindowBuilder {
         self.transparent = transparent;
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_level(&mut self, level: WindowLevel) {
         self.level = Some(level);
     }
 
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.always_on_top = always_on_top;
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+    }
+
+    pub fn set_icon(&mut self, icon: ImageBuf) {
+        self.icon = Some(icon);
+    }
+
     pub fn set_position(&mut self, position: Point) {
         self.position = Some(position)
     }
@@ -262,6 +293,8 @@ impl WindowBuilder {
             if self.transparent {
                 window.setOpaque_(NO);
                 window.setBackgroundColor_(NSColor::clearColor(nil));
+            } else if let Some(color) = &self.background_color {
+                window.setBackgroundColor_(make_nscolor(color));
             }
 
             window.setTitle_(make_nsstring(&self.title));
@@ -293,6 +326,18 @@ impl WindowBuilder {
                 handle.set_level(level)
             }
 
+            if self.always_on_top {
+                handle.set_always_on_top(true);
+            }
+
+            if let Some(icon) = &self.icon {
+                handle.set_icon(icon);
+            }
+
+            if self.fullscreen {
+                handle.set_fullscreen(true);
+            }
+
             // set_window_state above could have invalidated the frame size
             let frame = NSView::frame(content_view);
 
@@ -360,6 +405,14 @@ lazy_static! {
             sel!(windowDidResignKey:),
             window_did_resign_key as extern "C" fn(&mut Object, Sel, id),
         );
+        decl.add_method(
+            sel!(windowDidMiniaturize:),
+            window_did_miniaturize as extern "C" fn(&mut Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(windowDidDeminiaturize:),
+            window_did_deminiaturize as extern "C" fn(&mut Object, Sel, id),
+        );
         decl.add_method(
             sel!(setFrameSize:),
             set_frame_size as extern "C" fn(&mut Object, Sel, NSSize),
@@ -432,6 +485,10 @@ lazy_static! {
         decl.add_method(sel!(runIdle), run_idle as extern "C" fn(&mut Object, Sel));
         decl.add_method(sel!(viewWillDraw), view_will_draw as extern "C" fn(&mut Object, Sel));
         decl.add_method(sel!(redraw), redraw as extern "C" fn(&mut Object, Sel));
+        decl.add_method(
+            sel!(viewDidChangeEffectiveAppearance),
+            view_did_change_effective_appearance as extern "C" fn(&mut Object, Sel),
+        );
         decl.add_method(
             sel!(handleTimer:),
             handle_timer as extern "C" fn(&mut Object, Sel, id),
@@ -444,6 +501,11 @@ lazy_static! {
             sel!(showContextMenu:),
             show_context_menu as extern "C" fn(&mut Object, Sel, id),
         );
+        decl.add_method(
+            sel!(draggingSession:sourceOperationMaskForDraggingContext:),
+            super::drag::source_operation_mask
+                as extern "C" fn(&mut Object, Sel, id, NSInteger) -> NSUInteger,
+        );
         decl.add_method(
             sel!(windowShouldClose:),
             window_should_close as extern "C" fn(&mut Object, Sel, id)->BOOL,
@@ -607,6 +669,7 @@ fn mouse_event(
             focus,
             button,
             wheel_delta,
+            pointer_type: PointerType::Mouse,
         }
     }
 }
@@ -618,6 +681,7 @@ fn get_mouse_button(button: NSInteger) -> Option<MouseButton> {
         2 => Some(MouseButton::Middle),
         3 => Some(MouseButton::X1),
         4 => Some(MouseButton::X2),
+        5..=255 => Some(MouseButton::Other(button as u8)),
         _ => None,
     }
 }
@@ -820,6 +884,22 @@ extern "C" fn view_will_draw(this: &mut Object, _: Sel) {
     }
 }
 
+/// An `NSColor` in the sRGB color space.
+unsafe fn make_nscolor(color: &Color) -> id {
+    let (r, g, b, a) = color.as_rgba();
+    msg_send![class!(NSColor), colorWithSRGBRed: r green: g blue: b alpha: a]
+}
+
+// this is called when the user switches between light and dark mode, or
+// changes the accent color, which changes the selection color.
+extern "C" fn view_did_change_effective_appearance(this: &mut Object, _: Sel) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        (*view_state).handler.system_settings_changed();
+    }
+}
+
 extern "C" fn draw_rect(this: &mut Object, _: Sel, dirtyRect: NSRect) {
     unsafe {
         let context: id = msg_send![class![NSGraphicsContext], currentContext];
@@ -966,6 +1046,60 @@ extern "C" fn window_did_resign_key(this: &mut Object, _: Sel, _notification: id
     }
 }
 
+/// Create an autoreleased `NSImage` from `image`.
+unsafe fn make_nsimage(image: &ImageBuf) -> id {
+    const NSBitmapFormatAlphaNonpremultiplied: NSUInteger = 1 << 1;
+
+    let width = image.width() as NSInteger;
+    let height = image.height() as NSInteger;
+    let rep: id = msg_send![class!(NSBitmapImageRep), alloc];
+    // Passing null planes makes the rep allocate its own pixel buffer.
+    let rep: id = msg_send![rep,
+        initWithBitmapDataPlanes: std::ptr::null_mut::<*mut u8>()
+        pixelsWide: width
+        pixelsHigh: height
+        bitsPerSample: 8 as NSInteger
+        samplesPerPixel: 4 as NSInteger
+        hasAlpha: YES
+        isPlanar: NO
+        colorSpaceName: make_nsstring("NSDeviceRGBColorSpace")
+        bitmapFormat: NSBitmapFormatAlphaNonpremultiplied
+        bytesPerRow: width * 4
+        bitsPerPixel: 32 as NSInteger];
+    let data: *mut u8 = msg_send![rep, bitmapData];
+    let pixels = std::slice::from_raw_parts_mut(data, image.width() * image.height() * 4);
+    for (dst, color) in pixels
+        .chunks_exact_mut(4)
+        .zip(image.pixel_colors().flatten())
+    {
+        let (r, g, b, a) = color.as_rgba8();
+        dst.copy_from_slice(&[r, g, b, a]);
+    }
+
+    let nsimage: id = msg_send![class!(NSImage), alloc];
+    let nsimage: id =
+        msg_send![nsimage, initWithSize: NSSize::new(width as CGFloat, height as CGFloat)];
+    let () = msg_send![nsimage, addRepresentation: rep];
+    let () = msg_send![rep, release];
+    msg_send![nsimage, autorelease]
+}
+
+extern "C" fn window_did_miniaturize(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        (*view_state).handler.suspended();
+    }
+}
+
+extern "C" fn window_did_deminiaturize(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        (*view_state).handler.resumed();
+    }
+}
+
 extern "C" fn window_should_close(this: &mut Object, _: Sel, _window: id) -> BOOL {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
@@ -1197,9 +1331,76 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_background_color(&self, color: Color) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            // transparent windows keep their clear background.
+            let opaque: BOOL = msg_send![window, isOpaque];
+            if opaque == YES {
+                window.setBackgroundColor_(make_nscolor(&color));
+            }
+        }
+    }
+
+    // AppKit only supports one of resize increments and aspect ratio at a
+    // time; setting one removes the other.
+    pub fn set_resize_increments(&self, width: f64, height: f64) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            // increments of one point are the same as no increments.
+            let size = NSSize::new(width.max(1.0), height.max(1.0));
+            let () = msg_send![window, setContentResizeIncrements: size];
+        }
+    }
+
+    pub fn set_aspect_ratio(&self, numerator: u32, denominator: u32) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            if numerator == 0 || denominator == 0 {
+                // there is no way to unset the aspect ratio, other than
+                // setting the increments.
+                let () = msg_send![window, setContentResizeIncrements: NSSize::new(1.0, 1.0)];
+            } else {
+                let size = NSSize::new(numerator as f64, denominator as f64);
+                let () = msg_send![window, setContentAspectRatio: size];
+            }
+        }
+    }
+
     // TODO: Implement this
     pub fn show_titlebar(&self, _show_titlebar: bool) {}
 
+    pub fn announce(&self, text: &str, politeness: Politeness) {
+        let priority = match politeness {
+            Politeness::Polite => NSAccessibilityPriorityMedium,
+            Politeness::Assertive => NSAccessibilityPriorityHigh,
+        };
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            if window.is_null() {
+                return;
+            }
+            let priority: id = msg_send![class!(NSNumber), numberWithInteger: priority];
+            let keys = [NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey];
+            let values = [make_nsstring(text), priority];
+            let user_info: id = msg_send![class!(NSDictionary),
+                dictionaryWithObjects: values.as_ptr()
+                forKeys: keys.as_ptr()
+                count: keys.len()];
+            NSAccessibilityPostNotificationWithUserInfo(
+                window,
+                NSAccessibilityAnnouncementRequestedNotification,
+                user_info,
+            );
+        }
+    }
+
+    pub fn start_file_drag(&self, promise: FilePromise) {
+        unsafe {
+            drag::start_file_drag(*self.nsview.load(), promise);
+        }
+    }
+
     // Need to translate mac y coords, as they start from bottom left
     pub fn set_position(&self, position: Point) {
         self.defer(DeferredOp::SetPosition(position))
@@ -1256,6 +1457,38 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        let level = if always_on_top {
+            levels::NSFloatingWindowLevel
+        } else {
+            levels::NSNormalWindowLevel
+        };
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            let () = msg_send![window, setLevel: level];
+        }
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            let is_fullscreen = window
+                .styleMask()
+                .contains(NSWindowStyleMask::NSFullScreenWindowMask);
+            if is_fullscreen != fullscreen {
+                let () = msg_send![window, toggleFullScreen: nil];
+            }
+        }
+    }
+
+    /// Windows don't have icons on macOS, so this sets the application's icon in the Dock.
+    pub fn set_icon(&self, icon: &ImageBuf) {
+        unsafe {
+            let image = make_nsimage(icon);
+            let () = msg_send![NSApp(), setApplicationIconImage: image];
+        }
+    }
+
     pub fn set_size(&self, size: Size) {
         self.defer(DeferredOp::SetSize(size));
     }
@@ -1331,6 +1564,18 @@ impl WindowHandle {
         }
     }
 
+    pub fn update_menu_item(
+        &self,
+        id: u32,
+        text: &str,
+        _key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
+    ) {
+        // the menu of the frontmost window is the application's menu.
+        menu::update_item(id, text, enabled, selected);
+    }
+
     //FIXME: we should be using the x, y values passed by the caller, but then
     //we have to figure out some way to pass them along with this performSelector:
     //call. This isn't super hard, I'm just not up for it right now.
@@ -1363,6 +1608,127 @@ impl WindowHandle {
         // TODO: Get actual Scale
         Ok(Scale::new(1.0, 1.0))
     }
+
+    #[cfg(feature = "raw-win-handle")]
+    pub unsafe fn embed_foreign_window(
+        &self,
+        child: RawWindowHandle,
+    ) -> Result<ForeignWindow, Error> {
+        let view = match child {
+            RawWindowHandle::MacOS(handle) if !handle.ns_view.is_null() => handle.ns_view as id,
+            _ => return Err(anyhow::anyhow!("expected a non-null NSView").into()),
+        };
+        let parent = self.nsview.load();
+        if parent.is_null() {
+            return Err(Error::WindowDropped);
+        }
+        let () = msg_send![*parent, addSubview: view];
+        Ok(ForeignWindow {
+            view: StrongPtr::retain(view),
+        })
+    }
+
+    #[cfg(feature = "raw-win-handle")]
+    pub fn create_render_surface(&self, frame: Rect) -> Result<RenderSurface, Error> {
+        unsafe {
+            let parent = self.nsview.load();
+            if parent.is_null() {
+                return Err(Error::WindowDropped);
+            }
+            // our view is flipped, so this is already in the right coordinate space.
+            let frame = NSRect::new(
+                NSPoint::new(frame.x0, frame.y0),
+                NSSize::new(frame.width(), frame.height()),
+            );
+            let view: id = msg_send![class!(NSView), alloc];
+            let view = StrongPtr::new(msg_send![view, initWithFrame: frame]);
+            // Metal and OpenGL renderers need a layer to present into. Mouse events that the
+            // view doesn't handle go up the responder chain to our view.
+            let () = msg_send![*view, setWantsLayer: YES];
+            let () = msg_send![*parent, addSubview: *view];
+            Ok(RenderSurface { view })
+        }
+    }
+}
+
+/// A view created by someone else, that has been added as a subview of one of
+/// our views.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) struct ForeignWindow {
+    view: StrongPtr,
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl ForeignWindow {
+    pub fn set_frame(&self, frame: Rect) {
+        // our view is flipped, so this is already in the right coordinate space.
+        let frame = NSRect::new(
+            NSPoint::new(frame.x0, frame.y0),
+            NSSize::new(frame.width(), frame.height()),
+        );
+        unsafe {
+            let () = msg_send![*self.view, setFrame: frame];
+        }
+    }
+
+    pub fn focus(&self) {
+        unsafe {
+            let window: id = msg_send![*self.view, window];
+            if !window.is_null() {
+                let _: BOOL = msg_send![window, makeFirstResponder: *self.view];
+            }
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl Drop for ForeignWindow {
+    fn drop(&mut self) {
+        unsafe {
+            let () = msg_send![*self.view, removeFromSuperview];
+        }
+    }
+}
+
+/// A subview that we don't draw to.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) struct RenderSurface {
+    view: StrongPtr,
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl RenderSurface {
+    pub fn set_frame(&self, frame: Rect) {
+        // our view is flipped, so this is already in the right coordinate space.
+        let frame = NSRect::new(
+            NSPoint::new(frame.x0, frame.y0),
+            NSSize::new(frame.width(), frame.height()),
+        );
+        unsafe {
+            let () = msg_send![*self.view, setFrame: frame];
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl Drop for RenderSurface {
+    fn drop(&mut self) {
+        unsafe {
+            let () = msg_send![*self.view, removeFromSuperview];
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe impl HasRawWindowHandle for RenderSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let ns_window: id = unsafe { msg_send![*self.view, window] };
+        RawWindowHandle::MacOS(MacOSHandle {
+            ns_window: ns_window as *mut _,
+            ns_view: *self.view as *mut _,
+            ..MacOSHandle::empty()
+        })
+    }
 }
 
 #[cfg(feature = "raw-win-handle")]
@@ -0,0 +1,57 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading the preferred locales on Linux.
+
+use std::env;
+
+/// The user's preferred locales, from the environment variables that gettext
+/// looks at.
+///
+/// See <https://www.gnu.org/software/gettext/manual/html_node/Locale-Environment-Variables.html>.
+pub fn preferred_locales() -> Vec<String> {
+    let var_non_empty = |var| env::var(var).ok().filter(|value| !value.is_empty());
+    // LANGUAGE is a list of locales separated by colons, which is only used
+    // when there is a locale other than "C".
+    let locale = var_non_empty("LC_ALL")
+        .or_else(|| var_non_empty("LC_MESSAGES"))
+        .or_else(|| var_non_empty("LANG"));
+    let mut locales: Vec<String> = Vec::new();
+    let names = match (var_non_empty("LANGUAGE"), &locale) {
+        (Some(language), Some(locale)) if to_language_identifier(locale).is_some() => {
+            format!("{}:{}", language, locale)
+        }
+        (_, Some(locale)) => locale.clone(),
+        (_, None) => return locales,
+    };
+    for locale in names.split(':').filter_map(to_language_identifier) {
+        if !locales.contains(&locale) {
+            locales.push(locale);
+        }
+    }
+    locales
+}
+
+/// Turn a POSIX locale name, such as `de_CH.UTF-8@euro`, into a Unicode
+/// language identifier, such as `de-CH`.
+///
+/// The "C" and "POSIX" locales have no language, and return `None`.
+fn to_language_identifier(name: &str) -> Option<String> {
+    let name = name.split(['.', '@']).next().unwrap_or_default();
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        None
+    } else {
+        Some(name.replace('_', "-"))
+    }
+}
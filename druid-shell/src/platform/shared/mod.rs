@@ -20,3 +20,12 @@ cfg_if::cfg_if! {
         pub use keyboard::*;
     }
 }
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "linux")] {
+        mod locale;
+        pub use locale::*;
+        mod power;
+        pub use power::*;
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading the power status on Linux.
+
+use std::fs;
+use std::path::Path;
+
+use crate::application::PowerStatus;
+
+const POWER_SUPPLIES: &str = "/sys/class/power_supply";
+
+/// Read the power status from the power supplies that the kernel knows about.
+pub fn power_status() -> PowerStatus {
+    read_power_status(Path::new(POWER_SUPPLIES))
+}
+
+/// Read the power status from a directory laid out like `/sys/class/power_supply`.
+fn read_power_status(dir: &Path) -> PowerStatus {
+    let supplies = match fs::read_dir(dir) {
+        Ok(supplies) => supplies,
+        Err(e) => {
+            tracing::debug!("failed to read {}: {}", dir.display(), e);
+            return PowerStatus::default();
+        }
+    };
+    let mut plugged_in = false;
+    let mut batteries = 0;
+    let mut capacity = 0;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        match read_attribute(&path, "type").as_deref() {
+            Some("Mains") => plugged_in |= read_attribute(&path, "online").as_deref() == Some("1"),
+            // batteries of devices such as mice have a "Device" scope.
+            Some("Battery") if read_attribute(&path, "scope").as_deref() != Some("Device") => {
+                let percent = read_attribute(&path, "capacity").and_then(|c| c.parse::<u32>().ok());
+                if let Some(percent) = percent {
+                    batteries += 1;
+                    capacity += percent.min(100);
+                }
+            }
+            _ => (),
+        }
+    }
+    let battery_percent = capacity.checked_div(batteries).map(|percent| percent as u8);
+    PowerStatus {
+        on_battery: battery_percent.is_some() && !plugged_in,
+        battery_percent,
+    }
+}
+
+fn read_attribute(supply: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(supply.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn add_supply(dir: &Path, name: &str, attributes: &[(&str, &str)]) {
+        let supply = dir.join(name);
+        fs::create_dir_all(&supply).unwrap();
+        for (attribute, value) in attributes {
+            fs::write(supply.join(attribute), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn batteries_and_mains() {
+        let dir = std::env::temp_dir().join(format!("druid-shell-power-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(read_power_status(&dir), PowerStatus::default());
+
+        add_supply(&dir, "BAT0", &[("type", "Battery"), ("capacity", "80")]);
+        add_supply(&dir, "BAT1", &[("type", "Battery"), ("capacity", "40")]);
+        // a mouse's battery doesn't power the system.
+        add_supply(
+            &dir,
+            "hid-mouse",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "5")],
+        );
+        add_supply(&dir, "AC", &[("type", "Mains"), ("online", "0")]);
+        let status = read_power_status(&dir);
+        assert!(status.on_battery);
+        assert_eq!(status.battery_percent, Some(60));
+
+        add_supply(&dir, "AC", &[("online", "1")]);
+        let status = read_power_status(&dir);
+        assert!(!status.on_battery);
+        assert_eq!(status.battery_percent, Some(60));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -14,7 +14,9 @@
 
 //! Web implementation of features at the application scope.
 
-use crate::application::AppHandler;
+use std::path::Path;
+
+use crate::application::{AppHandler, MouseSettings, PowerStatus, RegionalSettings, TextSettings};
 
 use super::clipboard::Clipboard;
 use super::error::Error;
@@ -31,6 +33,14 @@ impl Application {
 
     pub fn quit(&self) {}
 
+    pub fn set_badge(&self, _count: Option<u64>) {
+        tracing::warn!("Application::set_badge unimplemented for web");
+    }
+
+    pub fn add_recent_document(&self, _path: &Path) {
+        tracing::warn!("Application::add_recent_document unimplemented for web");
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
@@ -40,4 +50,34 @@ impl Application {
             .and_then(|w| w.navigator().language())
             .unwrap_or_else(|| "en-US".into())
     }
+
+    pub fn preferred_locales() -> Vec<String> {
+        web_sys::window()
+            .map(|w| {
+                w.navigator()
+                    .languages()
+                    .iter()
+                    .filter_map(|language| language.as_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn regional_settings() -> RegionalSettings {
+        RegionalSettings::default()
+    }
+
+    pub fn get_text_settings() -> TextSettings {
+        TextSettings::default()
+    }
+
+    pub fn get_mouse_settings() -> MouseSettings {
+        MouseSettings::default()
+    }
+
+    pub fn power_status() -> PowerStatus {
+        // The battery status API of browsers is asynchronous.
+        tracing::warn!("Application::power_status unimplemented for web");
+        PowerStatus::default()
+    }
 }
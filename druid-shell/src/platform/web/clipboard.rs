@@ -14,6 +14,8 @@
 
 //! Interactions with the browser pasteboard.
 
+use std::path::{Path, PathBuf};
+
 use crate::clipboard::{ClipboardFormat, FormatId};
 
 /// The browser clipboard.
@@ -31,6 +33,17 @@ impl Clipboard {
         tracing::warn!("unimplemented");
     }
 
+    /// Put a list of files on the system clipboard.
+    pub fn put_files(&mut self, _paths: &[&Path]) {
+        tracing::warn!("unimplemented");
+    }
+
+    /// Get a list of files from the system clipboard, if there is one.
+    pub fn get_files(&self) -> Option<Vec<PathBuf>> {
+        tracing::warn!("unimplemented");
+        None
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         tracing::warn!("unimplemented");
@@ -29,7 +29,7 @@ use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
 
-use crate::piet::{PietText, RenderContext};
+use crate::piet::{Color, ImageBuf, PietText, RenderContext};
 
 use super::application::Application;
 use super::error::Error;
@@ -37,16 +37,18 @@ use super::keycodes::convert_keyboard_event;
 use super::menu::Menu;
 use crate::common_util::{ClickCounter, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType};
+use crate::drag::FilePromise;
 use crate::error::Error as ShellError;
+use crate::hotkey::HotKey;
 use crate::scale::{Scale, ScaledArea};
 
 use crate::keyboard::{KbKey, KeyState, Modifiers};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::region::Region;
 use crate::text::{simulate_input, Event};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    FileDialogToken, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 // This is a macro instead of a function since KeyboardEvent and MouseEvent has identical functions
@@ -75,11 +77,45 @@ pub(crate) struct WindowBuilder {
     title: String,
     cursor: Cursor,
     menu: Option<Menu>,
+    background_color: Option<Color>,
 }
 
 #[derive(Clone, Default)]
 pub struct WindowHandle(Weak<WindowState>);
 
+/// Embedding foreign windows is not supported, so this can't be created.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) enum ForeignWindow {}
+
+#[cfg(feature = "raw-win-handle")]
+impl ForeignWindow {
+    pub fn set_frame(&self, _frame: Rect) {
+        match *self {}
+    }
+
+    pub fn focus(&self) {
+        match *self {}
+    }
+}
+
+/// Render surfaces are not supported, so this can't be created.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) enum RenderSurface {}
+
+#[cfg(feature = "raw-win-handle")]
+impl RenderSurface {
+    pub fn set_frame(&self, _frame: Rect) {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe impl HasRawWindowHandle for RenderSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        match *self {}
+    }
+}
+
 #[cfg(feature = "raw-win-handle")]
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
@@ -111,6 +147,9 @@ struct WindowState {
     context: web_sys::CanvasRenderingContext2d,
     invalid: RefCell<Region>,
     click_counter: ClickCounter,
+    /// The kind of device of the most recent pointer event, which browsers send
+    /// before the mouse events that they make for it.
+    pointer_type: Cell<PointerType>,
     active_text_input: Cell<Option<TextFieldToken>>,
     rendering_soon: Cell<bool>,
 }
@@ -178,6 +217,20 @@ impl WindowState {
     }
 }
 
+fn setup_pointer_type_callback(ws: &Rc<WindowState>) {
+    for event_type in &["pointerdown", "pointermove"] {
+        let state = ws.clone();
+        register_canvas_event_listener(ws, event_type, move |event: web_sys::PointerEvent| {
+            let pointer_type = match event.pointer_type().as_str() {
+                "touch" => PointerType::Touch,
+                "pen" => PointerType::Pen,
+                _ => PointerType::Mouse,
+            };
+            state.pointer_type.set(pointer_type);
+        });
+    }
+}
+
 fn setup_mouse_down_callback(ws: &Rc<WindowState>) {
     let state = ws.clone();
     register_canvas_event_listener(ws, "mousedown", move |event: web_sys::MouseEvent| {
@@ -194,6 +247,7 @@ fn setup_mouse_down_callback(ws: &Rc<WindowState>) {
                 focus: false,
                 button,
                 wheel_delta: Vec2::ZERO,
+                pointer_type: state.pointer_type.get(),
             };
             state.handler.borrow_mut().mouse_down(&event);
         }
@@ -213,6 +267,7 @@ fn setup_mouse_up_callback(ws: &Rc<WindowState>) {
                 focus: false,
                 button,
                 wheel_delta: Vec2::ZERO,
+                pointer_type: state.pointer_type.get(),
             };
             state.handler.borrow_mut().mouse_up(&event);
         }
@@ -231,6 +286,7 @@ fn setup_mouse_move_callback(ws: &Rc<WindowState>) {
             focus: false,
             button: MouseButton::None,
             wheel_delta: Vec2::ZERO,
+            pointer_type: state.pointer_type.get(),
         };
         state.handler.borrow_mut().mouse_move(&event);
     });
@@ -266,6 +322,7 @@ fn setup_scroll_callback(ws: &Rc<WindowState>) {
             focus: false,
             button: MouseButton::None,
             wheel_delta,
+            pointer_type: PointerType::Mouse,
         };
         state.handler.borrow_mut().wheel(&event);
     });
@@ -335,6 +392,7 @@ where
 }
 
 fn setup_web_callbacks(window_state: &Rc<WindowState>) {
+    setup_pointer_type_callback(window_state);
     setup_mouse_down_callback(window_state);
     setup_mouse_move_callback(window_state);
     setup_mouse_up_callback(window_state);
@@ -351,6 +409,7 @@ impl WindowBuilder {
             title: String::new(),
             cursor: Cursor::Arrow,
             menu: None,
+            background_color: None,
         }
     }
 
@@ -379,6 +438,10 @@ impl WindowBuilder {
         // Ignored
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_position(&mut self, _position: Point) {
         // Ignored
     }
@@ -391,6 +454,18 @@ impl WindowBuilder {
         // ignored
     }
 
+    pub fn set_always_on_top(&mut self, _always_on_top: bool) {
+        warn!("WindowBuilder::set_always_on_top is currently unimplemented for web.");
+    }
+
+    pub fn set_fullscreen(&mut self, _fullscreen: bool) {
+        warn!("WindowBuilder::set_fullscreen is currently unimplemented for web.");
+    }
+
+    pub fn set_icon(&mut self, _icon: ImageBuf) {
+        warn!("WindowBuilder::set_icon is currently unimplemented for web.");
+    }
+
     pub fn set_title<S: Into<String>>(&mut self, title: S) {
         self.title = title.into();
     }
@@ -430,6 +505,9 @@ impl WindowBuilder {
         let size_dp = area.size_dp();
 
         set_cursor(&canvas, &self.cursor);
+        if let Some(color) = &self.background_color {
+            set_background_color(&canvas, color);
+        }
 
         let handler = self.handler.unwrap();
 
@@ -443,6 +521,7 @@ impl WindowBuilder {
             context,
             invalid: RefCell::new(Region::EMPTY),
             click_counter: ClickCounter::default(),
+            pointer_type: Cell::new(PointerType::Mouse),
             active_text_input: Cell::new(None),
             rendering_soon: Cell::new(false),
         });
@@ -487,6 +566,18 @@ impl WindowHandle {
         warn!("WindowHandle::set_level  is currently unimplemented for web.");
     }
 
+    pub fn set_always_on_top(&self, _always_on_top: bool) {
+        warn!("WindowHandle::set_always_on_top is currently unimplemented for web.");
+    }
+
+    pub fn set_fullscreen(&self, _fullscreen: bool) {
+        warn!("WindowHandle::set_fullscreen is currently unimplemented for web.");
+    }
+
+    pub fn set_icon(&self, _icon: &ImageBuf) {
+        warn!("WindowHandle::set_icon is currently unimplemented for web.");
+    }
+
     pub fn get_position(&self) -> Point {
         warn!("WindowHandle::get_position unimplemented for web.");
         Point::new(0.0, 0.0)
@@ -670,14 +761,62 @@ impl WindowHandle {
             .get())
     }
 
+    #[cfg(feature = "raw-win-handle")]
+    pub unsafe fn embed_foreign_window(
+        &self,
+        _child: RawWindowHandle,
+    ) -> Result<ForeignWindow, ShellError> {
+        Err(anyhow::anyhow!("embedding foreign windows is not supported on web").into())
+    }
+
+    #[cfg(feature = "raw-win-handle")]
+    pub fn create_render_surface(&self, _frame: Rect) -> Result<RenderSurface, ShellError> {
+        Err(anyhow::anyhow!("render surfaces are not supported on web").into())
+    }
+
     pub fn set_menu(&self, _menu: Menu) {
         warn!("set_menu unimplemented for web");
     }
 
+    pub fn update_menu_item(
+        &self,
+        _id: u32,
+        _text: &str,
+        _key: Option<&HotKey>,
+        _enabled: bool,
+        _selected: bool,
+    ) {
+        // there are no menus on the web, see set_menu.
+    }
+
     pub fn show_context_menu(&self, _menu: Menu, _pos: Point) {
         warn!("show_context_menu unimplemented for web");
     }
 
+    pub fn start_file_drag(&self, _promise: FilePromise) {
+        warn!("start_file_drag unimplemented for web");
+    }
+
+    pub fn announce(&self, text: &str, politeness: Politeness) {
+        if let Err(e) = announce(text, politeness) {
+            warn!("failed to announce text: {}", e);
+        }
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        if let Some(state) = self.0.upgrade() {
+            set_background_color(&state.canvas, &color);
+        }
+    }
+
+    pub fn set_resize_increments(&self, _width: f64, _height: f64) {
+        warn!("WindowHandle::set_resize_increments unimplemented for web");
+    }
+
+    pub fn set_aspect_ratio(&self, _numerator: u32, _denominator: u32) {
+        warn!("WindowHandle::set_aspect_ratio unimplemented for web");
+    }
+
     pub fn set_title(&self, title: impl Into<String>) {
         if let Some(state) = self.0.upgrade() {
             state.canvas.set_title(&(title.into()))
@@ -685,6 +824,39 @@ impl WindowHandle {
     }
 }
 
+/// Put `text` in a visually hidden ARIA live region, creating it if needed.
+fn announce(text: &str, politeness: Politeness) -> Result<(), Error> {
+    let (id, live, role) = match politeness {
+        Politeness::Polite => ("druid-live-region-polite", "polite", "status"),
+        Politeness::Assertive => ("druid-live-region-assertive", "assertive", "alert"),
+    };
+    let document = web_sys::window()
+        .ok_or(Error::NoWindow)?
+        .document()
+        .ok_or(Error::NoDocument)?;
+    let region = match document.get_element_by_id(id) {
+        Some(region) => region,
+        None => {
+            let region = document.create_element("div")?;
+            region.set_id(id);
+            region.set_attribute("aria-live", live)?;
+            region.set_attribute("role", role)?;
+            // screen readers still see it, but it isn't drawn.
+            region.set_attribute(
+                "style",
+                "position: absolute; width: 1px; height: 1px; overflow: hidden; clip: rect(0 0 0 0);",
+            )?;
+            document
+                .body()
+                .ok_or(Error::NoDocument)?
+                .append_child(&region)?;
+            region
+        }
+    };
+    region.set_text_content(Some(text));
+    Ok(())
+}
+
 unsafe impl Send for IdleHandle {}
 
 impl IdleHandle {
@@ -732,6 +904,7 @@ fn mouse_button(button: i16) -> Option<MouseButton> {
         2 => Some(MouseButton::Right),
         3 => Some(MouseButton::X1),
         4 => Some(MouseButton::X2),
+        5..=255 => Some(MouseButton::Other(button as u8)),
         _ => None,
     }
 }
@@ -756,6 +929,14 @@ fn mouse_buttons(mask: u16) -> MouseButtons {
     buttons
 }
 
+fn set_background_color(canvas: &web_sys::HtmlCanvasElement, color: &Color) {
+    let (r, g, b, a) = color.as_rgba8();
+    let css = format!("rgba({}, {}, {}, {})", r, g, b, a as f64 / 255.0);
+    if let Err(e) = canvas.style().set_property("background-color", &css) {
+        warn!("failed to set the background color: {:?}", e);
+    }
+}
+
 fn set_cursor(canvas: &web_sys::HtmlCanvasElement, cursor: &Cursor) {
     canvas
         .style()
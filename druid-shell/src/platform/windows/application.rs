@@ -17,29 +17,43 @@
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::mem;
+use std::path::Path;
 use std::ptr;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use winapi::shared::minwindef::{FALSE, HINSTANCE};
+use winapi::ctypes::c_int;
+use winapi::shared::minwindef::{DWORD, FALSE, HINSTANCE, LPCVOID, UINT, ULONG};
 use winapi::shared::ntdef::LPCWSTR;
 use winapi::shared::windef::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, HCURSOR, HWND};
 use winapi::shared::winerror::HRESULT_FROM_WIN32;
 use winapi::um::errhandlingapi::GetLastError;
 use winapi::um::shellscalingapi::PROCESS_PER_MONITOR_DPI_AWARE;
+use winapi::um::winbase::{GetSystemPowerStatus, INFINITE, SYSTEM_POWER_STATUS};
+use winapi::um::winnls::{
+    GetLocaleInfoEx, GetUserPreferredUILanguages, LCTYPE, LOCALE_IFIRSTDAYOFWEEK, LOCALE_SDECIMAL,
+    LOCALE_SSHORTDATE, LOCALE_STHOUSAND, MUI_LANGUAGE_NAME,
+};
 use winapi::um::winuser::{
-    DispatchMessageW, GetAncestor, GetMessageW, LoadIconW, PeekMessageW, PostMessageW,
-    PostQuitMessage, RegisterClassW, TranslateAcceleratorW, TranslateMessage, GA_ROOT,
-    IDI_APPLICATION, MSG, PM_NOREMOVE, WM_TIMER, WNDCLASSW,
+    DispatchMessageW, GetAncestor, GetCaretBlinkTime, GetDoubleClickTime, GetMessageW, GetSysColor,
+    GetSystemMetrics, LoadIconW, PeekMessageW, PostMessageW, PostQuitMessage, RegisterClassW,
+    SystemParametersInfoW, TranslateAcceleratorW, TranslateMessage, COLOR_HIGHLIGHT,
+    COLOR_HIGHLIGHTTEXT, GA_ROOT, IDI_APPLICATION, MSG, PM_NOREMOVE, SM_CXDOUBLECLK,
+    SPI_GETCARETWIDTH, WM_TIMER, WNDCLASSW,
 };
 
 use piet_common::D2DLoadedFonts;
 
-use crate::application::AppHandler;
+use crate::application::{
+    AppHandler, MouseSettings, PowerEvent, PowerStatus, RegionalSettings, TextSettings, Weekday,
+};
+use crate::piet::Color;
 
 use super::accels;
 use super::clipboard::Clipboard;
 use super::error::Error;
+use super::taskbar;
 use super::util::{self, ToWide, CLASS_NAME, OPTIONAL_FUNCTIONS};
 use super::window::{self, DS_REQUEST_DESTROY};
 
@@ -52,6 +66,23 @@ pub(crate) struct Application {
 struct State {
     quitting: bool,
     windows: HashSet<HWND>,
+    handler: Option<Box<dyn AppHandler>>,
+    // The power state that was last reported to the handler.
+    asleep: bool,
+    displays_off: bool,
+    power_status: PowerStatus,
+    // The locale settings that were last reported to the handler.
+    locale: (Vec<String>, RegionalSettings),
+    // The count that is shown on the taskbar buttons of all windows.
+    badge: Option<u64>,
+}
+
+// TODO: remove these when they get added to winapi
+const SHARD_PATHW: UINT = 3;
+
+#[link(name = "shell32")]
+extern "system" {
+    fn SHAddToRecentDocs(flags: UINT, pv: LPCVOID);
 }
 
 /// Used to ensure the window class is registered only once per process.
@@ -63,6 +94,15 @@ impl Application {
         let state = Rc::new(RefCell::new(State {
             quitting: false,
             windows: HashSet::new(),
+            handler: None,
+            asleep: false,
+            displays_off: false,
+            power_status: Application::power_status(),
+            locale: (
+                Application::preferred_locales(),
+                Application::regional_settings(),
+            ),
+            badge: None,
         }));
         let fonts = D2DLoadedFonts::default();
         Ok(Application { state, fonts })
@@ -119,7 +159,8 @@ impl Application {
         self.state.borrow_mut().windows.remove(&hwnd)
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {
+    pub fn run(self, handler: Option<Box<dyn AppHandler>>) {
+        self.state.borrow_mut().handler = handler;
         unsafe {
             // Handle windows messages.
             //
@@ -160,6 +201,75 @@ impl Application {
                 }
             }
         }
+        self.state.borrow_mut().handler = None;
+    }
+
+    /// Deliver a power event to the app handler.
+    ///
+    /// Every top-level window is told about power changes, so events that do
+    /// not change the state that was last reported are dropped.
+    pub(crate) fn power_event(&self, event: PowerEvent) {
+        let changed = match self.state.try_borrow_mut() {
+            Ok(mut state) => match event {
+                PowerEvent::Suspending => !mem::replace(&mut state.asleep, true),
+                PowerEvent::Resumed => mem::replace(&mut state.asleep, false),
+                PowerEvent::DisplayOff => !mem::replace(&mut state.displays_off, true),
+                PowerEvent::DisplayOn => mem::replace(&mut state.displays_off, false),
+            },
+            Err(_) => {
+                tracing::warn!("Application state already borrowed");
+                return;
+            }
+        };
+        if changed {
+            self.with_handler(|handler| handler.power_event(event));
+        }
+    }
+
+    /// Tell the app handler about the new power status, if it has changed.
+    pub(crate) fn power_status_changed(&self) {
+        let status = Application::power_status();
+        let changed = match self.state.try_borrow_mut() {
+            Ok(mut state) => mem::replace(&mut state.power_status, status) != status,
+            Err(_) => {
+                tracing::warn!("Application state already borrowed");
+                return;
+            }
+        };
+        if changed {
+            self.with_handler(|handler| handler.power_status_changed(status));
+        }
+    }
+
+    /// Tell the app handler that the locale settings have changed, if they
+    /// have.
+    pub(crate) fn locale_changed(&self) {
+        let locale = (
+            Application::preferred_locales(),
+            Application::regional_settings(),
+        );
+        let changed = match self.state.try_borrow_mut() {
+            Ok(mut state) => mem::replace(&mut state.locale, locale.clone()) != locale,
+            Err(_) => {
+                tracing::warn!("Application state already borrowed");
+                return;
+            }
+        };
+        if changed {
+            self.with_handler(|handler| handler.locale_changed());
+        }
+    }
+
+    /// Call the app handler, if there is one.
+    ///
+    /// The state is not borrowed while the handler runs, so that it can quit
+    /// the application.
+    fn with_handler(&self, f: impl FnOnce(&mut dyn AppHandler)) {
+        let handler = self.state.borrow_mut().handler.take();
+        if let Some(mut handler) = handler {
+            f(handler.as_mut());
+            self.state.borrow_mut().handler = Some(handler);
+        }
     }
 
     pub fn quit(&self) {
@@ -189,12 +299,173 @@ impl Application {
         }
     }
 
+    pub fn set_badge(&self, count: Option<u64>) {
+        let windows: Vec<HWND> = match self.state.try_borrow_mut() {
+            Ok(mut state) => {
+                state.badge = count;
+                state.windows.iter().copied().collect()
+            }
+            Err(_) => {
+                tracing::warn!("Application state already borrowed");
+                return;
+            }
+        };
+        for hwnd in windows {
+            taskbar::set_badge(hwnd, count);
+        }
+    }
+
+    pub fn add_recent_document(&self, path: &Path) {
+        let path = path.to_wide();
+        unsafe {
+            SHAddToRecentDocs(SHARD_PATHW, path.as_ptr() as LPCVOID);
+        }
+    }
+
+    /// The count that new taskbar buttons should show.
+    pub(crate) fn badge(&self) -> Option<u64> {
+        self.state.try_borrow().ok().and_then(|state| state.badge)
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
 
     pub fn get_locale() -> String {
-        //TODO ahem
-        "en-US".into()
+        Application::preferred_locales()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "en-US".into())
     }
+
+    pub fn preferred_locales() -> Vec<String> {
+        let mut count: ULONG = 0;
+        let mut len: ULONG = 0;
+        unsafe {
+            if GetUserPreferredUILanguages(MUI_LANGUAGE_NAME, &mut count, ptr::null_mut(), &mut len)
+                == FALSE
+            {
+                return Vec::new();
+            }
+            let mut names = vec![0u16; len as usize];
+            if GetUserPreferredUILanguages(
+                MUI_LANGUAGE_NAME,
+                &mut count,
+                names.as_mut_ptr(),
+                &mut len,
+            ) == FALSE
+            {
+                return Vec::new();
+            }
+            // The names are separated by nuls, and the list ends with an empty name.
+            names
+                .split(|&c| c == 0)
+                .filter(|name| !name.is_empty())
+                .map(String::from_utf16_lossy)
+                .collect()
+        }
+    }
+
+    pub fn regional_settings() -> RegionalSettings {
+        const DAYS: [Weekday; 7] = [
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+        ];
+        RegionalSettings {
+            decimal_separator: locale_info(LOCALE_SDECIMAL),
+            grouping_separator: locale_info(LOCALE_STHOUSAND),
+            short_date_pattern: locale_info(LOCALE_SSHORTDATE),
+            // 0 is Monday.
+            first_day_of_week: locale_info(LOCALE_IFIRSTDAYOFWEEK)
+                .and_then(|day| day.parse::<usize>().ok())
+                .and_then(|day| DAYS.get(day).copied()),
+        }
+    }
+
+    pub fn get_text_settings() -> TextSettings {
+        unsafe {
+            let caret_blink_interval = match GetCaretBlinkTime() {
+                // zero means that the call failed.
+                0 => None,
+                INFINITE => Some(Duration::ZERO),
+                millis => Some(Duration::from_millis(millis as u64)),
+            };
+            let mut width: DWORD = 0;
+            let caret_width =
+                if SystemParametersInfoW(SPI_GETCARETWIDTH, 0, &mut width as *mut _ as _, 0) != 0 {
+                    Some(width as f64)
+                } else {
+                    None
+                };
+            TextSettings {
+                caret_blink_interval,
+                caret_width,
+                selection_color: Some(sys_color(COLOR_HIGHLIGHT)),
+                selected_text_color: Some(sys_color(COLOR_HIGHLIGHTTEXT)),
+            }
+        }
+    }
+
+    pub fn get_mouse_settings() -> MouseSettings {
+        unsafe {
+            // The clicks of a double click have to be within a rectangle of this width,
+            // centered on the first one.
+            let width = GetSystemMetrics(SM_CXDOUBLECLK);
+            MouseSettings {
+                double_click_interval: Some(Duration::from_millis(GetDoubleClickTime() as u64)),
+                double_click_distance: if width > 0 {
+                    Some(width as f64 / 2.0)
+                } else {
+                    None
+                },
+            }
+        }
+    }
+
+    pub fn power_status() -> PowerStatus {
+        let mut status: SYSTEM_POWER_STATUS = unsafe { mem::zeroed() };
+        if unsafe { GetSystemPowerStatus(&mut status) } == FALSE {
+            return PowerStatus::default();
+        }
+        // 255 means unknown, and battery flag 128 means that there is no battery.
+        let has_battery = status.BatteryFlag & 128 == 0;
+        let battery_percent = if has_battery && status.BatteryLifePercent <= 100 {
+            Some(status.BatteryLifePercent)
+        } else {
+            None
+        };
+        PowerStatus {
+            on_battery: has_battery && status.ACLineStatus == 0,
+            battery_percent,
+        }
+    }
+}
+
+/// A setting of the user's locale.
+fn locale_info(info: LCTYPE) -> Option<String> {
+    unsafe {
+        // A null name stands for the user's locale.
+        let len = GetLocaleInfoEx(ptr::null(), info, ptr::null_mut(), 0);
+        if len <= 0 {
+            return None;
+        }
+        let mut value = vec![0u16; len as usize];
+        let len = GetLocaleInfoEx(ptr::null(), info, value.as_mut_ptr(), len);
+        if len <= 0 {
+            return None;
+        }
+        // The length includes the terminating nul.
+        Some(String::from_utf16_lossy(&value[..len as usize - 1]))
+    }
+}
+
+/// One of the system colors, which are stored as `0x00BBGGRR`.
+fn sys_color(index: c_int) -> Color {
+    let rgb = unsafe { GetSysColor(index) };
+    Color::rgb8(rgb as u8, (rgb >> 8) as u8, (rgb >> 16) as u8)
 }
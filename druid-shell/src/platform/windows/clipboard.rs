@@ -14,8 +14,11 @@
 
 //! Interactions with the system pasteboard on Windows.
 
-use std::ffi::CString;
+use std::convert::TryInto;
+use std::ffi::{CString, OsString};
 use std::mem;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
 use std::ptr;
 
 use winapi::shared::minwindef::{FALSE, UINT};
@@ -31,6 +34,8 @@ use winapi::um::winuser::{
 
 use super::util::{FromWide, ToWide};
 use crate::clipboard::{ClipboardFormat, FormatId};
+#[cfg(all(feature = "image", feature = "image_png"))]
+use crate::piet::{ImageBuf, ImageFormat};
 
 #[derive(Debug, Clone, Default)]
 pub struct Clipboard;
@@ -72,6 +77,16 @@ impl Clipboard {
         });
     }
 
+    /// Put a list of files on the system clipboard.
+    pub fn put_files(&mut self, paths: &[&Path]) {
+        self.put_formats(&[ClipboardFormat::new("CF_HDROP", encode_hdrop(paths))])
+    }
+
+    /// Get a list of files from the system clipboard, if there is one.
+    pub fn get_files(&self) -> Option<Vec<PathBuf>> {
+        decode_hdrop(&self.get_format("CF_HDROP")?)
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         with_clipboard(|| unsafe {
@@ -290,6 +305,143 @@ fn get_format_name(format: UINT) -> String {
     }
 }
 
+/// The size of a `DROPFILES` struct, which starts a `CF_HDROP`.
+const DROPFILES_SIZE: usize = 20;
+
+/// Encode a list of files as a `CF_HDROP`: a `DROPFILES` struct followed by
+/// the paths in UTF-16.
+fn encode_hdrop(paths: &[&Path]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(DROPFILES_SIZE);
+    data.extend_from_slice(&(DROPFILES_SIZE as u32).to_le_bytes()); // pFiles
+    data.extend_from_slice(&[0; 12]); // pt, fNC
+    data.extend_from_slice(&1u32.to_le_bytes()); // fWide
+
+    // each path is null terminated, and an empty one ends the list.
+    for path in paths {
+        for unit in path.as_os_str().encode_wide().chain(Some(0)) {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+    data.extend_from_slice(&[0, 0]);
+    data
+}
+
+/// Decode a `CF_HDROP`, with either wide or ANSI paths.
+fn decode_hdrop(data: &[u8]) -> Option<Vec<PathBuf>> {
+    let u32_at = |i: usize| Some(u32::from_le_bytes(data.get(i..i + 4)?.try_into().ok()?));
+    let offset = u32_at(0)? as usize;
+    let wide = u32_at(16)? != 0;
+    let list = data.get(offset..)?;
+
+    let paths = if wide {
+        let units = list
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect::<Vec<_>>();
+        units
+            .split(|unit| *unit == 0)
+            .take_while(|path| !path.is_empty())
+            .map(|path| OsString::from_wide(path).into())
+            .collect()
+    } else {
+        // this is in the ANSI code page, which we treat as UTF-8; it is
+        // only used by very old programs.
+        list.split(|byte| *byte == 0)
+            .take_while(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).into_owned().into())
+            .collect()
+    };
+    Some(paths)
+}
+
+#[cfg(all(feature = "image", feature = "image_png"))]
+const BI_RGB: u32 = 0;
+#[cfg(all(feature = "image", feature = "image_png"))]
+const BI_BITFIELDS: u32 = 3;
+#[cfg(all(feature = "image", feature = "image_png"))]
+const BITMAPINFOHEADER_SIZE: usize = 40;
+
+/// Encode an image as a `CF_DIB`: a `BITMAPINFOHEADER` followed by bottom-up
+/// rows of BGRA pixels.
+#[cfg(all(feature = "image", feature = "image_png"))]
+pub(crate) fn encode_dib(image: &ImageBuf) -> Vec<u8> {
+    let (width, height) = (image.width(), image.height());
+    let pixels_len = width * height * 4;
+    let mut dib = Vec::with_capacity(BITMAPINFOHEADER_SIZE + pixels_len);
+    dib.extend_from_slice(&(BITMAPINFOHEADER_SIZE as u32).to_le_bytes());
+    dib.extend_from_slice(&(width as i32).to_le_bytes());
+    dib.extend_from_slice(&(height as i32).to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes()); // planes
+    dib.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    dib.extend_from_slice(&BI_RGB.to_le_bytes());
+    dib.extend_from_slice(&(pixels_len as u32).to_le_bytes());
+    // resolution, and palette sizes.
+    dib.extend_from_slice(&[0; 16]);
+
+    let rows: Vec<Vec<_>> = image.pixel_colors().map(Iterator::collect).collect();
+    for row in rows.iter().rev() {
+        for color in row {
+            let (r, g, b, a) = color.as_rgba8();
+            dib.extend_from_slice(&[b, g, r, a]);
+        }
+    }
+    dib
+}
+
+/// Decode an uncompressed 24 or 32 bit `CF_DIB`.
+#[cfg(all(feature = "image", feature = "image_png"))]
+pub(crate) fn decode_dib(dib: &[u8]) -> Option<ImageBuf> {
+    let u32_at = |i: usize| Some(u32::from_le_bytes(dib.get(i..i + 4)?.try_into().ok()?));
+    let u16_at = |i: usize| Some(u16::from_le_bytes(dib.get(i..i + 2)?.try_into().ok()?));
+
+    let header_size = u32_at(0)? as usize;
+    let width = u32_at(4)? as i32;
+    let height = u32_at(8)? as i32;
+    let bit_count = u16_at(14)? as usize;
+    let compression = u32_at(16)?;
+    if width <= 0 || height == 0 || !(bit_count == 24 || bit_count == 32) {
+        return None;
+    }
+    let mut offset = header_size;
+    match compression {
+        BI_RGB => (),
+        // the three color masks follow a plain BITMAPINFOHEADER; we assume
+        // they are the usual BGR ones.
+        BI_BITFIELDS if bit_count == 32 => {
+            if header_size == BITMAPINFOHEADER_SIZE {
+                offset += 12;
+            }
+        }
+        _ => return None,
+    }
+
+    let (width, bottom_up) = (width as usize, height > 0);
+    let height = (height as i64).abs() as usize;
+    let bytes_per_pixel = bit_count / 8;
+    let stride = (width * bytes_per_pixel + 3) & !3;
+    let pixels = dib.get(offset..offset + stride * height)?;
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let row = if bottom_up { height - 1 - y } else { y };
+        let row = &pixels[row * stride..][..width * bytes_per_pixel];
+        for pixel in row.chunks_exact(bytes_per_pixel) {
+            let alpha = if bytes_per_pixel == 4 { pixel[3] } else { 255 };
+            rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], alpha]);
+        }
+    }
+    // many programs leave the alpha channel of 32 bit DIBs empty.
+    if bytes_per_pixel == 4 && rgba.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+        rgba.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+    }
+    Some(ImageBuf::from_raw(
+        rgba,
+        ImageFormat::RgbaSeparate,
+        width,
+        height,
+    ))
+}
+
 // https://docs.microsoft.com/en-ca/windows/win32/dataxchg/standard-clipboard-formats
 static STANDARD_FORMATS: &[(UINT, &str)] = &[
     (1, "CF_TEXT"),
@@ -326,3 +478,64 @@ fn get_standard_format_name(format: UINT) -> Option<&'static str> {
         .find(|(id, _)| *id == format)
         .map(|(_, s)| *s)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn hdrop_round_trip() {
+        let paths = [
+            Path::new(r"C:\Users\me\notes.txt"),
+            Path::new(r"D:\日本語\写真.png"),
+        ];
+        let decoded = decode_hdrop(&encode_hdrop(&paths)).unwrap();
+        assert_eq!(decoded, paths.iter().map(PathBuf::from).collect::<Vec<_>>());
+        assert_eq!(decode_hdrop(&encode_hdrop(&[])), Some(Vec::new()));
+    }
+
+    #[test]
+    fn hdrop_ansi() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(DROPFILES_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 16]); // pt, fNC and fWide
+        data.extend_from_slice(b"C:\\a.txt\0C:\\b.txt\0\0");
+        let decoded = decode_hdrop(&data).unwrap();
+        assert_eq!(
+            decoded,
+            vec![PathBuf::from(r"C:\a.txt"), PathBuf::from(r"C:\b.txt")]
+        );
+        assert_eq!(decode_hdrop(&data[..8]), None);
+    }
+
+    #[cfg(all(feature = "image", feature = "image_png"))]
+    #[test]
+    fn dib_round_trip() {
+        // not square, to catch mixed up rows and columns.
+        let pixels: Vec<u8> = (0..3 * 2 * 4).map(|i| (i * 10) as u8).collect();
+        let image = ImageBuf::from_raw(pixels.clone(), ImageFormat::RgbaSeparate, 3, 2);
+        let decoded = decode_dib(&encode_dib(&image)).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        assert_eq!(decoded.raw_pixels(), &pixels[..]);
+    }
+
+    #[cfg(all(feature = "image", feature = "image_png"))]
+    #[test]
+    fn dib_24_bit() {
+        // a top-down 3x1 DIB, with each row padded to 12 bytes.
+        let mut dib = Vec::new();
+        dib.extend_from_slice(&(BITMAPINFOHEADER_SIZE as u32).to_le_bytes());
+        dib.extend_from_slice(&3i32.to_le_bytes());
+        dib.extend_from_slice(&(-1i32).to_le_bytes());
+        dib.extend_from_slice(&1u16.to_le_bytes());
+        dib.extend_from_slice(&24u16.to_le_bytes());
+        dib.extend_from_slice(&BI_RGB.to_le_bytes());
+        dib.extend_from_slice(&[0; 20]);
+        dib.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 0, 0]);
+
+        let decoded = decode_dib(&dib).unwrap();
+        let expected = [3, 2, 1, 255, 6, 5, 4, 255, 9, 8, 7, 255];
+        assert_eq!(decoded.raw_pixels(), &expected[..]);
+    }
+}
@@ -0,0 +1,478 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dragging data out of the application, Windows implementation.
+//!
+//! Files that do not exist yet are offered the way the shell expects for
+//! [virtual files]: a `FileGroupDescriptorW` with the name of the file, and
+//! `FileContents` that are only asked for once the file has been dropped.
+//!
+//! winapi has the interfaces, but no way to implement them, so the COM
+//! objects are laid out by hand.
+//!
+//! [virtual files]: https://docs.microsoft.com/en-us/windows/win32/shell/clipboard#cfstr_filecontents
+
+#![allow(non_snake_case)]
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ptr::{self, null_mut};
+
+use scopeguard::defer;
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::{IsEqualGUID, CLSID, IID, REFIID};
+use winapi::shared::minwindef::{BOOL, DWORD, FILETIME, MAX_PATH, UINT, ULONG, WORD};
+use winapi::shared::ntdef::LONG;
+use winapi::shared::windef::{POINTL, SIZEL};
+use winapi::shared::winerror::{
+    DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, DV_E_FORMATETC, DV_E_LINDEX,
+    DV_E_TYMED, E_FAIL, E_NOINTERFACE, E_NOTIMPL, E_OUTOFMEMORY, HRESULT, OLE_E_ADVISENOTSUPPORTED,
+    S_OK,
+};
+use winapi::um::objidl::IDataObject;
+use winapi::um::ole2::{DoDragDrop, OleInitialize, OleUninitialize};
+use winapi::um::oleidl::{IDropSource, DROPEFFECT_COPY};
+use winapi::um::unknwnbase::IUnknown;
+use winapi::um::winbase::{
+    GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE, GMEM_ZEROINIT,
+};
+use winapi::um::winuser::{RegisterClipboardFormatW, MK_LBUTTON, MK_MBUTTON, MK_RBUTTON};
+use winapi::Interface;
+
+use crate::drag::FilePromise;
+
+use super::error::Error;
+use super::util::{as_result, ToWide};
+
+// TODO: remove these when they get added to winapi
+const FD_PROGRESSUI: DWORD = 0x4000;
+const TYMED_HGLOBAL: DWORD = 1;
+const DVASPECT_CONTENT: DWORD = 1;
+const DATADIR_GET: DWORD = 1;
+
+#[repr(C)]
+struct FILEDESCRIPTORW {
+    dwFlags: DWORD,
+    clsid: CLSID,
+    sizel: SIZEL,
+    pointl: POINTL,
+    dwFileAttributes: DWORD,
+    ftCreationTime: FILETIME,
+    ftLastAccessTime: FILETIME,
+    ftLastWriteTime: FILETIME,
+    nFileSizeHigh: DWORD,
+    nFileSizeLow: DWORD,
+    cFileName: [u16; MAX_PATH],
+}
+
+#[repr(C)]
+struct FILEGROUPDESCRIPTORW {
+    cItems: UINT,
+    fgd: [FILEDESCRIPTORW; 1],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct FORMATETC {
+    cfFormat: WORD,
+    ptd: *mut c_void,
+    dwAspect: DWORD,
+    lindex: LONG,
+    tymed: DWORD,
+}
+
+#[repr(C)]
+struct STGMEDIUM {
+    tymed: DWORD,
+    // A union, of which only the HGLOBAL is used here.
+    hGlobal: *mut c_void,
+    pUnkForRelease: *mut c_void,
+}
+
+#[link(name = "shell32")]
+extern "system" {
+    fn SHCreateStdEnumFmtEtc(
+        cfmt: UINT,
+        afmt: *const FORMATETC,
+        ppenumFormatEtc: *mut *mut c_void,
+    ) -> HRESULT;
+}
+
+/// Drag `promise` out of the application.
+///
+/// This does not return until the file has been dropped, or the drag has
+/// been cancelled.
+pub(crate) unsafe fn drag_file_promise(promise: FilePromise) -> Result<(), Error> {
+    // Dragging needs OLE, which may be initialized any number of times.
+    as_result(OleInitialize(null_mut()))?;
+    defer!(OleUninitialize());
+
+    let data = DataObject::new(promise);
+    let source = DropSource::new();
+    let mut effect = 0;
+    let result = DoDragDrop(
+        data as *mut IDataObject,
+        source as *mut IDropSource,
+        DROPEFFECT_COPY,
+        &mut effect,
+    );
+    release::<DataObject>(data);
+    release::<DropSource>(source);
+    match result {
+        DRAGDROP_S_DROP | DRAGDROP_S_CANCEL => Ok(()),
+        _ => Err(Error::Hr(result)),
+    }
+}
+
+/// A COM object that is implemented here.
+///
+/// The objects start with a pointer to their vtable, which starts with the
+/// methods of `IUnknown`.
+trait ComObject: Sized {
+    /// The interface that the object implements, besides `IUnknown`.
+    fn iid() -> IID;
+
+    /// The reference count.
+    fn refs(&self) -> &Cell<ULONG>;
+}
+
+unsafe extern "system" fn query_interface<T: ComObject>(
+    this: *mut T,
+    riid: REFIID,
+    out: *mut *mut c_void,
+) -> HRESULT {
+    if IsEqualGUID(&*riid, &IUnknown::uuidof()) || IsEqualGUID(&*riid, &T::iid()) {
+        add_ref(this);
+        *out = this as *mut c_void;
+        S_OK
+    } else {
+        *out = null_mut();
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn add_ref<T: ComObject>(this: *mut T) -> ULONG {
+    let refs = (*this).refs();
+    refs.set(refs.get() + 1);
+    refs.get()
+}
+
+unsafe extern "system" fn release<T: ComObject>(this: *mut T) -> ULONG {
+    let refs = (*this).refs().get() - 1;
+    (*this).refs().set(refs);
+    if refs == 0 {
+        drop(Box::from_raw(this));
+    }
+    refs
+}
+
+#[repr(C)]
+struct DataObjectVtbl {
+    QueryInterface: unsafe extern "system" fn(*mut DataObject, REFIID, *mut *mut c_void) -> HRESULT,
+    AddRef: unsafe extern "system" fn(*mut DataObject) -> ULONG,
+    Release: unsafe extern "system" fn(*mut DataObject) -> ULONG,
+    GetData:
+        unsafe extern "system" fn(*mut DataObject, *const FORMATETC, *mut STGMEDIUM) -> HRESULT,
+    GetDataHere:
+        unsafe extern "system" fn(*mut DataObject, *const FORMATETC, *mut STGMEDIUM) -> HRESULT,
+    QueryGetData: unsafe extern "system" fn(*mut DataObject, *const FORMATETC) -> HRESULT,
+    GetCanonicalFormatEtc:
+        unsafe extern "system" fn(*mut DataObject, *const FORMATETC, *mut FORMATETC) -> HRESULT,
+    SetData: unsafe extern "system" fn(
+        *mut DataObject,
+        *const FORMATETC,
+        *mut STGMEDIUM,
+        BOOL,
+    ) -> HRESULT,
+    EnumFormatEtc: unsafe extern "system" fn(*mut DataObject, DWORD, *mut *mut c_void) -> HRESULT,
+    DAdvise: unsafe extern "system" fn(
+        *mut DataObject,
+        *const FORMATETC,
+        DWORD,
+        *mut c_void,
+        *mut DWORD,
+    ) -> HRESULT,
+    DUnadvise: unsafe extern "system" fn(*mut DataObject, DWORD) -> HRESULT,
+    EnumDAdvise: unsafe extern "system" fn(*mut DataObject, *mut *mut c_void) -> HRESULT,
+}
+
+static DATA_OBJECT_VTBL: DataObjectVtbl = DataObjectVtbl {
+    QueryInterface: query_interface::<DataObject>,
+    AddRef: add_ref::<DataObject>,
+    Release: release::<DataObject>,
+    GetData: DataObject::get_data,
+    GetDataHere: DataObject::get_data_here,
+    QueryGetData: DataObject::query_get_data,
+    GetCanonicalFormatEtc: DataObject::get_canonical_format_etc,
+    SetData: DataObject::set_data,
+    EnumFormatEtc: DataObject::enum_format_etc,
+    DAdvise: DataObject::d_advise,
+    DUnadvise: DataObject::d_unadvise,
+    EnumDAdvise: DataObject::enum_d_advise,
+};
+
+/// An `IDataObject` that offers a [`FilePromise`] as a virtual file.
+#[repr(C)]
+struct DataObject {
+    vtbl: &'static DataObjectVtbl,
+    refs: Cell<ULONG>,
+    formats: [FORMATETC; 2],
+    promise: RefCell<FilePromise>,
+    // The contents of the file, once they have been written.
+    contents: RefCell<Option<Vec<u8>>>,
+}
+
+impl ComObject for DataObject {
+    fn iid() -> IID {
+        IDataObject::uuidof()
+    }
+
+    fn refs(&self) -> &Cell<ULONG> {
+        &self.refs
+    }
+}
+
+impl DataObject {
+    unsafe fn new(promise: FilePromise) -> *mut DataObject {
+        let format = |name: &str, lindex| FORMATETC {
+            cfFormat: RegisterClipboardFormatW(name.to_wide().as_ptr()) as WORD,
+            ptd: null_mut(),
+            dwAspect: DVASPECT_CONTENT,
+            lindex,
+            tymed: TYMED_HGLOBAL,
+        };
+        Box::into_raw(Box::new(DataObject {
+            vtbl: &DATA_OBJECT_VTBL,
+            refs: Cell::new(1),
+            formats: [
+                format("FileGroupDescriptorW", -1),
+                format("FileContents", 0),
+            ],
+            promise: RefCell::new(promise),
+            contents: RefCell::new(None),
+        }))
+    }
+
+    /// The index in `formats` of the format that is asked for.
+    fn find_format(&self, format: &FORMATETC) -> Result<usize, HRESULT> {
+        let index = self
+            .formats
+            .iter()
+            .position(|f| f.cfFormat == format.cfFormat)
+            .ok_or(DV_E_FORMATETC)?;
+        if format.tymed & TYMED_HGLOBAL == 0 {
+            return Err(DV_E_TYMED);
+        }
+        // there is only one file, so that is the only valid index.
+        if index == 1 && format.lindex != 0 && format.lindex != -1 {
+            return Err(DV_E_LINDEX);
+        }
+        Ok(index)
+    }
+
+    /// The file group descriptor, naming the one file.
+    fn descriptor(&self) -> Vec<u8> {
+        let promise = self.promise.borrow();
+        let mut name = promise.file_name().to_wide();
+        // keep room for the nul.
+        name.truncate(MAX_PATH - 1);
+        let mut descriptor: FILEGROUPDESCRIPTORW = unsafe { mem::zeroed() };
+        descriptor.cItems = 1;
+        descriptor.fgd[0].dwFlags = FD_PROGRESSUI;
+        descriptor.fgd[0].cFileName[..name.len()].copy_from_slice(&name);
+        let bytes = &descriptor as *const FILEGROUPDESCRIPTORW as *const u8;
+        unsafe { std::slice::from_raw_parts(bytes, mem::size_of_val(&descriptor)).to_vec() }
+    }
+
+    /// The contents of the file, which are written the first time they are
+    /// asked for.
+    fn contents(&self) -> Result<Vec<u8>, HRESULT> {
+        let mut contents = self.contents.borrow_mut();
+        if contents.is_none() {
+            let mut buf = Vec::new();
+            if let Err(e) = self.promise.borrow_mut().write(&mut buf) {
+                tracing::warn!("failed to write dragged file: {}", e);
+                return Err(E_FAIL);
+            }
+            *contents = Some(buf);
+        }
+        Ok(contents.clone().unwrap_or_default())
+    }
+
+    unsafe extern "system" fn get_data(
+        this: *mut DataObject,
+        format: *const FORMATETC,
+        medium: *mut STGMEDIUM,
+    ) -> HRESULT {
+        let this = &*this;
+        let bytes = match this.find_format(&*format) {
+            Ok(0) => this.descriptor(),
+            Ok(_) => match this.contents() {
+                Ok(bytes) => bytes,
+                Err(hr) => return hr,
+            },
+            Err(hr) => return hr,
+        };
+        // an empty HGLOBAL is not allowed.
+        let global = GlobalAlloc(GMEM_MOVEABLE | GMEM_ZEROINIT, bytes.len().max(1));
+        if global.is_null() {
+            return E_OUTOFMEMORY;
+        }
+        let ptr = GlobalLock(global);
+        if ptr.is_null() {
+            GlobalFree(global);
+            return E_OUTOFMEMORY;
+        }
+        ptr::copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, bytes.len());
+        GlobalUnlock(global);
+        *medium = STGMEDIUM {
+            tymed: TYMED_HGLOBAL,
+            hGlobal: global as *mut c_void,
+            pUnkForRelease: null_mut(),
+        };
+        S_OK
+    }
+
+    unsafe extern "system" fn get_data_here(
+        _this: *mut DataObject,
+        _format: *const FORMATETC,
+        _medium: *mut STGMEDIUM,
+    ) -> HRESULT {
+        E_NOTIMPL
+    }
+
+    unsafe extern "system" fn query_get_data(
+        this: *mut DataObject,
+        format: *const FORMATETC,
+    ) -> HRESULT {
+        match (*this).find_format(&*format) {
+            Ok(_) => S_OK,
+            Err(hr) => hr,
+        }
+    }
+
+    unsafe extern "system" fn get_canonical_format_etc(
+        _this: *mut DataObject,
+        _format: *const FORMATETC,
+        out: *mut FORMATETC,
+    ) -> HRESULT {
+        (*out).ptd = null_mut();
+        E_NOTIMPL
+    }
+
+    unsafe extern "system" fn set_data(
+        _this: *mut DataObject,
+        _format: *const FORMATETC,
+        _medium: *mut STGMEDIUM,
+        _release: BOOL,
+    ) -> HRESULT {
+        E_NOTIMPL
+    }
+
+    unsafe extern "system" fn enum_format_etc(
+        this: *mut DataObject,
+        direction: DWORD,
+        out: *mut *mut c_void,
+    ) -> HRESULT {
+        if direction != DATADIR_GET {
+            *out = null_mut();
+            return E_NOTIMPL;
+        }
+        let formats = &(*this).formats;
+        SHCreateStdEnumFmtEtc(formats.len() as UINT, formats.as_ptr(), out)
+    }
+
+    unsafe extern "system" fn d_advise(
+        _this: *mut DataObject,
+        _format: *const FORMATETC,
+        _advf: DWORD,
+        _sink: *mut c_void,
+        _connection: *mut DWORD,
+    ) -> HRESULT {
+        OLE_E_ADVISENOTSUPPORTED
+    }
+
+    unsafe extern "system" fn d_unadvise(_this: *mut DataObject, _connection: DWORD) -> HRESULT {
+        OLE_E_ADVISENOTSUPPORTED
+    }
+
+    unsafe extern "system" fn enum_d_advise(
+        _this: *mut DataObject,
+        _out: *mut *mut c_void,
+    ) -> HRESULT {
+        OLE_E_ADVISENOTSUPPORTED
+    }
+}
+
+#[repr(C)]
+struct DropSourceVtbl {
+    QueryInterface: unsafe extern "system" fn(*mut DropSource, REFIID, *mut *mut c_void) -> HRESULT,
+    AddRef: unsafe extern "system" fn(*mut DropSource) -> ULONG,
+    Release: unsafe extern "system" fn(*mut DropSource) -> ULONG,
+    QueryContinueDrag: unsafe extern "system" fn(*mut DropSource, BOOL, DWORD) -> HRESULT,
+    GiveFeedback: unsafe extern "system" fn(*mut DropSource, DWORD) -> HRESULT,
+}
+
+static DROP_SOURCE_VTBL: DropSourceVtbl = DropSourceVtbl {
+    QueryInterface: query_interface::<DropSource>,
+    AddRef: add_ref::<DropSource>,
+    Release: release::<DropSource>,
+    QueryContinueDrag: DropSource::query_continue_drag,
+    GiveFeedback: DropSource::give_feedback,
+};
+
+/// An `IDropSource` that drops when the mouse buttons are released, and
+/// uses the default cursors.
+#[repr(C)]
+struct DropSource {
+    vtbl: &'static DropSourceVtbl,
+    refs: Cell<ULONG>,
+}
+
+impl ComObject for DropSource {
+    fn iid() -> IID {
+        IDropSource::uuidof()
+    }
+
+    fn refs(&self) -> &Cell<ULONG> {
+        &self.refs
+    }
+}
+
+impl DropSource {
+    fn new() -> *mut DropSource {
+        Box::into_raw(Box::new(DropSource {
+            vtbl: &DROP_SOURCE_VTBL,
+            refs: Cell::new(1),
+        }))
+    }
+
+    unsafe extern "system" fn query_continue_drag(
+        _this: *mut DropSource,
+        escape_pressed: BOOL,
+        key_state: DWORD,
+    ) -> HRESULT {
+        if escape_pressed != 0 {
+            DRAGDROP_S_CANCEL
+        } else if key_state & (MK_LBUTTON | MK_RBUTTON | MK_MBUTTON) as DWORD == 0 {
+            DRAGDROP_S_DROP
+        } else {
+            S_OK
+        }
+    }
+
+    unsafe extern "system" fn give_feedback(_this: *mut DropSource, _effect: DWORD) -> HRESULT {
+        DRAGDROP_S_USEDEFAULTCURSORS
+    }
+}
@@ -19,6 +19,7 @@ use std::mem;
 use std::ptr::null;
 
 use winapi::shared::basetsd::*;
+use winapi::shared::minwindef::UINT;
 use winapi::shared::windef::*;
 use winapi::um::winuser::*;
 
@@ -101,24 +102,12 @@ impl Menu {
         enabled: bool,
         selected: bool,
     ) {
-        let mut anno_text = text.to_string();
-        if let Some(key) = key {
-            anno_text.push('\t');
-            format_hotkey(&key, &mut anno_text);
-        }
         unsafe {
-            let mut flags = MF_STRING;
-            if !enabled {
-                flags |= MF_GRAYED;
-            }
-            if selected {
-                flags |= MF_CHECKED;
-            }
             AppendMenuW(
                 self.hmenu,
-                flags,
+                item_flags(enabled, selected),
                 id as UINT_PTR,
-                anno_text.to_wide().as_ptr(),
+                item_text(text, key).to_wide().as_ptr(),
             );
         }
 
@@ -145,6 +134,49 @@ impl Menu {
     }
 }
 
+/// Change the item with the given `id` in `hmenu` or one of its submenus.
+///
+/// Returns `false` if there is no such item.
+pub(crate) fn update_item(
+    hmenu: HMENU,
+    id: u32,
+    text: &str,
+    key: Option<&HotKey>,
+    enabled: bool,
+    selected: bool,
+) -> bool {
+    unsafe {
+        ModifyMenuW(
+            hmenu,
+            id,
+            MF_BYCOMMAND | item_flags(enabled, selected),
+            id as UINT_PTR,
+            item_text(text, key).to_wide().as_ptr(),
+        ) != 0
+    }
+}
+
+fn item_flags(enabled: bool, selected: bool) -> UINT {
+    let mut flags = MF_STRING;
+    if !enabled {
+        flags |= MF_GRAYED;
+    }
+    if selected {
+        flags |= MF_CHECKED;
+    }
+    flags
+}
+
+/// The text of an item, with its hotkey shown on the right.
+fn item_text(text: &str, key: Option<&HotKey>) -> String {
+    let mut anno_text = text.to_string();
+    if let Some(key) = key {
+        anno_text.push('\t');
+        format_hotkey(&key, &mut anno_text);
+    }
+    anno_text
+}
+
 /// Convert a hotkey to an accelerator.
 ///
 /// Note that this conversion is dependent on the keyboard map.
@@ -19,11 +19,13 @@ pub mod application;
 pub mod clipboard;
 pub mod dcomp;
 pub mod dialog;
+mod drag;
 pub mod error;
 mod keyboard;
 pub mod menu;
 pub mod paint;
 pub mod screen;
+mod taskbar;
 mod timers;
 pub mod util;
 pub mod window;
@@ -0,0 +1,209 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Badges on taskbar buttons, Windows implementation.
+//!
+//! Windows has no badges as such, so the count is drawn into an [overlay icon].
+//!
+//! [overlay icon]: https://docs.microsoft.com/en-us/windows/win32/api/shobjidl_core/nf-shobjidl_core-itaskbarlist3-setoverlayicon
+
+#![allow(non_upper_case_globals)]
+
+use std::ptr::{null, null_mut};
+
+use lazy_static::lazy_static;
+use winapi::shared::minwindef::{DWORD, LPVOID, TRUE, UINT};
+use winapi::shared::windef::{HICON, HWND, RECT};
+use winapi::shared::winerror::HRESULT_FROM_WIN32;
+use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+use winapi::um::combaseapi::CoCreateInstance;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::shobjidl_core::ITaskbarList3;
+use winapi::um::wingdi::{
+    CreateBitmap, CreateCompatibleBitmap, CreateCompatibleDC, CreateFontW, CreateSolidBrush,
+    DeleteDC, DeleteObject, Ellipse, GetStockObject, PatBlt, SelectObject, SetBkMode, SetTextColor,
+    BLACKNESS, BLACK_BRUSH, CLEARTYPE_QUALITY, DEFAULT_CHARSET, FW_BOLD, NULL_PEN, RGB,
+    TRANSPARENT, WHITENESS,
+};
+use winapi::um::winuser::{
+    CreateIconIndirect, DestroyIcon, DrawTextW, GetDC, GetSystemMetrics, GetWindowLongW,
+    RegisterWindowMessageW, ReleaseDC, DT_CENTER, DT_NOPREFIX, DT_SINGLELINE, DT_VCENTER,
+    GWL_EXSTYLE, GWL_STYLE, ICONINFO, SM_CXSMICON, WS_CHILD, WS_EX_TOOLWINDOW,
+};
+use winapi::{Interface, DEFINE_GUID};
+use wio::com::ComPtr;
+
+use super::error::Error;
+use super::util::{as_result, ToWide};
+
+// TODO: remove this when it gets added to winapi
+DEFINE_GUID! {CLSID_TaskbarList,
+0x56FD_F344, 0xFD6D, 0x11D0, 0x95, 0x8A, 0x00, 0x60, 0x97, 0xC9, 0xA0, 0x90}
+
+lazy_static! {
+    /// The message that is sent to a window when its taskbar button has been
+    /// created. Overlays set before then are lost.
+    pub(crate) static ref TASKBAR_BUTTON_CREATED: UINT =
+        unsafe { RegisterWindowMessageW("TaskbarButtonCreated".to_wide().as_ptr()) };
+}
+
+/// Show `count` over the taskbar button of `hwnd`, or remove the overlay.
+pub(crate) fn set_badge(hwnd: HWND, count: Option<u64>) {
+    // tooltips and child windows have no button.
+    let (style, ex_style) = unsafe {
+        (
+            GetWindowLongW(hwnd, GWL_STYLE) as DWORD,
+            GetWindowLongW(hwnd, GWL_EXSTYLE) as DWORD,
+        )
+    };
+    if style & WS_CHILD != 0 || ex_style & WS_EX_TOOLWINDOW != 0 {
+        return;
+    }
+    if let Err(e) = unsafe { set_overlay(hwnd, count) } {
+        tracing::warn!("failed to set the taskbar overlay icon: {}", e);
+    }
+}
+
+unsafe fn set_overlay(hwnd: HWND, count: Option<u64>) -> Result<(), Error> {
+    let mut taskbar: *mut ITaskbarList3 = null_mut();
+    as_result(CoCreateInstance(
+        &CLSID_TaskbarList,
+        null_mut(),
+        CLSCTX_INPROC_SERVER,
+        &ITaskbarList3::uuidof(),
+        &mut taskbar as *mut *mut ITaskbarList3 as *mut LPVOID,
+    ))?;
+    let taskbar = ComPtr::from_raw(taskbar);
+    as_result(taskbar.HrInit())?;
+
+    let label = count.map(badge_label);
+    let icon = match &label {
+        Some(label) => draw_badge(label)?,
+        None => null_mut(),
+    };
+    // the label is also what screen readers announce.
+    let description = label.unwrap_or_default().to_wide();
+    let result = as_result(taskbar.SetOverlayIcon(hwnd, icon, description.as_ptr()));
+    // the taskbar keeps its own copy of the icon.
+    if !icon.is_null() {
+        DestroyIcon(icon);
+    }
+    result
+}
+
+/// The text of the badge, which only has room for two digits.
+fn badge_label(count: u64) -> String {
+    if count > 99 {
+        "99+".to_string()
+    } else {
+        count.to_string()
+    }
+}
+
+/// Draw `label` in white on a red circle, at the size of small icons.
+unsafe fn draw_badge(label: &str) -> Result<HICON, Error> {
+    let size = GetSystemMetrics(SM_CXSMICON);
+    let screen = GetDC(null_mut());
+    let dc = CreateCompatibleDC(screen);
+    let color = CreateCompatibleBitmap(screen, size, size);
+    let mask = CreateBitmap(size, size, 1, 1, null());
+    ReleaseDC(null_mut(), screen);
+
+    // In the mask, black is the part of the icon that is drawn.
+    let old_bitmap = SelectObject(dc, mask as _);
+    let old_pen = SelectObject(dc, GetStockObject(NULL_PEN as _));
+    let old_brush = SelectObject(dc, GetStockObject(BLACK_BRUSH as _));
+    PatBlt(dc, 0, 0, size, size, WHITENESS);
+    // NULL_PEN leaves out the bottom and right edges, hence the extra pixel.
+    Ellipse(dc, 0, 0, size + 1, size + 1);
+
+    SelectObject(dc, color as _);
+    let red = CreateSolidBrush(RGB(0xD1, 0x34, 0x38));
+    SelectObject(dc, red as _);
+    PatBlt(dc, 0, 0, size, size, BLACKNESS);
+    Ellipse(dc, 0, 0, size + 1, size + 1);
+    let font = CreateFontW(
+        -(size * 5 / 8),
+        0,
+        0,
+        0,
+        FW_BOLD,
+        0,
+        0,
+        0,
+        DEFAULT_CHARSET,
+        0,
+        0,
+        CLEARTYPE_QUALITY,
+        0,
+        "Segoe UI".to_wide().as_ptr(),
+    );
+    let old_font = SelectObject(dc, font as _);
+    SetTextColor(dc, RGB(0xFF, 0xFF, 0xFF));
+    SetBkMode(dc, TRANSPARENT as _);
+    let text = label.to_wide();
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: size,
+        bottom: size,
+    };
+    // -1 because the string is nul-terminated.
+    DrawTextW(
+        dc,
+        text.as_ptr(),
+        -1,
+        &mut rect,
+        DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX,
+    );
+
+    SelectObject(dc, old_font);
+    SelectObject(dc, old_brush);
+    SelectObject(dc, old_pen);
+    SelectObject(dc, old_bitmap);
+    DeleteObject(font as _);
+    DeleteObject(red as _);
+    DeleteDC(dc);
+
+    let mut info = ICONINFO {
+        fIcon: TRUE,
+        xHotspot: 0,
+        yHotspot: 0,
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    let icon = CreateIconIndirect(&mut info);
+    let error = GetLastError();
+    DeleteObject(mask as _);
+    DeleteObject(color as _);
+    if icon.is_null() {
+        Err(Error::Hr(HRESULT_FROM_WIN32(error)))
+    } else {
+        Ok(icon)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn badge_labels() {
+        assert_eq!(badge_label(0), "0");
+        assert_eq!(badge_label(7), "7");
+        assert_eq!(badge_label(99), "99");
+        assert_eq!(badge_label(100), "99+");
+        assert_eq!(badge_label(u64::MAX), "99+");
+    }
+}
@@ -21,7 +21,7 @@ use std::mem;
 use std::panic::Location;
 use std::ptr::{null, null_mut};
 use std::rc::{Rc, Weak};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Once};
 use std::time::{Duration, Instant};
 
 use scopeguard::defer;
@@ -31,6 +31,7 @@ use winapi::shared::dxgi::*;
 use winapi::shared::dxgi1_2::*;
 use winapi::shared::dxgiformat::*;
 use winapi::shared::dxgitype::*;
+use winapi::shared::guiddef::IsEqualGUID;
 use winapi::shared::minwindef::*;
 use winapi::shared::windef::*;
 use winapi::shared::winerror::*;
@@ -53,30 +54,35 @@ use piet_common::d2d::{D2DFactory, DeviceContext};
 use piet_common::dwrite::DwriteFactory;
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::piet::{Color, ImageBuf, Piet, PietText, RenderContext};
 
 use super::accels::register_accel;
 use super::application::Application;
 use super::dcomp::D3D11Device;
 use super::dialog::get_file_dialog_path;
+use super::drag;
 use super::error::Error;
 use super::keyboard::KeyboardState;
-use super::menu::Menu;
+use super::menu::{self, Menu};
 use super::paint;
+use super::taskbar::{self, TASKBAR_BUTTON_CREATED};
 use super::timers::TimerSlots;
 use super::util::{self, as_result, FromWide, ToWide, OPTIONAL_FUNCTIONS};
 
+use crate::application::PowerEvent;
 use crate::common_util::IdleCallback;
 use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
+use crate::drag::FilePromise;
 use crate::error::Error as ShellError;
+use crate::hotkey::HotKey;
 use crate::keyboard::{KbKey, KeyState};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
 use crate::text::{simulate_input, Event};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    FileDialogToken, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 /// The platform target DPI.
@@ -95,10 +101,14 @@ pub(crate) struct WindowBuilder {
     show_titlebar: bool,
     size: Option<Size>,
     transparent: bool,
+    background_color: Option<Color>,
     min_size: Option<Size>,
     position: Option<Point>,
     level: Option<WindowLevel>,
     state: window::WindowState,
+    always_on_top: bool,
+    fullscreen: bool,
+    icon: Option<ImageBuf>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -155,11 +165,15 @@ enum DeferredOp {
     SaveAs(FileDialogOptions, FileDialogToken),
     Open(FileDialogOptions, FileDialogToken),
     ContextMenu(Menu, Point),
+    DragFile(FilePromise),
     ShowTitlebar(bool),
     SetPosition(Point),
     SetSize(Size),
     SetResizable(bool),
     SetWindowState(window::WindowState),
+    SetAlwaysOnTop(bool),
+    SetFullscreen(bool),
+    SetIcon(ImageBuf),
     ReleaseMouseCapture,
 }
 
@@ -189,6 +203,130 @@ unsafe impl HasRawWindowHandle for WindowHandle {
     }
 }
 
+/// A window created by someone else, that has been made a child of one of our
+/// windows.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) struct ForeignWindow {
+    hwnd: HWND,
+    parent: WindowHandle,
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl ForeignWindow {
+    pub fn set_frame(&self, frame: Rect) {
+        let scale = self.parent.get_scale().unwrap_or_default();
+        let frame = frame.to_px(scale).round();
+        unsafe {
+            if SetWindowPos(
+                self.hwnd,
+                null_mut(),
+                frame.x0 as i32,
+                frame.y0 as i32,
+                frame.width() as i32,
+                frame.height() as i32,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            ) == 0
+            {
+                warn!(
+                    "failed to move foreign window: {}",
+                    Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+                );
+            }
+        }
+    }
+
+    pub fn focus(&self) {
+        unsafe {
+            SetFocus(self.hwnd);
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl Drop for ForeignWindow {
+    fn drop(&mut self) {
+        unsafe {
+            ShowWindow(self.hwnd, SW_HIDE);
+            SetParent(self.hwnd, null_mut());
+        }
+    }
+}
+
+/// The window class of render surfaces.
+#[cfg(feature = "raw-win-handle")]
+const RENDER_SURFACE_CLASS_NAME: &str = "druid-render-surface";
+
+/// A child window that we don't draw to, created by [`WindowHandle::create_render_surface`].
+#[cfg(feature = "raw-win-handle")]
+pub(crate) struct RenderSurface {
+    hwnd: HWND,
+    parent: WindowHandle,
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl RenderSurface {
+    pub fn set_frame(&self, frame: Rect) {
+        let scale = self.parent.get_scale().unwrap_or_default();
+        let frame = frame.to_px(scale).round();
+        unsafe {
+            if SetWindowPos(
+                self.hwnd,
+                null_mut(),
+                frame.x0 as i32,
+                frame.y0 as i32,
+                frame.width() as i32,
+                frame.height() as i32,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            ) == 0
+            {
+                warn!(
+                    "failed to move render surface: {}",
+                    Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl Drop for RenderSurface {
+    fn drop(&mut self) {
+        unsafe {
+            DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe impl HasRawWindowHandle for RenderSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        RawWindowHandle::Windows(WindowsHandle {
+            hwnd: self.hwnd as *mut core::ffi::c_void,
+            hinstance: unsafe {
+                winapi::um::libloaderapi::GetModuleHandleW(0 as winapi::um::winnt::LPCWSTR)
+                    as *mut core::ffi::c_void
+            },
+            ..WindowsHandle::empty()
+        })
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe extern "system" fn render_surface_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        // Let the mouse through to the parent, so that druid still gets the input.
+        WM_NCHITTEST => HTTRANSPARENT,
+        // The renderer draws the whole surface.
+        WM_ERASEBKGND => 1,
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
 /// A handle that can get used to schedule an idle handler. Note that
 /// this handle is thread safe. If the handle is used after the hwnd
 /// has been destroyed, probably not much will go wrong (the DS_RUN_IDLE
@@ -219,6 +357,12 @@ struct WindowState {
     deferred_queue: RefCell<Vec<DeferredOp>>,
     has_titlebar: Cell<bool>,
     is_transparent: Cell<bool>,
+    /// The color that WM_ERASEBKGND fills the window with.
+    background_color: Cell<Option<COLORREF>>,
+    /// The steps that the client area is resized in, in display points.
+    resize_increments: Cell<Option<Size>>,
+    /// The width to height ratio that the client area is kept at.
+    aspect_ratio: Cell<Option<f64>>,
     // For resizable borders, window can still be resized with code.
     is_resizable: Cell<bool>,
     handle_titlebar: Cell<bool>,
@@ -226,6 +370,21 @@ struct WindowState {
     // Is the window focusable ("activatable" in Win32 terminology)?
     // False for tooltips, to prevent stealing focus from owner window.
     is_focusable: bool,
+    // While the window is fullscreen, the style and window rect to go back to.
+    fullscreen_restore: Cell<Option<(u32, RECT)>>,
+    // The icon that we gave the window, which we have to destroy.
+    icon: Cell<HICON>,
+}
+
+impl Drop for WindowState {
+    fn drop(&mut self) {
+        let icon = self.icon.get();
+        if !icon.is_null() {
+            unsafe {
+                DestroyIcon(icon);
+            }
+        }
+    }
 }
 
 /// Generic handler trait for the winapi window procedure entry point.
@@ -247,6 +406,8 @@ struct MyWndProc {
     text: PietText,
     state: RefCell<Option<WndState>>,
     present_strategy: PresentStrategy,
+    // The registration for display power notifications, if there is one.
+    power_notify: Cell<HPOWERNOTIFY>,
 }
 
 /// The mutable state of the window.
@@ -267,6 +428,8 @@ struct WndState {
     last_click_time: Instant,
     last_click_pos: (i32, i32),
     click_count: u8,
+    // Whether the window is minimized, which we report as being suspended.
+    minimized: bool,
 }
 
 /// State for DXGI swapchains.
@@ -317,6 +480,25 @@ impl Default for PresentStrategy {
     }
 }
 
+/// The kind of device that the mouse message being handled came from.
+///
+/// Windows sends mouse messages for touch and pen input as well, and marks
+/// them with a signature in the message's extra info.
+fn get_pointer_type() -> PointerType {
+    const SIGNATURE_MASK: u32 = 0xFFFF_FF00;
+    const MI_WP_SIGNATURE: u32 = 0xFF51_5700;
+    const TOUCH_BIT: u32 = 0x80;
+
+    let info = unsafe { GetMessageExtraInfo() } as u32;
+    if info & SIGNATURE_MASK != MI_WP_SIGNATURE {
+        PointerType::Mouse
+    } else if info & TOUCH_BIT != 0 {
+        PointerType::Touch
+    } else {
+        PointerType::Pen
+    }
+}
+
 /// Extract the buttons that are being held down from wparam in mouse events.
 fn get_buttons(wparam: WPARAM) -> MouseButtons {
     let mut buttons = MouseButtons::new();
@@ -354,6 +536,181 @@ fn is_point_in_client_rect(hwnd: HWND, x: i32, y: i32) -> bool {
     }
 }
 
+/// GDI has no alpha, so that is ignored.
+fn to_colorref(color: &Color) -> COLORREF {
+    let (r, g, b, _) = color.as_rgba8();
+    RGB(r, g, b)
+}
+
+/// Adjust the window `rect` that the user is dragging `edge` of, so that the
+/// client area keeps to the resize increments and aspect ratio.
+fn constrain_sizing(
+    hwnd: HWND,
+    rect: &mut RECT,
+    edge: WPARAM,
+    increments: Option<Size>,
+    aspect_ratio: Option<f64>,
+) {
+    // the size of the frame, which isn't constrained.
+    let (frame_width, frame_height) = unsafe {
+        let mut window_rect: RECT = mem::zeroed();
+        let mut client_rect: RECT = mem::zeroed();
+        GetWindowRect(hwnd, &mut window_rect);
+        GetClientRect(hwnd, &mut client_rect);
+        (
+            (window_rect.right - window_rect.left) - (client_rect.right - client_rect.left),
+            (window_rect.bottom - window_rect.top) - (client_rect.bottom - client_rect.top),
+        )
+    };
+    let is_edge = |edges: &[_]| edges.iter().any(|&e| e as WPARAM == edge);
+    let mut width = (rect.right - rect.left - frame_width) as f64;
+    let mut height = (rect.bottom - rect.top - frame_height) as f64;
+
+    if let Some(ratio) = aspect_ratio {
+        if is_edge(&[WMSZ_TOP, WMSZ_BOTTOM]) {
+            width = height * ratio;
+        } else {
+            height = width / ratio;
+        }
+    }
+    if let Some(increments) = increments {
+        let snap = |length: f64, step: f64| {
+            if step > 0.0 {
+                (length / step).round().max(1.0) * step
+            } else {
+                length
+            }
+        };
+        width = snap(width, increments.width);
+        height = snap(height, increments.height);
+    }
+
+    let width = width.round() as i32 + frame_width;
+    let height = height.round() as i32 + frame_height;
+    if is_edge(&[WMSZ_LEFT, WMSZ_TOPLEFT, WMSZ_BOTTOMLEFT]) {
+        rect.left = rect.right - width;
+    } else {
+        rect.right = rect.left + width;
+    }
+    if is_edge(&[WMSZ_TOP, WMSZ_TOPLEFT, WMSZ_TOPRIGHT]) {
+        rect.top = rect.bottom - height;
+    } else {
+        rect.bottom = rect.top + height;
+    }
+}
+
+/// Read the display state from a `PBT_POWERSETTINGCHANGE` broadcast.
+///
+/// # Safety
+///
+/// `lparam` must point to a `POWERBROADCAST_SETTING`.
+unsafe fn display_power_event(lparam: LPARAM) -> Option<PowerEvent> {
+    let setting = &*(lparam as *const POWERBROADCAST_SETTING);
+    if !IsEqualGUID(&setting.PowerSetting, &GUID_CONSOLE_DISPLAY_STATE)
+        || (setting.DataLength as usize) < mem::size_of::<DWORD>()
+    {
+        return None;
+    }
+    // The display state is 0 when off, 1 when on and 2 when dimmed.
+    match *(setting.Data.as_ptr() as *const DWORD) {
+        0 => Some(PowerEvent::DisplayOff),
+        1 => Some(PowerEvent::DisplayOn),
+        _ => None,
+    }
+}
+
+/// Make the window cover its monitor without decorations, or go back to how it
+/// was before.
+///
+/// `restore` holds the style and window rect from before the window was made
+/// fullscreen.
+fn set_fullscreen(hwnd: HWND, restore: &Cell<Option<(u32, RECT)>>, fullscreen: bool) {
+    unsafe {
+        if fullscreen {
+            if restore.get().is_some() {
+                return;
+            }
+            let style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
+            let mut rect: RECT = mem::zeroed();
+            let mut info: MONITORINFO = mem::zeroed();
+            info.cbSize = mem::size_of::<MONITORINFO>() as DWORD;
+            let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+            if style == 0
+                || GetWindowRect(hwnd, &mut rect) == 0
+                || GetMonitorInfoW(monitor, &mut info) == 0
+            {
+                warn!(
+                    "failed to make the window fullscreen: {}",
+                    Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+                );
+                return;
+            }
+            restore.set(Some((style, rect)));
+            SetWindowLongPtrW(hwnd, GWL_STYLE, (style & !WS_OVERLAPPEDWINDOW) as _);
+            let monitor = info.rcMonitor;
+            SetWindowPos(
+                hwnd,
+                HWND_TOP,
+                monitor.left,
+                monitor.top,
+                monitor.right - monitor.left,
+                monitor.bottom - monitor.top,
+                SWP_NOOWNERZORDER | SWP_FRAMECHANGED,
+            );
+        } else if let Some((style, rect)) = restore.take() {
+            SetWindowLongPtrW(hwnd, GWL_STYLE, style as _);
+            SetWindowPos(
+                hwnd,
+                null_mut(),
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOOWNERZORDER | SWP_FRAMECHANGED,
+            );
+        }
+    }
+}
+
+/// Create an icon from `image`.
+///
+/// The color bitmap has an alpha channel, so the mask is left empty. Returns
+/// null if the icon could not be created.
+unsafe fn create_icon(image: &ImageBuf) -> HICON {
+    let width = image.width() as c_int;
+    let height = image.height() as c_int;
+    // 32-bit bitmaps are BGRA, row by row from the top.
+    let mut pixels = Vec::with_capacity(image.width() * image.height() * 4);
+    for row in image.pixel_colors() {
+        for color in row {
+            let (r, g, b, a) = color.as_rgba8();
+            pixels.extend_from_slice(&[b, g, r, a]);
+        }
+    }
+    let color = CreateBitmap(width, height, 1, 32, pixels.as_ptr() as *const c_void);
+    if color.is_null() {
+        return null_mut();
+    }
+    defer!(DeleteObject(color as _););
+
+    // The rows of monochrome bitmaps are padded to 16 bits.
+    let mask_bits = vec![0u8; (image.width() + 15) / 16 * 2 * image.height()];
+    let mask = CreateBitmap(width, height, 1, 1, mask_bits.as_ptr() as *const c_void);
+    if mask.is_null() {
+        return null_mut();
+    }
+    defer!(DeleteObject(mask as _););
+
+    let mut icon_info = ICONINFO {
+        fIcon: TRUE,
+        xHotspot: 0,
+        yHotspot: 0,
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    CreateIconIndirect(&mut icon_info)
+}
+
 fn set_style(hwnd: HWND, resizable: bool, titlebar: bool) {
     unsafe {
         let mut style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
@@ -597,6 +954,49 @@ impl MyWndProc {
                         ShowWindow(hwnd, show);
                     }
                 }
+                DeferredOp::SetAlwaysOnTop(always_on_top) => unsafe {
+                    let insert_after = if always_on_top {
+                        HWND_TOPMOST
+                    } else {
+                        HWND_NOTOPMOST
+                    };
+                    if SetWindowPos(
+                        hwnd,
+                        insert_after,
+                        0,
+                        0,
+                        0,
+                        0,
+                        SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+                    ) == 0
+                    {
+                        warn!(
+                            "failed to change the window's z-order: {}",
+                            Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+                        );
+                    }
+                },
+                DeferredOp::SetFullscreen(fullscreen) => {
+                    self.with_window_state(|s| {
+                        set_fullscreen(hwnd, &s.fullscreen_restore, fullscreen)
+                    });
+                }
+                DeferredOp::SetIcon(image) => unsafe {
+                    let icon = create_icon(&image);
+                    if icon.is_null() {
+                        warn!(
+                            "failed to create the window icon: {}",
+                            Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+                        );
+                    } else {
+                        SendMessageW(hwnd, WM_SETICON, ICON_BIG as WPARAM, icon as LPARAM);
+                        SendMessageW(hwnd, WM_SETICON, ICON_SMALL as WPARAM, icon as LPARAM);
+                        let old = self.with_window_state(|s| s.icon.replace(icon));
+                        if !old.is_null() {
+                            DestroyIcon(old);
+                        }
+                    }
+                },
                 DeferredOp::SaveAs(options, token) => {
                     let info = unsafe {
                         get_file_dialog_path(hwnd, FileDialogType::Save, options)
@@ -631,6 +1031,13 @@ impl MyWndProc {
                         }
                     }
                 }
+                DeferredOp::DragFile(promise) => unsafe {
+                    // the drag takes over the mouse until it is dropped.
+                    ReleaseCapture();
+                    if let Err(e) = drag::drag_file_promise(promise) {
+                        warn!("failed to drag file: {}", e);
+                    }
+                },
                 DeferredOp::ReleaseMouseCapture => unsafe {
                     if ReleaseCapture() == FALSE {
                         let result = HRESULT_FROM_WIN32(GetLastError());
@@ -730,6 +1137,16 @@ impl WndProc for MyWndProc {
                         error!("error building render target: {}", e);
                     }
                 }
+
+                // Displays turning on and off are only reported to windows that ask for it.
+                let power_notify = unsafe {
+                    RegisterPowerSettingNotification(
+                        hwnd as HANDLE,
+                        &GUID_CONSOLE_DISPLAY_STATE,
+                        DEVICE_NOTIFY_WINDOW_HANDLE,
+                    )
+                };
+                self.power_notify.set(power_notify);
                 Some(0)
             }
             WM_ACTIVATE => {
@@ -772,7 +1189,21 @@ impl WndProc for MyWndProc {
                 }
                 Some(0)
             }
-            WM_ERASEBKGND => Some(0),
+            WM_ERASEBKGND => {
+                // this is what shows until we have painted for the first time.
+                let color = self.with_window_state(|state| state.background_color.get());
+                match color {
+                    Some(color) if !self.is_transparent() => unsafe {
+                        let mut rect: RECT = mem::zeroed();
+                        GetClientRect(hwnd, &mut rect);
+                        let brush = CreateSolidBrush(color);
+                        FillRect(wparam as HDC, &rect, brush);
+                        DeleteObject(brush as _);
+                        Some(1)
+                    },
+                    _ => Some(0),
+                }
+            }
             WM_SETFOCUS => {
                 self.with_wnd_state(|s| s.handler.got_focus());
                 Some(0)
@@ -781,6 +1212,35 @@ impl WndProc for MyWndProc {
                 self.with_wnd_state(|s| s.handler.lost_focus());
                 Some(0)
             }
+            WM_SETTINGCHANGE | WM_SYSCOLORCHANGE => {
+                // Regional settings are reported as a change to the "intl" section.
+                if msg == WM_SETTINGCHANGE && lparam != 0 {
+                    let section = (lparam as LPWSTR).from_wide();
+                    if section.as_deref() == Some("intl") {
+                        self.app.locale_changed();
+                    }
+                }
+                self.with_wnd_state(|s| s.handler.system_settings_changed());
+                Some(0)
+            }
+            WM_POWERBROADCAST => {
+                let event = if wparam == PBT_APMSUSPEND as WPARAM {
+                    Some(PowerEvent::Suspending)
+                } else if wparam == PBT_APMRESUMEAUTOMATIC as WPARAM {
+                    Some(PowerEvent::Resumed)
+                } else if wparam == PBT_POWERSETTINGCHANGE as WPARAM {
+                    unsafe { display_power_event(lparam) }
+                } else if wparam == PBT_APMPOWERSTATUSCHANGE as WPARAM {
+                    self.app.power_status_changed();
+                    None
+                } else {
+                    None
+                };
+                if let Some(event) = event {
+                    self.app.power_event(event);
+                }
+                Some(TRUE as LRESULT)
+            }
             WM_PAINT => unsafe {
                 self.with_wnd_state(|s| {
                     // We call prepare_paint before GetUpdateRect, so that anything invalidated during
@@ -911,6 +1371,17 @@ impl WndProc for MyWndProc {
                 Some(hit)
             },
             WM_SIZE => unsafe {
+                let minimized = wparam == SIZE_MINIMIZED;
+                self.with_wnd_state(|s| {
+                    if s.minimized != minimized {
+                        s.minimized = minimized;
+                        if minimized {
+                            s.handler.suspended();
+                        } else {
+                            s.handler.resumed();
+                        }
+                    }
+                });
                 let width = LOWORD(lparam as u32) as u32;
                 let height = HIWORD(lparam as u32) as u32;
                 if width == 0 || height == 0 {
@@ -1006,9 +1477,7 @@ impl WndProc for MyWndProc {
                     let system_delta = HIWORD(wparam as u32) as i16 as f64;
                     let down_state = LOWORD(wparam as u32) as usize;
                     let mods = s.keyboard_state.get_modifiers();
-                    let is_shift = mods.shift();
                     let wheel_delta = match msg {
-                        WM_MOUSEWHEEL if is_shift => Vec2::new(-system_delta, 0.),
                         WM_MOUSEWHEEL => Vec2::new(0., -system_delta),
                         WM_MOUSEHWHEEL => Vec2::new(system_delta, 0.),
                         _ => unreachable!(),
@@ -1038,6 +1507,7 @@ impl WndProc for MyWndProc {
                         focus: false,
                         button: MouseButton::None,
                         wheel_delta,
+                        pointer_type: get_pointer_type(),
                     };
                     s.handler.wheel(&event);
                     true
@@ -1087,6 +1557,7 @@ impl WndProc for MyWndProc {
                         focus: false,
                         button: MouseButton::None,
                         wheel_delta: Vec2::ZERO,
+                        pointer_type: get_pointer_type(),
                     };
                     s.handler.mouse_move(&event);
                 });
@@ -1167,6 +1638,7 @@ impl WndProc for MyWndProc {
                             focus: false,
                             button,
                             wheel_delta: Vec2::ZERO,
+                            pointer_type: get_pointer_type(),
                         };
                         if count > 0 {
                             s.enter_mouse_capture(hwnd, button);
@@ -1192,6 +1664,12 @@ impl WndProc for MyWndProc {
                 Some(0)
             }
             WM_DESTROY => {
+                let power_notify = self.power_notify.replace(null_mut());
+                if !power_notify.is_null() {
+                    unsafe {
+                        UnregisterPowerSettingNotification(power_notify);
+                    }
+                }
                 self.with_wnd_state(|s| s.handler.destroy());
                 Some(0)
             }
@@ -1209,6 +1687,18 @@ impl WndProc for MyWndProc {
                 self.with_wnd_state(|s| s.captured_mouse_buttons.clear());
                 Some(0)
             }
+            WM_SIZING => {
+                let (increments, aspect_ratio) = self.with_window_state(|state| {
+                    (state.resize_increments.get(), state.aspect_ratio.get())
+                });
+                if increments.is_none() && aspect_ratio.is_none() {
+                    return None;
+                }
+                let increments = increments.map(|size| size.to_px(self.scale()));
+                let rect = unsafe { &mut *(lparam as *mut RECT) };
+                constrain_sizing(hwnd, rect, wparam, increments, aspect_ratio);
+                Some(1)
+            }
             WM_GETMINMAXINFO => {
                 let min_max_info = unsafe { &mut *(lparam as *mut MINMAXINFO) };
                 self.with_wnd_state(|s| {
@@ -1231,6 +1721,12 @@ impl WndProc for MyWndProc {
                     }
                 })
                 .map(|_| 0),
+            _ if msg == *TASKBAR_BUTTON_CREATED => {
+                if let Some(count) = self.app.badge() {
+                    taskbar::set_badge(hwnd, Some(count));
+                }
+                Some(0)
+            }
             _ => None,
         }
     }
@@ -1246,12 +1742,16 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             transparent: false,
+            background_color: None,
             present_strategy: Default::default(),
             size: None,
             min_size: None,
             position: None,
             level: None,
             state: window::WindowState::Restored,
+            always_on_top: false,
+            fullscreen: false,
+            icon: None,
         }
     }
 
@@ -1289,6 +1789,10 @@ impl WindowBuilder {
         }
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_title<S: Into<String>>(&mut self, title: S) {
         self.title = title.into();
     }
@@ -1314,6 +1818,18 @@ impl WindowBuilder {
         }
     }
 
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.always_on_top = always_on_top;
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+    }
+
+    pub fn set_icon(&mut self, icon: ImageBuf) {
+        self.icon = Some(icon);
+    }
+
     pub fn build(self) -> Result<WindowHandle, Error> {
         unsafe {
             let class_name = super::util::CLASS_NAME.to_wide();
@@ -1327,6 +1843,7 @@ impl WindowBuilder {
                 text: text.clone(),
                 state: RefCell::new(None),
                 present_strategy: self.present_strategy,
+                power_notify: Cell::new(null_mut()),
             };
 
             let (pos_x, pos_y) = match self.position {
@@ -1388,9 +1905,14 @@ impl WindowBuilder {
                 has_titlebar: Cell::new(self.show_titlebar),
                 is_resizable: Cell::new(self.resizable),
                 is_transparent: Cell::new(self.transparent),
+                background_color: Cell::new(self.background_color.as_ref().map(to_colorref)),
+                resize_increments: Cell::new(None),
+                aspect_ratio: Cell::new(None),
                 handle_titlebar: Cell::new(false),
                 active_text_input: Cell::new(None),
                 is_focusable: focusable,
+                fullscreen_restore: Cell::new(None),
+                icon: Cell::new(null_mut()),
             };
             let win = Rc::new(window);
             let handle = WindowHandle {
@@ -1410,6 +1932,7 @@ impl WindowBuilder {
                 last_click_time: Instant::now(),
                 last_click_pos: (0, 0),
                 click_count: 0,
+                minimized: false,
             };
             win.wndproc.connect(&handle, state);
 
@@ -1423,6 +1946,9 @@ impl WindowBuilder {
             if self.present_strategy == PresentStrategy::Flip {
                 dwExStyle |= WS_EX_NOREDIRECTIONBITMAP;
             }
+            if self.always_on_top {
+                dwExStyle |= WS_EX_TOPMOST;
+            }
 
             match self.state {
                 window::WindowState::Maximized => dwStyle |= WS_MAXIMIZE,
@@ -1474,6 +2000,12 @@ impl WindowBuilder {
             if let Some(accels) = accels {
                 register_accel(hwnd, &accels);
             }
+            if self.fullscreen {
+                handle.set_fullscreen(true);
+            }
+            if let Some(icon) = self.icon {
+                handle.set_icon(&icon);
+            }
             Ok(handle)
         }
     }
@@ -1802,6 +2334,35 @@ impl WindowHandle {
         }
     }
 
+    pub fn announce(&self, _text: &str, _politeness: Politeness) {
+        warn!("WindowHandle::announce is currently unimplemented for Windows.");
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        if let Some(w) = self.state.upgrade() {
+            w.background_color.set(Some(to_colorref(&color)));
+        }
+    }
+
+    pub fn set_resize_increments(&self, width: f64, height: f64) {
+        if let Some(w) = self.state.upgrade() {
+            let increments = Size::new(width, height);
+            w.resize_increments
+                .set(Some(increments).filter(|size| size.width > 0.0 || size.height > 0.0));
+        }
+    }
+
+    pub fn set_aspect_ratio(&self, numerator: u32, denominator: u32) {
+        if let Some(w) = self.state.upgrade() {
+            let ratio = if numerator == 0 || denominator == 0 {
+                None
+            } else {
+                Some(numerator as f64 / denominator as f64)
+            };
+            w.aspect_ratio.set(ratio);
+        }
+    }
+
     /// Set the title for this menu.
     pub fn set_title(&self, title: &str) {
         if let Some(w) = self.state.upgrade() {
@@ -1828,6 +2389,18 @@ impl WindowHandle {
         warn!("Window level unimplemented for Windows!");
     }
 
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.defer(DeferredOp::SetAlwaysOnTop(always_on_top));
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.defer(DeferredOp::SetFullscreen(fullscreen));
+    }
+
+    pub fn set_icon(&self, icon: &ImageBuf) {
+        self.defer(DeferredOp::SetIcon(icon.clone()));
+    }
+
     // Gets the position of the window in virtual screen coordinates
     pub fn get_position(&self) -> Point {
         if let Some(w) = self.state.upgrade() {
@@ -1973,10 +2546,33 @@ impl WindowHandle {
         }
     }
 
+    pub fn update_menu_item(
+        &self,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
+    ) {
+        if let Some(w) = self.state.upgrade() {
+            let hwnd = w.hwnd.get();
+            unsafe {
+                let hmenu = GetMenu(hwnd);
+                if !hmenu.is_null() && menu::update_item(hmenu, id, text, key, enabled, selected) {
+                    DrawMenuBar(hwnd);
+                }
+            }
+        }
+    }
+
     pub fn show_context_menu(&self, menu: Menu, pos: Point) {
         self.defer(DeferredOp::ContextMenu(menu, pos));
     }
 
+    pub fn start_file_drag(&self, promise: FilePromise) {
+        self.defer(DeferredOp::DragFile(promise));
+    }
+
     pub fn text(&self) -> PietText {
         self.text.clone()
     }
@@ -2142,6 +2738,85 @@ impl WindowHandle {
             .get())
     }
 
+    /// Make `child` a child window of this window.
+    #[cfg(feature = "raw-win-handle")]
+    pub unsafe fn embed_foreign_window(
+        &self,
+        child: RawWindowHandle,
+    ) -> Result<ForeignWindow, ShellError> {
+        let parent = self.get_hwnd().ok_or(ShellError::WindowDropped)?;
+        let hwnd = match child {
+            RawWindowHandle::Windows(handle) if !handle.hwnd.is_null() => handle.hwnd as HWND,
+            _ => return Err(Error::NullHwnd.into()),
+        };
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as DWORD;
+        let style = (style & !(WS_POPUP | WS_CAPTION | WS_THICKFRAME)) | WS_CHILD;
+        SetWindowLongW(hwnd, GWL_STYLE, style as i32);
+        if SetParent(hwnd, parent).is_null() {
+            return Err(Error::Hr(HRESULT_FROM_WIN32(GetLastError())).into());
+        }
+        ShowWindow(hwnd, SW_SHOW);
+        Ok(ForeignWindow {
+            hwnd,
+            parent: self.clone(),
+        })
+    }
+
+    /// Create a child window that we don't draw to.
+    #[cfg(feature = "raw-win-handle")]
+    pub fn create_render_surface(&self, frame: Rect) -> Result<RenderSurface, ShellError> {
+        static REGISTER_CLASS: Once = Once::new();
+
+        let parent = self.get_hwnd().ok_or(ShellError::WindowDropped)?;
+        let frame = frame.to_px(self.get_scale()?).round();
+        let class_name = RENDER_SURFACE_CLASS_NAME.to_wide();
+        unsafe {
+            REGISTER_CLASS.call_once(|| {
+                let wnd = WNDCLASSW {
+                    style: 0,
+                    lpfnWndProc: Some(render_surface_proc),
+                    cbClsExtra: 0,
+                    cbWndExtra: 0,
+                    hInstance: 0 as HINSTANCE,
+                    hIcon: null_mut(),
+                    hCursor: null_mut(),
+                    hbrBackground: null_mut(),
+                    lpszMenuName: null(),
+                    lpszClassName: class_name.as_ptr(),
+                };
+                if RegisterClassW(&wnd) == 0 {
+                    error!("failed to register the render surface class");
+                }
+            });
+
+            // Keep our own painting out of the surface.
+            let style = GetWindowLongW(parent, GWL_STYLE) as DWORD;
+            SetWindowLongW(parent, GWL_STYLE, (style | WS_CLIPCHILDREN) as i32);
+
+            let hwnd = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                null(),
+                WS_CHILD | WS_VISIBLE | WS_CLIPSIBLINGS,
+                frame.x0 as i32,
+                frame.y0 as i32,
+                frame.width() as i32,
+                frame.height() as i32,
+                parent,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+            );
+            if hwnd.is_null() {
+                return Err(Error::Hr(HRESULT_FROM_WIN32(GetLastError())).into());
+            }
+            Ok(RenderSurface {
+                hwnd,
+                parent: self.clone(),
+            })
+        }
+    }
+
     /// Allocate a timer slot.
     ///
     /// Returns an id and an elapsed time in ms
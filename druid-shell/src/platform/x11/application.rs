@@ -15,26 +15,36 @@
 //! X11 implementation of features at the application scope.
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::os::unix::io::RawFd;
+use std::path::Path;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Error};
 use x11rb::connection::Connection;
 use x11rb::protocol::present::ConnectionExt as _;
+use x11rb::protocol::randr::{ConnectionExt as _, NotifyMask};
 use x11rb::protocol::xfixes::ConnectionExt as _;
 use x11rb::protocol::xproto::{self, ConnectionExt, CreateWindowAux, EventMask, WindowClass};
 use x11rb::protocol::Event;
 use x11rb::resource_manager::Database as ResourceDb;
 use x11rb::xcb_ffi::XCBConnection;
 
-use crate::application::AppHandler;
+use crate::application::{AppHandler, MouseSettings, PowerStatus, RegionalSettings, TextSettings};
+use crate::keyboard::{KeyEvent, KeyState};
+use crate::kurbo::Point;
+use crate::scale::Scale;
 
-use super::clipboard::Clipboard;
+use super::super::shared;
+use super::clipboard::{Clipboard, ClipboardState};
+use super::screen::{self, MonitorInfo};
 use super::util;
 use super::window::Window;
+use super::xim::{Xim, XimEvent};
+use super::xkb::Keyboard;
+use super::xsettings::{self, Setting};
 
 #[derive(Clone)]
 pub(crate) struct Application {
@@ -83,6 +93,17 @@ pub(crate) struct Application {
     idle_write: RawFd,
     /// The major opcode of the Present extension, if it is supported.
     present_opcode: Option<u8>,
+    /// Events that were read from the connection while waiting for something else, such as
+    /// clipboard data, and that still need to be handled by the event loop.
+    pending_events: Rc<RefCell<VecDeque<Event>>>,
+    /// The contents of the selections that we own, shared by all `Clipboard` handles.
+    clipboard_state: Rc<ClipboardState>,
+    /// The keyboard layout and state, if the XKB extension could be set up.
+    keyboard: Rc<RefCell<Option<Keyboard>>>,
+    /// The monitors of the screen, updated when RandR tells us that they changed.
+    monitors: Rc<RefCell<Vec<MonitorInfo>>>,
+    /// The connection to the input method, if there is one.
+    xim: Rc<RefCell<Option<Xim>>>,
 }
 
 /// The mutable `Application` state.
@@ -99,11 +120,41 @@ pub(crate) struct Cursors {
     pub text: Option<xproto::Cursor>,
     pub pointer: Option<xproto::Cursor>,
     pub crosshair: Option<xproto::Cursor>,
+    pub open_hand: Option<xproto::Cursor>,
     pub not_allowed: Option<xproto::Cursor>,
     pub row_resize: Option<xproto::Cursor>,
     pub col_resize: Option<xproto::Cursor>,
 }
 
+impl Cursors {
+    /// Loads the standard cursors with `load`, which returns `NONE` or `None` for the cursors
+    /// that the theme doesn't have.
+    fn load(mut load: impl FnMut(&str) -> Option<xproto::Cursor>) -> Cursors {
+        // Cursor themes don't agree on the names of cursors: newer ones follow the CSS names, and
+        // older ones (as well as the core cursor font) use the names from X11's cursorfont.h.
+        let mut load_cursor = |names: &[&str]| {
+            let cursor = names
+                .iter()
+                .find_map(|name| load(name).filter(|cursor| *cursor != x11rb::NONE));
+            if cursor.is_none() {
+                tracing::warn!("No cursor found for {:?}", names);
+            }
+            cursor
+        };
+
+        Cursors {
+            default: load_cursor(&["default", "left_ptr"]),
+            text: load_cursor(&["text", "xterm"]),
+            pointer: load_cursor(&["pointer", "hand2"]),
+            crosshair: load_cursor(&["crosshair", "cross"]),
+            open_hand: load_cursor(&["grab", "openhand", "hand1"]),
+            not_allowed: load_cursor(&["not-allowed", "crossed_circle"]),
+            row_resize: load_cursor(&["row-resize", "sb_v_double_arrow"]),
+            col_resize: load_cursor(&["col-resize", "sb_h_double_arrow"]),
+        }
+    }
+}
+
 impl Application {
     pub fn new() -> Result<Application, Error> {
         // If we want to support OpenGL, we will need to open a connection with Xlib support (see
@@ -138,23 +189,39 @@ impl Application {
             }
         };
 
+        let keyboard = match Keyboard::new(&connection) {
+            Ok(keyboard) => Some(keyboard),
+            Err(e) => {
+                tracing::warn!("failed to set up XKB, falling back to a US layout: {:#}", e);
+                None
+            }
+        };
+
+        let xim = Xim::new(&connection, screen_num).unwrap_or_else(|e| {
+            tracing::warn!("failed to connect to the input method: {:#}", e);
+            None
+        });
+
+        let monitors = screen::query_monitors(&connection, screen_num, &rdb)?;
+        if let Some(root) = connection.setup().roots.get(screen_num) {
+            let mask = NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE;
+            log_x11!(connection.randr_select_input(root.root, mask));
+        }
+
         let handle = x11rb::cursor::Handle::new(connection.as_ref(), screen_num, &rdb)?.reply()?;
-        let load_cursor = |cursor| {
+        let cursors = Cursors::load(|name| {
             handle
-                .load_cursor(connection.as_ref(), cursor)
-                .map_err(|e| tracing::warn!("Unable to load cursor {}, error: {}", cursor, e))
+                .load_cursor(connection.as_ref(), name)
+                .map_err(|e| tracing::warn!("Unable to load cursor {}, error: {}", name, e))
                 .ok()
-        };
+        });
 
-        let cursors = Cursors {
-            default: load_cursor("default"),
-            text: load_cursor("text"),
-            pointer: load_cursor("pointer"),
-            crosshair: load_cursor("crosshair"),
-            not_allowed: load_cursor("not-allowed"),
-            row_resize: load_cursor("row-resize"),
-            col_resize: load_cursor("col-resize"),
-        };
+        let pending_events = Rc::new(RefCell::new(VecDeque::new()));
+        let clipboard_state = Rc::new(ClipboardState::new(
+            Rc::clone(&connection),
+            window_id,
+            Rc::clone(&pending_events),
+        ));
 
         Ok(Application {
             connection,
@@ -166,6 +233,11 @@ impl Application {
             cursors,
             idle_write,
             present_opcode,
+            pending_events,
+            clipboard_state,
+            keyboard: Rc::new(RefCell::new(keyboard)),
+            monitors: Rc::new(RefCell::new(monitors)),
+            xim: Rc::new(RefCell::new(xim)),
             marker: std::marker::PhantomData,
         })
     }
@@ -248,7 +320,8 @@ impl Application {
             // Visual ID
             x11rb::COPY_FROM_PARENT,
             // Window properties mask
-            &CreateWindowAux::new().event_mask(EventMask::STRUCTURE_NOTIFY),
+            &CreateWindowAux::new()
+                .event_mask(EventMask::STRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE),
         )?
         .check()
         .context("create input-only window")?;
@@ -258,11 +331,19 @@ impl Application {
 
     pub(crate) fn add_window(&self, id: u32, window: Rc<Window>) -> Result<(), Error> {
         borrow_mut!(self.state)?.windows.insert(id, window);
+        if let Some(xim) = borrow_mut!(self.xim)?.as_mut() {
+            if let Err(e) = xim.add_window(id) {
+                tracing::warn!("failed to create an input context: {:#}", e);
+            }
+        }
         Ok(())
     }
 
     /// Remove the specified window from the `Application` and return the number of windows left.
     fn remove_window(&self, id: u32) -> Result<usize, Error> {
+        if let Some(xim) = borrow_mut!(self.xim)?.as_mut() {
+            log_x11!(xim.remove_window(id));
+        }
         let mut state = borrow_mut!(self.state)?;
         state.windows.remove(&id);
         Ok(state.windows.len())
@@ -276,6 +357,15 @@ impl Application {
             .ok_or_else(|| anyhow!("No window with id {}", id))
     }
 
+    /// Returns the window whose menu is shown in the window `id`, if there is one.
+    fn menu_owner(&self, id: u32) -> Result<Option<Rc<Window>>, Error> {
+        Ok(borrow!(self.state)?
+            .windows
+            .values()
+            .find(|w| w.has_menu_window(id))
+            .cloned())
+    }
+
     #[inline]
     pub(crate) fn connection(&self) -> &Rc<XCBConnection> {
         &self.connection
@@ -286,6 +376,93 @@ impl Application {
         self.screen_num
     }
 
+    /// The settings that the desktop shares through XSETTINGS.
+    pub(crate) fn xsettings(&self) -> Result<HashMap<String, Setting>, Error> {
+        xsettings::read(&self.connection, self.screen_num)
+    }
+
+    /// The monitors of the screen, with their geometry in pixels.
+    pub(crate) fn monitors(&self) -> Result<Vec<MonitorInfo>, Error> {
+        Ok(borrow!(self.monitors)?.clone())
+    }
+
+    /// The scale of the monitor that contains `point`, which is in root window pixels.
+    pub(crate) fn scale_at(&self, point: Point) -> Result<Scale, Error> {
+        Ok(screen::monitor_at(&borrow!(self.monitors)?, point)
+            .map(|m| m.scale)
+            .unwrap_or_default())
+    }
+
+    /// Queries the monitors again, and updates the scale of the windows that ended up on a
+    /// monitor with a different scale.
+    fn update_monitors(&self) -> Result<(), Error> {
+        let monitors =
+            screen::query_monitors(&self.connection, self.screen_num as usize, &self.rdb)?;
+        *borrow_mut!(self.monitors)? = monitors;
+        let windows: Vec<_> = borrow!(self.state)?.windows.values().cloned().collect();
+        for w in windows {
+            w.update_scale()?;
+        }
+        Ok(())
+    }
+
+    /// Sends a key event in `window` to the input method, if it wants it.
+    ///
+    /// Returns `true` if the input method got the event. It sends the event back if it
+    /// doesn't use it.
+    pub(crate) fn xim_forward_key(&self, window: u32, event: &xproto::KeyPressEvent) -> bool {
+        match borrow_mut!(self.xim) {
+            Ok(mut xim) => xim
+                .as_mut()
+                .map(|xim| xim.forward_key(window, event))
+                .unwrap_or(false),
+            Err(e) => {
+                tracing::error!("{:#}", e);
+                false
+            }
+        }
+    }
+
+    /// Tells the input method whether text is being entered in `window`.
+    pub(crate) fn xim_set_focus(&self, window: u32, focused: bool) {
+        match borrow_mut!(self.xim) {
+            Ok(mut xim) => {
+                if let Some(xim) = xim.as_mut() {
+                    log_x11!(xim.set_focus(window, focused));
+                }
+            }
+            Err(e) => tracing::error!("{:#}", e),
+        }
+    }
+
+    fn handle_xim_events(&self, events: Vec<XimEvent>) -> Result<(), Error> {
+        for event in events {
+            match event {
+                XimEvent::Key(window, ev) => self.window(window)?.handle_unfiltered_key(&ev),
+                XimEvent::Commit(window, text) => self.window(window)?.handle_ime_commit(&text),
+                XimEvent::Preedit(window, text, caret) => {
+                    self.window(window)?.handle_ime_preedit(&text, caret)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Translates a key press or release with the XKB keymap.
+    ///
+    /// Returns `None` if XKB is not available, and `Some(None)` if the key
+    /// press cancelled a compose sequence and should be dropped.
+    pub(crate) fn xkb_key_event(
+        &self,
+        keycode: xproto::Keycode,
+        state: KeyState,
+    ) -> Option<Option<KeyEvent>> {
+        borrow_mut!(self.keyboard)
+            .ok()?
+            .as_mut()
+            .map(|keyboard| keyboard.key_event(keycode, state))
+    }
+
     /// Returns `Ok(true)` if we want to exit the main loop.
     fn handle_event(&self, ev: &Event) -> Result<bool, Error> {
         match ev {
@@ -297,10 +474,15 @@ impl Application {
             //       CIRCULATE_NOTIFY, GRAVITY_NOTIFY
             //       MAP_NOTIFY, REPARENT_NOTIFY, UNMAP_NOTIFY
             Event::Expose(ev) => {
-                let w = self
-                    .window(ev.window)
-                    .context("EXPOSE - failed to get window")?;
-                w.handle_expose(ev).context("EXPOSE - failed to handle")?;
+                if let Some(w) = self.menu_owner(ev.window)? {
+                    w.handle_menu_expose(ev)
+                        .context("EXPOSE - failed to paint menu")?;
+                } else {
+                    let w = self
+                        .window(ev.window)
+                        .context("EXPOSE - failed to get window")?;
+                    w.handle_expose(ev).context("EXPOSE - failed to handle")?;
+                }
             }
             Event::KeyPress(ev) => {
                 let w = self
@@ -308,6 +490,34 @@ impl Application {
                     .context("KEY_PRESS - failed to get window")?;
                 w.handle_key_press(ev);
             }
+            Event::KeyRelease(ev) => {
+                let w = self
+                    .window(ev.event)
+                    .context("KEY_RELEASE - failed to get window")?;
+                w.handle_key_release(ev);
+            }
+            Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => {
+                self.update_monitors()
+                    .context("RANDR_NOTIFY - failed to update monitors")?;
+            }
+            Event::XkbStateNotify(ev) => {
+                if let Some(keyboard) = borrow_mut!(self.keyboard)?.as_mut() {
+                    keyboard.update_state(ev);
+                }
+            }
+            Event::FocusOut(_) => {
+                // the releases of the keys that are held down will go elsewhere.
+                if let Some(keyboard) = borrow_mut!(self.keyboard)?.as_mut() {
+                    keyboard.reset();
+                }
+            }
+            Event::XkbMapNotify(_) => {
+                if let Some(keyboard) = borrow_mut!(self.keyboard)?.as_mut() {
+                    keyboard
+                        .reload_keymap(&self.connection)
+                        .context("XKB_MAP_NOTIFY - failed to reload the keymap")?;
+                }
+            }
             Event::ButtonPress(ev) => {
                 let w = self
                     .window(ev.event)
@@ -340,10 +550,46 @@ impl Application {
                 w.handle_motion_notify(ev)?;
             }
             Event::ClientMessage(ev) => {
-                let w = self
-                    .window(ev.window)
-                    .context("CLIENT_MESSAGE - failed to get window")?;
-                w.handle_client_message(ev);
+                let xim_window = borrow!(self.xim)?.as_ref().map(|xim| xim.window());
+                if xim_window == Some(ev.window) {
+                    let events = match borrow_mut!(self.xim)?.as_mut() {
+                        Some(xim) => xim
+                            .handle_client_message(ev)
+                            .context("CLIENT_MESSAGE - failed to handle XIM message")?,
+                        None => Vec::new(),
+                    };
+                    self.handle_xim_events(events)?;
+                } else {
+                    let w = self
+                        .window(ev.window)
+                        .context("CLIENT_MESSAGE - failed to get window")?;
+                    w.handle_client_message(ev);
+                }
+            }
+            Event::PropertyNotify(ev) => {
+                // Properties of other windows (like the clipboard's) are not our business here.
+                if let Ok(w) = self.window(ev.window) {
+                    w.handle_property_notify(*ev)
+                        .context("PROPERTY_NOTIFY - failed to handle")?;
+                }
+            }
+            Event::SelectionNotify(ev) => {
+                if let Some(xim) = borrow_mut!(self.xim)?.as_mut() {
+                    if ev.requestor == xim.window() {
+                        xim.handle_selection_notify(ev)
+                            .context("SELECTION_NOTIFY - failed to connect to the input method")?;
+                    }
+                }
+            }
+            Event::SelectionRequest(ev) if ev.owner == self.window_id => {
+                self.clipboard_state
+                    .handle_selection_request(ev)
+                    .context("SELECTION_REQUEST - failed to answer")?;
+            }
+            Event::SelectionClear(ev) if ev.owner == self.window_id => {
+                self.clipboard_state
+                    .handle_selection_clear(*ev)
+                    .context("SELECTION_CLEAR - failed to handle")?;
             }
             Event::DestroyNotify(ev) => {
                 if ev.window == self.window_id {
@@ -400,6 +646,16 @@ impl Application {
         Ok(false)
     }
 
+    /// Returns the next event to handle, if there is one ready.
+    ///
+    /// Events that were set aside while waiting for something else come first.
+    fn next_event(&self) -> Result<Option<Event>, Error> {
+        if let Some(ev) = borrow_mut!(self.pending_events)?.pop_front() {
+            return Ok(Some(ev));
+        }
+        Ok(self.connection.poll_for_event()?)
+    }
+
     fn run_inner(self) -> Result<(), Error> {
         // Try to figure out the refresh rate of the current screen. We run the idle loop at that
         // rate. The rate-limiting of the idle loop has two purposes:
@@ -430,7 +686,7 @@ impl Application {
             // Before we poll on the connection's file descriptor, check whether there are any
             // events ready. It could be that XCB has some events in its internal buffers because
             // of something that happened during the idle loop.
-            let mut event = self.connection.poll_for_event()?;
+            let mut event = self.next_event()?;
 
             if event.is_none() {
                 poll_with_timeout(
@@ -453,7 +709,7 @@ impl Application {
                         tracing::error!("Error handling event: {:#}", e);
                     }
                 }
-                event = self.connection.poll_for_event()?;
+                event = self.next_event()?;
             }
 
             let now = Instant::now();
@@ -510,6 +766,10 @@ impl Application {
     }
 
     fn finalize_quit(&self) {
+        // Our clipboard contents go away with our window, unless a clipboard manager takes them.
+        if let Err(e) = self.clipboard_state.save_to_clipboard_manager() {
+            tracing::error!("Failed to save the clipboard contents: {:#}", e);
+        }
         log_x11!(self.connection.destroy_window(self.window_id));
         if let Err(e) = nix::unistd::close(self.idle_read) {
             tracing::error!("Error closing idle_read: {}", e);
@@ -519,10 +779,30 @@ impl Application {
         }
     }
 
+    pub fn set_badge(&self, _count: Option<u64>) {
+        // TODO(x11/badge): send the Unity launcher API signal, as the GTK backend does
+        tracing::warn!("Application::set_badge is currently unimplemented for X11 platforms.");
+    }
+
+    pub fn add_recent_document(&self, _path: &Path) {
+        // TODO(x11/recent_documents): write to recently-used.xbel, as GtkRecentManager does
+        tracing::warn!(
+            "Application::add_recent_document is currently unimplemented for X11 platforms."
+        );
+    }
+
     pub fn clipboard(&self) -> Clipboard {
-        // TODO(x11/clipboard): implement Application::clipboard
-        tracing::warn!("Application::clipboard is currently unimplemented for X11 platforms.");
-        Clipboard {}
+        self.selection(b"CLIPBOARD")
+    }
+
+    /// Returns a handle to the `PRIMARY` selection, which holds the most recently selected text.
+    pub fn primary(&self) -> Clipboard {
+        self.selection(b"PRIMARY")
+    }
+
+    /// Returns a handle to the selection called `name`.
+    pub(crate) fn selection(&self, name: &'static [u8]) -> Clipboard {
+        Clipboard::new(Rc::clone(&self.clipboard_state), name)
     }
 
     pub fn get_locale() -> String {
@@ -544,6 +824,48 @@ impl Application {
             .unwrap_or_else(|| "en-US".to_string())
     }
 
+    pub fn preferred_locales() -> Vec<String> {
+        shared::preferred_locales()
+    }
+
+    pub fn regional_settings() -> RegionalSettings {
+        // TODO(x11/regional_settings): read LC_NUMERIC and LC_TIME
+        RegionalSettings::default()
+    }
+
+    pub fn get_text_settings() -> TextSettings {
+        // TODO(x11/text_settings): read these from the XSETTINGS of the desktop
+        TextSettings::default()
+    }
+
+    pub fn get_mouse_settings() -> MouseSettings {
+        let app = match crate::Application::try_global() {
+            Some(app) => app,
+            None => return MouseSettings::default(),
+        };
+        let settings = match app.platform_app.xsettings() {
+            Ok(settings) => settings,
+            Err(e) => {
+                tracing::warn!("failed to read the XSETTINGS: {:#}", e);
+                return MouseSettings::default();
+            }
+        };
+        let int = |name| match settings.get(name) {
+            Some(Setting::Int(value)) => Some((*value).max(0)),
+            _ => None,
+        };
+        MouseSettings {
+            double_click_interval: int("Net/DoubleClickTime")
+                .map(|millis| Duration::from_millis(millis as u64)),
+            double_click_distance: int("Net/DoubleClickDistance").map(|distance| distance as f64),
+        }
+    }
+
+    pub fn power_status() -> PowerStatus {
+        // TODO(x11/power_status): tell the app handler about changes, as the GTK backend does
+        shared::power_status()
+    }
+
     pub(crate) fn idle_pipe(&self) -> RawFd {
         self.idle_write
     }
@@ -664,3 +986,45 @@ fn poll_with_timeout(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cursor_names() {
+        // a theme with only the names of the core cursor font
+        let legacy = [
+            "left_ptr",
+            "xterm",
+            "hand2",
+            "cross",
+            "hand1",
+            "crossed_circle",
+            "sb_v_double_arrow",
+            "sb_h_double_arrow",
+        ];
+        let cursors =
+            Cursors::load(|name| legacy.iter().position(|n| *n == name).map(|i| i as u32 + 1));
+        assert_eq!(cursors.default, Some(1));
+        assert_eq!(cursors.text, Some(2));
+        assert_eq!(cursors.pointer, Some(3));
+        assert_eq!(cursors.crosshair, Some(4));
+        assert_eq!(cursors.open_hand, Some(5));
+        assert_eq!(cursors.not_allowed, Some(6));
+        assert_eq!(cursors.row_resize, Some(7));
+        assert_eq!(cursors.col_resize, Some(8));
+
+        // the CSS names are preferred, and NONE means that the theme doesn't have the cursor
+        let cursors = Cursors::load(|name| match name {
+            "default" => Some(10),
+            "left_ptr" => Some(11),
+            "text" => Some(x11rb::NONE),
+            "xterm" => Some(12),
+            _ => None,
+        });
+        assert_eq!(cursors.default, Some(10));
+        assert_eq!(cursors.text, Some(12));
+        assert_eq!(cursors.pointer, None);
+    }
+}
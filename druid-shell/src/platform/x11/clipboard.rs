@@ -12,46 +12,935 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Interactions with the system pasteboard on X11.
+//! Interactions with the system pasteboard and the primary selection on X11.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::os::raw::c_int;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Error};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, GetPropertyReply,
+    PropMode, Property, SelectionClearEvent, SelectionNotifyEvent, SelectionRequestEvent,
+    Timestamp, Window, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::xcb_ffi::XCBConnection;
 
 use crate::clipboard::{ClipboardFormat, FormatId};
 use tracing::warn;
 
-#[derive(Debug, Clone, Default)]
-pub struct Clipboard;
+use super::util;
+
+/// How long we wait for the selection owner to answer, before giving up.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long we keep the application alive on exit, waiting for the clipboard manager to copy
+/// the clipboard contents.
+const SAVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The property of our window that selection owners write the clipboard contents to.
+const TRANSFER_PROPERTY: &[u8] = b"DRUID_CLIPBOARD_CONTENTS";
+
+/// The clipboard state that is shared by the whole application.
+///
+/// Besides what is needed to read selections, this holds the contents of the selections that we
+/// own, and answers the requests of other clients for them.
+pub(crate) struct ClipboardState {
+    connection: Rc<XCBConnection>,
+    /// The window that receives the clipboard contents, and that owns our selections; this is
+    /// the application's input-only event window.
+    window_id: Window,
+    /// Events that we read while waiting for the clipboard contents, to be handled
+    /// later by the event loop.
+    pending_events: Rc<RefCell<VecDeque<Event>>>,
+    /// The contents of the selections that we own, by selection atom.
+    owned: RefCell<HashMap<Atom, Contents>>,
+}
+
+/// The contents of a selection that we own.
+struct Contents {
+    /// The server time at which we became the owner.
+    timestamp: Timestamp,
+    /// The targets that we offer, with their data.
+    formats: Vec<(Atom, Vec<u8>)>,
+}
+
+/// The targets that we support for every selection that we own, besides its formats.
+struct SpecialTargets {
+    targets: Atom,
+    timestamp: Atom,
+    multiple: Atom,
+}
+
+/// The contents of a selection that we own, converted to a target.
+#[derive(Debug, PartialEq)]
+enum Conversion<'a> {
+    /// A list of atoms, for `TARGETS`.
+    Atoms(Vec<Atom>),
+    /// A 32-bit integer, for `TIMESTAMP`.
+    Integer(u32),
+    /// The data of one of the formats.
+    Data(&'a [u8]),
+}
+
+impl Contents {
+    /// Whether a request made at `time` is for these contents. Requests from before we became
+    /// the owner are for someone else's contents.
+    fn requested_at(&self, time: Timestamp) -> bool {
+        time == x11rb::CURRENT_TIME || time >= self.timestamp
+    }
+
+    /// The targets that we can convert these contents to.
+    fn targets(&self, special: &SpecialTargets) -> Vec<Atom> {
+        let mut targets = vec![special.targets, special.timestamp, special.multiple];
+        targets.extend(self.formats.iter().map(|(target, _)| *target));
+        targets
+    }
+
+    /// Converts these contents to `target`, or returns `None` if we can't.
+    ///
+    /// `MULTIPLE` isn't a conversion of its own, it asks for several of them.
+    fn convert(&self, target: Atom, special: &SpecialTargets) -> Option<Conversion<'_>> {
+        if target == special.targets {
+            Some(Conversion::Atoms(self.targets(special)))
+        } else if target == special.timestamp {
+            Some(Conversion::Integer(self.timestamp))
+        } else {
+            self.formats
+                .iter()
+                .find(|(t, _)| *t == target)
+                .map(|(_, data)| Conversion::Data(data))
+        }
+    }
+}
+
+/// A conversion that is too large for a single property, and that we send in chunks.
+///
+/// This is the sending side of the `INCR` protocol from the ICCCM: after the requestor has been
+/// notified, it deletes the property each time it has read a chunk, and we answer with the next
+/// one, ending with an empty chunk.
+struct IncrTransfer {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    data: Vec<u8>,
+}
+
+/// A handle to an X11 selection.
+///
+/// X11 has several selections. `CLIPBOARD` is the one that is used for explicit copy and paste,
+/// while `PRIMARY` holds the most recently selected text, and is pasted with the middle mouse
+/// button.
+#[derive(Clone)]
+pub struct Clipboard {
+    state: Rc<ClipboardState>,
+    /// The name of the selection atom, such as `CLIPBOARD` or `PRIMARY`.
+    selection: &'static [u8],
+}
+
+impl std::fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Clipboard")
+            .field("selection", &String::from_utf8_lossy(self.selection))
+            .field("window_id", &self.state.window_id)
+            .finish()
+    }
+}
 
 impl Clipboard {
-    pub fn put_string(&mut self, _s: impl AsRef<str>) {
-        // TODO(x11/clipboard): implement Clipboard::put_string
-        warn!("Clipboard::put_string is currently unimplemented for X11 platforms.");
+    pub(crate) fn new(state: Rc<ClipboardState>, selection: &'static [u8]) -> Self {
+        Clipboard { state, selection }
     }
 
-    pub fn put_formats(&mut self, _formats: &[ClipboardFormat]) {
-        // TODO(x11/clipboard): implement Clipboard::put_formats
-        warn!("Clipboard::put_formats is currently unimplemented for X11 platforms.");
+    pub fn put_string(&mut self, s: impl AsRef<str>) {
+        self.put_formats(&[ClipboardFormat::from(s.as_ref())]);
     }
 
-    pub fn get_string(&self) -> Option<String> {
-        // TODO(x11/clipboard): implement Clipboard::get_string
-        warn!("Clipboard::set_string is currently unimplemented for X11 platforms.");
-        None
+    pub fn put_formats(&mut self, formats: &[ClipboardFormat]) {
+        if let Err(e) = self.put_formats_inner(formats) {
+            tracing::error!("Failed to set the clipboard contents: {:#}", e);
+        }
     }
 
-    pub fn preferred_format(&self, _formats: &[FormatId]) -> Option<FormatId> {
-        // TODO(x11/clipboard): implement Clipboard::preferred_format
-        warn!("Clipboard::preferred_format is currently unimplemented for X11 platforms.");
-        None
+    pub fn put_files(&mut self, _paths: &[&Path]) {
+        // TODO(x11/clipboard): implement Clipboard::put_files
+        warn!("Clipboard::put_files is currently unimplemented for X11 platforms.");
     }
 
-    pub fn get_format(&self, _format: FormatId) -> Option<Vec<u8>> {
-        // TODO(x11/clipboard): implement Clipboard::get_format
-        warn!("Clipboard::get_format is currently unimplemented for X11 platforms.");
+    pub fn get_files(&self) -> Option<Vec<PathBuf>> {
+        // TODO(x11/clipboard): implement Clipboard::get_files
+        warn!("Clipboard::get_files is currently unimplemented for X11 platforms.");
         None
     }
 
+    pub fn get_string(&self) -> Option<String> {
+        if let Some(bytes) = self.get_format("UTF8_STRING") {
+            return String::from_utf8(bytes).ok();
+        }
+        // STRING is ISO Latin-1, whose code points are the first 256 of Unicode.
+        self.get_format("STRING")
+            .map(|bytes| bytes.into_iter().map(char::from).collect())
+    }
+
+    pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
+        let available = self.available_type_names();
+        formats
+            .iter()
+            .find(|format| available.iter().any(|name| name == *format))
+            .copied()
+    }
+
+    pub fn get_format(&self, format: FormatId) -> Option<Vec<u8>> {
+        match self.get_format_inner(format) {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::error!("Failed to read clipboard format {}: {:#}", format, e);
+                None
+            }
+        }
+    }
+
     pub fn available_type_names(&self) -> Vec<String> {
-        // TODO(x11/clipboard): implement Clipboard::available_type_names
-        warn!("Clipboard::available_type_names is currently unimplemented for X11 platforms.");
-        vec![]
+        match self.available_type_names_inner() {
+            Ok(names) => names,
+            Err(e) => {
+                tracing::error!("Failed to read clipboard targets: {:#}", e);
+                vec![]
+            }
+        }
+    }
+
+    fn put_formats_inner(&self, formats: &[ClipboardFormat]) -> Result<(), Error> {
+        let mut targets = Vec::with_capacity(formats.len());
+        for format in formats {
+            let target = self.state.intern(format.identifier.as_bytes())?;
+            targets.push((target, format.data.clone()));
+            if format.identifier == ClipboardFormat::TEXT {
+                // Some clients ask for text by its MIME type instead.
+                let mime = self.state.intern(b"text/plain;charset=utf-8")?;
+                targets.push((mime, format.data.clone()));
+            }
+        }
+        let selection = self.state.intern(self.selection)?;
+        self.state.take_ownership(selection, targets)
+    }
+
+    fn get_format_inner(&self, format: FormatId) -> Result<Option<Vec<u8>>, Error> {
+        let target = self.state.intern(format.as_bytes())?;
+        self.convert(target)
+    }
+
+    fn available_type_names_inner(&self) -> Result<Vec<String>, Error> {
+        let targets = self.state.intern(b"TARGETS")?;
+        let bytes = match self.convert(targets)? {
+            Some(bytes) => bytes,
+            None => return Ok(vec![]),
+        };
+        util::atom_names(&self.state.connection, &targets_from_bytes(&bytes))
+    }
+
+    /// Asks the owner of the selection to convert its contents to `target`, and returns them.
+    ///
+    /// Returns `None` if there is no owner, or if it could not convert the contents.
+    fn convert(&self, target: Atom) -> Result<Option<Vec<u8>>, Error> {
+        let state = self.state.as_ref();
+        let conn = state.connection.as_ref();
+        let selection = state.intern(self.selection)?;
+        let property = state.intern(TRANSFER_PROPERTY)?;
+        let incr = state.intern(b"INCR")?;
+
+        let owner = conn
+            .get_selection_owner(selection)?
+            .reply()
+            .context("get selection owner")?
+            .owner;
+        if owner == x11rb::NONE {
+            return Ok(None);
+        }
+        if owner == state.window_id {
+            // We can't wait for an answer that we would have to send ourselves.
+            return state.local_conversion(selection, target);
+        }
+
+        conn.delete_property(state.window_id, property)?;
+        conn.convert_selection(
+            state.window_id,
+            selection,
+            target,
+            property,
+            x11rb::CURRENT_TIME,
+        )?;
+        conn.flush()?;
+
+        let deadline = Instant::now() + TRANSFER_TIMEOUT;
+        let notify = state.wait_for_event(deadline, |ev| match ev {
+            Event::SelectionNotify(ev) => {
+                ev.requestor == state.window_id && ev.selection == selection && ev.target == target
+            }
+            _ => false,
+        })?;
+        match notify {
+            Some(Event::SelectionNotify(ev)) if ev.property != x11rb::NONE => {}
+            Some(_) => return Ok(None),
+            None => {
+                warn!("Timed out waiting for the clipboard owner to send its contents.");
+                return Ok(None);
+            }
+        }
+
+        let reply = state.read_property(property)?;
+        if reply.type_ != incr {
+            return Ok(Some(reply.value));
+        }
+
+        // The contents are too large for a single property, so they come in chunks: the owner
+        // writes a chunk each time we delete the property, and ends with an empty one.
+        let mut data = Vec::new();
+        loop {
+            let deadline = Instant::now() + TRANSFER_TIMEOUT;
+            let notify = state.wait_for_event(deadline, |ev| match ev {
+                Event::PropertyNotify(ev) => {
+                    ev.window == state.window_id
+                        && ev.atom == property
+                        && ev.state == Property::NEW_VALUE
+                }
+                _ => false,
+            })?;
+            if notify.is_none() {
+                warn!("Timed out during an incremental clipboard transfer.");
+                return Ok(None);
+            }
+            let chunk = state.read_property(property)?;
+            if append_incr_chunk(&mut data, &chunk.value) {
+                return Ok(Some(data));
+            }
+        }
+    }
+}
+
+/// Converts the pairs of a target and a property of a `MULTIPLE` request with `convert`.
+///
+/// The targets that can't be converted are replaced with `None`. `MULTIPLE` itself can't be
+/// requested again, and neither can a pair without a property.
+fn convert_pairs(
+    pairs: &mut [u32],
+    multiple: Atom,
+    mut convert: impl FnMut(Atom, Atom) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    for pair in pairs.chunks_exact_mut(2) {
+        let converted = pair[0] != multiple && pair[1] != x11rb::NONE && convert(pair[0], pair[1])?;
+        if !converted {
+            pair[0] = x11rb::NONE;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `ev` is part of handing the clipboard of `window` to the clipboard `manager`: either
+/// a request of the manager for the contents, or the notification that it is done.
+fn is_clipboard_manager_event(ev: &Event, window: Window, manager: Atom) -> bool {
+    match ev {
+        Event::SelectionRequest(ev) => ev.owner == window,
+        Event::SelectionNotify(ev) => ev.requestor == window && ev.selection == manager,
+        _ => false,
+    }
+}
+
+/// Reads the atoms of a `TARGETS` conversion.
+///
+/// `TARGETS` is a list of 32-bit atoms, in the byte order of our side of the connection.
+fn targets_from_bytes(bytes: &[u8]) -> Vec<Atom> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Adds a chunk of an `INCR` transfer to `data`, and returns `true` if it was the empty chunk
+/// that ends the transfer.
+fn append_incr_chunk(data: &mut Vec<u8>, chunk: &[u8]) -> bool {
+    data.extend_from_slice(chunk);
+    chunk.is_empty()
+}
+
+/// Splits `data` into the chunks of an `INCR` transfer, ending with the empty chunk.
+///
+/// The chunks are a quarter of the largest request, which leaves room for the rest of the
+/// `ChangeProperty` request.
+fn incr_chunks(data: &[u8], max_request_bytes: usize) -> impl Iterator<Item = &[u8]> {
+    data.chunks(max_request_bytes / 4)
+        .chain(std::iter::once(&[][..]))
+}
+
+impl ClipboardState {
+    pub(crate) fn new(
+        connection: Rc<XCBConnection>,
+        window_id: Window,
+        pending_events: Rc<RefCell<VecDeque<Event>>>,
+    ) -> Self {
+        ClipboardState {
+            connection,
+            window_id,
+            pending_events,
+            owned: Default::default(),
+        }
+    }
+
+    fn intern(&self, name: &[u8]) -> Result<Atom, Error> {
+        Ok(self
+            .connection
+            .intern_atom(false, name)?
+            .reply()
+            .context("intern atom")?
+            .atom)
+    }
+
+    /// Asks the server for the current time.
+    ///
+    /// We need this to take ownership of a selection, because the ICCCM forbids using
+    /// `CurrentTime` for that. Appending nothing to a property changes nothing, but still
+    /// sends us a `PropertyNotify` event with the time.
+    fn server_time(&self) -> Result<Timestamp, Error> {
+        let property = self.intern(TRANSFER_PROPERTY)?;
+        self.connection.change_property8(
+            PropMode::APPEND,
+            self.window_id,
+            property,
+            AtomEnum::STRING,
+            &[],
+        )?;
+        self.connection.flush()?;
+        let deadline = Instant::now() + TRANSFER_TIMEOUT;
+        let notify = self.wait_for_event(deadline, |ev| match ev {
+            Event::PropertyNotify(ev) => ev.window == self.window_id && ev.atom == property,
+            _ => false,
+        })?;
+        match notify {
+            Some(Event::PropertyNotify(ev)) => Ok(ev.time),
+            _ => Err(anyhow!("timed out waiting for the server time")),
+        }
+    }
+
+    /// Makes our window the owner of `selection`, offering `formats`.
+    fn take_ownership(&self, selection: Atom, formats: Vec<(Atom, Vec<u8>)>) -> Result<(), Error> {
+        let timestamp = self.server_time()?;
+        self.connection
+            .set_selection_owner(self.window_id, selection, timestamp)?;
+        let owner = self
+            .connection
+            .get_selection_owner(selection)?
+            .reply()
+            .context("get selection owner")?
+            .owner;
+        if owner != self.window_id {
+            return Err(anyhow!("failed to become the selection owner"));
+        }
+        borrow_mut!(self.owned)?.insert(selection, Contents { timestamp, formats });
+        Ok(())
+    }
+
+    fn special_targets(&self) -> Result<SpecialTargets, Error> {
+        Ok(SpecialTargets {
+            targets: self.intern(b"TARGETS")?,
+            timestamp: self.intern(b"TIMESTAMP")?,
+            multiple: self.intern(b"MULTIPLE")?,
+        })
+    }
+
+    /// Converts the contents of a selection that we own, without going through the server.
+    fn local_conversion(&self, selection: Atom, target: Atom) -> Result<Option<Vec<u8>>, Error> {
+        let owned = borrow!(self.owned)?;
+        let contents = match owned.get(&selection) {
+            Some(contents) => contents,
+            None => return Ok(None),
+        };
+        Ok(match contents.convert(target, &self.special_targets()?) {
+            Some(Conversion::Atoms(atoms)) => Some(
+                atoms
+                    .iter()
+                    .flat_map(|atom| atom.to_ne_bytes().to_vec())
+                    .collect(),
+            ),
+            Some(Conversion::Integer(value)) => Some(value.to_ne_bytes().to_vec()),
+            Some(Conversion::Data(data)) => Some(data.to_vec()),
+            None => None,
+        })
+    }
+
+    /// Answers another client's request for the contents of a selection that we own.
+    pub(crate) fn handle_selection_request(&self, ev: &SelectionRequestEvent) -> Result<(), Error> {
+        // Obsolete clients don't name a property, and expect us to use the target instead.
+        let property = if ev.property == x11rb::NONE {
+            ev.target
+        } else {
+            ev.property
+        };
+        let mut incr = Vec::new();
+        let converted = {
+            let owned = borrow!(self.owned)?;
+            match owned.get(&ev.selection) {
+                Some(contents) if contents.requested_at(ev.time) => {
+                    if ev.target == self.intern(b"MULTIPLE")? {
+                        ev.property != x11rb::NONE
+                            && self.convert_multiple(contents, ev.requestor, property, &mut incr)?
+                    } else {
+                        self.convert_to(contents, ev.requestor, ev.target, property, &mut incr)?
+                    }
+                }
+                _ => false,
+            }
+        };
+
+        let event = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: ev.time,
+            requestor: ev.requestor,
+            selection: ev.selection,
+            target: ev.target,
+            property: if converted { property } else { x11rb::NONE },
+        };
+        self.connection
+            .send_event(false, ev.requestor, EventMask::NO_EVENT, event)?;
+        self.connection.flush()?;
+        for transfer in incr {
+            self.send_incr(transfer)?;
+        }
+        Ok(())
+    }
+
+    /// Forgets the contents of a selection after another client took it over.
+    pub(crate) fn handle_selection_clear(&self, ev: SelectionClearEvent) -> Result<(), Error> {
+        borrow_mut!(self.owned)?.remove(&ev.selection);
+        Ok(())
+    }
+
+    /// Writes `contents`, converted to `target`, to `property` of `requestor`.
+    ///
+    /// Returns `false` if we can't convert to `target`. Contents that are too large for a single
+    /// request are announced with an `INCR` property, and pushed to `incr`, to be sent after the
+    /// requestor has been notified.
+    fn convert_to(
+        &self,
+        contents: &Contents,
+        requestor: Window,
+        target: Atom,
+        property: Atom,
+        incr: &mut Vec<IncrTransfer>,
+    ) -> Result<bool, Error> {
+        let conn = self.connection.as_ref();
+        let data = match contents.convert(target, &self.special_targets()?) {
+            Some(Conversion::Atoms(atoms)) => {
+                conn.change_property32(
+                    PropMode::REPLACE,
+                    requestor,
+                    property,
+                    AtomEnum::ATOM,
+                    &atoms,
+                )?;
+                return Ok(true);
+            }
+            Some(Conversion::Integer(value)) => {
+                conn.change_property32(
+                    PropMode::REPLACE,
+                    requestor,
+                    property,
+                    AtomEnum::INTEGER,
+                    &[value],
+                )?;
+                return Ok(true);
+            }
+            Some(Conversion::Data(data)) => data,
+            None => return Ok(false),
+        };
+        // A ChangeProperty request has 24 bytes besides the data.
+        if data.len() + 24 > conn.maximum_request_bytes() {
+            // We need to hear when the requestor deletes the property, to send each chunk.
+            conn.change_window_attributes(
+                requestor,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )?;
+            // The value of an INCR property is a lower bound on the size of the contents.
+            let size = u32::try_from(data.len()).unwrap_or(u32::MAX);
+            conn.change_property32(
+                PropMode::REPLACE,
+                requestor,
+                property,
+                self.intern(b"INCR")?,
+                &[size],
+            )?;
+            incr.push(IncrTransfer {
+                requestor,
+                property,
+                target,
+                data: data.to_vec(),
+            });
+        } else {
+            conn.change_property8(PropMode::REPLACE, requestor, property, target, data)?;
+        }
+        Ok(true)
+    }
+
+    /// Answers a `MULTIPLE` request, which asks for several targets at once.
+    ///
+    /// `property` holds pairs of a target and the property to write it to. As the ICCCM says, we
+    /// replace the targets that we can't convert with `None`.
+    fn convert_multiple(
+        &self,
+        contents: &Contents,
+        requestor: Window,
+        property: Atom,
+        incr: &mut Vec<IncrTransfer>,
+    ) -> Result<bool, Error> {
+        let multiple = self.intern(b"MULTIPLE")?;
+        let reply = self
+            .connection
+            .get_property(false, requestor, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()
+            .context("read MULTIPLE property")?;
+        let mut pairs: Vec<u32> = match reply.value32() {
+            Some(values) => values.collect(),
+            None => return Ok(false),
+        };
+        convert_pairs(&mut pairs, multiple, |target, property| {
+            self.convert_to(contents, requestor, target, property, incr)
+        })?;
+        self.connection.change_property32(
+            PropMode::REPLACE,
+            requestor,
+            property,
+            reply.type_,
+            &pairs,
+        )?;
+        Ok(true)
+    }
+
+    /// Sends the contents of an `INCR` transfer, one chunk each time the requestor deletes the
+    /// property.
+    ///
+    /// Like reading the clipboard, this blocks until the transfer is done. If the requestor stops
+    /// reading for longer than the `TRANSFER_TIMEOUT`, we give up on the transfer.
+    fn send_incr(&self, transfer: IncrTransfer) -> Result<(), Error> {
+        let conn = self.connection.as_ref();
+        for chunk in incr_chunks(&transfer.data, conn.maximum_request_bytes()) {
+            let deadline = Instant::now() + TRANSFER_TIMEOUT;
+            let notify = self.wait_for_event(deadline, |ev| match ev {
+                Event::PropertyNotify(ev) => {
+                    ev.window == transfer.requestor
+                        && ev.atom == transfer.property
+                        && ev.state == Property::DELETE
+                }
+                _ => false,
+            })?;
+            if notify.is_none() {
+                warn!("Timed out during an incremental clipboard transfer.");
+                break;
+            }
+            conn.change_property8(
+                PropMode::REPLACE,
+                transfer.requestor,
+                transfer.property,
+                transfer.target,
+                chunk,
+            )?;
+            conn.flush()?;
+        }
+        conn.change_window_attributes(
+            transfer.requestor,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+        )?;
+        conn.flush()?;
+        Ok(())
+    }
+
+    /// Hands the contents of `CLIPBOARD` to the clipboard manager, so that they are still
+    /// available after we exit.
+    ///
+    /// This follows the [freedesktop clipboard manager spec]: we convert the `CLIPBOARD_MANAGER`
+    /// selection to `SAVE_TARGETS`, answer the requests of the manager for the contents, and
+    /// return when it tells us that it's done, or after the `SAVE_TIMEOUT`. Nothing happens if we
+    /// don't own `CLIPBOARD`, or if no clipboard manager is running.
+    ///
+    /// [freedesktop clipboard manager spec]: https://www.freedesktop.org/wiki/ClipboardManager/
+    pub(crate) fn save_to_clipboard_manager(&self) -> Result<(), Error> {
+        let conn = self.connection.as_ref();
+        let clipboard = self.intern(b"CLIPBOARD")?;
+        let manager = self.intern(b"CLIPBOARD_MANAGER")?;
+        let save_targets = self.intern(b"SAVE_TARGETS")?;
+        let property = self.intern(TRANSFER_PROPERTY)?;
+
+        let targets = match borrow!(self.owned)?.get(&clipboard) {
+            Some(contents) => contents.formats.iter().map(|(t, _)| *t).collect::<Vec<_>>(),
+            None => return Ok(()),
+        };
+        let owner = conn.get_selection_owner(clipboard)?;
+        let manager_owner = conn.get_selection_owner(manager)?;
+        if owner.reply().context("get clipboard owner")?.owner != self.window_id
+            || manager_owner
+                .reply()
+                .context("get clipboard manager")?
+                .owner
+                == x11rb::NONE
+        {
+            return Ok(());
+        }
+
+        // The property tells the manager which targets to save.
+        conn.change_property32(
+            PropMode::REPLACE,
+            self.window_id,
+            property,
+            AtomEnum::ATOM,
+            &targets,
+        )?;
+        conn.convert_selection(
+            self.window_id,
+            manager,
+            save_targets,
+            property,
+            x11rb::CURRENT_TIME,
+        )?;
+        conn.flush()?;
+
+        let deadline = Instant::now() + SAVE_TIMEOUT;
+        loop {
+            let ev = self.wait_for_event(deadline, |ev| {
+                is_clipboard_manager_event(ev, self.window_id, manager)
+            })?;
+            match ev {
+                Some(Event::SelectionRequest(ev)) => self.handle_selection_request(&ev)?,
+                Some(_) => return Ok(()),
+                None => {
+                    warn!("Timed out handing the clipboard contents to the clipboard manager.");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Reads and deletes `property` of our window.
+    fn read_property(&self, property: Atom) -> Result<GetPropertyReply, Error> {
+        self.connection
+            .get_property(true, self.window_id, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()
+            .context("read clipboard property")
+    }
+
+    /// Waits until an event that matches `filter` arrives, and returns it.
+    ///
+    /// Any other events are set aside for the event loop. Returns `None` if no matching event
+    /// arrived before the `deadline`.
+    fn wait_for_event(
+        &self,
+        deadline: Instant,
+        filter: impl Fn(&Event) -> bool,
+    ) -> Result<Option<Event>, Error> {
+        use nix::poll::{poll, PollFd, PollFlags};
+
+        loop {
+            while let Some(ev) = self.connection.poll_for_event()? {
+                if filter(&ev) {
+                    return Ok(Some(ev));
+                }
+                borrow_mut!(self.pending_events)?.push_back(ev);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            let millis =
+                c_int::try_from(deadline.duration_since(now).as_millis()).unwrap_or(c_int::MAX - 1);
+            let mut poll_fds = [PollFd::new(self.connection.as_raw_fd(), PollFlags::POLLIN)];
+            match poll(&mut poll_fds, millis + 1) {
+                Ok(_) => {}
+                Err(nix::Error::Sys(nix::errno::Errno::EINTR)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use x11rb::protocol::xproto::SELECTION_REQUEST_EVENT;
+
+    const SPECIAL: SpecialTargets = SpecialTargets {
+        targets: 1,
+        timestamp: 2,
+        multiple: 3,
+    };
+    const UTF8_STRING: Atom = 10;
+    const PNG: Atom = 11;
+
+    fn contents() -> Contents {
+        Contents {
+            timestamp: 1000,
+            formats: vec![(UTF8_STRING, b"hello".to_vec()), (PNG, vec![0x89, b'P'])],
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        let contents = contents();
+        assert_eq!(
+            contents.convert(SPECIAL.targets, &SPECIAL),
+            Some(Conversion::Atoms(vec![1, 2, 3, UTF8_STRING, PNG]))
+        );
+        assert_eq!(
+            contents.convert(SPECIAL.timestamp, &SPECIAL),
+            Some(Conversion::Integer(1000))
+        );
+        assert_eq!(
+            contents.convert(UTF8_STRING, &SPECIAL),
+            Some(Conversion::Data(b"hello"))
+        );
+        assert_eq!(contents.convert(SPECIAL.multiple, &SPECIAL), None);
+        assert_eq!(contents.convert(99, &SPECIAL), None);
+    }
+
+    #[test]
+    fn requests_from_before_ownership() {
+        let contents = contents();
+        assert!(contents.requested_at(x11rb::CURRENT_TIME));
+        assert!(contents.requested_at(1000));
+        assert!(contents.requested_at(1001));
+        assert!(!contents.requested_at(999));
+    }
+
+    #[test]
+    fn multiple() {
+        let contents = contents();
+        let mut pairs = vec![
+            UTF8_STRING,
+            20,
+            // can't be converted
+            99,
+            21,
+            // MULTIPLE can't be nested
+            SPECIAL.multiple,
+            22,
+            // no property to write to
+            PNG,
+            x11rb::NONE,
+            SPECIAL.timestamp,
+            23,
+        ];
+        let mut written = Vec::new();
+        convert_pairs(&mut pairs, SPECIAL.multiple, |target, property| {
+            let converted = contents.convert(target, &SPECIAL).is_some();
+            if converted {
+                written.push(property);
+            }
+            Ok(converted)
+        })
+        .unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                UTF8_STRING,
+                20,
+                x11rb::NONE,
+                21,
+                x11rb::NONE,
+                22,
+                x11rb::NONE,
+                x11rb::NONE,
+                SPECIAL.timestamp,
+                23
+            ]
+        );
+        assert_eq!(written, vec![20, 23]);
+    }
+
+    #[test]
+    fn clipboard_manager_handoff() {
+        let (window, manager, clipboard) = (5, 30, 31);
+        let request = |owner| {
+            Event::SelectionRequest(SelectionRequestEvent {
+                response_type: SELECTION_REQUEST_EVENT,
+                sequence: 0,
+                time: x11rb::CURRENT_TIME,
+                owner,
+                requestor: 6,
+                selection: clipboard,
+                target: UTF8_STRING,
+                property: 40,
+            })
+        };
+        let notify = |selection| {
+            Event::SelectionNotify(SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: x11rb::CURRENT_TIME,
+                requestor: window,
+                selection,
+                target: 32,
+                property: 40,
+            })
+        };
+
+        // the manager asks us for the contents while it saves them
+        assert!(is_clipboard_manager_event(
+            &request(window),
+            window,
+            manager
+        ));
+        assert!(!is_clipboard_manager_event(&request(7), window, manager));
+        // and tells us when it is done
+        assert!(is_clipboard_manager_event(
+            &notify(manager),
+            window,
+            manager
+        ));
+        assert!(!is_clipboard_manager_event(
+            &notify(clipboard),
+            window,
+            manager
+        ));
+    }
+
+    #[test]
+    fn targets() {
+        let atoms = [1, 0x1234_5678, u32::MAX];
+        let bytes: Vec<u8> = atoms
+            .iter()
+            .flat_map(|atom| atom.to_ne_bytes().to_vec())
+            .collect();
+        assert_eq!(targets_from_bytes(&bytes), atoms);
+        // a truncated atom at the end is ignored.
+        assert_eq!(targets_from_bytes(&bytes[..10]), &atoms[..2]);
+    }
+
+    #[test]
+    fn incr_round_trip() {
+        let contents: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let chunks: Vec<&[u8]> = incr_chunks(&contents, 4096).collect();
+        assert_eq!(chunks.len(), 11);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 1024));
+
+        let mut data = Vec::new();
+        let mut done = false;
+        for chunk in chunks {
+            assert!(!done, "a chunk arrived after the end of the transfer");
+            done = append_incr_chunk(&mut data, chunk);
+        }
+        assert!(done);
+        assert_eq!(data, contents);
+    }
+
+    #[test]
+    fn empty_incr_transfer() {
+        let chunks: Vec<&[u8]> = incr_chunks(&[], 4096).collect();
+        assert_eq!(chunks, vec![&[][..]]);
     }
 }
@@ -0,0 +1,257 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! File open/save dialogs, using the FileChooser portal of [xdg-desktop-portal].
+//!
+//! The portal shows the dialog of the desktop environment in another process, so this works
+//! without a toolkit. Talking to it blocks until the user closes the dialog, so we do that on a
+//! separate thread, and hand the result back to the window through its idle queue.
+//!
+//! This needs the `xdg-portal` feature, because it links to libdbus.
+//!
+//! [xdg-desktop-portal]: https://flatpak.github.io/xdg-desktop-portal/
+
+use std::ffi::OsString;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Error};
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+use dbus::Message;
+
+use super::window::IdleHandle;
+use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo, FileSpec};
+use crate::window::FileDialogToken;
+
+const PORTAL_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const FILE_CHOOSER_INTERFACE: &str = "org.freedesktop.portal.FileChooser";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// How long we wait for the portal to accept a request (not for the user to choose a file).
+const CALL_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// The response code and chosen URIs of a finished request.
+type Response = Arc<Mutex<Option<(u32, Vec<String>)>>>;
+
+/// Show a file dialog for the window `window`.
+///
+/// The result is passed to [`WinHandler::open_files`] or [`WinHandler::save_as`] on the idle loop
+/// of the window. If the portal can't be reached, the handler gets no files, as if the user had
+/// cancelled the dialog.
+///
+/// [`WinHandler::open_files`]: crate::WinHandler::open_files
+/// [`WinHandler::save_as`]: crate::WinHandler::save_as
+pub(crate) fn show(
+    window: u32,
+    idle: IdleHandle,
+    ty: FileDialogType,
+    options: FileDialogOptions,
+) -> FileDialogToken {
+    let token = FileDialogToken::next();
+    let spawned = std::thread::Builder::new()
+        .name("druid-file-dialog".into())
+        .spawn(move || {
+            let files = match run(window, ty, options, token) {
+                Ok(paths) => paths.into_iter().map(|path| FileInfo { path }).collect(),
+                Err(e) => {
+                    tracing::error!(
+                        "File dialog failed (is xdg-desktop-portal running?): {:#}",
+                        e
+                    );
+                    Vec::new()
+                }
+            };
+            idle.add_idle_callback(move |handler| match ty {
+                FileDialogType::Open => handler.open_files(token, files),
+                FileDialogType::Save => handler.save_as(token, files.into_iter().next()),
+            });
+        });
+    if let Err(e) = spawned {
+        tracing::error!("Failed to start the file dialog thread: {}", e);
+    }
+    token
+}
+
+fn run(
+    window: u32,
+    ty: FileDialogType,
+    options: FileDialogOptions,
+    token: FileDialogToken,
+) -> Result<Vec<PathBuf>, Error> {
+    let conn = Connection::new_session().context("connect to the session bus")?;
+
+    // We need to listen for the response before making the request, or we might miss it. The
+    // portal derives the path of the request from our name and the `handle_token` option.
+    let handle_token = format!("druid_{}_{}", std::process::id(), token.into_raw());
+    let sender = conn.unique_name().trim_start_matches(':').replace('.', "_");
+    let expected = format!("{}/request/{}/{}", PORTAL_PATH, sender, handle_token);
+    let response: Response = Arc::new(Mutex::new(None));
+    listen_for_response(&conn, expected.clone(), &response)?;
+
+    let (method, default_title) = match (ty, options.select_directories) {
+        (FileDialogType::Open, false) => ("OpenFile", "Open File"),
+        (FileDialogType::Open, true) => ("OpenFile", "Open Folder"),
+        (FileDialogType::Save, _) => ("SaveFile", "Save File"),
+    };
+    let title = options
+        .title
+        .clone()
+        .unwrap_or_else(|| default_title.to_string());
+    let parent = format!("x11:{:x}", window);
+    let request_options = request_options(ty, &options, handle_token);
+
+    let proxy = conn.with_proxy(PORTAL_NAME, PORTAL_PATH, CALL_TIMEOUT);
+    let (handle,): (dbus::Path<'static>,) = proxy
+        .method_call(
+            FILE_CHOOSER_INTERFACE,
+            method,
+            (parent, title, request_options),
+        )
+        .context("call the FileChooser portal")?;
+    if *handle != expected {
+        // Old versions of the portal ignore `handle_token`.
+        listen_for_response(&conn, String::from(&*handle), &response)?;
+    }
+
+    loop {
+        if let Some((code, uris)) = response.lock().unwrap().take() {
+            return match code {
+                0 => Ok(uris.iter().filter_map(|uri| path_from_uri(uri)).collect()),
+                // the user cancelled the dialog
+                1 => Ok(Vec::new()),
+                _ => Err(anyhow!("the portal ended the request with code {}", code)),
+            };
+        }
+        conn.process(Duration::from_secs(1))
+            .context("wait for the portal response")?;
+    }
+}
+
+fn listen_for_response(conn: &Connection, path: String, response: &Response) -> Result<(), Error> {
+    let path = dbus::Path::new(path).map_err(|e| anyhow!("invalid request path: {}", e))?;
+    let rule = MatchRule::new_signal(REQUEST_INTERFACE, "Response").with_path(path);
+    let response = Arc::clone(response);
+    conn.add_match(
+        rule,
+        move |(code, results): (u32, PropMap), _: &Connection, _: &Message| {
+            let uris = results
+                .get("uris")
+                .and_then(|uris| uris.0.as_iter())
+                .map(|uris| {
+                    uris.filter_map(|uri| uri.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            *response.lock().unwrap() = Some((code, uris));
+            // every request gets a single response
+            false
+        },
+    )
+    .context("listen for the portal response")?;
+    Ok(())
+}
+
+fn variant<T: RefArg + 'static>(value: T) -> Variant<Box<dyn RefArg>> {
+    Variant(Box::new(value))
+}
+
+/// The portal's representation of a filter: a name, and a list of glob patterns.
+fn filter(spec: &FileSpec) -> (String, Vec<(u32, String)>) {
+    let patterns = spec
+        .extensions
+        .iter()
+        .map(|ext| (0, format!("*.{}", ext)))
+        .collect();
+    (spec.name.to_string(), patterns)
+}
+
+fn request_options(ty: FileDialogType, options: &FileDialogOptions, token: String) -> PropMap {
+    let mut map = PropMap::new();
+    map.insert("handle_token".into(), variant(token));
+    map.insert("modal".into(), variant(true));
+    if let Some(label) = &options.button_text {
+        map.insert("accept_label".into(), variant(label.clone()));
+    }
+
+    if ty == FileDialogType::Open {
+        map.insert("multiple".into(), variant(options.multi_selection));
+        map.insert("directory".into(), variant(options.select_directories));
+    }
+    // Filters would keep us from navigating into folders when choosing one.
+    if !(ty == FileDialogType::Open && options.select_directories) {
+        if let Some(types) = &options.allowed_types {
+            let filters: Vec<_> = types.iter().map(filter).collect();
+            map.insert("filters".into(), variant(filters));
+        }
+        if let Some(default) = &options.default_type {
+            map.insert("current_filter".into(), variant(filter(default)));
+        }
+    }
+
+    if ty == FileDialogType::Save {
+        if let Some(name) = &options.default_name {
+            map.insert("current_name".into(), variant(name.clone()));
+        }
+    }
+    if let Some(dir) = &options.starting_directory {
+        // this is a NUL-terminated byte string, because paths need not be UTF-8
+        let mut bytes = dir.as_os_str().as_bytes().to_vec();
+        bytes.push(0);
+        map.insert("current_folder".into(), variant(bytes));
+    }
+    map
+}
+
+/// Converts a `file://` URI into a path, undoing the percent-encoding.
+fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                bytes.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        bytes.push(encoded[i]);
+        i += 1;
+    }
+    Some(OsString::from_vec(bytes).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uri_to_path() {
+        assert_eq!(
+            path_from_uri("file:///home/me/My%20Files/na%C3%AFve.txt"),
+            Some(PathBuf::from("/home/me/My Files/naïve.txt"))
+        );
+        assert_eq!(
+            path_from_uri("file:///tmp/100%"),
+            Some(PathBuf::from("/tmp/100%"))
+        );
+        assert_eq!(path_from_uri("https://example.com/a.txt"), None);
+    }
+}
@@ -13,43 +13,757 @@
 // limitations under the License.
 
 //! X11 menus implementation.
+//!
+//! X11 has no menus of its own, so we draw them ourselves. Every open menu (and submenu) is an
+//! override-redirect window that we paint with cairo. While a menu is open, the window that it
+//! belongs to grabs the pointer and the keyboard, so that all input goes to that window, which
+//! gives it to the menu.
+//!
+//! There is no menubar: the window menu opens as a popup in the corner of the window when F10 is
+//! pressed, and the hotkeys of its entries work whether it is open or not.
 
+use std::convert::TryInto;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Context, Error};
+use cairo::XCBSurface;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    self, ConnectionExt, EventMask, GrabMode, GrabStatus, Visualtype, WindowClass,
+};
+use x11rb::xcb_ffi::XCBConnection;
+
+use crate::common_util::strip_access_key;
 use crate::hotkey::HotKey;
+use crate::keyboard::{KbKey, KeyEvent, Modifiers};
+use crate::kurbo::{BezPath, Line, Point, Rect, Size};
+use crate::piet::{
+    Color, FontFamily, Piet, PietText, PietTextLayout, RenderContext, Text, TextLayout,
+    TextLayoutBuilder,
+};
+use crate::scale::{Scalable, Scale};
+use crate::screen::AnchorSide;
+
+use super::util;
+use super::window::create_cairo_surface;
+
+const FONT_SIZE: f64 = 13.0;
+const ROW_HEIGHT: f64 = 24.0;
+const SEPARATOR_HEIGHT: f64 = 9.0;
+/// The space above the first row and below the last one.
+const PADDING: f64 = 4.0;
+/// Where the labels start; the check marks go to the left of them.
+const LABEL_X: f64 = 24.0;
+/// The space between the labels and the hotkeys.
+const HOTKEY_GAP: f64 = 32.0;
+/// The space to the right of the hotkeys, where the submenu arrows go.
+const ARROW_WIDTH: f64 = 20.0;
+const MIN_WIDTH: f64 = 120.0;
+
+const BACKGROUND: Color = Color::rgb8(0xfa, 0xfa, 0xfa);
+const BORDER: Color = Color::rgb8(0xb0, 0xb0, 0xb0);
+const HOT_BACKGROUND: Color = Color::rgb8(0xd0, 0xe0, 0xf6);
+const SEPARATOR: Color = Color::rgb8(0xdd, 0xdd, 0xdd);
+const TEXT: Color = Color::rgb8(0x20, 0x20, 0x20);
+const DISABLED_TEXT: Color = Color::rgb8(0xa0, 0xa0, 0xa0);
+
+#[derive(Clone, Default)]
+pub struct Menu {
+    items: Vec<MenuItem>,
+}
 
-pub struct Menu;
+#[derive(Clone)]
+enum MenuItem {
+    Entry {
+        name: String,
+        id: u32,
+        key: Option<HotKey>,
+        enabled: bool,
+        selected: bool,
+    },
+    SubMenu {
+        name: String,
+        menu: Menu,
+        enabled: bool,
+    },
+    Separator,
+}
 
 impl Menu {
     pub fn new() -> Menu {
-        // TODO(x11/menus): implement Menu::new (currently a no-op)
-        tracing::warn!("Menu::new is currently unimplemented for X11 platforms.");
-        Menu {}
+        Menu::default()
     }
 
     pub fn new_for_popup() -> Menu {
-        // TODO(x11/menus): implement Menu::new_for_popup (currently a no-op)
-        tracing::warn!("Menu::new_for_popup is currently unimplemented for X11 platforms.");
-        Menu {}
+        Menu::default()
     }
 
-    pub fn add_dropdown(&mut self, mut _menu: Menu, _text: &str, _enabled: bool) {
-        // TODO(x11/menus): implement Menu::add_dropdown (currently a no-op)
-        tracing::warn!("Menu::add_dropdown is currently unimplemented for X11 platforms.");
+    pub fn add_dropdown(&mut self, menu: Menu, text: &str, enabled: bool) {
+        self.items.push(MenuItem::SubMenu {
+            name: strip_access_key(text),
+            menu,
+            enabled,
+        });
     }
 
     pub fn add_item(
         &mut self,
-        _id: u32,
-        _text: &str,
-        _key: Option<&HotKey>,
-        _enabled: bool,
-        _selected: bool,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
     ) {
-        // TODO(x11/menus): implement Menu::add_item (currently a no-op)
-        tracing::warn!("Menu::add_item is currently unimplemented for X11 platforms.");
+        self.items.push(MenuItem::Entry {
+            name: strip_access_key(text),
+            id,
+            key: key.cloned(),
+            enabled,
+            selected,
+        });
     }
 
     pub fn add_separator(&mut self) {
-        // TODO(x11/menus): implement Menu::add_separator (currently a no-op)
-        tracing::warn!("Menu::add_separator is currently unimplemented for X11 platforms.");
+        self.items.push(MenuItem::Separator);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the id of the enabled entry whose hotkey matches `event`, if there is one.
+    pub(crate) fn command_for_key(&self, event: &KeyEvent) -> Option<u32> {
+        self.items.iter().find_map(|item| match item {
+            MenuItem::Entry {
+                id,
+                key: Some(key),
+                enabled: true,
+                ..
+            } if key.matches(event) => Some(*id),
+            MenuItem::SubMenu {
+                menu,
+                enabled: true,
+                ..
+            } => menu.command_for_key(event),
+            _ => None,
+        })
+    }
+
+    /// Replaces the entry with the id `id`. Returns `false` if there is no such entry.
+    pub(crate) fn update_item(
+        &mut self,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
+    ) -> bool {
+        self.items.iter_mut().any(|item| match item {
+            MenuItem::Entry { id: item_id, .. } if *item_id == id => {
+                *item = MenuItem::Entry {
+                    name: strip_access_key(text),
+                    id,
+                    key: key.cloned(),
+                    enabled,
+                    selected,
+                };
+                true
+            }
+            MenuItem::SubMenu { menu, .. } => menu.update_item(id, text, key, enabled, selected),
+            _ => false,
+        })
+    }
+
+    /// The submenu that is reached by following the submenu entries at the indices in `path`.
+    fn submenu(&self, path: &[usize]) -> &Menu {
+        path.iter()
+            .fold(self, |menu, &index| match &menu.items[index] {
+                MenuItem::SubMenu { menu, .. } => menu,
+                _ => menu,
+            })
+    }
+
+    /// Whether the item at `index` can be highlighted and chosen.
+    fn is_selectable(&self, index: usize) -> bool {
+        match self.items.get(index) {
+            Some(MenuItem::Entry { enabled, .. }) | Some(MenuItem::SubMenu { enabled, .. }) => {
+                *enabled
+            }
+            _ => false,
+        }
+    }
+
+    fn is_submenu(&self, index: usize) -> bool {
+        matches!(
+            self.items.get(index),
+            Some(MenuItem::SubMenu { enabled: true, .. })
+        )
+    }
+
+    /// The next selectable item after `from` (or before it, if `forward` is `false`), wrapping
+    /// around at the ends.
+    fn next_selectable(&self, from: Option<usize>, forward: bool) -> Option<usize> {
+        let len = self.items.len();
+        let mut index = from;
+        for _ in 0..len {
+            let next = match (index, forward) {
+                (None, true) => 0,
+                (None, false) => len - 1,
+                (Some(i), true) => (i + 1) % len,
+                (Some(i), false) => (i + len - 1) % len,
+            };
+            if self.is_selectable(next) {
+                return Some(next);
+            }
+            index = Some(next);
+        }
+        None
+    }
+}
+
+/// What a window should do after giving an event to its open menu.
+pub(crate) enum MenuAction {
+    /// Keep the menu open.
+    None,
+    /// Close the menu.
+    Close,
+    /// Close the menu, and run the command with this id.
+    Command(u32),
+}
+
+/// A menu that is being shown, along with the submenus that are open in it.
+pub(crate) struct MenuPopup {
+    conn: Rc<XCBConnection>,
+    root: u32,
+    visual_type: Visualtype,
+    scale: Scale,
+    /// The area that the menus are kept inside, in pixels.
+    bounds: Rect,
+    menu: Menu,
+    /// The open menus, from the one that was shown to the innermost submenu.
+    levels: Vec<Level>,
+    /// Whether a button was pressed or the pointer moved to another entry since the menu was
+    /// shown. Until then, releasing a button doesn't choose an entry, because it's most likely
+    /// the end of the click that opened the menu.
+    armed: bool,
+}
+
+/// One open menu window.
+struct Level {
+    window: u32,
+    surface: XCBSurface,
+    /// The indices of the submenu entries that lead from the outermost menu to this one.
+    path: Vec<usize>,
+    /// The position and size of the window, in pixels relative to the root window.
+    rect: Rect,
+    /// One row for each item, in display points relative to the window.
+    rows: Vec<Row>,
+    /// The item under the pointer, or the one that was selected with the keyboard.
+    hot: Option<usize>,
+}
+
+struct Row {
+    rect: Rect,
+    label: Option<PietTextLayout>,
+    hotkey: Option<PietTextLayout>,
+}
+
+impl MenuPopup {
+    /// Shows `menu` at `pos` (in pixels, relative to the root window), for the window `owner`.
+    ///
+    /// The owner grabs the pointer and the keyboard until the popup is dropped. If `select_first`
+    /// is `true`, the first entry is highlighted, as is usual for menus opened with the keyboard.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        conn: Rc<XCBConnection>,
+        owner: u32,
+        root: u32,
+        visual_type: Visualtype,
+        scale: Scale,
+        bounds: Rect,
+        menu: Menu,
+        pos: Point,
+        select_first: bool,
+    ) -> Result<MenuPopup, Error> {
+        let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION;
+        let grab = conn
+            .grab_pointer(
+                false,
+                owner,
+                u32::from(mask) as u16,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                x11rb::CURRENT_TIME,
+            )?
+            .reply()
+            .context("grab the pointer for a menu")?;
+        if grab.status != GrabStatus::SUCCESS {
+            return Err(anyhow!("failed to grab the pointer: {:?}", grab.status));
+        }
+        // From here on, dropping the popup releases the grabs.
+        let mut popup = MenuPopup {
+            conn,
+            root,
+            visual_type,
+            scale,
+            bounds,
+            menu,
+            levels: Vec::new(),
+            armed: false,
+        };
+        let grab = popup
+            .conn
+            .grab_keyboard(
+                false,
+                owner,
+                x11rb::CURRENT_TIME,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?
+            .reply()
+            .context("grab the keyboard for a menu")?;
+        if grab.status != GrabStatus::SUCCESS {
+            return Err(anyhow!("failed to grab the keyboard: {:?}", grab.status));
+        }
+        popup.open(Vec::new(), Rect::from_points(pos, pos))?;
+        if select_first {
+            let first = popup.menu.next_selectable(None, true);
+            popup.set_hot(0, first)?;
+        }
+        Ok(popup)
+    }
+
+    /// Returns `true` if `window` is one of the windows of this popup.
+    pub(crate) fn has_window(&self, window: u32) -> bool {
+        self.levels.iter().any(|level| level.window == window)
+    }
+
+    pub(crate) fn handle_expose(&self, window: u32) -> Result<(), Error> {
+        match self.levels.iter().position(|level| level.window == window) {
+            Some(index) => self.paint(index),
+            None => Ok(()),
+        }
+    }
+
+    /// Handles the pointer moving to `pos`, in pixels relative to the root window.
+    pub(crate) fn handle_motion(&mut self, pos: Point) -> Result<MenuAction, Error> {
+        if let Some((level, row)) = self.hit_test(pos) {
+            let menu = self.menu.submenu(&self.levels[level].path);
+            let row = row.filter(|&index| menu.is_selectable(index));
+            if row != self.levels[level].hot {
+                self.armed = true;
+                self.set_hot(level, row)?;
+                if let Some(index) = row {
+                    self.open_submenu(level, index)?;
+                }
+            }
+        }
+        Ok(MenuAction::None)
+    }
+
+    pub(crate) fn handle_button_press(&mut self, pos: Point) -> Result<MenuAction, Error> {
+        if self.hit_test(pos).is_none() {
+            // Clicking anywhere else closes the menu.
+            return Ok(MenuAction::Close);
+        }
+        self.armed = true;
+        Ok(MenuAction::None)
+    }
+
+    pub(crate) fn handle_button_release(&mut self, pos: Point) -> Result<MenuAction, Error> {
+        if !std::mem::replace(&mut self.armed, true) {
+            return Ok(MenuAction::None);
+        }
+        match self.hit_test(pos) {
+            Some((level, Some(row))) => self.activate(level, row),
+            _ => Ok(MenuAction::None),
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, event: &KeyEvent) -> Result<MenuAction, Error> {
+        let level = self.levels.len() - 1;
+        let hot = self.levels[level].hot;
+        let menu = self.menu.submenu(&self.levels[level].path);
+        match &event.key {
+            KbKey::ArrowDown | KbKey::ArrowUp => {
+                let next = menu.next_selectable(hot, event.key == KbKey::ArrowDown);
+                self.set_hot(level, next)?;
+            }
+            KbKey::ArrowRight => {
+                if let Some(index) = hot.filter(|&index| menu.is_submenu(index)) {
+                    self.activate(level, index)?;
+                }
+            }
+            KbKey::ArrowLeft if level > 0 => self.close_levels(level),
+            KbKey::Escape if level > 0 => self.close_levels(level),
+            KbKey::Escape => return Ok(MenuAction::Close),
+            KbKey::Enter => {
+                if let Some(index) = hot {
+                    return self.activate(level, index);
+                }
+            }
+            KbKey::Character(c) if c == " " => {
+                if let Some(index) = hot {
+                    return self.activate(level, index);
+                }
+            }
+            _ => {}
+        }
+        Ok(MenuAction::None)
+    }
+
+    /// Chooses the item at `index` in the menu at `level`: an entry runs its command, and a
+    /// submenu opens with its first entry highlighted.
+    fn activate(&mut self, level: usize, index: usize) -> Result<MenuAction, Error> {
+        let menu = self.menu.submenu(&self.levels[level].path);
+        match &menu.items[index] {
+            MenuItem::Entry {
+                id, enabled: true, ..
+            } => Ok(MenuAction::Command(*id)),
+            MenuItem::SubMenu { enabled: true, .. } => {
+                self.set_hot(level, Some(index))?;
+                self.open_submenu(level, index)?;
+                let submenu = self.menu.submenu(&self.levels[level + 1].path);
+                let first = submenu.next_selectable(None, true);
+                self.set_hot(level + 1, first)?;
+                Ok(MenuAction::None)
+            }
+            _ => Ok(MenuAction::None),
+        }
+    }
+
+    /// Finds the menu at `pos`, and the row under it, if any.
+    fn hit_test(&self, pos: Point) -> Option<(usize, Option<usize>)> {
+        // The submenus are on top, so look at them first.
+        self.levels
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, level)| {
+                if !level.rect.contains(pos) {
+                    return None;
+                }
+                let local = Point::new(
+                    (pos.x - level.rect.x0) / self.scale.x(),
+                    (pos.y - level.rect.y0) / self.scale.y(),
+                );
+                let row = level.rows.iter().position(|row| row.rect.contains(local));
+                Some((index, row))
+            })
+    }
+
+    /// Highlights the row `hot` in the menu at `level`, closing the submenus of any other row.
+    fn set_hot(&mut self, level: usize, hot: Option<usize>) -> Result<(), Error> {
+        let keep_submenu = self
+            .levels
+            .get(level + 1)
+            .is_some_and(|submenu| submenu.path.last().copied() == hot);
+        self.close_levels(if keep_submenu { level + 2 } else { level + 1 });
+        if self.levels[level].hot != hot {
+            self.levels[level].hot = hot;
+            self.paint(level)?;
+        }
+        Ok(())
+    }
+
+    /// Opens the submenu at `index` of the menu at `level`, if it is one and isn't open yet.
+    fn open_submenu(&mut self, level: usize, index: usize) -> Result<(), Error> {
+        let parent = &self.levels[level];
+        if !self.menu.submenu(&parent.path).is_submenu(index) || self.levels.len() > level + 1 {
+            return Ok(());
+        }
+        let row = parent.rows[index].rect.to_px(self.scale);
+        let padding = PADDING * self.scale.y();
+        let anchor = Rect::new(
+            parent.rect.x0,
+            parent.rect.y0 + row.y0 - padding,
+            parent.rect.x1,
+            parent.rect.y0 + row.y1 + padding,
+        );
+        let mut path = parent.path.clone();
+        path.push(index);
+        self.open(path, anchor)
+    }
+
+    /// Opens a window for the submenu at `path`, next to `anchor`.
+    fn open(&mut self, path: Vec<usize>, anchor: Rect) -> Result<(), Error> {
+        let mut text = PietText::new();
+        let menu = self.menu.submenu(&path);
+        let (rows, size) = layout_rows(menu, &mut text)?;
+        let size_px = size.to_px(self.scale);
+        let size_px = Size::new(size_px.width.ceil(), size_px.height.ceil());
+        let origin = place_menu(size_px, anchor, self.bounds);
+        let rect = Rect::from_origin_size(origin, size_px);
+
+        let conn = &self.conn;
+        let window = conn.generate_id()?;
+        let cw_values = xproto::CreateWindowAux::new()
+            .override_redirect(1)
+            .save_under(1)
+            .background_pixel(util::color_to_pixel(&self.visual_type, &BACKGROUND))
+            .event_mask(EventMask::EXPOSURE);
+        conn.create_window(
+            x11rb::COPY_FROM_PARENT.try_into().unwrap(),
+            window,
+            self.root,
+            rect.x0 as i16,
+            rect.y0 as i16,
+            rect.width() as u16,
+            rect.height() as u16,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            self.visual_type.visual_id,
+            &cw_values,
+        )?
+        .check()
+        .context("create menu window")?;
+        let surface = match create_cairo_surface(conn, window, &self.visual_type, size_px) {
+            Ok(surface) => surface,
+            Err(e) => {
+                log_x11!(conn.destroy_window(window));
+                return Err(e);
+            }
+        };
+        conn.map_window(window)?;
+        conn.flush()?;
+
+        self.levels.push(Level {
+            window,
+            surface,
+            path,
+            rect,
+            rows,
+            hot: None,
+        });
+        Ok(())
+    }
+
+    /// Closes the menus from `level` on.
+    fn close_levels(&mut self, level: usize) {
+        while self.levels.len() > level {
+            if let Some(closed) = self.levels.pop() {
+                // The surface has to let go of the window before it is destroyed.
+                closed.surface.finish();
+                log_x11!(self.conn.destroy_window(closed.window));
+            }
+        }
+        log_x11!(self.conn.flush());
+    }
+
+    fn paint(&self, index: usize) -> Result<(), Error> {
+        let level = &self.levels[index];
+        let menu = self.menu.submenu(&level.path);
+        let cairo_ctx = cairo::Context::new(&level.surface);
+        cairo_ctx.scale(self.scale.x(), self.scale.y());
+        let mut piet_ctx = Piet::new(&cairo_ctx);
+
+        let bounds = level.rect.size().to_dp(self.scale).to_rect();
+        piet_ctx.fill(bounds, &BACKGROUND);
+        piet_ctx.stroke(bounds.inset(-0.5), &BORDER, 1.0);
+        for (i, (item, row)) in menu.items.iter().zip(&level.rows).enumerate() {
+            if let MenuItem::Separator = item {
+                let y = row.rect.center().y.floor() + 0.5;
+                let line = Line::new((row.rect.x0 + 1.0, y), (row.rect.x1 - 1.0, y));
+                piet_ctx.stroke(line, &SEPARATOR, 1.0);
+                continue;
+            }
+            if level.hot == Some(i) {
+                piet_ctx.fill(row.rect.inset((-1.0, 0.0)), &HOT_BACKGROUND);
+            }
+            let (enabled, selected, submenu) = match item {
+                MenuItem::Entry {
+                    enabled, selected, ..
+                } => (*enabled, *selected, false),
+                MenuItem::SubMenu { enabled, .. } => (*enabled, false, true),
+                MenuItem::Separator => unreachable!(),
+            };
+            let color = if enabled { &TEXT } else { &DISABLED_TEXT };
+            let center_y = row.rect.center().y;
+            if let Some(label) = &row.label {
+                let y = center_y - label.size().height / 2.0;
+                piet_ctx.draw_text(label, (LABEL_X, y));
+            }
+            if let Some(hotkey) = &row.hotkey {
+                let size = hotkey.size();
+                let x = row.rect.x1 - ARROW_WIDTH - size.width;
+                piet_ctx.draw_text(hotkey, (x, center_y - size.height / 2.0));
+            }
+            if selected {
+                let mut check = BezPath::new();
+                check.move_to((8.0, center_y));
+                check.line_to((11.0, center_y + 3.0));
+                check.line_to((16.0, center_y - 4.0));
+                piet_ctx.stroke(check, color, 1.5);
+            }
+            if submenu {
+                let x = row.rect.x1 - ARROW_WIDTH / 2.0;
+                let mut arrow = BezPath::new();
+                arrow.move_to((x - 2.0, center_y - 4.0));
+                arrow.line_to((x + 2.0, center_y));
+                arrow.line_to((x - 2.0, center_y + 4.0));
+                arrow.close_path();
+                piet_ctx.fill(arrow, color);
+            }
+        }
+
+        piet_ctx
+            .finish()
+            .map_err(|e| anyhow!("MenuPopup::paint - piet finish failed: {}", e))?;
+        level.surface.flush();
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for MenuPopup {
+    fn drop(&mut self) {
+        self.close_levels(0);
+        log_x11!(self.conn.ungrab_pointer(x11rb::CURRENT_TIME));
+        log_x11!(self.conn.ungrab_keyboard(x11rb::CURRENT_TIME));
+        log_x11!(self.conn.flush());
+    }
+}
+
+/// Lays out the items of `menu`, returning their rows and the size of the menu, in display
+/// points.
+fn layout_rows(menu: &Menu, text: &mut PietText) -> Result<(Vec<Row>, Size), Error> {
+    let mut build = |s: &str, enabled: bool| {
+        let color = if enabled { TEXT } else { DISABLED_TEXT };
+        text.new_text_layout(s.to_string())
+            .font(FontFamily::SYSTEM_UI, FONT_SIZE)
+            .text_color(color)
+            .build()
+            .map_err(|e| anyhow!("failed to lay out menu text: {}", e))
+    };
+
+    let mut rows = Vec::with_capacity(menu.items.len());
+    let mut label_width: f64 = 0.0;
+    let mut hotkey_width: f64 = 0.0;
+    let mut y = PADDING;
+    for item in &menu.items {
+        let (label, hotkey, height) = match item {
+            MenuItem::Entry {
+                name, key, enabled, ..
+            } => {
+                let hotkey = match key {
+                    Some(key) => Some(build(&hotkey_label(key), *enabled)?),
+                    None => None,
+                };
+                (Some(build(name, *enabled)?), hotkey, ROW_HEIGHT)
+            }
+            MenuItem::SubMenu { name, enabled, .. } => {
+                (Some(build(name, *enabled)?), None, ROW_HEIGHT)
+            }
+            MenuItem::Separator => (None, None, SEPARATOR_HEIGHT),
+        };
+        if let Some(label) = &label {
+            label_width = label_width.max(label.size().width);
+        }
+        if let Some(hotkey) = &hotkey {
+            hotkey_width = hotkey_width.max(hotkey.size().width);
+        }
+        rows.push(Row {
+            rect: Rect::new(0.0, y, 0.0, y + height),
+            label,
+            hotkey,
+        });
+        y += height;
+    }
+
+    let hotkeys = if hotkey_width > 0.0 {
+        HOTKEY_GAP + hotkey_width
+    } else {
+        0.0
+    };
+    let width = (LABEL_X + label_width + hotkeys + ARROW_WIDTH).max(MIN_WIDTH);
+    for row in &mut rows {
+        row.rect.x1 = width;
+    }
+    Ok((rows, Size::new(width, y + PADDING)))
+}
+
+/// How a hotkey is shown next to its entry, like "Ctrl+Shift+S".
+fn hotkey_label(key: &HotKey) -> String {
+    let mods: Modifiers = key.mods.into();
+    let mut label = String::new();
+    let names = [
+        (Modifiers::CONTROL, "Ctrl"),
+        (Modifiers::ALT, "Alt"),
+        (Modifiers::SHIFT, "Shift"),
+        (Modifiers::META, "Super"),
+    ];
+    for (modifier, name) in names.iter() {
+        if mods.contains(*modifier) {
+            label.push_str(name);
+            label.push('+');
+        }
+    }
+    match &key.key {
+        KbKey::Character(c) if c == " " => label.push_str("Space"),
+        KbKey::Character(c) => label.push_str(&c.to_uppercase()),
+        key => label.push_str(&key.to_string()),
+    }
+    label
+}
+
+/// Where to put a menu of `size` next to `anchor`, keeping it inside `bounds`.
+///
+/// The menu goes to the right of the anchor, level with its top, if it fits there. Otherwise it
+/// goes to the left, or ends level with the bottom of the anchor. A context menu is anchored to
+/// the point where it was opened, and a submenu to its entry in the parent menu.
+fn place_menu(size: Size, anchor: Rect, bounds: Rect) -> Point {
+    AnchorSide::End.place(anchor, size, bounds)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hotkey::SysMods;
+
+    #[test]
+    fn menu_placement() {
+        let bounds = Rect::new(0., 0., 1000., 800.);
+        let size = Size::new(200., 300.);
+
+        let at = |x, y| Rect::from_points((x, y), (x, y));
+        assert_eq!(place_menu(size, at(10., 20.), bounds), Point::new(10., 20.));
+        // flipped to the left of and above the pointer near the bottom right corner
+        assert_eq!(
+            place_menu(size, at(900., 700.), bounds),
+            Point::new(700., 400.)
+        );
+
+        // a submenu next to its parent, or on the other side of it
+        let parent = Rect::new(100., 100., 300., 120.);
+        assert_eq!(place_menu(size, parent, bounds), Point::new(300., 100.));
+        let parent = Rect::new(750., 100., 950., 120.);
+        assert_eq!(place_menu(size, parent, bounds), Point::new(550., 100.));
+
+        // too big to fit either way
+        let tall = Size::new(200., 900.);
+        assert_eq!(place_menu(tall, at(10., 20.), bounds), Point::new(10., 0.));
+    }
+
+    #[test]
+    fn hotkeys_in_submenus() {
+        let save = HotKey::new(SysMods::Cmd, "s");
+        let quit = HotKey::new(SysMods::Cmd, "q");
+        let mut file = Menu::new();
+        file.add_item(1, "&Save", Some(&save), true, false);
+        file.add_item(2, "&Quit", Some(&quit), false, false);
+        let mut menu = Menu::new();
+        menu.add_dropdown(file, "&File", true);
+
+        let event = |key| KeyEvent::for_test(Modifiers::CONTROL, key);
+        assert_eq!(menu.command_for_key(&event("s")), Some(1));
+        // disabled entries don't run
+        assert_eq!(menu.command_for_key(&event("q")), None);
+
+        assert!(menu.update_item(2, "Quit", Some(&quit), true, false));
+        assert_eq!(menu.command_for_key(&event("q")), Some(2));
+        assert!(!menu.update_item(3, "Missing", None, true, false));
+        assert_eq!(hotkey_label(&save), "Ctrl+S");
     }
 }
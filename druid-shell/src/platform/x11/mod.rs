@@ -35,8 +35,13 @@ mod util;
 
 pub mod application;
 pub mod clipboard;
+#[cfg(feature = "xdg-portal")]
+mod dialog;
 pub mod error;
 pub mod keycodes;
 pub mod menu;
 pub mod screen;
 pub mod window;
+mod xim;
+mod xkb;
+mod xsettings;
@@ -14,9 +14,351 @@
 
 //! X11 Monitors and Screen information.
 
+use anyhow::{anyhow, Context, Error};
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, Window};
+use x11rb::resource_manager::Database as ResourceDb;
+use x11rb::xcb_ffi::XCBConnection;
+
+use crate::kurbo::{Point, Rect};
+use crate::scale::Scale;
 use crate::screen::Monitor;
 
+/// A monitor of the X11 screen, with its geometry in pixels.
+#[derive(Clone, Debug)]
+pub(crate) struct MonitorInfo {
+    pub primary: bool,
+    pub rect: Rect,
+    pub work_rect: Rect,
+    /// The physical width, or zero if it is unknown.
+    pub width_mm: u32,
+    pub scale: Scale,
+}
+
 pub(crate) fn get_monitors() -> Vec<Monitor> {
-    tracing::warn!("Screen::get_monitors() is currently unimplemented for X11 platforms.");
-    Vec::new()
+    let app = match crate::Application::try_global() {
+        Some(app) => app,
+        None => {
+            tracing::warn!("Screen::get_monitors() requires an Application on X11 platforms.");
+            return Vec::new();
+        }
+    };
+    match app.platform_app.monitors() {
+        Ok(monitors) => monitors
+            .into_iter()
+            .map(|m| Monitor::new(m.primary, m.rect, m.work_rect))
+            .collect(),
+        Err(e) => {
+            tracing::error!("failed to get monitors: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Returns the monitor that contains `point`, or the primary monitor if none does.
+pub(crate) fn monitor_at(monitors: &[MonitorInfo], point: Point) -> Option<&MonitorInfo> {
+    monitors
+        .iter()
+        .find(|m| m.rect.contains(point))
+        .or_else(|| monitors.iter().find(|m| m.primary))
+        .or_else(|| monitors.first())
+}
+
+/// Queries the monitors of the screen with the root window `root`.
+///
+/// This uses RandR 1.5 monitors if they are available, then the CRTCs of older
+/// RandR versions, and finally falls back to treating the whole screen as one monitor.
+pub(crate) fn query_monitors(
+    conn: &XCBConnection,
+    screen_num: usize,
+    rdb: &ResourceDb,
+) -> Result<Vec<MonitorInfo>, Error> {
+    let screen = conn
+        .setup()
+        .roots
+        .get(screen_num)
+        .ok_or_else(|| anyhow!("invalid screen num: {}", screen_num))?;
+
+    let mut monitors = match randr_monitors(conn, screen.root) {
+        Ok(monitors) => monitors,
+        Err(e) => {
+            tracing::info!("failed to get RandR monitors, trying CRTCs: {:#}", e);
+            randr_crtcs(conn, screen.root).unwrap_or_else(|e| {
+                tracing::info!("failed to get RandR CRTCs: {:#}", e);
+                Vec::new()
+            })
+        }
+    };
+    if monitors.is_empty() {
+        let rect = Rect::new(
+            0.,
+            0.,
+            screen.width_in_pixels as f64,
+            screen.height_in_pixels as f64,
+        );
+        monitors.push(MonitorInfo {
+            primary: true,
+            rect,
+            work_rect: rect,
+            width_mm: screen.width_in_millimeters.into(),
+            scale: Scale::default(),
+        });
+    }
+
+    match work_area(conn, screen.root) {
+        Ok(Some(area)) => {
+            for m in &mut monitors {
+                let work_rect = m.rect.intersect(area);
+                if work_rect.area() > 0. {
+                    m.work_rect = work_rect;
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::info!("failed to get the work area: {:#}", e),
+    }
+
+    assign_scales(&mut monitors, configured_dpi(rdb), use_physical_dpi());
+    Ok(monitors)
+}
+
+fn randr_monitors(conn: &XCBConnection, root: Window) -> Result<Vec<MonitorInfo>, Error> {
+    let reply = conn
+        .randr_get_monitors(root, true)?
+        .reply()
+        .context("get RandR monitors")?;
+    Ok(reply
+        .monitors
+        .iter()
+        .map(|m| {
+            let rect = Rect::new(
+                m.x as f64,
+                m.y as f64,
+                m.x as f64 + m.width as f64,
+                m.y as f64 + m.height as f64,
+            );
+            MonitorInfo {
+                primary: m.primary,
+                rect,
+                work_rect: rect,
+                width_mm: m.width_in_millimeters,
+                scale: Scale::default(),
+            }
+        })
+        .collect())
+}
+
+fn randr_crtcs(conn: &XCBConnection, root: Window) -> Result<Vec<MonitorInfo>, Error> {
+    let resources = conn
+        .randr_get_screen_resources_current(root)?
+        .reply()
+        .context("get RandR screen resources")?;
+    let primary = conn
+        .randr_get_output_primary(root)?
+        .reply()
+        .context("get RandR primary output")?
+        .output;
+
+    let mut monitors = Vec::new();
+    for &crtc in &resources.crtcs {
+        let info = conn
+            .randr_get_crtc_info(crtc, resources.config_timestamp)?
+            .reply()
+            .context("get RandR CRTC info")?;
+        if info.width == 0 || info.height == 0 {
+            // This CRTC is not in use.
+            continue;
+        }
+        let width_mm = match info.outputs.first() {
+            Some(&output) => {
+                conn.randr_get_output_info(output, resources.config_timestamp)?
+                    .reply()
+                    .context("get RandR output info")?
+                    .mm_width
+            }
+            None => 0,
+        };
+        let rect = Rect::new(
+            info.x as f64,
+            info.y as f64,
+            info.x as f64 + info.width as f64,
+            info.y as f64 + info.height as f64,
+        );
+        monitors.push(MonitorInfo {
+            primary: info.outputs.contains(&primary),
+            rect,
+            work_rect: rect,
+            width_mm,
+            scale: Scale::default(),
+        });
+    }
+    Ok(monitors)
+}
+
+/// The EWMH work area of the current desktop.
+///
+/// This is a single rectangle for the whole screen, so on multiple monitors we can only
+/// intersect it with each of them.
+fn work_area(conn: &XCBConnection, root: Window) -> Result<Option<Rect>, Error> {
+    let workarea = conn.intern_atom(false, b"_NET_WORKAREA")?;
+    let current_desktop = conn.intern_atom(false, b"_NET_CURRENT_DESKTOP")?;
+    let workarea = workarea.reply().context("intern _NET_WORKAREA")?.atom;
+    let current_desktop = current_desktop
+        .reply()
+        .context("intern _NET_CURRENT_DESKTOP")?
+        .atom;
+
+    let desktop = conn
+        .get_property(false, root, current_desktop, AtomEnum::CARDINAL, 0, 1)?
+        .reply()
+        .context("get _NET_CURRENT_DESKTOP")?
+        .value32()
+        .and_then(|mut values| values.next())
+        .unwrap_or(0);
+    let areas = conn
+        .get_property(false, root, workarea, AtomEnum::CARDINAL, 0, u32::MAX)?
+        .reply()
+        .context("get _NET_WORKAREA")?;
+    let values: Vec<u32> = match areas.value32() {
+        Some(values) => values.collect(),
+        None => return Ok(None),
+    };
+    Ok(values.chunks_exact(4).nth(desktop as usize).map(|area| {
+        let (x, y) = (area[0] as f64, area[1] as f64);
+        Rect::new(x, y, x + area[2] as f64, y + area[3] as f64)
+    }))
+}
+
+/// The DPI that the user asked for, either with `DRUID_X11_DPI` or with `Xft.dpi`.
+///
+/// The `bool` is true if it came from `DRUID_X11_DPI`, which applies to all monitors.
+fn configured_dpi(rdb: &ResourceDb) -> Option<(f64, bool)> {
+    if let Ok(dpi) = std::env::var("DRUID_X11_DPI") {
+        match dpi.parse::<f64>() {
+            Ok(dpi) => return Some((dpi, true)),
+            Err(err) => tracing::warn!("Unable to parse DRUID_X11_DPI {:?}: {}", dpi, err),
+        }
+    }
+    match rdb.get_value::<f64>("Xft.dpi", "") {
+        Ok(dpi) => dpi.map(|dpi| (dpi, false)),
+        Err(err) => {
+            tracing::warn!("Unable to parse Xft.dpi: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Whether the user opted into scaling by the physical size of the monitors,
+/// by setting `DRUID_X11_PHYSICAL_DPI=1`.
+///
+/// The sizes reported by monitors are often wrong, so this is off by default.
+fn use_physical_dpi() -> bool {
+    match std::env::var("DRUID_X11_PHYSICAL_DPI") {
+        Ok(value) => value == "1" || value.eq_ignore_ascii_case("true"),
+        Err(_) => false,
+    }
+}
+
+/// The DPI of the monitor from its physical size, if that is known and plausible.
+fn physical_dpi(monitor: &MonitorInfo) -> Option<f64> {
+    if monitor.width_mm == 0 {
+        return None;
+    }
+    let dpi = monitor.rect.width() * 25.4 / monitor.width_mm as f64;
+    // Projectors and broken EDIDs report all sorts of sizes.
+    if (48.0..=480.0).contains(&dpi) {
+        Some(dpi)
+    } else {
+        None
+    }
+}
+
+/// Picks the scale of each monitor.
+///
+/// By default every monitor uses the configured DPI, or a scale of 1 without one.
+///
+/// With `use_physical`, `Xft.dpi` is taken to describe only the primary monitor, and
+/// the others use their physical DPI where we know it.
+fn assign_scales(
+    monitors: &mut [MonitorInfo],
+    configured: Option<(f64, bool)>,
+    use_physical: bool,
+) {
+    for m in monitors.iter_mut() {
+        let physical = if use_physical { physical_dpi(m) } else { None };
+        let scale = match (configured, physical) {
+            (Some((dpi, from_env)), _) if from_env || m.primary => dpi / 96.,
+            // Physical sizes are imprecise, and anything below 1 is more likely a big
+            // display that is viewed from further away.
+            (_, Some(own)) => (own / 96.).max(1.),
+            (Some((dpi, _)), None) => dpi / 96.,
+            (None, None) => 1.,
+        };
+        // Rounding to quarters avoids blurry scales like 1.07.
+        let scale = ((scale * 4.).round() / 4.).max(0.25);
+        m.scale = Scale::new(scale, scale);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn monitor(primary: bool, width: f64, width_mm: u32) -> MonitorInfo {
+        let rect = Rect::new(0., 0., width, width * 9. / 16.);
+        MonitorInfo {
+            primary,
+            rect,
+            work_rect: rect,
+            width_mm,
+            scale: Scale::default(),
+        }
+    }
+
+    #[test]
+    fn xft_dpi_applies_to_all_monitors() {
+        // A 14" 4K laptop panel and a 24" 1080p display.
+        let mut monitors = [monitor(true, 3840., 310), monitor(false, 1920., 530)];
+        assign_scales(&mut monitors, Some((192., false)), false);
+        assert_eq!(monitors[0].scale, Scale::new(2., 2.));
+        assert_eq!(monitors[1].scale, Scale::new(2., 2.));
+
+        assign_scales(&mut monitors, None, false);
+        assert_eq!(monitors[0].scale, Scale::new(1., 1.));
+        assert_eq!(monitors[1].scale, Scale::new(1., 1.));
+    }
+
+    #[test]
+    fn physical_dpi_applies_to_other_monitors() {
+        let mut monitors = [monitor(true, 3840., 310), monitor(false, 1920., 530)];
+        assign_scales(&mut monitors, Some((192., false)), true);
+        assert_eq!(monitors[0].scale, Scale::new(2., 2.));
+        assert_eq!(monitors[1].scale, Scale::new(1., 1.));
+
+        assign_scales(&mut monitors, None, true);
+        assert_eq!(monitors[0].scale, Scale::new(3.25, 3.25));
+        assert_eq!(monitors[1].scale, Scale::new(1., 1.));
+
+        assign_scales(&mut monitors, Some((144., true)), true);
+        assert_eq!(monitors[1].scale, Scale::new(1.5, 1.5));
+    }
+
+    #[test]
+    fn monitor_at_falls_back_to_primary() {
+        let mut left = monitor(false, 1920., 0);
+        let mut right = monitor(true, 1920., 0);
+        right.rect = right.rect.with_origin((1920., 0.));
+        left.scale = Scale::new(2., 2.);
+        let monitors = [left, right];
+        assert_eq!(
+            monitor_at(&monitors, Point::new(100., 100.)).unwrap().scale,
+            Scale::new(2., 2.)
+        );
+        assert!(
+            monitor_at(&monitors, Point::new(-50., -50.))
+                .unwrap()
+                .primary
+        );
+    }
 }
@@ -18,11 +18,15 @@ use std::cmp::Ordering;
 use std::rc::Rc;
 use std::time::Instant;
 
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, Context, Error};
 use x11rb::protocol::randr::{ConnectionExt, ModeFlag};
-use x11rb::protocol::xproto::{Screen, Visualtype, Window};
+use x11rb::protocol::xproto::{
+    Atom, ClientMessageData, ClientMessageEvent, ConnectionExt as _, Screen, Visualtype, Window,
+    CLIENT_MESSAGE_EVENT,
+};
 use x11rb::xcb_ffi::XCBConnection;
 
+use crate::piet::Color;
 use crate::window::TimerToken;
 
 // See: https://github.com/rtbo/rust-xcb/blob/master/examples/randr_screen_modes.rs
@@ -68,6 +72,47 @@ pub fn refresh_rate(conn: &Rc<XCBConnection>, window_id: Window) -> Option<f64>
     }
 }
 
+/// Returns the names of `atoms`, such as the formats of a selection.
+pub fn atom_names(conn: &XCBConnection, atoms: &[Atom]) -> Result<Vec<String>, Error> {
+    // send all the requests before waiting for the first reply.
+    let cookies = atoms
+        .iter()
+        .map(|&atom| conn.get_atom_name(atom))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut names = Vec::with_capacity(cookies.len());
+    for cookie in cookies {
+        let reply = cookie.reply().context("get atom name")?;
+        names.push(String::from_utf8_lossy(&reply.name).into_owned());
+    }
+    Ok(names)
+}
+
+/// The locale that text is entered in, picked from the environment the way `setlocale` does.
+pub fn locale() -> String {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "C".into())
+}
+
+/// A client message of the given `format` (8, 16 or 32 bits), ready to be sent with `send_event`.
+pub fn client_message(
+    format: u8,
+    window: Window,
+    type_: Atom,
+    data: impl Into<ClientMessageData>,
+) -> ClientMessageEvent {
+    ClientMessageEvent {
+        response_type: CLIENT_MESSAGE_EVENT,
+        format,
+        sequence: 0,
+        window,
+        type_,
+        data: data.into(),
+    }
+}
+
 // Apparently you have to get the visualtype this way :|
 pub fn get_visual_from_screen(screen: &Screen) -> Option<Visualtype> {
     for depth in &screen.allowed_depths {
@@ -80,6 +125,16 @@ pub fn get_visual_from_screen(screen: &Screen) -> Option<Visualtype> {
     None
 }
 
+/// The pixel value of `color` in a TrueColor `visual`, ignoring alpha.
+pub fn color_to_pixel(visual: &Visualtype, color: &Color) -> u32 {
+    let (r, g, b, _) = color.as_rgba8();
+    let channel = |value: u8, mask: u32| {
+        let shift = mask.trailing_zeros();
+        (value as u32 * (mask >> shift) / 255) << shift
+    };
+    channel(r, visual.red_mask) | channel(g, visual.green_mask) | channel(b, visual.blue_mask)
+}
+
 macro_rules! log_x11 {
     ($val:expr) => {
         if let Err(e) = $val {
@@ -28,8 +28,9 @@ use anyhow::{anyhow, Context, Error};
 use cairo::{XCBConnection as CairoXCBConnection, XCBDrawable, XCBSurface, XCBVisualType};
 use tracing::{error, info, warn};
 use x11rb::atom_manager;
-use x11rb::connection::Connection;
+use x11rb::connection::{Connection, RequestConnection};
 use x11rb::protocol::present::{CompleteNotifyEvent, ConnectionExt as _, IdleNotifyEvent};
+use x11rb::protocol::render::{ConnectionExt as _, CreatePictureAux, PictType, Pictformat};
 use x11rb::protocol::xfixes::{ConnectionExt as _, Region as XRegion};
 use x11rb::protocol::xproto::{
     self, AtomEnum, ChangeWindowAttributesAux, ConfigureNotifyEvent, ConnectionExt, CreateGCAux,
@@ -41,24 +42,30 @@ use x11rb::xcb_ffi::XCBConnection;
 #[cfg(feature = "raw-win-handle")]
 use raw_window_handle::{unix::XcbHandle, HasRawWindowHandle, RawWindowHandle};
 
+use crate::clipboard::Clipboard;
 use crate::common_util::IdleCallback;
-use crate::dialog::FileDialogOptions;
+use crate::dialog::{FileDialogOptions, FileDialogType};
+use crate::drag::{DropEvent, FilePromise};
 use crate::error::Error as ShellError;
-use crate::keyboard::{KeyEvent, KeyState, Modifiers};
+use crate::hotkey::HotKey;
+use crate::keyboard::{KbKey, KeyEvent, KeyState, Modifiers};
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
+use crate::piet::{Color, ImageBuf, Piet, PietText, RenderContext};
 use crate::region::Region;
 use crate::scale::Scale;
-use crate::text::{simulate_input, Event};
+use crate::text::{simulate_input, Event, Selection};
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    FileDialogToken, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 use crate::{window, ScaledArea};
 
 use super::application::Application;
+#[cfg(feature = "xdg-portal")]
+use super::dialog;
 use super::keycodes;
-use super::menu::Menu;
+use super::menu::{Menu, MenuAction, MenuPopup};
+use super::screen;
 use super::util::{self, Timer};
 
 /// A version of XCB's `xcb_visualtype_t` struct. This was copied from the [example] in x11rb; it
@@ -96,15 +103,49 @@ impl From<Visualtype> for xcb_visualtype_t {
     }
 }
 
+/// Creates a cairo surface of `size` pixels that draws to `drawable`, which has the visual
+/// `visual_type`.
+pub(super) fn create_cairo_surface(
+    conn: &XCBConnection,
+    drawable: u32,
+    visual_type: &Visualtype,
+    size: Size,
+) -> Result<XCBSurface, Error> {
+    let cairo_xcb_connection = unsafe {
+        CairoXCBConnection::from_raw_none(
+            conn.get_raw_xcb_connection() as *mut cairo_sys::xcb_connection_t
+        )
+    };
+    let cairo_drawable = XCBDrawable(drawable);
+    let mut xcb_visual = xcb_visualtype_t::from(*visual_type);
+    let cairo_visual_type = unsafe {
+        XCBVisualType::from_raw_none(
+            &mut xcb_visual as *mut xcb_visualtype_t as *mut cairo_sys::xcb_visualtype_t,
+        )
+    };
+    let cairo_surface = XCBSurface::create(
+        &cairo_xcb_connection,
+        &cairo_drawable,
+        &cairo_visual_type,
+        size.width as i32,
+        size.height as i32,
+    )
+    .map_err(|status| anyhow!("Failed to create cairo surface: {}", status))?;
+    Ok(cairo_surface)
+}
+
 pub(crate) struct WindowBuilder {
     app: Application,
     handler: Option<Box<dyn WinHandler>>,
     title: String,
     size: Size,
-
-    // TODO: implement min_size for X11
-    #[allow(dead_code)]
+    background_color: Option<Color>,
     min_size: Size,
+    resizable: bool,
+    show_titlebar: bool,
+    state: WmState,
+    icon: Option<ImageBuf>,
+    menu: Option<Menu>,
 }
 
 impl WindowBuilder {
@@ -114,7 +155,13 @@ impl WindowBuilder {
             handler: None,
             title: String::new(),
             size: Size::new(500.0, 400.0),
+            background_color: None,
             min_size: Size::new(0.0, 0.0),
+            resizable: true,
+            show_titlebar: true,
+            state: WmState::default(),
+            icon: None,
+            menu: None,
         }
     }
 
@@ -127,22 +174,25 @@ impl WindowBuilder {
     }
 
     pub fn set_min_size(&mut self, min_size: Size) {
-        warn!("WindowBuilder::set_min_size is implemented, but the setting is currently unused for X11 platforms.");
         self.min_size = min_size;
     }
 
-    pub fn resizable(&mut self, _resizable: bool) {
-        warn!("WindowBuilder::resizable is currently unimplemented for X11 platforms.");
+    pub fn resizable(&mut self, resizable: bool) {
+        self.resizable = resizable;
     }
 
-    pub fn show_titlebar(&mut self, _show_titlebar: bool) {
-        warn!("WindowBuilder::show_titlebar is currently unimplemented for X11 platforms.");
+    pub fn show_titlebar(&mut self, show_titlebar: bool) {
+        self.show_titlebar = show_titlebar;
     }
 
     pub fn set_transparent(&mut self, _transparent: bool) {
         // Ignored
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_position(&mut self, _position: Point) {
         warn!("WindowBuilder::set_position is currently unimplemented for X11 platforms.");
     }
@@ -151,16 +201,28 @@ impl WindowBuilder {
         warn!("WindowBuilder::set_level  is currently unimplemented for X11 platforms.");
     }
 
-    pub fn set_window_state(&self, _state: window::WindowState) {
-        warn!("WindowBuilder::set_window_state is currently unimplemented for X11 platforms.");
+    pub fn set_window_state(&mut self, state: window::WindowState) {
+        self.state.set_window_state(state);
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.state.above = always_on_top;
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.state.fullscreen = fullscreen;
+    }
+
+    pub fn set_icon(&mut self, icon: ImageBuf) {
+        self.icon = Some(icon);
     }
 
     pub fn set_title<S: Into<String>>(&mut self, title: S) {
         self.title = title.into();
     }
 
-    pub fn set_menu(&mut self, _menu: Menu) {
-        // TODO(x11/menus): implement WindowBuilder::set_menu (currently a no-op)
+    pub fn set_menu(&mut self, menu: Menu) {
+        self.menu = Some(menu);
     }
 
     /// Registers and returns all the atoms that the window will need.
@@ -182,6 +244,17 @@ impl WindowBuilder {
         .check()
         .context("set WM_PROTOCOLS")?;
 
+        // Accept drops, with version 5 of the XDND protocol.
+        conn.change_property32(
+            PropMode::REPLACE,
+            window_id,
+            atoms.XdndAware,
+            AtomEnum::ATOM,
+            &[XDND_VERSION],
+        )?
+        .check()
+        .context("set XdndAware")?;
+
         Ok(atoms)
     }
 
@@ -190,75 +263,48 @@ impl WindowBuilder {
         window_id: u32,
         visual_type: &Visualtype,
     ) -> Result<XCBSurface, Error> {
-        let conn = self.app.connection();
-        let cairo_xcb_connection = unsafe {
-            CairoXCBConnection::from_raw_none(
-                conn.get_raw_xcb_connection() as *mut cairo_sys::xcb_connection_t
-            )
-        };
-        let cairo_drawable = XCBDrawable(window_id);
-        let mut xcb_visual = xcb_visualtype_t::from(*visual_type);
-        let cairo_visual_type = unsafe {
-            XCBVisualType::from_raw_none(
-                &mut xcb_visual as *mut xcb_visualtype_t as *mut cairo_sys::xcb_visualtype_t,
-            )
-        };
-        let cairo_surface = XCBSurface::create(
-            &cairo_xcb_connection,
-            &cairo_drawable,
-            &cairo_visual_type,
-            self.size.width as i32,
-            self.size.height as i32,
-        )
-        .map_err(|status| anyhow!("Failed to create cairo surface: {}", status))?;
-        Ok(cairo_surface)
+        create_cairo_surface(self.app.connection(), window_id, visual_type, self.size)
     }
 
-    // TODO(x11/menus): make menus if requested
     pub fn build(self) -> Result<WindowHandle, Error> {
         let conn = self.app.connection();
         let screen_num = self.app.screen_num();
         let id = conn.generate_id()?;
         let setup = conn.setup();
 
-        let env_dpi = std::env::var("DRUID_X11_DPI")
-            .ok()
-            .map(|x| x.parse::<f64>());
-
-        let scale = match env_dpi.or_else(|| self.app.rdb.get_value("Xft.dpi", "").transpose()) {
-            Some(Ok(dpi)) => {
-                let scale = dpi / 96.;
-                Scale::new(scale, scale)
-            }
-            None => Scale::default(),
-            Some(Err(err)) => {
-                let default = Scale::default();
-                warn!(
-                    "Unable to parse dpi: {:?}, defaulting to {:?}",
-                    err, default
-                );
-                default
-            }
-        };
-
-        let size_px = self.size.to_px(scale);
         let screen = setup
             .roots
             .get(screen_num as usize)
             .ok_or_else(|| anyhow!("Invalid screen num: {}", screen_num))?;
+        // We don't know where the window manager will put the window yet, but it's usually on
+        // the monitor with the pointer. We correct the scale once the window is mapped.
+        let pointer = conn
+            .query_pointer(screen.root)?
+            .reply()
+            .context("query pointer")?;
+        let scale = self
+            .app
+            .scale_at(Point::new(pointer.root_x as f64, pointer.root_y as f64))?;
+        let size_px = self.size.to_px(scale);
         let visual_type = util::get_visual_from_screen(&screen)
             .ok_or_else(|| anyhow!("Couldn't get visual from screen"))?;
         let visual_id = visual_type.visual_id;
 
-        let cw_values = xproto::CreateWindowAux::new().event_mask(
+        let mut cw_values = xproto::CreateWindowAux::new().event_mask(
             EventMask::EXPOSURE
                 | EventMask::STRUCTURE_NOTIFY
+                | EventMask::PROPERTY_CHANGE
                 | EventMask::KEY_PRESS
                 | EventMask::KEY_RELEASE
                 | EventMask::BUTTON_PRESS
                 | EventMask::BUTTON_RELEASE
-                | EventMask::POINTER_MOTION,
+                | EventMask::POINTER_MOTION
+                | EventMask::FOCUS_CHANGE,
         );
+        if let Some(color) = &self.background_color {
+            // the server fills exposed areas with this before we paint them.
+            cw_values = cw_values.background_pixel(util::color_to_pixel(&visual_type, color));
+        }
 
         // Create the actual window
         let (width_px, height_px) = (size_px.width as u16, size_px.height as u16);
@@ -338,6 +384,14 @@ impl WindowBuilder {
         let window = Rc::new(Window {
             id,
             gc,
+            visual_type,
+            resize_increments: Cell::new(None),
+            aspect_ratio: Cell::new(None),
+            min_size: Cell::new(self.min_size),
+            resizable: Cell::new(self.resizable),
+            show_titlebar: Cell::new(self.show_titlebar),
+            wm_state: Cell::new(self.state),
+            mapped: Cell::new(false),
             app: self.app.clone(),
             handler,
             cairo_surface,
@@ -350,10 +404,19 @@ impl WindowBuilder {
             idle_queue: Arc::new(Mutex::new(Vec::new())),
             idle_pipe: self.app.idle_pipe(),
             present_data: RefCell::new(present_data),
+            rendering: Cell::new(false),
             buffers,
             active_text_field: Cell::new(None),
+            xdnd: RefCell::new(None),
+            menu: RefCell::new(self.menu),
+            menu_popup: RefCell::new(None),
         });
         window.set_title(&self.title);
+        window.update_normal_hints();
+        window.update_motif_hints();
+        if let Some(icon) = &self.icon {
+            window.set_icon(icon);
+        }
 
         let handle = WindowHandle::new(id, Rc::downgrade(&window));
         window.connect(handle.clone())?;
@@ -417,6 +480,21 @@ impl WindowBuilder {
 pub(crate) struct Window {
     id: u32,
     gc: Gcontext,
+    visual_type: Visualtype,
+    /// The steps that the window is resized in, in display points.
+    resize_increments: Cell<Option<Size>>,
+    /// The width to height ratio that the window is kept at.
+    aspect_ratio: Cell<Option<(u32, u32)>>,
+    /// The smallest size of the window, in display points.
+    min_size: Cell<Size>,
+    resizable: Cell<bool>,
+    show_titlebar: Cell<bool>,
+    /// Before the window is mapped, this is the state that we will ask for. Afterwards, it is the
+    /// state that the window manager reports in `_NET_WM_STATE` and `WM_STATE`.
+    wm_state: Cell<WmState>,
+    /// Whether `show` was called. Until then, we can set the state properties directly; afterwards
+    /// we have to ask the window manager to change them.
+    mapped: Cell<bool>,
     app: Application,
     handler: RefCell<Box<dyn WinHandler>>,
     cairo_surface: RefCell<XCBSurface>,
@@ -441,30 +519,135 @@ pub(crate) struct Window {
     /// get drawn at the next vblank, and some time shortly after that we'll get a notification
     /// that the drawing was completed.
     ///
-    /// There are three ways that rendering can get triggered:
+    /// We only ever render in response to a `CompleteNotifyEvent`, so that painting is locked to
+    /// the refresh rate of the monitor:
     /// 1) We render a frame, and it signals to us that an animation is requested. In this case, we
-    ///     will render the next frame as soon as we get a notification that the just-presented
-    ///     frame completed. In other words, we use `CompleteNotifyEvent` to schedule rendering.
-    /// 2) We get an expose event telling us that a region got invalidated. In
-    ///    this case, we will render the next frame immediately unless we're already waiting for a
-    ///    completion notification. (If we are waiting for a completion notification, we just make
-    ///    a note to schedule a new frame once we get it.)
-    /// 3) Someone calls `invalidate` or `invalidate_rect` on us. We schedule ourselves to repaint
-    ///    in the idle loop. This is better than rendering straight away, because for example they
-    ///    might have called `invalidate` from their paint callback, and then we'd end up painting
-    ///    re-entrantively.
+    ///    will render the next frame as soon as we get a notification that the just-presented
+    ///    frame completed.
+    /// 2) We get an expose event, someone calls `invalidate` or `invalidate_rect` on us, or an
+    ///    animation frame is requested while no frame is being presented. In this case, we ask X
+    ///    to notify us at the next vblank with `NotifyMSC`, and render when that completes. If we
+    ///    are already waiting for a completion notification, we just make a note to render once
+    ///    we get it.
     ///
-    /// This is probably not the best (or at least, not the lowest-latency) scheme we can come up
-    /// with, because invalidations that happen shortly after a vblank might need to wait 2 frames
-    /// before they appear. If we're getting lots of invalidations, it might be better to render more
-    /// than once per frame. Note that if we do, it will require some changes to part 1) above,
-    /// because if we render twice in a frame then we will get two completion notifications in a
-    /// row, so we don't want to present on both of them. The `msc` field of the completion
-    /// notification might be useful here, because it allows us to check how many frames have
-    /// actually been presented.
+    /// Either way, there is at most one request in flight, so we never render more than once per
+    /// frame. The cost is latency: an invalidation that happens shortly after a vblank waits for
+    /// the next one before it is rendered, and for the one after that before it appears.
+    ///
+    /// When this is `None`, we render from the idle loop, which the application runs at the
+    /// refresh rate of the screen.
     present_data: RefCell<Option<PresentData>>,
+    /// Set while we are rendering, so that frames that the handler requests in the meantime are
+    /// picked up when the present of this frame completes.
+    rendering: Cell<bool>,
     buffers: RefCell<Buffers>,
     active_text_field: Cell<Option<TextFieldToken>>,
+    /// Something that is being dragged over the window, from another window.
+    xdnd: RefCell<Option<XdndState>>,
+    /// The window menu, whose hotkeys we handle and which F10 opens.
+    menu: RefCell<Option<Menu>>,
+    /// The menu that is open, if any. It holds the pointer and keyboard grabs.
+    menu_popup: RefCell<Option<MenuPopup>>,
+}
+
+/// The version of the XDND protocol that we support.
+const XDND_VERSION: u32 = 5;
+
+/// The state of an XDND drag over the window.
+///
+/// https://freedesktop.org/wiki/Specifications/XDND/
+struct XdndState {
+    /// The window that the drag comes from.
+    source: u32,
+    /// The names of the formats that the data is available in.
+    formats: Vec<String>,
+    /// The last position of the mouse, in display points.
+    pos: Point,
+    /// Whether the handler accepted a drop at `pos`.
+    accepted: bool,
+}
+
+/// The `WM_SIZE_HINTS` for a window, in the layout of the ICCCM.
+///
+/// Sizes are in pixels. A window with a `fixed_size` can't be resized, and otherwise it can't
+/// be made smaller than `min_size`.
+fn size_hints(
+    min_size: Size,
+    fixed_size: Option<Size>,
+    increments: Option<Size>,
+    aspect_ratio: Option<(u32, u32)>,
+) -> [u32; 18] {
+    const P_MIN_SIZE: u32 = 1 << 4;
+    const P_MAX_SIZE: u32 = 1 << 5;
+    const P_RESIZE_INC: u32 = 1 << 6;
+    const P_ASPECT: u32 = 1 << 7;
+    let mut hints = [0u32; 18];
+    if let Some(size) = fixed_size {
+        // the window manager keeps the window at its current size
+        hints[0] |= P_MIN_SIZE | P_MAX_SIZE;
+        hints[5] = size.width as u32;
+        hints[6] = size.height as u32;
+        hints[7] = size.width as u32;
+        hints[8] = size.height as u32;
+    } else if min_size.width > 0.0 || min_size.height > 0.0 {
+        hints[0] |= P_MIN_SIZE;
+        hints[5] = min_size.width.ceil() as u32;
+        hints[6] = min_size.height.ceil() as u32;
+    }
+    if let Some(increments) = increments {
+        hints[0] |= P_RESIZE_INC;
+        hints[9] = (increments.width.round() as u32).max(1);
+        hints[10] = (increments.height.round() as u32).max(1);
+    }
+    if let Some((numerator, denominator)) = aspect_ratio {
+        hints[0] |= P_ASPECT;
+        hints[11] = numerator;
+        hints[12] = denominator;
+        hints[13] = numerator;
+        hints[14] = denominator;
+    }
+    hints
+}
+
+/// The states from `WM_STATE` in the ICCCM.
+const NORMAL_STATE: u32 = 1;
+const ICONIC_STATE: u32 = 3;
+
+/// The state of a window, as far as the window manager is concerned.
+///
+/// https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407959456
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct WmState {
+    maximized: bool,
+    minimized: bool,
+    fullscreen: bool,
+    above: bool,
+}
+
+impl WmState {
+    fn set_window_state(&mut self, state: window::WindowState) {
+        match state {
+            window::WindowState::Maximized => {
+                self.maximized = true;
+                self.minimized = false;
+            }
+            window::WindowState::Minimized => self.minimized = true,
+            window::WindowState::Restored => {
+                self.maximized = false;
+                self.minimized = false;
+            }
+        }
+    }
+
+    fn window_state(self) -> window::WindowState {
+        if self.minimized {
+            window::WindowState::Minimized
+        } else if self.maximized {
+            window::WindowState::Maximized
+        } else {
+            window::WindowState::Restored
+        }
+    }
 }
 
 // This creates a `struct WindowAtoms` containing the specified atoms as members (along with some
@@ -502,6 +685,40 @@ pub(crate) struct Window {
 // UTF8_STRING
 //
 // The type of _NET_WM_NAME
+//
+// XdndAware, XdndEnter, XdndPosition, XdndStatus, XdndLeave, XdndDrop, XdndFinished,
+// XdndTypeList, XdndActionCopy
+//
+// The property that advertises which version of the drag-and-drop protocol a window supports,
+// the client messages of the protocol, the list of formats for drags with more than three, and
+// the action that we accept.
+//
+// https://freedesktop.org/wiki/Specifications/XDND/
+//
+// _NET_WM_STATE, _NET_WM_STATE_MAXIMIZED_VERT, _NET_WM_STATE_MAXIMIZED_HORZ,
+// _NET_WM_STATE_FULLSCREEN, _NET_WM_STATE_ABOVE
+//
+// The states of a window that the window manager keeps, and the message type for asking it to
+// change them.
+//
+// https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407959456
+//
+// WM_STATE, WM_CHANGE_STATE
+//
+// The ICCCM state of a window (which tells us whether it is iconic), and the message for asking
+// the window manager to iconify it.
+//
+// https://www.x.org/releases/X11R7.6/doc/xorg-docs/specs/ICCCM/icccm.html#changing_window_state
+//
+// _NET_WM_ICON
+//
+// The icon of the window, as ARGB pixels.
+//
+// https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407920176
+//
+// _MOTIF_WM_HINTS
+//
+// The Motif hints, which are the de facto way of asking for a window without decorations.
 atom_manager! {
     WindowAtoms: WindowAtomsCookie {
         WM_PROTOCOLS,
@@ -509,6 +726,24 @@ atom_manager! {
         _NET_WM_PID,
         _NET_WM_NAME,
         UTF8_STRING,
+        XdndAware,
+        XdndEnter,
+        XdndPosition,
+        XdndStatus,
+        XdndLeave,
+        XdndDrop,
+        XdndFinished,
+        XdndTypeList,
+        XdndActionCopy,
+        _NET_WM_STATE,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_WM_STATE_ABOVE,
+        WM_STATE,
+        WM_CHANGE_STATE,
+        _NET_WM_ICON,
+        _MOTIF_WM_HINTS,
     }
 }
 
@@ -552,7 +787,8 @@ struct PresentData {
     serial: u32,
     /// The region that we use for telling X what to present.
     region: XRegion,
-    /// Did we submit a present that hasn't completed yet? If so, this is its serial number.
+    /// Did we submit a present or a notify request that hasn't completed yet? If so, this is
+    /// its serial number.
     waiting_on: Option<u32>,
     /// We need to render another frame as soon as the current one is done presenting.
     needs_present: bool,
@@ -566,8 +802,27 @@ struct PresentData {
     last_ust: Option<u64>,
 }
 
-#[derive(Clone, PartialEq)]
-pub struct CustomCursor(xproto::Cursor);
+/// A cursor made from an image, which is freed when the last clone is dropped.
+#[derive(Clone)]
+pub struct CustomCursor(Rc<CursorHandle>);
+
+struct CursorHandle {
+    conn: Rc<XCBConnection>,
+    cursor: xproto::Cursor,
+}
+
+impl PartialEq for CustomCursor {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Drop for CursorHandle {
+    fn drop(&mut self) {
+        // The server keeps the cursor for as long as a window still uses it.
+        log_x11!(self.conn.free_cursor(self.cursor));
+    }
+}
 
 impl Window {
     #[track_caller]
@@ -612,6 +867,7 @@ impl Window {
     /// Start the destruction of the window.
     pub fn destroy(&self) {
         if !self.destroyed() {
+            self.close_menu();
             self.destroyed.set(true);
             log_x11!(self.app.connection().destroy_window(self.id));
         }
@@ -677,6 +933,14 @@ impl Window {
     }
 
     fn render(&self) -> Result<(), Error> {
+        self.set_needs_present(false)?;
+        self.rendering.set(true);
+        let result = self.paint_and_present();
+        self.rendering.set(false);
+        result
+    }
+
+    fn paint_and_present(&self) -> Result<(), Error> {
         self.with_handler(|h| h.prepare_paint());
 
         if self.destroyed() {
@@ -727,8 +991,6 @@ impl Window {
             err?;
         }
 
-        self.set_needs_present(false)?;
-
         let mut buffers = borrow_mut!(self.buffers)?;
         let pixmap = *buffers
             .idle_pixmaps
@@ -752,9 +1014,205 @@ impl Window {
     }
 
     fn show(&self) {
-        if !self.destroyed() {
+        if self.destroyed() {
+            return;
+        }
+        if !self.mapped.replace(true) {
+            // The window manager reads these when the window is mapped for the first time.
+            self.write_initial_state();
+        }
+        log_x11!(self.app.connection().map_window(self.id));
+    }
+
+    /// Set the `_NET_WM_STATE` and `WM_HINTS` properties that the window starts out with.
+    fn write_initial_state(&self) {
+        let conn = self.app.connection();
+        let atoms = &self.atoms;
+        let state = self.wm_state.get();
+        let mut net_state = Vec::new();
+        if state.maximized {
+            net_state.push(atoms._NET_WM_STATE_MAXIMIZED_VERT);
+            net_state.push(atoms._NET_WM_STATE_MAXIMIZED_HORZ);
+        }
+        if state.fullscreen {
+            net_state.push(atoms._NET_WM_STATE_FULLSCREEN);
+        }
+        if state.above {
+            net_state.push(atoms._NET_WM_STATE_ABOVE);
+        }
+        log_x11!(conn.change_property32(
+            PropMode::REPLACE,
+            self.id,
+            atoms._NET_WM_STATE,
+            AtomEnum::ATOM,
+            &net_state,
+        ));
+
+        // the layout of WM_HINTS, from the ICCCM: we take input, and start out either normal or
+        // iconic.
+        const INPUT_HINT: u32 = 1;
+        const STATE_HINT: u32 = 1 << 1;
+        let initial_state = if state.minimized {
+            ICONIC_STATE
+        } else {
+            NORMAL_STATE
+        };
+        let hints = [INPUT_HINT | STATE_HINT, 1, initial_state, 0, 0, 0, 0, 0, 0];
+        log_x11!(conn.change_property32(
+            PropMode::REPLACE,
+            self.id,
+            AtomEnum::WM_HINTS,
+            AtomEnum::WM_HINTS,
+            &hints,
+        ));
+    }
+
+    fn set_window_state(&self, state: window::WindowState) {
+        if self.destroyed() {
+            return;
+        }
+        let old = self.wm_state.get();
+        let mut new = old;
+        new.set_window_state(state);
+        if !self.mapped.get() {
+            self.wm_state.set(new);
+            return;
+        }
+
+        // Once the window is mapped, the window manager owns the state. It tells us about the
+        // changes that it makes in `handle_property_notify`.
+        if old.minimized && !new.minimized {
+            // mapping an iconic window makes it normal again
             log_x11!(self.app.connection().map_window(self.id));
         }
+        if new.maximized != old.maximized {
+            self.change_net_wm_state(
+                new.maximized,
+                self.atoms._NET_WM_STATE_MAXIMIZED_VERT,
+                self.atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+            );
+        }
+        if new.minimized && !old.minimized {
+            self.send_wm_message(self.atoms.WM_CHANGE_STATE, [ICONIC_STATE, 0, 0, 0, 0]);
+        }
+    }
+
+    fn get_window_state(&self) -> window::WindowState {
+        self.wm_state.get().window_state()
+    }
+
+    fn set_always_on_top(&self, always_on_top: bool) {
+        if !self.mapped.get() {
+            let mut state = self.wm_state.get();
+            state.above = always_on_top;
+            self.wm_state.set(state);
+        } else {
+            self.change_net_wm_state(always_on_top, self.atoms._NET_WM_STATE_ABOVE, 0);
+        }
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        if !self.mapped.get() {
+            let mut state = self.wm_state.get();
+            state.fullscreen = fullscreen;
+            self.wm_state.set(state);
+        } else {
+            self.change_net_wm_state(fullscreen, self.atoms._NET_WM_STATE_FULLSCREEN, 0);
+        }
+    }
+
+    /// Ask the window manager to add or remove up to two `_NET_WM_STATE`s.
+    ///
+    /// https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407959456
+    fn change_net_wm_state(&self, add: bool, first: u32, second: u32) {
+        const REMOVE: u32 = 0;
+        const ADD: u32 = 1;
+        // the request comes from a normal application (and not from a pager)
+        const SOURCE_APPLICATION: u32 = 1;
+        let action = if add { ADD } else { REMOVE };
+        self.send_wm_message(
+            self.atoms._NET_WM_STATE,
+            [action, first, second, SOURCE_APPLICATION, 0],
+        );
+    }
+
+    /// Send a client message about this window to the window manager.
+    fn send_wm_message(&self, type_: u32, data: [u32; 5]) {
+        if self.destroyed() {
+            return;
+        }
+        let conn = self.app.connection();
+        let root = conn.setup().roots[self.app.screen_num() as usize].root;
+        let event = util::client_message(32, self.id, type_, data);
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        log_x11!(conn.send_event(false, root, mask, event));
+        log_x11!(conn.flush());
+    }
+
+    /// Set the icon that the window manager shows for the window, in `_NET_WM_ICON`.
+    fn set_icon(&self, icon: &ImageBuf) {
+        if self.destroyed() {
+            return;
+        }
+        // The width, the height, and then the pixels as (non-premultiplied) ARGB, row by row.
+        let mut data = Vec::with_capacity(2 + icon.width() * icon.height());
+        data.push(icon.width() as u32);
+        data.push(icon.height() as u32);
+        for row in icon.pixel_colors() {
+            for color in row {
+                let (r, g, b, a) = color.as_rgba8();
+                data.push(u32::from_be_bytes([a, r, g, b]));
+            }
+        }
+        log_x11!(self.app.connection().change_property32(
+            PropMode::REPLACE,
+            self.id,
+            self.atoms._NET_WM_ICON,
+            AtomEnum::CARDINAL,
+            &data,
+        ));
+    }
+
+    /// Keep track of the state that the window manager gives the window.
+    pub fn handle_property_notify(&self, event: xproto::PropertyNotifyEvent) -> Result<(), Error> {
+        let atoms = &self.atoms;
+        let conn = self.app.connection();
+        let mut state = self.wm_state.get();
+        if event.atom == atoms._NET_WM_STATE {
+            let reply = conn
+                .get_property(false, self.id, atoms._NET_WM_STATE, AtomEnum::ATOM, 0, 1024)?
+                .reply()
+                .context("get _NET_WM_STATE")?;
+            let net_state: Vec<u32> = reply.value32().map(Iterator::collect).unwrap_or_default();
+            let has = |atom| net_state.contains(&atom);
+            state.maximized =
+                has(atoms._NET_WM_STATE_MAXIMIZED_VERT) && has(atoms._NET_WM_STATE_MAXIMIZED_HORZ);
+            state.fullscreen = has(atoms._NET_WM_STATE_FULLSCREEN);
+            state.above = has(atoms._NET_WM_STATE_ABOVE);
+        } else if event.atom == atoms.WM_STATE {
+            let reply = conn
+                .get_property(false, self.id, atoms.WM_STATE, atoms.WM_STATE, 0, 2)?
+                .reply()
+                .context("get WM_STATE")?;
+            state.minimized = reply.value32().and_then(|mut v| v.next()) == Some(ICONIC_STATE);
+        } else {
+            return Ok(());
+        }
+        let old_state = self.wm_state.get();
+        if state != old_state {
+            info!(
+                "window manager changed the state of window {} to {:?}",
+                self.id, state
+            );
+            self.wm_state.set(state);
+        }
+        // There is nothing to paint while the window is minimized.
+        if state.minimized && !old_state.minimized {
+            self.with_handler(|h| h.suspended());
+        } else if !state.minimized && old_state.minimized {
+            self.with_handler(|h| h.resumed());
+        }
+        Ok(())
     }
 
     fn close(&self) {
@@ -762,13 +1220,39 @@ impl Window {
     }
 
     /// Set whether the window should be resizable
-    fn resizable(&self, _resizable: bool) {
-        warn!("Window::resizeable is currently unimplemented for X11 platforms.");
+    fn resizable(&self, resizable: bool) {
+        self.resizable.set(resizable);
+        self.update_normal_hints();
     }
 
     /// Set whether the window should show titlebar
-    fn show_titlebar(&self, _show_titlebar: bool) {
-        warn!("Window::show_titlebar is currently unimplemented for X11 platforms.");
+    fn show_titlebar(&self, show_titlebar: bool) {
+        self.show_titlebar.set(show_titlebar);
+        self.update_motif_hints();
+    }
+
+    /// Tell the window manager whether to decorate the window, in the `_MOTIF_WM_HINTS` that
+    /// most window managers understand (there is no EWMH equivalent).
+    fn update_motif_hints(&self) {
+        if self.destroyed() {
+            return;
+        }
+        const MWM_HINTS_DECORATIONS: u32 = 1 << 1;
+        // flags, functions, decorations, input mode, status
+        let hints = [
+            MWM_HINTS_DECORATIONS,
+            0,
+            u32::from(self.show_titlebar.get()),
+            0,
+            0,
+        ];
+        log_x11!(self.app.connection().change_property32(
+            PropMode::REPLACE,
+            self.id,
+            self.atoms._MOTIF_WM_HINTS,
+            self.atoms._MOTIF_WM_HINTS,
+            &hints,
+        ));
     }
 
     /// Bring this window to the front of the window stack and give it focus.
@@ -798,34 +1282,48 @@ impl Window {
 
     /// Redraw more-or-less now.
     ///
-    /// "More-or-less" because if we're already waiting on a present, we defer the drawing until it
-    /// completes.
+    /// "More-or-less" because if we're using the present extension, we defer the drawing until
+    /// the next vblank.
     fn redraw_now(&self) -> Result<(), Error> {
-        if self.waiting_on_present()? {
-            self.set_needs_present(true)?;
-        } else {
+        if !self.schedule_present_frame()? {
             self.render()?;
         }
         Ok(())
     }
 
-    /// Schedule a redraw on the idle loop, or if we are waiting on present then schedule it for
-    /// when the current present finishes.
+    /// Schedule a redraw for the next vblank, or on the idle loop if we aren't using the present
+    /// extension.
     fn request_anim_frame(&self) {
-        if let Ok(true) = self.waiting_on_present() {
-            if let Err(e) = self.set_needs_present(true) {
-                error!(
-                    "Window::request_anim_frame - failed to schedule present: {}",
-                    e
-                );
+        match self.schedule_present_frame() {
+            Ok(true) => {}
+            Ok(false) => {
+                let idle = IdleHandle {
+                    queue: Arc::clone(&self.idle_queue),
+                    pipe: self.idle_pipe,
+                };
+                idle.schedule_redraw();
             }
-        } else {
-            let idle = IdleHandle {
-                queue: Arc::clone(&self.idle_queue),
-                pipe: self.idle_pipe,
-            };
-            idle.schedule_redraw();
+            Err(e) => error!(
+                "Window::request_anim_frame - failed to schedule present: {}",
+                e
+            ),
+        }
+    }
+
+    /// Make sure that we render when the next present completes, asking X for a notification at
+    /// the next vblank if nothing is being presented.
+    ///
+    /// Returns `false` if we aren't using the present extension.
+    fn schedule_present_frame(&self) -> Result<bool, Error> {
+        let mut present_data = borrow_mut!(self.present_data)?;
+        let present = match present_data.as_mut() {
+            Some(present) => present,
+            None => return Ok(false),
+        };
+        if present.schedule_frame(self.rendering.get()) && !self.destroyed() {
+            present.notify_msc(self.app.connection(), self.id)?;
         }
+        Ok(true)
     }
 
     fn invalidate(&self) {
@@ -844,6 +1342,55 @@ impl Window {
         self.request_anim_frame();
     }
 
+    fn set_background_color(&self, color: &Color) {
+        if self.destroyed() {
+            return;
+        }
+
+        let pixel = util::color_to_pixel(&self.visual_type, color);
+        log_x11!(self.app.connection().change_window_attributes(
+            self.id,
+            &ChangeWindowAttributesAux::new().background_pixel(pixel),
+        ));
+    }
+
+    fn set_resize_increments(&self, increments: Option<Size>) {
+        self.resize_increments.set(increments);
+        self.update_normal_hints();
+    }
+
+    fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        self.aspect_ratio.set(ratio);
+        self.update_normal_hints();
+    }
+
+    /// Tell the window manager about the size limits, resize increments and aspect ratio.
+    fn update_normal_hints(&self) {
+        if self.destroyed() {
+            return;
+        }
+
+        let fixed_size = if self.resizable.get() {
+            None
+        } else {
+            Some(self.size().size_px())
+        };
+        let scale = self.scale.get();
+        let hints = size_hints(
+            self.min_size.get().to_px(scale),
+            fixed_size,
+            self.resize_increments.get().map(|size| size.to_px(scale)),
+            self.aspect_ratio.get(),
+        );
+        log_x11!(self.app.connection().change_property32(
+            xproto::PropMode::REPLACE,
+            self.id,
+            AtomEnum::WM_NORMAL_HINTS,
+            AtomEnum::WM_SIZE_HINTS,
+            &hints,
+        ));
+    }
+
     fn set_title(&self, title: &str) {
         if self.destroyed() {
             return;
@@ -876,78 +1423,339 @@ impl Window {
             Cursor::IBeam => cursors.text,
             Cursor::Pointer => cursors.pointer,
             Cursor::Crosshair => cursors.crosshair,
-            Cursor::OpenHand => {
-                warn!("Cursor::OpenHand not supported for x11 backend. using arrow cursor");
-                None
-            }
+            Cursor::OpenHand => cursors.open_hand,
             Cursor::NotAllowed => cursors.not_allowed,
             Cursor::ResizeLeftRight => cursors.col_resize,
             Cursor::ResizeUpDown => cursors.row_resize,
-            // TODO: (x11/custom cursor)
-            Cursor::Custom(_) => None,
+            Cursor::Custom(custom) => Some(custom.0.cursor),
+        };
+        let cursor = match cursor.or(cursors.default) {
+            Some(cursor) => cursor,
+            None => {
+                warn!("Unable to load cursor {:?}", cursor);
+                return;
+            }
         };
-        if cursor.is_none() {
-            warn!("Unable to load cursor {:?}", cursor);
-            return;
-        }
         let conn = self.app.connection();
         let changes = ChangeWindowAttributesAux::new().cursor(cursor);
         if let Err(e) = conn.change_window_attributes(self.id, &changes) {
             error!("Changing cursor window attribute failed {}", e);
+            return;
         };
+        // The server only updates the pointer once it gets the request, so don't let it sit in
+        // the output buffer until the next event.
+        log_x11!(conn.flush());
     }
 
-    fn set_menu(&self, _menu: Menu) {
-        // TODO(x11/menus): implement Window::set_menu (currently a no-op)
+    fn set_menu(&self, menu: Menu) {
+        match borrow_mut!(self.menu) {
+            Ok(mut m) => *m = Some(menu),
+            Err(e) => error!("Failed to set the menu: {}", e),
+        }
     }
 
-    fn get_scale(&self) -> Result<Scale, Error> {
-        Ok(self.scale.get())
+    fn update_menu_item(
+        &self,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
+    ) -> Result<(), Error> {
+        if let Some(menu) = borrow_mut!(self.menu)?.as_mut() {
+            if !menu.update_item(id, text, key, enabled, selected) {
+                warn!("There is no menu item with the id {}", id);
+            }
+        }
+        Ok(())
     }
 
-    pub fn handle_expose(&self, expose: &xproto::ExposeEvent) -> Result<(), Error> {
-        let rect = Rect::from_origin_size(
-            (expose.x as f64, expose.y as f64),
-            (expose.width as f64, expose.height as f64),
-        )
-        .to_dp(self.scale.get());
-
-        self.add_invalid_rect(rect)?;
-        if self.waiting_on_present()? {
-            self.set_needs_present(true)?;
-        } else if expose.count == 0 {
-            self.request_anim_frame();
+    /// Open `menu` at `pos`, in display points relative to the window.
+    fn show_menu(&self, menu: Menu, pos: Point, select_first: bool) -> Result<(), Error> {
+        // Only one menu can be open at a time.
+        self.close_menu();
+        if menu.is_empty() || self.destroyed() {
+            return Ok(());
         }
+
+        let conn = self.app.connection();
+        let screen = &conn.setup().roots[self.app.screen_num() as usize];
+        let pos = pos.to_px(self.scale.get());
+        let reply = conn
+            .translate_coordinates(self.id, screen.root, pos.x as i16, pos.y as i16)?
+            .reply()
+            .context("get the position of the menu")?;
+        let pos = Point::new(reply.dst_x as f64, reply.dst_y as f64);
+        let bounds = screen::monitor_at(&self.app.monitors()?, pos)
+            .map(|monitor| monitor.work_rect)
+            .unwrap_or_else(|| {
+                let size = (
+                    screen.width_in_pixels as f64,
+                    screen.height_in_pixels as f64,
+                );
+                Rect::from_origin_size(Point::ORIGIN, size)
+            });
+
+        let popup = MenuPopup::new(
+            Rc::clone(conn),
+            self.id,
+            screen.root,
+            self.visual_type,
+            self.scale.get(),
+            bounds,
+            menu,
+            pos,
+            select_first,
+        )?;
+        *borrow_mut!(self.menu_popup)? = Some(popup);
         Ok(())
     }
 
-    pub fn handle_key_press(&self, key_press: &xproto::KeyPressEvent) {
-        let hw_keycode = key_press.detail;
-        let code = keycodes::hardware_keycode_to_code(hw_keycode);
-        let mods = key_mods(key_press.state);
-        let key = keycodes::code_to_key(code, mods);
-        let location = keycodes::code_to_location(code);
-        let state = KeyState::Down;
-        let key_event = KeyEvent {
-            code,
-            key,
-            mods,
-            location,
-            state,
-            repeat: false,
-            is_composing: false,
+    /// Open the window menu in the top left corner of the window, as a substitute for a menubar.
+    ///
+    /// Returns `false` if the window has no menu.
+    fn show_window_menu(&self) -> bool {
+        let menu = match borrow!(self.menu) {
+            Ok(menu) => menu.clone(),
+            Err(e) => {
+                error!("Failed to get the menu: {}", e);
+                return false;
+            }
         };
-        self.with_handler(|h| {
-            if !h.key_down(key_event.clone()) {
-                simulate_input(h, self.active_text_field.get(), key_event);
+        match menu {
+            Some(menu) => {
+                if let Err(e) = self.show_menu(menu, Point::ORIGIN, true) {
+                    error!("Failed to show the window menu: {:#}", e);
+                }
+                true
             }
-        });
+            None => false,
+        }
+    }
+
+    fn close_menu(&self) {
+        match borrow_mut!(self.menu_popup) {
+            // Dropping the popup closes its windows and releases the grabs.
+            Ok(mut popup) => drop(popup.take()),
+            Err(e) => error!("Failed to close the menu: {}", e),
+        }
+    }
+
+    fn menu_is_open(&self) -> bool {
+        self.menu_popup
+            .try_borrow()
+            .is_ok_and(|popup| popup.is_some())
+    }
+
+    /// Returns `true` if `window` is one of the windows of the open menu.
+    pub fn has_menu_window(&self, window: u32) -> bool {
+        self.menu_popup
+            .try_borrow()
+            .is_ok_and(|popup| popup.as_ref().is_some_and(|p| p.has_window(window)))
+    }
+
+    pub fn handle_menu_expose(&self, expose: &xproto::ExposeEvent) -> Result<(), Error> {
+        if expose.count == 0 {
+            if let Some(popup) = borrow!(self.menu_popup)?.as_ref() {
+                popup.handle_expose(expose.window)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Give an event to the open menu, and do what it asks for. While a menu is open, we have
+    /// grabbed the pointer and the keyboard, so all input events go through here.
+    ///
+    /// Returns `false` if no menu is open, in which case the event is for us.
+    fn with_menu_popup(&self, f: impl FnOnce(&mut MenuPopup) -> Result<MenuAction, Error>) -> bool {
+        let action = match borrow_mut!(self.menu_popup) {
+            Ok(mut popup) => match popup.as_mut() {
+                Some(popup) => f(popup),
+                None => return false,
+            },
+            Err(e) => {
+                error!("Failed to get the menu: {}", e);
+                return false;
+            }
+        };
+        match action {
+            Ok(MenuAction::None) => {}
+            Ok(MenuAction::Close) => self.close_menu(),
+            Ok(MenuAction::Command(id)) => {
+                self.close_menu();
+                self.with_handler(|h| h.command(id));
+            }
+            Err(e) => {
+                error!("Failed to handle a menu event: {:#}", e);
+                self.close_menu();
+            }
+        }
+        true
+    }
+
+    /// Handle a key press that isn't for the input method: it either runs the command of a menu
+    /// entry, or goes to the handler.
+    fn key_down(&self, key_event: KeyEvent) {
+        if key_event.key == KbKey::F10 && key_event.mods.is_empty() && self.show_window_menu() {
+            return;
+        }
+        let command = match borrow!(self.menu) {
+            Ok(menu) => menu
+                .as_ref()
+                .and_then(|menu| menu.command_for_key(&key_event)),
+            Err(e) => {
+                error!("Failed to get the menu: {}", e);
+                None
+            }
+        };
+        self.with_handler(|h| match command {
+            Some(id) => h.command(id),
+            None => {
+                if !h.key_down(key_event.clone()) {
+                    simulate_input(h, self.active_text_field.get(), key_event);
+                }
+            }
+        });
+    }
+
+    fn get_scale(&self) -> Result<Scale, Error> {
+        Ok(self.scale.get())
+    }
+
+    pub fn handle_expose(&self, expose: &xproto::ExposeEvent) -> Result<(), Error> {
+        let rect = Rect::from_origin_size(
+            (expose.x as f64, expose.y as f64),
+            (expose.width as f64, expose.height as f64),
+        )
+        .to_dp(self.scale.get());
+
+        self.add_invalid_rect(rect)?;
+        if expose.count == 0 {
+            self.request_anim_frame();
+        }
+        Ok(())
+    }
+
+    pub fn handle_key_press(&self, key_press: &xproto::KeyPressEvent) {
+        if self.menu_is_open() {
+            if let Some(key_event) = self.key_event(key_press, KeyState::Down) {
+                self.with_menu_popup(|menu| menu.handle_key(&key_event));
+            }
+            return;
+        }
+        // While text is being entered, keys go to the input method first, and it sends back
+        // the ones that it doesn't use.
+        if self.active_text_field.get().is_some() && self.app.xim_forward_key(self.id, key_press) {
+            return;
+        }
+        if let Some(key_event) = self.key_event(key_press, KeyState::Down) {
+            self.key_down(key_event);
+        }
+    }
+
+    pub fn handle_key_release(&self, key_release: &xproto::KeyReleaseEvent) {
+        if self.menu_is_open() {
+            return;
+        }
+        if self.active_text_field.get().is_some() && self.app.xim_forward_key(self.id, key_release)
+        {
+            return;
+        }
+        if let Some(key_event) = self.key_event(key_release, KeyState::Up) {
+            self.with_handler(|h| h.key_up(key_event));
+        }
+    }
+
+    /// Handle a key event that the input method sent back because it didn't use it.
+    pub fn handle_unfiltered_key(&self, event: &xproto::KeyPressEvent) {
+        if event.response_type & 0x7f == xproto::KEY_PRESS_EVENT {
+            if let Some(key_event) = self.key_event(event, KeyState::Down) {
+                self.key_down(key_event);
+            }
+        } else if let Some(key_event) = self.key_event(event, KeyState::Up) {
+            self.with_handler(|h| h.key_up(key_event));
+        }
+    }
+
+    /// Show `text`, the text that the input method is composing, in the focused text field,
+    /// with the caret `caret` characters into it.
+    pub fn handle_ime_preedit(&self, text: &str, caret: usize) {
+        let token = match self.active_text_field.get() {
+            Some(token) => token,
+            None => return,
+        };
+        self.with_handler(|h| {
+            let mut input = h.acquire_input_lock(token, true);
+            let range = input
+                .composition_range()
+                .unwrap_or_else(|| input.selection().range());
+            input.replace_range(range.clone(), text);
+            if text.is_empty() {
+                input.set_composition_range(None);
+                input.set_selection(Selection::caret(range.start));
+            } else {
+                input.set_composition_range(Some(range.start..range.start + text.len()));
+                let caret = text
+                    .char_indices()
+                    .nth(caret)
+                    .map(|(i, _)| i)
+                    .unwrap_or_else(|| text.len());
+                input.set_selection(Selection::caret(range.start + caret));
+            }
+            drop(input);
+            h.release_input_lock(token);
+        });
+    }
+
+    /// Enter `text` from the input method into the focused text field, in place of the text
+    /// that was being composed.
+    pub fn handle_ime_commit(&self, text: &str) {
+        let token = match self.active_text_field.get() {
+            Some(token) => token,
+            None => return,
+        };
+        self.with_handler(|h| {
+            let mut input = h.acquire_input_lock(token, true);
+            let range = input
+                .composition_range()
+                .unwrap_or_else(|| input.selection().range());
+            input.replace_range(range.clone(), text);
+            input.set_composition_range(None);
+            input.set_selection(Selection::caret(range.start + text.len()));
+            drop(input);
+            h.release_input_lock(token);
+        });
+    }
+
+    /// Returns `None` if the event should be dropped, because it cancelled a compose sequence.
+    fn key_event(&self, event: &xproto::KeyPressEvent, state: KeyState) -> Option<KeyEvent> {
+        if let Some(key_event) = self.app.xkb_key_event(event.detail, state) {
+            return key_event;
+        }
+        // Without XKB we only know the US layout, and can't tell repeats apart.
+        let code = keycodes::hardware_keycode_to_code(event.detail);
+        let mods = key_mods(event.state);
+        let key = keycodes::code_to_key(code, mods);
+        let location = keycodes::code_to_location(code);
+        Some(KeyEvent {
+            code,
+            key,
+            mods,
+            location,
+            state,
+            repeat: false,
+            is_composing: false,
+        })
     }
 
     pub fn handle_button_press(
         &self,
         button_press: &xproto::ButtonPressEvent,
     ) -> Result<(), Error> {
+        let root_pos = Point::new(button_press.root_x as f64, button_press.root_y as f64);
+        if self.with_menu_popup(|menu| menu.handle_button_press(root_pos)) {
+            return Ok(());
+        }
         let button = mouse_button(button_press.detail);
         let scale = self.scale.get();
         let mouse_event = MouseEvent {
@@ -961,6 +1769,7 @@ impl Window {
             focus: false,
             button,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
         };
         self.with_handler(|h| h.mouse_down(&mouse_event));
         Ok(())
@@ -970,6 +1779,10 @@ impl Window {
         &self,
         button_release: &xproto::ButtonReleaseEvent,
     ) -> Result<(), Error> {
+        let root_pos = Point::new(button_release.root_x as f64, button_release.root_y as f64);
+        if self.with_menu_popup(|menu| menu.handle_button_release(root_pos)) {
+            return Ok(());
+        }
         let scale = self.scale.get();
         let button = mouse_button(button_release.detail);
         let mouse_event = MouseEvent {
@@ -983,22 +1796,22 @@ impl Window {
             focus: false,
             button,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
         };
         self.with_handler(|h| h.mouse_up(&mouse_event));
         Ok(())
     }
 
     pub fn handle_wheel(&self, event: &xproto::ButtonPressEvent) -> Result<(), Error> {
+        if self.menu_is_open() {
+            return Ok(());
+        }
         let button = event.detail;
-        let mods = key_mods(event.state);
         let scale = self.scale.get();
 
         // We use a delta of 120 per tick to match the behavior of Windows.
-        let is_shift = mods.shift();
         let delta = match button {
-            4 if is_shift => (-120.0, 0.0),
             4 => (0.0, -120.0),
-            5 if is_shift => (120.0, 0.0),
             5 => (0.0, 120.0),
             6 => (-120.0, 0.0),
             7 => (120.0, 0.0),
@@ -1012,6 +1825,7 @@ impl Window {
             focus: false,
             button: MouseButton::None,
             wheel_delta: delta.into(),
+            pointer_type: PointerType::Mouse,
         };
 
         self.with_handler(|h| h.wheel(&mouse_event));
@@ -1022,6 +1836,10 @@ impl Window {
         &self,
         motion_notify: &xproto::MotionNotifyEvent,
     ) -> Result<(), Error> {
+        let root_pos = Point::new(motion_notify.root_x as f64, motion_notify.root_y as f64);
+        if self.with_menu_popup(|menu| menu.handle_motion(root_pos)) {
+            return Ok(());
+        }
         let scale = self.scale.get();
         let mouse_event = MouseEvent {
             pos: Point::new(motion_notify.event_x as f64, motion_notify.event_y as f64)
@@ -1032,6 +1850,7 @@ impl Window {
             focus: false,
             button: MouseButton::None,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
         };
         self.with_handler(|h| h.mouse_move(&mouse_event));
         Ok(())
@@ -1045,7 +1864,127 @@ impl Window {
             if protocol == self.atoms.WM_DELETE_WINDOW {
                 self.with_handler(|h| h.request_close());
             }
+        } else if client_message.format == 32 {
+            if let Err(e) = self.handle_xdnd_message(client_message) {
+                error!("Failed to handle XDND message: {:#}", e);
+            }
+        }
+    }
+
+    /// Handle the client messages that a drag source sends while something is dragged over the
+    /// window.
+    fn handle_xdnd_message(&self, message: &xproto::ClientMessageEvent) -> Result<(), Error> {
+        let atoms = &self.atoms;
+        let conn = self.app.connection();
+        let data = message.data.as_data32();
+        let source = data[0];
+
+        if message.type_ == atoms.XdndEnter {
+            // The first three formats are in the message, and if there are more, they are all in
+            // a property of the source window.
+            let types: Vec<u32> = if data[1] & 1 != 0 {
+                conn.get_property(
+                    false,
+                    source,
+                    atoms.XdndTypeList,
+                    AtomEnum::ATOM,
+                    0,
+                    u32::MAX,
+                )?
+                .reply()
+                .context("get XdndTypeList")?
+                .value32()
+                .map(Iterator::collect)
+                .unwrap_or_default()
+            } else {
+                data[2..5]
+                    .iter()
+                    .copied()
+                    .filter(|&atom| atom != x11rb::NONE)
+                    .collect()
+            };
+            let formats = util::atom_names(conn, &types)?;
+            *borrow_mut!(self.xdnd)? = Some(XdndState {
+                source,
+                formats,
+                pos: Point::ZERO,
+                accepted: false,
+            });
+        } else if message.type_ == atoms.XdndPosition {
+            // The position is in root window coordinates.
+            let (x, y) = ((data[2] >> 16) as i16, (data[2] & 0xffff) as i16);
+            let root = conn.setup().roots[self.app.screen_num() as usize].root;
+            let reply = conn
+                .translate_coordinates(root, self.id, x, y)?
+                .reply()
+                .context("translate XDND position")?;
+            let pos = Point::new(reply.dst_x as f64, reply.dst_y as f64).to_dp(self.scale.get());
+            let event = match borrow!(self.xdnd)?.as_ref() {
+                Some(state) if state.source == source => DropEvent {
+                    pos,
+                    formats: state.formats.clone(),
+                },
+                _ => return Ok(()),
+            };
+            let accepted = self.with_handler(|h| h.drag_over(&event)).unwrap_or(false);
+            if let Some(state) = borrow_mut!(self.xdnd)?.as_mut() {
+                state.pos = pos;
+                state.accepted = accepted;
+            }
+            let action = if accepted {
+                atoms.XdndActionCopy
+            } else {
+                x11rb::NONE
+            };
+            // An empty rectangle asks for a new position message for every move.
+            self.send_xdnd_message(
+                source,
+                atoms.XdndStatus,
+                [self.id, accepted as u32, 0, 0, action],
+            )?;
+        } else if message.type_ == atoms.XdndLeave {
+            if borrow_mut!(self.xdnd)?.take().is_some() {
+                self.with_handler(|h| h.drag_leave());
+            }
+        } else if message.type_ == atoms.XdndDrop {
+            let state = match borrow_mut!(self.xdnd)?.take() {
+                Some(state) if state.source == source => state,
+                _ => return Ok(()),
+            };
+            let handled = if state.accepted {
+                let event = DropEvent {
+                    pos: state.pos,
+                    formats: state.formats,
+                };
+                let data: Clipboard = self.app.selection(b"XdndSelection").into();
+                self.with_handler(|h| h.dropped(&event, &data))
+                    .unwrap_or(false)
+            } else {
+                self.with_handler(|h| h.drag_leave());
+                false
+            };
+            let action = if handled {
+                atoms.XdndActionCopy
+            } else {
+                x11rb::NONE
+            };
+            self.send_xdnd_message(
+                source,
+                atoms.XdndFinished,
+                [self.id, handled as u32, action, 0, 0],
+            )?;
         }
+        Ok(())
+    }
+
+    fn send_xdnd_message(&self, target: u32, type_: u32, data: [u32; 5]) -> Result<(), Error> {
+        let event = util::client_message(32, target, type_, data);
+        self.app
+            .connection()
+            .send_event(false, target, EventMask::NO_EVENT, event)?
+            .check()
+            .context("send XDND message")?;
+        Ok(())
     }
 
     #[allow(clippy::trivially_copy_pass_by_ref)]
@@ -1054,44 +1993,43 @@ impl Window {
     }
 
     pub fn handle_configure_notify(&self, event: &ConfigureNotifyEvent) -> Result<(), Error> {
-        self.set_size(Size::new(event.width as f64, event.height as f64))
+        self.set_size(Size::new(event.width as f64, event.height as f64))?;
+        self.update_scale()
+    }
+
+    /// Switch to the scale of the monitor that the window is on, if it moved to another one.
+    pub fn update_scale(&self) -> Result<(), Error> {
+        let conn = self.app.connection();
+        let root = conn.setup().roots[self.app.screen_num() as usize].root;
+        // The window's own position is relative to the window manager's frame, so ask where
+        // it is on the root window.
+        let origin = conn
+            .translate_coordinates(self.id, root, 0, 0)?
+            .reply()
+            .context("translate window coordinates")?;
+        let size_px = self.size().size_px();
+        let center = Point::new(
+            origin.dst_x as f64 + size_px.width / 2.,
+            origin.dst_y as f64 + size_px.height / 2.,
+        );
+        let scale = self.app.scale_at(center)?;
+        if scale != self.scale.get() {
+            self.scale.set(scale);
+            self.area.set(ScaledArea::from_px(size_px, scale));
+            // the minimum size is in display points
+            self.update_normal_hints();
+            self.add_invalid_rect(size_px.to_dp(scale).to_rect())?;
+            self.with_handler(|h| {
+                h.scale(scale);
+                h.size(size_px.to_dp(scale));
+            });
+        }
+        Ok(())
     }
 
     pub fn handle_complete_notify(&self, event: &CompleteNotifyEvent) -> Result<(), Error> {
         if let Some(present) = borrow_mut!(self.present_data)?.as_mut() {
-            // A little sanity check (which isn't worth an early return): we should only have
-            // one present request in flight, so we should only get notified about the request
-            // that we're waiting for.
-            if present.waiting_on != Some(event.serial) {
-                warn!(
-                    "Got a notify for serial {}, but waiting on {:?}",
-                    event.serial, present.waiting_on
-                );
-            }
-
-            // Check whether we missed presenting on any frames.
-            if let Some(last_msc) = present.last_msc {
-                if last_msc.wrapping_add(1) != event.msc {
-                    tracing::debug!(
-                        "missed a present: msc went from {} to {}",
-                        last_msc,
-                        event.msc
-                    );
-                    if let Some(last_ust) = present.last_ust {
-                        tracing::debug!("ust went from {} to {}", last_ust, event.ust);
-                    }
-                }
-            }
-
-            // Only store the last MSC if we're animating (if we aren't animating, missed MSCs
-            // aren't interesting).
-            present.last_msc = if present.needs_present {
-                Some(event.msc)
-            } else {
-                None
-            };
-            present.last_ust = Some(event.ust);
-            present.waiting_on = None;
+            present.complete(event.serial, event.msc, event.ust);
         }
 
         if self.needs_present()? {
@@ -1116,13 +2054,6 @@ impl Window {
         Ok(())
     }
 
-    fn waiting_on_present(&self) -> Result<bool, Error> {
-        Ok(borrow!(self.present_data)?
-            .as_ref()
-            .map(|p| p.waiting_on.is_some())
-            .unwrap_or(false))
-    }
-
     fn set_needs_present(&self, val: bool) -> Result<(), Error> {
         if let Some(present) = borrow_mut!(self.present_data)?.as_mut() {
             present.needs_present = val;
@@ -1266,6 +2197,43 @@ impl Buffers {
 }
 
 impl PresentData {
+    /// Records that another frame is wanted, and returns whether we need to ask for a
+    /// `CompleteNotifyEvent` to drive it. If a request is already in flight, or we are in the
+    /// middle of rendering, the frame will be picked up when that finishes.
+    fn schedule_frame(&mut self, rendering: bool) -> bool {
+        self.needs_present = true;
+        self.waiting_on.is_none() && !rendering
+    }
+
+    /// Handles the completion of the request with the given serial, at the given MSC and UST.
+    fn complete(&mut self, serial: u32, msc: u64, ust: u64) {
+        // A little sanity check (which isn't worth an early return): we should only have
+        // one present request in flight, so we should only get notified about the request
+        // that we're waiting for.
+        if self.waiting_on != Some(serial) {
+            warn!(
+                "Got a notify for serial {}, but waiting on {:?}",
+                serial, self.waiting_on
+            );
+        }
+
+        // Check whether we missed presenting on any frames.
+        if let Some(last_msc) = self.last_msc {
+            if last_msc.wrapping_add(1) != msc {
+                tracing::debug!("missed a present: msc went from {} to {}", last_msc, msc);
+                if let Some(last_ust) = self.last_ust {
+                    tracing::debug!("ust went from {} to {}", last_ust, ust);
+                }
+            }
+        }
+
+        // Only store the last MSC if we're animating (if we aren't animating, missed MSCs
+        // aren't interesting).
+        self.last_msc = if self.needs_present { Some(msc) } else { None };
+        self.last_ust = Some(ust);
+        self.waiting_on = None;
+    }
+
     // We have already rendered into the active pixmap buffer. Present it to the
     // X server, and then rotate the buffers.
     fn present(
@@ -1324,6 +2292,15 @@ impl PresentData {
         self.serial += 1;
         Ok(())
     }
+
+    /// Ask for a `CompleteNotifyEvent` at the next vblank, without presenting anything.
+    fn notify_msc(&mut self, conn: &Rc<XCBConnection>, window_id: u32) -> Result<(), Error> {
+        // target msc 0 with divisor 1 means the next msc
+        conn.present_notify_msc(window_id, self.serial, 0, 1, 0)?;
+        self.waiting_on = Some(self.serial);
+        self.serial += 1;
+        Ok(())
+    }
 }
 
 // Converts from, e.g., the `details` field of `xcb::xproto::ButtonPressEvent`
@@ -1336,6 +2313,8 @@ fn mouse_button(button: u8) -> MouseButton {
         4..=7 => MouseButton::None,
         8 => MouseButton::X1,
         9 => MouseButton::X2,
+        // the other buttons come after the scroll buttons, so they are numbered from 10
+        10..=u8::MAX => MouseButton::Other(button - 5),
         _ => {
             warn!("unknown mouse button code {}", button);
             MouseButton::None
@@ -1385,6 +2364,118 @@ fn key_mods(mods: u16) -> Modifiers {
     ret
 }
 
+/// Uploads the image of a `CursorDesc` to the X server as an ARGB cursor.
+///
+/// This uses the RENDER extension, which is what the themed cursors are made of as well.
+fn make_argb_cursor(app: &Application, desc: &CursorDesc) -> Result<xproto::Cursor, Error> {
+    let conn = app.connection();
+    let setup = conn.setup();
+    let screen = setup
+        .roots
+        .get(app.screen_num() as usize)
+        .ok_or_else(|| anyhow!("invalid screen num: {}", app.screen_num()))?;
+    let width = u16::try_from(desc.image.width()).context("cursor image is too wide")?;
+    let height = u16::try_from(desc.image.height()).context("cursor image is too tall")?;
+    if width == 0 || height == 0 {
+        return Err(anyhow!("cursor image is empty"));
+    }
+    let format = argb32_format(conn)?;
+
+    // RENDER wants premultiplied pixels, in the byte order of the server.
+    let big_endian = setup.image_byte_order == xproto::ImageOrder::MSB_FIRST;
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in desc.image.pixel_colors() {
+        for color in row {
+            let (r, g, b, a) = color.as_rgba8();
+            let premul = |c: u8| (u32::from(c) * u32::from(a) + 127) / 255;
+            let pixel = u32::from(a) << 24 | premul(r) << 16 | premul(g) << 8 | premul(b);
+            if big_endian {
+                data.extend_from_slice(&pixel.to_be_bytes());
+            } else {
+                data.extend_from_slice(&pixel.to_le_bytes());
+            }
+        }
+    }
+
+    // The hot spot has to be inside of the image.
+    let hot_x = desc.hot.x.round().max(0.0).min(f64::from(width - 1)) as u16;
+    let hot_y = desc.hot.y.round().max(0.0).min(f64::from(height - 1)) as u16;
+
+    // The cursor keeps its own copy of the image, so everything else is freed either way.
+    let pixmap = conn.generate_id()?;
+    conn.create_pixmap(32, pixmap, screen.root, width, height)?;
+    let cursor = (|| -> Result<xproto::Cursor, Error> {
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, pixmap, &CreateGCAux::new())?;
+        let uploaded = put_image_in_chunks(conn, pixmap, gc, width, &data);
+        log_x11!(conn.free_gc(gc));
+        uploaded?;
+
+        let picture = conn.generate_id()?;
+        conn.render_create_picture(picture, pixmap, format, &CreatePictureAux::new())?;
+        let cursor = conn.generate_id().map_err(Error::from).and_then(|cursor| {
+            conn.render_create_cursor(cursor, picture, hot_x, hot_y)?;
+            Ok(cursor)
+        });
+        log_x11!(conn.render_free_picture(picture));
+        cursor
+    })();
+    log_x11!(conn.free_pixmap(pixmap));
+    cursor
+}
+
+/// Uploads a 32-bit image to `drawable`, in as many requests as it takes to stay below the
+/// maximum request length of the server.
+fn put_image_in_chunks(
+    conn: &XCBConnection,
+    drawable: xproto::Drawable,
+    gc: xproto::Gcontext,
+    width: u16,
+    data: &[u8],
+) -> Result<(), Error> {
+    // The fixed part of a PutImage request.
+    const HEADER: usize = 24;
+    let stride = width as usize * 4;
+    let max_rows = (conn.maximum_request_bytes().saturating_sub(HEADER) / stride).max(1);
+    for (i, rows) in data.chunks(stride * max_rows).enumerate() {
+        conn.put_image(
+            xproto::ImageFormat::Z_PIXMAP,
+            drawable,
+            gc,
+            width,
+            (rows.len() / stride) as u16,
+            0,
+            (i * max_rows) as i16,
+            0,
+            32,
+            rows,
+        )?;
+    }
+    Ok(())
+}
+
+/// Finds the RENDER picture format for 32-bit ARGB pixels.
+fn argb32_format(conn: &XCBConnection) -> Result<Pictformat, Error> {
+    let formats = conn
+        .render_query_pict_formats()?
+        .reply()
+        .context("query RENDER picture formats")?;
+    formats
+        .formats
+        .iter()
+        .find(|f| {
+            let d = &f.direct;
+            f.type_ == PictType::DIRECT
+                && f.depth == 32
+                && (d.alpha_shift, d.alpha_mask) == (24, 0xff)
+                && (d.red_shift, d.red_mask) == (16, 0xff)
+                && (d.green_shift, d.green_mask) == (8, 0xff)
+                && (d.blue_shift, d.blue_mask) == (0, 0xff)
+        })
+        .map(|f| f.id)
+        .ok_or_else(|| anyhow!("the X server has no ARGB32 picture format"))
+}
+
 /// A handle that can get used to schedule an idle handler. Note that
 /// this handle can be cloned and sent between threads.
 #[derive(Clone)]
@@ -1508,13 +2599,45 @@ impl WindowHandle {
         Size::new(0.0, 0.0)
     }
 
-    pub fn set_window_state(&self, _state: window::WindowState) {
-        warn!("WindowHandle::set_window_state is currently unimplemented for X11 platforms.");
+    pub fn set_window_state(&self, state: window::WindowState) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_window_state(state);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
     }
 
     pub fn get_window_state(&self) -> window::WindowState {
-        warn!("WindowHandle::get_window_state is currently unimplemented for X11 platforms.");
-        window::WindowState::Restored
+        if let Some(w) = self.window.upgrade() {
+            w.get_window_state()
+        } else {
+            error!("Window {} has already been dropped", self.id);
+            window::WindowState::Restored
+        }
+    }
+
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_always_on_top(always_on_top);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_fullscreen(fullscreen);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn set_icon(&self, icon: &ImageBuf) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_icon(icon);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
     }
 
     pub fn handle_titlebar(&self, _val: bool) {
@@ -1553,6 +2676,42 @@ impl WindowHandle {
         }
     }
 
+    pub fn announce(&self, _text: &str, _politeness: Politeness) {
+        warn!("WindowHandle::announce is currently unimplemented for X11 platforms.");
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_background_color(&color);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn set_resize_increments(&self, width: f64, height: f64) {
+        if let Some(w) = self.window.upgrade() {
+            let increments = Size::new(width, height);
+            w.set_resize_increments(
+                Some(increments).filter(|size| size.width > 0.0 || size.height > 0.0),
+            );
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn set_aspect_ratio(&self, numerator: u32, denominator: u32) {
+        if let Some(w) = self.window.upgrade() {
+            let ratio = if numerator == 0 || denominator == 0 {
+                None
+            } else {
+                Some((numerator, denominator))
+            };
+            w.set_aspect_ratio(ratio);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
     pub fn set_title(&self, title: &str) {
         if let Some(w) = self.window.upgrade() {
             w.set_title(title);
@@ -1580,7 +2739,8 @@ impl WindowHandle {
     pub fn remove_text_field(&self, token: TextFieldToken) {
         if let Some(window) = self.window.upgrade() {
             if window.active_text_field.get() == Some(token) {
-                window.active_text_field.set(None)
+                window.active_text_field.set(None);
+                window.app.xim_set_focus(window.id, false);
             }
         }
     }
@@ -1588,6 +2748,7 @@ impl WindowHandle {
     pub fn set_focused_text_field(&self, active_field: Option<TextFieldToken>) {
         if let Some(window) = self.window.upgrade() {
             window.active_text_field.set(active_field);
+            window.app.xim_set_focus(window.id, active_field.is_some());
         }
     }
 
@@ -1611,26 +2772,78 @@ impl WindowHandle {
         }
     }
 
-    pub fn make_cursor(&self, _cursor_desc: &CursorDesc) -> Option<Cursor> {
-        warn!("Custom cursors are not yet supported in the X11 backend");
-        None
+    pub fn make_cursor(&self, cursor_desc: &CursorDesc) -> Option<Cursor> {
+        let w = self.window.upgrade()?;
+        match make_argb_cursor(&w.app, cursor_desc) {
+            Ok(cursor) => Some(Cursor::Custom(CustomCursor(Rc::new(CursorHandle {
+                conn: w.app.connection().clone(),
+                cursor,
+            })))),
+            Err(e) => {
+                warn!("Failed to create a custom cursor: {:#}", e);
+                None
+            }
+        }
     }
 
-    pub fn open_file(&mut self, _options: FileDialogOptions) -> Option<FileDialogToken> {
-        // TODO(x11/file_dialogs): implement WindowHandle::open_file
-        warn!("WindowHandle::open_file is currently unimplemented for X11 platforms.");
-        None
+    pub fn open_file(&mut self, options: FileDialogOptions) -> Option<FileDialogToken> {
+        self.file_dialog(FileDialogType::Open, options)
+    }
+
+    pub fn save_as(&mut self, options: FileDialogOptions) -> Option<FileDialogToken> {
+        self.file_dialog(FileDialogType::Save, options)
+    }
+
+    #[cfg(feature = "xdg-portal")]
+    fn file_dialog(
+        &self,
+        ty: FileDialogType,
+        options: FileDialogOptions,
+    ) -> Option<FileDialogToken> {
+        let idle = self.get_idle_handle()?;
+        Some(dialog::show(self.id, idle, ty, options))
     }
 
-    pub fn save_as(&mut self, _options: FileDialogOptions) -> Option<FileDialogToken> {
-        // TODO(x11/file_dialogs): implement WindowHandle::save_as
-        warn!("WindowHandle::save_as is currently unimplemented for X11 platforms.");
+    #[cfg(not(feature = "xdg-portal"))]
+    fn file_dialog(
+        &self,
+        _ty: FileDialogType,
+        _options: FileDialogOptions,
+    ) -> Option<FileDialogToken> {
+        warn!("File dialogs on X11 need the xdg-portal feature.");
         None
     }
 
-    pub fn show_context_menu(&self, _menu: Menu, _pos: Point) {
-        // TODO(x11/menus): implement WindowHandle::show_context_menu
-        warn!("WindowHandle::show_context_menu is currently unimplemented for X11 platforms.");
+    pub fn update_menu_item(
+        &self,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
+    ) {
+        if let Some(w) = self.window.upgrade() {
+            if let Err(e) = w.update_menu_item(id, text, key, enabled, selected) {
+                error!("Failed to update menu item {}: {:#}", id, e);
+            }
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn show_context_menu(&self, menu: Menu, pos: Point) {
+        if let Some(w) = self.window.upgrade() {
+            if let Err(e) = w.show_menu(menu, pos, false) {
+                error!("Failed to show the context menu: {:#}", e);
+            }
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn start_file_drag(&self, _promise: FilePromise) {
+        // TODO(x11/file_promise): implement XDND and the direct save protocol (XDS)
+        warn!("WindowHandle::start_file_drag is currently unimplemented for X11 platforms.");
     }
 
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
@@ -1648,6 +2861,182 @@ impl WindowHandle {
             Ok(Scale::new(1.0, 1.0))
         }
     }
+
+    #[cfg(feature = "raw-win-handle")]
+    pub unsafe fn embed_foreign_window(
+        &self,
+        child: RawWindowHandle,
+    ) -> Result<ForeignWindow, ShellError> {
+        let window = self.window.upgrade().ok_or(ShellError::WindowDropped)?;
+        let id = match child {
+            RawWindowHandle::Xcb(handle) if handle.window != 0 => handle.window,
+            RawWindowHandle::Xlib(handle) if handle.window != 0 => handle.window as u32,
+            _ => return Err(anyhow!("expected an X11 window handle").into()),
+        };
+        let conn = window.app.connection();
+        conn.reparent_window(id, self.id, 0, 0)
+            .context("reparent foreign window")?;
+        conn.map_window(id).context("map foreign window")?;
+        conn.flush().context("flush")?;
+        Ok(ForeignWindow {
+            id,
+            parent: self.window.clone(),
+        })
+    }
+
+    #[cfg(feature = "raw-win-handle")]
+    pub fn create_render_surface(&self, frame: Rect) -> Result<RenderSurface, ShellError> {
+        let window = self.window.upgrade().ok_or(ShellError::WindowDropped)?;
+        let (x, y, width, height) = render_surface_geometry(frame, window.get_scale()?);
+        let conn = window.app.connection();
+        let id = conn.generate_id().context("generate id")?;
+        // Without a background, the server leaves the contents alone until the renderer draws.
+        conn.create_window(
+            x11rb::COPY_FROM_PARENT.try_into().unwrap(),
+            id,
+            self.id,
+            x,
+            y,
+            width,
+            height,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            window.visual_type.visual_id,
+            &xproto::CreateWindowAux::new(),
+        )
+        .map_err(Error::from)?
+        .check()
+        .context("create render surface")?;
+        conn.map_window(id).context("map render surface")?;
+        conn.flush().context("flush")?;
+        Ok(RenderSurface {
+            id,
+            parent: self.window.clone(),
+        })
+    }
+}
+
+/// A child window that we don't draw to.
+///
+/// It selects no input, so mouse and keyboard events propagate to the parent window.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) struct RenderSurface {
+    id: u32,
+    parent: Weak<Window>,
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl RenderSurface {
+    pub fn set_frame(&self, frame: Rect) {
+        if let Some(w) = self.parent.upgrade() {
+            let scale = w.get_scale().unwrap_or_default();
+            let (x, y, width, height) = render_surface_geometry(frame, scale);
+            let conn = w.app.connection();
+            log_x11!(conn.configure_window(
+                self.id,
+                &xproto::ConfigureWindowAux::new()
+                    .x(i32::from(x))
+                    .y(i32::from(y))
+                    .width(u32::from(width))
+                    .height(u32::from(height)),
+            ));
+            log_x11!(conn.flush());
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl Drop for RenderSurface {
+    fn drop(&mut self) {
+        if let Some(w) = self.parent.upgrade() {
+            let conn = w.app.connection();
+            log_x11!(conn.destroy_window(self.id));
+            log_x11!(conn.flush());
+        }
+    }
+}
+
+/// The position and size in pixels of a render surface covering `frame`, which is in display
+/// points. X doesn't allow windows with no area, so the surface is at least one pixel wide and
+/// high.
+#[cfg(feature = "raw-win-handle")]
+fn render_surface_geometry(frame: Rect, scale: Scale) -> (i16, i16, u16, u16) {
+    let frame = frame.to_px(scale).round();
+    (
+        frame.x0 as i16,
+        frame.y0 as i16,
+        frame.width().max(1.0) as u16,
+        frame.height().max(1.0) as u16,
+    )
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe impl HasRawWindowHandle for RenderSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let mut handle = XcbHandle {
+            window: self.id,
+            ..XcbHandle::empty()
+        };
+        if let Some(w) = self.parent.upgrade() {
+            handle.connection = w.app.connection().get_raw_xcb_connection();
+        } else {
+            error!("Failed to get XCBConnection, returning incomplete handle");
+        }
+        RawWindowHandle::Xcb(handle)
+    }
+}
+
+/// A window created by someone else, that has been reparented into one of our
+/// windows.
+#[cfg(feature = "raw-win-handle")]
+pub(crate) struct ForeignWindow {
+    id: u32,
+    parent: Weak<Window>,
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl ForeignWindow {
+    pub fn set_frame(&self, frame: Rect) {
+        if let Some(w) = self.parent.upgrade() {
+            let scale = w.get_scale().unwrap_or_default();
+            let frame = frame.to_px(scale).round();
+            let conn = w.app.connection();
+            log_x11!(conn.configure_window(
+                self.id,
+                &xproto::ConfigureWindowAux::new()
+                    .x(frame.x0 as i32)
+                    .y(frame.y0 as i32)
+                    .width(frame.width().max(1.0) as u32)
+                    .height(frame.height().max(1.0) as u32),
+            ));
+            log_x11!(conn.flush());
+        }
+    }
+
+    pub fn focus(&self) {
+        if let Some(w) = self.parent.upgrade() {
+            let conn = w.app.connection();
+            log_x11!(conn.set_input_focus(
+                xproto::InputFocus::PARENT,
+                self.id,
+                xproto::Time::CURRENT_TIME,
+            ));
+            log_x11!(conn.flush());
+        }
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+impl Drop for ForeignWindow {
+    fn drop(&mut self) {
+        if let Some(w) = self.parent.upgrade() {
+            let conn = w.app.connection();
+            let root = conn.setup().roots[w.app.screen_num() as usize].root;
+            log_x11!(conn.unmap_window(self.id));
+            log_x11!(conn.reparent_window(self.id, root, 0, 0));
+            log_x11!(conn.flush());
+        }
+    }
 }
 
 #[cfg(feature = "raw-win-handle")]
@@ -1669,3 +3058,99 @@ unsafe impl HasRawWindowHandle for WindowHandle {
         RawWindowHandle::Xcb(handle)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resize_increments_and_aspect_ratio() {
+        let hints = size_hints(Size::ZERO, None, None, None);
+        assert_eq!(hints, [0; 18]);
+
+        let hints = size_hints(Size::ZERO, None, Some(Size::new(8., 0.)), Some((16, 9)));
+        assert_eq!(hints[0], 1 << 6 | 1 << 7);
+        // a zero increment is sent as 1, which doesn't constrain anything
+        assert_eq!(&hints[9..11], &[8, 1]);
+        assert_eq!(&hints[11..15], &[16, 9, 16, 9]);
+    }
+
+    #[test]
+    fn min_and_fixed_size() {
+        let hints = size_hints(Size::new(99.5, 50.), None, None, None);
+        assert_eq!(hints[0], 1 << 4);
+        assert_eq!(&hints[5..9], &[100, 50, 0, 0]);
+
+        // a fixed size overrides the minimum size
+        let hints = size_hints(Size::new(10., 10.), Some(Size::new(300., 200.)), None, None);
+        assert_eq!(hints[0], 1 << 4 | 1 << 5);
+        assert_eq!(&hints[5..9], &[300, 200, 300, 200]);
+    }
+
+    #[test]
+    fn present_scheduling() {
+        let mut present = PresentData {
+            serial: 0,
+            region: x11rb::NONE,
+            waiting_on: None,
+            needs_present: false,
+            last_msc: None,
+            last_ust: None,
+        };
+
+        // nothing is in flight, so the first frame asks for a notification
+        assert!(present.schedule_frame(false));
+        // stand-in for `notify_msc`, which needs a connection
+        present.waiting_on = Some(0);
+        // further requests are coalesced until that notification arrives
+        assert!(!present.schedule_frame(false));
+
+        present.complete(0, 10, 1000);
+        assert_eq!(present.waiting_on, None);
+        assert!(present.needs_present);
+        assert_eq!(present.last_msc, Some(10));
+        assert_eq!(present.last_ust, Some(1000));
+
+        // while rendering, the frame is picked up once the present completes
+        present.needs_present = false;
+        assert!(!present.schedule_frame(true));
+        assert!(present.needs_present);
+
+        // once we stop animating, we stop tracking the MSC
+        present.needs_present = false;
+        present.complete(1, 11, 1016);
+        assert_eq!(present.last_msc, None);
+        assert_eq!(present.last_ust, Some(1016));
+    }
+
+    #[test]
+    #[cfg(feature = "raw-win-handle")]
+    fn render_surface_frames() {
+        let frame = Rect::new(10.2, 20.0, 110.2, 70.0);
+        assert_eq!(
+            render_surface_geometry(frame, Scale::new(1.0, 1.0)),
+            (10, 20, 100, 50)
+        );
+        assert_eq!(
+            render_surface_geometry(frame, Scale::new(2.0, 2.0)),
+            (20, 40, 200, 100)
+        );
+        // an empty frame still gets a window
+        let empty = Rect::from_origin_size((5.0, 5.0), Size::ZERO);
+        assert_eq!(
+            render_surface_geometry(empty, Scale::new(1.5, 1.5)),
+            (8, 8, 1, 1)
+        );
+    }
+
+    #[test]
+    fn extra_mouse_buttons() {
+        assert_eq!(mouse_button(8), MouseButton::BACK);
+        assert_eq!(mouse_button(9), MouseButton::FORWARD);
+        // the scroll buttons are not reported as presses
+        assert_eq!(mouse_button(4), MouseButton::None);
+        // the other buttons are numbered as on the web, after the forward button
+        assert_eq!(mouse_button(10), MouseButton::Other(5));
+        assert_eq!(mouse_button(12), MouseButton::Other(7));
+    }
+}
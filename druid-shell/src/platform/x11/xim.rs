@@ -0,0 +1,843 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Input methods, with the X Input Method (XIM) protocol.
+//!
+//! This is how input method frameworks like fcitx and ibus talk to X11 applications.
+//! The protocol is described in <https://www.x.org/releases/X11R7.7/doc/libX11/XIM/xim.html>,
+//! and the way its messages are sent over the X connection in
+//! <https://www.x.org/releases/X11R7.7/doc/libX11/XIM/xtransport.html>.
+//!
+//! We create an input context for every window, and ask for "on-the-spot" preediting: the
+//! input method tells us the text that is being composed, and the text field shows it inline.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Context, Error};
+use x11rb::atom_manager;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    self, Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, CreateWindowAux, EventMask,
+    KeyPressEvent, PropMode, SelectionNotifyEvent, WindowClass,
+};
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::x11_utils::TryParse;
+use x11rb::xcb_ffi::XCBConnection;
+
+use super::util;
+
+// The opcodes of the XIM messages that we send or handle.
+const XIM_CONNECT: u8 = 1;
+const XIM_CONNECT_REPLY: u8 = 2;
+const XIM_ERROR: u8 = 20;
+const XIM_OPEN: u8 = 30;
+const XIM_OPEN_REPLY: u8 = 31;
+const XIM_SET_EVENT_MASK: u8 = 37;
+const XIM_ENCODING_NEGOTIATION: u8 = 38;
+const XIM_ENCODING_NEGOTIATION_REPLY: u8 = 39;
+const XIM_CREATE_IC: u8 = 50;
+const XIM_CREATE_IC_REPLY: u8 = 51;
+const XIM_DESTROY_IC: u8 = 52;
+const XIM_SET_IC_FOCUS: u8 = 58;
+const XIM_UNSET_IC_FOCUS: u8 = 59;
+const XIM_FORWARD_EVENT: u8 = 60;
+const XIM_SYNC: u8 = 61;
+const XIM_SYNC_REPLY: u8 = 62;
+const XIM_COMMIT: u8 = 63;
+const XIM_PREEDIT_START: u8 = 73;
+const XIM_PREEDIT_START_REPLY: u8 = 74;
+const XIM_PREEDIT_DRAW: u8 = 75;
+const XIM_PREEDIT_CARET: u8 = 76;
+const XIM_PREEDIT_CARET_REPLY: u8 = 77;
+const XIM_PREEDIT_DONE: u8 = 78;
+
+// Input styles.
+const XIM_PREEDIT_CALLBACKS: u32 = 0x0002;
+const XIM_PREEDIT_NOTHING: u32 = 0x0008;
+const XIM_STATUS_NOTHING: u32 = 0x0400;
+
+// Flags of XIM_FORWARD_EVENT and XIM_COMMIT.
+const FLAG_SYNCHRONOUS: u16 = 0x0001;
+const FLAG_LOOKUP_CHARS: u16 = 0x0002;
+const FLAG_LOOKUP_KEYSYM: u16 = 0x0004;
+
+// The events that the input method can ask us to forward, in XIM_SET_EVENT_MASK.
+const KEY_PRESS_MASK: u32 = 0x1;
+const KEY_RELEASE_MASK: u32 = 0x2;
+
+/// The encodings that we can read committed text in, in the order we prefer them.
+const ENCODINGS: [&[u8]; 2] = [b"UTF8_STRING", b"COMPOUND_TEXT"];
+
+/// How many property names we use to send long messages, in turn, so that a message is not
+/// overwritten before the input method has read it. This is what Xlib does, too.
+const PROPERTY_COUNT: usize = 20;
+
+atom_manager! {
+    XimAtoms: XimAtomsCookie {
+        XIM_SERVERS,
+        TRANSPORT,
+        _XIM_XCONNECT,
+        _XIM_PROTOCOL,
+        _XIM_MOREDATA,
+    }
+}
+
+/// Something that the input method did, which the window it happened in needs to handle.
+pub(crate) enum XimEvent {
+    /// A key press or release that the input method did not use.
+    Key(xproto::Window, KeyPressEvent),
+    /// Text was entered.
+    Commit(xproto::Window, String),
+    /// The text that is being composed changed, and the caret is at the given character
+    /// offset into it.
+    Preedit(xproto::Window, String, usize),
+}
+
+/// How far the connection to the input method server got.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    Transport,
+    XConnect,
+    Connect,
+    Open,
+    Encoding,
+    Ready,
+    Failed,
+}
+
+struct InputContext {
+    /// `None` until the input method replies to its creation.
+    id: Option<u16>,
+    /// The events that the input method wants to see.
+    forward_mask: u32,
+    focused: bool,
+    /// The text that is being composed.
+    preedit: Vec<char>,
+}
+
+/// A connection to an input method server.
+pub(crate) struct Xim {
+    conn: Rc<XCBConnection>,
+    atoms: XimAtoms,
+    /// Our window, which the server sends its messages to.
+    window: xproto::Window,
+    server_atom: Atom,
+    /// The owner of the server's selection, which we connect to.
+    server_owner: xproto::Window,
+    /// The window that the server receives messages on, once we are connected.
+    server_window: xproto::Window,
+    phase: Phase,
+    im_id: u16,
+    /// The ids of the input context attributes, by name.
+    ic_attributes: HashMap<String, u16>,
+    utf8: bool,
+    style: u32,
+    contexts: HashMap<xproto::Window, InputContext>,
+    /// The windows whose input contexts we asked for, in order; the replies don't say which
+    /// window they are for.
+    creating: VecDeque<xproto::Window>,
+    /// A message that is being received in several client messages.
+    partial: Vec<u8>,
+    properties: Vec<Atom>,
+    next_property: usize,
+}
+
+impl Xim {
+    /// Start connecting to the input method server that `XMODIFIERS` names, or the first one
+    /// there is.
+    ///
+    /// Returns `None` if there is no input method server.
+    pub(crate) fn new(conn: &Rc<XCBConnection>, screen_num: usize) -> Result<Option<Xim>, Error> {
+        let atoms = XimAtoms::new(conn.as_ref())?
+            .reply()
+            .context("intern XIM atoms")?;
+        let root = conn
+            .setup()
+            .roots
+            .get(screen_num)
+            .ok_or_else(|| anyhow!("invalid screen num: {}", screen_num))?
+            .root;
+
+        let servers: Vec<Atom> = conn
+            .get_property(false, root, atoms.XIM_SERVERS, AtomEnum::ATOM, 0, u32::MAX)?
+            .reply()
+            .context("get XIM_SERVERS")?
+            .value32()
+            .map(|atoms| atoms.collect())
+            .unwrap_or_default();
+        let names = util::atom_names(conn, &servers)?;
+        let wanted = std::env::var("XMODIFIERS")
+            .ok()
+            .and_then(|modifiers| {
+                modifiers
+                    .split('@')
+                    .find_map(|m| m.strip_prefix("im=").map(|name| name.trim().to_string()))
+            })
+            .filter(|name| !name.is_empty() && name != "none");
+        let server = servers.iter().zip(&names).find(|(_, name)| match &wanted {
+            Some(wanted) => name.strip_prefix("@server=") == Some(wanted.as_str()),
+            None => true,
+        });
+        let server_atom = match server {
+            Some((&atom, name)) => {
+                tracing::debug!("using input method server {}", name);
+                atom
+            }
+            None => return Ok(None),
+        };
+        let server_owner = conn
+            .get_selection_owner(server_atom)?
+            .reply()
+            .context("get XIM server owner")?
+            .owner;
+        if server_owner == x11rb::NONE {
+            return Ok(None);
+        }
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            x11rb::COPY_FROM_PARENT.try_into().unwrap(),
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )?
+        .check()
+        .context("create XIM window")?;
+
+        let mut properties = Vec::with_capacity(PROPERTY_COUNT);
+        for i in 0..PROPERTY_COUNT {
+            let name = format!("_DRUID_XIM_{}", i);
+            properties.push(conn.intern_atom(false, name.as_bytes())?.reply()?.atom);
+        }
+
+        // The server tells us how to reach it in the TRANSPORT target of its selection.
+        conn.convert_selection(
+            window,
+            server_atom,
+            atoms.TRANSPORT,
+            atoms.TRANSPORT,
+            x11rb::CURRENT_TIME,
+        )?;
+        conn.flush()?;
+
+        Ok(Some(Xim {
+            conn: conn.clone(),
+            atoms,
+            window,
+            server_atom,
+            server_owner,
+            server_window: x11rb::NONE,
+            phase: Phase::Transport,
+            im_id: 0,
+            ic_attributes: HashMap::new(),
+            utf8: false,
+            style: XIM_PREEDIT_CALLBACKS | XIM_STATUS_NOTHING,
+            contexts: HashMap::new(),
+            creating: VecDeque::new(),
+            partial: Vec::new(),
+            properties,
+            next_property: 0,
+        }))
+    }
+
+    /// The window that the input method server sends its messages to.
+    pub(crate) fn window(&self) -> xproto::Window {
+        self.window
+    }
+
+    /// Create an input context for `window`.
+    pub(crate) fn add_window(&mut self, window: xproto::Window) -> Result<(), Error> {
+        self.contexts.insert(
+            window,
+            InputContext {
+                id: None,
+                forward_mask: KEY_PRESS_MASK,
+                focused: false,
+                preedit: Vec::new(),
+            },
+        );
+        if self.phase == Phase::Ready {
+            self.create_ic(window)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove_window(&mut self, window: xproto::Window) -> Result<(), Error> {
+        if let Some(InputContext { id: Some(id), .. }) = self.contexts.remove(&window) {
+            self.send(XIM_DESTROY_IC, &[self.im_id, id], &[])?;
+        }
+        Ok(())
+    }
+
+    /// Tell the input method whether text is being entered in `window`.
+    pub(crate) fn set_focus(&mut self, window: xproto::Window, focused: bool) -> Result<(), Error> {
+        let ic = match self.contexts.get_mut(&window) {
+            Some(ic) if ic.focused != focused => ic,
+            _ => return Ok(()),
+        };
+        ic.focused = focused;
+        if let Some(id) = ic.id {
+            let opcode = if focused {
+                XIM_SET_IC_FOCUS
+            } else {
+                XIM_UNSET_IC_FOCUS
+            };
+            self.send(opcode, &[self.im_id, id], &[])?;
+        }
+        Ok(())
+    }
+
+    /// Send a key event to the input method, if it wants it.
+    ///
+    /// Returns `true` if the event was sent. The input method then either uses it, or
+    /// sends it back as an [`XimEvent::Key`].
+    pub(crate) fn forward_key(&mut self, window: xproto::Window, event: &KeyPressEvent) -> bool {
+        let mask = if event.response_type & 0x7f == xproto::KEY_PRESS_EVENT {
+            KEY_PRESS_MASK
+        } else {
+            KEY_RELEASE_MASK
+        };
+        let id = match self.contexts.get(&window) {
+            Some(InputContext {
+                id: Some(id),
+                focused: true,
+                forward_mask,
+                ..
+            }) if forward_mask & mask != 0 => *id,
+            _ => return false,
+        };
+        let raw = <[u8; 32]>::from(event);
+        match self.send(XIM_FORWARD_EVENT, &[self.im_id, id, 0, 0], &raw) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!("failed to forward a key event to the input method: {:#}", e);
+                false
+            }
+        }
+    }
+
+    /// Handle the server's reply to our request for its transport.
+    pub(crate) fn handle_selection_notify(
+        &mut self,
+        event: &SelectionNotifyEvent,
+    ) -> Result<(), Error> {
+        if self.phase != Phase::Transport || event.selection != self.server_atom {
+            return Ok(());
+        }
+        if event.property == x11rb::NONE {
+            self.phase = Phase::Failed;
+            return Err(anyhow!("the input method server has no transport"));
+        }
+        let transport = self
+            .conn
+            .get_property(
+                true,
+                self.window,
+                event.property,
+                AtomEnum::ANY,
+                0,
+                u32::MAX,
+            )?
+            .reply()
+            .context("get XIM transport")?
+            .value;
+        // This is something like "@transport=X/,tcp/host:port", and we only speak X.
+        let transport = String::from_utf8_lossy(&transport);
+        let supports_x = transport
+            .strip_prefix("@transport=")
+            .map(|list| list.split(',').any(|t| t == "X/"))
+            .unwrap_or(false);
+        if !supports_x {
+            self.phase = Phase::Failed;
+            return Err(anyhow!("unsupported XIM transport {:?}", transport));
+        }
+
+        // Ask for transport version 0.0: short messages in client messages, long ones in
+        // properties.
+        let event = util::client_message(
+            32,
+            self.server_owner,
+            self.atoms._XIM_XCONNECT,
+            [self.window, 0, 0, 0, 0],
+        );
+        self.conn
+            .send_event(false, self.server_owner, EventMask::NO_EVENT, event)?;
+        self.conn.flush()?;
+        self.phase = Phase::XConnect;
+        Ok(())
+    }
+
+    /// Handle a client message that the server sent to our window.
+    pub(crate) fn handle_client_message(
+        &mut self,
+        event: &ClientMessageEvent,
+    ) -> Result<Vec<XimEvent>, Error> {
+        let mut events = Vec::new();
+        if event.type_ == self.atoms._XIM_XCONNECT {
+            if self.phase == Phase::XConnect {
+                self.server_window = event.data.as_data32()[0];
+                self.phase = Phase::Connect;
+                let byte_order = if cfg!(target_endian = "little") {
+                    b'l'
+                } else {
+                    b'B'
+                };
+                let mut body = vec![byte_order, 0];
+                // protocol version 1.0, and no authentication.
+                for value in &[1u16, 0, 0] {
+                    body.extend_from_slice(&value.to_ne_bytes());
+                }
+                self.send(XIM_CONNECT, &[], &body)?;
+            }
+        } else if event.type_ == self.atoms._XIM_MOREDATA && event.format == 8 {
+            self.partial.extend_from_slice(&event.data.as_data8());
+        } else if event.type_ == self.atoms._XIM_PROTOCOL {
+            let message = if event.format == 8 {
+                self.partial.extend_from_slice(&event.data.as_data8());
+                std::mem::take(&mut self.partial)
+            } else {
+                let data = event.data.as_data32();
+                let (length, property) = (data[0], data[1]);
+                self.conn
+                    .get_property(
+                        true,
+                        self.window,
+                        property,
+                        AtomEnum::ANY,
+                        0,
+                        length.div_ceil(4),
+                    )?
+                    .reply()
+                    .context("get XIM message")?
+                    .value
+            };
+            self.handle_message(&message, &mut events)?;
+        }
+        Ok(events)
+    }
+
+    fn handle_message(&mut self, message: &[u8], events: &mut Vec<XimEvent>) -> Result<(), Error> {
+        if message.len() < 4 {
+            return Err(anyhow!("truncated XIM message"));
+        }
+        let opcode = message[0];
+        let length = u16::from_ne_bytes([message[2], message[3]]) as usize * 4;
+        let body = message
+            .get(4..4 + length)
+            .ok_or_else(|| anyhow!("truncated XIM message {}", opcode))?;
+        let mut reader = Reader { data: body, pos: 0 };
+        let r = &mut reader;
+
+        match opcode {
+            XIM_CONNECT_REPLY => {
+                let locale = util::locale();
+                let mut body = vec![locale.len() as u8];
+                body.extend_from_slice(locale.as_bytes());
+                self.phase = Phase::Open;
+                self.send(XIM_OPEN, &[], &body)?;
+            }
+            XIM_OPEN_REPLY => {
+                self.im_id = r.u16()?;
+                let im_attributes_len = r.u16()? as usize;
+                r.skip(im_attributes_len)?;
+                let ic_attributes_len = r.u16()? as usize;
+                r.skip(2)?;
+                let end = r.pos + ic_attributes_len;
+                while r.pos < end {
+                    let id = r.u16()?;
+                    let _type = r.u16()?;
+                    let name_len = r.u16()? as usize;
+                    let name = String::from_utf8_lossy(r.bytes(name_len)?).into_owned();
+                    r.skip(pad(2 + name_len))?;
+                    self.ic_attributes.insert(name, id);
+                }
+
+                let mut names = Vec::new();
+                for encoding in &ENCODINGS {
+                    names.push(encoding.len() as u8);
+                    names.extend_from_slice(encoding);
+                }
+                let mut body = (names.len() as u16).to_ne_bytes().to_vec();
+                body.extend_from_slice(&names);
+                body.resize(body.len() + pad(names.len()), 0);
+                // no detailed encoding information.
+                body.extend_from_slice(&[0; 4]);
+                self.phase = Phase::Encoding;
+                self.send(XIM_ENCODING_NEGOTIATION, &[self.im_id], &body)?;
+            }
+            XIM_ENCODING_NEGOTIATION_REPLY => {
+                let _im_id = r.u16()?;
+                let _category = r.u16()?;
+                let index = r.u16()? as i16;
+                self.utf8 = index == 0;
+                if index < 0 {
+                    tracing::warn!("the input method supports none of our encodings");
+                }
+                self.phase = Phase::Ready;
+                let windows: Vec<_> = self.contexts.keys().copied().collect();
+                for window in windows {
+                    self.create_ic(window)?;
+                }
+            }
+            XIM_CREATE_IC_REPLY => {
+                let _im_id = r.u16()?;
+                let id = r.u16()?;
+                let window = self.creating.pop_front();
+                match window.and_then(|window| self.contexts.get_mut(&window)) {
+                    Some(ic) => {
+                        ic.id = Some(id);
+                        if ic.focused {
+                            self.send(XIM_SET_IC_FOCUS, &[self.im_id, id], &[])?;
+                        }
+                    }
+                    // The window was closed in the meantime.
+                    None => self.send(XIM_DESTROY_IC, &[self.im_id, id], &[])?,
+                }
+            }
+            XIM_SET_EVENT_MASK => {
+                let _im_id = r.u16()?;
+                let id = r.u16()?;
+                let forward_mask = r.u32()?;
+                if let Some((_, ic)) = self.context(id) {
+                    ic.forward_mask = forward_mask;
+                }
+            }
+            XIM_FORWARD_EVENT => {
+                let im_id = r.u16()?;
+                let id = r.u16()?;
+                let flag = r.u16()?;
+                let _serial = r.u16()?;
+                let (event, _) = KeyPressEvent::try_parse(r.bytes(32)?)?;
+                let kind = event.response_type & 0x7f;
+                if kind == xproto::KEY_PRESS_EVENT || kind == xproto::KEY_RELEASE_EVENT {
+                    if let Some((window, _)) = self.context(id) {
+                        events.push(XimEvent::Key(window, event));
+                    }
+                }
+                if flag & FLAG_SYNCHRONOUS != 0 {
+                    self.send(XIM_SYNC_REPLY, &[im_id, id], &[])?;
+                }
+            }
+            XIM_COMMIT => {
+                let im_id = r.u16()?;
+                let id = r.u16()?;
+                let flag = r.u16()?;
+                if flag & FLAG_LOOKUP_KEYSYM != 0 {
+                    r.skip(2)?;
+                    let _keysym = r.u32()?;
+                }
+                if flag & FLAG_LOOKUP_CHARS != 0 {
+                    let len = r.u16()? as usize;
+                    let text = self.decode(r.bytes(len)?);
+                    if let Some((window, ic)) = self.context(id) {
+                        ic.preedit.clear();
+                        events.push(XimEvent::Commit(window, text));
+                    }
+                }
+                if flag & FLAG_SYNCHRONOUS != 0 {
+                    self.send(XIM_SYNC_REPLY, &[im_id, id], &[])?;
+                }
+            }
+            XIM_SYNC => {
+                let im_id = r.u16()?;
+                let id = r.u16()?;
+                self.send(XIM_SYNC_REPLY, &[im_id, id], &[])?;
+            }
+            XIM_PREEDIT_START => {
+                let im_id = r.u16()?;
+                let id = r.u16()?;
+                // -1: there is no limit to the length of the preedit text.
+                self.send(
+                    XIM_PREEDIT_START_REPLY,
+                    &[im_id, id],
+                    &(-1i32).to_ne_bytes(),
+                )?;
+            }
+            XIM_PREEDIT_DRAW => {
+                let _im_id = r.u16()?;
+                let id = r.u16()?;
+                let caret = r.u32()? as i32;
+                let change_first = r.u32()? as usize;
+                let change_len = r.u32()? as usize;
+                let status = r.u32()?;
+                let len = r.u16()? as usize;
+                let text = r.bytes(len)?;
+                // Bit 0 of the status means that there is no text.
+                let text = if status & 1 == 0 {
+                    self.decode(text)
+                } else {
+                    String::new()
+                };
+                if let Some((window, ic)) = self.context(id) {
+                    let start = change_first.min(ic.preedit.len());
+                    let end = (change_first + change_len).min(ic.preedit.len());
+                    ic.preedit.splice(start..end, text.chars());
+                    let caret = (caret.max(0) as usize).min(ic.preedit.len());
+                    events.push(XimEvent::Preedit(
+                        window,
+                        ic.preedit.iter().collect(),
+                        caret,
+                    ));
+                }
+            }
+            XIM_PREEDIT_CARET => {
+                let im_id = r.u16()?;
+                let id = r.u16()?;
+                let position = r.u32()?;
+                // We don't move the caret around the preedit text ourselves, so we just agree
+                // with where the input method wants it.
+                self.send(
+                    XIM_PREEDIT_CARET_REPLY,
+                    &[im_id, id],
+                    &position.to_ne_bytes(),
+                )?;
+            }
+            XIM_PREEDIT_DONE => {
+                let _im_id = r.u16()?;
+                let id = r.u16()?;
+                if let Some((window, ic)) = self.context(id) {
+                    if !ic.preedit.is_empty() {
+                        ic.preedit.clear();
+                        events.push(XimEvent::Preedit(window, String::new(), 0));
+                    }
+                }
+            }
+            XIM_ERROR => {
+                let _im_id = r.u16()?;
+                let _id = r.u16()?;
+                let flag = r.u16()?;
+                let code = r.u16()?;
+                let detail_len = r.u16()? as usize;
+                r.skip(2)?;
+                let detail = String::from_utf8_lossy(r.bytes(detail_len)?).into_owned();
+                tracing::warn!("XIM error {}: {}", code, detail);
+
+                // Bit 1 of the flag means that the error is about an existing input context, so
+                // without it, while we are creating some, it is about creating one.
+                if self.phase != Phase::Ready {
+                    self.phase = Phase::Failed;
+                } else if flag & 2 == 0 && !self.creating.is_empty() {
+                    if self.style & XIM_PREEDIT_CALLBACKS != 0 {
+                        tracing::info!("falling back to preediting in the input method's window");
+                        self.style = XIM_PREEDIT_NOTHING | XIM_STATUS_NOTHING;
+                        let windows: Vec<_> = self.creating.drain(..).collect();
+                        for window in windows {
+                            self.create_ic(window)?;
+                        }
+                    } else {
+                        self.creating.pop_front();
+                    }
+                }
+            }
+            _ => tracing::trace!("ignoring XIM message {}", opcode),
+        }
+        Ok(())
+    }
+
+    fn create_ic(&mut self, window: xproto::Window) -> Result<(), Error> {
+        let mut attributes = Vec::new();
+        for &(name, value) in &[
+            ("inputStyle", self.style),
+            ("clientWindow", window),
+            ("focusWindow", window),
+        ] {
+            let id = *self
+                .ic_attributes
+                .get(name)
+                .ok_or_else(|| anyhow!("the input method has no {} attribute", name))?;
+            attributes.extend_from_slice(&id.to_ne_bytes());
+            attributes.extend_from_slice(&4u16.to_ne_bytes());
+            attributes.extend_from_slice(&value.to_ne_bytes());
+        }
+        let mut body = (attributes.len() as u16).to_ne_bytes().to_vec();
+        body.extend_from_slice(&attributes);
+        self.send(XIM_CREATE_IC, &[self.im_id], &body)?;
+        self.creating.push_back(window);
+        Ok(())
+    }
+
+    /// The window and input context with the id `id`.
+    fn context(&mut self, id: u16) -> Option<(xproto::Window, &mut InputContext)> {
+        self.contexts
+            .iter_mut()
+            .find(|(_, ic)| ic.id == Some(id))
+            .map(|(&window, ic)| (window, ic))
+    }
+
+    fn decode(&self, text: &[u8]) -> String {
+        if self.utf8 {
+            String::from_utf8_lossy(text).into_owned()
+        } else {
+            decode_compound_text(text)
+        }
+    }
+
+    /// Send a message, made of the 16-bit `fields` followed by `rest`.
+    fn send(&mut self, opcode: u8, fields: &[u16], rest: &[u8]) -> Result<(), Error> {
+        let mut message = vec![opcode, 0, 0, 0];
+        for field in fields {
+            message.extend_from_slice(&field.to_ne_bytes());
+        }
+        message.extend_from_slice(rest);
+        message.resize(message.len() + pad(message.len()), 0);
+        let length = ((message.len() - 4) / 4) as u16;
+        message[2..4].copy_from_slice(&length.to_ne_bytes());
+
+        let window = self.server_window;
+        if message.len() <= 20 {
+            let mut data = [0; 20];
+            data[..message.len()].copy_from_slice(&message);
+            let event = util::client_message(8, window, self.atoms._XIM_PROTOCOL, data);
+            self.conn
+                .send_event(false, window, EventMask::NO_EVENT, event)?;
+        } else {
+            let property = self.properties[self.next_property];
+            self.next_property = (self.next_property + 1) % self.properties.len();
+            self.conn.change_property8(
+                PropMode::APPEND,
+                window,
+                property,
+                AtomEnum::STRING,
+                &message,
+            )?;
+            let event = util::client_message(
+                32,
+                window,
+                self.atoms._XIM_PROTOCOL,
+                [message.len() as u32, property, 0, 0, 0],
+            );
+            self.conn
+                .send_event(false, window, EventMask::NO_EVENT, event)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Xim {
+    fn drop(&mut self) {
+        log_x11!(self.conn.destroy_window(self.window));
+    }
+}
+
+/// The number of bytes that pad `len` bytes to a multiple of four.
+fn pad(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+/// Reads the fields of an XIM message, which are in our byte order.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow!("truncated XIM message"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_ne_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Decode the parts of compound text that we understand.
+///
+/// Compound text switches between character sets with ISO 2022 escape sequences. We understand
+/// ASCII, Latin-1 and UTF-8 segments, and leave out text in other character sets, such as the
+/// legacy CJK ones; input methods that use those usually also support UTF-8.
+fn decode_compound_text(text: &[u8]) -> String {
+    let mut decoded = String::new();
+    // Whether we understand the character sets of the left (below 0x80) and right halves.
+    let (mut left, mut right) = (true, true);
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == 0x1b {
+            // An escape sequence is made of intermediate bytes and one final byte.
+            let end = text[i + 1..]
+                .iter()
+                .position(|b| (0x30..=0x7e).contains(b))
+                .map(|end| i + 1 + end)
+                .unwrap_or(text.len() - 1);
+            match &text[i + 1..=end] {
+                b"%G" => {
+                    // UTF-8, up to the escape sequence that ends it.
+                    let start = end + 1;
+                    let len = text[start..]
+                        .windows(3)
+                        .position(|w| w == b"\x1b%@")
+                        .unwrap_or(text.len() - start);
+                    decoded.push_str(&String::from_utf8_lossy(&text[start..start + len]));
+                    i = (start + len + 3).min(text.len());
+                    continue;
+                }
+                b"(B" => left = true,
+                b"-A" => right = true,
+                [b'(', ..] | [b'$', b'(', ..] | [b'$', b'A'..=b'Z'] => left = false,
+                _ => right = false,
+            }
+            i = end + 1;
+            continue;
+        }
+        let byte = text[i];
+        let understood = if byte < 0x80 { left } else { right };
+        if understood {
+            // Latin-1 maps straight to the first 256 code points.
+            decoded.push(byte as char);
+        }
+        i += 1;
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compound_text() {
+        assert_eq!(decode_compound_text(b"abc"), "abc");
+        assert_eq!(decode_compound_text(b"caf\xe9"), "café");
+        assert_eq!(
+            decode_compound_text("a\x1b%G日本\x1b%@b".as_bytes()),
+            "a日本b"
+        );
+        // JIS X 0208 in the right half, which we don't understand.
+        assert_eq!(decode_compound_text(b"x\x1b$)B\xc6\xfc\x1b-Ay"), "xy");
+    }
+}
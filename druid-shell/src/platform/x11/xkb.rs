@@ -0,0 +1,545 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keyboard layouts, dead keys and compose sequences, using xkbcommon.
+
+#![allow(unsafe_code)]
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use anyhow::{anyhow, Context, Error};
+use x11rb::protocol::xkb::{
+    self, ConnectionExt as _, MapPart, PerClientFlag, SelectEventsAux, StateNotifyEvent,
+};
+use x11rb::protocol::xproto::Keycode;
+use x11rb::xcb_ffi::XCBConnection;
+
+use crate::keyboard::{KbKey, KeyEvent, KeyState, Modifiers};
+
+use super::{keycodes, util};
+
+/// The parts of the xkbcommon and xkbcommon-x11 APIs that we use.
+#[allow(non_camel_case_types)]
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    pub enum xkb_context {}
+    pub enum xkb_keymap {}
+    pub enum xkb_state {}
+    pub enum xkb_compose_table {}
+    pub enum xkb_compose_state {}
+
+    pub type xkb_keycode_t = u32;
+    pub type xkb_keysym_t = u32;
+
+    pub const XKB_X11_MIN_MAJOR_XKB_VERSION: u16 = 1;
+    pub const XKB_X11_MIN_MINOR_XKB_VERSION: u16 = 0;
+    pub const XKB_STATE_MODS_EFFECTIVE: c_int = 1 << 3;
+    pub const XKB_COMPOSE_FEED_ACCEPTED: c_int = 1;
+    pub const XKB_COMPOSE_COMPOSING: c_int = 1;
+    pub const XKB_COMPOSE_COMPOSED: c_int = 2;
+    pub const XKB_COMPOSE_CANCELLED: c_int = 3;
+
+    #[link(name = "xkbcommon")]
+    extern "C" {
+        pub fn xkb_context_new(flags: c_int) -> *mut xkb_context;
+        pub fn xkb_context_unref(context: *mut xkb_context);
+        pub fn xkb_keymap_unref(keymap: *mut xkb_keymap);
+        pub fn xkb_state_unref(state: *mut xkb_state);
+        pub fn xkb_state_update_mask(
+            state: *mut xkb_state,
+            depressed_mods: u32,
+            latched_mods: u32,
+            locked_mods: u32,
+            depressed_layout: u32,
+            latched_layout: u32,
+            locked_layout: u32,
+        ) -> c_int;
+        pub fn xkb_state_key_get_one_sym(state: *mut xkb_state, key: xkb_keycode_t)
+            -> xkb_keysym_t;
+        pub fn xkb_state_key_get_utf8(
+            state: *mut xkb_state,
+            key: xkb_keycode_t,
+            buffer: *mut c_char,
+            size: usize,
+        ) -> c_int;
+        pub fn xkb_state_mod_name_is_active(
+            state: *mut xkb_state,
+            name: *const c_char,
+            type_: c_int,
+        ) -> c_int;
+        pub fn xkb_compose_table_new_from_locale(
+            context: *mut xkb_context,
+            locale: *const c_char,
+            flags: c_int,
+        ) -> *mut xkb_compose_table;
+        pub fn xkb_compose_table_unref(table: *mut xkb_compose_table);
+        pub fn xkb_compose_state_new(
+            table: *mut xkb_compose_table,
+            flags: c_int,
+        ) -> *mut xkb_compose_state;
+        pub fn xkb_compose_state_unref(state: *mut xkb_compose_state);
+        pub fn xkb_compose_state_feed(state: *mut xkb_compose_state, keysym: xkb_keysym_t)
+            -> c_int;
+        pub fn xkb_compose_state_reset(state: *mut xkb_compose_state);
+        pub fn xkb_compose_state_get_status(state: *mut xkb_compose_state) -> c_int;
+        pub fn xkb_compose_state_get_utf8(
+            state: *mut xkb_compose_state,
+            buffer: *mut c_char,
+            size: usize,
+        ) -> c_int;
+    }
+
+    #[link(name = "xkbcommon-x11")]
+    extern "C" {
+        pub fn xkb_x11_setup_xkb_extension(
+            connection: *mut c_void,
+            major_xkb_version: u16,
+            minor_xkb_version: u16,
+            flags: c_int,
+            major_xkb_version_out: *mut u16,
+            minor_xkb_version_out: *mut u16,
+            base_event_out: *mut u8,
+            base_error_out: *mut u8,
+        ) -> c_int;
+        pub fn xkb_x11_get_core_keyboard_device_id(connection: *mut c_void) -> i32;
+        pub fn xkb_x11_keymap_new_from_device(
+            context: *mut xkb_context,
+            connection: *mut c_void,
+            device_id: i32,
+            flags: c_int,
+        ) -> *mut xkb_keymap;
+        pub fn xkb_x11_state_new_from_device(
+            keymap: *mut xkb_keymap,
+            connection: *mut c_void,
+            device_id: i32,
+        ) -> *mut xkb_state;
+    }
+}
+
+/// The keyboard of the X server, as described by its XKB keymap.
+///
+/// This turns keycodes into keys and text for the layout that is in use,
+/// follows the state of the modifiers and the layout as the server reports
+/// changes, and composes dead keys and compose sequences.
+pub(crate) struct Keyboard {
+    context: *mut ffi::xkb_context,
+    device_id: i32,
+    keymap: *mut ffi::xkb_keymap,
+    state: *mut ffi::xkb_state,
+    /// These are null if there is no compose table for the locale.
+    compose_table: *mut ffi::xkb_compose_table,
+    compose_state: *mut ffi::xkb_compose_state,
+    /// The keys that are held down, to tell repeats apart from new presses.
+    pressed: HashSet<Keycode>,
+}
+
+impl Keyboard {
+    /// Set up the XKB extension, and load the keymap of the core keyboard.
+    ///
+    /// This also asks the server for the events that keep the keymap and
+    /// state up to date, and for detectable auto-repeat, so that a held key
+    /// sends repeated presses without releases in between.
+    pub(crate) fn new(conn: &XCBConnection) -> Result<Keyboard, Error> {
+        let raw = conn.get_raw_xcb_connection();
+        let device_id = unsafe {
+            let ok = ffi::xkb_x11_setup_xkb_extension(
+                raw,
+                ffi::XKB_X11_MIN_MAJOR_XKB_VERSION,
+                ffi::XKB_X11_MIN_MINOR_XKB_VERSION,
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            if ok == 0 {
+                return Err(anyhow!("the X server does not support XKB"));
+            }
+            ffi::xkb_x11_get_core_keyboard_device_id(raw)
+        };
+        if device_id < 0 {
+            return Err(anyhow!("failed to get the core keyboard device"));
+        }
+        let context = unsafe { ffi::xkb_context_new(0) };
+        if context.is_null() {
+            return Err(anyhow!("failed to create an xkbcommon context"));
+        }
+
+        let mut keyboard = Keyboard {
+            context,
+            device_id,
+            keymap: ptr::null_mut(),
+            state: ptr::null_mut(),
+            compose_table: ptr::null_mut(),
+            compose_state: ptr::null_mut(),
+            pressed: HashSet::new(),
+        };
+        keyboard.reload_keymap(conn)?;
+        keyboard.load_compose_table();
+
+        let device_spec = device_id as xkb::DeviceSpec;
+        // A new keyboard also sends `MapNotify`, so that is all we need to
+        // know when to reload the keymap.
+        let events = xkb::EventType::MAP_NOTIFY | xkb::EventType::STATE_NOTIFY;
+        let map_parts = MapPart::KEY_TYPES
+            | MapPart::KEY_SYMS
+            | MapPart::MODIFIER_MAP
+            | MapPart::EXPLICIT_COMPONENTS
+            | MapPart::KEY_ACTIONS
+            | MapPart::KEY_BEHAVIORS
+            | MapPart::VIRTUAL_MODS
+            | MapPart::VIRTUAL_MOD_MAP;
+        let details = SelectEventsAux::new();
+        conn.xkb_select_events(device_spec, 0u16, events, map_parts, map_parts, &details)?
+            .check()
+            .context("select XKB events")?;
+
+        let repeat = u32::from(PerClientFlag::DETECTABLE_AUTO_REPEAT);
+        let flags = conn
+            .xkb_per_client_flags(device_spec, repeat, repeat, 0u32, 0u32, 0u32)?
+            .reply()
+            .context("enable detectable auto-repeat")?;
+        if flags.supported & repeat == 0 {
+            tracing::info!("The X server does not support detectable auto-repeat");
+        }
+
+        Ok(keyboard)
+    }
+
+    /// Load the keymap of the keyboard again, after it was changed.
+    pub(crate) fn reload_keymap(&mut self, conn: &XCBConnection) -> Result<(), Error> {
+        let raw = conn.get_raw_xcb_connection();
+        let keymap =
+            unsafe { ffi::xkb_x11_keymap_new_from_device(self.context, raw, self.device_id, 0) };
+        if keymap.is_null() {
+            return Err(anyhow!("failed to get the keymap of the keyboard"));
+        }
+        let state = unsafe { ffi::xkb_x11_state_new_from_device(keymap, raw, self.device_id) };
+        if state.is_null() {
+            unsafe { ffi::xkb_keymap_unref(keymap) };
+            return Err(anyhow!("failed to get the state of the keyboard"));
+        }
+        self.unref_keymap();
+        self.keymap = keymap;
+        self.state = state;
+        Ok(())
+    }
+
+    fn load_compose_table(&mut self) {
+        let locale = match CString::new(util::locale()) {
+            Ok(locale) => locale,
+            Err(_) => return,
+        };
+        unsafe {
+            self.compose_table =
+                ffi::xkb_compose_table_new_from_locale(self.context, locale.as_ptr(), 0);
+            if self.compose_table.is_null() {
+                tracing::info!("No compose table for {:?}", locale);
+                return;
+            }
+            self.compose_state = ffi::xkb_compose_state_new(self.compose_table, 0);
+        }
+    }
+
+    /// Update the modifiers and the layout from an XKB state notification.
+    pub(crate) fn update_state(&mut self, event: &StateNotifyEvent) {
+        unsafe {
+            ffi::xkb_state_update_mask(
+                self.state,
+                event.base_mods.into(),
+                event.latched_mods.into(),
+                event.locked_mods.into(),
+                event.base_group as u32,
+                event.latched_group as u32,
+                u8::from(event.locked_group).into(),
+            );
+        }
+    }
+
+    /// The modifiers that are active.
+    pub(crate) fn mods(&self) -> Modifiers {
+        let mut mods = Modifiers::empty();
+        let names: [(&[u8], Modifiers); 7] = [
+            (b"Shift\0", Modifiers::SHIFT),
+            (b"Control\0", Modifiers::CONTROL),
+            (b"Mod1\0", Modifiers::ALT),
+            (b"Mod2\0", Modifiers::NUM_LOCK),
+            (b"Mod4\0", Modifiers::META),
+            (b"Mod5\0", Modifiers::ALT_GRAPH),
+            (b"Lock\0", Modifiers::CAPS_LOCK),
+        ];
+        for (name, modifier) in &names {
+            let active = unsafe {
+                ffi::xkb_state_mod_name_is_active(
+                    self.state,
+                    name.as_ptr() as *const c_char,
+                    ffi::XKB_STATE_MODS_EFFECTIVE,
+                )
+            };
+            if active > 0 {
+                mods |= *modifier;
+            }
+        }
+        mods
+    }
+
+    /// Forget the keys that are held down and the compose sequence in progress.
+    ///
+    /// This is for when the keyboard focus leaves our windows, because the
+    /// releases of those keys then go elsewhere.
+    pub(crate) fn reset(&mut self) {
+        self.pressed.clear();
+        if !self.compose_state.is_null() {
+            unsafe { ffi::xkb_compose_state_reset(self.compose_state) };
+        }
+    }
+
+    /// Turn the press or release of the key with `keycode` into a `KeyEvent`.
+    ///
+    /// Returns `None` for a press that cancelled a compose sequence, which
+    /// drops both the sequence and the key.
+    pub(crate) fn key_event(&mut self, keycode: Keycode, state: KeyState) -> Option<KeyEvent> {
+        let code = keycodes::hardware_keycode_to_code(keycode);
+        let repeat = match state {
+            KeyState::Down => !self.pressed.insert(keycode),
+            KeyState::Up => {
+                self.pressed.remove(&keycode);
+                false
+            }
+        };
+        let xkb_key = keycode as ffi::xkb_keycode_t;
+        let keysym = unsafe { ffi::xkb_state_key_get_one_sym(self.state, xkb_key) };
+        let mods = self.mods();
+
+        let mut compose = Compose::Ignored;
+        if state == KeyState::Down && !self.compose_state.is_null() {
+            let compose_state = self.compose_state;
+            unsafe {
+                if ffi::xkb_compose_state_feed(compose_state, keysym)
+                    == ffi::XKB_COMPOSE_FEED_ACCEPTED
+                {
+                    match ffi::xkb_compose_state_get_status(compose_state) {
+                        ffi::XKB_COMPOSE_COMPOSING => compose = Compose::Composing,
+                        ffi::XKB_COMPOSE_COMPOSED => {
+                            compose = Compose::Composed(read_utf8(|buf, size| {
+                                ffi::xkb_compose_state_get_utf8(compose_state, buf, size)
+                            }));
+                            ffi::xkb_compose_state_reset(compose_state);
+                        }
+                        ffi::XKB_COMPOSE_CANCELLED => {
+                            compose = Compose::Cancelled;
+                            ffi::xkb_compose_state_reset(compose_state);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        let is_composing = compose == Compose::Composing;
+        let state_ptr = self.state;
+        let text = read_utf8(|buf, size| unsafe {
+            ffi::xkb_state_key_get_utf8(state_ptr, xkb_key, buf, size)
+        });
+        let key = key_for(compose, keysym, text, || keycodes::code_to_key(code, mods))?;
+
+        Some(KeyEvent {
+            code,
+            key,
+            mods,
+            location: keycodes::code_to_location(code),
+            state,
+            repeat,
+            is_composing,
+        })
+    }
+
+    fn unref_keymap(&mut self) {
+        unsafe {
+            if !self.state.is_null() {
+                ffi::xkb_state_unref(self.state);
+            }
+            if !self.keymap.is_null() {
+                ffi::xkb_keymap_unref(self.keymap);
+            }
+        }
+        self.state = ptr::null_mut();
+        self.keymap = ptr::null_mut();
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        self.unref_keymap();
+        unsafe {
+            if !self.compose_state.is_null() {
+                ffi::xkb_compose_state_unref(self.compose_state);
+            }
+            if !self.compose_table.is_null() {
+                ffi::xkb_compose_table_unref(self.compose_table);
+            }
+            ffi::xkb_context_unref(self.context);
+        }
+    }
+}
+
+/// Read a string from one of the xkbcommon functions that write UTF-8 into a
+/// buffer and return its length.
+fn read_utf8(read: impl FnOnce(*mut c_char, usize) -> c_int) -> Option<String> {
+    let mut buf = [0u8; 64];
+    let len = read(buf.as_mut_ptr() as *mut c_char, buf.len());
+    if len <= 0 || len as usize >= buf.len() {
+        return None;
+    }
+    String::from_utf8(buf[..len as usize].to_vec()).ok()
+}
+
+/// What a key press did to the compose sequence in progress.
+#[derive(Debug, Clone, PartialEq)]
+enum Compose {
+    /// The key is not part of a sequence.
+    Ignored,
+    /// The key started or continued a sequence.
+    Composing,
+    /// The key completed a sequence, which produced this text, if any.
+    Composed(Option<String>),
+    /// The key did not fit the sequence, which was abandoned.
+    Cancelled,
+}
+
+/// The key of an event, given what it did to the compose sequence, its keysym
+/// and the text it produces on its own.
+///
+/// Returns `None` if the event should be dropped.
+fn key_for(
+    compose: Compose,
+    keysym: ffi::xkb_keysym_t,
+    text: Option<String>,
+    fallback: impl FnOnce() -> KbKey,
+) -> Option<KbKey> {
+    let key = match compose {
+        // the key that did not fit the sequence is dropped with it.
+        Compose::Cancelled => return None,
+        Compose::Composing => KbKey::Dead,
+        Compose::Composed(Some(text)) => KbKey::Character(text),
+        Compose::Composed(None) | Compose::Ignored => match keysym_to_key(keysym) {
+            Some(key) => key,
+            None => match text {
+                Some(text) if !text.chars().any(char::is_control) => KbKey::Character(text),
+                _ => fallback(),
+            },
+        },
+    };
+    Some(key)
+}
+
+/// The key for keysyms that do not produce text.
+///
+/// The values are from `xkbcommon-keysyms.h`.
+fn keysym_to_key(keysym: ffi::xkb_keysym_t) -> Option<KbKey> {
+    const FUNCTION_KEYS: [KbKey; 12] = [
+        KbKey::F1,
+        KbKey::F2,
+        KbKey::F3,
+        KbKey::F4,
+        KbKey::F5,
+        KbKey::F6,
+        KbKey::F7,
+        KbKey::F8,
+        KbKey::F9,
+        KbKey::F10,
+        KbKey::F11,
+        KbKey::F12,
+    ];
+    let key = match keysym {
+        0xff08 => KbKey::Backspace,
+        0xff09 | 0xfe20 => KbKey::Tab,
+        0xff0d | 0xff8d => KbKey::Enter,
+        0xff13 => KbKey::Pause,
+        0xff14 => KbKey::ScrollLock,
+        0xff1b => KbKey::Escape,
+        0xff20 => KbKey::Compose,
+        0xff50 | 0xff95 => KbKey::Home,
+        0xff51 | 0xff96 => KbKey::ArrowLeft,
+        0xff52 | 0xff97 => KbKey::ArrowUp,
+        0xff53 | 0xff98 => KbKey::ArrowRight,
+        0xff54 | 0xff99 => KbKey::ArrowDown,
+        0xff55 | 0xff9a => KbKey::PageUp,
+        0xff56 | 0xff9b => KbKey::PageDown,
+        0xff57 | 0xff9c => KbKey::End,
+        0xff61 => KbKey::PrintScreen,
+        0xff63 | 0xff9e => KbKey::Insert,
+        0xff67 => KbKey::ContextMenu,
+        0xff7f => KbKey::NumLock,
+        0xffbe..=0xffc9 => FUNCTION_KEYS[(keysym - 0xffbe) as usize].clone(),
+        0xffe1 | 0xffe2 => KbKey::Shift,
+        0xffe3 | 0xffe4 => KbKey::Control,
+        0xffe5 => KbKey::CapsLock,
+        0xffe7 | 0xffe8 | 0xffeb | 0xffec => KbKey::Meta,
+        0xffe9 | 0xffea => KbKey::Alt,
+        0xfe03 => KbKey::AltGraph,
+        0xffff | 0xff9f => KbKey::Delete,
+        // dead keys, when there is no compose table.
+        0xfe50..=0xfe8f => KbKey::Dead,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keysyms_without_text() {
+        assert_eq!(keysym_to_key(0xff08), Some(KbKey::Backspace));
+        assert_eq!(keysym_to_key(0xff8d), Some(KbKey::Enter));
+        assert_eq!(keysym_to_key(0xff96), Some(KbKey::ArrowLeft));
+        assert_eq!(keysym_to_key(0xffbe), Some(KbKey::F1));
+        assert_eq!(keysym_to_key(0xffc9), Some(KbKey::F12));
+        assert_eq!(keysym_to_key(0xfe03), Some(KbKey::AltGraph));
+        assert_eq!(keysym_to_key(0xfe51), Some(KbKey::Dead));
+        // `a` produces text.
+        assert_eq!(keysym_to_key(0x61), None);
+    }
+
+    #[test]
+    fn compose_sequences() {
+        let fallback = || KbKey::Unidentified;
+        let a = || Some("a".to_string());
+        // dead_acute, then `a`.
+        assert_eq!(
+            key_for(Compose::Composing, 0xfe51, None, fallback),
+            Some(KbKey::Dead)
+        );
+        assert_eq!(
+            key_for(Compose::Composed(Some("á".into())), 0x61, a(), fallback),
+            Some(KbKey::Character("á".into()))
+        );
+        assert_eq!(key_for(Compose::Cancelled, 0x61, a(), fallback), None);
+        assert_eq!(
+            key_for(Compose::Ignored, 0x61, a(), fallback),
+            Some(KbKey::Character("a".into()))
+        );
+        // control characters are not text.
+        assert_eq!(
+            key_for(Compose::Ignored, 0x61, Some("\u{1}".into()), fallback),
+            Some(KbKey::Unidentified)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading the desktop settings that are shared through XSETTINGS.
+//!
+//! The settings manager of the desktop owns a selection for each screen, and keeps the settings
+//! in a property of the owner window.
+//!
+//! https://specifications.freedesktop.org/xsettings-spec/xsettings-spec-0.5.html
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use anyhow::{anyhow, Context, Error};
+use x11rb::protocol::xproto::ConnectionExt;
+use x11rb::xcb_ffi::XCBConnection;
+
+/// The value of a setting.
+// Only some of the settings are used so far.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Setting {
+    Int(i32),
+    String(String),
+    /// Red, green, blue and alpha.
+    Color([u16; 4]),
+}
+
+/// The settings of the screen `screen_num`, by name.
+///
+/// This is empty if no settings manager is running.
+pub(crate) fn read(
+    conn: &XCBConnection,
+    screen_num: i32,
+) -> Result<HashMap<String, Setting>, Error> {
+    let intern = |name: &str| -> Result<u32, Error> {
+        Ok(conn
+            .intern_atom(false, name.as_bytes())?
+            .reply()
+            .with_context(|| format!("intern {}", name))?
+            .atom)
+    };
+    let selection = intern(&format!("_XSETTINGS_S{}", screen_num))?;
+    let property = intern("_XSETTINGS_SETTINGS")?;
+
+    let owner = conn
+        .get_selection_owner(selection)?
+        .reply()
+        .context("get the XSETTINGS owner")?
+        .owner;
+    if owner == x11rb::NONE {
+        return Ok(HashMap::new());
+    }
+    let reply = conn
+        .get_property(false, owner, property, property, 0, u32::MAX / 4)?
+        .reply()
+        .context("get the XSETTINGS")?;
+    parse(&reply.value)
+}
+
+/// Parses the contents of the `_XSETTINGS_SETTINGS` property.
+fn parse(data: &[u8]) -> Result<HashMap<String, Setting>, Error> {
+    let mut reader = Reader {
+        data,
+        big_endian: false,
+    };
+    reader.big_endian = match reader.u8()? {
+        0 => false,
+        1 => true,
+        order => return Err(anyhow!("invalid XSETTINGS byte order {}", order)),
+    };
+    reader.skip(3)?;
+    let _serial = reader.u32()?;
+    let count = reader.u32()?;
+
+    let mut settings = HashMap::new();
+    for _ in 0..count {
+        let kind = reader.u8()?;
+        reader.skip(1)?;
+        let name_len = reader.u16()? as usize;
+        let name = reader.bytes(name_len)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        reader.skip(pad(name_len))?;
+        let _last_change_serial = reader.u32()?;
+        let value = match kind {
+            0 => Setting::Int(reader.u32()? as i32),
+            1 => {
+                let len = reader.u32()? as usize;
+                let value = String::from_utf8_lossy(reader.bytes(len)?).into_owned();
+                reader.skip(pad(len))?;
+                Setting::String(value)
+            }
+            2 => {
+                // Note the order, which is not the usual one.
+                let red = reader.u16()?;
+                let blue = reader.u16()?;
+                let green = reader.u16()?;
+                let alpha = reader.u16()?;
+                Setting::Color([red, green, blue, alpha])
+            }
+            kind => return Err(anyhow!("unknown XSETTINGS type {} of {}", kind, name)),
+        };
+        settings.insert(name, value);
+    }
+    Ok(settings)
+}
+
+/// The number of bytes that pad `len` bytes to a multiple of four.
+fn pad(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < len {
+            return Err(anyhow!("XSETTINGS data is too short"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.bytes(2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.bytes(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_settings() {
+        let mut data = vec![0, 0, 0, 0];
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        // an integer, with a name that needs padding
+        data.extend_from_slice(&[0, 0, 19, 0]);
+        data.extend_from_slice(b"Net/DoubleClickTime\0");
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&400i32.to_le_bytes());
+        // a string
+        data.extend_from_slice(&[1, 0, 13, 0]);
+        data.extend_from_slice(b"Net/ThemeName\0\0\0");
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.extend_from_slice(b"Adwaita\0");
+        // a color, as red, blue, green and alpha
+        data.extend_from_slice(&[2, 0, 4, 0]);
+        data.extend_from_slice(b"Test");
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for channel in &[1u16, 3, 2, 4] {
+            data.extend_from_slice(&channel.to_le_bytes());
+        }
+
+        let settings = parse(&data).unwrap();
+        assert_eq!(
+            settings.get("Net/DoubleClickTime"),
+            Some(&Setting::Int(400))
+        );
+        assert_eq!(
+            settings.get("Net/ThemeName"),
+            Some(&Setting::String("Adwaita".into()))
+        );
+        assert_eq!(settings.get("Test"), Some(&Setting::Color([1, 2, 3, 4])));
+
+        assert!(parse(&data[..data.len() - 2]).is_err());
+    }
+}
@@ -136,6 +136,99 @@ impl Scale {
     pub fn px_to_dp_xy<T: Into<f64>>(self, x: T, y: T) -> (f64, f64) {
         (x.into() / self.x, y.into() / self.y)
     }
+
+    /// Rounds a point in display points to the nearest pixel corner.
+    pub fn snap_to_pixel(self, point: Point) -> Point {
+        Point::new(
+            (point.x * self.x).round() / self.x,
+            (point.y * self.y).round() / self.y,
+        )
+    }
+
+    /// Rounds the edges of a rectangle in display points to the nearest pixel boundaries.
+    pub fn snap_rect(self, rect: Rect) -> Rect {
+        Rect::from_points(
+            self.snap_to_pixel(Point::new(rect.x0, rect.y0)),
+            self.snap_to_pixel(Point::new(rect.x1, rect.y1)),
+        )
+    }
+
+    /// Rounds a stroke width in display points to a whole number of pixels, but at least one.
+    ///
+    /// If the axes have different scale factors, the larger one is used.
+    pub fn snap_stroke_width(self, width: f64) -> f64 {
+        let scale = self.x.max(self.y);
+        self.stroke_pixels(width) / scale
+    }
+
+    /// Snaps the stroke of a rectangle so that it covers whole pixels.
+    ///
+    /// The edges of `rect` are rounded to pixel boundaries and the width to whole pixels,
+    /// and the path is placed according to `alignment`.
+    /// Returns the path to stroke and the width to stroke it with.
+    pub fn snap_stroke(self, rect: Rect, width: f64, alignment: StrokeAlignment) -> (Rect, f64) {
+        let pixels = self.stroke_pixels(width);
+        let width = self.snap_stroke_width(width);
+        let rect = self.snap_rect(rect);
+        let path = match alignment {
+            StrokeAlignment::Inside => rect.inset(-width / 2.0),
+            StrokeAlignment::Outside => rect.inset(width / 2.0),
+            // A stroke of an odd number of pixels has to be centered on a pixel,
+            // so it moves inside by half a pixel.
+            StrokeAlignment::Center if pixels % 2.0 == 1.0 => {
+                rect.inset(Insets::uniform_xy(-0.5 / self.x, -0.5 / self.y))
+            }
+            StrokeAlignment::Center => rect,
+        };
+        (path.abs(), width)
+    }
+
+    /// Snaps a horizontal or vertical line so that its stroke covers whole pixels.
+    ///
+    /// The ends of the line are rounded to pixel boundaries, and the line is moved across
+    /// to where a stroke of the snapped width is crisp. Other lines are returned unchanged.
+    /// Returns the line to stroke and the width to stroke it with.
+    pub fn snap_line(self, line: Line, width: f64) -> (Line, f64) {
+        let odd = self.stroke_pixels(width) % 2.0 == 1.0;
+        let width = self.snap_stroke_width(width);
+        let across = |value: f64, scale: f64| {
+            if odd {
+                ((value * scale).floor() + 0.5) / scale
+            } else {
+                (value * scale).round() / scale
+            }
+        };
+        let along = |value: f64, scale: f64| (value * scale).round() / scale;
+        let Line { p0, p1 } = line;
+        let line = if p0.y == p1.y {
+            let y = across(p0.y, self.y);
+            Line::new((along(p0.x, self.x), y), (along(p1.x, self.x), y))
+        } else if p0.x == p1.x {
+            let x = across(p0.x, self.x);
+            Line::new((x, along(p0.y, self.y)), (x, along(p1.y, self.y)))
+        } else {
+            line
+        };
+        (line, width)
+    }
+
+    /// The number of pixels a stroke of `width` display points covers when snapped.
+    fn stroke_pixels(self, width: f64) -> f64 {
+        (width * self.x.max(self.y)).round().max(1.0)
+    }
+}
+
+/// Where the stroke of a shape goes, relative to its outline.
+///
+/// This is used by [`Scale::snap_stroke`] to place a crisp stroke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrokeAlignment {
+    /// The stroke is inside the outline, like a border.
+    Inside,
+    /// The stroke is centered on the outline.
+    Center,
+    /// The stroke is outside the outline, like a focus ring.
+    Outside,
 }
 
 impl Scalable for Vec2 {
@@ -302,3 +395,41 @@ impl ScaledArea {
         self.size_px
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn snap_strokes() {
+        let scale = Scale::new(1.5, 1.5);
+        assert_eq!(
+            scale.snap_to_pixel(Point::new(1.0, 0.9)),
+            Point::new(4.0 / 3.0, 2.0 / 3.0)
+        );
+        assert_eq!(scale.snap_stroke_width(1.0), 4.0 / 3.0);
+        assert_eq!(scale.snap_stroke_width(0.1), 2.0 / 3.0);
+
+        // two pixels wide, so the border is one pixel inside the snapped rect
+        let (path, width) = scale.snap_stroke(
+            Rect::new(0.0, 0.0, 10.0, 10.0),
+            1.0,
+            StrokeAlignment::Inside,
+        );
+        assert_eq!(width, 4.0 / 3.0);
+        let edge = 2.0 / 3.0;
+        assert_eq!(path, Rect::new(edge, edge, 10.0 - edge, 10.0 - edge));
+
+        let scale = Scale::new(1.0, 1.0);
+        let (path, width) =
+            scale.snap_stroke(Rect::new(0.2, 0.2, 9.8, 9.8), 1.0, StrokeAlignment::Center);
+        assert_eq!(width, 1.0);
+        assert_eq!(path, Rect::new(0.5, 0.5, 9.5, 9.5));
+
+        let (line, width) = scale.snap_line(Line::new((0.2, 3.0), (9.8, 3.0)), 1.0);
+        assert_eq!(width, 1.0);
+        assert_eq!(line, Line::new((0.0, 3.5), (10.0, 3.5)));
+        let (line, _) = scale.snap_line(Line::new((3.2, 0.0), (3.2, 5.0)), 2.0);
+        assert_eq!(line, Line::new((3.0, 0.0), (3.0, 5.0)));
+    }
+}
@@ -14,7 +14,7 @@
 
 //! Module to get information about monitors
 
-use crate::kurbo::Rect;
+use crate::kurbo::{Point, Rect, Size};
 use crate::platform;
 use std::fmt;
 use std::fmt::Display;
@@ -30,7 +30,7 @@ pub struct Monitor {
     // https://developer.apple.com/documentation/appkit/nsscreen/1388369-visibleframe
     // https://developer.gnome.org/gdk3/stable/GdkMonitor.html#gdk-monitor-get-workarea
     // https://docs.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-monitorinfo
-    // Unsure about x11
+    // https://specifications.freedesktop.org/wm-spec/1.3/ar01s03.html (_NET_WORKAREA)
     work_rect: Rect,
 }
 
@@ -59,6 +59,151 @@ impl Monitor {
     pub fn virtual_work_rect(&self) -> Rect {
         self.work_rect
     }
+
+    /// Returns the position of a window of `size`, centered in the working
+    /// rectangle.
+    ///
+    /// If the window is larger than the working rectangle, it is aligned to
+    /// the top left of it instead, so that its title bar stays reachable.
+    pub fn center_in_work_rect(&self, size: Size) -> Point {
+        let work = self.work_rect;
+        let x = work.x0 + ((work.width() - size.width) / 2.0).max(0.0);
+        let y = work.y0 + ((work.height() - size.height) / 2.0).max(0.0);
+        Point::new(x.round(), y.round())
+    }
+
+    /// Moves `rect` as little as possible so that it lies inside the working
+    /// rectangle.
+    ///
+    /// The size of `rect` is kept. If it is larger than the working rectangle,
+    /// it is aligned to the top left of it.
+    pub fn fit_in_work_rect(&self, rect: Rect) -> Rect {
+        let work = self.work_rect;
+        let x = rect.x0.min(work.x1 - rect.width()).max(work.x0);
+        let y = rect.y0.min(work.y1 - rect.height()).max(work.y0);
+        rect.with_origin((x, y))
+    }
+
+    /// The distance from `point` to the monitor, which is zero if the monitor
+    /// contains it.
+    fn distance_to(&self, point: Point) -> f64 {
+        let rect = self.rect;
+        let nearest = Point::new(
+            point.x.max(rect.x0).min(rect.x1),
+            point.y.max(rect.y0).min(rect.y1),
+        );
+        nearest.distance(point)
+    }
+}
+
+/// The side of an anchor rectangle that a popup, such as a dropdown, tooltip
+/// or menu, prefers to go on.
+///
+/// This is used with [`AnchorSide::place`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnchorSide {
+    /// Below the anchor, aligned with its left edge.
+    Below,
+    /// Above the anchor, aligned with its left edge.
+    Above,
+    /// Left of the anchor, aligned with its top edge.
+    Start,
+    /// Right of the anchor, aligned with its top edge.
+    End,
+}
+
+impl AnchorSide {
+    /// The side across the anchor from this one.
+    pub fn flipped(self) -> AnchorSide {
+        match self {
+            AnchorSide::Below => AnchorSide::Above,
+            AnchorSide::Above => AnchorSide::Below,
+            AnchorSide::Start => AnchorSide::End,
+            AnchorSide::End => AnchorSide::Start,
+        }
+    }
+
+    /// Returns the origin for a popup of `size` next to `anchor`, keeping it
+    /// inside `bounds`, which is usually the [working rectangle] of the
+    /// monitor.
+    ///
+    /// If the popup doesn't fit on this side of the anchor, it goes on the
+    /// other side. If it doesn't fit where it is aligned with the anchor, it
+    /// is aligned with the anchor's other edge instead. Whatever still sticks
+    /// out is then pushed back inside `bounds`.
+    ///
+    /// [working rectangle]: Monitor::virtual_work_rect
+    pub fn place(self, anchor: Rect, size: Size, bounds: Rect) -> Point {
+        let Point { mut x, mut y } = self.place_unchecked(anchor, size);
+        match self {
+            AnchorSide::Below | AnchorSide::Above => {
+                if y < bounds.y0 || y + size.height > bounds.y1 {
+                    y = self.flipped().place_unchecked(anchor, size).y;
+                }
+                if x + size.width > bounds.x1 {
+                    x = anchor.x1 - size.width;
+                }
+            }
+            AnchorSide::Start | AnchorSide::End => {
+                if x < bounds.x0 || x + size.width > bounds.x1 {
+                    x = self.flipped().place_unchecked(anchor, size).x;
+                }
+                if y + size.height > bounds.y1 {
+                    y = anchor.y1 - size.height;
+                }
+            }
+        }
+        let max_x = (bounds.x1 - size.width).max(bounds.x0);
+        let max_y = (bounds.y1 - size.height).max(bounds.y0);
+        Point::new(x.min(max_x).max(bounds.x0), y.min(max_y).max(bounds.y0))
+    }
+
+    /// The origin on this side of the anchor, ignoring any bounds.
+    fn place_unchecked(self, anchor: Rect, size: Size) -> Point {
+        match self {
+            AnchorSide::Below => Point::new(anchor.x0, anchor.y1),
+            AnchorSide::Above => Point::new(anchor.x0, anchor.y0 - size.height),
+            AnchorSide::Start => Point::new(anchor.x0 - size.width, anchor.y0),
+            AnchorSide::End => Point::new(anchor.x1, anchor.y0),
+        }
+    }
+}
+
+/// Chooses one of the [`Monitor`]s of the system.
+///
+/// This is used with [`Screen::get_monitor`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MonitorSelector {
+    /// The primary monitor.
+    Primary,
+    /// The monitor at this index in [`Screen::get_monitors`].
+    ///
+    /// If there is no such monitor, the primary monitor is chosen.
+    Index(usize),
+    /// The monitor that contains this point, in virtual screen coordinates,
+    /// or else the one nearest to it.
+    Containing(Point),
+}
+
+impl MonitorSelector {
+    /// Picks a monitor out of `monitors`.
+    fn select(self, monitors: &[Monitor]) -> Option<&Monitor> {
+        let primary = || {
+            monitors
+                .iter()
+                .find(|m| m.primary)
+                .or_else(|| monitors.first())
+        };
+        match self {
+            MonitorSelector::Primary => primary(),
+            MonitorSelector::Index(index) => monitors.get(index).or_else(primary),
+            MonitorSelector::Containing(point) => monitors.iter().min_by(|a, b| {
+                a.distance_to(point)
+                    .partial_cmp(&b.distance_to(point))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            }),
+        }
+    }
 }
 
 impl Display for Monitor {
@@ -87,6 +232,13 @@ impl Screen {
         platform::screen::get_monitors()
     }
 
+    /// Returns the monitor chosen by `selector`.
+    ///
+    /// This is `None` only if the platform doesn't report any monitors.
+    pub fn get_monitor(selector: MonitorSelector) -> Option<Monitor> {
+        selector.select(&Self::get_monitors()).cloned()
+    }
+
     /// Returns the bounding rectangle of the total virtual screen space in pixels.
     pub fn get_display_rect() -> Rect {
         Self::get_monitors()
@@ -95,3 +247,103 @@ impl Screen {
             .fold(Rect::ZERO, |a, b| a.union(b))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn monitors() -> Vec<Monitor> {
+        vec![
+            Monitor::new(
+                false,
+                Rect::new(-1280.0, 0.0, 0.0, 1024.0),
+                Rect::new(-1280.0, 0.0, 0.0, 1024.0),
+            ),
+            // with a taskbar at the bottom
+            Monitor::new(
+                true,
+                Rect::new(0.0, 0.0, 1920.0, 1080.0),
+                Rect::new(0.0, 0.0, 1920.0, 1040.0),
+            ),
+        ]
+    }
+
+    #[test]
+    fn select_monitor() {
+        let monitors = monitors();
+        let select = |selector: MonitorSelector| selector.select(&monitors).unwrap().primary;
+        assert!(select(MonitorSelector::Primary));
+        assert!(!select(MonitorSelector::Index(0)));
+        assert!(select(MonitorSelector::Index(5)));
+        assert!(!select(MonitorSelector::Containing(Point::new(
+            -10.0, 500.0
+        ))));
+        assert!(select(MonitorSelector::Containing(Point::new(
+            100.0, 500.0
+        ))));
+        // below both monitors, but nearer to the second one
+        assert!(select(MonitorSelector::Containing(Point::new(
+            100.0, 1500.0
+        ))));
+        assert!(MonitorSelector::Primary.select(&[]).is_none());
+    }
+
+    #[test]
+    fn anchored_placement() {
+        let bounds = Rect::new(0., 0., 1000., 800.);
+        let size = Size::new(200., 300.);
+        let button = |x, y| Rect::new(x, y, x + 100., y + 30.);
+
+        assert_eq!(
+            AnchorSide::Below.place(button(10., 20.), size, bounds),
+            Point::new(10., 50.)
+        );
+        // flipped above near the bottom, and aligned with the right edge
+        // near the right side
+        assert_eq!(
+            AnchorSide::Below.place(button(850., 600.), size, bounds),
+            Point::new(750., 300.)
+        );
+        assert_eq!(
+            AnchorSide::Above.place(button(10., 20.), size, bounds),
+            Point::new(10., 50.)
+        );
+        assert_eq!(
+            AnchorSide::End.place(button(10., 20.), size, bounds),
+            Point::new(110., 20.)
+        );
+        assert_eq!(
+            AnchorSide::Start.place(button(10., 20.), size, bounds),
+            Point::new(110., 20.)
+        );
+        // too big to fit either way
+        let tall = Size::new(200., 900.);
+        assert_eq!(
+            AnchorSide::Below.place(button(10., 20.), tall, bounds),
+            Point::new(10., 0.)
+        );
+    }
+
+    #[test]
+    fn work_rect_placement() {
+        let primary = &monitors()[1];
+        assert_eq!(
+            primary.center_in_work_rect(Size::new(400.0, 300.0)),
+            Point::new(760.0, 370.0)
+        );
+        assert_eq!(
+            primary.center_in_work_rect(Size::new(2000.0, 300.0)),
+            Point::new(0.0, 370.0)
+        );
+        // straddling the taskbar and the right edge
+        assert_eq!(
+            primary.fit_in_work_rect(Rect::new(1800.0, 1000.0, 2000.0, 1100.0)),
+            Rect::new(1720.0, 940.0, 1920.0, 1040.0)
+        );
+        // straddling both monitors
+        assert_eq!(
+            primary.fit_in_work_rect(Rect::new(-100.0, 10.0, 100.0, 110.0)),
+            Rect::new(0.0, 10.0, 200.0, 110.0)
+        );
+    }
+}
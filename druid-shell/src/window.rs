@@ -18,13 +18,17 @@ use std::any::Any;
 use std::time::Duration;
 
 use crate::application::Application;
+use crate::clipboard::Clipboard;
 use crate::common_util::Counter;
 use crate::dialog::{FileDialogOptions, FileInfo};
+use crate::drag::{DropEvent, FilePromise};
 use crate::error::Error;
+use crate::hotkey::HotKey;
 use crate::keyboard::KeyEvent;
 use crate::kurbo::{Insets, Point, Rect, Size};
 use crate::menu::Menu;
 use crate::mouse::{Cursor, CursorDesc, MouseEvent};
+use crate::piet::{Color, ImageBuf};
 use crate::platform::window as platform;
 use crate::region::Region;
 use crate::scale::Scale;
@@ -160,6 +164,15 @@ pub enum WindowLevel {
     Modal,
 }
 
+/// How urgently an announcement should be read out by a screen reader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Politeness {
+    /// Wait until the screen reader has finished what it is saying.
+    Polite,
+    /// Interrupt whatever the screen reader is saying.
+    Assertive,
+}
+
 /// Contains the different states a Window can be in.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WindowState {
@@ -277,6 +290,28 @@ impl WindowHandle {
         self.0.set_level(level)
     }
 
+    /// Set whether the window should stay above other windows.
+    ///
+    /// This is not implemented on the web.
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.0.set_always_on_top(always_on_top)
+    }
+
+    /// Set whether the window should cover the whole screen, without decorations.
+    ///
+    /// This is not implemented on the web.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.0.set_fullscreen(fullscreen)
+    }
+
+    /// Set the icon that the system shows for the window, such as in the task bar.
+    ///
+    /// Windows don't have their own icons on macOS, so there this sets the application's
+    /// icon in the Dock. This is not implemented on the web.
+    pub fn set_icon(&self, icon: &ImageBuf) {
+        self.0.set_icon(icon)
+    }
+
     /// Bring this window to the front of the window stack and give it focus.
     pub fn bring_to_front_and_focus(&self) {
         self.0.bring_to_front_and_focus()
@@ -304,16 +339,73 @@ impl WindowHandle {
         self.0.invalidate_rect(rect);
     }
 
+    /// Set the color that the window is cleared to before it is painted.
+    ///
+    /// This is what the platform shows in parts of the window that have not
+    /// been painted yet, such as when the window is first shown or grows.
+    pub fn set_background_color(&self, color: Color) {
+        self.0.set_background_color(color)
+    }
+
+    /// Make the size of the window's content area change in steps of `width`
+    /// by `height` [display points](crate::Scale) when the user resizes it,
+    /// like a terminal emulator that is sized in whole character cells.
+    ///
+    /// Passing zero for both removes the increments.
+    ///
+    /// On macOS, a window can have either resize increments or an aspect
+    /// ratio, but not both; setting one removes the other.
+    pub fn set_resize_increments(&self, width: f64, height: f64) {
+        self.0.set_resize_increments(width, height)
+    }
+
+    /// Keep the ratio of the width to the height of the window's content area
+    /// at `numerator` to `denominator` when the user resizes it, like a video
+    /// player does.
+    ///
+    /// Passing zero for either removes the constraint.
+    pub fn set_aspect_ratio(&self, numerator: u32, denominator: u32) {
+        self.0.set_aspect_ratio(numerator, denominator)
+    }
+
     /// Set the title for this menu.
     pub fn set_title(&self, title: &str) {
         self.0.set_title(title)
     }
 
+    /// Ask screen readers to read out `text`, for instance because a status
+    /// message changed.
+    ///
+    /// This is currently implemented on macOS and the web; elsewhere it only
+    /// logs a warning.
+    pub fn announce(&self, text: &str, politeness: Politeness) {
+        self.0.announce(text, politeness)
+    }
+
     /// Set the top-level menu for this window.
     pub fn set_menu(&self, menu: Menu) {
         self.0.set_menu(menu.into_inner())
     }
 
+    /// Change an item of this window's menu, without replacing the whole menu.
+    ///
+    /// `id` is the id that the item was added with, and the other arguments are as for
+    /// [`Menu::add_item`]. The hotkey of an item can not be changed this way, so `key` has to be
+    /// the one that the item was added with. If the menu has no item with this `id`, this does
+    /// nothing.
+    ///
+    /// GTK does not show whether items are selected, so `selected` has no effect there.
+    pub fn update_menu_item(
+        &self,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+        selected: bool,
+    ) {
+        self.0.update_menu_item(id, text, key, enabled, selected)
+    }
+
     /// Get access to a type that can perform text layout.
     pub fn text(&self) -> PietText {
         self.0.text()
@@ -406,6 +498,20 @@ impl WindowHandle {
         self.0.show_context_menu(menu.into_inner(), pos)
     }
 
+    /// Start dragging a file out of the window, whose contents are only
+    /// written when it is dropped.
+    ///
+    /// This should be called while a mouse button is held down, usually when
+    /// the mouse has moved far enough from where the button was pressed. The
+    /// platform takes over the mouse for the rest of the drag, so no
+    /// mouse-up event is delivered for that button.
+    ///
+    /// This is currently implemented on Windows and macOS; elsewhere it only
+    /// logs a warning.
+    pub fn start_file_drag(&self, promise: FilePromise) {
+        self.0.start_file_drag(promise)
+    }
+
     /// Get a handle that can be used to schedule an idle task.
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         self.0.get_idle_handle().map(IdleHandle)
@@ -419,6 +525,49 @@ impl WindowHandle {
     pub fn get_scale(&self) -> Result<Scale, Error> {
         self.0.get_scale().map_err(Into::into)
     }
+
+    /// Embed a native window that was created outside of druid-shell, such as
+    /// a plugin's editor or a legacy control, in this window.
+    ///
+    /// On Windows the child becomes a `WS_CHILD` of this window, on X11 it is
+    /// reparented, and on macOS its `NSView` is added as a subview. The child
+    /// is detached again when the returned [`ForeignWindow`] is dropped.
+    ///
+    /// This is not supported on GTK or the web.
+    ///
+    /// # Safety
+    ///
+    /// `child` must be a valid handle to a window of the same platform, and it
+    /// must stay alive for as long as the `ForeignWindow`.
+    #[cfg(feature = "raw-win-handle")]
+    pub unsafe fn embed_foreign_window(
+        &self,
+        child: RawWindowHandle,
+    ) -> Result<ForeignWindow, Error> {
+        self.0
+            .embed_foreign_window(child)
+            .map(ForeignWindow)
+            .map_err(Into::into)
+    }
+
+    /// Create a native child window covering `frame`, for a renderer such as
+    /// wgpu or OpenGL to present into.
+    ///
+    /// `frame` is in display points, relative to the content area of this
+    /// window. The surface is drawn above this window's content, so druid
+    /// never paints over it; mouse events over it are still delivered to this
+    /// window. The surface implements `HasRawWindowHandle`, which is what
+    /// most graphics libraries need to create their swap chain. On X11 it
+    /// has the same visual as this window.
+    ///
+    /// This is supported on Windows, X11 and macOS.
+    #[cfg(feature = "raw-win-handle")]
+    pub fn create_render_surface(&self, frame: Rect) -> Result<RenderSurface, Error> {
+        self.0
+            .create_render_surface(frame)
+            .map(RenderSurface)
+            .map_err(Into::into)
+    }
 }
 
 #[cfg(feature = "raw-win-handle")]
@@ -428,6 +577,56 @@ unsafe impl HasRawWindowHandle for WindowHandle {
     }
 }
 
+/// A native window, created outside of druid-shell, that is embedded in one of
+/// our windows.
+///
+/// This is created with [`WindowHandle::embed_foreign_window`]. The embedded
+/// window is detached from its parent when this is dropped.
+#[cfg(feature = "raw-win-handle")]
+pub struct ForeignWindow(platform::ForeignWindow);
+
+#[cfg(feature = "raw-win-handle")]
+impl ForeignWindow {
+    /// Move and resize the embedded window.
+    ///
+    /// `frame` is in display points, relative to the content area of the
+    /// parent window.
+    pub fn set_frame(&self, frame: Rect) {
+        self.0.set_frame(frame)
+    }
+
+    /// Give keyboard focus to the embedded window.
+    pub fn focus(&self) {
+        self.0.focus()
+    }
+}
+
+/// A native child window that druid-shell doesn't draw to, for external
+/// renderers to present into.
+///
+/// This is created with [`WindowHandle::create_render_surface`]. The child
+/// window is destroyed when this is dropped.
+#[cfg(feature = "raw-win-handle")]
+pub struct RenderSurface(platform::RenderSurface);
+
+#[cfg(feature = "raw-win-handle")]
+impl RenderSurface {
+    /// Move and resize the surface.
+    ///
+    /// `frame` is in display points, relative to the content area of the
+    /// parent window.
+    pub fn set_frame(&self, frame: Rect) {
+        self.0.set_frame(frame)
+    }
+}
+
+#[cfg(feature = "raw-win-handle")]
+unsafe impl HasRawWindowHandle for RenderSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.0.raw_window_handle()
+    }
+}
+
 /// A builder type for creating new windows.
 pub struct WindowBuilder(platform::WindowBuilder);
 
@@ -500,6 +699,15 @@ impl WindowBuilder {
         self.0.set_level(level);
     }
 
+    /// Set the color that the window is cleared to before it is painted.
+    ///
+    /// Setting this to the background color of the application avoids a
+    /// flash of the platform's default color (often white) when the window
+    /// is first shown. It has no effect on transparent windows.
+    pub fn set_background_color(&mut self, color: Color) {
+        self.0.set_background_color(color);
+    }
+
     /// Set the window's initial title.
     pub fn set_title(&mut self, title: impl Into<String>) {
         self.0.set_title(title)
@@ -515,6 +723,28 @@ impl WindowBuilder {
         self.0.set_window_state(state);
     }
 
+    /// Set whether the window should stay above other windows.
+    ///
+    /// This is not implemented on the web.
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.0.set_always_on_top(always_on_top);
+    }
+
+    /// Set whether the window should start out covering the whole screen.
+    ///
+    /// This is not implemented on the web.
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.0.set_fullscreen(fullscreen);
+    }
+
+    /// Set the icon that the system shows for the window, such as in the task bar.
+    ///
+    /// Windows don't have their own icons on macOS, so there this sets the application's
+    /// icon in the Dock. This is not implemented on the web.
+    pub fn set_icon(&mut self, icon: ImageBuf) {
+        self.0.set_icon(icon);
+    }
+
     /// Attempt to construct the platform window.
     ///
     /// If this fails, your application should exit.
@@ -586,6 +816,17 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn open_file(&mut self, token: FileDialogToken, file: Option<FileInfo>) {}
 
+    /// Called when an "Open" dialog that may return several files is closed.
+    ///
+    /// `token` is the value returned by [`WindowHandle::open_file`]. `files` contains the chosen
+    /// paths, and is empty if the dialog was cancelled. Backends that can return several files
+    /// (currently only X11) call this instead of [`WinHandler::open_file`].
+    ///
+    /// The default implementation calls [`WinHandler::open_file`] with the first file.
+    fn open_files(&mut self, token: FileDialogToken, files: Vec<FileInfo>) {
+        self.open_file(token, files.into_iter().next())
+    }
+
     /// Called on a key down event.
     ///
     /// Return `true` if the event is handled.
@@ -659,6 +900,32 @@ pub trait WinHandler {
     /// Called when the mouse cursor has left the application window
     fn mouse_leave(&mut self) {}
 
+    /// Called when something is dragged into the window, and each time it
+    /// moves while over it.
+    ///
+    /// Return `true` if it may be dropped at `event.pos`. This is currently
+    /// only called on X11.
+    #[allow(unused_variables)]
+    fn drag_over(&mut self, event: &DropEvent) -> bool {
+        false
+    }
+
+    /// Called when something that was dragged into the window leaves it, or
+    /// the drag is cancelled.
+    fn drag_leave(&mut self) {}
+
+    /// Called when something is dropped on the window, at a position where
+    /// [`drag_over`] accepted it.
+    ///
+    /// The data can be read from `data`, in any of `event.formats`. Return
+    /// `true` if the drop was handled.
+    ///
+    /// [`drag_over`]: WinHandler::drag_over
+    #[allow(unused_variables)]
+    fn dropped(&mut self, event: &DropEvent, data: &Clipboard) -> bool {
+        false
+    }
+
     /// Called on timer event.
     ///
     /// This is called at (approximately) the requested deadline by a
@@ -675,6 +942,31 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn lost_focus(&mut self) {}
 
+    /// Called when there is nothing to paint the window on, such as when it is
+    /// minimized.
+    ///
+    /// Until [`resumed`] is called, the window will not be painted, and
+    /// requests for animation frames are ignored.
+    ///
+    /// This is called when the window is minimized on Windows, macOS, GTK and
+    /// X11; the web backend never suspends windows.
+    ///
+    /// [`resumed`]: WinHandler::resumed
+    fn suspended(&mut self) {}
+
+    /// Called when a window that was [`suspended`] can be painted again.
+    ///
+    /// [`suspended`]: WinHandler::suspended
+    fn resumed(&mut self) {}
+
+    /// Called when the user changes system settings that affect the
+    /// application, such as those returned by [`Application::get_text_settings`]
+    /// and [`Application::get_mouse_settings`].
+    ///
+    /// [`Application::get_text_settings`]: crate::Application::get_text_settings
+    /// [`Application::get_mouse_settings`]: crate::Application::get_mouse_settings
+    fn system_settings_changed(&mut self) {}
+
     /// Called when the shell requests to close the window, for example because the user clicked
     /// the little "X" in the titlebar.
     ///
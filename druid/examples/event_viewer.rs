@@ -155,16 +155,14 @@ impl LoggedEvent {
     fn mouse_button(&self) -> String {
         self.mouse
             .as_ref()
-            .map(|m| {
-                match m.button {
-                    MouseButton::Left => "Left",
-                    MouseButton::Right => "Right",
-                    MouseButton::X1 => "X1",
-                    MouseButton::X2 => "X2",
-                    MouseButton::None => "",
-                    MouseButton::Middle => "Middle",
-                }
-                .into()
+            .map(|m| match m.button {
+                MouseButton::Left => "Left".into(),
+                MouseButton::Right => "Right".into(),
+                MouseButton::X1 => "Back".into(),
+                MouseButton::X2 => "Forward".into(),
+                MouseButton::Other(n) => format!("Other({})", n),
+                MouseButton::None => "".into(),
+                MouseButton::Middle => "Middle".into(),
             })
             .unwrap_or_default()
     }
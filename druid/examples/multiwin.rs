@@ -33,10 +33,11 @@ struct State {
 }
 
 pub fn main() {
-    let main_window = WindowDesc::new(ui_builder()).menu(make_menu).title(
+    let main_window = WindowDesc::new(ui_builder()).title(
         LocalizedString::new("multiwin-demo-window-title").with_placeholder("Many windows!"),
     );
     AppLauncher::with_window(main_window)
+        .menu(make_menu)
         .delegate(Delegate {
             windows: Vec::new(),
         })
@@ -155,7 +156,6 @@ impl AppDelegate<State> for Delegate {
     ) -> Handled {
         if cmd.is(sys_cmds::NEW_FILE) {
             let new_win = WindowDesc::new(ui_builder())
-                .menu(make_menu)
                 .window_size((data.selected as f64 * 100.0 + 300.0, 500.0));
             ctx.new_window(new_win);
             Handled::Yes
@@ -190,7 +190,7 @@ impl AppDelegate<State> for Delegate {
 }
 
 #[allow(unused_assignments)]
-fn make_menu(_: Option<WindowId>, state: &State, _: &Env) -> Menu<State> {
+fn make_menu(_: Option<WindowId>, _: &State, _: &Env) -> Menu<State> {
     let mut base = Menu::empty();
     #[cfg(target_os = "macos")]
     {
@@ -200,23 +200,27 @@ fn make_menu(_: Option<WindowId>, state: &State, _: &Env) -> Menu<State> {
     {
         base = base.entry(druid::platform_menus::win::file::default());
     }
-    if state.menu_count != 0 {
-        let mut custom = Menu::new(LocalizedString::new("Custom"));
-
-        for i in 1..=state.menu_count {
-            custom = custom.entry(
-                MenuItem::new(
-                    LocalizedString::new("hello-counter")
-                        .with_arg("count", move |_: &State, _| i.into()),
-                )
-                .on_activate(move |_ctx, data, _env| data.selected = i)
-                .enabled_if(move |_data, _env| i % 3 != 0)
-                .selected_if(move |data, _env| i == data.selected),
-            );
-        }
-        base = base.entry(custom);
-    }
-    base.rebuild_on(|old_data, data, _env| old_data.menu_count != data.menu_count)
+    // Only the entries of this menu are rebuilt when the count changes, not the whole menu.
+    let custom = Menu::new(LocalizedString::new("Custom"))
+        .enabled_if(|data: &State, _env| data.menu_count != 0)
+        .dynamic_entries(
+            |old_data, data, _env| old_data.menu_count != data.menu_count,
+            |data, _env| {
+                (1..=data.menu_count)
+                    .map(|i| {
+                        MenuItem::new(
+                            LocalizedString::new("hello-counter")
+                                .with_arg("count", move |_: &State, _| i.into()),
+                        )
+                        .on_activate(move |_ctx, data: &mut State, _env| data.selected = i)
+                        .enabled_if(move |_data, _env| i % 3 != 0)
+                        .selected_if(move |data, _env| i == data.selected)
+                        .into()
+                    })
+                    .collect()
+            },
+        );
+    base.entry(custom)
 }
 
 fn make_context_menu() -> Menu<State> {
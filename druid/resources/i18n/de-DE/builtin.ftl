@@ -39,3 +39,6 @@ common-menu-copy = Kopieren
 common-menu-paste = Einfügen
 common-menu-undo = Rückgängig
 common-menu-redo = Wiederherstellen
+common-menu-zoom-in = Vergrößern
+common-menu-zoom-out = Verkleinern
+common-menu-reset-zoom = Originalgröße
@@ -39,3 +39,6 @@ common-menu-copy = Copy
 common-menu-paste = Paste
 common-menu-undo = Undo
 common-menu-redo = Redo
+common-menu-zoom-in = Zoom In
+common-menu-zoom-out = Zoom Out
+common-menu-reset-zoom = Actual Size
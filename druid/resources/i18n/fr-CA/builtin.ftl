@@ -39,3 +39,6 @@ common-menu-copy = Copier
 common-menu-paste = Coller
 common-menu-undo = Annuler
 common-menu-redo = Rétablir
+common-menu-zoom-in = Zoom avant
+common-menu-zoom-out = Zoom arrière
+common-menu-reset-zoom = Taille réelle
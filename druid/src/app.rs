@@ -14,14 +14,20 @@
 
 //! Window building and app lifecycle.
 
-use crate::ext_event::{ExtEventHost, ExtEventSink};
-use crate::kurbo::{Point, Size};
-use crate::menu::MenuManager;
-use crate::shell::{Application, Error as PlatformError, WindowBuilder, WindowHandle, WindowLevel};
+use crate::ext_event::{Coalesce, ExtEventHost, ExtEventSink};
+use crate::kurbo::{Point, Rect, Size};
+use crate::menu::{AppMenu, MenuCustomize, MenuManager};
+use crate::shell::{
+    AnchorSide, Application, Error as PlatformError, MonitorSelector, Screen, WindowBuilder,
+    WindowHandle, WindowLevel,
+};
 use crate::widget::LabelText;
 use crate::win_handler::{AppHandler, AppState};
 use crate::window::WindowId;
-use crate::{AppDelegate, Data, Env, LocalizedString, Menu, Widget};
+use crate::{
+    theme, AppDelegate, Color, Data, Env, LocalizationBundle, LocalizedString, Menu, Selector,
+    Widget,
+};
 
 use druid_shell::WindowState;
 
@@ -33,8 +39,14 @@ pub struct AppLauncher<T> {
     windows: Vec<WindowDesc<T>>,
     env_setup: Option<Box<EnvSetupFn<T>>>,
     l10n_resources: Option<(Vec<String>, String)>,
+    l10n_bundles: Vec<LocalizationBundle>,
     delegate: Option<Box<dyn AppDelegate<T>>>,
+    menu: Option<AppMenu<T>>,
     ext_event_host: ExtEventHost,
+    #[cfg(all(feature = "tracing-chrome", not(target_arch = "wasm32")))]
+    chrome_trace_guard: Option<tracing_chrome::FlushGuard>,
+    #[cfg(feature = "data-diff")]
+    data_diff: Option<fn(&T, &T) -> crate::DataDiff>,
 }
 
 /// Defines how a windows size should be determined
@@ -57,8 +69,10 @@ pub struct WindowConfig {
     pub(crate) size: Option<Size>,
     pub(crate) min_size: Option<Size>,
     pub(crate) position: Option<Point>,
+    pub(crate) placement: Option<Placement>,
     pub(crate) resizable: Option<bool>,
     pub(crate) transparent: Option<bool>,
+    pub(crate) background_color: Option<Color>,
     pub(crate) show_titlebar: Option<bool>,
     pub(crate) level: Option<WindowLevel>,
     pub(crate) state: Option<WindowState>,
@@ -83,8 +97,47 @@ pub struct PendingWindow<T> {
     pub(crate) title: LabelText<T>,
     pub(crate) transparent: bool,
     pub(crate) menu: Option<MenuManager<T>>,
+    pub(crate) customize_menu: Option<MenuCustomize<T>>,
+    // Copied over from the WindowConfig, like `size_policy`.
+    pub(crate) background_color: Option<Color>,
     pub(crate) size_policy: WindowSizePolicy, // This is copied over from the WindowConfig
-                                              // when the native window is constructed.
+    // when the native window is constructed.
+    /// Where to put the window once its size is known, if it couldn't be
+    /// placed when it was built.
+    pub(crate) placement: Option<Placement>,
+}
+
+/// A position for a window that depends on its size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Placement {
+    /// Centered in the work area of a monitor.
+    Centered(MonitorSelector),
+    /// Next to a rectangle, in screen coordinates.
+    Anchored(Rect, AnchorSide),
+}
+
+impl Placement {
+    /// The position of a window of `size`.
+    pub(crate) fn position(self, size: Size) -> Option<Point> {
+        match self {
+            Placement::Centered(selector) => {
+                Some(Screen::get_monitor(selector)?.center_in_work_rect(size))
+            }
+            Placement::Anchored(anchor, side) => {
+                let bounds = Screen::get_monitor(MonitorSelector::Containing(anchor.center()))
+                    .map(|monitor| monitor.virtual_work_rect())
+                    .unwrap_or_else(|| {
+                        Rect::new(
+                            f64::NEG_INFINITY,
+                            f64::NEG_INFINITY,
+                            f64::INFINITY,
+                            f64::INFINITY,
+                        )
+                    });
+                Some(side.place(anchor, size, bounds))
+            }
+        }
+    }
 }
 
 impl<T: Data> PendingWindow<T> {
@@ -97,9 +150,12 @@ impl<T: Data> PendingWindow<T> {
         PendingWindow {
             root: Box::new(root),
             title: LocalizedString::new("app-name").into(),
-            menu: MenuManager::platform_default(),
+            menu: None,
+            customize_menu: None,
             transparent: false,
             size_policy: WindowSizePolicy::User,
+            background_color: None,
+            placement: None,
         }
     }
 
@@ -132,6 +188,19 @@ impl<T: Data> PendingWindow<T> {
         self.menu = Some(MenuManager::new(menu));
         self
     }
+
+    /// Add to the application's menu for this window.
+    ///
+    /// `customize` is called with the menu set with [`AppLauncher::menu`], whenever that menu is
+    /// built for this window, and returns the menu this window should have. This has no effect if
+    /// the window has its own menu.
+    pub fn customize_menu(
+        mut self,
+        customize: impl FnMut(Menu<T>, WindowId, &T, &Env) -> Menu<T> + 'static,
+    ) -> Self {
+        self.customize_menu = Some(Box::new(customize));
+        self
+    }
 }
 
 impl<T: Data> AppLauncher<T> {
@@ -141,11 +210,34 @@ impl<T: Data> AppLauncher<T> {
             windows: vec![window],
             env_setup: None,
             l10n_resources: None,
+            l10n_bundles: Vec::new(),
             delegate: None,
+            menu: None,
             ext_event_host: ExtEventHost::new(),
+            #[cfg(all(feature = "tracing-chrome", not(target_arch = "wasm32")))]
+            chrome_trace_guard: None,
+            #[cfg(feature = "data-diff")]
+            data_diff: None,
         }
     }
 
+    /// Set the menu that is shared by all windows.
+    ///
+    /// Windows that set their own menu with [`WindowDesc::menu`] don't use this one, and
+    /// windows can add to it with [`WindowDesc::customize_menu`]. As with [`WindowDesc::menu`],
+    /// the first argument of `menu` is the id of the window that will have the menu, or `None` if
+    /// it's building the menu for when there are no windows, on macOS.
+    ///
+    /// Each window keeps its menu up to date with the data on its own, so checkmarks and enabled
+    /// states are shared, while rebuilding the menu of one window leaves the others alone.
+    pub fn menu(
+        mut self,
+        menu: impl FnMut(Option<WindowId>, &T, &Env) -> Menu<T> + 'static,
+    ) -> Self {
+        self.menu = Some(AppMenu::new(menu));
+        self
+    }
+
     /// Provide an optional closure that will be given mutable access to
     /// the environment and immutable access to the app state before launch.
     ///
@@ -211,6 +303,62 @@ impl<T: Data> AppLauncher<T> {
         self
     }
 
+    /// Initialize a tracing subscriber that records everything, down to TRACE
+    /// level, into a file in the Chrome trace format.
+    ///
+    /// The file can be opened in `chrome://tracing` or [Perfetto]. Each pass
+    /// over the widget tree shows up as a span, and inside it there is a span
+    /// for every widget that took part, with the widget's type and id as
+    /// arguments; this makes it possible to see which widgets a slow frame
+    /// was spent in.
+    ///
+    /// The trace is written when the application exits.
+    ///
+    /// This requires the `tracing-chrome` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the subscriber fails to initialize.
+    ///
+    /// [Perfetto]: https://ui.perfetto.dev
+    #[cfg(all(feature = "tracing-chrome", not(target_arch = "wasm32")))]
+    pub fn log_to_chrome_trace(mut self, path: impl AsRef<std::path::Path>) -> Self {
+        use tracing_subscriber::prelude::*;
+        let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+            .file(path.as_ref())
+            .include_args(true)
+            .build();
+
+        tracing_subscriber::registry()
+            .with(tracing_subscriber::filter::LevelFilter::TRACE)
+            .with(chrome_layer)
+            .init();
+        self.chrome_trace_guard = Some(guard);
+        self
+    }
+
+    /// Log what changed in the app data before every update pass.
+    ///
+    /// The changes are found by comparing the `Debug` representations of the
+    /// data before and after, as described in [`DataDiff`], and are logged at
+    /// DEBUG level with the `druid::data_diff` target. When the data is
+    /// unchanged, or only replaced by an equal copy, that is logged instead;
+    /// together, this helps to find out why a widget did or didn't update.
+    ///
+    /// Formatting the whole app data on every update is slow, so this is
+    /// meant for use during development only. It requires the `data-diff`
+    /// feature.
+    ///
+    /// [`DataDiff`]: crate::DataDiff
+    #[cfg(feature = "data-diff")]
+    pub fn log_data_diffs(mut self) -> Self
+    where
+        T: std::fmt::Debug,
+    {
+        self.data_diff = Some(crate::DataDiff::new::<T>);
+        self
+    }
+
     /// Use custom localization resource
     ///
     /// `resources` is a list of file names that contain strings. `base_dir`
@@ -224,6 +372,17 @@ impl<T: Data> AppLauncher<T> {
         self
     }
 
+    /// Add an additional [`LocalizationBundle`].
+    ///
+    /// This can be used to add translations provided by libraries, or
+    /// translations that are embedded in the application binary. This method
+    /// can be called multiple times; bundles added later take precedence over
+    /// those added earlier.
+    pub fn localization_bundle(mut self, bundle: LocalizationBundle) -> Self {
+        self.l10n_bundles.push(bundle);
+        self
+    }
+
     /// Returns an [`ExtEventSink`] that can be moved between threads,
     /// and can be used to submit commands back to the application.
     ///
@@ -232,17 +391,48 @@ impl<T: Data> AppLauncher<T> {
         self.ext_event_host.make_sink()
     }
 
+    /// Limit the number of commands from [`ExtEventSink`]s that can wait to be
+    /// delivered.
+    ///
+    /// Once the limit is reached, [`ExtEventSink::submit_command`] waits for
+    /// room, and [`ExtEventSink::try_submit_command`] returns an error. This
+    /// keeps a background thread that produces commands faster than the UI can
+    /// handle them from flooding the UI thread. By default there is no limit.
+    pub fn ext_event_capacity(mut self, capacity: usize) -> Self {
+        self.ext_event_host.set_capacity(Some(capacity));
+        self
+    }
+
+    /// Set what happens to commands from [`ExtEventSink`]s with `selector`
+    /// that are submitted while another one with the same target is waiting
+    /// to be delivered.
+    ///
+    /// See [`Coalesce`] for the choices.
+    pub fn coalesce_ext_events<P>(mut self, selector: Selector<P>, coalesce: Coalesce) -> Self {
+        self.ext_event_host.set_coalesce(selector, coalesce);
+        self
+    }
+
     /// Build the windows and start the runloop.
     ///
     /// Returns an error if a window cannot be instantiated. This is usually
     /// a fatal error.
     pub fn launch(mut self, data: T) -> Result<(), PlatformError> {
+        // the trace is written when the guard is dropped, after the app exits.
+        #[cfg(all(feature = "tracing-chrome", not(target_arch = "wasm32")))]
+        let _chrome_trace_guard = self.chrome_trace_guard.take();
+
         let app = Application::new()?;
 
-        let mut env = self
-            .l10n_resources
-            .map(|it| Env::with_i10n(it.0, &it.1))
-            .unwrap_or_default();
+        let mut env = match self.l10n_resources {
+            Some((resources, base_dir)) => Env::with_i10n(resources, &base_dir, self.l10n_bundles),
+            None => Env::with_default_i10n(self.l10n_bundles),
+        };
+        theme::add_text_settings(&mut env, &Application::get_text_settings());
+        theme::add_mouse_settings(&mut env, &Application::get_mouse_settings());
+        // kept so that we can tell which keys the application changed, when
+        // the system settings change.
+        let system_env = env.clone();
 
         if let Some(f) = self.env_setup.take() {
             f(&mut env, &data);
@@ -252,9 +442,13 @@ impl<T: Data> AppLauncher<T> {
             app.clone(),
             data,
             env,
+            system_env,
             self.delegate.take(),
             self.ext_event_host,
         );
+        state.set_app_menu(self.menu);
+        #[cfg(feature = "data-diff")]
+        state.set_data_diff(self.data_diff);
 
         for desc in self.windows {
             let window = desc.build_native(&mut state)?;
@@ -274,9 +468,11 @@ impl Default for WindowConfig {
             size: None,
             min_size: None,
             position: None,
+            placement: None,
             resizable: None,
             show_titlebar: None,
             transparent: None,
+            background_color: None,
             level: None,
             state: None,
         }
@@ -354,9 +550,52 @@ impl WindowConfig {
     /// [`position`]: struct.Point.html
     pub fn set_position(mut self, position: Point) -> Self {
         self.position = Some(position);
+        self.placement = None;
         self
     }
 
+    /// Centers the window in the working area of a monitor, which excludes
+    /// things like the taskbar, docks and panels.
+    ///
+    /// This replaces any position set with [`set_position`]. If the window is
+    /// sized to its content, it is moved once its size is known.
+    ///
+    /// [`set_position`]: WindowConfig::set_position
+    pub fn centered_on_monitor(mut self, monitor: MonitorSelector) -> Self {
+        self.placement = Some(Placement::Centered(monitor));
+        self.position = None;
+        self
+    }
+
+    /// Puts the window next to `anchor`, a rectangle in screen coordinates,
+    /// on the preferred `side` of it.
+    ///
+    /// This is meant for popups such as dropdowns and tooltips; the anchor is
+    /// usually the [`screen_rect`] of the widget that opens the window. If the
+    /// window doesn't fit on that side within the working area of the
+    /// monitor, it goes on the other side, and it is kept on the monitor. See
+    /// [`AnchorSide::place`] for the details.
+    ///
+    /// This replaces any position set with [`set_position`]. If the window is
+    /// sized to its content, it is moved once its size is known.
+    ///
+    /// [`screen_rect`]: crate::EventCtx::screen_rect
+    /// [`set_position`]: WindowConfig::set_position
+    pub fn anchored_to(mut self, anchor: Rect, side: AnchorSide) -> Self {
+        self.placement = Some(Placement::Anchored(anchor, side));
+        self.position = None;
+        self
+    }
+
+    /// The placement to apply after the window is built, because its size
+    /// isn't known before.
+    pub(crate) fn deferred_placement(&self) -> Option<Placement> {
+        match (self.size, self.size_policy) {
+            (Some(_), WindowSizePolicy::User) => None,
+            _ => self.placement,
+        }
+    }
+
     /// Sets the [`WindowLevel`] of the window
     ///
     /// [`WindowLevel`]: enum.WindowLevel.html
@@ -379,6 +618,17 @@ impl WindowConfig {
         self
     }
 
+    /// Set the color that the window is cleared to before its widgets are
+    /// painted.
+    ///
+    /// By default this is the theme's [`WINDOW_BACKGROUND_COLOR`].
+    ///
+    /// [`WINDOW_BACKGROUND_COLOR`]: crate::theme::WINDOW_BACKGROUND_COLOR
+    pub fn background_color(mut self, color: impl Into<Color>) -> Self {
+        self.background_color = Some(color.into());
+        self
+    }
+
     /// Apply this window configuration to the passed in WindowBuilder
     pub fn apply_to_builder(&self, builder: &mut WindowBuilder) {
         if let Some(resizable) = self.resizable {
@@ -397,12 +647,20 @@ impl WindowConfig {
 
         if let Some(position) = self.position {
             builder.set_position(position);
+        } else if let (Some(size), WindowSizePolicy::User) = (self.size, self.size_policy) {
+            if let Some(position) = self.placement.and_then(|p| p.position(size)) {
+                builder.set_position(position);
+            }
         }
 
         if let Some(transparent) = self.transparent {
             builder.set_transparent(transparent);
         }
 
+        if let Some(color) = &self.background_color {
+            builder.set_background_color(color.clone());
+        }
+
         if let Some(level) = self.level {
             builder.set_level(level)
         }
@@ -435,12 +693,21 @@ impl WindowConfig {
 
         if let Some(position) = self.position {
             win_handle.set_position(position);
+        } else if let Some(placement) = self.placement {
+            let size = self.size.unwrap_or_else(|| win_handle.get_size());
+            if let Some(position) = placement.position(size) {
+                win_handle.set_position(position);
+            }
         }
 
         if let Some(level) = self.level {
             win_handle.set_level(level)
         }
 
+        if let Some(color) = &self.background_color {
+            win_handle.set_background_color(color.clone());
+        }
+
         if let Some(state) = self.state {
             win_handle.set_window_state(state);
         }
@@ -486,6 +753,17 @@ impl<T: Data> WindowDesc<T> {
         self
     }
 
+    /// Add to the application's menu for this window.
+    ///
+    /// See [`PendingWindow::customize_menu`] for more.
+    pub fn customize_menu(
+        mut self,
+        customize: impl FnMut(Menu<T>, WindowId, &T, &Env) -> Menu<T> + 'static,
+    ) -> Self {
+        self.pending = self.pending.customize_menu(customize);
+        self
+    }
+
     /// Set the window size policy
     pub fn window_size_policy(mut self, size_policy: WindowSizePolicy) -> Self {
         #[cfg(windows)]
@@ -558,6 +836,18 @@ impl<T: Data> WindowDesc<T> {
         self
     }
 
+    /// Builder-style method to set the color that this window is cleared to
+    /// before its widgets are painted.
+    ///
+    /// By default this is the theme's [`WINDOW_BACKGROUND_COLOR`], which the
+    /// platform also shows before the window is painted for the first time.
+    ///
+    /// [`WINDOW_BACKGROUND_COLOR`]: crate::theme::WINDOW_BACKGROUND_COLOR
+    pub fn background_color(mut self, color: impl Into<Color>) -> Self {
+        self.config = self.config.background_color(color);
+        self
+    }
+
     /// Sets the initial window position in [display points], relative to the origin
     /// of the [virtual screen].
     ///
@@ -568,6 +858,27 @@ impl<T: Data> WindowDesc<T> {
         self
     }
 
+    /// Centers the window in the working area of a monitor, so that it
+    /// doesn't open under a taskbar or dock, or straddle two monitors.
+    ///
+    /// This replaces any position set with [`set_position`]. If the window is
+    /// sized to its content, it is moved once its size is known.
+    ///
+    /// [`set_position`]: WindowDesc::set_position
+    pub fn centered_on_monitor(mut self, monitor: MonitorSelector) -> Self {
+        self.config = self.config.centered_on_monitor(monitor);
+        self
+    }
+
+    /// Puts the window next to `anchor`, a rectangle in screen coordinates,
+    /// flipping it to the other side if it doesn't fit on the preferred one.
+    ///
+    /// See [`WindowConfig::anchored_to`] for the details.
+    pub fn anchored_to(mut self, anchor: Rect, side: AnchorSide) -> Self {
+        self.config = self.config.anchored_to(anchor, side);
+        self
+    }
+
     /// Sets the [`WindowLevel`] of the window
     ///
     /// [`WindowLevel`]: enum.WindowLevel.html
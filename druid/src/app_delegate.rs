@@ -15,10 +15,14 @@
 //! Customizing application-level behaviour.
 
 use std::any::{Any, TypeId};
+use std::time::Duration;
 
+use crate::command::ScheduledCommand;
+use crate::registry::Registries;
 use crate::{
-    commands, core::CommandQueue, ext_event::ExtEventHost, Command, Data, Env, Event, ExtEventSink,
-    Handled, SingleUse, Target, WindowDesc, WindowId,
+    commands, core::CommandQueue, ext_event::ExtEventHost, Command, CommandTimer, Data, Env, Event,
+    ExtEventSink, Handled, SingleUse, Target, WidgetId, WidgetInfo, WidgetRegistry, WindowDesc,
+    WindowId,
 };
 
 /// A context passed in to [`AppDelegate`] functions.
@@ -28,6 +32,7 @@ pub struct DelegateCtx<'a> {
     pub(crate) command_queue: &'a mut CommandQueue,
     pub(crate) ext_event_host: &'a ExtEventHost,
     pub(crate) app_data_type: TypeId,
+    pub(crate) registries: &'a dyn Registries,
 }
 
 impl<'a> DelegateCtx<'a> {
@@ -46,6 +51,55 @@ impl<'a> DelegateCtx<'a> {
             .push_back(command.into().default_to(Target::Global))
     }
 
+    /// Submit a [`Command`] once `delay` has passed.
+    ///
+    /// The command waits on a timer of the window it targets, or of some
+    /// open window if it doesn't target one, and is dropped if that window
+    /// closes first. The returned [`CommandTimer`] can cancel it.
+    pub fn submit_command_after(
+        &mut self,
+        command: impl Into<Command>,
+        delay: Duration,
+    ) -> CommandTimer {
+        self.schedule_command(command.into(), delay, false)
+    }
+
+    /// Submit a [`Command`] every `interval`, until the returned
+    /// [`CommandTimer`] is cancelled.
+    ///
+    /// The first command is submitted once `interval` has passed. See
+    /// [`submit_command_after`] for the window that it waits on.
+    ///
+    /// [`submit_command_after`]: DelegateCtx::submit_command_after
+    pub fn submit_command_every(
+        &mut self,
+        command: impl Into<Command>,
+        interval: Duration,
+    ) -> CommandTimer {
+        self.schedule_command(command.into(), interval, true)
+    }
+
+    fn schedule_command(
+        &mut self,
+        command: Command,
+        delay: Duration,
+        repeat: bool,
+    ) -> CommandTimer {
+        let timer = CommandTimer::default();
+        let command = ScheduledCommand {
+            command: command.default_to(Target::Global),
+            delay,
+            repeat,
+            timer: timer.clone(),
+        };
+        self.submit_command(
+            commands::SCHEDULE_COMMAND
+                .with(SingleUse::new(command))
+                .to(Target::Global),
+        );
+        timer
+    }
+
     /// Returns an [`ExtEventSink`] that can be moved between threads,
     /// and can be used to submit commands back to the application.
     ///
@@ -54,6 +108,19 @@ impl<'a> DelegateCtx<'a> {
         self.ext_event_host.make_sink()
     }
 
+    /// The widgets of a window, as of its last layout.
+    ///
+    /// Returns `None` if the window is not open.
+    pub fn widget_registry(&self, window_id: WindowId) -> Option<&WidgetRegistry> {
+        self.registries.registry(window_id)
+    }
+
+    /// Look up a widget in every open window, returning the window that
+    /// contains it along with what is known about it.
+    pub fn widget_info(&self, widget_id: WidgetId) -> Option<(WindowId, &WidgetInfo)> {
+        self.registries.find(widget_id)
+    }
+
     /// Create a new window.
     /// `T` must be the application's root `Data` type (the type provided to [`AppLauncher::launch`]).
     ///
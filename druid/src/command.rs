@@ -17,10 +17,14 @@
 use std::any::{self, Any};
 use std::{
     marker::PhantomData,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
-use crate::{WidgetId, WindowId};
+use crate::{Lens, LensExt, WidgetId, WindowId};
 
 /// The identity of a [`Selector`].
 ///
@@ -177,6 +181,31 @@ pub enum Target {
     Auto,
 }
 
+/// A handle to a [`Command`] that is submitted later.
+///
+/// This is returned by [`EventCtx::submit_command_after`] and
+/// [`EventCtx::submit_command_every`] (and the same methods on the other
+/// contexts and on [`DelegateCtx`]), and can be used to cancel the command.
+/// Dropping the handle does not cancel it.
+///
+/// The handle can be cloned, and sent to other threads.
+///
+/// [`EventCtx::submit_command_after`]: crate::EventCtx::submit_command_after
+/// [`EventCtx::submit_command_every`]: crate::EventCtx::submit_command_every
+/// [`DelegateCtx`]: crate::DelegateCtx
+#[derive(Debug, Clone, Default)]
+pub struct CommandTimer(Arc<AtomicBool>);
+
+/// A command that is submitted when a timer fires.
+#[derive(Debug, Clone)]
+pub(crate) struct ScheduledCommand {
+    pub(crate) command: Command,
+    /// The time until the command is submitted, and between submissions if it repeats.
+    pub(crate) delay: Duration,
+    pub(crate) repeat: bool,
+    pub(crate) timer: CommandTimer,
+}
+
 /// Commands with special meaning, defined by druid.
 ///
 /// See [`Command`] for more info.
@@ -184,11 +213,14 @@ pub enum Target {
 /// [`Command`]: ../struct.Command.html
 pub mod sys {
     use std::any::Any;
+    use std::time::Duration;
 
-    use super::Selector;
+    use super::{DataWrite, ScheduledCommand, Selector};
     use crate::{
         sub_window::{SubWindowDesc, SubWindowUpdate},
-        FileDialogOptions, FileInfo, SingleUse, WidgetId, WindowConfig,
+        widget::{Connection, StatusMessage},
+        Color, FileDialogOptions, FileInfo, ImageBuf, PowerEvent, PowerStatus, Rect, SingleUse,
+        WidgetId, WindowConfig,
     };
 
     /// Quit the running application. This command is handled by the druid library.
@@ -221,6 +253,52 @@ pub mod sys {
     /// will automatically target the window containing the widget.
     pub const SHOW_WINDOW: Selector = Selector::new("druid-builtin.show-window");
 
+    /// Toggle the frame timing overlay of a window.
+    ///
+    /// While enabled, the time spent in the event, update, layout and paint
+    /// passes of each frame is measured, and shown in the top left corner of
+    /// the window along with the frame rate and the widget that took the
+    /// longest to lay out or paint.
+    ///
+    /// The command must target a specific window.
+    /// When calling `submit_command` on a `Widget`s context, passing `None` as target
+    /// will automatically target the window containing the widget.
+    pub const TOGGLE_FRAME_STATS: Selector = Selector::new("druid-builtin.toggle-frame-stats");
+
+    /// Toggle the widget inspector of a window.
+    ///
+    /// The inspector shows the tree of visible widgets in a panel on the right
+    /// side of the window, and highlights the widget under the cursor along
+    /// with its id, layout rect, constraints, and (for widgets wrapped in
+    /// [`InspectData`]) data. In debug builds it can also be toggled with
+    /// ctrl+shift+I (cmd+shift+I on macOS), when no widget handles that key.
+    ///
+    /// The command must target a specific window.
+    /// When calling `submit_command` on a `Widget`s context, passing `None` as target
+    /// will automatically target the window containing the widget.
+    ///
+    /// [`InspectData`]: crate::widget::InspectData
+    pub const TOGGLE_INSPECTOR: Selector = Selector::new("druid-builtin.toggle-inspector");
+
+    /// Render a window offscreen into an image, at the window's scale.
+    ///
+    /// The image is sent back to the window as a [`WINDOW_IMAGE_CAPTURED`]
+    /// command, which can be handled by a [`Controller`] or the
+    /// [`AppDelegate`]. The inspector and frame statistics overlays are left
+    /// out.
+    ///
+    /// The command must target a specific window.
+    /// When calling `submit_command` on a `Widget`s context, passing `None` as target
+    /// will automatically target the window containing the widget.
+    ///
+    /// [`Controller`]: crate::widget::Controller
+    /// [`AppDelegate`]: crate::AppDelegate
+    pub const CAPTURE_WINDOW_IMAGE: Selector = Selector::new("druid-builtin.capture-window-image");
+
+    /// The image of a window that was asked for with [`CAPTURE_WINDOW_IMAGE`].
+    pub const WINDOW_IMAGE_CAPTURED: Selector<ImageBuf> =
+        Selector::new("druid-builtin.window-image-captured");
+
     /// Apply the configuration payload to an existing window. The target should be a WindowId.
     pub const CONFIGURE_WINDOW: Selector<WindowConfig> =
         Selector::new("druid-builtin.configure-window");
@@ -245,6 +323,36 @@ pub mod sys {
     pub(crate) const SUB_WINDOW_HOST_TO_PARENT: Selector<Box<dyn Any>> =
         Selector::new("druid-builtin.host_to_parent");
 
+    /// Schedule a command, for [`DelegateCtx::submit_command_after`] and
+    /// [`DelegateCtx::submit_command_every`].
+    ///
+    /// [`DelegateCtx::submit_command_after`]: crate::DelegateCtx::submit_command_after
+    /// [`DelegateCtx::submit_command_every`]: crate::DelegateCtx::submit_command_every
+    pub(crate) const SCHEDULE_COMMAND: Selector<SingleUse<ScheduledCommand>> =
+        Selector::new("druid-builtin.schedule-command");
+
+    /// Sent to a widget to ask where it is. The widget's `WidgetPod` answers
+    /// with a [`WIDGET_LOCATED`] notification, so that any widget can be located.
+    pub(crate) const LOCATE_WIDGET: Selector = Selector::new("druid-builtin.locate-widget");
+
+    /// The answer to [`LOCATE_WIDGET`]: the layout rect of the widget, in
+    /// window coordinates.
+    pub(crate) const WIDGET_LOCATED: Selector<Rect> = Selector::new("druid-builtin.widget-located");
+
+    /// Write a value into the data, through a lens.
+    ///
+    /// When this targets a widget, the value is written into the data of that
+    /// widget, by its `WidgetPod`, if the lens operates on the type of that
+    /// data. Otherwise, the [`AppDelegate`] gets a chance to handle it first,
+    /// and then it is written into the app data, if the lens operates on its
+    /// type. Either way, the widgets are updated as usual.
+    ///
+    /// See [`DataWrite`] for an example.
+    ///
+    /// [`AppDelegate`]: crate::AppDelegate
+    /// [`DataWrite`]: crate::DataWrite
+    pub const WRITE_DATA: Selector<DataWrite> = Selector::new("druid-builtin.write-data");
+
     /// Show the application preferences.
     pub const SHOW_PREFERENCES: Selector = Selector::new("druid-builtin.menu-show-preferences");
 
@@ -260,6 +368,9 @@ pub mod sys {
     /// When submitted by the application, a file picker dialog will be shown to the user,
     /// and an [`OPEN_FILE`] command will be sent if a path is chosen.
     ///
+    /// If the options allow choosing several files, the command is sent once for each of them
+    /// on platforms that support it; the others only return one.
+    ///
     /// [`OPEN_FILE`]: constant.OPEN_FILE.html
     pub const SHOW_OPEN_PANEL: Selector<FileDialogOptions> =
         Selector::new("druid-builtin.menu-file-open");
@@ -327,6 +438,214 @@ pub mod sys {
     /// Select all.
     pub const SELECT_ALL: Selector = Selector::new("druid-builtin.menu-select-all");
 
+    /// Go back, as with a browser's back button.
+    ///
+    /// This is submitted to a window when the [back button] of the mouse is
+    /// pressed in it and no widget handles the press. A [`WebView`] goes back
+    /// in its history, and [`Breadcrumbs`] go up to the previous segment.
+    ///
+    /// [back button]: crate::MouseButton::BACK
+    /// [`WebView`]: crate::widget::WebView
+    /// [`Breadcrumbs`]: crate::widget::Breadcrumbs
+    pub const NAVIGATE_BACK: Selector = Selector::new("druid-builtin.navigate-back");
+
+    /// Go forward, as with a browser's forward button.
+    ///
+    /// This is submitted to a window when the [forward button] of the mouse
+    /// is pressed in it and no widget handles the press. A [`WebView`] goes
+    /// forward in its history.
+    ///
+    /// [forward button]: crate::MouseButton::FORWARD
+    /// [`WebView`]: crate::widget::WebView
+    pub const NAVIGATE_FORWARD: Selector = Selector::new("druid-builtin.navigate-forward");
+
+    /// Zoom the UI of the application in, to the next zoom level.
+    ///
+    /// This applies to all windows, wherever the command is sent. The
+    /// [`zoom_in`] menu item submits it on ctrl+= (cmd on macOS). See
+    /// [`UI_ZOOM`].
+    ///
+    /// [`zoom_in`]: crate::menu::sys::common::zoom_in
+    /// [`UI_ZOOM`]: crate::theme::UI_ZOOM
+    pub const ZOOM_IN: Selector = Selector::new("druid-builtin.zoom-in");
+
+    /// Zoom the UI of the application out, to the previous zoom level.
+    ///
+    /// This applies to all windows, wherever the command is sent. The
+    /// [`zoom_out`] menu item submits it on ctrl+- (cmd on macOS). See
+    /// [`UI_ZOOM`].
+    ///
+    /// [`zoom_out`]: crate::menu::sys::common::zoom_out
+    /// [`UI_ZOOM`]: crate::theme::UI_ZOOM
+    pub const ZOOM_OUT: Selector = Selector::new("druid-builtin.zoom-out");
+
+    /// Reset the zoom of the application's UI to `1.0`.
+    ///
+    /// This applies to all windows, wherever the command is sent. The
+    /// [`reset_zoom`] menu item submits it on ctrl+0 (cmd on macOS). See
+    /// [`UI_ZOOM`].
+    ///
+    /// [`reset_zoom`]: crate::menu::sys::common::reset_zoom
+    /// [`UI_ZOOM`]: crate::theme::UI_ZOOM
+    pub const RESET_ZOOM: Selector = Selector::new("druid-builtin.reset-zoom");
+
+    /// Set the zoom of the application's UI to the payload.
+    ///
+    /// The zoom is limited to between `0.5` and `3.0`. This applies to all
+    /// windows, wherever the command is sent. See [`UI_ZOOM`].
+    ///
+    /// [`UI_ZOOM`]: crate::theme::UI_ZOOM
+    pub const SET_ZOOM: Selector<f64> = Selector::new("druid-builtin.set-zoom");
+
+    /// Load a URL in a [`WebView`]. The target should be the id of the `WebView`.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_LOAD_URL: Selector<String> = Selector::new("druid-builtin.webview-load-url");
+
+    /// Show the payload as HTML in a [`WebView`]. The target should be the id
+    /// of the `WebView`.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_LOAD_HTML: Selector<String> =
+        Selector::new("druid-builtin.webview-load-html");
+
+    /// Run the payload as JavaScript in the page shown by a [`WebView`]. The
+    /// target should be the id of the `WebView`.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_EVALUATE_SCRIPT: Selector<String> =
+        Selector::new("druid-builtin.webview-evaluate-script");
+
+    /// Go back in the history of a [`WebView`]. The target should be the id
+    /// of the `WebView`.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_GO_BACK: Selector = Selector::new("druid-builtin.webview-go-back");
+
+    /// Go forward in the history of a [`WebView`]. The target should be the
+    /// id of the `WebView`.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_GO_FORWARD: Selector = Selector::new("druid-builtin.webview-go-forward");
+
+    /// Reload the page shown by a [`WebView`]. The target should be the id of
+    /// the `WebView`.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_RELOAD: Selector = Selector::new("druid-builtin.webview-reload");
+
+    /// A notification sent by a [`WebView`] when the page sends a message to
+    /// the application. The payload is the message.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_MESSAGE: Selector<String> = Selector::new("druid-builtin.webview-message");
+
+    /// A notification sent by a [`WebView`] when it has navigated to a new
+    /// page. The payload is the URL of the page.
+    ///
+    /// [`WebView`]: crate::widget::WebView
+    pub const WEBVIEW_NAVIGATED: Selector<String> =
+        Selector::new("druid-builtin.webview-navigated");
+
+    /// A notification sent by a [`Video`] whenever it shows a new frame. The
+    /// payload is the timestamp of the frame.
+    ///
+    /// [`Video`]: crate::widget::Video
+    pub const VIDEO_POSITION: Selector<Duration> = Selector::new("druid-builtin.video-position");
+
+    /// A notification sent by a [`Video`] when it has reached the end.
+    ///
+    /// [`Video`]: crate::widget::Video
+    pub const VIDEO_ENDED: Selector = Selector::new("druid-builtin.video-ended");
+
+    /// Sent to the [`AppDelegate`] and to every window when the system or its
+    /// displays go to sleep or wake up.
+    ///
+    /// Windows are repainted after waking up, but anything else, such as
+    /// pausing animations or reconnecting to the network, is up to the
+    /// application.
+    ///
+    /// [`AppDelegate`]: crate::AppDelegate
+    pub const POWER_EVENT: Selector<PowerEvent> = Selector::new("druid-builtin.power-event");
+
+    /// Sent to the [`AppDelegate`] and to every window when the system
+    /// switches between battery and external power, or when the charge of the
+    /// battery changes. The payload is the new status.
+    ///
+    /// The current status is available from [`Application::power_status`].
+    ///
+    /// [`AppDelegate`]: crate::AppDelegate
+    /// [`Application::power_status`]: crate::Application::power_status
+    pub const POWER_STATUS_CHANGED: Selector<PowerStatus> =
+        Selector::new("druid-builtin.power-status-changed");
+
+    /// Sent to the [`AppDelegate`] and to every window when the user's
+    /// preferred locales or regional settings change.
+    ///
+    /// Formatters such as [`NumberFormatter`] pick up the user's settings
+    /// when they are created, so they need to be created again. Localized
+    /// strings keep using the locale that was chosen at launch.
+    ///
+    /// [`AppDelegate`]: crate::AppDelegate
+    /// [`NumberFormatter`]: crate::text::NumberFormatter
+    pub const LOCALE_CHANGED: Selector = Selector::new("druid-builtin.locale-changed");
+
+    /// Show a message in place of the left widget of every [`StatusBar`] the
+    /// command reaches, replacing any message that is already shown.
+    ///
+    /// [`StatusBar`]: crate::widget::StatusBar
+    pub const SHOW_STATUS_MESSAGE: Selector<StatusMessage> =
+        Selector::new("druid-builtin.show-status-message");
+
+    /// Hide the message shown by every [`StatusBar`] the command reaches.
+    ///
+    /// [`StatusBar`]: crate::widget::StatusBar
+    pub const CLEAR_STATUS_MESSAGE: Selector = Selector::new("druid-builtin.clear-status-message");
+
+    /// A notification sent by [`Breadcrumbs`] when a segment is clicked. The
+    /// payload is the index of the segment; the path has already been cut
+    /// back to it.
+    ///
+    /// [`Breadcrumbs`]: crate::widget::Breadcrumbs
+    pub const BREADCRUMB_SELECTED: Selector<usize> =
+        Selector::new("druid-builtin.breadcrumb-selected");
+
+    /// A notification sent by [`SwatchGrid`] when a swatch is clicked, with
+    /// its color.
+    ///
+    /// [`SwatchGrid`]: crate::widget::SwatchGrid
+    pub const COLOR_SELECTED: Selector<Color> = Selector::new("druid-builtin.color-selected");
+
+    /// A notification sent by [`GradientEditor`] when a stop is selected, with
+    /// the index of the stop, so that its color can be edited.
+    ///
+    /// [`GradientEditor`]: crate::widget::GradientEditor
+    pub const GRADIENT_STOP_SELECTED: Selector<usize> =
+        Selector::new("druid-builtin.gradient-stop-selected");
+
+    /// A notification sent by [`NodeGraph`] when a wire is dragged from an
+    /// output to an input. The graph is not changed; handle this to add the
+    /// connection.
+    ///
+    /// [`NodeGraph`]: crate::widget::NodeGraph
+    pub const CONNECT_PORTS: Selector<Connection> = Selector::new("druid-builtin.connect-ports");
+
+    /// A notification sent by [`NodeGraph`] when a wire is dragged away from
+    /// its input. The graph is not changed; handle this to remove the
+    /// connection.
+    ///
+    /// [`NodeGraph`]: crate::widget::NodeGraph
+    pub const DISCONNECT_PORTS: Selector<Connection> =
+        Selector::new("druid-builtin.disconnect-ports");
+
+    /// A notification sent by [`CalendarView`] when a day is selected, with
+    /// its date.
+    ///
+    /// [`CalendarView`]: crate::widget::CalendarView
+    #[cfg(feature = "chrono")]
+    pub const DATE_SELECTED: Selector<chrono::NaiveDate> =
+        Selector::new("druid-builtin.date-selected");
+
     /// Text input state has changed, and we need to notify the platform.
     pub(crate) const INVALIDATE_IME: Selector<ImeInvalidation> =
         Selector::new("druid-builtin.invalidate-ime");
@@ -563,6 +882,22 @@ impl Notification {
     }
 }
 
+impl CommandTimer {
+    /// Stop the command from being submitted again.
+    ///
+    /// A command that was already submitted is not affected.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if [`cancel`] was called on this timer, or a clone of it.
+    ///
+    /// [`cancel`]: CommandTimer::cancel
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 impl<T: Any> SingleUse<T> {
     /// Create a new single-use payload.
     pub fn new(data: T) -> Self {
@@ -575,6 +910,83 @@ impl<T: Any> SingleUse<T> {
     }
 }
 
+/// A value to write into the data through a lens, the payload of a
+/// [`WRITE_DATA`] command.
+///
+/// This lets the result of some work, like an async task that reports back
+/// through an [`ExtEventSink`], be put where it belongs in the data without a
+/// selector and a hand-written handler for every kind of result.
+///
+/// ```
+/// use druid::commands::WRITE_DATA;
+/// use druid::{Data, DataWrite, Lens, Target};
+///
+/// #[derive(Clone, Data, Lens)]
+/// struct AppState {
+///     status: String,
+///     progress: f64,
+/// }
+///
+/// let mut data = AppState { status: "loading".into(), progress: 0.0 };
+/// // this is what a background thread would send with `ExtEventSink::submit_command`:
+/// let command = WRITE_DATA.with(DataWrite::new(AppState::progress, 0.5)).to(Target::Global);
+/// // and this is how it is written into the app data, once it arrives:
+/// assert!(command.get_unchecked(WRITE_DATA).apply(&mut data));
+/// assert_eq!(data.progress, 0.5);
+/// ```
+///
+/// [`WRITE_DATA`]: sys::WRITE_DATA
+/// [`ExtEventSink`]: crate::ExtEventSink
+pub struct DataWrite {
+    /// A `Box<dyn Fn(&mut T) + Send>`, where `T` is the type of the data.
+    write: Box<dyn Any + Send>,
+    data_type: &'static str,
+}
+
+type WriteFn<T> = Box<dyn Fn(&mut T) + Send>;
+
+impl DataWrite {
+    /// Create a write of `value` through `lens`, into data of type `T`.
+    pub fn new<T, U, L>(lens: L, value: U) -> Self
+    where
+        T: 'static,
+        U: Clone + Send + 'static,
+        L: Lens<T, U> + Send + 'static,
+    {
+        let write: WriteFn<T> = Box::new(move |data| lens.put(data, value.clone()));
+        DataWrite {
+            write: Box::new(write),
+            data_type: any::type_name::<T>(),
+        }
+    }
+
+    /// Returns `true` if this writes into data of type `T`.
+    pub fn applies_to<T: 'static>(&self) -> bool {
+        self.write.is::<WriteFn<T>>()
+    }
+
+    /// Write the value into `data`, if this writes into data of type `T`.
+    ///
+    /// Returns `true` if the value was written.
+    pub fn apply<T: 'static>(&self, data: &mut T) -> bool {
+        match self.write.downcast_ref::<WriteFn<T>>() {
+            Some(write) => {
+                write(data);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for DataWrite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataWrite")
+            .field("data_type", &self.data_type)
+            .finish()
+    }
+}
+
 impl From<Selector> for Command {
     fn from(selector: Selector) -> Command {
         Command {
@@ -16,6 +16,7 @@
 
 use std::{
     any::{Any, TypeId},
+    cell::RefCell,
     collections::VecDeque,
     ops::{Deref, DerefMut},
     rc::Rc,
@@ -23,17 +24,25 @@ use std::{
 };
 use tracing::{error, trace, warn};
 
+// Automatically defaults to std::time::Instant on non Wasm platforms
+use instant::Instant;
+
+use crate::command::ScheduledCommand;
 use crate::core::{CommandQueue, CursorChange, FocusChange, WidgetState};
 use crate::env::KeyLike;
+use crate::frame_stats::FrameStats;
+use crate::inspector::Inspector;
+use crate::kurbo::Line;
 use crate::menu::ContextMenu;
 use crate::piet::{Piet, PietText, RenderContext};
+use crate::registry::{FocusDirection, WidgetRegistry};
 use crate::shell::text::Event as ImeInvalidation;
-use crate::shell::Region;
+use crate::shell::{FilePromise, Region, StrokeAlignment};
 use crate::text::{ImeHandlerRef, TextFieldRegistration};
 use crate::{
-    commands, sub_window::SubWindowDesc, widget::Widget, Affine, Command, Cursor, Data, Env,
-    ExtEventSink, Insets, Menu, Notification, Point, Rect, SingleUse, Size, Target, TimerToken,
-    Vec2, WidgetId, WindowConfig, WindowDesc, WindowHandle, WindowId,
+    commands, sub_window::SubWindowDesc, widget::Widget, Affine, Command, CommandTimer, Cursor,
+    Data, Env, ExtEventSink, Insets, Menu, Notification, Point, Rect, Scale, SingleUse, Size,
+    Target, TimerToken, Vec2, WidgetId, WindowConfig, WindowDesc, WindowHandle, WindowId,
 };
 
 /// A macro for implementing methods on multiple contexts.
@@ -60,6 +69,28 @@ pub(crate) struct ContextState<'a> {
     /// The id of the widget that currently has focus.
     pub(crate) focus_widget: Option<WidgetId>,
     pub(crate) root_app_data_type: TypeId,
+    /// Set while the window is measuring frame times.
+    pub(crate) frame_stats: Option<&'a mut FrameStats>,
+    /// Set while the widget inspector is open.
+    pub(crate) inspector: Option<&'a mut Inspector>,
+    /// The widget registry of the window, during lifecycle passes.
+    pub(crate) registry: Option<&'a mut WidgetRegistry>,
+    /// Set during lifecycle passes, for the widgets that are added to keep.
+    pub(crate) removal: Option<&'a Rc<RemovalContext>>,
+}
+
+/// What a widget needs in order to be told that it was removed.
+///
+/// Widgets can be dropped at any time, with no context at hand, so each
+/// widget keeps a reference to this from when it was added.
+pub(crate) struct RemovalContext {
+    window_id: WindowId,
+    window: WindowHandle,
+    ext_handle: ExtEventSink,
+    text: PietText,
+    root_app_data_type: TypeId,
+    /// Commands submitted by removed widgets, until the window takes them.
+    commands: RefCell<CommandQueue>,
 }
 
 /// A mutable context provided to event handling methods of widgets.
@@ -162,6 +193,13 @@ impl_context_method!(
             self.state.window_id
         }
 
+        /// The scale of the current window, from display points to pixels.
+        ///
+        /// This is `1.0` on both axes if the platform does not know the scale yet.
+        pub fn scale(&self) -> Scale {
+            self.state.window.get_scale().unwrap_or_default()
+        }
+
         /// Get an object which can create text layouts.
         pub fn text(&mut self) -> &mut PietText {
             &mut self.state.text
@@ -212,6 +250,15 @@ impl_context_method!(
             content_origin + self.to_window(widget_point).to_vec2()
         }
 
+        /// The widget's layout rect in screen coordinates.
+        ///
+        /// This is what a popup is anchored to with [`WindowConfig::anchored_to`].
+        ///
+        /// [`WindowConfig::anchored_to`]: crate::WindowConfig::anchored_to
+        pub fn screen_rect(&self) -> Rect {
+            Rect::from_origin_size(self.to_screen(Point::ORIGIN), self.size())
+        }
+
         /// The "hot" (aka hover) status of a widget.
         ///
         /// A widget is "hot" when the mouse is hovered over it. Widgets will
@@ -293,6 +340,17 @@ impl_context_method!(
         pub fn is_disabled(&self) -> bool {
             self.widget_state.is_disabled()
         }
+
+        /// Returns `true` if any part of this widget is inside the viewports that
+        /// enclose it, such as that of a [`Scroll`], as of the last layout.
+        ///
+        /// Widgets are told when this changes with
+        /// [`LifeCycle::ViewportVisibilityChanged`].
+        ///
+        /// [`Scroll`]: crate::widget::Scroll
+        pub fn is_viewport_visible(&self) -> bool {
+            self.widget_state.is_viewport_visible
+        }
     }
 );
 
@@ -373,10 +431,20 @@ impl_context_method!(EventCtx<'_, '_>, UpdateCtx<'_, '_>, LifeCycleCtx<'_, '_>,
     /// (such as if it would like to change the layout of children in
     /// response to some event) it must call this method.
     ///
+    /// Layout is incremental: only this widget and its ancestors are laid
+    /// out again, along with any descendants that receive new constraints.
+    /// Subtrees that have not requested layout and whose constraints did not
+    /// change keep their previous layout.
+    ///
+    /// This also implies a [`request_paint`]; any other widgets that move or
+    /// change size during layout will be repainted as well.
+    ///
     /// [`layout`]: trait.Widget.html#tymethod.layout
+    /// [`request_paint`]: #method.request_paint
     pub fn request_layout(&mut self) {
         trace!("request_layout");
         self.widget_state.needs_layout = true;
+        self.request_paint();
     }
 
     /// Request an animation frame.
@@ -492,6 +560,40 @@ impl_context_method!(
             trace!("request_timer deadline={:?}", deadline);
             self.state.request_timer(&mut self.widget_state, deadline)
         }
+
+        /// Submit a [`Command`] once `delay` has passed.
+        ///
+        /// This is like [`submit_command`], except that the command waits on a
+        /// timer of the window, so it is dropped if the window closes first.
+        /// The returned [`CommandTimer`] can cancel it.
+        ///
+        /// [`submit_command`]: Self::submit_command
+        pub fn submit_command_after(
+            &mut self,
+            cmd: impl Into<Command>,
+            delay: Duration,
+        ) -> CommandTimer {
+            trace!("submit_command_after delay={:?}", delay);
+            self.state
+                .schedule_command(self.widget_state, cmd.into(), delay, false)
+        }
+
+        /// Submit a [`Command`] every `interval`, until the returned
+        /// [`CommandTimer`] is cancelled.
+        ///
+        /// The first command is submitted once `interval` has passed. As with
+        /// [`submit_command_after`], this stops when the window closes.
+        ///
+        /// [`submit_command_after`]: Self::submit_command_after
+        pub fn submit_command_every(
+            &mut self,
+            cmd: impl Into<Command>,
+            interval: Duration,
+        ) -> CommandTimer {
+            trace!("submit_command_every interval={:?}", interval);
+            self.state
+                .schedule_command(self.widget_state, cmd.into(), interval, true)
+        }
     }
 );
 
@@ -552,12 +654,15 @@ impl EventCtx<'_, '_> {
     }
 
     /// Show the context menu in the window containing the current widget.
-    /// `T` must be the application's root `Data` type (the type provided to [`AppLauncher::launch`]).
+    /// `T` must be the application's root `Data` type (the type provided to [`AppLauncher::launch`]),
+    /// or `()` for menus whose items only submit commands.
     ///
     /// [`AppLauncher::launch`]: struct.AppLauncher.html#method.launch
     pub fn show_context_menu<T: Any>(&mut self, menu: Menu<T>, location: Point) {
         trace!("show_context_menu");
-        if self.state.root_app_data_type == TypeId::of::<T>() {
+        if self.state.root_app_data_type == TypeId::of::<T>()
+            || TypeId::of::<T>() == TypeId::of::<()>()
+        {
             let menu = ContextMenu { menu, location };
             self.submit_command(
                 commands::SHOW_CONTEXT_MENU
@@ -571,6 +676,20 @@ impl EventCtx<'_, '_> {
         }
     }
 
+    /// Start dragging a file out of the window, whose contents are only
+    /// written when it is dropped.
+    ///
+    /// This should be called while handling a mouse move with a button held
+    /// down. The widget stops being [active], because the platform takes over
+    /// the mouse and no mouse-up event will arrive.
+    ///
+    /// [active]: EventCtx::is_active
+    pub fn start_file_drag(&mut self, promise: FilePromise) {
+        trace!("start_file_drag");
+        self.set_active(false);
+        self.state.window.start_file_drag(promise);
+    }
+
     /// Set the event as "handled", which stops its propagation to other
     /// widgets.
     pub fn set_handled(&mut self) {
@@ -631,6 +750,32 @@ impl EventCtx<'_, '_> {
         }
     }
 
+    /// Transfer focus to the focusable widget that is nearest to the focused
+    /// one in `direction`, if there is any.
+    ///
+    /// This should only be called by a widget that currently has focus. The
+    /// distances are measured between the widgets' rects in the window, as of
+    /// the last layout; see [`WidgetRegistry::nearest_in_direction`]. To move
+    /// focus like this with the arrow keys, use a [`SpatialNavigation`]
+    /// controller.
+    ///
+    /// See [`is_focused`] for more information about focus.
+    ///
+    /// [`WidgetRegistry::nearest_in_direction`]: crate::WidgetRegistry::nearest_in_direction
+    /// [`SpatialNavigation`]: crate::widget::SpatialNavigation
+    /// [`is_focused`]: struct.EventCtx.html#method.is_focused
+    pub fn focus_in_direction(&mut self, direction: FocusDirection) {
+        trace!("focus_in_direction {:?}", direction);
+        if self.has_focus() {
+            self.widget_state.request_focus = Some(FocusChange::Direction(direction));
+        } else {
+            warn!(
+                "focus_in_direction can only be called by the currently \
+                            focused widget or one of its ancestors."
+            );
+        }
+    }
+
     /// Transfer focus to the previous focusable widget.
     ///
     /// This should only be called by a widget that currently has focus.
@@ -813,6 +958,37 @@ impl PaintCtx<'_, '_, '_> {
         &self.region
     }
 
+    /// Rounds a point in the widget's coordinate space to the nearest pixel corner.
+    ///
+    /// Painting with snapped coordinates keeps edges crisp at fractional scale factors.
+    /// See [`Scale::snap_to_pixel`].
+    pub fn snap_to_pixel(&self, point: Point) -> Point {
+        let origin = self.window_origin().to_vec2();
+        self.scale().snap_to_pixel(point + origin) - origin
+    }
+
+    /// Snaps the stroke of a rectangle in the widget's coordinate space to whole pixels.
+    ///
+    /// Returns the path to stroke and the width to stroke it with.
+    /// See [`Scale::snap_stroke`].
+    pub fn snap_stroke(&self, rect: Rect, width: f64, alignment: StrokeAlignment) -> (Rect, f64) {
+        let origin = self.window_origin().to_vec2();
+        let (path, width) = self.scale().snap_stroke(rect + origin, width, alignment);
+        (path - origin, width)
+    }
+
+    /// Snaps a horizontal or vertical line in the widget's coordinate space to whole pixels.
+    ///
+    /// Returns the line to stroke and the width to stroke it with.
+    /// See [`Scale::snap_line`].
+    pub fn snap_line(&self, line: Line, width: f64) -> (Line, f64) {
+        let origin = self.window_origin().to_vec2();
+        let (line, width) = self
+            .scale()
+            .snap_line(Line::new(line.p0 + origin, line.p1 + origin), width);
+        (Line::new(line.p0 - origin, line.p1 - origin), width)
+    }
+
     /// Creates a temporary `PaintCtx` with a new visible region, and calls
     /// the provided function with that `PaintCtx`.
     ///
@@ -882,6 +1058,33 @@ impl PaintCtx<'_, '_, '_> {
     }
 }
 
+impl RemovalContext {
+    pub(crate) fn new<T: 'static>(
+        window_id: WindowId,
+        window: WindowHandle,
+        ext_handle: ExtEventSink,
+    ) -> Self {
+        RemovalContext {
+            window_id,
+            text: window.text(),
+            window,
+            ext_handle,
+            root_app_data_type: TypeId::of::<T>(),
+            commands: RefCell::new(CommandQueue::new()),
+        }
+    }
+
+    /// Add commands that were submitted by a removed widget.
+    pub(crate) fn submit_commands(&self, commands: &mut CommandQueue) {
+        self.commands.borrow_mut().append(commands);
+    }
+
+    /// Take the commands that removed widgets submitted.
+    pub(crate) fn take_commands(&self) -> CommandQueue {
+        self.commands.take()
+    }
+}
+
 impl<'a> ContextState<'a> {
     pub(crate) fn new<T: 'static>(
         command_queue: &'a mut CommandQueue,
@@ -898,6 +1101,49 @@ impl<'a> ContextState<'a> {
             focus_widget,
             text: window.text(),
             root_app_data_type: TypeId::of::<T>(),
+            frame_stats: None,
+            inspector: None,
+            registry: None,
+            removal: None,
+        }
+    }
+
+    /// A context for telling a widget that it was removed, outside of any pass.
+    pub(crate) fn for_removal(
+        removal: &'a RemovalContext,
+        command_queue: &'a mut CommandQueue,
+    ) -> Self {
+        ContextState {
+            command_queue,
+            ext_handle: &removal.ext_handle,
+            window: &removal.window,
+            window_id: removal.window_id,
+            focus_widget: None,
+            text: removal.text.clone(),
+            root_app_data_type: removal.root_app_data_type,
+            frame_stats: None,
+            inspector: None,
+            registry: None,
+            removal: None,
+        }
+    }
+
+    /// Start timing a widget, if frame stats are enabled.
+    pub(crate) fn begin_widget_timing(&mut self) -> Option<Instant> {
+        self.frame_stats.as_mut().map(|stats| stats.begin_widget())
+    }
+
+    /// Finish timing a widget started with [`begin_widget_timing`].
+    ///
+    /// [`begin_widget_timing`]: ContextState::begin_widget_timing
+    pub(crate) fn end_widget_timing(
+        &mut self,
+        start: Option<Instant>,
+        name: &'static str,
+        id: WidgetId,
+    ) {
+        if let (Some(start), Some(stats)) = (start, self.frame_stats.as_mut()) {
+            stats.end_widget(start, name, id);
         }
     }
 
@@ -910,9 +1156,28 @@ impl<'a> ContextState<'a> {
     fn request_timer(&self, widget_state: &mut WidgetState, deadline: Duration) -> TimerToken {
         trace!("request_timer deadline={:?}", deadline);
         let timer_token = self.window.request_timer(deadline);
-        widget_state.add_timer(timer_token);
+        widget_state.add_timer(timer_token, deadline);
         timer_token
     }
+
+    fn schedule_command(
+        &self,
+        widget_state: &mut WidgetState,
+        command: Command,
+        delay: Duration,
+        repeat: bool,
+    ) -> CommandTimer {
+        let timer = CommandTimer::default();
+        let command = ScheduledCommand {
+            command: command.default_to(self.window_id.into()),
+            delay,
+            repeat,
+            timer: timer.clone(),
+        };
+        let timer_token = self.window.request_timer(delay);
+        widget_state.add_command_timer(timer_token, command);
+        timer
+    }
 }
 
 impl<'c> Deref for PaintCtx<'_, '_, 'c> {
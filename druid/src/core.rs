@@ -15,24 +15,51 @@
 //! The fundamental druid types.
 
 use std::collections::{HashMap, VecDeque};
-use tracing::{info_span, trace, warn};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::time::Duration;
+
+use tracing::{info_span, trace, trace_span, warn};
 
 use crate::bloom::Bloom;
-use crate::command::sys::{CLOSE_WINDOW, SUB_WINDOW_HOST_TO_PARENT, SUB_WINDOW_PARENT_TO_HOST};
-use crate::contexts::ContextState;
+use crate::command::sys::{
+    CLOSE_WINDOW, LOCATE_WIDGET, SUB_WINDOW_HOST_TO_PARENT, SUB_WINDOW_PARENT_TO_HOST,
+    WIDGET_LOCATED, WRITE_DATA,
+};
+use crate::command::ScheduledCommand;
+use crate::contexts::{ContextState, RemovalContext};
 use crate::kurbo::{Affine, Insets, Point, Rect, Shape, Size, Vec2};
+use crate::registry::{FocusDirection, WidgetInfo};
 use crate::sub_window::SubWindowUpdate;
 use crate::text::TextFieldRegistration;
-use crate::util::ExtendDrain;
+use crate::util::{short_type_name, ExtendDrain};
 use crate::{
-    ArcStr, BoxConstraints, Color, Command, Cursor, Data, Env, Event, EventCtx, InternalEvent,
-    InternalLifeCycle, LayoutCtx, LifeCycle, LifeCycleCtx, Notification, PaintCtx, Region,
-    RenderContext, Target, TextLayout, TimerToken, UpdateCtx, Widget, WidgetId, WindowId,
+    theme, ArcStr, BoxConstraints, Color, Command, Cursor, Data, Env, Event, EventCtx,
+    InternalEvent, InternalLifeCycle, LayoutCtx, LifeCycle, LifeCycleCtx, Notification, PaintCtx,
+    Region, RenderContext, Target, TextLayout, TimerToken, UpdateCtx, Widget, WidgetId, WindowId,
 };
 
 /// Our queue type
 pub(crate) type CommandQueue = VecDeque<Command>;
 
+/// What a `WidgetPod` needs to send `LifeCycle::Removed` when it is dropped.
+type Removal<T, W> = (
+    Rc<RemovalContext>,
+    fn(&mut WidgetPod<T, W>, &RemovalContext),
+);
+
+/// A timer that was requested by a widget.
+#[derive(Debug, Clone)]
+pub(crate) struct TimerRequest {
+    /// The widget that requested the timer, and will receive the `Timer` event.
+    pub(crate) widget: WidgetId,
+    /// The time between the request and the deadline.
+    pub(crate) delay: Duration,
+    /// The command to submit when the timer fires, instead of sending the
+    /// `Timer` event.
+    pub(crate) command: Option<ScheduledCommand>,
+}
+
 /// A container for one widget in the hierarchy.
 ///
 /// Generally, container widgets don't contain other widgets directly,
@@ -52,11 +79,25 @@ pub struct WidgetPod<T, W> {
     state: WidgetState,
     old_data: Option<T>,
     env: Option<Env>,
-    inner: W,
+    inner: PodWidget<W>,
+    /// Set when the widget is added, to tell it with `LifeCycle::Removed` when
+    /// it is dropped.
+    removal: Option<Removal<T, W>>,
+    /// Whether updates are held back while the widget is frozen.
+    defer_updates: bool,
+    /// The frozen state that the widget was last told about, with
+    /// `LifeCycle::FrozenChanged`.
+    frozen_seen: bool,
     // stashed layout so we don't recompute this when debugging
     debug_widget_text: TextLayout<ArcStr>,
 }
 
+/// The widget in a [`WidgetPod`].
+///
+/// `WidgetPod` implements `Drop`, so the widget can't be moved out of it
+/// directly; [`WidgetPod::boxed`] takes it out of this instead.
+struct PodWidget<W>(Option<W>);
+
 /// Generic state for all widgets in the hierarchy.
 ///
 /// This struct contains the widget's layout rect, flags
@@ -100,11 +141,25 @@ pub(crate) struct WidgetState {
     // The region that needs to be repainted, relative to the widget's bounds.
     pub(crate) invalid: Region,
 
+    // The paint rect of this widget in its parent's coordinate space, as of the
+    // last call to `set_origin`. If this changes during layout, both the old and
+    // the new rect need to be repainted.
+    last_paint_rect: Rect,
+
     // The part of this widget that is visible on the screen is offset by this
     // much. This will be non-zero for widgets that are children of `Scroll`, or
     // similar, and it is used for propagating invalid regions.
     pub(crate) viewport_offset: Vec2,
 
+    // The part of the window, in window coordinates, that this widget's children
+    // can be seen in: the paint rect of this widget, clipped by the viewports of
+    // its ancestors.
+    pub(crate) viewport: Rect,
+
+    // `true` if any part of this widget was inside `viewport` of its parent at the
+    // last `ParentWindowOrigin` pass.
+    pub(crate) is_viewport_visible: bool,
+
     // TODO: consider using bitflags for the booleans.
     // `true` if a descendent of this widget changed its disabled state and should receive
     // LifeCycle::DisabledChanged or InternalLifeCycle::RouteDisabledChanged
@@ -121,12 +176,23 @@ pub(crate) struct WidgetState {
     // LifeCycle::DisabledChanged or InternalLifeCycle::RouteDisabledChanged
     pub(crate) is_explicitly_disabled_new: bool,
 
+    // `true` if this widget is frozen: it is not painted, and the animation frames and paints
+    // that its subtree requests are held back until it is thawed.
+    pub(crate) is_frozen: bool,
+
+    /// A repaint was requested in this frozen subtree, and has to happen once it is thawed.
+    paint_on_thaw: bool,
+
     pub(crate) is_hot: bool,
 
     pub(crate) is_active: bool,
 
     pub(crate) needs_layout: bool,
 
+    /// The constraints this widget was last laid out with. If these are
+    /// unchanged and `needs_layout` is `false`, layout can be skipped.
+    last_bc: Option<BoxConstraints>,
+
     /// Because of some scrolling or something, `parent_window_origin` needs to be updated.
     pub(crate) needs_window_origin: bool,
 
@@ -150,7 +216,7 @@ pub(crate) struct WidgetState {
     pub(crate) children: Bloom<WidgetId>,
     pub(crate) children_changed: bool,
     /// Associate timers with widgets that requested them.
-    pub(crate) timers: HashMap<TimerToken, WidgetId>,
+    pub(crate) timers: HashMap<TimerToken, TimerRequest>,
     /// The cursor that was set using one of the context methods.
     pub(crate) cursor_change: CursorChange,
     /// The result of merging up children cursors. This gets cleared when merging state up (unlike
@@ -174,6 +240,8 @@ pub(crate) enum FocusChange {
     Next,
     /// Focus should pass to the previous focusable widget
     Previous,
+    /// Focus should pass to the nearest focusable widget in a direction
+    Direction(FocusDirection),
 }
 
 /// The possible cursor states for a widget.
@@ -202,7 +270,10 @@ impl<T, W: Widget<T>> WidgetPod<T, W> {
             state,
             old_data: None,
             env: None,
-            inner,
+            inner: PodWidget(Some(inner)),
+            removal: None,
+            defer_updates: false,
+            frozen_seen: false,
             debug_widget_text: TextLayout::new(),
         }
     }
@@ -243,6 +314,44 @@ impl<T, W: Widget<T>> WidgetPod<T, W> {
         self.state.id
     }
 
+    /// Freeze or thaw the widget and its descendants.
+    ///
+    /// This is meant for widgets that are kept around while they are not
+    /// shown, like the pages of [`Tabs`] that are not selected. A frozen
+    /// widget is not painted, and the animation frames and repaints that are
+    /// requested in its subtree are held back until it is thawed. It is still
+    /// laid out, and still gets events.
+    ///
+    /// The subtree is told with [`LifeCycle::FrozenChanged`], the next time
+    /// [`update`] is called on this pod; when freezing from an event, use
+    /// [`EventCtx::request_update`] to make sure that happens.
+    ///
+    /// [`Tabs`]: crate::widget::Tabs
+    /// [`update`]: WidgetPod::update
+    /// [`EventCtx::request_update`]: crate::EventCtx::request_update
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.state.is_frozen = frozen;
+    }
+
+    /// Returns `true` if the widget is frozen.
+    ///
+    /// See [`set_frozen`](WidgetPod::set_frozen).
+    pub fn is_frozen(&self) -> bool {
+        self.state.is_frozen
+    }
+
+    /// Set whether updates are deferred while the widget is frozen.
+    ///
+    /// When set, a frozen widget does not see changes to the data or the
+    /// [`Env`] until it is thawed, and then gets a single [`update`] with all
+    /// the changes. Note that the events it gets in the meantime still come
+    /// with the current data.
+    ///
+    /// [`update`]: WidgetPod::update
+    pub fn set_defer_updates(&mut self, defer: bool) {
+        self.defer_updates = defer;
+    }
+
     /// Set the layout [`Rect`].
     ///
     /// This is soft-deprecated; you should use [`set_origin`] instead for new code.
@@ -272,10 +381,21 @@ impl<T, W: Widget<T>> WidgetPod<T, W> {
         self.state.is_expecting_set_origin_call = false;
         let layout_rect = self.layout_rect();
 
+        // if the widget has moved or changed size, the area it used to cover
+        // and the area it now covers both need to be repainted.
+        let paint_rect = self.state.paint_rect() - self.state.viewport_offset;
+        if paint_rect != self.state.last_paint_rect {
+            ctx.widget_state
+                .invalid
+                .add_rect(self.state.last_paint_rect);
+            ctx.widget_state.invalid.add_rect(paint_rect);
+            self.state.last_paint_rect = paint_rect;
+        }
+
         // if the widget has moved, it may have moved under the mouse, in which
         // case we need to handle that.
         if WidgetPod::set_hot_state(
-            &mut self.inner,
+            &mut *self.inner,
             &mut self.state,
             ctx.state,
             layout_rect,
@@ -429,6 +549,14 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
     /// [`Widget::paint`]: trait.Widget.html#tymethod.paint
     /// [`paint`]: #method.paint
     pub fn paint_raw(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let _span = trace_span!("paint", widget = self.short_name(), id = self.id().to_raw());
+        let _span = _span.enter();
+
+        if self.state.is_frozen {
+            trace!("widget is frozen, skipping paint");
+            return;
+        }
+
         // we need to do this before we borrow from self
         if env.get(Env::DEBUG_WIDGET_ID) {
             self.make_widget_id_layout_if_needed(self.state.id, ctx, env);
@@ -442,7 +570,23 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             widget_state: &self.state,
             depth: ctx.depth,
         };
+        if let Some(inspector) = inner_ctx.state.inspector.as_mut() {
+            let rect = Rect::from_origin_size(self.state.window_origin(), self.state.size);
+            inspector.begin_widget(
+                self.state.id,
+                self.inner.type_name(),
+                rect,
+                self.state.last_bc,
+            );
+        }
+        let start = inner_ctx.state.begin_widget_timing();
         self.inner.paint(&mut inner_ctx, data, env);
+        inner_ctx
+            .state
+            .end_widget_timing(start, self.inner.type_name(), self.state.id);
+        if let Some(inspector) = inner_ctx.state.inspector.as_mut() {
+            inspector.end_widget();
+        }
 
         let debug_ids = inner_ctx.is_hot() && env.get(Env::DEBUG_WIDGET_ID);
         if debug_ids {
@@ -551,6 +695,13 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
         data: &T,
         env: &Env,
     ) -> Size {
+        let _span = trace_span!(
+            "layout",
+            widget = self.short_name(),
+            id = self.id().to_raw()
+        );
+        let _span = _span.enter();
+
         if !self.is_initialized() {
             debug_panic!(
                 "{:?}: layout method called before receiving WidgetAdded.",
@@ -559,9 +710,18 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             return Size::ZERO;
         }
 
+        // If nothing in this subtree has requested layout and the constraints
+        // are the same as last time, the previous layout is still valid.
+        if !self.state.needs_layout && self.state.last_bc == Some(*bc) {
+            trace!("skipping layout of clean subtree");
+            self.state.is_expecting_set_origin_call = true;
+            return self.state.size;
+        }
+
         self.state.needs_layout = false;
         self.state.needs_window_origin = false;
         self.state.is_expecting_set_origin_call = true;
+        self.state.last_bc = Some(*bc);
 
         let child_mouse_pos = ctx
             .mouse_pos
@@ -574,7 +734,12 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             mouse_pos: child_mouse_pos,
         };
 
+        let start = child_ctx.state.begin_widget_timing();
         let new_size = self.inner.layout(&mut child_ctx, bc, data, env);
+        let id = child_ctx.widget_state.id;
+        child_ctx
+            .state
+            .end_widget_timing(start, self.inner.type_name(), id);
         if new_size != prev_size {
             let mut child_ctx = LifeCycleCtx {
                 widget_state: child_ctx.widget_state,
@@ -595,6 +760,12 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
         new_size
     }
 
+    /// The type name of the widget without its module path, used to attribute
+    /// spans to widgets.
+    fn short_name(&self) -> &'static str {
+        short_type_name(self.inner.type_name())
+    }
+
     fn log_layout_issues(&self, size: Size) {
         if size.width.is_infinite() {
             let name = self.widget().type_name();
@@ -632,6 +803,9 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
     ///
     /// [`event`]: trait.Widget.html#tymethod.event
     pub fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        let _span = trace_span!("event", widget = self.short_name(), id = self.id().to_raw());
+        let _span = _span.enter();
+
         if !self.is_initialized() {
             debug_panic!(
                 "{:?}: event method called before receiving WidgetAdded.",
@@ -667,7 +841,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             Event::Internal(internal) => match internal {
                 InternalEvent::MouseLeave => {
                     let hot_changed = WidgetPod::set_hot_state(
-                        &mut self.inner,
+                        &mut *self.inner,
                         &mut self.state,
                         ctx.state,
                         rect,
@@ -712,7 +886,10 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                     }
                 }
             },
-            Event::WindowConnected | Event::WindowCloseRequested => true,
+            Event::WindowConnected
+            | Event::WindowCloseRequested
+            | Event::WindowSuspended
+            | Event::WindowResumed => true,
             Event::WindowDisconnected => {
                 for (window_id, _) in &self.state.sub_window_hosts {
                     ctx.submit_command(CLOSE_WINDOW.to(*window_id))
@@ -725,7 +902,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             }
             Event::MouseDown(mouse_event) => {
                 WidgetPod::set_hot_state(
-                    &mut self.inner,
+                    &mut *self.inner,
                     &mut self.state,
                     ctx.state,
                     rect,
@@ -744,7 +921,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             }
             Event::MouseUp(mouse_event) => {
                 WidgetPod::set_hot_state(
-                    &mut self.inner,
+                    &mut *self.inner,
                     &mut self.state,
                     ctx.state,
                     rect,
@@ -763,7 +940,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             }
             Event::MouseMove(mouse_event) => {
                 let hot_changed = WidgetPod::set_hot_state(
-                    &mut self.inner,
+                    &mut *self.inner,
                     &mut self.state,
                     ctx.state,
                     rect,
@@ -785,7 +962,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             }
             Event::Wheel(mouse_event) => {
                 WidgetPod::set_hot_state(
-                    &mut self.inner,
+                    &mut *self.inner,
                     &mut self.state,
                     ctx.state,
                     rect,
@@ -802,6 +979,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                     false
                 }
             }
+            Event::AnimFrame(_) if self.state.is_frozen => false,
             Event::AnimFrame(_) => {
                 let r = self.state.request_anim;
                 self.state.request_anim = false;
@@ -810,6 +988,27 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             Event::KeyDown(_) => self.state.has_focus,
             Event::KeyUp(_) => self.state.has_focus,
             Event::Paste(_) => self.state.has_focus,
+            Event::DragOver(drop_event) => {
+                if rect.contains(drop_event.pos) {
+                    let mut drop_event = drop_event.clone();
+                    drop_event.pos -= rect.origin().to_vec2();
+                    modified_event = Some(Event::DragOver(drop_event));
+                    true
+                } else {
+                    false
+                }
+            }
+            Event::DragLeave => true,
+            Event::Dropped(drop_event, clipboard) => {
+                if rect.contains(drop_event.pos) {
+                    let mut drop_event = drop_event.clone();
+                    drop_event.pos -= rect.origin().to_vec2();
+                    modified_event = Some(Event::Dropped(drop_event, clipboard.clone()));
+                    true
+                } else {
+                    false
+                }
+            }
             Event::Zoom(_) => had_active || self.state.is_hot,
             Event::Timer(_) => false, // This event was targeted only to our parent
             Event::ImeStateChange => true, // once delivered to the focus widget, recurse to the component?
@@ -818,6 +1017,7 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
         };
 
         if recurse {
+            let self_id = self.id();
             let mut notifications = VecDeque::new();
             let mut inner_ctx = EventCtx {
                 state: ctx.state,
@@ -839,6 +1039,23 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                     }
                     ctx.is_handled = true
                 }
+                Event::Command(cmd)
+                    if cmd.is(WRITE_DATA)
+                        && cmd.target() == Target::Widget(self_id)
+                        && cmd.get_unchecked(WRITE_DATA).applies_to::<T>() =>
+                {
+                    // Written here rather than by the widget, so that any widget's data can be written.
+                    cmd.get_unchecked(WRITE_DATA).apply(data);
+                    ctx.is_handled = true
+                }
+                Event::Command(cmd)
+                    if cmd.is(LOCATE_WIDGET) && cmd.target() == Target::Widget(self_id) =>
+                {
+                    // Answered here rather than by the widget, so that any widget can be located.
+                    let rect = inner_ctx.size().to_rect() + inner_ctx.window_origin().to_vec2();
+                    inner_ctx.submit_notification(WIDGET_LOCATED.with(rect));
+                    ctx.is_handled = true
+                }
                 _ => {
                     self.inner.event(&mut inner_ctx, &inner_event, data, env);
 
@@ -908,6 +1125,13 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
     ///
     /// [`LifeCycle`]: enum.LifeCycle.html
     pub fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        let _span = trace_span!(
+            "lifecycle",
+            widget = self.short_name(),
+            id = self.id().to_raw()
+        );
+        let _span = _span.enter();
+
         // in the case of an internal routing event, if we are at our target
         // we may send an extra event after the actual event
         let mut extra_event = None;
@@ -939,6 +1163,9 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                     self.state.is_explicitly_disabled = self.state.is_explicitly_disabled_new;
 
                     if was_disabled != self.state.is_disabled() {
+                        if let Some(registry) = ctx.state.registry.as_mut() {
+                            registry.set_disabled(self.id(), self.state.is_disabled());
+                        }
                         extra_event = Some(LifeCycle::DisabledChanged(self.state.is_disabled()));
                         //Each widget needs only one of DisabledChanged and RouteDisabledChanged
                         false
@@ -973,9 +1200,43 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                 InternalLifeCycle::ParentWindowOrigin => {
                     self.state.parent_window_origin = ctx.widget_state.window_origin();
                     self.state.needs_window_origin = false;
+
+                    let clip = ctx.widget_state.viewport;
+                    let window_rect =
+                        Rect::from_origin_size(self.state.window_origin(), self.state.size());
+                    self.state.viewport = (window_rect + self.state.paint_insets).intersect(clip);
+                    let is_visible = window_rect.intersect(clip).area() > 0.0;
+                    if is_visible != self.state.is_viewport_visible {
+                        self.state.is_viewport_visible = is_visible;
+                        extra_event = Some(LifeCycle::ViewportVisibilityChanged(is_visible));
+                    }
+
+                    if let Some(registry) = ctx.state.registry.as_mut() {
+                        let id = self.id();
+                        registry.insert(WidgetInfo {
+                            id,
+                            type_name: self.inner.type_name(),
+                            // the root widget is laid out in a state with its own id
+                            parent: Some(ctx.widget_state.id).filter(|parent| *parent != id),
+                            layout_rect: self.state.layout_rect(),
+                            window_rect,
+                            has_focus: ctx.state.focus_widget == Some(id),
+                            is_disabled: self.state.is_disabled(),
+                            is_visible,
+                        });
+                    }
                     true
                 }
-                #[cfg(test)]
+                InternalLifeCycle::RouteViewportHidden { widgets } => {
+                    if self.state.is_viewport_visible && widgets.contains(&self.id()) {
+                        self.state.is_viewport_visible = false;
+                        extra_event = Some(LifeCycle::ViewportVisibilityChanged(false));
+                    }
+                    // The bloom filter we're checking can return false positives.
+                    widgets
+                        .iter()
+                        .any(|widget| self.state.children.may_contain(widget))
+                }
                 InternalLifeCycle::DebugRequestState { widget, state_cell } => {
                     if *widget == self.id() {
                         state_cell.set(self.state.clone());
@@ -986,11 +1247,11 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                         self.state.children.may_contain(&widget)
                     }
                 }
-                #[cfg(test)]
                 InternalLifeCycle::DebugInspectState(f) => {
                     f.call(&self.state);
                     true
                 }
+                InternalLifeCycle::DebugFindLabel(_) => true,
             },
             LifeCycle::WidgetAdded => {
                 assert!(self.old_data.is_none());
@@ -1000,9 +1261,16 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
 
                 self.old_data = Some(data.clone());
                 self.env = Some(env.clone());
+                if let Some(removal) = ctx.state.removal {
+                    self.removal = Some((removal.clone(), Self::send_removed));
+                }
 
                 true
             }
+            LifeCycle::Removed => {
+                // This is sent to each widget as it is dropped, so don't recurse.
+                false
+            }
             _ if !self.is_initialized() => {
                 debug_panic!(
                     "{:?}: received LifeCycle::{:?} before WidgetAdded.",
@@ -1016,6 +1284,11 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
                 // This event was meant only for our parent, so don't recurse.
                 false
             }
+            LifeCycle::FrozenChanged(_) => true,
+            LifeCycle::ViewportVisibilityChanged(_) => {
+                // This is sent to each widget on its own, so don't recurse.
+                false
+            }
             LifeCycle::DisabledChanged(ancestors_disabled) => {
                 self.state.update_focus_chain = true;
 
@@ -1023,6 +1296,9 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
 
                 self.state.is_explicitly_disabled = self.state.is_explicitly_disabled_new;
                 self.state.ancestor_disabled = *ancestors_disabled;
+                if let Some(registry) = ctx.state.registry.as_mut() {
+                    registry.set_disabled(self.id(), self.state.is_disabled());
+                }
 
                 // the change direction (true -> false or false -> true) of our parent and ourself
                 // is always the same, or we dont change at all, because we stay disabled if either
@@ -1110,6 +1386,26 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
         ctx.widget_state.merge_up(&mut self.state);
     }
 
+    /// Tell the widget that it was removed from the tree, as it is dropped.
+    fn send_removed(&mut self, removal: &RemovalContext) {
+        let (data, env) = match (self.old_data.as_ref(), self.env.as_ref()) {
+            (Some(data), Some(env)) => (data, env),
+            _ => return,
+        };
+        trace!("Sending LifeCycle::Removed");
+        // the widget may drop its own children while it handles this, which
+        // tells them in turn, so the commands are collected here first.
+        let mut queue = CommandQueue::new();
+        let mut state = ContextState::for_removal(removal, &mut queue);
+        let mut ctx = LifeCycleCtx {
+            state: &mut state,
+            widget_state: &mut self.state,
+        };
+        self.inner
+            .lifecycle(&mut ctx, &LifeCycle::Removed, data, env);
+        removal.submit_commands(&mut queue);
+    }
+
     /// Propagate a data update.
     ///
     /// Generally called by container widgets as part of their [`update`]
@@ -1117,6 +1413,36 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
     ///
     /// [`update`]: trait.Widget.html#tymethod.update
     pub fn update(&mut self, ctx: &mut UpdateCtx, data: &T, env: &Env) {
+        let _span = trace_span!(
+            "update",
+            widget = self.short_name(),
+            id = self.id().to_raw()
+        );
+        let _span = _span.enter();
+
+        if self.frozen_seen != self.state.is_frozen {
+            self.frozen_seen = self.state.is_frozen;
+            if let Some(old_data) = self.old_data.as_ref() {
+                let mut child_ctx = LifeCycleCtx {
+                    state: ctx.state,
+                    widget_state: &mut self.state,
+                };
+                let event = LifeCycle::FrozenChanged(self.frozen_seen);
+                self.inner.lifecycle(&mut child_ctx, &event, old_data, env);
+            }
+            if !self.state.is_frozen && self.state.paint_on_thaw {
+                self.state.paint_on_thaw = false;
+                self.state.invalid.set_rect(
+                    self.state.paint_rect() - self.state.layout_rect().origin().to_vec2(),
+                );
+            }
+            ctx.widget_state.merge_up(&mut self.state);
+        }
+        if self.state.is_frozen && self.defer_updates {
+            trace!("widget is frozen, deferring update");
+            return;
+        }
+
         if !self.state.request_update {
             match (self.old_data.as_ref(), self.env.as_ref()) {
                 (Some(d), Some(e)) if d.same(data) && e.same(env) => {
@@ -1165,6 +1491,11 @@ impl<T: Data, W: Widget<T>> WidgetPod<T, W> {
             env,
         };
 
+        // everything is measured in the theme's metrics, which the zoom changes.
+        if child_ctx.env_key_changed(&theme::UI_ZOOM) {
+            child_ctx.request_layout();
+        }
+
         self.inner
             .update(&mut child_ctx, self.old_data.as_ref().unwrap(), data, env);
         self.old_data = Some(data.clone());
@@ -1180,12 +1511,19 @@ impl<T, W: Widget<T> + 'static> WidgetPod<T, W> {
     ///
     /// Convert a `WidgetPod` containing a widget of a specific concrete type
     /// into a dynamically boxed widget.
-    pub fn boxed(self) -> WidgetPod<T, Box<dyn Widget<T>>> {
-        WidgetPod::new(Box::new(self.inner))
+    pub fn boxed(mut self) -> WidgetPod<T, Box<dyn Widget<T>>> {
+        // the widget lives on in the new pod, so it isn't removed.
+        self.removal = None;
+        WidgetPod::new(Box::new(self.inner.0.take().unwrap()))
     }
 }
 
 impl<T, W> WidgetPod<T, W> {
+    /// Don't send [`LifeCycle::Removed`] to the widget when it is dropped.
+    pub(crate) fn skip_removed(&mut self) {
+        self.removal = None;
+    }
+
     /// Return a reference to the inner widget.
     pub fn widget(&self) -> &W {
         &self.inner
@@ -1197,6 +1535,36 @@ impl<T, W> WidgetPod<T, W> {
     }
 }
 
+impl<T, W> Drop for WidgetPod<T, W> {
+    fn drop(&mut self) {
+        // a panic while panicking would abort.
+        if std::thread::panicking() {
+            return;
+        }
+        if let Some((removal, send_removed)) = self.removal.take() {
+            send_removed(self, &removal);
+        }
+    }
+}
+
+impl<W> Deref for PodWidget<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        self.0
+            .as_ref()
+            .expect("widget was taken out of its WidgetPod")
+    }
+}
+
+impl<W> DerefMut for PodWidget<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        self.0
+            .as_mut()
+            .expect("widget was taken out of its WidgetPod")
+    }
+}
+
 impl WidgetState {
     pub(crate) fn new(id: WidgetId, size: Option<Size>) -> WidgetState {
         WidgetState {
@@ -1207,13 +1575,17 @@ impl WidgetState {
             is_expecting_set_origin_call: true,
             paint_insets: Insets::ZERO,
             invalid: Region::EMPTY,
+            last_paint_rect: Rect::ZERO,
             viewport_offset: Vec2::ZERO,
+            viewport: size.unwrap_or_default().to_rect(),
+            is_viewport_visible: false,
             children_disabled_changed: false,
             ancestor_disabled: false,
             is_explicitly_disabled: false,
             baseline_offset: 0.0,
             is_hot: false,
             needs_layout: false,
+            last_bc: None,
             needs_window_origin: false,
             is_active: false,
             has_active: false,
@@ -1229,6 +1601,8 @@ impl WidgetState {
             cursor: None,
             sub_window_hosts: Vec::new(),
             is_explicitly_disabled_new: false,
+            is_frozen: false,
+            paint_on_thaw: false,
             text_registrations: Vec::new(),
             update_focus_chain: false,
         }
@@ -1243,8 +1617,22 @@ impl WidgetState {
             || self.is_explicitly_disabled != self.is_explicitly_disabled_new
     }
 
-    pub(crate) fn add_timer(&mut self, timer_token: TimerToken) {
-        self.timers.insert(timer_token, self.id);
+    pub(crate) fn add_timer(&mut self, timer_token: TimerToken, delay: Duration) {
+        let request = TimerRequest {
+            widget: self.id,
+            delay,
+            command: None,
+        };
+        self.timers.insert(timer_token, request);
+    }
+
+    pub(crate) fn add_command_timer(&mut self, timer_token: TimerToken, command: ScheduledCommand) {
+        let request = TimerRequest {
+            widget: self.id,
+            delay: command.delay,
+            command: Some(command),
+        };
+        self.timers.insert(timer_token, request);
     }
 
     /// Update to incorporate state changes from a child.
@@ -1263,10 +1651,15 @@ impl WidgetState {
             .with_origin(Point::ORIGIN)
             .inset(self.paint_insets);
         let offset = child_state.layout_rect().origin().to_vec2() - child_state.viewport_offset;
-        for &r in child_state.invalid.rects() {
-            let r = (r + offset).intersect(clip);
-            if r.area() != 0.0 {
-                self.invalid.add_rect(r);
+        // A frozen child is not painted, so there is nothing to invalidate until it is thawed.
+        if child_state.is_frozen {
+            child_state.paint_on_thaw |= !child_state.invalid.is_empty();
+        } else {
+            for &r in child_state.invalid.rects() {
+                let r = (r + offset).intersect(clip);
+                if r.area() != 0.0 {
+                    self.invalid.add_rect(r);
+                }
             }
         }
         // Clearing the invalid rects here is less fragile than doing it while painting. The
@@ -1277,7 +1670,8 @@ impl WidgetState {
 
         self.needs_layout |= child_state.needs_layout;
         self.needs_window_origin |= child_state.needs_window_origin;
-        self.request_anim |= child_state.request_anim;
+        // Animation frames are requested again once a frozen child is thawed.
+        self.request_anim |= child_state.request_anim && !child_state.is_frozen;
         self.children_disabled_changed |= child_state.children_disabled_changed;
         self.children_disabled_changed |=
             child_state.is_explicitly_disabled_new != child_state.is_explicitly_disabled;
@@ -526,6 +526,12 @@ impl Data for piet::Color {
     }
 }
 
+impl Data for piet::GradientStop {
+    fn same(&self, other: &Self) -> bool {
+        self.pos.same(&other.pos) && self.color.same(&other.color)
+    }
+}
+
 impl Data for piet::FontFamily {
     fn same(&self, other: &Self) -> bool {
         self == other
@@ -617,9 +623,255 @@ impl<T: Data, const N: usize> Data for [T; N] {
     }
 }
 
+/// A wrapper that tracks modifications of its contents with a version stamp.
+///
+/// Comparing two `Versioned` values only compares their stamps, which makes
+/// [`Data::same`] constant-time no matter how expensive the inner type is to
+/// compare. This lets the `update` pass skip any subtree lensed onto a
+/// `Versioned` value that was not modified, without ever comparing the
+/// contents; it also allows types that do not implement `Data` at all (such
+/// as `Vec`) to be used as app state.
+///
+/// A fresh stamp is taken on every explicit write through [`get_mut`]. Stamps
+/// are unique across the whole program, so two values are only considered
+/// the same if one is an unmodified clone of the other. There is deliberately
+/// no `DerefMut`: widgets get a `&mut` to their data for every event, and that
+/// alone must not look like a change.
+///
+/// To lens into the contents, use [`LensExt::in_arc`]; like for an `Arc`, the
+/// contents are compared and a new stamp is only taken if they changed.
+///
+/// # Examples
+///
+/// ```
+/// use druid::{Data, Versioned};
+///
+/// let items = Versioned::new(vec![1, 2, 3]);
+/// let mut copy = items.clone();
+/// assert!(items.same(&copy));
+///
+/// copy.get_mut().push(4);
+/// assert!(!items.same(&copy));
+/// ```
+///
+/// [`get_mut`]: Versioned::get_mut
+/// [`LensExt::in_arc`]: crate::LensExt::in_arc
+#[derive(Debug, Clone)]
+pub struct Versioned<T> {
+    inner: T,
+    version: u64,
+}
+
+impl<T> Versioned<T> {
+    /// Wrap `inner`, giving it a new version stamp.
+    pub fn new(inner: T) -> Self {
+        Versioned {
+            inner,
+            version: Self::next_version(),
+        }
+    }
+
+    /// The current version stamp.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns a reference to the contents.
+    pub fn get(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the contents, taking a new version stamp.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.version = Self::next_version();
+        &mut self.inner
+    }
+
+    /// Unwrap the contents.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn next_version() -> u64 {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static VERSION: AtomicU64 = AtomicU64::new(1);
+        VERSION.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl<T> std::ops::Deref for Versioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: Clone> MakeMut for Versioned<T> {
+    fn make_mut(&mut self) -> &mut T {
+        self.get_mut()
+    }
+
+    /// Always `false`: any widget may have kept the current stamp, so writes
+    /// have to be compared with the original value.
+    fn is_unique(&mut self) -> bool {
+        false
+    }
+}
+
+impl<T: Default> Default for Versioned<T> {
+    fn default() -> Self {
+        Versioned::new(T::default())
+    }
+}
+
+impl<T> From<T> for Versioned<T> {
+    fn from(inner: T) -> Self {
+        Versioned::new(inner)
+    }
+}
+
+impl<T: Clone + 'static> Data for Versioned<T> {
+    fn same(&self, other: &Self) -> bool {
+        self.version == other.version
+    }
+}
+
+/// A pointer to a value that can be borrowed mutably with copy-on-write
+/// semantics, like `Arc`, `Rc` and [`Versioned`].
+///
+/// This is used by [`Edit`], [`InArc`] and [`lens_path!`].
+///
+/// [`InArc`]: crate::lens::InArc
+/// [`lens_path!`]: crate::lens_path
+pub trait MakeMut: Clone + std::ops::Deref {
+    /// Returns a mutable reference to the value, cloning it first if the
+    /// pointer is shared.
+    fn make_mut(&mut self) -> &mut Self::Target;
+
+    /// Returns `true` if there are no other pointers to the value.
+    fn is_unique(&mut self) -> bool;
+}
+
+impl<T: Clone> MakeMut for Arc<T> {
+    fn make_mut(&mut self) -> &mut T {
+        Arc::make_mut(self)
+    }
+
+    fn is_unique(&mut self) -> bool {
+        Arc::get_mut(self).is_some()
+    }
+}
+
+impl<T: Clone> MakeMut for Rc<T> {
+    fn make_mut(&mut self) -> &mut T {
+        Rc::make_mut(self)
+    }
+
+    fn is_unique(&mut self) -> bool {
+        Rc::get_mut(self).is_some()
+    }
+}
+
+/// A guard for editing the value behind an `Arc` or an `Rc` in place.
+///
+/// The value is only cloned the first time it is borrowed mutably, and only
+/// if the pointer is shared, which is always the case for data that has been
+/// shown by a widget. Reading through the guard never clones.
+///
+/// When the guard is dropped, the edited value is compared with the original
+/// one. If they are the same, the original pointer is put back, so that a
+/// no-op edit doesn't look like a change to the widgets that compare it.
+/// Such an edit still paid for a deep clone, though, so it is logged at the
+/// debug level; where it is cheap to tell, only borrow the value mutably when
+/// it is going to change.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use druid::{Data, Edit};
+///
+/// #[derive(Clone, Data)]
+/// struct Settings { volume: f64, muted: bool }
+///
+/// let mut settings = Arc::new(Settings { volume: 0.5, muted: false });
+/// let shown = settings.clone();
+///
+/// let mut edit = Edit::new(&mut settings);
+/// if edit.volume > 0.4 {
+///     edit.volume = 0.4;
+/// }
+/// drop(edit);
+/// assert!(!shown.same(&settings));
+/// assert_eq!(shown.volume, 0.5);
+/// ```
+pub struct Edit<'a, P: MakeMut>
+where
+    P::Target: Data,
+{
+    ptr: &'a mut P,
+    /// The pointer from before the first mutable borrow, if that cloned the value.
+    original: Option<P>,
+}
+
+impl<'a, P: MakeMut> Edit<'a, P>
+where
+    P::Target: Data,
+{
+    /// Start editing the value behind `ptr`.
+    pub fn new(ptr: &'a mut P) -> Self {
+        Edit {
+            ptr,
+            original: None,
+        }
+    }
+}
+
+impl<P: MakeMut> std::ops::Deref for Edit<'_, P>
+where
+    P::Target: Data,
+{
+    type Target = P::Target;
+
+    fn deref(&self) -> &P::Target {
+        self.ptr
+    }
+}
+
+impl<P: MakeMut> std::ops::DerefMut for Edit<'_, P>
+where
+    P::Target: Data,
+{
+    fn deref_mut(&mut self) -> &mut P::Target {
+        if self.original.is_none() && !self.ptr.is_unique() {
+            self.original = Some(self.ptr.clone());
+        }
+        self.ptr.make_mut()
+    }
+}
+
+impl<P: MakeMut> Drop for Edit<'_, P>
+where
+    P::Target: Data,
+{
+    fn drop(&mut self) {
+        if let Some(original) = self.original.take() {
+            if (*original).same(&**self.ptr) {
+                *self.ptr = original;
+                tracing::debug!(
+                    "an Edit of {} cloned the value without changing it",
+                    std::any::type_name::<P>()
+                );
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Data;
+    use super::{Data, Edit, Versioned};
+    use std::sync::Arc;
     use test_env_log::test;
 
     #[test]
@@ -660,4 +912,47 @@ mod test {
         // intern it, and reuse it for all "text" `&'static str`s.
         assert!(Data::same(&first, &same));
     }
+
+    #[test]
+    fn versioned() {
+        let one = Versioned::new(vec![1, 2, 3]);
+        let mut two = one.clone();
+        assert!(one.same(&two));
+
+        two.get_mut().push(4);
+        assert!(!one.same(&two));
+
+        // equal contents are not enough; only unmodified clones are the same.
+        let three = Versioned::new(vec![1, 2, 3]);
+        assert!(!one.same(&three));
+    }
+
+    #[test]
+    fn edit_clones_only_when_writing() {
+        let mut data = Arc::new((1, 2));
+        let shown = data.clone();
+
+        let edit = Edit::new(&mut data);
+        assert_eq!(edit.0, 1);
+        drop(edit);
+        assert!(Arc::ptr_eq(&shown, &data));
+
+        let mut edit = Edit::new(&mut data);
+        edit.1 = 3;
+        drop(edit);
+        assert_eq!(*data, (1, 3));
+        assert_eq!(*shown, (1, 2));
+    }
+
+    #[test]
+    fn edit_without_change_keeps_pointer() {
+        let mut data = Arc::new((1, 2));
+        let shown = data.clone();
+
+        // like a clamp that writes back the value it already had.
+        let mut edit = Edit::new(&mut data);
+        edit.1 = edit.1.min(5);
+        drop(edit);
+        assert!(Arc::ptr_eq(&shown, &data));
+    }
 }
@@ -0,0 +1,347 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structural diffs of app data, for debugging updates.
+
+use std::fmt::{self, Debug, Display};
+
+use crate::Data;
+
+/// Above this many pairs of lines, changed regions are not diffed line by
+/// line, but reported as a whole.
+const MAX_DIFF_COST: usize = 250_000;
+
+/// The differences between two values, as found in their pretty-printed
+/// `Debug` representations.
+///
+/// Every changed line is reported with the path of the field it belongs to,
+/// like `settings.recent[2].name`, which makes it easy to find out what part
+/// of the app data changed between two update passes.
+///
+/// An empty diff for two values that are not [`same`] means that a value was
+/// replaced by an equal copy, like an `Arc` that was cloned deeply; widgets
+/// will still be updated.
+///
+/// This requires the `data-diff` feature. To log the diff of the app data
+/// before every update, use [`AppLauncher::log_data_diffs`].
+///
+/// ```
+/// use druid::DataDiff;
+///
+/// #[derive(Debug)]
+/// struct Theme { accent: u32, names: Vec<&'static str> }
+///
+/// let old = Theme { accent: 1, names: vec!["light", "dark"] };
+/// let new = Theme { accent: 1, names: vec!["light", "dusk"] };
+/// let diff = DataDiff::new(&old, &new);
+/// assert_eq!(diff.to_string(), "- names[1]: \"dark\"\n+ names[1]: \"dusk\"\n");
+/// ```
+///
+/// [`same`]: Data::same
+/// [`AppLauncher::log_data_diffs`]: crate::AppLauncher::log_data_diffs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataDiff {
+    lines: Vec<DiffLine>,
+}
+
+/// A line that was removed from or added to the `Debug` representation of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    /// A line of the old value.
+    Removed {
+        /// The path of the field the line belongs to.
+        path: String,
+        /// The line, without its indentation and field name.
+        text: String,
+    },
+    /// A line of the new value.
+    Added {
+        /// The path of the field the line belongs to.
+        path: String,
+        /// The line, without its indentation and field name.
+        text: String,
+    },
+}
+
+/// A line of a pretty-printed value, and where it is in the value.
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    /// The line, without its indentation and trailing comma.
+    raw: &'a str,
+    path: String,
+    /// The line, without its field name.
+    text: &'a str,
+}
+
+/// A container that is open at some line, when walking the lines.
+struct Open {
+    /// `{`, `[` or `(`.
+    kind: char,
+    path: String,
+    children: usize,
+}
+
+impl DataDiff {
+    /// Compute the differences between two values.
+    pub fn new<T: Debug + ?Sized>(old: &T, new: &T) -> Self {
+        let old_text = format!("{:#?}", old);
+        let new_text = format!("{:#?}", new);
+        let old_lines = lines(&old_text);
+        let new_lines = lines(&new_text);
+
+        let prefix = old_lines
+            .iter()
+            .zip(&new_lines)
+            .take_while(|(o, n)| o.raw == n.raw && o.path == n.path)
+            .count();
+        let suffix = old_lines[prefix..]
+            .iter()
+            .rev()
+            .zip(new_lines[prefix..].iter().rev())
+            .take_while(|(o, n)| o.raw == n.raw && o.path == n.path)
+            .count();
+        let old_lines = &old_lines[prefix..old_lines.len() - suffix];
+        let new_lines = &new_lines[prefix..new_lines.len() - suffix];
+
+        let mut diff = DataDiff::default();
+        if old_lines.len() * new_lines.len() > MAX_DIFF_COST {
+            diff.lines.extend(old_lines.iter().map(DiffLine::removed));
+            diff.lines.extend(new_lines.iter().map(DiffLine::added));
+        } else {
+            diff.diff_lines(old_lines, new_lines);
+        }
+        diff
+    }
+
+    /// Returns `true` if the `Debug` representations of the values are the same.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The changed lines, with the removed lines of each change before the added ones.
+    pub fn lines(&self) -> &[DiffLine] {
+        &self.lines
+    }
+
+    /// Diff two runs of lines by their longest common subsequence.
+    fn diff_lines(&mut self, old: &[Line], new: &[Line]) {
+        let same = |o: &Line, n: &Line| o.raw == n.raw && o.path == n.path;
+        // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+        let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i][j] = if same(&old[i], &new[j]) {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let mut added = Vec::new();
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && same(&old[i], &new[j]) {
+                self.lines.append(&mut added);
+                i += 1;
+                j += 1;
+            } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+                self.lines.push(DiffLine::removed(&old[i]));
+                i += 1;
+            } else {
+                added.push(DiffLine::added(&new[j]));
+                j += 1;
+            }
+        }
+        self.lines.append(&mut added);
+    }
+}
+
+impl DiffLine {
+    fn removed(line: &Line) -> Self {
+        DiffLine::Removed {
+            path: line.path.clone(),
+            text: line.text.to_string(),
+        }
+    }
+
+    fn added(line: &Line) -> Self {
+        DiffLine::Added {
+            path: line.path.clone(),
+            text: line.text.to_string(),
+        }
+    }
+}
+
+impl Display for DataDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DiffLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (sign, path, text) = match self {
+            DiffLine::Removed { path, text } => ('-', path, text),
+            DiffLine::Added { path, text } => ('+', path, text),
+        };
+        if path.is_empty() {
+            write!(f, "{} {}", sign, text)
+        } else {
+            write!(f, "{} {}: {}", sign, path, text)
+        }
+    }
+}
+
+/// Split a value pretty-printed with `{:#?}` into lines, and find the path of each.
+fn lines(text: &str) -> Vec<Line> {
+    let mut open: Vec<Open> = Vec::new();
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let raw = line.trim_start();
+        let raw = raw.strip_suffix(',').unwrap_or(raw);
+        if raw.starts_with(&['}', ']', ')'][..]) {
+            let path = open.pop().map(|o| o.path).unwrap_or_default();
+            lines.push(Line {
+                raw,
+                path,
+                text: raw,
+            });
+            continue;
+        }
+
+        let (path, text) = match open.last_mut() {
+            None => (String::new(), raw),
+            Some(parent) => {
+                let index = parent.children;
+                parent.children += 1;
+                let (segment, text) = match parent.kind {
+                    '{' => match raw.find(": ") {
+                        Some(colon) => (format!(".{}", &raw[..colon]), &raw[colon + 2..]),
+                        None => (format!("[{}]", index), raw),
+                    },
+                    '[' => (format!("[{}]", index), raw),
+                    _ => (format!(".{}", index), raw),
+                };
+                let path = format!("{}{}", parent.path, segment);
+                let path = path.strip_prefix('.').map(str::to_string).unwrap_or(path);
+                (path, text)
+            }
+        };
+        if let Some(kind) = raw.chars().last().filter(|c| ['{', '[', '('].contains(c)) {
+            open.push(Open {
+                kind,
+                path: path.clone(),
+                children: 0,
+            });
+        }
+        lines.push(Line { raw, path, text });
+    }
+    lines
+}
+
+/// Logs the diff of the app data before every update.
+pub(crate) struct DiffLog<T> {
+    last: T,
+    diff: fn(&T, &T) -> DataDiff,
+}
+
+impl<T: Data> DiffLog<T> {
+    pub(crate) fn new(data: &T, diff: fn(&T, &T) -> DataDiff) -> Self {
+        DiffLog {
+            last: data.clone(),
+            diff,
+        }
+    }
+
+    pub(crate) fn log(&mut self, data: &T) {
+        if self.last.same(data) {
+            tracing::debug!(target: "druid::data_diff", "app data is unchanged");
+            return;
+        }
+        let diff = (self.diff)(&self.last, data);
+        if diff.is_empty() {
+            tracing::debug!(
+                target: "druid::data_diff",
+                "app data is not the same, but is equal; something was replaced by an equal copy"
+            );
+        } else {
+            tracing::debug!(target: "druid::data_diff", "app data changed:\n{}", diff);
+        }
+        self.last = data.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[derive(Debug)]
+    struct Settings {
+        theme: Theme,
+        recent: Vec<(u32, &'static str)>,
+    }
+
+    #[derive(Debug)]
+    struct Theme {
+        accent: u32,
+        dark: bool,
+    }
+
+    fn settings(accent: u32, recent: &[(u32, &'static str)]) -> Settings {
+        Settings {
+            theme: Theme {
+                accent,
+                dark: false,
+            },
+            recent: recent.to_vec(),
+        }
+    }
+
+    #[test]
+    fn paths_of_changes() {
+        let old = settings(1, &[(1, "one"), (2, "two")]);
+        let new = settings(2, &[(1, "one"), (2, "zwei")]);
+        let diff = DataDiff::new(&old, &new);
+        assert_eq!(
+            diff.to_string(),
+            "- theme.accent: 1\n+ theme.accent: 2\n- recent[1].1: \"two\"\n+ recent[1].1: \"zwei\"\n"
+        );
+        assert!(DataDiff::new(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn added_elements() {
+        let old = settings(1, &[(1, "one")]);
+        let new = settings(1, &[(1, "one"), (2, "two")]);
+        let added: Vec<_> = DataDiff::new(&old, &new)
+            .lines()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            added,
+            vec![
+                "+ recent[1]: (",
+                "+ recent[1].0: 2",
+                "+ recent[1].1: \"two\"",
+                "+ recent[1]: )",
+            ]
+        );
+    }
+}
@@ -22,9 +22,9 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 use std::sync::Arc;
 
-use crate::localization::L10nManager;
+use crate::localization::{L10nManager, LocalizationBundle};
 use crate::text::FontDescriptor;
-use crate::{ArcStr, Color, Data, Insets, Point, Rect, Size};
+use crate::{ArcStr, Color, Data, InputMode, Insets, Point, Rect, Size};
 
 /// An environment passed down through all widget traversals.
 ///
@@ -111,6 +111,7 @@ pub enum Value {
     UnsignedInt(u64),
     String(ArcStr),
     Font(FontDescriptor),
+    InputMode(InputMode),
 }
 // ANCHOR_END: value_type
 
@@ -445,6 +446,7 @@ impl Value {
                 | (UnsignedInt(_), UnsignedInt(_))
                 | (String(_), String(_))
                 | (Font(_), Font(_))
+                | (InputMode(_), InputMode(_))
         )
     }
 }
@@ -462,6 +464,7 @@ impl Debug for Value {
             Value::UnsignedInt(x) => write!(f, "UnsignedInt {}", x),
             Value::String(s) => write!(f, "String {:?}", s),
             Value::Font(font) => write!(f, "Font {:?}", font),
+            Value::InputMode(mode) => write!(f, "InputMode {:?}", mode),
         }
     }
 }
@@ -507,13 +510,21 @@ const DEBUG_COLOR: &[Color] = &[
 
 impl Default for Env {
     fn default() -> Self {
-        Env::with_i10n(vec!["builtin.ftl".into()], "./resources/i18n/")
+        Env::with_default_i10n(Vec::new())
     }
 }
 
 impl Env {
-    pub(crate) fn with_i10n(resources: Vec<String>, base_dir: &str) -> Self {
-        let l10n = L10nManager::new(resources, base_dir);
+    pub(crate) fn with_default_i10n(bundles: Vec<LocalizationBundle>) -> Self {
+        Env::with_i10n(vec!["builtin.ftl".into()], "./resources/i18n/", bundles)
+    }
+
+    pub(crate) fn with_i10n(
+        resources: Vec<String>,
+        base_dir: &str,
+        bundles: Vec<LocalizationBundle>,
+    ) -> Self {
+        let l10n = L10nManager::new(resources, base_dir, bundles);
 
         let inner = EnvImpl {
             l10n: Arc::new(l10n),
@@ -597,6 +608,7 @@ impl_value_type!(Size, Size);
 impl_value_type!(Insets, Insets);
 impl_value_type!(ArcStr, String);
 impl_value_type!(FontDescriptor, Font);
+impl_value_type!(InputMode, InputMode);
 
 impl<T: ValueType> KeyOrValue<T> {
     /// Resolve the concrete type `T` from this `KeyOrValue`, using the provided
@@ -16,10 +16,10 @@
 
 use crate::kurbo::{Rect, Shape, Size, Vec2};
 
-use druid_shell::{Clipboard, KeyEvent, TimerToken};
+use druid_shell::{Clipboard, DropEvent, KbKey, KeyEvent, PointerType, TimerToken};
 
 use crate::mouse::MouseEvent;
-use crate::{Command, Notification, WidgetId};
+use crate::{Command, Data, Notification, WidgetId};
 
 /// An event, propagated downwards during event flow.
 ///
@@ -74,6 +74,18 @@ pub enum Event {
     /// This event means the window *will* go away; it is safe to dispose of resources and
     /// do any other cleanup.
     WindowDisconnected,
+    /// Sent to all widgets in a given window when the platform suspends it,
+    /// for instance because it was minimized.
+    ///
+    /// The window is not painted and no animation frames are sent until
+    /// [`Event::WindowResumed`]. Widgets that do work in the background, such
+    /// as playing media, may want to pause it.
+    WindowSuspended,
+    /// Sent to all widgets in a given window when it can be painted again
+    /// after [`Event::WindowSuspended`].
+    ///
+    /// The whole window is repainted.
+    WindowResumed,
     /// Called on the root widget when the window size changes.
     ///
     /// Discussion: it's not obvious this should be propagated to user
@@ -111,6 +123,27 @@ pub enum Event {
     KeyUp(KeyEvent),
     /// Called when a paste command is received.
     Paste(Clipboard),
+    /// Called when something is dragged over the window, such as files from a
+    /// file manager, and each time it moves while over it.
+    ///
+    /// This is sent to the widgets under [`DropEvent::pos`]. A widget that can
+    /// take the data at that position should call [`set_handled`]; otherwise
+    /// it can't be dropped there. This is currently only sent on X11.
+    ///
+    /// [`set_handled`]: crate::EventCtx::set_handled
+    DragOver(DropEvent),
+    /// Sent to all widgets in a window when something that was dragged over it
+    /// leaves it, or the drag is cancelled.
+    DragLeave,
+    /// Called when something is dropped on the window, at a position where
+    /// the last [`Event::DragOver`] was handled.
+    ///
+    /// Like [`Event::DragOver`], this is sent to the widgets under the
+    /// position. The data can be read from the [`Clipboard`] in any of the
+    /// [`DropEvent::formats`]; call [`set_handled`] once it has been used.
+    ///
+    /// [`set_handled`]: crate::EventCtx::set_handled
+    Dropped(DropEvent, Clipboard),
     /// Called when the trackpad is pinched.
     ///
     /// The value is a delta.
@@ -274,6 +307,51 @@ pub enum LifeCycle {
     /// [`is_disabled`]: crate::EventCtx::is_disabled
     /// [`set_disabled`]: crate::EventCtx::set_disabled
     DisabledChanged(bool),
+    /// Called when a widget, or one of its ancestors, is frozen or thawed.
+    ///
+    /// Frozen widgets are not painted, and do not get animation frames; this
+    /// is a good time to release resources that are only needed for painting,
+    /// like cached images.
+    ///
+    /// See [`WidgetPod::set_frozen`] for details.
+    ///
+    /// [`WidgetPod::set_frozen`]: crate::WidgetPod::set_frozen
+    FrozenChanged(bool),
+    /// Called when the widget starts or stops being visible inside the
+    /// viewports that enclose it.
+    ///
+    /// A widget is visible when any part of it is inside the window, and
+    /// inside the visible part of every [`Scroll`] or similar container that it
+    /// is in. Widgets that are not shown at all, like the pages of a [`Tabs`]
+    /// that are not selected, are not visible. Widgets start out not visible,
+    /// and are told that they are visible after their first layout.
+    ///
+    /// This is a good time to start or stop work that is only needed while the
+    /// widget can be seen, like decoding video or polling for data.
+    ///
+    /// See [`is_viewport_visible`] for the current state.
+    ///
+    /// [`Scroll`]: crate::widget::Scroll
+    /// [`Tabs`]: crate::widget::Tabs
+    /// [`is_viewport_visible`]: crate::LifeCycleCtx::is_viewport_visible
+    ViewportVisibilityChanged(bool),
+    /// Called when the widget leaves the tree, just before it is dropped.
+    ///
+    /// This is sent to every widget that received [`WidgetAdded`], however it
+    /// leaves the tree: whether its container drops it, or the container is
+    /// dropped along with it, or its window closes. A widget is told before
+    /// its children, which it can still reach, but it doesn't need to pass
+    /// this event on; each child is told on its own as it is dropped.
+    ///
+    /// This is the place to release resources that would otherwise outlive
+    /// the widget, like a [`CommandTimer`] that repeats, a running task, or a
+    /// subscription to some data source. The widget is no longer part of the
+    /// window, so the context can't be used to change it; commands submitted
+    /// with it are still delivered.
+    ///
+    /// [`WidgetAdded`]: LifeCycle::WidgetAdded
+    /// [`CommandTimer`]: crate::CommandTimer
+    Removed,
     /// Called when the "hot" status changes.
     ///
     /// This will always be called _before_ the event that triggered it; that is,
@@ -333,19 +411,85 @@ pub enum InternalLifeCycle {
     RouteDisabledChanged,
     /// The parents widget origin in window coordinate space has changed.
     ParentWindowOrigin,
+    /// Used to route `ViewportVisibilityChanged(false)` to widgets that were
+    /// visible, but were not reached by the last `ParentWindowOrigin`, because
+    /// they are no longer shown.
+    RouteViewportHidden {
+        /// the widgets that are no longer shown
+        widgets: Vec<WidgetId>,
+    },
     /// Testing only: request the `WidgetState` of a specific widget.
     ///
     /// During testing, you may wish to verify that the state of a widget
     /// somewhere in the tree is as expected. In that case you can dispatch
     /// this event, specifying the widget in question, and that widget will
     /// set its state in the provided `Cell`, if it exists.
-    #[cfg(test)]
+    #[doc(hidden)]
     DebugRequestState {
         widget: WidgetId,
         state_cell: StateCell,
     },
-    #[cfg(test)]
+    #[doc(hidden)]
     DebugInspectState(StateCheckFn),
+    /// Testing only: find the widgets whose label shows some text.
+    #[doc(hidden)]
+    DebugFindLabel(LabelQuery),
+}
+
+/// The kind of input that the user interacted with most recently.
+///
+/// This is tracked by druid and available as [`theme::INPUT_MODE`] in the
+/// [`Env`]. It is changed by pressing a mouse button, turning the mouse wheel,
+/// touching the screen, or pressing a key other than a modifier key. Moving
+/// the mouse doesn't change it, because platforms also report moves when the
+/// content under the mouse moves.
+///
+/// [`theme::INPUT_MODE`]: crate::theme::INPUT_MODE
+/// [`Env`]: crate::Env
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub enum InputMode {
+    /// A mouse, trackpad or pen. This is the initial mode.
+    Mouse,
+    /// A touchscreen.
+    Touch,
+    /// The keyboard.
+    Keyboard,
+}
+
+impl InputMode {
+    /// The input mode that an event puts the application in, if it changes it.
+    pub(crate) fn for_event(event: &Event) -> Option<InputMode> {
+        match event {
+            Event::MouseDown(mouse) | Event::Wheel(mouse) => match mouse.pointer_type {
+                PointerType::Touch => Some(InputMode::Touch),
+                PointerType::Mouse | PointerType::Pen => Some(InputMode::Mouse),
+            },
+            Event::KeyDown(key) if !is_modifier_key(&key.key) => Some(InputMode::Keyboard),
+            _ => None,
+        }
+    }
+}
+
+/// Returns `true` for keys that are only pressed together with another key,
+/// which on their own don't mean that the user switched to the keyboard.
+fn is_modifier_key(key: &KbKey) -> bool {
+    matches!(
+        key,
+        KbKey::Alt
+            | KbKey::AltGraph
+            | KbKey::CapsLock
+            | KbKey::Control
+            | KbKey::Fn
+            | KbKey::FnLock
+            | KbKey::Hyper
+            | KbKey::Meta
+            | KbKey::NumLock
+            | KbKey::ScrollLock
+            | KbKey::Shift
+            | KbKey::Super
+            | KbKey::Symbol
+            | KbKey::SymbolLock
+    )
 }
 
 impl Event {
@@ -392,6 +536,24 @@ impl Event {
                     None
                 }
             }
+            Event::DragOver(drop_event) => {
+                if viewport.winding(drop_event.pos) != 0 {
+                    let mut drop_event = drop_event.clone();
+                    drop_event.pos += offset;
+                    Some(Event::DragOver(drop_event))
+                } else {
+                    None
+                }
+            }
+            Event::Dropped(drop_event, clipboard) => {
+                if viewport.winding(drop_event.pos) != 0 {
+                    let mut drop_event = drop_event.clone();
+                    drop_event.pos += offset;
+                    Some(Event::Dropped(drop_event, clipboard.clone()))
+                } else {
+                    None
+                }
+            }
             _ => Some(self.clone()),
         }
     }
@@ -403,7 +565,10 @@ impl Event {
             Event::WindowConnected
             | Event::WindowCloseRequested
             | Event::WindowDisconnected
+            | Event::WindowSuspended
+            | Event::WindowResumed
             | Event::WindowSize(_)
+            | Event::DragLeave
             | Event::Timer(_)
             | Event::AnimFrame(_)
             | Event::Command(_)
@@ -416,6 +581,8 @@ impl Event {
             | Event::KeyDown(_)
             | Event::KeyUp(_)
             | Event::Paste(_)
+            | Event::DragOver(_)
+            | Event::Dropped(..)
             | Event::ImeStateChange
             | Event::Zoom(_) => false,
         }
@@ -428,7 +595,11 @@ impl LifeCycle {
     pub fn should_propagate_to_hidden(&self) -> bool {
         match self {
             LifeCycle::Internal(internal) => internal.should_propagate_to_hidden(),
-            LifeCycle::WidgetAdded | LifeCycle::DisabledChanged(_) => true,
+            LifeCycle::WidgetAdded
+            | LifeCycle::DisabledChanged(_)
+            | LifeCycle::FrozenChanged(_)
+            | LifeCycle::ViewportVisibilityChanged(_)
+            | LifeCycle::Removed => true,
             LifeCycle::Size(_)
             | LifeCycle::HotChanged(_)
             | LifeCycle::FocusChanged(_)
@@ -444,19 +615,18 @@ impl InternalLifeCycle {
         match self {
             InternalLifeCycle::RouteWidgetAdded
             | InternalLifeCycle::RouteFocusChanged { .. }
-            | InternalLifeCycle::RouteDisabledChanged => true,
-            InternalLifeCycle::ParentWindowOrigin => false,
-            #[cfg(test)]
+            | InternalLifeCycle::RouteDisabledChanged
+            | InternalLifeCycle::RouteViewportHidden { .. } => true,
+            // hidden widgets can't be interacted with, so they are not found.
+            InternalLifeCycle::ParentWindowOrigin | InternalLifeCycle::DebugFindLabel(_) => false,
             InternalLifeCycle::DebugRequestState { .. }
             | InternalLifeCycle::DebugInspectState(_) => true,
         }
     }
 }
 
-#[cfg(test)]
-pub(crate) use state_cell::{StateCell, StateCheckFn};
+pub(crate) use state_cell::{LabelQuery, StateCell, StateCheckFn};
 
-#[cfg(test)]
 mod state_cell {
     use crate::core::WidgetState;
     use crate::WidgetId;
@@ -469,6 +639,13 @@ mod state_cell {
     #[derive(Clone)]
     pub struct StateCheckFn(Rc<dyn Fn(&WidgetState)>);
 
+    /// The text to look for, and the widgets whose labels were found to show it.
+    #[derive(Clone)]
+    pub struct LabelQuery {
+        text: Rc<str>,
+        found: Rc<RefCell<Vec<WidgetId>>>,
+    }
+
     /// a hacky way of printing the widget id if we panic
     struct WidgetDrop(bool, WidgetId);
 
@@ -508,6 +685,29 @@ mod state_cell {
         }
     }
 
+    impl LabelQuery {
+        pub(crate) fn new(text: &str) -> Self {
+            LabelQuery {
+                text: text.into(),
+                found: Default::default(),
+            }
+        }
+
+        pub(crate) fn text(&self) -> &str {
+            &self.text
+        }
+
+        /// Record that the label of `widget` shows the text.
+        pub(crate) fn found(&self, widget: WidgetId) {
+            self.found.borrow_mut().push(widget);
+        }
+
+        #[allow(dead_code)]
+        pub(crate) fn take(&self) -> Vec<WidgetId> {
+            self.found.take()
+        }
+    }
+
     impl std::fmt::Debug for StateCell {
         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
             let inner = if self.0.borrow().is_some() {
@@ -524,4 +724,55 @@ mod state_cell {
             write!(f, "StateCheckFn")
         }
     }
+
+    impl std::fmt::Debug for LabelQuery {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "LabelQuery({:?})", self.text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Modifiers, MouseButton, MouseButtons, Point};
+    use test_env_log::test;
+
+    #[test]
+    fn input_mode_for_event() {
+        let mouse_down = |pointer_type| {
+            Event::MouseDown(MouseEvent {
+                pos: Point::ZERO,
+                window_pos: Point::ZERO,
+                buttons: MouseButtons::new().with(MouseButton::Left),
+                mods: Modifiers::empty(),
+                count: 1,
+                focus: false,
+                button: MouseButton::Left,
+                wheel_delta: Vec2::ZERO,
+                pointer_type,
+            })
+        };
+        let key_down = |key: KbKey| Event::KeyDown(KeyEvent::for_test(Modifiers::empty(), key));
+
+        assert_eq!(
+            InputMode::for_event(&mouse_down(PointerType::Mouse)),
+            Some(InputMode::Mouse)
+        );
+        assert_eq!(
+            InputMode::for_event(&mouse_down(PointerType::Pen)),
+            Some(InputMode::Mouse)
+        );
+        assert_eq!(
+            InputMode::for_event(&mouse_down(PointerType::Touch)),
+            Some(InputMode::Touch)
+        );
+        assert_eq!(
+            InputMode::for_event(&key_down(KbKey::Tab)),
+            Some(InputMode::Keyboard)
+        );
+        // a modifier key on its own, like for a ctrl-click, doesn't count.
+        assert_eq!(InputMode::for_event(&key_down(KbKey::Control)), None);
+        assert_eq!(InputMode::for_event(&Event::WindowConnected), None);
+    }
 }
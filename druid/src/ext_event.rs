@@ -16,7 +16,8 @@
 
 use std::any::Any;
 use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
 
 use crate::shell::IdleHandle;
 use crate::win_handler::EXT_EVENT_IDLE_TOKEN;
@@ -30,7 +31,7 @@ pub(crate) type ExtCommand = (SelectorSymbol, Box<dyn Any + Send>, Target);
 /// This API is preliminary, and may be changed or removed without warning.
 #[derive(Clone)]
 pub struct ExtEventSink {
-    queue: Arc<Mutex<VecDeque<ExtCommand>>>,
+    queue: Arc<ExtEventQueue>,
     handle: Arc<Mutex<Option<IdleHandle>>>,
 }
 
@@ -39,7 +40,7 @@ pub struct ExtEventSink {
 #[derive(Default)]
 pub(crate) struct ExtEventHost {
     /// A shared queue of items that have been sent to us.
-    queue: Arc<Mutex<VecDeque<ExtCommand>>>,
+    queue: Arc<ExtEventQueue>,
     /// This doesn't exist when the app starts and it can go away if a window closes, so we keep a
     /// reference here and can update it when needed. Note that this reference is shared with all
     /// `ExtEventSink`s, so that we can update them too.
@@ -49,14 +50,70 @@ pub(crate) struct ExtEventHost {
     pub(crate) handle_window_id: Option<WindowId>,
 }
 
+/// The queue shared by the host and its sinks.
+#[derive(Default)]
+struct ExtEventQueue {
+    state: Mutex<QueueState>,
+    /// Notified when a command is taken out of the queue, or the host goes away.
+    room: Condvar,
+}
+
+#[derive(Default)]
+struct QueueState {
+    commands: VecDeque<ExtCommand>,
+    /// The most commands that can wait in the queue, if there is a limit.
+    capacity: Option<usize>,
+    coalesce: Vec<(SelectorSymbol, Coalesce)>,
+    /// Set when the host is dropped, after which nothing is received.
+    closed: bool,
+    /// The thread the host was created on, which receives the commands. It
+    /// must never wait for room, because nobody would make any.
+    ui_thread: Option<ThreadId>,
+}
+
+/// What happens to an external command when another one with the same
+/// [`Selector`] and [`Target`] is still waiting to be delivered.
+///
+/// This is set per selector with [`AppLauncher::coalesce_ext_events`]. It
+/// is useful for commands that only report the latest state, such as progress,
+/// where a producer that is faster than the UI would otherwise queue up
+/// commands that are stale by the time they are delivered.
+///
+/// [`AppLauncher::coalesce_ext_events`]: crate::AppLauncher::coalesce_ext_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coalesce {
+    /// Both commands are delivered. This is the default.
+    Never,
+    /// The new command replaces the waiting one, and is delivered in its place.
+    KeepLatest,
+    /// The new command is dropped.
+    KeepFirst,
+}
+
 /// An error that occurs if an external event cannot be submitted.
 /// This probably means that the application has gone away.
 #[derive(Debug, Clone)]
 pub struct ExtEventError;
 
+/// An error that occurs if [`ExtEventSink::try_submit_command`] cannot
+/// submit a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySubmitError {
+    /// The queue is at its capacity, so the command was not submitted.
+    ///
+    /// The capacity is set with [`AppLauncher::ext_event_capacity`].
+    ///
+    /// [`AppLauncher::ext_event_capacity`]: crate::AppLauncher::ext_event_capacity
+    Full,
+    /// The application has gone away.
+    Disconnected,
+}
+
 impl ExtEventHost {
     pub(crate) fn new() -> Self {
-        Default::default()
+        let host = ExtEventHost::default();
+        host.queue.state.lock().unwrap().ui_thread = Some(thread::current().id());
+        host
     }
 
     pub(crate) fn make_sink(&self) -> ExtEventSink {
@@ -71,16 +128,105 @@ impl ExtEventHost {
         self.handle_window_id = Some(window_id);
     }
 
+    /// Limit the number of commands that can wait in the queue.
+    pub(crate) fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.queue.state.lock().unwrap().capacity = capacity;
+        // a larger capacity may make room.
+        self.queue.room.notify_all();
+    }
+
+    pub(crate) fn set_coalesce<T>(&mut self, selector: Selector<T>, coalesce: Coalesce) {
+        let symbol = selector.symbol();
+        let mut state = self.queue.state.lock().unwrap();
+        state.coalesce.retain(|(s, _)| *s != symbol);
+        if coalesce != Coalesce::Never {
+            state.coalesce.push((symbol, coalesce));
+        }
+    }
+
     pub(crate) fn has_pending_items(&self) -> bool {
-        !self.queue.lock().unwrap().is_empty()
+        !self.queue.state.lock().unwrap().commands.is_empty()
     }
 
     pub(crate) fn recv(&mut self) -> Option<Command> {
-        self.queue
-            .lock()
-            .unwrap()
-            .pop_front()
+        let command = self.queue.state.lock().unwrap().commands.pop_front();
+        self.queue.room.notify_all();
+        command.map(|(selector, payload, target)| Command::from_ext(selector, payload, target))
+    }
+
+    /// Take all of the commands that are waiting.
+    ///
+    /// Commands submitted after this are left for the next call, so that a
+    /// producer that keeps the queue full can't keep us here forever.
+    pub(crate) fn recv_pending(&mut self) -> Vec<Command> {
+        let commands = std::mem::take(&mut self.queue.state.lock().unwrap().commands);
+        self.queue.room.notify_all();
+        commands
+            .into_iter()
             .map(|(selector, payload, target)| Command::from_ext(selector, payload, target))
+            .collect()
+    }
+}
+
+impl Drop for ExtEventHost {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() {
+            state.closed = true;
+            state.commands.clear();
+        }
+        // wake up any sinks that are waiting for room.
+        self.queue.room.notify_all();
+    }
+}
+
+impl ExtEventQueue {
+    /// Add a command to the queue, waiting for room if `wait` is `true`.
+    fn push(&self, command: ExtCommand, wait: bool) -> Result<(), TrySubmitError> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| TrySubmitError::Disconnected)?;
+        loop {
+            if state.closed {
+                return Err(TrySubmitError::Disconnected);
+            }
+            let coalesce = state
+                .coalesce
+                .iter()
+                .find(|(symbol, _)| *symbol == command.0)
+                .map_or(Coalesce::Never, |(_, coalesce)| *coalesce);
+            if coalesce != Coalesce::Never {
+                let waiting = state
+                    .commands
+                    .iter_mut()
+                    .find(|(symbol, _, target)| *symbol == command.0 && *target == command.2);
+                if let Some(waiting) = waiting {
+                    if coalesce == Coalesce::KeepLatest {
+                        *waiting = command;
+                    }
+                    return Ok(());
+                }
+            }
+            if state
+                .capacity
+                .is_none_or(|capacity| state.commands.len() < capacity)
+            {
+                state.commands.push_back(command);
+                return Ok(());
+            }
+            if !wait {
+                return Err(TrySubmitError::Full);
+            }
+            debug_assert!(
+                state.ui_thread != Some(thread::current().id()),
+                "ExtEventSink::submit_command would wait for room in a full queue on the UI \
+                 thread, which never comes; use try_submit_command there"
+            );
+            state = self
+                .room
+                .wait(state)
+                .map_err(|_| TrySubmitError::Disconnected)?;
+        }
     }
 }
 
@@ -95,26 +241,56 @@ impl ExtEventSink {
     ///
     /// If the [`Target::Auto`] is equivalent to [`Target::Global`].
     ///
+    /// If the queue has a capacity (see [`AppLauncher::ext_event_capacity`]) and
+    /// is full, this blocks until the application has taken commands out of it.
+    /// This slows down a producer that is faster than the UI, but it must not be
+    /// called on the UI thread then; use [`try_submit_command`] there instead.
+    /// Waiting on the UI thread would never end, so in debug builds it panics.
+    ///
     /// [`Command`]: struct.Command.html
     /// [`Selector`]: struct.Selector.html
     /// [`Target::Auto`]: enum.Target.html#variant.Auto
     /// [`Target::Global`]: enum.Target.html#variant.Global
+    /// [`AppLauncher::ext_event_capacity`]: crate::AppLauncher::ext_event_capacity
+    /// [`try_submit_command`]: ExtEventSink::try_submit_command
     pub fn submit_command<T: Any + Send>(
         &self,
         selector: Selector<T>,
         payload: impl Into<Box<T>>,
         target: impl Into<Target>,
     ) -> Result<(), ExtEventError> {
-        let target = target.into();
-        let payload = payload.into();
+        self.submit(selector, payload.into(), target.into(), true)
+            .map_err(|_| ExtEventError)
+    }
+
+    /// Submit a [`Command`] to the running application, unless the queue is full.
+    ///
+    /// This is like [`submit_command`], except that it returns
+    /// [`TrySubmitError::Full`] instead of waiting for room in the queue.
+    ///
+    /// [`Command`]: struct.Command.html
+    /// [`submit_command`]: ExtEventSink::submit_command
+    pub fn try_submit_command<T: Any + Send>(
+        &self,
+        selector: Selector<T>,
+        payload: impl Into<Box<T>>,
+        target: impl Into<Target>,
+    ) -> Result<(), TrySubmitError> {
+        self.submit(selector, payload.into(), target.into(), false)
+    }
+
+    fn submit<T: Any + Send>(
+        &self,
+        selector: Selector<T>,
+        payload: Box<T>,
+        target: Target,
+        wait: bool,
+    ) -> Result<(), TrySubmitError> {
+        self.queue
+            .push((selector.symbol(), payload, target), wait)?;
         if let Some(handle) = self.handle.lock().unwrap().as_mut() {
             handle.schedule_idle(EXT_EVENT_IDLE_TOKEN);
         }
-        self.queue.lock().map_err(|_| ExtEventError)?.push_back((
-            selector.symbol(),
-            payload,
-            target,
-        ));
         Ok(())
     }
 }
@@ -126,3 +302,101 @@ impl std::fmt::Display for ExtEventError {
 }
 
 impl std::error::Error for ExtEventError {}
+
+impl std::fmt::Display for TrySubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TrySubmitError::Full => write!(f, "External event queue is full"),
+            TrySubmitError::Disconnected => write!(f, "Window missing for external event"),
+        }
+    }
+}
+
+impl std::error::Error for TrySubmitError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    const PROGRESS: Selector<u32> = Selector::new("druid-test.progress");
+    const LOG: Selector<u32> = Selector::new("druid-test.log");
+
+    fn payloads(host: &mut ExtEventHost) -> Vec<u32> {
+        host.recv_pending()
+            .iter()
+            .map(|cmd| {
+                *cmd.get(PROGRESS)
+                    .or_else(|| cmd.get(LOG))
+                    .expect("unexpected command")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bounded_queue() {
+        let mut host = ExtEventHost::new();
+        host.set_capacity(Some(2));
+        let sink = host.make_sink();
+        assert!(sink.try_submit_command(LOG, 1, Target::Auto).is_ok());
+        assert!(sink.try_submit_command(LOG, 2, Target::Auto).is_ok());
+        assert_eq!(
+            sink.try_submit_command(LOG, 3, Target::Auto),
+            Err(TrySubmitError::Full)
+        );
+        assert_eq!(payloads(&mut host), vec![1, 2]);
+        assert!(sink.try_submit_command(LOG, 4, Target::Auto).is_ok());
+        assert_eq!(payloads(&mut host), vec![4]);
+
+        // a blocked producer continues once the commands have been received.
+        sink.submit_command(LOG, 5, Target::Auto).unwrap();
+        sink.submit_command(LOG, 6, Target::Auto).unwrap();
+        let producer = {
+            let sink = sink.clone();
+            std::thread::spawn(move || sink.submit_command(LOG, 7, Target::Auto).is_ok())
+        };
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            received.extend(payloads(&mut host));
+        }
+        assert!(producer.join().unwrap());
+        assert_eq!(received, vec![5, 6, 7]);
+
+        drop(host);
+        assert_eq!(
+            sink.try_submit_command(LOG, 8, Target::Auto),
+            Err(TrySubmitError::Disconnected)
+        );
+        assert!(sink.submit_command(LOG, 8, Target::Auto).is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "use try_submit_command there")]
+    fn full_queue_on_ui_thread_panics() {
+        let mut host = ExtEventHost::new();
+        host.set_capacity(Some(1));
+        let sink = host.make_sink();
+        sink.submit_command(LOG, 1, Target::Auto).unwrap();
+        // this thread created the host, so nobody would ever make room.
+        let _ = sink.submit_command(LOG, 2, Target::Auto);
+    }
+
+    #[test]
+    fn coalesce_commands() {
+        let mut host = ExtEventHost::new();
+        host.set_coalesce(PROGRESS, Coalesce::KeepLatest);
+        let sink = host.make_sink();
+        sink.submit_command(PROGRESS, 1, Target::Auto).unwrap();
+        sink.submit_command(LOG, 10, Target::Auto).unwrap();
+        sink.submit_command(PROGRESS, 2, Target::Auto).unwrap();
+        sink.submit_command(LOG, 11, Target::Auto).unwrap();
+        sink.submit_command(PROGRESS, 3, Target::Auto).unwrap();
+        assert_eq!(payloads(&mut host), vec![3, 10, 11]);
+
+        host.set_coalesce(PROGRESS, Coalesce::KeepFirst);
+        sink.submit_command(PROGRESS, 4, Target::Auto).unwrap();
+        sink.submit_command(PROGRESS, 5, Target::Auto).unwrap();
+        assert_eq!(payloads(&mut host), vec![4]);
+    }
+}
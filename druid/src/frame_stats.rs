@@ -0,0 +1,220 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timing of the passes that make up a frame, for diagnosing jank.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::piet::{FontFamily, Piet, Text, TextLayout, TextLayoutBuilder};
+use crate::util::short_type_name;
+use crate::{Color, Rect, RenderContext, Size, WidgetId};
+
+/// The area of the window covered by the overlay.
+pub(crate) const OVERLAY_RECT: Rect = Rect::new(4.0, 4.0, 364.0, 64.0);
+
+const OVERLAY_FONT_SIZE: f64 = 12.0;
+const OVERLAY_PADDING: f64 = 6.0;
+
+/// One of the passes that druid runs over the widget tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pass {
+    Event,
+    Update,
+    Layout,
+    Paint,
+}
+
+/// The total time spent in each pass during one frame.
+#[derive(Debug, Default, Clone, Copy)]
+struct PassTimes {
+    event: Duration,
+    update: Duration,
+    layout: Duration,
+    paint: Duration,
+}
+
+/// The widget that spent the most time in its own `layout` or `paint`
+/// during a frame, not counting time spent in its children.
+#[derive(Debug, Clone, Copy)]
+struct SlowWidget {
+    name: &'static str,
+    id: WidgetId,
+    time: Duration,
+}
+
+/// Per-window timing information.
+///
+/// Timing is only done while the stats are enabled, which is toggled with
+/// the [`TOGGLE_FRAME_STATS`] command. While enabled, the stats of the last
+/// frame are painted over the top left corner of the window.
+///
+/// [`TOGGLE_FRAME_STATS`]: crate::commands::TOGGLE_FRAME_STATS
+#[derive(Debug, Default)]
+pub(crate) struct FrameStats {
+    enabled: bool,
+    current: PassTimes,
+    last: PassTimes,
+    slowest: Option<SlowWidget>,
+    last_slowest: Option<SlowWidget>,
+    /// The time spent in the children of each widget that is currently being
+    /// timed, innermost last.
+    widget_stack: Vec<Duration>,
+    /// The times at which frames were finished during the last second.
+    frames: VecDeque<Instant>,
+}
+
+impl FrameStats {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        *self = FrameStats {
+            enabled: !self.enabled,
+            ..Default::default()
+        };
+    }
+
+    /// Add the time spent in one run of a pass to the current frame.
+    pub(crate) fn record_pass(&mut self, pass: Pass, time: Duration) {
+        let times = &mut self.current;
+        match pass {
+            Pass::Event => times.event += time,
+            Pass::Update => times.update += time,
+            Pass::Layout => times.layout += time,
+            Pass::Paint => times.paint += time,
+        }
+    }
+
+    /// Start timing a single widget's `layout` or `paint`.
+    pub(crate) fn begin_widget(&mut self) -> Instant {
+        self.widget_stack.push(Duration::ZERO);
+        Instant::now()
+    }
+
+    /// Finish timing a widget that was started with [`begin_widget`].
+    ///
+    /// [`begin_widget`]: FrameStats::begin_widget
+    pub(crate) fn end_widget(&mut self, start: Instant, name: &'static str, id: WidgetId) {
+        let total = start.elapsed();
+        let in_children = self.widget_stack.pop().unwrap_or_default();
+        if let Some(parent) = self.widget_stack.last_mut() {
+            *parent += total;
+        }
+
+        let own = total.checked_sub(in_children).unwrap_or_default();
+        if self.slowest.map(|slow| own > slow.time).unwrap_or(true) {
+            self.slowest = Some(SlowWidget {
+                name,
+                id,
+                time: own,
+            });
+        }
+    }
+
+    /// Called when a frame has been painted.
+    pub(crate) fn finish_frame(&mut self) {
+        let now = Instant::now();
+        self.frames.push_back(now);
+        while let Some(first) = self.frames.front() {
+            if now.duration_since(*first) > Duration::from_secs(1) {
+                self.frames.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        self.last = std::mem::take(&mut self.current);
+        self.last_slowest = self.slowest.take();
+        self.widget_stack.clear();
+    }
+
+    /// Draw the stats of the last frame.
+    pub(crate) fn paint_overlay(&self, piet: &mut Piet) {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let last = &self.last;
+        let mut text = format!(
+            "{} fps\nevent {:.2}ms  update {:.2}ms  layout {:.2}ms  paint {:.2}ms",
+            self.frames.len(),
+            ms(last.event),
+            ms(last.update),
+            ms(last.layout),
+            ms(last.paint),
+        );
+        if let Some(slow) = &self.last_slowest {
+            text.push_str(&format!(
+                "\nslowest: {} ({}) {:.2}ms",
+                short_type_name(slow.name),
+                slow.id.to_raw(),
+                ms(slow.time)
+            ));
+        }
+
+        let layout = match piet
+            .text()
+            .new_text_layout(text)
+            .font(FontFamily::MONOSPACE, OVERLAY_FONT_SIZE)
+            .text_color(Color::WHITE)
+            .build()
+        {
+            Ok(layout) => layout,
+            Err(e) => {
+                tracing::warn!("failed to build frame stats text: {}", e);
+                return;
+            }
+        };
+
+        let origin = OVERLAY_RECT.origin();
+        let size = layout.size() + Size::new(OVERLAY_PADDING * 2.0, OVERLAY_PADDING * 2.0);
+        let background = Rect::from_origin_size(origin, size).intersect(OVERLAY_RECT);
+        piet.fill(background, &Color::rgba8(0, 0, 0, 0xC0));
+        piet.draw_text(&layout, origin + (OVERLAY_PADDING, OVERLAY_PADDING));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exclusive_widget_time() {
+        let mut stats = FrameStats::default();
+        let parent_id = WidgetId::next();
+        let child_id = WidgetId::next();
+
+        let parent = stats.begin_widget();
+        let child = stats.begin_widget();
+        std::thread::sleep(Duration::from_millis(20));
+        stats.end_widget(child, "child", child_id);
+        stats.end_widget(parent, "parent", parent_id);
+
+        // the parent took longer in total, but almost all of that was spent
+        // in the child.
+        let slowest = stats.slowest.unwrap();
+        assert_eq!(slowest.id, child_id);
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(short_type_name("druid::widget::Label<u32>"), "Label<u32>");
+        assert_eq!(
+            short_type_name("druid::widget::Flex<alloc::string::String>"),
+            "Flex<alloc::string::String>"
+        );
+        assert_eq!(short_type_name("Label"), "Label");
+    }
+}
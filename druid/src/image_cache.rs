@@ -0,0 +1,784 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An application-level cache of decoded images.
+//!
+//! Decoding an image is expensive, and widgets that display images (such as
+//! the rows of a [`List`] of thumbnails) are frequently rebuilt. An
+//! [`ImageCache`] decodes each [`ImageSource`] once, on a small pool of
+//! background threads, and keeps the result around until it is evicted.
+//!
+//! Images can also be loaded from URLs, by providing a fetcher with
+//! [`ImageCache::with_fetcher`]; druid does not include a network client.
+//!
+//! The cache also keeps the platform textures created from each image, one
+//! per window, so that drawing a cached image does not need to upload it to
+//! the render context again. The textures for a window are dropped when
+//! [`ImageCache::remove_window`] is called, which should be done when the
+//! window closes.
+//!
+//! When an image finishes loading, an [`IMAGE_LOADED`] command is sent to
+//! every window, carrying the source of the image. Widgets that are waiting
+//! on an image should handle this command and request a layout or a paint.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use druid::image_cache::{ImageCache, ImageSource, IMAGE_LOADED};
+//! use druid::piet::InterpolationMode;
+//! use druid::widget::{Controller, Painter};
+//! use druid::{AppLauncher, Env, Event, EventCtx, Widget, WidgetExt, WindowDesc};
+//!
+//! struct RepaintOnLoad(ImageCache);
+//!
+//! impl<W: Widget<()>> Controller<(), W> for RepaintOnLoad {
+//!     fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut (), env: &Env) {
+//!         if let Event::Command(cmd) = event {
+//!             if cmd.is(IMAGE_LOADED) {
+//!                 ctx.request_paint();
+//!             }
+//!         }
+//!         if let Event::WindowDisconnected = event {
+//!             self.0.remove_window(ctx.window_id());
+//!         }
+//!         child.event(ctx, event, data, env)
+//!     }
+//! }
+//!
+//! let launcher = AppLauncher::with_window(WindowDesc::new(Painter::new(|_, _: &(), _| {})));
+//! let cache = ImageCache::new(launcher.get_external_handle());
+//!
+//! let source = ImageSource::file("thumbnail.png");
+//! let controller = RepaintOnLoad(cache.clone());
+//! let thumbnail = Painter::new(move |ctx, _: &(), _| {
+//!     let rect = ctx.size().to_rect();
+//!     cache.draw(ctx, &source, rect, InterpolationMode::Bilinear);
+//! })
+//! .controller(controller);
+//! # let _ = thumbnail;
+//! ```
+//!
+//! [`List`]: crate::widget::List
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::rc::Rc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use crate::piet::{ImageBuf, ImageFormat, InterpolationMode, PietImage};
+use crate::{Data, ExtEventSink, PaintCtx, Rect, RenderContext, Selector, Target, WindowId};
+
+/// Sent to every window when an image has finished loading, successfully or
+/// not.
+///
+/// The payload is the source of the image.
+pub const IMAGE_LOADED: Selector<ImageSource> = Selector::new("druid-builtin.image-loaded");
+
+/// The default capacity of an [`ImageCache`], in bytes.
+pub const DEFAULT_CAPACITY: usize = 64 * 1024 * 1024;
+
+/// The default number of threads an [`ImageCache`] decodes images on.
+pub const DEFAULT_THREADS: usize = 4;
+
+/// Where an image comes from.
+///
+/// Images are cached by source: two sources refer to the same cached image if
+/// they name the same file or URL, or if they contain the same bytes.
+#[derive(Clone)]
+pub struct ImageSource(SourceInner);
+
+#[derive(Clone)]
+enum SourceInner {
+    File(PathBuf),
+    Url(Arc<str>),
+    Bytes(Bytes),
+}
+
+/// The key we use to look up a source in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ImageKey {
+    File(PathBuf),
+    Url(Arc<str>),
+    Bytes(Bytes),
+}
+
+/// Encoded image data, compared by its contents.
+///
+/// The hash is computed once, so that looking up a large image does not
+/// hash all of its bytes again.
+#[derive(Clone)]
+struct Bytes {
+    data: Arc<[u8]>,
+    hash: u64,
+}
+
+/// A function that fetches the encoded bytes of an image from a URL.
+///
+/// This is called on a background thread.
+pub type Fetcher = dyn Fn(&str) -> Result<Vec<u8>, String> + Send + Sync;
+
+/// The state of an image in an [`ImageCache`].
+#[derive(Clone)]
+pub enum ImageState {
+    /// The image is being decoded.
+    Loading,
+    /// The image was decoded successfully.
+    Ready(ImageBuf),
+    /// The image could not be loaded or decoded.
+    Failed(Arc<str>),
+}
+
+/// A cache of decoded images, with least-recently-used eviction.
+///
+/// This is a cheap handle to shared state; clones refer to the same cache.
+/// See the [module documentation] for more details.
+///
+/// [module documentation]: crate::image_cache
+#[derive(Clone)]
+pub struct ImageCache {
+    inner: Rc<RefCell<CacheInner>>,
+}
+
+struct CacheInner {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    sink: ExtEventSink,
+    fetcher: Option<Arc<Fetcher>>,
+    capacity: usize,
+    threads: usize,
+    /// Started when the first image is loaded.
+    pool: Option<Pool>,
+    entries: HashMap<ImageKey, Entry>,
+    /// Incremented on every access, and used to find the least-recently-used entry.
+    clock: u64,
+    /// Images that have been decoded on a background thread, but not yet moved
+    /// into `entries`.
+    finished: Finished,
+}
+
+type Finished = Arc<Mutex<Vec<(ImageKey, Result<ImageBuf, String>)>>>;
+
+struct Entry {
+    state: ImageState,
+    /// Textures created from this image, one for each window it was drawn in.
+    textures: Vec<(WindowId, PietImage)>,
+    last_used: u64,
+}
+
+impl ImageSource {
+    /// An image that will be loaded from a file.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        ImageSource(SourceInner::File(path.into()))
+    }
+
+    /// An image that will be fetched from a URL.
+    ///
+    /// The cache must have a fetcher; see [`ImageCache::with_fetcher`].
+    pub fn url(url: impl Into<Arc<str>>) -> Self {
+        ImageSource(SourceInner::Url(url.into()))
+    }
+
+    /// An image that will be decoded from encoded bytes, such as the contents
+    /// of a PNG file.
+    pub fn bytes(data: impl Into<Arc<[u8]>>) -> Self {
+        let data = data.into();
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let hash = hasher.finish();
+        ImageSource(SourceInner::Bytes(Bytes { data, hash }))
+    }
+
+    fn key(&self) -> ImageKey {
+        match &self.0 {
+            SourceInner::File(path) => ImageKey::File(path.clone()),
+            SourceInner::Url(url) => ImageKey::Url(url.clone()),
+            SourceInner::Bytes(bytes) => ImageKey::Bytes(bytes.clone()),
+        }
+    }
+
+    fn decode(&self, fetcher: Option<&Fetcher>) -> Result<ImageBuf, String> {
+        match &self.0 {
+            SourceInner::File(path) => {
+                to_image_buf(crate::image::open(path).map_err(|e| e.to_string())?)
+            }
+            SourceInner::Url(url) => {
+                let fetcher = fetcher.ok_or("no fetcher is set for loading images from URLs")?;
+                let data = fetcher(url)?;
+                decode_data(&data)
+            }
+            SourceInner::Bytes(bytes) => decode_data(&bytes.data),
+        }
+    }
+}
+
+// piet can only decode images when one of the image format features is
+// enabled, so this goes through the `image` crate directly; without those
+// features, decoding fails with an error instead.
+fn decode_data(data: &[u8]) -> Result<ImageBuf, String> {
+    to_image_buf(crate::image::load_from_memory(data).map_err(|e| e.to_string())?)
+}
+
+fn to_image_buf(image: crate::image::DynamicImage) -> Result<ImageBuf, String> {
+    let image = image.into_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(ImageBuf::from_raw(
+        image.into_raw(),
+        ImageFormat::RgbaSeparate,
+        width as usize,
+        height as usize,
+    ))
+}
+
+impl fmt::Debug for ImageSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            SourceInner::File(path) => f.debug_tuple("File").field(path).finish(),
+            SourceInner::Url(url) => f.debug_tuple("Url").field(url).finish(),
+            SourceInner::Bytes(bytes) => bytes.fmt(f),
+        }
+    }
+}
+
+impl PartialEq for Bytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && (Arc::ptr_eq(&self.data, &other.data) || self.data == other.data)
+    }
+}
+
+impl Eq for Bytes {}
+
+impl Hash for Bytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl fmt::Debug for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bytes")
+            .field("len", &self.data.len())
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+impl Data for ImageSource {
+    fn same(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl ImageState {
+    /// Returns the decoded image, if it is ready.
+    pub fn image(&self) -> Option<&ImageBuf> {
+        match self {
+            ImageState::Ready(image) => Some(image),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for ImageState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageState::Loading => write!(f, "Loading"),
+            ImageState::Ready(image) => write!(f, "Ready({}x{})", image.width(), image.height()),
+            ImageState::Failed(err) => f.debug_tuple("Failed").field(err).finish(),
+        }
+    }
+}
+
+impl ImageCache {
+    /// Create a new, empty cache.
+    ///
+    /// The [`ExtEventSink`] is used to send [`IMAGE_LOADED`] when an image
+    /// finishes decoding; it can be obtained from
+    /// [`AppLauncher::get_external_handle`] or from any of the contexts.
+    ///
+    /// [`AppLauncher::get_external_handle`]: crate::AppLauncher::get_external_handle
+    pub fn new(sink: ExtEventSink) -> Self {
+        ImageCache {
+            inner: Rc::new(RefCell::new(CacheInner {
+                sink,
+                fetcher: None,
+                capacity: DEFAULT_CAPACITY,
+                threads: DEFAULT_THREADS,
+                pool: None,
+                entries: HashMap::new(),
+                clock: 0,
+                finished: Arc::new(Mutex::new(Vec::new())),
+            })),
+        }
+    }
+
+    /// Builder-style method to set the function used to fetch images from URLs.
+    ///
+    /// The fetcher is called on a background thread with the URL, and should
+    /// return the encoded bytes of the image.
+    pub fn with_fetcher(
+        self,
+        fetcher: impl Fn(&str) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.inner.borrow_mut().fetcher = Some(Arc::new(fetcher));
+        self
+    }
+
+    /// Builder-style method to set the capacity of the cache, in bytes.
+    ///
+    /// When the decoded images (and their textures) take up more than this
+    /// much memory, the least-recently-used ones are evicted. The default is
+    /// [`DEFAULT_CAPACITY`].
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        self.set_capacity(capacity);
+        self
+    }
+
+    /// Builder-style method to set the number of threads images are decoded
+    /// and fetched on.
+    ///
+    /// At most this many images are loaded at the same time; the others wait
+    /// in a queue. The threads are started when the first image is loaded,
+    /// so this has no effect after that. The default is [`DEFAULT_THREADS`].
+    pub fn with_threads(self, threads: usize) -> Self {
+        self.inner.borrow_mut().threads = threads.max(1);
+        self
+    }
+
+    /// Set the capacity of the cache, in bytes.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.borrow_mut();
+        inner.capacity = capacity;
+        inner.evict(None);
+    }
+
+    /// Returns the state of the image for this source, and starts loading
+    /// it if it is not in the cache.
+    pub fn get(&self, source: &ImageSource) -> ImageState {
+        let key = source.key();
+        let mut inner = self.inner.borrow_mut();
+        inner.collect_finished();
+        if let Some(state) = inner.touch(&key) {
+            return state;
+        }
+
+        inner.insert(key.clone(), ImageState::Loading);
+        let job = Job {
+            source: source.clone(),
+            key,
+            fetcher: inner.fetcher.clone(),
+            finished: inner.finished.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            sink: inner.sink.clone(),
+        };
+        let pool = inner.pool();
+        // the inner state must not be borrowed while decoding, because on
+        // wasm decoding happens synchronously.
+        drop(inner);
+        pool.run(job);
+
+        let mut inner = self.inner.borrow_mut();
+        inner.collect_finished();
+        inner.touch(&source.key()).unwrap_or(ImageState::Loading)
+    }
+
+    /// Add an already decoded image to the cache.
+    pub fn insert(&self, source: &ImageSource, image: ImageBuf) {
+        let mut inner = self.inner.borrow_mut();
+        inner.insert(source.key(), ImageState::Ready(image));
+    }
+
+    /// Remove the image for this source from the cache.
+    ///
+    /// If the image is still loading, the result is discarded when it arrives.
+    pub fn remove(&self, source: &ImageSource) {
+        self.inner.borrow_mut().entries.remove(&source.key());
+    }
+
+    /// Drop the textures that were created for drawing images in `window`.
+    ///
+    /// This should be called when the window closes, for instance on
+    /// [`Event::WindowDisconnected`]; [`AsyncImage`] does this for its cache.
+    ///
+    /// [`Event::WindowDisconnected`]: crate::Event::WindowDisconnected
+    /// [`AsyncImage`]: crate::widget::AsyncImage
+    pub fn remove_window(&self, window: WindowId) {
+        for entry in self.inner.borrow_mut().entries.values_mut() {
+            entry.textures.retain(|(id, _)| *id != window);
+        }
+    }
+
+    /// Remove every image from the cache.
+    pub fn clear(&self) {
+        self.inner.borrow_mut().entries.clear();
+    }
+
+    /// Returns `true` if there is an entry for this source, in any state.
+    pub fn contains(&self, source: &ImageSource) -> bool {
+        self.inner.borrow().entries.contains_key(&source.key())
+    }
+
+    /// The number of entries in the cache, including images that are still
+    /// loading or that failed to load.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().entries.is_empty()
+    }
+
+    /// Draw the image for this source into `rect`, if it is ready.
+    ///
+    /// If the image is not in the cache it starts loading; you should handle
+    /// [`IMAGE_LOADED`] and request a paint when it arrives.
+    ///
+    /// Returns `true` if the image was drawn.
+    pub fn draw(
+        &self,
+        ctx: &mut PaintCtx,
+        source: &ImageSource,
+        rect: Rect,
+        interpolation: InterpolationMode,
+    ) -> bool {
+        if self.get(source).image().is_none() {
+            return false;
+        }
+
+        let window_id = ctx.window_id();
+        let mut inner = self.inner.borrow_mut();
+        let entry = match inner.entries.get_mut(&source.key()) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let texture = match entry.textures.iter().position(|(id, _)| *id == window_id) {
+            Some(idx) => &entry.textures[idx].1,
+            None => {
+                let texture = match &entry.state {
+                    ImageState::Ready(image) => image.to_image(ctx.render_ctx),
+                    _ => return false,
+                };
+                entry.textures.push((window_id, texture));
+                &entry.textures.last().unwrap().1
+            }
+        };
+        ctx.draw_image(texture, rect, interpolation);
+        inner.evict(Some(&source.key()));
+        true
+    }
+}
+
+impl CacheInner {
+    /// The pool that loads images, started if this is the first image.
+    fn pool(&mut self) -> Pool {
+        let threads = self.threads;
+        self.pool.get_or_insert_with(|| Pool::new(threads)).clone()
+    }
+
+    /// Move images that were decoded in the background into the cache.
+    fn collect_finished(&mut self) {
+        let finished = std::mem::take(&mut *self.finished.lock().unwrap());
+        for (key, result) in finished {
+            // if the entry is gone, it was removed while loading.
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.state = match result {
+                    Ok(image) => ImageState::Ready(image),
+                    Err(err) => {
+                        tracing::warn!("failed to load image {:?}: {}", key, err);
+                        ImageState::Failed(err.into())
+                    }
+                };
+                entry.textures.clear();
+            }
+        }
+        self.evict(None);
+    }
+
+    /// Mark an entry as used, and return its state.
+    fn touch(&mut self, key: &ImageKey) -> Option<ImageState> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|entry| {
+            entry.last_used = clock;
+            entry.state.clone()
+        })
+    }
+
+    fn insert(&mut self, key: ImageKey, state: ImageState) {
+        self.clock += 1;
+        let entry = Entry {
+            state,
+            textures: Vec::new(),
+            last_used: self.clock,
+        };
+        self.entries.insert(key.clone(), entry);
+        self.evict(Some(&key));
+    }
+
+    /// Evict least-recently-used entries until we are within our capacity.
+    ///
+    /// The entry for `keep` is never evicted, so that a single image larger
+    /// than the whole cache can still be used.
+    fn evict(&mut self, keep: Option<&ImageKey>) {
+        let mut size: usize = self.entries.values().map(Entry::size).sum();
+        while size > self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .filter(|(key, entry)| Some(*key) != keep && entry.size() > 0)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => {
+                    if let Some(entry) = self.entries.remove(&key) {
+                        size -= entry.size();
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl Entry {
+    /// The approximate memory used by this entry, in bytes.
+    fn size(&self) -> usize {
+        match &self.state {
+            ImageState::Ready(image) => image.raw_pixels().len() * (1 + self.textures.len()),
+            _ => 0,
+        }
+    }
+}
+
+/// An image waiting to be loaded.
+struct Job {
+    source: ImageSource,
+    key: ImageKey,
+    fetcher: Option<Arc<Fetcher>>,
+    finished: Finished,
+    #[cfg(not(target_arch = "wasm32"))]
+    sink: ExtEventSink,
+}
+
+impl Job {
+    fn run(self) {
+        let result = self.source.decode(self.fetcher.as_deref());
+        self.finished.lock().unwrap().push((self.key, result));
+        // on wasm the image is loaded before `get` returns, so there is
+        // nobody to tell.
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self
+            .sink
+            .submit_command(IMAGE_LOADED, self.source, Target::Global)
+        {
+            tracing::warn!("failed to send IMAGE_LOADED: {}", e);
+        }
+    }
+}
+
+/// The threads that load images, taking jobs from a shared queue.
+///
+/// The threads exit when the last cache handle is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+struct Pool {
+    jobs: mpsc::Sender<Job>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Pool {
+    fn new(threads: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..threads {
+            let queue = queue.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("druid-image-{}", i))
+                .spawn(move || loop {
+                    // the lock is released before the job runs.
+                    let job = queue.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job.run(),
+                        Err(_) => break,
+                    }
+                });
+            if let Err(e) = spawned {
+                tracing::warn!("failed to start an image loading thread: {}", e);
+            }
+        }
+        Pool { jobs }
+    }
+
+    fn run(&self, job: Job) {
+        // if no thread could be started, load the image right here.
+        if let Err(mpsc::SendError(job)) = self.jobs.send(job) {
+            job.run();
+        }
+    }
+}
+
+/// Images are loaded immediately; there are no threads on wasm.
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone)]
+struct Pool;
+
+#[cfg(target_arch = "wasm32")]
+impl Pool {
+    fn new(_threads: usize) -> Self {
+        Pool
+    }
+
+    fn run(&self, job: Job) {
+        job.run();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ext_event::ExtEventHost;
+    use crate::piet::ImageFormat;
+    use crate::testing::Harness;
+    use crate::widget::AsyncImage;
+    use crate::Event;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    fn make_cache() -> ImageCache {
+        ImageCache::new(ExtEventHost::default().make_sink())
+    }
+
+    fn make_image() -> ImageBuf {
+        ImageBuf::from_raw(vec![0u8; 10 * 10 * 4], ImageFormat::RgbaSeparate, 10, 10)
+    }
+
+    #[test]
+    fn lru_eviction() {
+        let cache = make_cache().with_capacity(2 * 400);
+        let one = ImageSource::bytes(vec![1u8]);
+        let two = ImageSource::bytes(vec![2u8]);
+        let three = ImageSource::bytes(vec![3u8]);
+
+        cache.insert(&one, make_image());
+        cache.insert(&two, make_image());
+        // `one` is now more recently used than `two`.
+        assert!(cache.get(&one).image().is_some());
+
+        cache.insert(&three, make_image());
+        assert!(cache.contains(&one));
+        assert!(!cache.contains(&two));
+        assert!(cache.contains(&three));
+    }
+
+    #[test]
+    fn sources_are_keyed_by_contents() {
+        let one = ImageSource::bytes(vec![1u8, 2, 3]);
+        let same = ImageSource::bytes(vec![1u8, 2, 3]);
+        let other = ImageSource::bytes(vec![3u8, 2, 1]);
+        assert!(one.same(&same));
+        assert!(!one.same(&other));
+        assert!(ImageSource::file("a.png").same(&ImageSource::file("a.png")));
+
+        // a hash collision doesn't make two images the same.
+        let one = Bytes {
+            data: vec![1u8, 2, 3].into(),
+            hash: 7,
+        };
+        let other = Bytes {
+            data: vec![3u8, 2, 1].into(),
+            hash: 7,
+        };
+        assert_ne!(ImageKey::Bytes(one), ImageKey::Bytes(other));
+    }
+
+    #[test]
+    fn loads_are_limited_to_the_pool() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (running2, most2) = (running.clone(), most.clone());
+        let cache = make_cache().with_threads(2).with_fetcher(move |_url| {
+            let now = running2.fetch_add(1, Ordering::SeqCst) + 1;
+            most2.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            running2.fetch_sub(1, Ordering::SeqCst);
+            Err("not an image".into())
+        });
+
+        let sources: Vec<_> = (0..6)
+            .map(|i| ImageSource::url(format!("https://example.com/{}.png", i)))
+            .collect();
+        let start = Instant::now();
+        loop {
+            let loading = sources
+                .iter()
+                .filter(|source| matches!(cache.get(source), ImageState::Loading))
+                .count();
+            if loading == 0 {
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "load timed out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(most.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn textures_are_dropped_with_their_window() {
+        let cache = make_cache();
+        let source = ImageSource::bytes(vec![1u8]);
+        cache.insert(&source, make_image());
+        let textures = |cache: &ImageCache| {
+            let inner = cache.inner.borrow();
+            inner
+                .entries
+                .values()
+                .map(|e| e.textures.len())
+                .sum::<usize>()
+        };
+
+        let widget = AsyncImage::new().with_cache(cache.clone());
+        Harness::create_simple(source, widget, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            harness.paint();
+            assert_eq!(textures(&cache), 1);
+
+            harness.event(Event::WindowDisconnected);
+            assert_eq!(textures(&cache), 0);
+        });
+    }
+
+    #[test]
+    fn failed_load() {
+        let cache = make_cache();
+        let source = ImageSource::file("this/file/does/not/exist.png");
+        let start = Instant::now();
+        loop {
+            match cache.get(&source) {
+                ImageState::Failed(_) => break,
+                ImageState::Ready(_) => panic!("image should not load"),
+                ImageState::Loading => {
+                    assert!(start.elapsed() < Duration::from_secs(10), "load timed out");
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            }
+        }
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An overlay for inspecting the widget tree of a window.
+
+use crate::piet::{FontFamily, Piet, Text, TextLayoutBuilder};
+use crate::util::short_type_name;
+use crate::{BoxConstraints, Color, Point, Rect, RenderContext, Size, WidgetId};
+
+/// The width of the panel that lists the widget tree.
+const PANEL_WIDTH: f64 = 420.0;
+const FONT_SIZE: f64 = 11.0;
+/// An estimate of the height of a line, used to decide how many lines fit.
+const LINE_HEIGHT: f64 = FONT_SIZE * 1.4;
+const PADDING: f64 = 6.0;
+
+const PANEL_COLOR: Color = Color::rgba8(0x20, 0x20, 0x20, 0xE0);
+const TEXT_COLOR: Color = Color::rgb8(0xE0, 0xE0, 0xE0);
+const HIGHLIGHT_COLOR: Color = Color::rgba8(0x40, 0x90, 0xF0, 0x60);
+const OUTLINE_COLOR: Color = Color::rgb8(0x40, 0x90, 0xF0);
+
+/// A widget as it was seen during the last paint.
+#[derive(Debug, Clone)]
+struct Node {
+    id: WidgetId,
+    name: &'static str,
+    depth: usize,
+    /// The layout rect, in window coordinates.
+    rect: Rect,
+    bc: Option<BoxConstraints>,
+    data: Option<String>,
+}
+
+/// Per-window state of the widget inspector.
+///
+/// While enabled, every widget that is painted records itself here, which
+/// gives us a snapshot of the visible widget tree. The tree is drawn in a
+/// panel on the right side of the window, and the widget under the cursor is
+/// highlighted along with its constraints and data.
+///
+/// Data is only available for widgets wrapped in [`InspectData`], since the
+/// data of other widgets is not required to be `Debug`.
+///
+/// The inspector is toggled with the [`TOGGLE_INSPECTOR`] command, or with
+/// ctrl+shift+I (cmd+shift+I on macOS) in debug builds, when no widget
+/// handles that key.
+///
+/// [`InspectData`]: crate::widget::InspectData
+/// [`TOGGLE_INSPECTOR`]: crate::commands::TOGGLE_INSPECTOR
+#[derive(Debug, Default)]
+pub(crate) struct Inspector {
+    enabled: bool,
+    nodes: Vec<Node>,
+    /// Indices into `nodes` of the widgets that are currently being painted.
+    stack: Vec<usize>,
+}
+
+impl Inspector {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        *self = Inspector {
+            enabled: !self.enabled,
+            ..Default::default()
+        };
+    }
+
+    /// Forget the previous snapshot, before the tree is painted again.
+    pub(crate) fn begin_frame(&mut self) {
+        self.nodes.clear();
+        self.stack.clear();
+    }
+
+    /// Record a widget that is about to be painted.
+    pub(crate) fn begin_widget(
+        &mut self,
+        id: WidgetId,
+        name: &'static str,
+        rect: Rect,
+        bc: Option<BoxConstraints>,
+    ) {
+        self.stack.push(self.nodes.len());
+        self.nodes.push(Node {
+            id,
+            name,
+            depth: self.stack.len() - 1,
+            rect,
+            bc,
+            data: None,
+        });
+    }
+
+    /// Attach a description of the data to the widget currently being painted.
+    pub(crate) fn set_data(&mut self, data: String) {
+        if let Some(&idx) = self.stack.last() {
+            self.nodes[idx].data = Some(data);
+        }
+    }
+
+    /// Called when a widget has finished painting.
+    pub(crate) fn end_widget(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Returns the index of the innermost widget containing `pos`.
+    fn hovered(&self, pos: Point) -> Option<usize> {
+        // nodes are in paint order, so when two widgets at the same depth
+        // overlap, the later one is on top.
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.rect.contains(pos))
+            .max_by_key(|(idx, node)| (node.depth, *idx))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Draw the highlight and the tree panel.
+    pub(crate) fn paint_overlay(&self, piet: &mut Piet, mouse_pos: Option<Point>, size: Size) {
+        let hovered = mouse_pos.and_then(|pos| self.hovered(pos));
+        if let Some(node) = hovered.map(|idx| &self.nodes[idx]) {
+            piet.fill(node.rect, &HIGHLIGHT_COLOR);
+            piet.stroke(node.rect.inset(-0.5), &OUTLINE_COLOR, 1.0);
+        }
+
+        let panel = Rect::new(size.width - PANEL_WIDTH, 0.0, size.width, size.height);
+        piet.fill(panel, &PANEL_COLOR);
+
+        let mut text = String::new();
+        match hovered.map(|idx| &self.nodes[idx]) {
+            Some(node) => describe(node, &mut text),
+            None => text.push_str("hover over a widget to inspect it\n"),
+        }
+        text.push('\n');
+
+        // keep the hovered widget in view if the tree is too long to show.
+        let header_lines = text.lines().count() + 1;
+        let max_lines = ((size.height - PADDING * 2.0) / LINE_HEIGHT) as usize;
+        let tree_lines = max_lines.saturating_sub(header_lines).max(1);
+        let first = hovered
+            .map(|idx| idx.saturating_sub(tree_lines / 2))
+            .unwrap_or(0)
+            .min(self.nodes.len().saturating_sub(tree_lines));
+        for (idx, node) in self.nodes.iter().enumerate().skip(first).take(tree_lines) {
+            let marker = if Some(idx) == hovered { '>' } else { ' ' };
+            text.push_str(&format!(
+                "{}{:indent$}{} #{}\n",
+                marker,
+                "",
+                short_type_name(node.name),
+                node.id.to_raw(),
+                indent = node.depth * 2,
+            ));
+        }
+
+        let layout = match piet
+            .text()
+            .new_text_layout(text)
+            .font(FontFamily::MONOSPACE, FONT_SIZE)
+            .text_color(TEXT_COLOR)
+            .max_width(PANEL_WIDTH - PADDING * 2.0)
+            .build()
+        {
+            Ok(layout) => layout,
+            Err(e) => {
+                tracing::warn!("failed to build inspector text: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = piet.save() {
+            tracing::error!("Failed to save RenderContext: '{}'", e);
+            return;
+        }
+        piet.clip(panel);
+        piet.draw_text(&layout, panel.origin() + (PADDING, PADDING));
+        if let Err(e) = piet.restore() {
+            tracing::error!("Failed to restore RenderContext: '{}'", e);
+        }
+    }
+}
+
+/// Write the details of a single widget.
+fn describe(node: &Node, out: &mut String) {
+    let origin = node.rect.origin();
+    let size = node.rect.size();
+    out.push_str(&format!("{} #{}\n", node.name, node.id.to_raw()));
+    out.push_str(&format!(
+        "origin ({:.1}, {:.1})  size {:.1}x{:.1}\n",
+        origin.x, origin.y, size.width, size.height
+    ));
+    if let Some(bc) = &node.bc {
+        out.push_str(&format!(
+            "constraints {:.1}x{:.1} to {:.1}x{:.1}\n",
+            bc.min().width,
+            bc.min().height,
+            bc.max().width,
+            bc.max().height
+        ));
+    }
+    if let Some(data) = &node.data {
+        out.push_str(&format!("data {}\n", data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hovered_is_innermost() {
+        let mut inspector = Inspector::default();
+        let outer = WidgetId::next();
+        let inner = WidgetId::next();
+        let sibling = WidgetId::next();
+
+        inspector.begin_widget(outer, "outer", Rect::new(0., 0., 100., 100.), None);
+        inspector.begin_widget(inner, "inner", Rect::new(10., 10., 50., 50.), None);
+        inspector.set_data("42".into());
+        inspector.end_widget();
+        inspector.begin_widget(sibling, "sibling", Rect::new(50., 50., 90., 90.), None);
+        inspector.end_widget();
+        inspector.end_widget();
+
+        let depths: Vec<_> = inspector.nodes.iter().map(|n| n.depth).collect();
+        assert_eq!(depths, vec![0, 1, 1]);
+        assert_eq!(inspector.nodes[1].data.as_deref(), Some("42"));
+        assert!(inspector.nodes[0].data.is_none());
+
+        let hovered = |pos| inspector.hovered(pos).map(|idx| inspector.nodes[idx].id);
+        assert_eq!(hovered(Point::new(20., 20.)), Some(inner));
+        assert_eq!(hovered(Point::new(5., 5.)), Some(outer));
+        assert_eq!(hovered(Point::new(80., 80.)), Some(sibling));
+        assert_eq!(hovered(Point::new(200., 200.)), None);
+    }
+}
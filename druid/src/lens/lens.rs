@@ -14,9 +14,8 @@
 
 use std::marker::PhantomData;
 use std::ops;
-use std::sync::Arc;
 
-use crate::Data;
+use crate::{Data, MakeMut};
 
 /// A lens is a datatype that gives access to a part of a larger
 /// data structure.
@@ -188,6 +187,8 @@ pub trait LensExt<A: ?Sized, B: ?Sized>: Lens<A, B> {
 
     /// Adapt to operate on the contents of an `Arc` with efficient copy-on-write semantics
     ///
+    /// This works for any pointer that implements [`MakeMut`], like `Rc`.
+    ///
     /// ```
     /// # use druid::*; use std::sync::Arc;
     /// let lens = lens::Identity.index(2).in_arc();
@@ -198,6 +199,10 @@ pub trait LensExt<A: ?Sized, B: ?Sized>: Lens<A, B> {
     /// assert!(Arc::ptr_eq(&original, &x), "no-op writes don't cause a deep copy");
     /// lens.put(&mut x, 42);
     /// assert_eq!(&*x, &[0, 1, 42, 3]);
+    ///
+    /// let mut y = std::rc::Rc::new(vec![0, 1, 2, 3]);
+    /// lens.put(&mut y, 42);
+    /// assert_eq!(&*y, &[0, 1, 42, 3]);
     /// ```
     fn in_arc(self) -> InArc<Self>
     where
@@ -300,6 +305,107 @@ macro_rules! lens {
     };
 }
 
+/// Construct a lens along a path of fields, indices, enum variants and shared pointers
+///
+/// The path starts with the name of the type the lens operates on, or any type in
+/// parentheses, and is made of the following steps:
+///
+/// - `.field` or `.0` for a field of a struct or tuple;
+/// - `[index]` for an element of anything that can be indexed;
+/// - `.*` for the value in an `Arc` or `Rc`;
+/// - `.(Enum::Variant)` for the value in a variant with a single field, which panics if
+///   the data is in a different variant, the same way that indexing out of bounds does.
+///
+/// A path without `.*` makes a [`Field`] lens, like [`lens!`]. A path with `.*` makes a
+/// [`Map`] lens that only clones the value behind the pointer when a different value
+/// is written, like [`InArc`]; the target of such a lens must implement [`Data`].
+///
+/// ```
+/// use std::sync::Arc;
+/// use druid::{lens_path, Data, Lens, LensExt};
+///
+/// #[derive(Clone, Data)]
+/// struct AppState { settings: Arc<Settings>, mode: Mode }
+/// #[derive(Clone, Data)]
+/// struct Settings { theme: Theme, recent: (String, u32) }
+/// #[derive(Clone, Data)]
+/// struct Theme { accent: u32 }
+/// #[derive(Clone, Data)]
+/// enum Mode { Viewing, Editing(Theme) }
+///
+/// let mut state = AppState {
+///     settings: Arc::new(Settings {
+///         theme: Theme { accent: 1 },
+///         recent: ("notes.txt".into(), 2),
+///     }),
+///     mode: Mode::Editing(Theme { accent: 3 }),
+/// };
+/// let shared = state.settings.clone();
+///
+/// let accent = lens_path!(AppState.settings.*.theme.accent);
+/// assert_eq!(accent.get(&state), 1);
+/// // reading through `with_mut` doesn't copy the settings.
+/// accent.with_mut(&mut state, |_| ());
+/// assert!(Arc::ptr_eq(&shared, &state.settings));
+/// accent.put(&mut state, 5);
+/// assert_eq!(state.settings.theme.accent, 5);
+/// assert_eq!(shared.theme.accent, 1);
+///
+/// assert_eq!(lens_path!(AppState.settings.*.recent.1).get(&state), 2);
+/// assert_eq!(lens_path!(AppState.mode.(Mode::Editing).accent).get(&state), 3);
+/// assert_eq!(lens_path!((Vec<(u32, bool)>)[1].0).get(&vec![(1, true), (2, false)]), 2);
+/// ```
+#[macro_export]
+macro_rules! lens_path {
+    (($ty:ty) $($path:tt)*) => {
+        $crate::lens_path!(@step [$ty] x [] {x} {x} $($path)*)
+    };
+    ($ty:ident $($path:tt)*) => {
+        $crate::lens_path!(@step [$ty] x [] {x} {x} $($path)*)
+    };
+    (@step [$ty:ty] $x:ident [$($cow:tt)*] {$($get:tt)*} {$($mut:tt)*} . * $($rest:tt)*) => {
+        $crate::lens_path!(
+            @step [$ty] $x [cow]
+            {(*$($get)*)}
+            {(*$crate::MakeMut::make_mut(&mut $($mut)*))}
+            $($rest)*
+        )
+    };
+    (@step [$ty:ty] $x:ident [$($cow:tt)*] {$($get:tt)*} {$($mut:tt)*} . ($variant:path) $($rest:tt)*) => {
+        $crate::lens_path!(
+            @step [$ty] $x [$($cow)*]
+            {(*match &$($get)* {
+                $variant(value) => value,
+                _ => panic!(concat!("lens_path: data is not ", stringify!($variant))),
+            })}
+            {(*match &mut $($mut)* {
+                $variant(value) => value,
+                _ => panic!(concat!("lens_path: data is not ", stringify!($variant))),
+            })}
+            $($rest)*
+        )
+    };
+    (@step [$ty:ty] $x:ident [$($cow:tt)*] {$($get:tt)*} {$($mut:tt)*} . $field:tt $($rest:tt)*) => {
+        $crate::lens_path!(@step [$ty] $x [$($cow)*] {$($get)*.$field} {$($mut)*.$field} $($rest)*)
+    };
+    (@step [$ty:ty] $x:ident [$($cow:tt)*] {$($get:tt)*} {$($mut:tt)*} [$index:expr] $($rest:tt)*) => {
+        $crate::lens_path!(@step [$ty] $x [$($cow)*] {$($get)*[$index]} {$($mut)*[$index]} $($rest)*)
+    };
+    (@step [$ty:ty] $x:ident [] {$($get:tt)*} {$($mut:tt)*}) => {
+        $crate::lens::Field::new::<$ty, _>(move |$x| &$($get)*, move |$x| &mut $($mut)*)
+    };
+    (@step [$ty:ty] $x:ident [cow] {$($get:tt)*} {$($mut:tt)*}) => {
+        $crate::lens::Map::new::<$ty, _>(
+            move |$x| ::std::clone::Clone::clone(&$($get)*),
+            move |$x, value| {
+                if !$crate::Data::same(&$($get)*, &value) {
+                    *(&mut $($mut)*) = value;
+                }
+            },
+        )
+    };
+}
+
 /// `Lens` composed of two lenses joined together
 #[derive(Debug, Copy)]
 pub struct Then<T, U, B: ?Sized> {
@@ -477,7 +583,8 @@ impl<A: ?Sized> Lens<A, A> for Identity {
 
 /// A `Lens` that exposes data within an `Arc` with copy-on-write semantics
 ///
-/// A copy is only made in the event that a different value is written.
+/// A copy is only made in the event that a different value is written. This
+/// works for any pointer that implements [`MakeMut`], like `Rc`.
 #[derive(Debug, Copy, Clone)]
 pub struct InArc<L> {
     inner: L,
@@ -497,21 +604,21 @@ impl<L> InArc<L> {
     }
 }
 
-impl<A, B, L> Lens<Arc<A>, B> for InArc<L>
+impl<P, B, L> Lens<P, B> for InArc<L>
 where
-    A: Clone,
+    P: MakeMut,
     B: Data,
-    L: Lens<A, B>,
+    L: Lens<P::Target, B>,
 {
-    fn with<V, F: FnOnce(&B) -> V>(&self, data: &Arc<A>, f: F) -> V {
-        self.inner.with(data, f)
+    fn with<V, F: FnOnce(&B) -> V>(&self, data: &P, f: F) -> V {
+        self.inner.with(&**data, f)
     }
 
-    fn with_mut<V, F: FnOnce(&mut B) -> V>(&self, data: &mut Arc<A>, f: F) -> V {
-        let mut temp = self.inner.with(data, |x| x.clone());
+    fn with_mut<V, F: FnOnce(&mut B) -> V>(&self, data: &mut P, f: F) -> V {
+        let mut temp = self.inner.with(&**data, |x| x.clone());
         let v = f(&mut temp);
-        if self.inner.with(data, |x| !x.same(&temp)) {
-            self.inner.with_mut(Arc::make_mut(data), |x| *x = temp);
+        if self.inner.with(&**data, |x| !x.same(&temp)) {
+            self.inner.with_mut(data.make_mut(), |x| *x = temp);
         }
         v
     }
@@ -97,7 +97,14 @@
 //!          which is made available via the [`im` module].
 //! * `svg` - Scalable Vector Graphics for icons and other scalable images using the [`usvg` crate].
 //! * `image` - Bitmap image support using the [`image` crate].
+//! * `regex` - Regular expressions for filtering a [`LogView`], using the [`regex` crate].
 //! * `x11` - Work-in-progress X11 Linux backend instead of GTK.
+//! * `raw-win-handle` - Access to the native window handle, for instance to render
+//!                      into a [`NativeSurface`] with another graphics library.
+//! * `tracing-chrome` - Recording traces for `chrome://tracing` with
+//!                      `AppLauncher::log_to_chrome_trace`.
+//! * `data-diff` - Logging what changed in the app data before every update, with
+//!                 `AppLauncher::log_data_diffs`.
 //!
 //! Features can be added with `cargo`. For example, in your `Cargo.toml`:
 //! ```no_compile
@@ -119,6 +126,9 @@
 //! [`im` module]: im/index.html
 //! [`usvg` crate]: https://crates.io/crates/usvg
 //! [`image` crate]: https://crates.io/crates/image
+//! [`regex` crate]: https://crates.io/crates/regex
+//! [`LogView`]: widget::LogView
+//! [`NativeSurface`]: widget::NativeSurface
 
 #![deny(
     broken_intra_doc_links,
@@ -156,16 +166,25 @@ mod command;
 mod contexts;
 mod core;
 mod data;
+#[cfg(feature = "data-diff")]
+mod data_diff;
 mod dialog;
 mod env;
 mod event;
 mod ext_event;
+mod frame_stats;
+#[cfg(feature = "image")]
+pub mod image_cache;
+mod inspector;
 mod localization;
 pub mod menu;
 mod mouse;
+mod registry;
 pub mod scroll_component;
 mod sub_window;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
+#[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests;
 pub mod text;
@@ -182,36 +201,43 @@ pub use piet::{Color, ImageBuf, LinearGradient, RadialGradient, RenderContext, U
 #[cfg(feature = "image")]
 pub use shell::image;
 pub use shell::keyboard_types;
+#[cfg(feature = "raw-win-handle")]
+pub use shell::raw_window_handle;
 pub use shell::{
-    Application, Clipboard, ClipboardFormat, Code, Cursor, CursorDesc, Error as PlatformError,
-    FileInfo, FileSpec, FormatId, HotKey, KbKey, KeyEvent, Location, Modifiers, Monitor,
-    MouseButton, MouseButtons, RawMods, Region, Scalable, Scale, Screen, SysMods, TimerToken,
-    WindowHandle, WindowLevel, WindowState,
+    AnchorSide, Application, Clipboard, ClipboardFormat, Code, Cursor, CursorDesc, DropEvent,
+    Error as PlatformError, FileInfo, FilePromise, FileSpec, FormatId, HotKey, KbKey, KeyEvent,
+    Location, Modifiers, Monitor, MonitorSelector, MouseButton, MouseButtons, PointerType,
+    Politeness, PowerEvent, PowerStatus, RawMods, Region, RegionalSettings, Scalable, Scale,
+    Screen, StrokeAlignment, SysMods, TimerToken, Weekday, WindowHandle, WindowLevel, WindowState,
 };
 
 pub use crate::core::WidgetPod;
 pub use app::{AppLauncher, WindowConfig, WindowDesc, WindowSizePolicy};
 pub use app_delegate::{AppDelegate, DelegateCtx};
 pub use box_constraints::BoxConstraints;
-pub use command::{sys as commands, Command, Notification, Selector, SingleUse, Target};
+pub use command::{
+    sys as commands, Command, CommandTimer, DataWrite, Notification, Selector, SingleUse, Target,
+};
 pub use contexts::{EventCtx, LayoutCtx, LifeCycleCtx, PaintCtx, UpdateCtx};
-pub use data::Data;
+pub use data::{Data, Edit, MakeMut, Versioned};
+#[cfg(feature = "data-diff")]
+pub use data_diff::{DataDiff, DiffLine};
 pub use dialog::FileDialogOptions;
 pub use env::{Env, Key, KeyOrValue, Value, ValueType, ValueTypeError};
-pub use event::{Event, InternalEvent, InternalLifeCycle, LifeCycle};
-pub use ext_event::{ExtEventError, ExtEventSink};
+pub use event::{Event, InputMode, InternalEvent, InternalLifeCycle, LifeCycle};
+pub use ext_event::{Coalesce, ExtEventError, ExtEventSink, TrySubmitError};
 pub use lens::{Lens, LensExt};
-pub use localization::LocalizedString;
+pub use localization::{LocalizationBundle, LocalizedString};
 pub use menu::{sys as platform_menus, Menu, MenuItem};
 pub use mouse::MouseEvent;
+pub use registry::{FocusDirection, WidgetInfo, WidgetRegistry};
 pub use util::Handled;
 pub use widget::{Widget, WidgetExt, WidgetId};
 pub use win_handler::DruidHandler;
 pub use window::{Window, WindowId};
 
 #[cfg(not(target_arch = "wasm32"))]
-#[cfg(test)]
-pub(crate) use event::{StateCell, StateCheckFn};
+pub(crate) use event::{LabelQuery, StateCell, StateCheckFn};
 
 #[deprecated(since = "0.8.0", note = "import from druid::text module instead")]
 pub use piet::{FontFamily, FontStyle, FontWeight, TextAlignment};
@@ -27,26 +27,71 @@
 //! At runtime, you resolve your [`LocalizedString`] into an actual string,
 //! passing it the current [`Env`] and [`Data`].
 //!
+//! ## Plurals, selectors and numbers
+//!
+//! Arguments that are numbers (that is, [`FluentValue::Number`]) can be used
+//! in Fluent [selectors], and will be matched against the plural categories
+//! of the current locale. Arguments that are strings can be used to select
+//! between variants as well, which is useful for things like grammatical gender:
+//!
+//! ```ftl
+//! files-selected = { $count ->
+//!     [one] One file selected
+//!    *[other] { $count } files selected
+//! }
+//!
+//! shared-with = { $gender ->
+//!     [female] Shared with her
+//!     [male] Shared with him
+//!    *[other] Shared with them
+//! }
+//! ```
+//!
+//! When a number is inserted into a string, it is formatted with the decimal
+//! separator and digit grouping of the current locale. The builtin `NUMBER`
+//! function can be used to control this formatting, for instance
+//! `{ NUMBER($ratio, minimumFractionDigits: 2) }` or
+//! `{ NUMBER($count, useGrouping: "false") }`.
+//!
+//! ## Additional resources
+//!
+//! Besides the resources set with [`AppLauncher::localization_resources`],
+//! applications and libraries of widgets can provide their own translations
+//! as a [`LocalizationBundle`], which can be loaded from a directory at runtime
+//! or embedded in the binary. A bundle may have a namespace; its messages are
+//! then referred to with keys of the form `namespace:message-id`, so that
+//! libraries do not need to worry about conflicting with the application's
+//! message ids.
 //!
 //! [Fluent]: https://projectfluent.org
 //! [fluent-rs]: https://github.com/projectfluent/fluent-rs
 //! [`LocalizedString`]: struct.LocalizedString.html
 //! [`Env`]: struct.Env.html
 //! [`Data`]: trait.Data.html
+//! [`AppLauncher::localization_resources`]: crate::AppLauncher::localization_resources
+//! [`LocalizationBundle`]: crate::LocalizationBundle
+//! [`FluentValue::Number`]: https://docs.rs/fluent-bundle/0.15.1/fluent_bundle/enum.FluentValue.html
+//! [selectors]: https://projectfluent.org/fluent/guide/selectors.html
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{fs, io};
 
 use tracing::{debug, error, warn};
 
-use crate::{Application, ArcStr, Env};
+#[cfg(feature = "chrono")]
+use crate::Weekday;
+use crate::{Application, ArcStr, Env, RegionalSettings};
 
+use fluent_bundle::types::{FluentNumber, FluentNumberStyle};
 use fluent_bundle::{
     FluentArgs, FluentBundle, FluentError, FluentMessage, FluentResource, FluentValue,
 };
 use fluent_langneg::{negotiate_languages, NegotiationStrategy};
 use fluent_syntax::ast::Pattern as FluentPattern;
+use intl_memoizer::{IntlLangMemoizer, Memoizable};
 use unic_langid::LanguageIdentifier;
 
 // Localization looks for string files in druid/resources, but this path is hardcoded;
@@ -64,6 +109,12 @@ pub(crate) struct L10nManager {
     resources: Vec<String>,
     current_bundle: BundleStack,
     current_locale: LanguageIdentifier,
+    /// Bundles provided in addition to the application's main resources.
+    bundles: Vec<LocalizationBundle>,
+    /// The number of entries in `current_bundle` that come from the
+    /// application's main resources.
+    app_bundle_len: usize,
+    namespaces: HashMap<String, BundleStack>,
 }
 
 /// Manages a collection of localization files.
@@ -74,6 +125,45 @@ struct ResourceManager {
     path_scheme: String,
 }
 
+/// A set of localization resources that can be added to an application.
+///
+/// This lets an application, or a library of widgets, provide translations in
+/// addition to the resources set with [`AppLauncher::localization_resources`].
+/// Resources can be loaded from a directory at runtime, or embedded in the
+/// binary with `include_str!`.
+///
+/// If the bundle has a namespace, its messages are looked up with keys of the
+/// form `namespace:message-id`. Otherwise its messages are merged with those
+/// of the application; in either case, bundles added later take precedence
+/// over those added earlier, and the application's own resources take
+/// precedence over all added bundles.
+///
+/// Bundles are added with [`AppLauncher::localization_bundle`].
+///
+/// # Examples
+///
+/// ```
+/// use druid::LocalizationBundle;
+///
+/// // in a real library, this would be something like
+/// // `include_str!("../i18n/en-US/my-widgets.ftl")`
+/// let en_us = "ok-button = OK";
+///
+/// let bundle = LocalizationBundle::namespaced("my-widgets")
+///     .with_embedded("en-US", en_us)
+///     .with_directory("./i18n", vec!["my-widgets.ftl".into()]);
+/// assert_eq!(bundle.namespace(), Some("my-widgets"));
+/// ```
+///
+/// [`AppLauncher::localization_resources`]: crate::AppLauncher::localization_resources
+/// [`AppLauncher::localization_bundle`]: crate::AppLauncher::localization_bundle
+#[derive(Debug, Clone, Default)]
+pub struct LocalizationBundle {
+    namespace: Option<String>,
+    directories: Vec<(PathBuf, Vec<String>)>,
+    embedded: Vec<(LanguageIdentifier, Cow<'static, str>)>,
+}
+
 //NOTE: instead of a closure, at some point we can use something like a lens for this.
 //TODO: this is an Arc so that it can be clone, which is a bound on things like `Menu`.
 /// A closure that generates a localization value.
@@ -99,6 +189,30 @@ pub struct LocalizedString<T> {
 /// A stack of localization resources, used for fallback.
 struct BundleStack(Vec<FluentBundle<Arc<FluentResource>>>);
 
+/// The symbols used when displaying numbers in a given locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NumberSymbols {
+    pub(crate) decimal: char,
+    pub(crate) grouping: Option<char>,
+}
+
+/// The order of the components of a numeric date in a given locale.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+/// The conventions used when displaying numeric dates in a given locale.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DateSymbols {
+    pub(crate) order: DateOrder,
+    pub(crate) separator: char,
+}
+
 impl BundleStack {
     fn get_message(&self, id: &str) -> Option<FluentMessage> {
         self.0.iter().flat_map(|b| b.get_message(id)).next()
@@ -154,7 +268,7 @@ impl ResourceManager {
         debug!("resolved: {}", PrintLocales(resolved_locales.as_slice()));
         let mut stack = Vec::new();
         for locale in &resolved_locales {
-            let mut bundle = FluentBundle::new(resolved_locales.clone());
+            let mut bundle = new_bundle(resolved_locales.clone());
             for res_id in resource_ids {
                 let res = self.get_resource(&res_id, &locale.to_string());
                 bundle.add_resource(res).unwrap();
@@ -166,15 +280,429 @@ impl ResourceManager {
 
     /// Given a locale, returns the best set of available locales.
     pub(crate) fn resolve_locales(&self, locale: LanguageIdentifier) -> Vec<LanguageIdentifier> {
-        negotiate_languages(
-            &[locale],
-            &self.locales,
-            Some(&self.default_locale),
-            NegotiationStrategy::Filtering,
-        )
-        .into_iter()
-        .map(|l| l.to_owned())
-        .collect()
+        resolve_locales(locale, &self.locales, &self.default_locale)
+    }
+}
+
+impl LocalizationBundle {
+    /// Create a new, empty `LocalizationBundle`, whose messages are merged
+    /// with those of the application.
+    pub fn new() -> Self {
+        LocalizationBundle::default()
+    }
+
+    /// Create a new, empty `LocalizationBundle` with the given namespace.
+    ///
+    /// Messages in this bundle are looked up with keys of the form
+    /// `namespace:message-id`.
+    pub fn namespaced(namespace: impl Into<String>) -> Self {
+        LocalizationBundle {
+            namespace: Some(namespace.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Builder-style method to add resources from a directory.
+    ///
+    /// `resources` is a list of file names that contain strings. `base_dir`
+    /// is a path to a directory that includes per-locale subdirectories, of the
+    /// structure `base_dir/{locale}/{resource}`.
+    pub fn with_directory(mut self, base_dir: impl Into<PathBuf>, resources: Vec<String>) -> Self {
+        self.directories.push((base_dir.into(), resources));
+        self
+    }
+
+    /// Builder-style method to add the contents of a `.ftl` file for a locale.
+    ///
+    /// `locale` should be a valid BCP47 language tag; if it is not, the
+    /// resource is ignored.
+    pub fn with_embedded(mut self, locale: &str, contents: impl Into<Cow<'static, str>>) -> Self {
+        match locale.parse() {
+            Ok(locale) => self.embedded.push((locale, contents.into())),
+            Err(e) => error!("invalid locale '{}' for embedded resource: {}", locale, e),
+        }
+        self
+    }
+
+    /// The namespace of this bundle, if it has one.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// All the locales for which this bundle has resources.
+    fn available_locales(&self) -> Vec<LanguageIdentifier> {
+        let mut locales: Vec<LanguageIdentifier> = self
+            .directories
+            .iter()
+            .flat_map(|(dir, _)| get_available_locales(dir).unwrap_or_default())
+            .chain(self.embedded.iter().map(|(locale, _)| locale.clone()))
+            .collect();
+        locales.sort_by_cached_key(|l| l.to_string());
+        locales.dedup();
+        locales
+    }
+
+    /// Load the resources in this bundle for the provided locale.
+    fn load(
+        &self,
+        locale: &LanguageIdentifier,
+        default_locale: &LanguageIdentifier,
+    ) -> BundleStack {
+        let available = self.available_locales();
+        let resolved_locales = resolve_locales(locale.clone(), &available, default_locale);
+        let mut stack = Vec::new();
+        for locale in &resolved_locales {
+            let mut bundle = new_bundle(resolved_locales.clone());
+            let mut contents = Vec::new();
+            for (dir, res_ids) in &self.directories {
+                for res_id in res_ids {
+                    let path = dir.join(locale.to_string()).join(res_id);
+                    match fs::read_to_string(&path) {
+                        Ok(string) => contents.push(string),
+                        Err(e) => error!("failed to load resource {}: {}", path.display(), e),
+                    }
+                }
+            }
+            for (_, string) in self.embedded.iter().filter(|(l, _)| l == locale) {
+                contents.push(string.to_string());
+            }
+            for string in contents {
+                let res = match FluentResource::try_new(string) {
+                    Ok(res) => res,
+                    Err((res, _err)) => res,
+                };
+                if let Err(errs) = bundle.add_resource(Arc::new(res)) {
+                    for err in errs {
+                        warn!("localization error {:?}", err);
+                    }
+                }
+            }
+            stack.push(bundle);
+        }
+        BundleStack(stack)
+    }
+}
+
+/// Returns the locales for which `base_dir` has a subdirectory.
+fn get_available_locales(base_dir: &Path) -> Result<Vec<LanguageIdentifier>, io::Error> {
+    let mut locales = vec![];
+
+    let res_dir = fs::read_dir(base_dir)?;
+    for entry in res_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(name) = path.file_name() {
+                if let Some(name) = name.to_str() {
+                    let langid: LanguageIdentifier = name.parse().expect("Parsing failed.");
+                    locales.push(langid);
+                }
+            }
+        }
+    }
+    Ok(locales)
+}
+
+/// Given a locale, returns the best set of locales from those available.
+fn resolve_locales(
+    locale: LanguageIdentifier,
+    available: &[LanguageIdentifier],
+    default_locale: &LanguageIdentifier,
+) -> Vec<LanguageIdentifier> {
+    negotiate_languages(
+        &[locale],
+        available,
+        Some(default_locale),
+        NegotiationStrategy::Filtering,
+    )
+    .into_iter()
+    .map(|l| l.to_owned())
+    .collect()
+}
+
+/// Create a new bundle, with our number formatting and builtin functions.
+fn new_bundle(locales: Vec<LanguageIdentifier>) -> FluentBundle<Arc<FluentResource>> {
+    let mut bundle = FluentBundle::new(locales);
+    bundle.set_formatter(Some(format_fluent_value));
+    bundle
+        .add_function("NUMBER", number_function)
+        .expect("NUMBER function already registered");
+    bundle
+}
+
+/// The `NUMBER` builtin: applies the named arguments as formatting options.
+fn number_function<'a>(positional: &[FluentValue<'a>], named: &FluentArgs) -> FluentValue<'a> {
+    match positional {
+        [FluentValue::Number(number)] => {
+            let mut number = number.clone();
+            number.options.merge(named);
+            FluentValue::Number(number)
+        }
+        _ => FluentValue::Error,
+    }
+}
+
+/// Formats numbers using the symbols of the bundle's locale.
+///
+/// Other values use fluent's default formatting.
+fn format_fluent_value(value: &FluentValue, intls: &IntlLangMemoizer) -> Option<String> {
+    match value {
+        FluentValue::Number(number) => intls
+            .with_try_get::<NumberSymbols, _, _>((), |symbols| symbols.format(number))
+            .ok(),
+        _ => None,
+    }
+}
+
+/// The only character in `s`, if it has exactly one.
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Choose a locale from the user's preferred locales: the first one for which
+/// there are resources, or else the most preferred one.
+///
+/// Returns `None` if none of the preferred locales can be parsed.
+fn choose_locale(
+    preferred: &[String],
+    available: &[LanguageIdentifier],
+) -> Option<LanguageIdentifier> {
+    let preferred: Vec<LanguageIdentifier> =
+        preferred.iter().filter_map(|l| l.parse().ok()).collect();
+    preferred
+        .iter()
+        .find(|locale| {
+            !negotiate_languages(&[*locale], available, None, NegotiationStrategy::Filtering)
+                .is_empty()
+        })
+        .or_else(|| preferred.first())
+        .cloned()
+}
+
+/// Parse a locale identifier, such as the one returned by
+/// [`Application::get_locale`], falling back to `en-US` if it is invalid.
+pub(crate) fn parse_locale(locale: &str) -> LanguageIdentifier {
+    locale.parse().unwrap_or_else(|_| {
+        warn!("failed to parse locale '{}', using en-US", locale);
+        "en-US".parse().expect("failed to parse default locale")
+    })
+}
+
+/// The day that weeks start on in the provided locale.
+///
+/// This covers the common cases; unknown locales start on Monday.
+#[cfg(feature = "chrono")]
+pub(crate) fn first_weekday(locale: &LanguageIdentifier) -> Weekday {
+    const SUNDAY_REGIONS: &[&str] = &[
+        "AU", "BR", "CA", "CN", "HK", "IL", "IN", "JP", "KR", "MX", "PH", "TW", "US", "ZA",
+    ];
+    const SATURDAY_REGIONS: &[&str] = &["AE", "DZ", "EG", "IQ", "JO", "KW", "LY", "OM", "QA", "SA"];
+
+    match locale.region.as_ref().map(|r| r.as_str()) {
+        Some(region) if SUNDAY_REGIONS.contains(&region) => Weekday::Sunday,
+        Some(region) if SATURDAY_REGIONS.contains(&region) => Weekday::Saturday,
+        // without a region, English is most often American English.
+        None if locale.language.as_str() == "en" => Weekday::Sunday,
+        _ => Weekday::Monday,
+    }
+}
+
+impl NumberSymbols {
+    /// The symbols used by the provided locale.
+    ///
+    /// This covers the common cases; unknown locales use '.' as the decimal
+    /// separator and ',' for grouping.
+    pub(crate) fn for_locale(locale: &LanguageIdentifier) -> Self {
+        const NBSP: char = '\u{a0}';
+        const COMMA_DECIMAL_DOT_GROUPING: &[&str] = &[
+            "da", "de", "el", "es", "hr", "id", "it", "nl", "pt", "ro", "sl", "sr", "tr",
+        ];
+        const COMMA_DECIMAL_SPACE_GROUPING: &[&str] = &[
+            "bg", "cs", "et", "fi", "fr", "hu", "lt", "lv", "nb", "nn", "no", "pl", "ru", "sk",
+            "sv", "uk",
+        ];
+
+        let language = locale.language.as_str();
+        let region = locale.region.as_ref().map(|r| r.as_str());
+        let (decimal, grouping) = match (language, region) {
+            ("de", Some("CH")) | ("it", Some("CH")) => ('.', '\''),
+            ("pt", Some("PT")) => (',', NBSP),
+            _ if COMMA_DECIMAL_DOT_GROUPING.contains(&language) => (',', '.'),
+            _ if COMMA_DECIMAL_SPACE_GROUPING.contains(&language) => (',', NBSP),
+            _ => ('.', ','),
+        };
+        NumberSymbols {
+            decimal,
+            grouping: Some(grouping),
+        }
+    }
+
+    /// Use the separators that the user has chosen, if they are single
+    /// characters.
+    ///
+    /// An empty grouping separator turns grouping off.
+    pub(crate) fn with_regional_settings(mut self, settings: &RegionalSettings) -> Self {
+        if let Some(decimal) = settings.decimal_separator.as_deref().and_then(single_char) {
+            self.decimal = decimal;
+        }
+        match settings.grouping_separator.as_deref() {
+            Some("") => self.grouping = None,
+            Some(grouping) => {
+                if let Some(grouping) = single_char(grouping) {
+                    self.grouping = Some(grouping);
+                }
+            }
+            None => (),
+        }
+        self
+    }
+
+    /// Format a number, respecting the options set by the `NUMBER` function.
+    pub(crate) fn format(self, number: &FluentNumber) -> String {
+        let options = &number.options;
+        let value = match options.style {
+            FluentNumberStyle::Percent => number.value * 100.0,
+            _ => number.value,
+        };
+
+        let mut digits = match options.maximum_fraction_digits {
+            Some(max) => format!("{:.*}", max, value.abs()),
+            None => value.abs().to_string(),
+        };
+        let (mut int_part, mut frac_part) = match digits.find('.') {
+            Some(idx) => {
+                let frac = digits.split_off(idx + 1);
+                digits.pop();
+                (digits, frac)
+            }
+            None => (digits, String::new()),
+        };
+
+        let min_frac = options.minimum_fraction_digits.unwrap_or(0);
+        while frac_part.len() > min_frac && frac_part.ends_with('0') {
+            frac_part.pop();
+        }
+        while frac_part.len() < min_frac {
+            frac_part.push('0');
+        }
+        if let Some(min_int) = options.minimum_integer_digits {
+            while int_part.len() < min_int {
+                int_part.insert(0, '0');
+            }
+        }
+
+        let mut result = String::new();
+        if value.is_sign_negative() && value != 0.0 {
+            result.push('-');
+        }
+        match self.grouping {
+            Some(sep) if options.use_grouping && int_part.len() > 3 => {
+                for (i, digit) in int_part.chars().enumerate() {
+                    if i > 0 && (int_part.len() - i) % 3 == 0 {
+                        result.push(sep);
+                    }
+                    result.push(digit);
+                }
+            }
+            _ => result.push_str(&int_part),
+        }
+        if !frac_part.is_empty() {
+            result.push(self.decimal);
+            result.push_str(&frac_part);
+        }
+        if options.style == FluentNumberStyle::Percent {
+            result.push('%');
+        }
+        result
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DateSymbols {
+    /// The date conventions of the provided locale.
+    ///
+    /// This covers the common cases; unknown locales use day/month/year.
+    pub(crate) fn for_locale(locale: &LanguageIdentifier) -> Self {
+        const DOT_SEPARATED: &[&str] = &[
+            "bg", "cs", "da", "de", "et", "fi", "hr", "lv", "nb", "nn", "no", "pl", "ro", "ru",
+            "sk", "sl", "sr", "tr", "uk",
+        ];
+
+        let language = locale.language.as_str();
+        let region = locale.region.as_ref().map(|r| r.as_str());
+        let (order, separator) = match (language, region) {
+            ("en", Some("US")) | ("en", None) => (DateOrder::MonthDayYear, '/'),
+            ("en", Some("CA")) => (DateOrder::YearMonthDay, '-'),
+            ("ja", _) | ("zh", _) => (DateOrder::YearMonthDay, '/'),
+            ("hu", _) | ("ko", _) => (DateOrder::YearMonthDay, '.'),
+            ("lt", _) | ("sv", _) => (DateOrder::YearMonthDay, '-'),
+            ("nl", _) => (DateOrder::DayMonthYear, '-'),
+            _ if DOT_SEPARATED.contains(&language) => (DateOrder::DayMonthYear, '.'),
+            _ => (DateOrder::DayMonthYear, '/'),
+        };
+        DateSymbols { order, separator }
+    }
+
+    /// The date conventions of a pattern such as `dd.MM.yyyy`, in which `d`,
+    /// `M` and `y` stand for the day, month and year, as found in
+    /// [`RegionalSettings::short_date_pattern`].
+    ///
+    /// Returns `None` unless the pattern is a numeric date with a single
+    /// separator.
+    pub(crate) fn from_pattern(pattern: &str) -> Option<Self> {
+        let mut fields = String::new();
+        let mut separator = None;
+        for c in pattern.chars() {
+            match c {
+                'd' | 'M' | 'y' if !fields.contains(c) => fields.push(c),
+                'd' | 'M' | 'y' => (),
+                // spaces can surround the separator, as in Korean.
+                _ if c.is_whitespace() => (),
+                _ if c.is_alphanumeric() => return None,
+                _ if separator.is_none() || separator == Some(c) => separator = Some(c),
+                _ => return None,
+            }
+        }
+        let order = match fields.as_str() {
+            "dMy" => DateOrder::DayMonthYear,
+            "Mdy" => DateOrder::MonthDayYear,
+            "yMd" => DateOrder::YearMonthDay,
+            _ => return None,
+        };
+        Some(DateSymbols {
+            order,
+            separator: separator?,
+        })
+    }
+
+    /// A `strftime`-style pattern for dates in this locale.
+    pub(crate) fn pattern(self) -> String {
+        let sep = self.separator;
+        match self.order {
+            DateOrder::DayMonthYear => format!("%d{}%m{}%Y", sep, sep),
+            DateOrder::MonthDayYear => format!("%m{}%d{}%Y", sep, sep),
+            DateOrder::YearMonthDay => format!("%Y{}%m{}%d", sep, sep),
+        }
+    }
+
+    /// The maximum number of digits in each component of a date, in order.
+    pub(crate) fn component_widths(self) -> [usize; 3] {
+        match self.order {
+            DateOrder::DayMonthYear | DateOrder::MonthDayYear => [2, 2, 4],
+            DateOrder::YearMonthDay => [4, 2, 2],
+        }
+    }
+}
+
+impl Memoizable for NumberSymbols {
+    type Args = ();
+    type Error = ();
+
+    fn construct(lang: LanguageIdentifier, _args: ()) -> Result<Self, ()> {
+        Ok(NumberSymbols::for_locale(&lang))
     }
 }
 
@@ -187,31 +715,14 @@ impl L10nManager {
     /// This directory should be of the structure `base_dir/{locale}/{resource}`,
     /// where '{locale}' is a valid BCP47 language tag, and {resource} is a `.ftl`
     /// included in `resources`.
-    pub fn new(resources: Vec<String>, base_dir: &str) -> Self {
-        fn get_available_locales(base_dir: &str) -> Result<Vec<LanguageIdentifier>, io::Error> {
-            let mut locales = vec![];
-
-            let res_dir = fs::read_dir(base_dir)?;
-            for entry in res_dir.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(name) = path.file_name() {
-                        if let Some(name) = name.to_str() {
-                            let langid: LanguageIdentifier = name.parse().expect("Parsing failed.");
-                            locales.push(langid);
-                        }
-                    }
-                }
-            }
-            Ok(locales)
-        }
-
+    ///
+    /// `bundles` are additional resources, such as those provided by libraries.
+    pub fn new(resources: Vec<String>, base_dir: &str, bundles: Vec<LocalizationBundle>) -> Self {
         let default_locale: LanguageIdentifier =
             "en-US".parse().expect("failed to parse default locale");
-        let current_locale = Application::get_locale()
-            .parse()
-            .unwrap_or_else(|_| default_locale.clone());
-        let locales = get_available_locales(base_dir).unwrap_or_default();
+        let locales = get_available_locales(Path::new(base_dir)).unwrap_or_default();
+        let current_locale = choose_locale(&Application::preferred_locales(), &locales)
+            .unwrap_or_else(|| parse_locale(&Application::get_locale()));
         debug!(
             "available locales {}, current {}",
             PrintLocales(&locales),
@@ -228,13 +739,42 @@ impl L10nManager {
         };
 
         let current_bundle = res_mgr.get_bundle(&current_locale, &resources);
+        let app_bundle_len = current_bundle.0.len();
 
-        L10nManager {
+        let mut manager = L10nManager {
             res_mgr,
             resources,
             current_bundle,
             current_locale,
+            bundles: Vec::new(),
+            app_bundle_len,
+            namespaces: HashMap::new(),
+        };
+        for bundle in bundles {
+            manager.add_bundle(bundle);
         }
+        manager
+    }
+
+    /// Add an additional bundle of resources.
+    ///
+    /// Bundles added later take precedence over those added earlier.
+    fn add_bundle(&mut self, bundle: LocalizationBundle) {
+        let BundleStack(new) = bundle.load(&self.current_locale, &self.res_mgr.default_locale);
+        match bundle.namespace.as_ref() {
+            Some(namespace) => {
+                let stack = self
+                    .namespaces
+                    .entry(namespace.clone())
+                    .or_insert_with(|| BundleStack(Vec::new()));
+                stack.0.splice(0..0, new);
+            }
+            None => {
+                let idx = self.app_bundle_len;
+                self.current_bundle.0.splice(idx..idx, new);
+            }
+        }
+        self.bundles.push(bundle);
     }
 
     /// Fetch a localized string from the current bundle by key.
@@ -251,18 +791,13 @@ impl L10nManager {
         args: impl Into<Option<&'args FluentArgs<'args>>>,
     ) -> Option<ArcStr> {
         let args = args.into();
-        let value = match self
-            .current_bundle
-            .get_message(key)
-            .and_then(|msg| msg.value())
-        {
-            Some(v) => v,
-            None => return None,
+        let (stack, key) = match key.find(':') {
+            Some(idx) => (self.namespaces.get(&key[..idx])?, &key[idx + 1..]),
+            None => (&self.current_bundle, key),
         };
+        let value = stack.get_message(key).and_then(|msg| msg.value())?;
         let mut errs = Vec::new();
-        let result = self
-            .current_bundle
-            .format_pattern(key, value, args, &mut errs);
+        let result = stack.format_pattern(key, value, args, &mut errs);
         for err in errs {
             warn!("localization error {:?}", err);
         }
@@ -424,4 +959,198 @@ mod tests {
         assert_eq!(resmgr.resolve_locales(cn_hk), vec![en_us.clone()]);
         assert_eq!(resmgr.resolve_locales(pt_pt), vec![en_us]);
     }
+
+    #[test]
+    fn choose_preferred_locale() {
+        let available: Vec<LanguageIdentifier> =
+            vec!["en-US".parse().unwrap(), "de-DE".parse().unwrap()];
+        let preferred =
+            |locales: &[&str]| -> Vec<String> { locales.iter().map(|l| l.to_string()).collect() };
+
+        assert_eq!(
+            choose_locale(&preferred(&["fr-CA", "de-AT"]), &available),
+            Some("de-AT".parse().unwrap())
+        );
+        assert_eq!(
+            choose_locale(&preferred(&["fr-CA", "it-IT"]), &available),
+            Some("fr-CA".parse().unwrap())
+        );
+        assert_eq!(
+            choose_locale(&preferred(&["not a locale", "en-GB"]), &available),
+            Some("en-GB".parse().unwrap())
+        );
+        assert_eq!(choose_locale(&[], &available), None);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn locale_first_weekday() {
+        let first = |locale: &str| first_weekday(&locale.parse().unwrap());
+        assert_eq!(first("en-US"), Weekday::Sunday);
+        assert_eq!(first("en"), Weekday::Sunday);
+        assert_eq!(first("en-GB"), Weekday::Monday);
+        assert_eq!(first("de"), Weekday::Monday);
+        assert_eq!(first("ar-EG"), Weekday::Saturday);
+        assert_eq!(first("pt-BR"), Weekday::Sunday);
+    }
+
+    #[test]
+    fn regional_number_symbols() {
+        let en_us: LanguageIdentifier = "en-US".parse().unwrap();
+        let settings = RegionalSettings {
+            decimal_separator: Some(",".into()),
+            grouping_separator: Some("".into()),
+            ..Default::default()
+        };
+        let symbols = NumberSymbols::for_locale(&en_us).with_regional_settings(&settings);
+        assert_eq!(symbols.decimal, ',');
+        assert_eq!(symbols.grouping, None);
+
+        // separators of more than one character are ignored.
+        let settings = RegionalSettings {
+            decimal_separator: Some("..".into()),
+            grouping_separator: Some(" ".into()),
+            ..Default::default()
+        };
+        let symbols = NumberSymbols::for_locale(&en_us).with_regional_settings(&settings);
+        assert_eq!(symbols.decimal, '.');
+        assert_eq!(symbols.grouping, Some(' '));
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn date_symbols_from_pattern() {
+        let symbols = |pattern| DateSymbols::from_pattern(pattern).map(|s| (s.order, s.separator));
+
+        assert_eq!(symbols("dd.MM.yyyy"), Some((DateOrder::DayMonthYear, '.')));
+        assert_eq!(symbols("M/d/yy"), Some((DateOrder::MonthDayYear, '/')));
+        assert_eq!(symbols("yyyy. M. d."), Some((DateOrder::YearMonthDay, '.')));
+        assert_eq!(symbols("yyyy-MM-dd"), Some((DateOrder::YearMonthDay, '-')));
+        assert_eq!(symbols("d MMM yyyy"), None);
+        assert_eq!(symbols("EEE, d/M/y"), None);
+        assert_eq!(symbols("d/M-y"), None);
+    }
+
+    const TEST_STRINGS: &str = r#"
+files-selected = { $count ->
+    [one] One file selected
+   *[other] { $count } files selected
+}
+shared-with = { $gender ->
+    [female] Shared with her
+    [male] Shared with him
+   *[other] Shared with them
+}
+ratio = Ratio is { NUMBER($ratio, minimumFractionDigits: 2) }
+plain-count = { NUMBER($count, useGrouping: "false") } items
+"#;
+
+    fn test_manager(locale: &str) -> L10nManager {
+        let locale: LanguageIdentifier = locale.parse().unwrap();
+        let res = Arc::new(FluentResource::try_new(TEST_STRINGS.to_string()).unwrap());
+        let mut bundle = new_bundle(vec![locale.clone()]);
+        bundle.add_resource(res).unwrap();
+        L10nManager {
+            res_mgr: ResourceManager {
+                resources: HashMap::new(),
+                locales: vec![locale.clone()],
+                default_locale: locale.clone(),
+                path_scheme: String::new(),
+            },
+            resources: Vec::new(),
+            current_bundle: BundleStack(vec![bundle]),
+            current_locale: locale,
+            bundles: Vec::new(),
+            app_bundle_len: 1,
+            namespaces: HashMap::new(),
+        }
+    }
+
+    fn localize(manager: &L10nManager, key: &str, args: &[(&str, FluentValue<'static>)]) -> String {
+        let args: FluentArgs = args.iter().cloned().collect();
+        manager.localize(key, &args).unwrap().to_string()
+    }
+
+    #[test]
+    fn plural_and_select() {
+        let en = test_manager("en-US");
+        assert_eq!(
+            localize(&en, "files-selected", &[("count", 1.into())]),
+            "One file selected"
+        );
+        assert_eq!(
+            localize(&en, "files-selected", &[("count", 2.into())]),
+            "2 files selected"
+        );
+        assert_eq!(
+            localize(&en, "shared-with", &[("gender", "female".into())]),
+            "Shared with her"
+        );
+        assert_eq!(
+            localize(&en, "shared-with", &[("gender", "unknown".into())]),
+            "Shared with them"
+        );
+    }
+
+    #[test]
+    fn number_formatting() {
+        let en = test_manager("en-US");
+        let de = test_manager("de-DE");
+        let fr = test_manager("fr-FR");
+
+        assert_eq!(
+            localize(&en, "files-selected", &[("count", 12345.into())]),
+            "12,345 files selected"
+        );
+        assert_eq!(
+            localize(&de, "files-selected", &[("count", 12345.into())]),
+            "12.345 files selected"
+        );
+        assert_eq!(
+            localize(&fr, "files-selected", &[("count", 1234567.into())]),
+            "1\u{a0}234\u{a0}567 files selected"
+        );
+        assert_eq!(
+            localize(&en, "ratio", &[("ratio", 0.5.into())]),
+            "Ratio is 0.50"
+        );
+        assert_eq!(
+            localize(&de, "ratio", &[("ratio", (-1234.125).into())]),
+            "Ratio is -1.234,125"
+        );
+        assert_eq!(
+            localize(&en, "plain-count", &[("count", 5000.into())]),
+            "5000 items"
+        );
+    }
+
+    #[test]
+    fn additional_bundles() {
+        let mut manager = test_manager("en-US");
+        manager.add_bundle(LocalizationBundle::new().with_embedded(
+            "en-US",
+            "library-message = From a library\nratio = Overridden",
+        ));
+        manager.add_bundle(
+            LocalizationBundle::namespaced("widgets")
+                .with_embedded("en-US", "ok = OK\ncancel = Cancel")
+                .with_embedded("de-DE", "ok = Einverstanden"),
+        );
+        manager.add_bundle(
+            LocalizationBundle::namespaced("widgets").with_embedded("en-US", "ok = Okay"),
+        );
+
+        assert_eq!(localize(&manager, "library-message", &[]), "From a library");
+        // the application's own resources take precedence
+        assert_eq!(
+            localize(&manager, "ratio", &[("ratio", 1.into())]),
+            "Ratio is 1.00"
+        );
+        // later bundles take precedence
+        assert_eq!(localize(&manager, "widgets:ok", &[]), "Okay");
+        assert_eq!(localize(&manager, "widgets:cancel", &[]), "Cancel");
+        assert!(manager.localize("widgets:missing", None).is_none());
+        assert!(manager.localize("other:ok", None).is_none());
+        assert!(manager.localize("ok", None).is_none());
+    }
 }
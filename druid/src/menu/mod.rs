@@ -35,7 +35,9 @@
 //!   will be kept up-to-date for you.
 //!
 //!   The limitation of a "refresh" is that it cannot change the structure of the menus (e.g. by
-//!   adding new items or moving things around).
+//!   adding new items or moving things around). When only the titles, enabled states or
+//!   checkmarks of items in a window's menu change, the items are changed in place and the menu
+//!   is not replaced at all.
 //!
 //! - a "rebuild" is when the menu is rebuilt from scratch. When you first set a menu (e.g. using
 //!   [`WindowDesc::menu`]), you provide a callback for building the menu from data; a rebuild is
@@ -45,12 +47,27 @@
 //!   when to do them. You need to use [`Menu::rebuild_on`] to decide when rebuild should
 //!   occur.
 //!
+//! Parts of a menu whose structure depends on the data, like a list of recently opened files, can
+//! be added with [`Menu::dynamic_entries`]. These are rebuilt on their own, leaving the rest of the
+//! menu as it is.
+//!
+//! ## Sharing a menu between windows
+//!
+//! A menu set with [`AppLauncher::menu`] is used by every window that does not set its own menu
+//! with [`WindowDesc::menu`]. Each window can add to it with [`WindowDesc::customize_menu`], for
+//! example to add a menu for the document that is open in it.
+//!
 //! ## The macOS app menu
 //!
 //! On macOS, the main menu belongs to the application, not to the window.
 //!
 //! In Druid, whichever window is frontmost will have its menu displayed as the application menu.
 //!
+//! ## Menus drawn by widgets
+//!
+//! Widgets that draw a menu inside a window, like a menu bar or the list of a dropdown, can use
+//! [`MenuNavigation`] for the keyboard and pointer interaction, so that they all behave the same.
+//!
 //! ## Examples
 //!
 //! Creating the default app menu for macOS:
@@ -107,21 +124,30 @@
 //!
 //! [`LabelText::Dynamic`]: crate::widget::LabelText::Dynamic
 //! [`WindowDesc::menu`]: crate::WindowDesc::menu
+//! [`WindowDesc::customize_menu`]: crate::WindowDesc::customize_menu
+//! [`AppLauncher::menu`]: crate::AppLauncher::menu
 //! [`Command`]: crate::Command
 
+use std::cell::RefCell;
 use std::num::NonZeroU32;
+use std::rc::Rc;
 
 use crate::core::CommandQueue;
 use crate::kurbo::Point;
+use crate::lens::Unit;
 use crate::shell::{Counter, HotKey, IntoKey, Menu as PlatformMenu};
 use crate::widget::LabelText;
 use crate::{ArcStr, Command, Data, Env, Lens, RawMods, Target, WindowId};
 
 static COUNTER: Counter = Counter::new();
 
+mod navigation;
 pub mod sys;
 
+pub use navigation::{MenuAction, MenuNavigation, NavItem, SUBMENU_DELAY, TYPE_AHEAD_TIMEOUT};
+
 type MenuBuild<T> = Box<dyn FnMut(Option<WindowId>, &T, &Env) -> Menu<T>>;
+pub(crate) type MenuCustomize<T> = Box<dyn FnMut(Menu<T>, WindowId, &T, &Env) -> Menu<T>>;
 
 /// This is for completely recreating the menus (for when you want to change the actual menu
 /// structure, rather than just, say, enabling or disabling entries).
@@ -134,6 +160,19 @@ pub(crate) struct MenuManager<T> {
     menu: Option<Menu<T>>,
 }
 
+/// The menu that is shared by all windows that don't have their own.
+pub(crate) struct AppMenu<T> {
+    build: Rc<RefCell<MenuBuild<T>>>,
+}
+
+/// How a menu has changed, see [`MenuManager::update_items`].
+pub(crate) enum MenuChanges {
+    /// The menu has to be replaced with this one.
+    Replace(PlatformMenu),
+    /// Only these items have changed, and can be updated in place.
+    Items(Vec<(u32, MenuItemState)>),
+}
+
 /// A menu displayed as a pop-over.
 pub(crate) struct ContextMenu<T> {
     pub(crate) menu: Menu<T>,
@@ -211,18 +250,36 @@ impl<T: Data> MenuManager<T> {
         data: &T,
         env: &Env,
     ) -> Option<PlatformMenu> {
+        match self.update_items(window, data, env)? {
+            MenuChanges::Replace(menu) => Some(menu),
+            MenuChanges::Items(_) => Some(self.refresh(data, env)),
+        }
+    }
+
+    /// Update the menu based on a change to the data, without replacing it if only some of its
+    /// items have changed.
+    ///
+    /// Returns `None` if the menu hasn't changed.
+    pub fn update_items(
+        &mut self,
+        window: Option<WindowId>,
+        data: &T,
+        env: &Env,
+    ) -> Option<MenuChanges> {
         if let (Some(menu), Some(old_data)) = (self.menu.as_mut(), self.old_data.as_ref()) {
-            let ret = match menu.update(old_data, data, env) {
+            let mut ctx = MenuUpdateCtx::default();
+            let ret = match menu.update(&mut ctx, old_data, data, env) {
                 MenuUpdate::NeedsRebuild => {
                     if let Some(build) = &mut self.build {
                         self.menu = Some((build)(window, data, env));
                     } else {
                         tracing::warn!("tried to rebuild a context menu");
                     }
-                    Some(self.refresh(data, env))
+                    Some(MenuChanges::Replace(self.refresh(data, env)))
                 }
-                MenuUpdate::NeedsRefresh => Some(self.refresh(data, env)),
-                MenuUpdate::UpToDate => None,
+                MenuUpdate::NeedsRefresh => Some(MenuChanges::Replace(self.refresh(data, env))),
+                MenuUpdate::UpToDate if ctx.changed_items.is_empty() => None,
+                MenuUpdate::UpToDate => Some(MenuChanges::Items(ctx.changed_items)),
             };
             self.old_data = Some(data.clone());
             ret
@@ -248,6 +305,36 @@ impl<T: Data> MenuManager<T> {
     }
 }
 
+impl<T: Data> AppMenu<T> {
+    pub fn new(build: impl FnMut(Option<WindowId>, &T, &Env) -> Menu<T> + 'static) -> AppMenu<T> {
+        AppMenu {
+            build: Rc::new(RefCell::new(Box::new(build))),
+        }
+    }
+
+    /// Create a [`MenuManager`] for this menu, with the additions of a single window.
+    ///
+    /// Every window needs its own manager, because the menus keep the state of their items.
+    pub fn manager(&self, mut customize: Option<MenuCustomize<T>>) -> MenuManager<T> {
+        let build = self.build.clone();
+        MenuManager::new(move |window, data, env| {
+            let menu = (build.borrow_mut())(window, data, env);
+            match (window, customize.as_mut()) {
+                (Some(window), Some(customize)) => customize(menu, window, data, env),
+                _ => menu,
+            }
+        })
+    }
+}
+
+impl<T> Clone for AppMenu<T> {
+    fn clone(&self) -> Self {
+        AppMenu {
+            build: self.build.clone(),
+        }
+    }
+}
+
 /// This context is available to the callback that is called when a menu item is activated.
 ///
 /// Currently, it only allows for submission of [`Command`]s.
@@ -258,6 +345,12 @@ pub struct MenuEventCtx<'a> {
     queue: &'a mut CommandQueue,
 }
 
+/// This context collects the items whose state has changed, when the data changes.
+#[derive(Default)]
+struct MenuUpdateCtx {
+    changed_items: Vec<(u32, MenuItemState)>,
+}
+
 /// This context helps menu items to build the platform menu.
 struct MenuBuildCtx {
     current: PlatformMenu,
@@ -349,7 +442,10 @@ trait MenuVisitor<T> {
     fn activate(&mut self, ctx: &mut MenuEventCtx, id: MenuItemId, data: &mut T, env: &Env);
 
     /// Called when the data is changed.
-    fn update(&mut self, old_data: &T, data: &T, env: &Env) -> MenuUpdate;
+    ///
+    /// Items whose text or state changed, but that can stay where they are in the platform menu,
+    /// are added to `ctx` instead of requesting a refresh.
+    fn update(&mut self, ctx: &mut MenuUpdateCtx, old_data: &T, data: &T, env: &Env) -> MenuUpdate;
 
     /// Called to refresh the menu.
     fn refresh(&mut self, ctx: &mut MenuBuildCtx, data: &T, env: &Env);
@@ -375,7 +471,7 @@ impl<T: Data, U: Data, L: Lens<T, U>> MenuVisitor<T> for MenuLensWrap<L, U> {
             .with_mut(data, |u| inner.activate(ctx, id, u, env));
     }
 
-    fn update(&mut self, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
+    fn update(&mut self, ctx: &mut MenuUpdateCtx, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
         let inner = &mut self.inner;
         let lens = &self.lens;
         let cached_old_data = &mut self.old_data;
@@ -387,7 +483,7 @@ impl<T: Data, U: Data, L: Lens<T, U>> MenuVisitor<T> for MenuLensWrap<L, U> {
                 {
                     MenuUpdate::UpToDate
                 } else {
-                    inner.update(old, new, env)
+                    inner.update(ctx, old, new, env)
                 };
                 *cached_old_data = Some(new.clone());
                 *cached_old_env = Some(env.clone());
@@ -470,6 +566,15 @@ impl<T: Data> From<MenuItem<T>> for MenuEntry<T> {
 
 struct Separator;
 
+type DynamicEntriesBuild<T> = Box<dyn FnMut(&T, &Env) -> Vec<MenuEntry<T>>>;
+
+/// Menu entries that are rebuilt on their own, see [`Menu::dynamic_entries`].
+struct DynamicEntries<T> {
+    rebuild_on: MenuPredicate<T>,
+    build: DynamicEntriesBuild<T>,
+    entries: Option<Vec<MenuEntry<T>>>,
+}
+
 impl<T: Data> From<Separator> for MenuEntry<T> {
     fn from(s: Separator) -> MenuEntry<T> {
         MenuEntry { inner: Box::new(s) }
@@ -533,6 +638,27 @@ impl<T: Data> Menu<T> {
         self.entry(Separator)
     }
 
+    /// Append entries that are built from the data, returning the modified menu.
+    ///
+    /// `build` is called when the menu is first shown, and again whenever `rebuild_on` returns
+    /// true. The arguments to `rebuild_on` are the same as for [`rebuild_on`].
+    ///
+    /// Unlike [`rebuild_on`], this only rebuilds these entries and not the whole menu, so it is
+    /// the way to go for things like a list of recently opened files.
+    ///
+    /// [`rebuild_on`]: self::Menu<T>::rebuild_on
+    pub fn dynamic_entries(
+        self,
+        rebuild_on: impl FnMut(&T, &T, &Env) -> bool + 'static,
+        build: impl FnMut(&T, &Env) -> Vec<MenuEntry<T>> + 'static,
+    ) -> Self {
+        self.entry(DynamicEntries {
+            rebuild_on: Box::new(rebuild_on),
+            build: Box::new(build),
+            entries: None,
+        })
+    }
+
     /// Supply a function to check when this menu needs to refresh itself.
     ///
     /// The arguments to the callback are (in order):
@@ -596,6 +722,32 @@ impl<T: Data> Menu<T> {
     }
 }
 
+impl Menu<()> {
+    /// Turn a menu that doesn't use any data into one for `T`, keeping its entries at the
+    /// top level.
+    pub(crate) fn with_data<T: Data>(self) -> Menu<T> {
+        let children = self
+            .children
+            .into_iter()
+            .map(|child| {
+                MenuLensWrap {
+                    lens: Unit,
+                    inner: Box::new(child),
+                    old_data: None,
+                    old_env: None,
+                }
+                .into()
+            })
+            .collect();
+        Menu {
+            rebuild_on: None,
+            refresh_on: None,
+            item: MenuItem::new(""),
+            children,
+        }
+    }
+}
+
 impl<T: Data> MenuItem<T> {
     /// Create a new menu item with a given name.
     pub fn new(title: impl Into<LabelText<T>>) -> MenuItem<T> {
@@ -730,7 +882,7 @@ impl<T: Data> MenuVisitor<T> for Menu<T> {
         }
     }
 
-    fn update(&mut self, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
+    fn update(&mut self, ctx: &mut MenuUpdateCtx, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
         if let Some(rebuild_on) = &mut self.rebuild_on {
             if rebuild_on(old_data, data, env) {
                 return MenuUpdate::NeedsRebuild;
@@ -742,9 +894,14 @@ impl<T: Data> MenuVisitor<T> for Menu<T> {
             }
         }
 
-        let mut ret = self.item.update(old_data, data, env);
+        // submenus have no ids, so they can't be changed in place.
+        let mut ret = if self.item.resolve(data, env) {
+            MenuUpdate::NeedsRefresh
+        } else {
+            MenuUpdate::UpToDate
+        };
         for child in &mut self.children {
-            ret = ret.combine(child.update(old_data, data, env));
+            ret = ret.combine(child.update(ctx, old_data, data, env));
         }
         ret
     }
@@ -765,8 +922,8 @@ impl<T: Data> MenuVisitor<T> for MenuEntry<T> {
         self.inner.activate(ctx, id, data, env);
     }
 
-    fn update(&mut self, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
-        self.inner.update(old_data, data, env)
+    fn update(&mut self, ctx: &mut MenuUpdateCtx, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
+        self.inner.update(ctx, old_data, data, env)
     }
 
     fn refresh(&mut self, ctx: &mut MenuBuildCtx, data: &T, env: &Env) {
@@ -783,19 +940,31 @@ impl<T: Data> MenuVisitor<T> for MenuItem<T> {
         }
     }
 
-    fn update(&mut self, _old_data: &T, data: &T, env: &Env) -> MenuUpdate {
-        if self.resolve(data, env) {
-            MenuUpdate::NeedsRefresh
-        } else {
-            MenuUpdate::UpToDate
+    fn update(
+        &mut self,
+        ctx: &mut MenuUpdateCtx,
+        _old_data: &T,
+        data: &T,
+        env: &Env,
+    ) -> MenuUpdate {
+        let old_hotkey = self.old_state.as_ref().map(|state| state.hotkey.clone());
+        if !self.resolve(data, env) {
+            return MenuUpdate::UpToDate;
         }
+        let state = self.old_state.as_ref().unwrap();
+        // hotkeys are registered with the whole menu, so they can't be changed in place.
+        if old_hotkey.as_ref() != Some(&state.hotkey) {
+            return MenuUpdate::NeedsRefresh;
+        }
+        ctx.changed_items.push((self.id.as_u32(), state.clone()));
+        MenuUpdate::UpToDate
     }
 
     fn refresh(&mut self, ctx: &mut MenuBuildCtx, data: &T, env: &Env) {
         self.resolve(data, env);
         let state = self.old_state.as_ref().unwrap();
         ctx.add_item(
-            self.id.0.map(|x| x.get()).unwrap_or(0),
+            self.id.as_u32(),
             &state.title,
             state.hotkey.as_ref(),
             state.enabled,
@@ -807,7 +976,13 @@ impl<T: Data> MenuVisitor<T> for MenuItem<T> {
 impl<T: Data> MenuVisitor<T> for Separator {
     fn activate(&mut self, _ctx: &mut MenuEventCtx, _id: MenuItemId, _data: &mut T, _env: &Env) {}
 
-    fn update(&mut self, _old_data: &T, _data: &T, _env: &Env) -> MenuUpdate {
+    fn update(
+        &mut self,
+        _ctx: &mut MenuUpdateCtx,
+        _old_data: &T,
+        _data: &T,
+        _env: &Env,
+    ) -> MenuUpdate {
         MenuUpdate::UpToDate
     }
     fn refresh(&mut self, ctx: &mut MenuBuildCtx, _data: &T, _env: &Env) {
@@ -815,13 +990,48 @@ impl<T: Data> MenuVisitor<T> for Separator {
     }
 }
 
+impl<T: Data> From<DynamicEntries<T>> for MenuEntry<T> {
+    fn from(d: DynamicEntries<T>) -> MenuEntry<T> {
+        MenuEntry { inner: Box::new(d) }
+    }
+}
+
+impl<T: Data> MenuVisitor<T> for DynamicEntries<T> {
+    fn activate(&mut self, ctx: &mut MenuEventCtx, id: MenuItemId, data: &mut T, env: &Env) {
+        for entry in self.entries.iter_mut().flatten() {
+            entry.activate(ctx, id, data, env);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut MenuUpdateCtx, old_data: &T, data: &T, env: &Env) -> MenuUpdate {
+        if self.entries.is_none() || (self.rebuild_on)(old_data, data, env) {
+            // The new entries are resolved when the menu is refreshed.
+            self.entries = Some((self.build)(data, env));
+            return MenuUpdate::NeedsRefresh;
+        }
+        let mut ret = MenuUpdate::UpToDate;
+        for entry in self.entries.iter_mut().flatten() {
+            ret = ret.combine(entry.update(ctx, old_data, data, env));
+        }
+        ret
+    }
+
+    fn refresh(&mut self, ctx: &mut MenuBuildCtx, data: &T, env: &Env) {
+        let build = &mut self.build;
+        let entries = self.entries.get_or_insert_with(|| build(data, env));
+        for entry in entries {
+            entry.refresh(ctx, data, env);
+        }
+    }
+}
+
 // The resolved state of a menu item.
-#[derive(PartialEq)]
-struct MenuItemState {
-    title: ArcStr,
-    hotkey: Option<HotKey>,
-    selected: bool,
-    enabled: bool,
+#[derive(Clone, PartialEq)]
+pub(crate) struct MenuItemState {
+    pub(crate) title: ArcStr,
+    pub(crate) hotkey: Option<HotKey>,
+    pub(crate) selected: bool,
+    pub(crate) enabled: bool,
 }
 
 /// Uniquely identifies a menu item.
@@ -837,4 +1047,121 @@ impl MenuItemId {
     pub(crate) fn new(id: u32) -> MenuItemId {
         MenuItemId(NonZeroU32::new(id))
     }
+
+    fn as_u32(self) -> u32 {
+        self.0.map(|x| x.get()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use test_env_log::test;
+
+    #[test]
+    fn app_menu_is_customized_per_window() {
+        let built = Rc::new(Cell::new(0));
+        let customized = Rc::new(RefCell::new(Vec::new()));
+        let app_menu = {
+            let built = built.clone();
+            AppMenu::new(move |_, _: &u32, _| {
+                built.set(built.get() + 1);
+                Menu::empty().entry(MenuItem::new("Quit"))
+            })
+        };
+        let env = Env::default();
+
+        let window = WindowId::next();
+        let mut customized_manager = {
+            let customized = customized.clone();
+            app_menu.manager(Some(Box::new(move |menu, id, _, _| {
+                customized.borrow_mut().push(id);
+                menu.entry(MenuItem::new("Close document"))
+            })))
+        };
+        customized_manager.initialize(Some(window), &0, &env);
+        assert_eq!(built.get(), 1);
+        assert_eq!(*customized.borrow(), vec![window]);
+
+        // the menu for when there are no windows, and windows without
+        // additions, only get the shared menu.
+        app_menu
+            .manager(None)
+            .initialize(Some(WindowId::next()), &0, &env);
+        app_menu.manager(None).initialize(None, &0, &env);
+        assert_eq!(built.get(), 3);
+        assert_eq!(customized.borrow().len(), 1);
+    }
+
+    #[test]
+    fn dynamic_entries_rebuild_on_their_own() {
+        let menu_built = Rc::new(Cell::new(0));
+        let entries_built = Rc::new(Cell::new(0));
+        let mut manager = {
+            let menu_built = menu_built.clone();
+            let entries_built = entries_built.clone();
+            MenuManager::new(move |_, _: &u32, _| {
+                menu_built.set(menu_built.get() + 1);
+                let entries_built = entries_built.clone();
+                Menu::empty().entry(MenuItem::new("Open")).dynamic_entries(
+                    // the entries only depend on whether the count is even.
+                    |old, new, _| old % 2 != new % 2,
+                    move |count, _| {
+                        entries_built.set(entries_built.get() + 1);
+                        (0..*count)
+                            .map(|_| MenuItem::new("Recent").into())
+                            .collect()
+                    },
+                )
+            })
+        };
+        let env = Env::default();
+
+        manager.initialize(None, &1, &env);
+        assert_eq!((menu_built.get(), entries_built.get()), (1, 1));
+
+        assert!(manager.update_items(None, &3, &env).is_none());
+        assert_eq!((menu_built.get(), entries_built.get()), (1, 1));
+
+        assert!(manager.update_items(None, &4, &env).is_some());
+        assert_eq!((menu_built.get(), entries_built.get()), (1, 2));
+    }
+
+    #[test]
+    fn changed_items_are_updated_in_place() {
+        let mut manager = MenuManager::new(|_, _: &u32, _| {
+            Menu::empty()
+                .entry(
+                    MenuItem::new(|count: &u32, _: &Env| format!("{} unread", count))
+                        .enabled_if(|count, _| *count > 0),
+                )
+                .entry(MenuItem::new("Mark all read").dynamic_hotkey(|count, _| {
+                    if *count > 9 {
+                        Some(HotKey::new(RawMods::Ctrl, "m"))
+                    } else {
+                        None
+                    }
+                }))
+        });
+        let env = Env::default();
+        manager.initialize(None, &0, &env);
+
+        match manager.update_items(None, &1, &env) {
+            Some(MenuChanges::Items(items)) => {
+                assert_eq!(items.len(), 1);
+                let (_, state) = &items[0];
+                assert_eq!(&*state.title, "1 unread");
+                assert!(state.enabled);
+            }
+            _ => panic!("expected only the first item to change"),
+        }
+        assert!(manager.update_items(None, &1, &env).is_none());
+
+        // hotkeys are registered with the whole menu, so it is replaced.
+        assert!(matches!(
+            manager.update_items(None, &10, &env),
+            Some(MenuChanges::Replace(_))
+        ));
+    }
 }
@@ -0,0 +1,768 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keyboard and pointer interaction for menus that are drawn inside a window.
+
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::{KbKey, KeyEvent};
+
+/// How long the pointer has to rest on an item before its submenu opens, or
+/// before an open submenu is replaced by that of another item.
+pub const SUBMENU_DELAY: Duration = Duration::from_millis(250);
+
+/// How long after the last key press typing continues a type-ahead search,
+/// instead of starting a new one.
+pub const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// An entry in a menu driven by [`MenuNavigation`].
+///
+/// This only describes what the navigation needs to know; the widget that
+/// draws the menu keeps whatever else it needs (icons, hotkeys, callbacks)
+/// in parallel to these.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavItem {
+    title: String,
+    /// The byte offset of the mnemonic in `title`.
+    mnemonic: Option<usize>,
+    enabled: bool,
+    separator: bool,
+    children: Option<Vec<NavItem>>,
+}
+
+/// What a widget should do after passing an input to [`MenuNavigation`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuAction {
+    /// The input was not used by the menu, and can be handled elsewhere.
+    Ignored,
+    /// The highlighted item, the open submenus or the mnemonics changed;
+    /// the menu should be repainted.
+    Changed,
+    /// The item at this path was chosen; the first index is in the root menu.
+    ///
+    /// The navigation has been reset.
+    Activate(Vec<usize>),
+    /// The user closed the menu without choosing anything.
+    ///
+    /// The navigation has been reset.
+    Dismiss,
+}
+
+/// The interaction state of a menu that is drawn inside a window.
+///
+/// This implements the parts of menu behaviour that are the same for a menu
+/// bar, a context menu, or the list of a dropdown:
+///
+/// - the arrow keys move the highlight and open and close submenus; in a menu
+///   bar, left and right move between the menus of the bar;
+/// - hovering an item opens its submenu after [`SUBMENU_DELAY`];
+/// - pressing Alt shows the mnemonics, and in a menu bar, tapping it on its own
+///   activates the bar;
+/// - typing a mnemonic chooses its item, and typing anything else searches
+///   the titles of the current menu.
+///
+/// The owning widget forwards its key and mouse events, and acts on the
+/// returned [`MenuAction`]. The submenu delay needs a timer: after each input,
+/// the widget should request one for [`next_deadline`] and call [`tick`] when
+/// it fires.
+///
+/// [`next_deadline`]: MenuNavigation::next_deadline
+/// [`tick`]: MenuNavigation::tick
+#[derive(Debug, Clone)]
+pub struct MenuNavigation {
+    items: Vec<NavItem>,
+    bar: bool,
+    /// The highlighted item of each open menu, starting with the root.
+    open: Vec<Option<usize>>,
+    show_mnemonics: bool,
+    /// Set while Alt is held, until another key is pressed.
+    alt_alone: bool,
+    /// A hover that is applied when the submenu delay has passed.
+    pending: Option<(usize, Option<usize>, Instant)>,
+    type_ahead: String,
+    type_ahead_time: Option<Instant>,
+}
+
+impl NavItem {
+    /// Create a new item.
+    ///
+    /// A `&` in the title marks the following character as the mnemonic of
+    /// the item; use `&&` for a literal ampersand.
+    pub fn new(title: &str) -> NavItem {
+        let mut text = String::with_capacity(title.len());
+        let mut mnemonic = None;
+        let mut chars = title.chars();
+        while let Some(c) = chars.next() {
+            if c == '&' {
+                match chars.next() {
+                    Some('&') => text.push('&'),
+                    Some(c) => {
+                        if mnemonic.is_none() {
+                            mnemonic = Some(text.len());
+                        }
+                        text.push(c);
+                    }
+                    None => (),
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        NavItem {
+            title: text,
+            mnemonic,
+            enabled: true,
+            separator: false,
+            children: None,
+        }
+    }
+
+    /// Create a separator, which can never be highlighted.
+    pub fn separator() -> NavItem {
+        NavItem {
+            separator: true,
+            ..NavItem::new("")
+        }
+    }
+
+    /// Builder-style method to set whether the item can be chosen.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Builder-style method to make this item open a submenu.
+    pub fn submenu(mut self, children: Vec<NavItem>) -> Self {
+        self.children = Some(children);
+        self
+    }
+
+    /// The title, without the mnemonic marker.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The range of `title` that should be underlined when mnemonics are shown.
+    pub fn mnemonic_range(&self) -> Option<Range<usize>> {
+        let start = self.mnemonic?;
+        let len = self.title[start..].chars().next().map_or(0, char::len_utf8);
+        Some(start..start + len)
+    }
+
+    /// Returns `true` if the item can be chosen.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns `true` if this is a separator.
+    pub fn is_separator(&self) -> bool {
+        self.separator
+    }
+
+    /// The items of the submenu, if this item has one.
+    pub fn children(&self) -> Option<&[NavItem]> {
+        self.children.as_deref()
+    }
+
+    fn selectable(&self) -> bool {
+        self.enabled && !self.separator
+    }
+
+    fn has_mnemonic(&self, lowercase: &str) -> bool {
+        self.mnemonic_range()
+            .map(|range| self.title[range].to_lowercase() == lowercase)
+            .unwrap_or(false)
+    }
+}
+
+impl MenuNavigation {
+    /// The navigation for a menu bar, whose root menu is laid out horizontally
+    /// and whose items open the actual menus.
+    pub fn bar(items: Vec<NavItem>) -> MenuNavigation {
+        MenuNavigation::new(items, true)
+    }
+
+    /// The navigation for a popup, such as a context menu or the list of a dropdown.
+    pub fn popup(items: Vec<NavItem>) -> MenuNavigation {
+        MenuNavigation::new(items, false)
+    }
+
+    fn new(items: Vec<NavItem>, bar: bool) -> MenuNavigation {
+        MenuNavigation {
+            items,
+            bar,
+            open: vec![None],
+            show_mnemonics: false,
+            alt_alone: false,
+            pending: None,
+            type_ahead: String::new(),
+            type_ahead_time: None,
+        }
+    }
+
+    /// The items of the root menu.
+    pub fn items(&self) -> &[NavItem] {
+        &self.items
+    }
+
+    /// Replace the items, and reset the navigation.
+    pub fn set_items(&mut self, items: Vec<NavItem>) {
+        self.items = items;
+        self.reset();
+    }
+
+    /// Close all submenus and forget the highlight, for instance when the
+    /// owning widget loses focus.
+    pub fn reset(&mut self) {
+        self.open = vec![None];
+        self.show_mnemonics = false;
+        self.alt_alone = false;
+        self.pending = None;
+        self.type_ahead.clear();
+        self.type_ahead_time = None;
+    }
+
+    /// The open menus, starting with the root, each with its highlighted item.
+    pub fn open_menus(&self) -> Vec<(&[NavItem], Option<usize>)> {
+        (0..self.open.len())
+            .map(|level| (self.menu_at(level), self.open[level]))
+            .collect()
+    }
+
+    /// The indices of the highlighted items, from the root to the deepest open menu.
+    pub fn highlighted_path(&self) -> Vec<usize> {
+        self.open.iter().flatten().copied().collect()
+    }
+
+    /// Returns `true` if mnemonics should be underlined.
+    pub fn show_mnemonics(&self) -> bool {
+        self.show_mnemonics
+    }
+
+    /// Returns `true` if anything is highlighted.
+    ///
+    /// For a menu bar, this means that the bar has keyboard focus.
+    pub fn is_active(&self) -> bool {
+        self.open[0].is_some()
+    }
+
+    /// When [`tick`] should be called next, if a submenu is about to open.
+    ///
+    /// [`tick`]: MenuNavigation::tick
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, _, deadline)| deadline)
+    }
+
+    /// Apply a pending hover, if its delay has passed.
+    pub fn tick(&mut self, now: Instant) -> MenuAction {
+        match self.pending {
+            Some((level, index, deadline)) if deadline <= now => {
+                self.pending = None;
+                self.highlight(level, index);
+                self.open_submenu(false);
+                MenuAction::Changed
+            }
+            _ => MenuAction::Ignored,
+        }
+    }
+
+    /// Handle a key press.
+    pub fn key_down(&mut self, event: &KeyEvent, now: Instant) -> MenuAction {
+        if event.key == KbKey::Alt {
+            if !event.repeat {
+                self.alt_alone = true;
+            }
+            if self.show_mnemonics {
+                return MenuAction::Ignored;
+            }
+            self.show_mnemonics = true;
+            return MenuAction::Changed;
+        }
+        self.alt_alone = false;
+        if event.mods.ctrl() || event.mods.meta() {
+            return MenuAction::Ignored;
+        }
+
+        if self.bar && !self.is_active() {
+            // an inactive bar only reacts to Alt + mnemonic
+            return match &event.key {
+                KbKey::Character(c) if event.mods.alt() => self.choose_mnemonic(0, c),
+                _ => MenuAction::Ignored,
+            };
+        }
+
+        let level = self.open.len() - 1;
+        let in_bar = self.bar && level == 0;
+        match &event.key {
+            KbKey::ArrowDown if in_bar => {
+                if self.open_submenu(true) {
+                    MenuAction::Changed
+                } else {
+                    MenuAction::Ignored
+                }
+            }
+            KbKey::ArrowDown => self.move_highlight(level, true),
+            KbKey::ArrowUp if in_bar => MenuAction::Ignored,
+            KbKey::ArrowUp => self.move_highlight(level, false),
+            KbKey::ArrowRight if in_bar => self.move_highlight(0, true),
+            KbKey::ArrowLeft if in_bar => self.move_highlight(0, false),
+            KbKey::ArrowRight => {
+                if !self.open_submenu(true) && self.bar {
+                    self.open.truncate(1);
+                    self.move_highlight(0, true);
+                    self.open_submenu(true);
+                }
+                MenuAction::Changed
+            }
+            KbKey::ArrowLeft => {
+                if level > 1 || (level == 1 && !self.bar) {
+                    self.close_submenu()
+                } else if self.bar {
+                    self.open.truncate(1);
+                    self.move_highlight(0, false);
+                    self.open_submenu(true);
+                    MenuAction::Changed
+                } else {
+                    MenuAction::Ignored
+                }
+            }
+            KbKey::Home => {
+                self.open[level] = self.first_selectable(level, true);
+                MenuAction::Changed
+            }
+            KbKey::End => {
+                self.open[level] = self.first_selectable(level, false);
+                MenuAction::Changed
+            }
+            KbKey::Enter => self.activate_highlighted(),
+            KbKey::Character(c) if c == " " && !self.continues_type_ahead(now) => {
+                self.activate_highlighted()
+            }
+            KbKey::Escape if level > 0 => self.close_submenu(),
+            KbKey::Escape => {
+                self.reset();
+                MenuAction::Dismiss
+            }
+            KbKey::Character(c) => match self.choose_mnemonic(level, c) {
+                MenuAction::Ignored => self.type_ahead(level, c, now),
+                action => action,
+            },
+            _ => MenuAction::Ignored,
+        }
+    }
+
+    /// Handle a key release.
+    pub fn key_up(&mut self, event: &KeyEvent) -> MenuAction {
+        if event.key != KbKey::Alt {
+            return MenuAction::Ignored;
+        }
+        let alone = std::mem::replace(&mut self.alt_alone, false);
+        if !self.bar {
+            MenuAction::Ignored
+        } else if alone && self.is_active() {
+            self.reset();
+            MenuAction::Dismiss
+        } else if alone {
+            self.open[0] = self.first_selectable(0, true);
+            MenuAction::Changed
+        } else if !self.is_active() && self.show_mnemonics {
+            // Alt was used for a shortcut
+            self.show_mnemonics = false;
+            MenuAction::Changed
+        } else {
+            MenuAction::Ignored
+        }
+    }
+
+    /// Handle the pointer moving over an item of the open menu at `level`,
+    /// or over no item of it if `index` is `None`.
+    pub fn hover(&mut self, level: usize, index: Option<usize>, now: Instant) -> MenuAction {
+        if level >= self.open.len() || (self.bar && level == 0 && !self.is_active()) {
+            return MenuAction::Ignored;
+        }
+        let index = index.filter(|&idx| {
+            self.menu_at(level)
+                .get(idx)
+                .map(NavItem::selectable)
+                .unwrap_or(false)
+        });
+        let submenu_open = self.open.len() > level + 1;
+        if self.open[level] == index && (submenu_open || index.is_none()) {
+            // back on the item whose submenu is open
+            self.pending = None;
+            return MenuAction::Ignored;
+        }
+
+        if self.bar && level == 0 {
+            if index.is_none() {
+                return MenuAction::Ignored;
+            }
+            // once a menu of the bar is open, the others follow the pointer immediately
+            self.highlight(0, index);
+            self.open_submenu(false);
+            return MenuAction::Changed;
+        }
+        let has_submenu = index
+            .map(|idx| self.menu_at(level)[idx].children.is_some())
+            .unwrap_or(false);
+        if !submenu_open {
+            self.highlight(level, index);
+        }
+        self.pending = if submenu_open || has_submenu {
+            Some((level, index, now + SUBMENU_DELAY))
+        } else {
+            None
+        };
+        MenuAction::Changed
+    }
+
+    /// Handle a click on an item of the open menu at `level`.
+    pub fn click(&mut self, level: usize, index: usize) -> MenuAction {
+        if level >= self.open.len() {
+            return MenuAction::Ignored;
+        }
+        let item = match self.menu_at(level).get(index) {
+            Some(item) if item.selectable() => item,
+            _ => return MenuAction::Ignored,
+        };
+        if item.children.is_none() {
+            self.open.truncate(level + 1);
+            self.open[level] = Some(index);
+            return self.activate_highlighted();
+        }
+        if self.bar && level == 0 && self.open[0] == Some(index) && self.open.len() > 1 {
+            // clicking an open menu of the bar closes it
+            self.reset();
+            return MenuAction::Dismiss;
+        }
+        self.highlight(level, Some(index));
+        self.open_submenu(false);
+        MenuAction::Changed
+    }
+
+    fn menu_at(&self, level: usize) -> &[NavItem] {
+        let mut menu = &self.items[..];
+        for idx in &self.open[..level] {
+            menu = idx
+                .and_then(|idx| menu[idx].children.as_deref())
+                .unwrap_or_default();
+        }
+        menu
+    }
+
+    /// Highlight an item, closing any submenus below `level`.
+    fn highlight(&mut self, level: usize, index: Option<usize>) {
+        self.open.truncate(level + 1);
+        self.open[level] = index;
+    }
+
+    fn first_selectable(&self, level: usize, forward: bool) -> Option<usize> {
+        let menu = self.menu_at(level);
+        if forward {
+            menu.iter().position(NavItem::selectable)
+        } else {
+            menu.iter().rposition(NavItem::selectable)
+        }
+    }
+
+    fn move_highlight(&mut self, level: usize, forward: bool) -> MenuAction {
+        let menu = self.menu_at(level);
+        let len = menu.len();
+        let next = match self.open[level] {
+            None => self.first_selectable(level, forward),
+            Some(current) => (1..=len)
+                .map(|step| {
+                    if forward {
+                        (current + step) % len
+                    } else {
+                        (current + len - step) % len
+                    }
+                })
+                .find(|&idx| menu[idx].selectable()),
+        };
+        self.highlight(level, next);
+        MenuAction::Changed
+    }
+
+    /// Open the submenu of the highlighted item in the deepest open menu.
+    fn open_submenu(&mut self, highlight_first: bool) -> bool {
+        let level = self.open.len() - 1;
+        let has_children = self.open[level]
+            .map(|idx| self.menu_at(level)[idx].children.is_some())
+            .unwrap_or(false);
+        if !has_children {
+            return false;
+        }
+        self.open.push(None);
+        if highlight_first {
+            self.open[level + 1] = self.first_selectable(level + 1, true);
+        }
+        true
+    }
+
+    fn close_submenu(&mut self) -> MenuAction {
+        self.open.pop();
+        self.pending = None;
+        MenuAction::Changed
+    }
+
+    fn activate_highlighted(&mut self) -> MenuAction {
+        if self.open_submenu(true) {
+            return MenuAction::Changed;
+        }
+        let level = self.open.len() - 1;
+        if self.open[level].is_none() {
+            return MenuAction::Ignored;
+        }
+        let path = self.highlighted_path();
+        self.reset();
+        MenuAction::Activate(path)
+    }
+
+    fn choose_mnemonic(&mut self, level: usize, c: &str) -> MenuAction {
+        let c = c.to_lowercase();
+        match self
+            .menu_at(level)
+            .iter()
+            .position(|item| item.selectable() && item.has_mnemonic(&c))
+        {
+            Some(idx) => {
+                self.highlight(level, Some(idx));
+                self.show_mnemonics = true;
+                self.activate_highlighted()
+            }
+            None => MenuAction::Ignored,
+        }
+    }
+
+    fn continues_type_ahead(&self, now: Instant) -> bool {
+        !self.type_ahead.is_empty()
+            && self
+                .type_ahead_time
+                .map(|time| now.saturating_duration_since(time) < TYPE_AHEAD_TIMEOUT)
+                .unwrap_or(false)
+    }
+
+    /// Highlight the next item whose title starts with what was typed.
+    fn type_ahead(&mut self, level: usize, c: &str, now: Instant) -> MenuAction {
+        if !self.continues_type_ahead(now) {
+            self.type_ahead.clear();
+        }
+        self.type_ahead_time = Some(now);
+        self.type_ahead.push_str(&c.to_lowercase());
+
+        // typing the same letter again moves on to the next item starting with it
+        let mut chars = self.type_ahead.chars();
+        let first = chars.next();
+        let repeated = chars.all(|c| Some(c) == first);
+        let prefix = if repeated {
+            first.map(String::from).unwrap_or_default()
+        } else {
+            self.type_ahead.clone()
+        };
+
+        let menu = self.menu_at(level);
+        let len = menu.len();
+        let start = match self.open[level] {
+            Some(current) if repeated => current + 1,
+            Some(current) => current,
+            None => 0,
+        };
+        let found = (0..len).map(|step| (start + step) % len).find(|&idx| {
+            menu[idx].selectable() && menu[idx].title.to_lowercase().starts_with(&prefix)
+        });
+        match found {
+            Some(idx) => {
+                self.highlight(level, Some(idx));
+                MenuAction::Changed
+            }
+            None => MenuAction::Ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Modifiers;
+    use test_env_log::test;
+
+    fn key(key: impl crate::shell::IntoKey) -> KeyEvent {
+        KeyEvent::for_test(Modifiers::default(), key)
+    }
+
+    fn file_menu() -> Vec<NavItem> {
+        vec![
+            NavItem::new("&New"),
+            NavItem::new("&Open"),
+            NavItem::separator(),
+            NavItem::new("&Recent").submenu(vec![NavItem::new("a.txt"), NavItem::new("b.txt")]),
+            NavItem::new("&Save").enabled(false),
+            NavItem::new("&Quit"),
+        ]
+    }
+
+    fn menu_bar() -> MenuNavigation {
+        MenuNavigation::bar(vec![
+            NavItem::new("&File").submenu(file_menu()),
+            NavItem::new("&Edit").submenu(vec![NavItem::new("Cu&t"), NavItem::new("&Copy")]),
+        ])
+    }
+
+    #[test]
+    fn mnemonics() {
+        let item = NavItem::new("Save && &Quit");
+        assert_eq!(item.title(), "Save & Quit");
+        assert_eq!(item.mnemonic_range(), Some(7..8));
+        assert_eq!(NavItem::new("&&").mnemonic_range(), None);
+        assert_eq!(NavItem::new("&Über").mnemonic_range(), Some(0..2));
+    }
+
+    #[test]
+    fn popup_arrows() {
+        let now = Instant::now();
+        let mut nav = MenuNavigation::popup(file_menu());
+        nav.key_down(&key(KbKey::ArrowDown), now);
+        assert_eq!(nav.highlighted_path(), vec![0]);
+        nav.key_down(&key(KbKey::ArrowUp), now);
+        assert_eq!(nav.highlighted_path(), vec![5]);
+        // skips the disabled item and the separator
+        nav.key_down(&key(KbKey::ArrowUp), now);
+        assert_eq!(nav.highlighted_path(), vec![3]);
+        nav.key_down(&key(KbKey::ArrowUp), now);
+        assert_eq!(nav.highlighted_path(), vec![1]);
+        nav.key_down(&key(KbKey::ArrowDown), now);
+
+        nav.key_down(&key(KbKey::ArrowRight), now);
+        assert_eq!(nav.highlighted_path(), vec![3, 0]);
+        nav.key_down(&key(KbKey::ArrowLeft), now);
+        assert_eq!(nav.highlighted_path(), vec![3]);
+        nav.key_down(&key(KbKey::Enter), now);
+        nav.key_down(&key(KbKey::ArrowDown), now);
+        assert_eq!(
+            nav.key_down(&key(KbKey::Enter), now),
+            MenuAction::Activate(vec![3, 1])
+        );
+        assert!(!nav.is_active());
+        assert_eq!(nav.key_down(&key(KbKey::Escape), now), MenuAction::Dismiss);
+    }
+
+    #[test]
+    fn bar_keyboard() {
+        let now = Instant::now();
+        let mut nav = menu_bar();
+        assert_eq!(
+            nav.key_down(&key(KbKey::ArrowDown), now),
+            MenuAction::Ignored
+        );
+
+        // tapping alt activates the bar
+        assert_eq!(nav.key_down(&key(KbKey::Alt), now), MenuAction::Changed);
+        assert!(nav.show_mnemonics());
+        assert_eq!(nav.key_up(&key(KbKey::Alt)), MenuAction::Changed);
+        assert_eq!(nav.highlighted_path(), vec![0]);
+
+        nav.key_down(&key(KbKey::ArrowRight), now);
+        nav.key_down(&key(KbKey::ArrowDown), now);
+        assert_eq!(nav.highlighted_path(), vec![1, 0]);
+        // moving right from an item without a submenu opens the next menu of the bar
+        nav.key_down(&key(KbKey::ArrowRight), now);
+        assert_eq!(nav.highlighted_path(), vec![0, 0]);
+        nav.key_down(&key(KbKey::ArrowLeft), now);
+        assert_eq!(nav.highlighted_path(), vec![1, 0]);
+
+        nav.key_down(&key(KbKey::Escape), now);
+        assert_eq!(nav.highlighted_path(), vec![1]);
+        assert_eq!(nav.key_down(&key(KbKey::Escape), now), MenuAction::Dismiss);
+        assert!(!nav.show_mnemonics());
+
+        // alt + mnemonic opens a menu, a mnemonic in it chooses an item
+        let alt_f = KeyEvent::for_test(Modifiers::ALT, "f");
+        nav.key_down(&key(KbKey::Alt), now);
+        assert_eq!(nav.key_down(&alt_f, now), MenuAction::Changed);
+        nav.key_up(&key(KbKey::Alt));
+        assert_eq!(nav.highlighted_path(), vec![0, 0]);
+        assert_eq!(nav.key_down(&key("s"), now), MenuAction::Ignored);
+        assert_eq!(
+            nav.key_down(&key("Q"), now),
+            MenuAction::Activate(vec![0, 5])
+        );
+    }
+
+    #[test]
+    fn type_ahead() {
+        let now = Instant::now();
+        let mut nav = MenuNavigation::popup(vec![
+            NavItem::new("Paste"),
+            NavItem::new("Paste Special"),
+            NavItem::new("Print"),
+        ]);
+        nav.key_down(&key("p"), now);
+        assert_eq!(nav.highlighted_path(), vec![0]);
+        nav.key_down(&key("r"), now);
+        assert_eq!(nav.highlighted_path(), vec![2]);
+
+        // typing the same letter again cycles through the items starting with it
+        let later = now + TYPE_AHEAD_TIMEOUT * 2;
+        nav.key_down(&key("p"), later);
+        assert_eq!(nav.highlighted_path(), vec![0]);
+        nav.key_down(&key("p"), later);
+        assert_eq!(nav.highlighted_path(), vec![1]);
+
+        // a space continues the search instead of choosing the item
+        let later = later + TYPE_AHEAD_TIMEOUT * 2;
+        for c in "paste s".chars() {
+            nav.key_down(&key(&*c.to_string()), later);
+        }
+        assert_eq!(nav.highlighted_path(), vec![1]);
+        nav.key_down(&key(KbKey::Escape), later);
+
+        let later = later + TYPE_AHEAD_TIMEOUT * 2;
+        nav.key_down(&key(KbKey::ArrowDown), later);
+        assert_eq!(
+            nav.key_down(&key(" "), later),
+            MenuAction::Activate(vec![0])
+        );
+    }
+
+    #[test]
+    fn hover_delay() {
+        let now = Instant::now();
+        let mut nav = MenuNavigation::popup(file_menu());
+        nav.hover(0, Some(3), now);
+        assert_eq!(nav.highlighted_path(), vec![3]);
+        assert_eq!(nav.open_menus().len(), 1);
+        assert_eq!(nav.next_deadline(), Some(now + SUBMENU_DELAY));
+        assert_eq!(nav.tick(now), MenuAction::Ignored);
+        assert_eq!(nav.tick(now + SUBMENU_DELAY), MenuAction::Changed);
+        assert_eq!(nav.open_menus().len(), 2);
+
+        // passing over another item on the way into the submenu keeps it open
+        nav.hover(0, Some(4), now);
+        nav.hover(0, Some(5), now);
+        nav.hover(1, Some(1), now);
+        assert_eq!(nav.next_deadline(), None);
+        assert_eq!(nav.highlighted_path(), vec![3, 1]);
+
+        // resting on another item closes it
+        nav.hover(0, Some(1), now);
+        nav.tick(now + SUBMENU_DELAY);
+        assert_eq!(nav.highlighted_path(), vec![1]);
+        assert_eq!(nav.open_menus().len(), 1);
+
+        assert_eq!(nav.click(0, 4), MenuAction::Ignored);
+        assert_eq!(nav.click(0, 0), MenuAction::Activate(vec![0]));
+    }
+}
@@ -62,6 +62,31 @@ pub mod common {
             item.hotkey(SysMods::CmdShift, "Z")
         }
     }
+
+    /// The 'Zoom In' menu item, which zooms the UI of the whole application.
+    ///
+    /// See [`UI_ZOOM`].
+    ///
+    /// [`UI_ZOOM`]: crate::theme::UI_ZOOM
+    pub fn zoom_in<T: Data>() -> MenuItem<T> {
+        MenuItem::new(LocalizedString::new("common-menu-zoom-in"))
+            .command(commands::ZOOM_IN)
+            .hotkey(SysMods::Cmd, "=")
+    }
+
+    /// The 'Zoom Out' menu item.
+    pub fn zoom_out<T: Data>() -> MenuItem<T> {
+        MenuItem::new(LocalizedString::new("common-menu-zoom-out"))
+            .command(commands::ZOOM_OUT)
+            .hotkey(SysMods::Cmd, "-")
+    }
+
+    /// The 'Actual Size' menu item, which resets the zoom.
+    pub fn reset_zoom<T: Data>() -> MenuItem<T> {
+        MenuItem::new(LocalizedString::new("common-menu-reset-zoom"))
+            .command(commands::RESET_ZOOM)
+            .hotkey(SysMods::Cmd, "0")
+    }
 }
 
 /// Windows.
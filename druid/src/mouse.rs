@@ -14,8 +14,15 @@
 
 //! The mousey bits
 
+use std::time::Duration;
+
+use instant::Instant;
+
 use crate::kurbo::{Point, Vec2};
-use crate::{Cursor, Data, Modifiers, MouseButton, MouseButtons};
+use crate::{theme, Cursor, Data, Env, Modifiers, MouseButton, MouseButtons, PointerType};
+
+/// The highest click count; the click after a triple click starts over.
+const MAX_CLICK_COUNT: u8 = 3;
 
 /// The state of the mouse for a click, mouse-up, move, or wheel event.
 ///
@@ -46,6 +53,14 @@ pub struct MouseEvent {
     pub mods: Modifiers,
     /// The number of mouse clicks associated with this event. This will always
     /// be `0` for a mouse-up and mouse-move events.
+    ///
+    /// Clicks with the same button count as one multi-click if each one comes
+    /// within [`DOUBLE_CLICK_INTERVAL`] of the one before, and no further than
+    /// [`DOUBLE_CLICK_DISTANCE`] away from it. The count goes up to `3`, for a
+    /// triple click, and starts again at `1` after that.
+    ///
+    /// [`DOUBLE_CLICK_INTERVAL`]: crate::theme::DOUBLE_CLICK_INTERVAL
+    /// [`DOUBLE_CLICK_DISTANCE`]: crate::theme::DOUBLE_CLICK_DISTANCE
     pub count: u8,
     /// Focus is `true` on macOS when the mouse-down event (or its companion mouse-up event)
     /// with `MouseButton::Left` was the event that caused the window to gain focus.
@@ -67,8 +82,38 @@ pub struct MouseEvent {
     /// move on scrolling. This polarity is consistent with the
     /// deltaX and deltaY values in a web [WheelEvent].
     ///
+    /// This is the movement as the device reported it; widgets that scroll
+    /// should usually use [`scroll_delta`] instead.
+    ///
     /// [WheelEvent]: https://w3c.github.io/uievents/#event-type-wheel
+    /// [`scroll_delta`]: MouseEvent::scroll_delta
     pub wheel_delta: Vec2,
+    /// The kind of device that caused the event.
+    ///
+    /// See [`theme::INPUT_MODE`] for the kind of input that the user
+    /// interacted with most recently.
+    pub pointer_type: PointerType,
+}
+
+impl MouseEvent {
+    /// The amount to scroll by for a wheel event.
+    ///
+    /// This is the [`wheel_delta`], except that vertical scrolling with shift
+    /// held down becomes horizontal scrolling if [`SCROLL_SHIFT_HORIZONTAL`]
+    /// is set in the `env`.
+    ///
+    /// [`wheel_delta`]: MouseEvent::wheel_delta
+    /// [`SCROLL_SHIFT_HORIZONTAL`]: crate::theme::SCROLL_SHIFT_HORIZONTAL
+    pub fn scroll_delta(&self, env: &Env) -> Vec2 {
+        let delta = self.wheel_delta;
+        // a delta that is already horizontal, from a tilt wheel or from a platform that applies
+        // the convention itself (like macOS), is left alone.
+        if self.mods.shift() && delta.x == 0.0 && env.get(theme::SCROLL_SHIFT_HORIZONTAL) {
+            Vec2::new(delta.y, 0.0)
+        } else {
+            delta
+        }
+    }
 }
 
 impl From<druid_shell::MouseEvent> for MouseEvent {
@@ -81,6 +126,7 @@ impl From<druid_shell::MouseEvent> for MouseEvent {
             focus,
             button,
             wheel_delta,
+            pointer_type,
         } = src;
         MouseEvent {
             pos,
@@ -91,6 +137,7 @@ impl From<druid_shell::MouseEvent> for MouseEvent {
             focus,
             button,
             wheel_delta,
+            pointer_type,
         }
     }
 }
@@ -100,3 +147,106 @@ impl Data for Cursor {
         self == other
     }
 }
+
+/// Counts the clicks of double and triple clicks.
+#[derive(Debug, Default)]
+pub(crate) struct ClickCounter {
+    /// The time, position and button of the last click.
+    last: Option<(Instant, Point, MouseButton)>,
+    count: u8,
+}
+
+impl ClickCounter {
+    /// Returns the click count for a click with `button` at `pos`, at `time`.
+    pub fn count_for_click(
+        &mut self,
+        pos: Point,
+        button: MouseButton,
+        time: Instant,
+        max_interval: Duration,
+        max_distance: f64,
+    ) -> u8 {
+        let continues = match self.last {
+            Some((last_time, last_pos, last_button)) => {
+                button == last_button
+                    && time >= last_time
+                    && time - last_time <= max_interval
+                    && last_pos.distance(pos) <= max_distance
+            }
+            None => false,
+        };
+        self.count = if continues && self.count < MAX_CLICK_COUNT {
+            self.count + 1
+        } else {
+            1
+        };
+        self.last = Some((time, pos, button));
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn click_counts() {
+        let interval = Duration::from_millis(500);
+        let mut counter = ClickCounter::default();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut click = |pos: (f64, f64), button, millis| {
+            counter.count_for_click(pos.into(), button, at(millis), interval, 4.0)
+        };
+
+        assert_eq!(click((10., 10.), MouseButton::Left, 0), 1);
+        assert_eq!(click((11., 10.), MouseButton::Left, 300), 2);
+        assert_eq!(click((12., 11.), MouseButton::Left, 600), 3);
+        // the count starts over after a triple click
+        assert_eq!(click((12., 11.), MouseButton::Left, 700), 1);
+        // too slow
+        assert_eq!(click((12., 11.), MouseButton::Left, 1300), 1);
+        // too far
+        assert_eq!(click((30., 11.), MouseButton::Left, 1400), 1);
+        // another button
+        assert_eq!(click((30., 11.), MouseButton::Right, 1500), 1);
+        assert_eq!(click((30., 11.), MouseButton::Right, 1600), 2);
+    }
+
+    #[test]
+    fn shift_scrolls_horizontally() {
+        let wheel = |delta: (f64, f64), mods| MouseEvent {
+            pos: Point::ZERO,
+            window_pos: Point::ZERO,
+            buttons: MouseButtons::new(),
+            mods,
+            count: 0,
+            focus: false,
+            button: MouseButton::None,
+            wheel_delta: delta.into(),
+            pointer_type: PointerType::Mouse,
+        };
+        let shift = Modifiers::SHIFT;
+        let env = Env::default();
+
+        assert_eq!(
+            wheel((0., 120.), Modifiers::empty()).scroll_delta(&env),
+            Vec2::new(0., 120.)
+        );
+        assert_eq!(
+            wheel((0., 120.), shift).scroll_delta(&env),
+            Vec2::new(120., 0.)
+        );
+        assert_eq!(
+            wheel((-30., 0.), shift).scroll_delta(&env),
+            Vec2::new(-30., 0.)
+        );
+
+        let env = env.adding(theme::SCROLL_SHIFT_HORIZONTAL, false);
+        assert_eq!(
+            wheel((0., 120.), shift).scroll_delta(&env),
+            Vec2::new(0., 120.)
+        );
+    }
+}
@@ -0,0 +1,308 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A record of the widgets in a window, by id.
+
+use std::collections::HashMap;
+
+use crate::{Point, Rect, WidgetId, WindowId};
+
+/// What is known about a widget, as of the last layout of its window.
+///
+/// Focus and disabled state are kept up to date between layouts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WidgetInfo {
+    /// The id of the widget.
+    pub id: WidgetId,
+    /// The type of the widget, as returned by [`Widget::type_name`].
+    ///
+    /// [`Widget::type_name`]: crate::Widget::type_name
+    pub type_name: &'static str,
+    /// The widget's parent, or `None` for the root widget of the window.
+    pub parent: Option<WidgetId>,
+    /// The widget's layout rect, in its parent's coordinate space.
+    pub layout_rect: Rect,
+    /// The widget's layout rect, in window coordinates.
+    ///
+    /// For a widget inside a scroll view, this takes the scroll offset into
+    /// account, so it may lie outside the visible part of the window.
+    pub window_rect: Rect,
+    /// Whether the widget has keyboard focus.
+    pub has_focus: bool,
+    /// Whether the widget is disabled, either explicitly or by an ancestor.
+    pub is_disabled: bool,
+    /// Whether any part of the widget is inside the viewports that enclose it.
+    ///
+    /// See [`LifeCycle::ViewportVisibilityChanged`].
+    ///
+    /// [`LifeCycle::ViewportVisibilityChanged`]: crate::LifeCycle::ViewportVisibilityChanged
+    pub is_visible: bool,
+}
+
+/// The widgets of a window, by id.
+///
+/// The registry is rebuilt whenever the layout of the window changes, and
+/// follows changes of focus and disabled state in between, so there is no
+/// need to keep track of the rects of other widgets manually.
+/// Widgets that are not shown, like the inactive pages of a [`Tabs`], are
+/// left out.
+///
+/// It is available from [`DelegateCtx::widget_registry`] and
+/// [`Harness::widget_registry`].
+///
+/// [`Tabs`]: crate::widget::Tabs
+/// [`DelegateCtx::widget_registry`]: crate::DelegateCtx::widget_registry
+/// [`Harness::widget_registry`]: crate::testing::Harness::widget_registry
+#[derive(Debug, Clone, Default)]
+pub struct WidgetRegistry {
+    widgets: HashMap<WidgetId, WidgetInfo>,
+}
+
+impl WidgetRegistry {
+    /// The information about a widget, if it is in the window.
+    pub fn get(&self, id: WidgetId) -> Option<&WidgetInfo> {
+        self.widgets.get(&id)
+    }
+
+    /// Returns `true` if the widget is in the window.
+    pub fn contains(&self, id: WidgetId) -> bool {
+        self.widgets.contains_key(&id)
+    }
+
+    /// The number of widgets in the window.
+    pub fn len(&self) -> usize {
+        self.widgets.len()
+    }
+
+    /// Returns `true` if the window has no widgets, which is the case until
+    /// its first layout.
+    pub fn is_empty(&self) -> bool {
+        self.widgets.is_empty()
+    }
+
+    /// All of the widgets in the window, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &WidgetInfo> {
+        self.widgets.values()
+    }
+
+    /// The widgets of type `W`.
+    pub fn of_type<W: 'static>(&self) -> impl Iterator<Item = &WidgetInfo> {
+        let type_name = std::any::type_name::<W>();
+        self.iter().filter(move |info| info.type_name == type_name)
+    }
+
+    /// The ancestors of a widget, starting with its parent.
+    pub fn ancestors(&self, id: WidgetId) -> impl Iterator<Item = &WidgetInfo> {
+        let parent = self.get(id).and_then(|info| info.parent);
+        std::iter::successors(parent.and_then(|id| self.get(id)), move |info| {
+            info.parent.and_then(|id| self.get(id))
+        })
+    }
+
+    /// The widget at `point`, in window coordinates, that is deepest in the tree.
+    ///
+    /// Widgets that overlap their siblings, such as the layers of a `ZStack`,
+    /// are not told apart; any of them may be returned.
+    pub fn widget_at(&self, point: Point) -> Option<&WidgetInfo> {
+        self.iter()
+            .filter(|info| info.window_rect.contains(point))
+            .max_by_key(|info| self.ancestors(info.id).count())
+    }
+
+    /// Of the `candidates`, the one that is nearest to `from` in `direction`,
+    /// by their window rects.
+    ///
+    /// This is how [`EventCtx::focus_in_direction`] finds the widget to focus,
+    /// with the focusable widgets as the candidates.
+    ///
+    /// [`EventCtx::focus_in_direction`]: crate::EventCtx::focus_in_direction
+    pub fn nearest_in_direction(
+        &self,
+        from: WidgetId,
+        direction: FocusDirection,
+        candidates: impl IntoIterator<Item = WidgetId>,
+    ) -> Option<WidgetId> {
+        let from_rect = self.get(from)?.window_rect;
+        candidates
+            .into_iter()
+            .filter(|id| *id != from)
+            .filter_map(|id| {
+                let distance = direction.distance(from_rect, self.get(id)?.window_rect)?;
+                Some((distance, id))
+            })
+            .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, id)| id)
+    }
+
+    pub(crate) fn insert(&mut self, info: WidgetInfo) {
+        self.widgets.insert(info.id, info);
+    }
+
+    /// The widgets that were visible in `previous`, but are not in this registry.
+    pub(crate) fn hidden_since(&self, previous: &WidgetRegistry) -> Vec<WidgetId> {
+        previous
+            .iter()
+            .filter(|info| info.is_visible && !self.contains(info.id))
+            .map(|info| info.id)
+            .collect()
+    }
+
+    pub(crate) fn set_focus(&mut self, old: Option<WidgetId>, new: Option<WidgetId>) {
+        for (id, has_focus) in old
+            .iter()
+            .map(|id| (id, false))
+            .chain(new.iter().map(|id| (id, true)))
+        {
+            if let Some(info) = self.widgets.get_mut(id) {
+                info.has_focus = has_focus;
+            }
+        }
+    }
+
+    pub(crate) fn set_disabled(&mut self, id: WidgetId, is_disabled: bool) {
+        if let Some(info) = self.widgets.get_mut(&id) {
+            info.is_disabled = is_disabled;
+        }
+    }
+}
+
+/// A direction to move focus in, with [`EventCtx::focus_in_direction`].
+///
+/// [`EventCtx::focus_in_direction`]: crate::EventCtx::focus_in_direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusDirection {
+    /// Towards the left edge of the window.
+    Left,
+    /// Towards the right edge of the window.
+    Right,
+    /// Towards the top of the window.
+    Up,
+    /// Towards the bottom of the window.
+    Down,
+}
+
+impl FocusDirection {
+    /// How far `to` is from `from` in this direction, or `None` if it is not
+    /// in this direction at all.
+    ///
+    /// Widgets that are out of line with `from` count as further away than
+    /// widgets that are just as far along the direction, but in line.
+    fn distance(self, from: Rect, to: Rect) -> Option<(f64, f64)> {
+        let (from_center, to_center) = (from.center(), to.center());
+        let (along, ahead, across, from_span, to_span) = match self {
+            FocusDirection::Left => (
+                from.x0 - to.x1,
+                to_center.x < from_center.x,
+                (from_center.y - to_center.y).abs(),
+                (from.y0, from.y1),
+                (to.y0, to.y1),
+            ),
+            FocusDirection::Right => (
+                to.x0 - from.x1,
+                to_center.x > from_center.x,
+                (from_center.y - to_center.y).abs(),
+                (from.y0, from.y1),
+                (to.y0, to.y1),
+            ),
+            FocusDirection::Up => (
+                from.y0 - to.y1,
+                to_center.y < from_center.y,
+                (from_center.x - to_center.x).abs(),
+                (from.x0, from.x1),
+                (to.x0, to.x1),
+            ),
+            FocusDirection::Down => (
+                to.y0 - from.y1,
+                to_center.y > from_center.y,
+                (from_center.x - to_center.x).abs(),
+                (from.x0, from.x1),
+                (to.x0, to.x1),
+            ),
+        };
+        if !ahead {
+            return None;
+        }
+        // the gap between the two widgets across the direction, if they don't overlap.
+        let gap = (from_span.0.max(to_span.0) - from_span.1.min(to_span.1)).max(0.0);
+        Some((along.max(0.0) + 2.0 * gap, across))
+    }
+}
+
+/// Access to the registries of the open windows.
+pub(crate) trait Registries {
+    fn registry(&self, window: WindowId) -> Option<&WidgetRegistry>;
+
+    /// The window that contains `widget`, and its information.
+    fn find(&self, widget: WidgetId) -> Option<(WindowId, &WidgetInfo)>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    fn info(id: WidgetId, parent: Option<WidgetId>, window_rect: Rect) -> WidgetInfo {
+        WidgetInfo {
+            id,
+            type_name: "test",
+            parent,
+            layout_rect: window_rect,
+            window_rect,
+            has_focus: false,
+            is_disabled: false,
+            is_visible: true,
+        }
+    }
+
+    #[test]
+    fn ancestors_and_hit_testing() {
+        let (root, row, leaf) = (WidgetId::next(), WidgetId::next(), WidgetId::next());
+        let mut registry = WidgetRegistry::default();
+        registry.insert(info(root, None, Rect::new(0., 0., 100., 100.)));
+        registry.insert(info(row, Some(root), Rect::new(0., 0., 100., 20.)));
+        registry.insert(info(leaf, Some(row), Rect::new(10., 0., 30., 20.)));
+
+        let ancestors: Vec<_> = registry.ancestors(leaf).map(|info| info.id).collect();
+        assert_eq!(ancestors, vec![row, root]);
+        assert_eq!(registry.ancestors(root).count(), 0);
+
+        assert_eq!(registry.widget_at((15., 5.).into()).unwrap().id, leaf);
+        assert_eq!(registry.widget_at((50., 5.).into()).unwrap().id, row);
+        assert_eq!(registry.widget_at((50., 50.).into()).unwrap().id, root);
+        assert!(registry.widget_at((150., 50.).into()).is_none());
+    }
+
+    #[test]
+    fn nearest_in_direction() {
+        // a b
+        //     c
+        // d
+        let ids: Vec<_> = (0..4).map(|_| WidgetId::next()).collect();
+        let (a, b, c, d) = (ids[0], ids[1], ids[2], ids[3]);
+        let mut registry = WidgetRegistry::default();
+        registry.insert(info(a, None, Rect::new(0., 0., 20., 20.)));
+        registry.insert(info(b, None, Rect::new(30., 0., 50., 20.)));
+        registry.insert(info(c, None, Rect::new(60., 30., 80., 50.)));
+        registry.insert(info(d, None, Rect::new(0., 60., 20., 80.)));
+
+        let nearest = |from, direction| registry.nearest_in_direction(from, direction, ids.clone());
+        assert_eq!(nearest(a, FocusDirection::Right), Some(b));
+        assert_eq!(nearest(b, FocusDirection::Right), Some(c));
+        assert_eq!(nearest(c, FocusDirection::Right), None);
+        assert_eq!(nearest(a, FocusDirection::Down), Some(d));
+        assert_eq!(nearest(c, FocusDirection::Left), Some(b));
+        assert_eq!(nearest(d, FocusDirection::Up), Some(a));
+        assert_eq!(nearest(a, FocusDirection::Up), None);
+    }
+}
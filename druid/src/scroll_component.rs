@@ -495,7 +495,7 @@ impl ScrollComponent {
     ) {
         if !ctx.is_handled() {
             if let Event::Wheel(mouse) = event {
-                if port.pan_by(mouse.wheel_delta) {
+                if port.pan_by(mouse.scroll_delta(env)) {
                     ctx.request_paint();
                     ctx.set_handled();
                     self.reset_scrollbar_fade(|d| ctx.request_timer(d), env);
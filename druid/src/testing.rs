@@ -0,0 +1,1019 @@
+// Copyright 2020 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tools and infrastructure for testing widgets.
+//!
+//! The [`Harness`] runs a widget tree without opening a window: you mount a
+//! widget with some data, feed it synthetic input, and then check the data,
+//! the layout of individual widgets, and the painted pixels.
+//!
+//! # Examples
+//!
+//! ```
+//! use druid::testing::Harness;
+//! use druid::widget::Button;
+//! use druid::{WidgetExt, WidgetId};
+//!
+//! let id = WidgetId::next();
+//! let button = Button::new("+1")
+//!     .on_click(|_ctx, count: &mut u32, _env| *count += 1)
+//!     .with_id(id);
+//!
+//! Harness::create_simple(0u32, button, |harness| {
+//!     harness.send_initial_events();
+//!     harness.just_layout();
+//!
+//!     harness.click(id);
+//!     assert_eq!(*harness.data(), 1);
+//! });
+//! ```
+//!
+//! # Golden images
+//!
+//! [`Harness::render_image`] paints a widget tree into an image at a fixed
+//! size and scale, which can be compared against a reference image with
+//! [`diff_images`]. With the `image` and `png` features enabled,
+//! [`assert_golden`] does this against a png file on disk, and (re)creates
+//! that file when the `DRUID_UPDATE_GOLDEN` environment variable is set.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(all(feature = "image", feature = "png"))]
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::app::PendingWindow;
+use crate::core::{CommandQueue, WidgetState};
+use crate::ext_event::ExtEventHost;
+use crate::piet::{BitmapTarget, Device, ImageFormat, Piet};
+use crate::registry::Registries;
+use crate::*;
+
+pub(crate) const DEFAULT_SIZE: Size = Size::new(400., 400.);
+
+/// The environment variable that makes [`assert_golden`] write golden images
+/// instead of comparing against them.
+pub const UPDATE_GOLDEN_VAR: &str = "DRUID_UPDATE_GOLDEN";
+
+/// The time between two `AnimFrame` events sent by [`Harness::advance_time`].
+pub const FRAME_INTERVAL: Duration = Duration::from_nanos(16_666_667);
+
+/// A type that tries very hard to provide a comforting and safe environment
+/// for widgets who are trying to find their way.
+///
+/// You create a `Harness` with some widget and its initial data; then you
+/// can send events to that widget and verify that expected conditions are met.
+///
+/// Harness tries to act like the normal druid environment; for instance, it will
+/// attempt to dispatch any `Command`s that are sent during event handling
+/// (including those submitted through an [`ExtEventSink`]), and it will call
+/// `update` automatically after an event.
+///
+/// That said, it _is_ missing a bunch of logic that would normally be handled
+/// in `AppState`: for instance it does not clear the `needs_inval` and
+/// `children_changed` flags on the window after an update.
+///
+/// In addition, layout and paint **are not called automatically**. This is
+/// because paint is triggered by druid-shell, and there is no druid-shell here;
+/// if you want those functions run you will need to call them yourself.
+///
+/// Time does not pass on its own either. The harness has a virtual clock
+/// that only moves when you call [`advance_time`], which delivers the timers
+/// and `AnimFrame` events that are due along the way; this makes animations
+/// and timeouts testable without sleeping. Alternatively, [`fire_timers`]
+/// delivers every timer that has been requested so far.
+///
+/// [`advance_time`]: Harness::advance_time
+/// [`fire_timers`]: Harness::fire_timers
+pub struct Harness<'a, T> {
+    piet: Piet<'a>,
+    inner: Inner<T>,
+    window_size: Size,
+    clock: Clock,
+}
+
+/// The virtual clock of a [`Harness`].
+#[derive(Debug, Default)]
+struct Clock {
+    /// The time since the harness was created.
+    now: Duration,
+    /// The deadlines of the timers that have not fired yet.
+    deadlines: HashMap<TimerToken, Duration>,
+    /// The time of the last `AnimFrame`, if an animation is running.
+    last_frame: Option<Duration>,
+}
+
+/// All of the state except for the `Piet` (render context). We need to pass
+/// that in to get around some lifetime issues.
+struct Inner<T> {
+    data: T,
+    env: Env,
+    window: Window<T>,
+    cmds: CommandQueue,
+    ext_host: ExtEventHost,
+    delegate: Option<Box<dyn AppDelegate<T>>>,
+}
+
+/// A way to clean up resources when our target goes out of scope.
+// the inner type is an option so that we can take ownership in `drop` even
+// though self is `& mut`.
+pub struct TargetGuard<'a>(Option<BitmapTarget<'a>>);
+
+impl<'a> TargetGuard<'a> {
+    /// Turns the TargetGuard into a array of pixels
+    pub fn into_raw(mut self) -> Arc<[u8]> {
+        let mut raw_target = self.0.take().unwrap();
+        raw_target
+            .to_image_buf(ImageFormat::RgbaPremul)
+            .unwrap()
+            .raw_pixels_shared()
+    }
+
+    /// Turns the TargetGuard into an [`ImageBuf`].
+    pub fn into_image_buf(mut self) -> ImageBuf {
+        let mut raw_target = self.0.take().unwrap();
+        raw_target.to_image_buf(ImageFormat::RgbaPremul).unwrap()
+    }
+
+    /// Saves the TargetGuard into a png
+    #[cfg(all(feature = "image", feature = "png"))]
+    pub fn into_png<P: AsRef<Path>>(self, path: P) -> Result<(), piet::Error> {
+        save_png(&self.into_image_buf(), path.as_ref())
+            .map_err(|e| piet::Error::BackendError(e.into()))
+    }
+}
+
+impl<T: Data> Harness<'_, T> {
+    /// Create a new `Harness` with the given data and a root widget,
+    /// and provide that harness to the passed in function.
+    ///
+    /// For lifetime reasons™, we cannot just make a harness. It's complicated.
+    /// I tried my best.
+    ///
+    /// This function is a subset of [create_with_render](struct.Harness.html#create_with_render)
+    pub fn create_simple(
+        data: T,
+        root: impl Widget<T> + 'static,
+        harness_closure: impl FnMut(&mut Harness<T>),
+    ) {
+        Self::create_with_render(data, root, DEFAULT_SIZE, harness_closure, |_target| {})
+    }
+
+    /// Create a new `Harness` with the given data and a root widget,
+    /// and provide that harness to the `harness_closure` callback and then the
+    /// render_context to the `render_context_closure` callback.
+    ///
+    /// For lifetime reasons™, we cannot just make a harness. It's complicated.
+    /// I tried my best.
+    ///
+    /// The with_render version of `create` also has a callback that can be used
+    /// to save or inspect the painted widget
+    ///
+    /// # Usage
+    ///
+    /// The create functions are used to test a widget. The function takes a `root` widget
+    /// and a data structure and uses them to create a `Harness`. The Harness can then be interacted
+    /// with via the `harness_closure` callback. The the final render of
+    /// the widget can be inspected with the `render_context_closure` callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A structure that matches the type of the widget and that will be
+    ///  passed to the `harness_closure` callback via the `Harness` structure.
+    ///
+    /// * `root` - The widget under test
+    ///
+    /// * `shape` - The shape of the render_context in the `Harness` structure
+    ///
+    /// * `harness_closure` - A closure used to interact with the widget under test through the
+    /// `Harness` structure.
+    ///
+    /// * `render_context_closure` - A closure used to inspect the final render_context via the `TargetGuard` structure.
+    ///
+    pub fn create_with_render(
+        data: T,
+        root: impl Widget<T> + 'static,
+        window_size: Size,
+        harness_closure: impl FnMut(&mut Harness<T>),
+        render_context_closure: impl FnMut(TargetGuard),
+    ) {
+        Self::create_with_render_scaled(
+            data,
+            root,
+            window_size,
+            1.0,
+            harness_closure,
+            render_context_closure,
+        )
+    }
+
+    /// Like [`create_with_render`], but rendering at the given scale.
+    ///
+    /// The window still has a size of `window_size` in display points, but the
+    /// image it is painted into is `scale` times as large in each dimension.
+    ///
+    /// [`create_with_render`]: Harness::create_with_render
+    pub fn create_with_render_scaled(
+        data: T,
+        root: impl Widget<T> + 'static,
+        window_size: Size,
+        scale: f64,
+        mut harness_closure: impl FnMut(&mut Harness<T>),
+        mut render_context_closure: impl FnMut(TargetGuard),
+    ) {
+        let ext_host = ExtEventHost::default();
+        let ext_handle = ext_host.make_sink();
+        let mut device = Device::new().expect("harness failed to get device");
+        let pixel_size = (window_size * scale).expand();
+        let target = device
+            .bitmap_target(pixel_size.width as usize, pixel_size.height as usize, scale)
+            .expect("bitmap_target");
+        let mut target = TargetGuard(Some(target));
+        {
+            let piet = target.0.as_mut().unwrap().render_context();
+
+            let pending = PendingWindow::new(root);
+            let window = Window::new(WindowId::next(), Default::default(), pending, ext_handle);
+
+            let inner = Inner {
+                data,
+                env: Env::default(),
+                window,
+                cmds: Default::default(),
+                ext_host,
+                delegate: None,
+            };
+
+            let mut harness = Harness {
+                piet,
+                inner,
+                window_size,
+                clock: Clock::default(),
+            };
+            harness_closure(&mut harness);
+        }
+        render_context_closure(target)
+    }
+
+    /// Paint a widget tree into an image.
+    ///
+    /// The harness sends the initial events, then calls `harness_closure` (which
+    /// can be used to get the widgets into the state to be captured), and then
+    /// paints the whole window into an image of `window_size * scale` pixels.
+    pub fn render_image(
+        data: T,
+        root: impl Widget<T> + 'static,
+        window_size: Size,
+        scale: f64,
+        mut harness_closure: impl FnMut(&mut Harness<T>),
+    ) -> ImageBuf {
+        let mut image = None;
+        Self::create_with_render_scaled(
+            data,
+            root,
+            window_size,
+            scale,
+            |harness| {
+                harness.send_initial_events();
+                harness_closure(harness);
+                harness.paint();
+            },
+            |target| image = Some(target.into_image_buf()),
+        );
+        image.unwrap()
+    }
+
+    /// Set the size without sending a resize event; intended to be used
+    /// before calling `send_initial_events`
+    pub fn set_initial_size(&mut self, size: Size) {
+        self.window_size = size;
+    }
+
+    pub(crate) fn window(&self) -> &Window<T> {
+        &self.inner.window
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn window_mut(&mut self) -> &mut Window<T> {
+        &mut self.inner.window
+    }
+
+    /// The current data.
+    pub fn data(&self) -> &T {
+        &self.inner.data
+    }
+
+    /// Modify the data from outside the widget tree, as an [`AppDelegate`]
+    /// or an [`ExtEventSink`] might, and then run `update`.
+    pub fn update_data(&mut self, f: impl FnOnce(&mut T)) {
+        f(&mut self.inner.data);
+        self.update();
+    }
+
+    /// Replace the [`Env`] the widgets are run with, and then run `update`.
+    pub fn set_env(&mut self, env: Env) {
+        self.inner.env = env;
+        self.update();
+    }
+
+    /// Set an [`AppDelegate`], which sees every command before the widgets do,
+    /// like the delegate of an application.
+    pub fn set_delegate(&mut self, delegate: impl AppDelegate<T> + 'static) {
+        self.inner.delegate = Some(Box::new(delegate));
+    }
+
+    /// An [`ExtEventSink`] whose commands are delivered to this harness.
+    ///
+    /// Commands submitted through the sink are delivered along with the
+    /// commands submitted by widgets, the next time an event is sent.
+    pub fn get_external_handle(&self) -> ExtEventSink {
+        self.inner.ext_host.make_sink()
+    }
+
+    /// Retrieve a copy of this widget's `WidgetState`, or die trying.
+    #[cfg(test)]
+    pub(crate) fn get_state(&mut self, widget: WidgetId) -> WidgetState {
+        match self.try_get_state(widget) {
+            Some(thing) => thing,
+            None => panic!("get_state failed for widget {:?}", widget),
+        }
+    }
+
+    /// Attempt to retrieve a copy of this widget's `WidgetState`.
+    pub(crate) fn try_get_state(&mut self, widget: WidgetId) -> Option<WidgetState> {
+        let cell = StateCell::default();
+        let state_cell = cell.clone();
+        self.lifecycle(LifeCycle::Internal(InternalLifeCycle::DebugRequestState {
+            widget,
+            state_cell,
+        }));
+        cell.take()
+    }
+
+    /// Inspect the `WidgetState` of each widget in the tree.
+    ///
+    /// The provided closure will be called on each widget.
+    #[allow(dead_code)]
+    pub(crate) fn inspect_state(&mut self, f: impl Fn(&WidgetState) + 'static) {
+        let checkfn = StateCheckFn::new(f);
+        self.lifecycle(LifeCycle::Internal(InternalLifeCycle::DebugInspectState(
+            checkfn,
+        )))
+    }
+
+    /// The layout rect of a widget, in its parent's coordinate space.
+    ///
+    /// Returns `None` if the widget is not in the tree.
+    pub fn layout_rect(&mut self, widget: WidgetId) -> Option<Rect> {
+        self.try_get_state(widget).map(|state| state.layout_rect())
+    }
+
+    /// The layout rect of a widget, in window coordinates.
+    ///
+    /// Returns `None` if the widget is not in the tree.
+    pub fn window_layout_rect(&mut self, widget: WidgetId) -> Option<Rect> {
+        self.try_get_state(widget)
+            .map(|state| Rect::from_origin_size(state.window_origin(), state.size()))
+    }
+
+    /// Returns `true` if the widget is [hot].
+    ///
+    /// [hot]: crate::EventCtx::is_hot
+    pub fn is_hot(&mut self, widget: WidgetId) -> bool {
+        self.try_get_state(widget)
+            .map(|state| state.is_hot)
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if the widget is [active].
+    ///
+    /// [active]: crate::EventCtx::is_active
+    pub fn is_active(&mut self, widget: WidgetId) -> bool {
+        self.try_get_state(widget)
+            .map(|state| state.is_active)
+            .unwrap_or(false)
+    }
+
+    /// The widgets of the window, as of its last layout.
+    pub fn widget_registry(&self) -> &WidgetRegistry {
+        self.inner.window.registry()
+    }
+
+    /// The widget that currently has keyboard focus.
+    pub fn focus(&self) -> Option<WidgetId> {
+        self.inner.window.focus
+    }
+
+    /// Send a command to a target.
+    pub fn submit_command(&mut self, cmd: impl Into<Command>) {
+        let command = cmd.into().default_to(self.inner.window.id.into());
+        self.inner.cmds.push_back(command);
+        self.process_commands();
+    }
+
+    /// Send the events that would normally be sent when the app starts.
+    // should we do this automatically? Also these will change regularly?
+    pub fn send_initial_events(&mut self) {
+        self.event(Event::WindowConnected);
+        self.event(Event::WindowSize(self.window_size));
+    }
+
+    /// Send an event to the widget.
+    ///
+    /// If this event triggers lifecycle events, they will also be dispatched,
+    /// as will any resulting commands. This will also trigger `update`.
+    ///
+    /// Commands dispatched during `update` will not be sent?
+    pub fn event(&mut self, event: Event) {
+        self.inner.event(event);
+        self.sync_timers();
+        self.process_commands();
+        self.update();
+    }
+
+    /// Move the mouse to `pos`, in window coordinates.
+    pub fn mouse_move(&mut self, pos: impl Into<Point>) {
+        self.event(Event::MouseMove(mouse_event(pos.into(), MouseButton::None)));
+    }
+
+    /// Press the left mouse button at `pos`.
+    pub fn mouse_down(&mut self, pos: impl Into<Point>) {
+        let mut event = mouse_event(pos.into(), MouseButton::Left);
+        event.buttons.insert(MouseButton::Left);
+        event.count = 1;
+        self.event(Event::MouseDown(event));
+    }
+
+    /// Release the left mouse button at `pos`.
+    pub fn mouse_up(&mut self, pos: impl Into<Point>) {
+        let mut event = mouse_event(pos.into(), MouseButton::Left);
+        event.count = 1;
+        self.event(Event::MouseUp(event));
+    }
+
+    /// Move the mouse to `pos` and click the left mouse button there.
+    pub fn mouse_click(&mut self, pos: impl Into<Point>) {
+        let pos = pos.into();
+        self.mouse_move(pos);
+        self.mouse_down(pos);
+        self.mouse_up(pos);
+    }
+
+    /// Find the widget that shows `text` in a [`Label`] or [`RawLabel`].
+    ///
+    /// This is the widget that contains the label, such as a [`Button`]. If
+    /// more than one label shows the text, the first one in the widget tree is
+    /// returned. Widgets that are hidden, such as the inactive tabs of
+    /// [`Tabs`], are not found.
+    ///
+    /// The widgets must have been laid out.
+    ///
+    /// [`Label`]: crate::widget::Label
+    /// [`RawLabel`]: crate::widget::RawLabel
+    /// [`Button`]: crate::widget::Button
+    /// [`Tabs`]: crate::widget::Tabs
+    pub fn find_by_label(&mut self, text: &str) -> Option<WidgetId> {
+        let query = LabelQuery::new(text);
+        self.lifecycle(LifeCycle::Internal(InternalLifeCycle::DebugFindLabel(
+            query.clone(),
+        )));
+        query.take().into_iter().next()
+    }
+
+    /// Click the center of a widget.
+    ///
+    /// Widgets are found by their [`WidgetId`], which you can give them with
+    /// [`WidgetExt::with_id`], or by their label with [`find_by_label`].
+    ///
+    /// The widget must have been laid out; this panics if it is not in the tree.
+    ///
+    /// [`WidgetExt::with_id`]: crate::WidgetExt::with_id
+    /// [`find_by_label`]: Harness::find_by_label
+    pub fn click(&mut self, widget: WidgetId) {
+        match self.window_layout_rect(widget) {
+            Some(rect) => self.mouse_click(rect.center()),
+            None => panic!("click: {:?} is not in the tree", widget),
+        }
+    }
+
+    /// Scroll the mouse wheel by `delta` at `pos`.
+    pub fn wheel(&mut self, pos: impl Into<Point>, delta: impl Into<Vec2>) {
+        let mut event = mouse_event(pos.into(), MouseButton::None);
+        event.wheel_delta = delta.into();
+        self.event(Event::Wheel(event));
+    }
+
+    /// Press a key.
+    pub fn key_down(&mut self, event: KeyEvent) {
+        self.event(Event::KeyDown(event));
+    }
+
+    /// Release a key.
+    pub fn key_up(&mut self, mut event: KeyEvent) {
+        event.state = keyboard_types::KeyState::Up;
+        self.event(Event::KeyUp(event));
+    }
+
+    /// Press and release a key for each character of `text`.
+    ///
+    /// This is how text arrives from a keyboard without an input method; see
+    /// [`ime_insert_text`] for text that arrives through the IME.
+    ///
+    /// [`ime_insert_text`]: Harness::ime_insert_text
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            let event = KeyEvent::for_test(Modifiers::default(), c.to_string().as_str());
+            self.key_down(event.clone());
+            self.key_up(event);
+        }
+    }
+
+    /// Insert text through the input method of the focused widget, replacing
+    /// its selection, as a platform IME would.
+    ///
+    /// Returns `false` if the focused widget does not accept text input.
+    pub fn ime_insert_text(&mut self, text: &str) -> bool {
+        self.with_ime_handler(|handler| {
+            let range = handler.selection().range();
+            handler.replace_range(range, text);
+        })
+    }
+
+    /// Perform an editing action through the input method of the focused
+    /// widget, as a platform IME would when a key such as Enter or Escape is
+    /// pressed.
+    ///
+    /// Returns `false` if the focused widget does not accept text input.
+    pub fn ime_action(&mut self, action: text::TextAction) -> bool {
+        self.with_ime_handler(|handler| handler.handle_action(action))
+    }
+
+    fn with_ime_handler(&mut self, f: impl FnOnce(&mut dyn text::InputHandler)) -> bool {
+        let focus = self.focus();
+        let token = match self
+            .inner
+            .window
+            .ime_handlers
+            .iter()
+            .find(|(_, reg)| Some(reg.widget_id) == focus)
+        {
+            Some((token, _)) => *token,
+            None => return false,
+        };
+
+        {
+            let mut handler = self.inner.window.get_ime_handler(token, true);
+            f(&mut *handler);
+        }
+        if let Some(widget) = self.inner.window.release_ime_lock(token) {
+            self.event(Event::Internal(InternalEvent::RouteImeStateChange(widget)));
+        }
+        true
+    }
+
+    /// Deliver every timer that has been requested and has not fired yet,
+    /// regardless of its deadline.
+    ///
+    /// Timers requested while handling these timers are not delivered until
+    /// the next call.
+    pub fn fire_timers(&mut self) {
+        let tokens: Vec<_> = self.inner.window.timers.keys().copied().collect();
+        for token in tokens {
+            self.event(Event::Timer(token));
+        }
+    }
+
+    /// The time on the harness's virtual clock, which starts at zero.
+    pub fn now(&self) -> Duration {
+        self.clock.now
+    }
+
+    /// Move the virtual clock forward by `duration`.
+    ///
+    /// Along the way, every timer is delivered when its deadline is reached,
+    /// in the order of the deadlines. While any widget requests animation
+    /// frames, an `AnimFrame` is sent right away and then every
+    /// [`FRAME_INTERVAL`], with the elapsed virtual time since the previous
+    /// frame. Timers and frames requested in response are delivered as well,
+    /// if they are due before the end of `duration`.
+    ///
+    /// Layout and paint are not run between frames.
+    pub fn advance_time(&mut self, duration: Duration) {
+        let end = self.clock.now + duration;
+        loop {
+            let animating = self.window().wants_animation_frame();
+            if !animating {
+                self.clock.last_frame = None;
+            }
+            let next_frame = if animating {
+                let now = self.clock.now;
+                Some(self.clock.last_frame.map_or(now, |t| t + FRAME_INTERVAL))
+            } else {
+                None
+            };
+            // break ties by token, so that timers fire in a deterministic order.
+            let next_timer = self
+                .clock
+                .deadlines
+                .iter()
+                .min_by_key(|(token, deadline)| (**deadline, token.into_raw()))
+                .map(|(token, deadline)| (*token, *deadline));
+
+            match (next_timer, next_frame) {
+                (Some((token, deadline)), frame)
+                    if deadline <= end && frame.is_none_or(|frame| deadline <= frame) =>
+                {
+                    self.clock.now = self.clock.now.max(deadline);
+                    self.clock.deadlines.remove(&token);
+                    self.event(Event::Timer(token));
+                }
+                (_, Some(frame)) if frame <= end => {
+                    let elapsed = self.clock.last_frame.map_or(0, |t| (frame - t).as_nanos());
+                    self.clock.now = frame;
+                    self.clock.last_frame = Some(frame);
+                    self.event(Event::AnimFrame(elapsed as u64));
+                }
+                _ => break,
+            }
+        }
+        self.clock.now = end;
+    }
+
+    /// Give the timers that were requested since the last call a deadline on
+    /// the virtual clock, and forget those that have fired.
+    fn sync_timers(&mut self) {
+        let now = self.clock.now;
+        let timers = &self.inner.window.timers;
+        let deadlines = &mut self.clock.deadlines;
+        deadlines.retain(|token, _| timers.contains_key(token));
+        for (token, request) in timers {
+            deadlines.entry(*token).or_insert(now + request.delay);
+        }
+    }
+
+    /// Deliver any pending commands, including those submitted through an
+    /// [`ExtEventSink`].
+    pub fn process_commands(&mut self) {
+        loop {
+            let cmd = self
+                .inner
+                .cmds
+                .pop_front()
+                .or_else(|| self.inner.ext_host.recv());
+            match cmd {
+                Some(cmd) => {
+                    if !self.inner.delegate_cmd(&cmd).is_handled() {
+                        self.event(Event::Internal(InternalEvent::TargetedCommand(cmd)));
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub(crate) fn lifecycle(&mut self, event: LifeCycle) {
+        self.inner.lifecycle(event);
+        self.sync_timers();
+    }
+
+    /// Run `update` on the widget tree.
+    pub fn update(&mut self) {
+        self.inner.update();
+        self.sync_timers();
+    }
+
+    /// Only do a layout pass, without painting
+    pub fn just_layout(&mut self) {
+        self.inner.layout();
+    }
+
+    /// Do a layout pass, and return the region that was damaged by widgets
+    /// moving or changing size.
+    pub fn layout_damage(&mut self) -> Region {
+        self.inner.layout()
+    }
+
+    /// Paints just the part of the window that was invalidated by calls to `request_paint` or
+    /// `request_paint_rect`.
+    ///
+    /// Also resets the invalid region.
+    pub fn paint_invalid(&mut self) {
+        let invalid = std::mem::replace(self.window_mut().invalid_mut(), Region::EMPTY);
+        self.inner.paint_region(&mut self.piet, &invalid);
+    }
+
+    /// Paints the entire window and resets the invalid region.
+    pub fn paint(&mut self) {
+        self.window_mut().invalid_mut().clear();
+        self.inner
+            .paint_region(&mut self.piet, &self.window_size.to_rect().into());
+    }
+
+    /// Render the whole window offscreen into an image of `window_size * scale`
+    /// pixels, the way [`CAPTURE_WINDOW_IMAGE`] does for real windows.
+    ///
+    /// [`CAPTURE_WINDOW_IMAGE`]: crate::commands::CAPTURE_WINDOW_IMAGE
+    pub fn capture_image(&mut self, scale: f64) -> ImageBuf {
+        let inner = &mut self.inner;
+        inner
+            .window
+            .capture_image(&mut inner.cmds, &inner.data, &inner.env, scale)
+            .expect("failed to capture image")
+    }
+}
+
+/// How far a rendered image may differ from its golden image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// The largest difference allowed in any channel of a pixel, for the
+    /// pixel to be considered the same.
+    pub max_channel_difference: u8,
+    /// The number of pixels that may differ by more than
+    /// `max_channel_difference`.
+    pub max_differing_pixels: usize,
+}
+
+impl Tolerance {
+    /// Images must match exactly.
+    pub const EXACT: Tolerance = Tolerance {
+        max_channel_difference: 0,
+        max_differing_pixels: 0,
+    };
+
+    /// Allow every channel to differ by up to `max_channel_difference`.
+    pub const fn channel(max_channel_difference: u8) -> Tolerance {
+        Tolerance {
+            max_channel_difference,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+/// The difference between two images, as returned by [`diff_images`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageDiff {
+    /// The images have different dimensions, in pixels.
+    Size {
+        /// The `(width, height)` of the actual image.
+        actual: (usize, usize),
+        /// The `(width, height)` of the expected image.
+        expected: (usize, usize),
+    },
+    /// The images have the same dimensions, but their pixels differ.
+    Pixels {
+        /// The number of pixels that are not within the tolerance.
+        differing_pixels: usize,
+        /// The largest difference in any channel of any pixel.
+        max_channel_difference: u8,
+        /// The first pixel that is not within the tolerance.
+        first: (usize, usize),
+    },
+}
+
+impl fmt::Display for ImageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageDiff::Size { actual, expected } => write!(
+                f,
+                "image is {}x{}, expected {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            ImageDiff::Pixels {
+                differing_pixels,
+                max_channel_difference,
+                first,
+            } => write!(
+                f,
+                "{} pixels differ (by up to {}), the first at ({}, {})",
+                differing_pixels, max_channel_difference, first.0, first.1
+            ),
+        }
+    }
+}
+
+/// Compare two images, pixel by pixel.
+///
+/// Pixels are compared as non-premultiplied RGBA, so images in different
+/// [`ImageFormat`]s can be compared. Returns `None` if the images are the
+/// same, within the `tolerance`.
+pub fn diff_images(
+    actual: &ImageBuf,
+    expected: &ImageBuf,
+    tolerance: Tolerance,
+) -> Option<ImageDiff> {
+    let actual_size = (actual.width(), actual.height());
+    let expected_size = (expected.width(), expected.height());
+    if actual_size != expected_size {
+        return Some(ImageDiff::Size {
+            actual: actual_size,
+            expected: expected_size,
+        });
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_channel_difference = 0;
+    let mut first = None;
+    let rows = actual.pixel_colors().zip(expected.pixel_colors());
+    for (y, (actual_row, expected_row)) in rows.enumerate() {
+        for (x, (a, e)) in actual_row.zip(expected_row).enumerate() {
+            let (ar, ag, ab, aa) = a.as_rgba8();
+            let (er, eg, eb, ea) = e.as_rgba8();
+            let difference = channel_difference(ar, er)
+                .max(channel_difference(ag, eg))
+                .max(channel_difference(ab, eb))
+                .max(channel_difference(aa, ea));
+            max_channel_difference = max_channel_difference.max(difference);
+            if difference > tolerance.max_channel_difference {
+                differing_pixels += 1;
+                first.get_or_insert((x, y));
+            }
+        }
+    }
+
+    if differing_pixels > tolerance.max_differing_pixels {
+        Some(ImageDiff::Pixels {
+            differing_pixels,
+            max_channel_difference,
+            first: first.unwrap_or_default(),
+        })
+    } else {
+        None
+    }
+}
+
+fn channel_difference(a: u8, b: u8) -> u8 {
+    a.max(b) - a.min(b)
+}
+
+/// Compare an image against the golden image at `path`, panicking if they
+/// differ by more than `tolerance`.
+///
+/// On failure the actual image is written next to the golden image, with
+/// `.actual.png` in place of the extension, so that the two can be compared.
+///
+/// If the [`UPDATE_GOLDEN_VAR`] environment variable is set, the golden image
+/// is overwritten with `image` instead.
+#[cfg(all(feature = "image", feature = "png"))]
+pub fn assert_golden(image: &ImageBuf, path: impl AsRef<Path>, tolerance: Tolerance) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("failed to create golden image directory");
+        }
+        save_png(image, path).expect("failed to write golden image");
+        return;
+    }
+
+    let expected = match ImageBuf::from_file(path) {
+        Ok(expected) => expected,
+        Err(e) => panic!(
+            "failed to load golden image {}: {}\n\
+             set {} to create it.",
+            path.display(),
+            e,
+            UPDATE_GOLDEN_VAR
+        ),
+    };
+    if let Some(diff) = diff_images(image, &expected, tolerance) {
+        let actual_path = path.with_extension("actual.png");
+        if let Err(e) = save_png(image, &actual_path) {
+            tracing::error!("failed to write {}: {}", actual_path.display(), e);
+        }
+        panic!(
+            "image does not match golden image {}: {}\n\
+             the actual image was written to {}; set {} to accept it.",
+            path.display(),
+            diff,
+            actual_path.display(),
+            UPDATE_GOLDEN_VAR
+        );
+    }
+}
+
+/// Write an image to a png file.
+#[cfg(all(feature = "image", feature = "png"))]
+fn save_png(image: &ImageBuf, path: &Path) -> Result<(), crate::image::ImageError> {
+    let raw: Vec<u8> = image
+        .pixel_colors()
+        .flatten()
+        .flat_map(|color| {
+            let (r, g, b, a) = color.as_rgba8();
+            vec![r, g, b, a]
+        })
+        .collect();
+    crate::image::save_buffer(
+        path,
+        &raw,
+        image.width() as u32,
+        image.height() as u32,
+        crate::image::ColorType::Rgba8,
+    )
+}
+
+fn mouse_event(pos: Point, button: MouseButton) -> MouseEvent {
+    MouseEvent {
+        pos,
+        window_pos: pos,
+        buttons: MouseButtons::default(),
+        mods: Modifiers::default(),
+        count: 0,
+        focus: false,
+        button,
+        wheel_delta: Vec2::ZERO,
+        pointer_type: PointerType::Mouse,
+    }
+}
+
+impl<T: Data> Inner<T> {
+    fn event(&mut self, event: Event) {
+        self.window
+            .event(&mut self.cmds, event, &mut self.data, &self.env);
+    }
+
+    fn lifecycle(&mut self, event: LifeCycle) {
+        self.window
+            .lifecycle(&mut self.cmds, &event, &self.data, &self.env, false);
+    }
+
+    fn update(&mut self) {
+        self.window.update(&mut self.cmds, &self.data, &self.env);
+    }
+
+    /// Give a command to the delegate, and run `update` if it changed the
+    /// data, like `AppState` does.
+    fn delegate_cmd(&mut self, cmd: &Command) -> Handled {
+        let delegate = match &mut self.delegate {
+            Some(delegate) => delegate,
+            None => return Handled::No,
+        };
+        let old_data = self.data.clone();
+        let mut ctx = DelegateCtx {
+            command_queue: &mut self.cmds,
+            ext_event_host: &self.ext_host,
+            app_data_type: TypeId::of::<T>(),
+            registries: &self.window,
+        };
+        let handled = delegate.command(&mut ctx, cmd.target(), cmd, &mut self.data, &self.env);
+        if !old_data.same(&self.data) {
+            self.update();
+        }
+        handled
+    }
+
+    fn layout(&mut self) -> Region {
+        self.window
+            .just_layout(&mut self.cmds, &self.data, &self.env)
+    }
+
+    fn paint_region(&mut self, piet: &mut Piet, invalid: &Region) {
+        self.window
+            .do_paint(piet, &invalid, &mut self.cmds, &self.data, &self.env);
+    }
+}
+
+impl<T> Registries for Window<T> {
+    fn registry(&self, window: WindowId) -> Option<&WidgetRegistry> {
+        if window == self.id {
+            Some(Window::registry(self))
+        } else {
+            None
+        }
+    }
+
+    fn find(&self, widget: WidgetId) -> Option<(WindowId, &WidgetInfo)> {
+        Some((self.id, Window::registry(self).get(widget)?))
+    }
+}
+
+impl<T> Drop for Harness<'_, T> {
+    fn drop(&mut self) {
+        // We need to call finish even if a test assert failed
+        if let Err(err) = self.piet.finish() {
+            // We can't panic, because we might already be panicking
+            tracing::error!("piet finish failed: {}", err);
+        }
+    }
+}
+
+impl Drop for TargetGuard<'_> {
+    fn drop(&mut self) {
+        // we need to call this to clean up the context
+        let _ = self
+            .0
+            .take()
+            .map(|mut t| t.to_image_buf(piet::ImageFormat::RgbaPremul));
+    }
+}
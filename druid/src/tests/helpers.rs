@@ -276,6 +276,7 @@ impl<T: Data, W: Widget<T>> Widget<T> for Recorder<W> {
             event,
             LifeCycle::Internal(InternalLifeCycle::DebugRequestState { .. })
                 | LifeCycle::Internal(InternalLifeCycle::DebugInspectState(_))
+                | LifeCycle::Internal(InternalLifeCycle::DebugFindLabel(_))
         );
 
         if should_record {
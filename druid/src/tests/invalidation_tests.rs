@@ -18,6 +18,7 @@ use float_cmp::approx_eq;
 use test_env_log::test;
 
 use super::*;
+use crate::core::WidgetState;
 
 #[test]
 fn invalidate_union() {
@@ -126,3 +127,53 @@ fn invalidate_scroll() {
 }
 
 // TODO: one with scroll
+
+#[test]
+fn layout_damage() {
+    const GROW: Selector = Selector::new("druid-test.grow");
+
+    let id_mover = WidgetId::next();
+    let id_still = WidgetId::next();
+
+    let grower = ModularWidget::new(10.0)
+        .event_fn(|width, ctx, event, _, _| {
+            if let Event::Command(cmd) = event {
+                if cmd.is(GROW) {
+                    *width += 10.0;
+                    ctx.request_layout();
+                }
+            }
+        })
+        .layout_fn(|width, _, _, _, _| Size::new(*width, 10.0));
+
+    let widget = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(SizedBox::empty().fix_size(10., 10.).with_id(id_still))
+        .with_child(
+            Flex::row()
+                .with_child(grower)
+                .with_child(SizedBox::empty().fix_size(10., 10.).with_id(id_mover)),
+        )
+        .fix_size(100., 100.);
+
+    Harness::create_simple((), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+
+        let window_rect =
+            |state: &WidgetState| state.layout_rect() + state.parent_window_origin.to_vec2();
+        let still_rect = window_rect(&harness.get_state(id_still));
+        let old_mover_rect = window_rect(&harness.get_state(id_mover));
+
+        harness.submit_command(GROW);
+        let damage = harness.layout_damage();
+        let new_mover_rect = window_rect(&harness.get_state(id_mover));
+        assert_ne!(old_mover_rect, new_mover_rect);
+
+        // a widget that moved is repainted at both its old and its new position...
+        assert!(damage.intersects(old_mover_rect));
+        assert!(damage.intersects(new_mover_rect));
+        // ...but widgets that didn't change are left alone.
+        assert!(!damage.intersects(still_rect));
+    });
+}
@@ -187,7 +187,7 @@ fn flex_paint_rect_overflow() {
     })
 }
 
-use crate::tests::harness::*;
+use crate::testing::*;
 use crate::widget::AspectRatioBox;
 use crate::widget::Label;
 use crate::WidgetExt;
@@ -273,3 +273,40 @@ fn aspect_ratio() {
         assert_eq!(state.layout_rect().size(), Size::new(1000., 500.));
     });
 }
+
+#[test]
+fn skip_clean_subtrees() {
+    const RELAYOUT: Selector = Selector::new("druid-test.relayout");
+
+    let still_rec = Recording::default();
+    let changed_rec = Recording::default();
+
+    let changer = ModularWidget::new(())
+        .event_fn(|_, ctx, event, _, _| {
+            if let Event::Command(cmd) = event {
+                if cmd.is(RELAYOUT) {
+                    ctx.request_layout();
+                }
+            }
+        })
+        .layout_fn(|_, _, _, _, _| Size::new(10., 10.));
+
+    let widget = Flex::column()
+        .with_child(SizedBox::empty().fix_size(10., 10.).record(&still_rec))
+        .with_child(changer.record(&changed_rec));
+
+    Harness::create_simple((), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        still_rec.clear();
+        changed_rec.clear();
+
+        harness.submit_command(RELAYOUT);
+        harness.just_layout();
+
+        let is_layout = |record: &Record| matches!(record, Record::Layout(_));
+        assert!(changed_rec.drain().any(|r| is_layout(&r)));
+        // the sibling gets the same constraints, so it is not laid out again.
+        assert!(!still_rec.drain().any(|r| is_layout(&r)));
+    });
+}
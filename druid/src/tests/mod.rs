@@ -14,20 +14,21 @@
 
 //! Additional unit tests that cross file or module boundaries.
 
-pub(crate) mod harness;
 mod helpers;
 mod invalidation_tests;
 mod layout_tests;
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::testing::*;
 use crate::widget::*;
 use crate::*;
-use harness::*;
 use helpers::*;
 use kurbo::Vec2;
 
@@ -42,6 +43,7 @@ fn move_mouse(p: impl Into<Point>) -> MouseEvent {
         focus: false,
         button: MouseButton::None,
         wheel_delta: Vec2::ZERO,
+        pointer_type: PointerType::Mouse,
     }
 }
 
@@ -56,6 +58,7 @@ fn scroll_mouse(p: impl Into<Point>, delta: impl Into<Vec2>) -> MouseEvent {
         focus: false,
         button: MouseButton::None,
         wheel_delta: delta.into(),
+        pointer_type: PointerType::Mouse,
     }
 }
 
@@ -954,3 +957,1372 @@ fn notifications() {
         assert!(saw_notification(&grandparent_rec));
     });
 }
+
+#[test]
+/// Test that the harness delivers timers and commands from an `ExtEventSink`.
+fn harness_timers_and_ext_commands() {
+    const EXTERNAL: Selector<u32> = Selector::new("druid-tests.external");
+
+    let widget =
+        ModularWidget::new(None).event_fn(|timer, ctx, event, data: &mut u32, _env| match event {
+            Event::WindowConnected => *timer = Some(ctx.request_timer(Duration::from_secs(60))),
+            Event::Timer(token) if Some(*token) == *timer => *data += 1,
+            Event::Command(cmd) => {
+                if let Some(value) = cmd.get(EXTERNAL) {
+                    *data += value;
+                }
+            }
+            _ => (),
+        });
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        assert_eq!(*harness.data(), 0);
+        harness.fire_timers();
+        assert_eq!(*harness.data(), 1);
+        // the timer only fires once.
+        harness.fire_timers();
+        assert_eq!(*harness.data(), 1);
+
+        let sink = harness.get_external_handle();
+        sink.submit_command(EXTERNAL, 10, Target::Auto).unwrap();
+        harness.process_commands();
+        assert_eq!(*harness.data(), 11);
+    });
+}
+
+#[test]
+/// Test that the virtual clock delivers timers and animation frames when they are due.
+fn harness_virtual_clock() {
+    let frames = Rc::new(RefCell::new(Vec::new()));
+    let frames_clone = frames.clone();
+    let widget = ModularWidget::new((None, None)).event_fn(
+        move |(short, long), ctx, event, data: &mut u32, _env| match event {
+            Event::WindowConnected => {
+                *long = Some(ctx.request_timer(Duration::from_millis(300)));
+                *short = Some(ctx.request_timer(Duration::from_millis(100)));
+            }
+            Event::Timer(token) if Some(*token) == *short => *data += 1,
+            Event::Timer(token) if Some(*token) == *long => {
+                *data += 10;
+                ctx.request_anim_frame();
+            }
+            Event::AnimFrame(interval) => {
+                let mut frames = frames_clone.borrow_mut();
+                frames.push(*interval);
+                if frames.len() < 3 {
+                    ctx.request_anim_frame();
+                }
+            }
+            _ => (),
+        },
+    );
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.advance_time(Duration::from_millis(99));
+        assert_eq!(*harness.data(), 0);
+        harness.advance_time(Duration::from_millis(1));
+        assert_eq!(*harness.data(), 1);
+        assert_eq!(harness.now(), Duration::from_millis(100));
+
+        harness.advance_time(Duration::from_millis(500));
+        assert_eq!(*harness.data(), 11);
+        assert_eq!(harness.now(), Duration::from_millis(600));
+        let interval = FRAME_INTERVAL.as_nanos() as u64;
+        assert_eq!(*frames.borrow(), vec![0, interval, interval]);
+    });
+}
+
+#[test]
+/// Test that commands are submitted after their delay, repeatedly, until they are cancelled.
+fn delayed_and_recurring_commands() {
+    const ONCE: Selector = Selector::new("druid-tests.once");
+    const TICK: Selector = Selector::new("druid-tests.tick");
+
+    let widget = ModularWidget::new(None).event_fn(
+        |ticker: &mut Option<CommandTimer>, ctx, event, data: &mut u32, _env| match event {
+            Event::WindowConnected => {
+                ctx.submit_command_after(ONCE, Duration::from_millis(100));
+                let interval = Duration::from_millis(30);
+                *ticker = Some(ctx.submit_command_every(TICK, interval));
+            }
+            Event::Command(cmd) if cmd.is(ONCE) => *data += 100,
+            Event::Command(cmd) if cmd.is(TICK) => {
+                *data += 1;
+                if *data == 3 {
+                    ticker.as_ref().unwrap().cancel();
+                }
+            }
+            _ => (),
+        },
+    );
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.advance_time(Duration::from_millis(29));
+        assert_eq!(*harness.data(), 0);
+        harness.advance_time(Duration::from_millis(70));
+        assert_eq!(*harness.data(), 3);
+        harness.advance_time(Duration::from_millis(1));
+        assert_eq!(*harness.data(), 103);
+        // the ticks were cancelled.
+        harness.advance_time(Duration::from_secs(1));
+        assert_eq!(*harness.data(), 103);
+        assert!(harness.window().timers.is_empty());
+    });
+}
+
+#[test]
+/// Test that a panic inside an `ErrorBoundary` does not take down the rest of the tree.
+fn error_boundary_contains_panics() {
+    let boundary_id = WidgetId::next();
+    let button_id = WidgetId::next();
+    let panicker = ModularWidget::new(()).event_fn(|_, _, event, _: &mut u32, _| {
+        if let Event::MouseDown(_) = event {
+            panic!("boom");
+        }
+    });
+    let widget = Flex::row()
+        .with_child(
+            panicker
+                .error_boundary()
+                .fix_size(100., 100.)
+                .with_id(boundary_id),
+        )
+        .with_child(
+            Button::new("+1")
+                .on_click(|_, data: &mut u32, _| *data += 1)
+                .with_id(button_id),
+        );
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.click(boundary_id);
+        // the child is never called again, so it can't panic again.
+        harness.click(boundary_id);
+        harness.just_layout();
+        harness.paint();
+
+        harness.click(button_id);
+        assert_eq!(*harness.data(), 1);
+    });
+}
+
+#[test]
+/// Test that the left widget of a `StatusBar` gets no input while a message hides it.
+fn status_bar_message_hides_left() {
+    let left = WidgetId::next();
+    let right = WidgetId::next();
+    let button = |id| {
+        Button::new("Click")
+            .on_click(|_ctx, data: &mut u32, _env| *data += 1)
+            .with_id(id)
+    };
+    let widget = StatusBar::new()
+        .with_left(button(left))
+        .with_right(button(right));
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.click(left);
+        assert_eq!(*harness.data(), 1);
+
+        harness.submit_command(commands::SHOW_STATUS_MESSAGE.with(StatusMessage::new("Saved")));
+        harness.just_layout();
+        harness.click(left);
+        assert_eq!(*harness.data(), 1);
+        harness.click(right);
+        assert_eq!(*harness.data(), 2);
+
+        harness.submit_command(commands::CLEAR_STATUS_MESSAGE);
+        harness.just_layout();
+        harness.click(left);
+        assert_eq!(*harness.data(), 3);
+    });
+}
+
+#[test]
+/// Test that `Harness::find_by_label` finds the widgets that show some text.
+fn find_by_label() {
+    let save = WidgetId::next();
+    let count = WidgetId::next();
+    let widget = Flex::column()
+        .with_child(
+            Button::new("Save")
+                .on_click(|_ctx, data: &mut u32, _env| *data += 1)
+                .with_id(save),
+        )
+        .with_child(Button::new("Cancel"))
+        .with_child(
+            Label::dynamic(|data: &u32, _env| format!("Saved {} times", data)).with_id(count),
+        );
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        assert_eq!(harness.find_by_label("Save"), Some(save));
+        assert_eq!(harness.find_by_label("Saved 0 times"), Some(count));
+        assert_eq!(harness.find_by_label("Delete"), None);
+
+        let button = harness.find_by_label("Save").unwrap();
+        harness.click(button);
+        assert_eq!(*harness.data(), 1);
+        assert_eq!(harness.find_by_label("Saved 0 times"), None);
+        assert_eq!(harness.find_by_label("Saved 1 times"), Some(count));
+    });
+}
+
+#[test]
+/// Test that a `NativeSurface` tells its content where it is, and passes on input.
+fn native_surface_placement() {
+    struct Content(Rc<RefCell<Vec<SurfacePlacement>>>);
+
+    impl SurfaceContent<u32> for Content {
+        fn placed(&mut self, _window: &WindowHandle, placement: SurfacePlacement) {
+            self.0.borrow_mut().push(placement);
+        }
+
+        fn event(&mut self, _ctx: &mut EventCtx, event: &Event, data: &mut u32, _env: &Env) {
+            if let Event::MouseDown(_) = event {
+                *data += 1;
+            }
+        }
+    }
+
+    let placements = Rc::new(RefCell::new(Vec::new()));
+    let id = WidgetId::next();
+    let widget = NativeSurface::new(Content(placements.clone()))
+        .fix_size(200., 100.)
+        .with_id(id)
+        .padding(10.);
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.paint();
+        let last = placements.borrow().last().map(|placement| placement.rect);
+        assert_eq!(last, Some(Rect::new(10., 10., 210., 110.)));
+
+        harness.click(id);
+        assert_eq!(*harness.data(), 1);
+    });
+}
+
+#[test]
+/// Test that a `LiveRegion` announces changes to its text, but not the initial or empty text.
+fn live_region_announcements() {
+    struct Spy(LiveRegion<String>, Rc<RefCell<Vec<String>>>);
+
+    impl Widget<String> for Spy {
+        fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut String, env: &Env) {
+            self.0.event(ctx, event, data, env)
+        }
+
+        fn lifecycle(
+            &mut self,
+            ctx: &mut LifeCycleCtx,
+            event: &LifeCycle,
+            data: &String,
+            env: &Env,
+        ) {
+            self.0.lifecycle(ctx, event, data, env)
+        }
+
+        fn update(&mut self, ctx: &mut UpdateCtx, old_data: &String, data: &String, env: &Env) {
+            self.0.update(ctx, old_data, data, env);
+            if let Some(text) = self.0.last_announcement() {
+                let mut announced = self.1.borrow_mut();
+                if announced.last().map(String::as_str) != Some(text) {
+                    announced.push(text.to_string());
+                }
+            }
+        }
+
+        fn layout(
+            &mut self,
+            ctx: &mut LayoutCtx,
+            bc: &BoxConstraints,
+            data: &String,
+            env: &Env,
+        ) -> Size {
+            self.0.layout(ctx, bc, data, env)
+        }
+
+        fn paint(&mut self, ctx: &mut PaintCtx, data: &String, env: &Env) {
+            self.0.paint(ctx, data, env)
+        }
+    }
+
+    let announced = Rc::new(RefCell::new(Vec::new()));
+    let live = LiveRegion::dynamic(|data: &String, _| data.clone());
+    let widget = Spy(live, announced.clone());
+
+    Harness::create_simple("Ready".to_string(), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        assert!(announced.borrow().is_empty());
+
+        harness.update_data(|data| *data = "Saved".into());
+        harness.update_data(|data| *data = String::new());
+        harness.update_data(|data| *data = "3 errors".into());
+        assert_eq!(*announced.borrow(), vec!["Saved", "3 errors"]);
+    });
+}
+
+#[test]
+/// Test that a `NativeSurface` takes its preferred size within its constraints.
+fn native_surface_preferred_size() {
+    struct Content;
+
+    impl SurfaceContent<u32> for Content {
+        fn placed(&mut self, _window: &WindowHandle, _placement: SurfacePlacement) {}
+    }
+
+    let small = WidgetId::next();
+    let large = WidgetId::next();
+    let widget = Flex::column()
+        .with_child(
+            NativeSurface::new(Content)
+                .with_preferred_size((120., 80.))
+                .with_id(small),
+        )
+        .with_child(
+            NativeSurface::new(Content)
+                .with_preferred_size((1000., 80.))
+                .with_id(large),
+        );
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        let size = harness.get_state(small).layout_rect().size();
+        assert_eq!(size, Size::new(120., 80.));
+        let size = harness.get_state(large).layout_rect().size();
+        assert_eq!(size, Size::new(400., 80.));
+    });
+}
+
+#[test]
+/// Test that a `WebView` passes commands to its backend, and messages back as notifications.
+fn web_view_messages() {
+    #[derive(Default)]
+    struct Shared {
+        calls: Vec<String>,
+        messenger: Option<WebViewMessenger>,
+    }
+
+    struct FakeBackend(Rc<RefCell<Shared>>);
+
+    impl WebViewBackend for FakeBackend {
+        fn connect(&mut self, messenger: WebViewMessenger) {
+            self.0.borrow_mut().messenger = Some(messenger);
+        }
+        fn set_bounds(&mut self, _window: &WindowHandle, _placement: SurfacePlacement) {}
+        fn load_url(&mut self, url: &str) {
+            self.0.borrow_mut().calls.push(format!("load_url {}", url));
+        }
+        fn load_html(&mut self, _html: &str) {
+            self.0.borrow_mut().calls.push("load_html".into());
+        }
+        fn evaluate_script(&mut self, script: &str) {
+            self.0
+                .borrow_mut()
+                .calls
+                .push(format!("evaluate {}", script));
+        }
+        fn go_back(&mut self) {
+            self.0.borrow_mut().calls.push("go_back".into());
+        }
+        fn go_forward(&mut self) {
+            self.0.borrow_mut().calls.push("go_forward".into());
+        }
+        fn reload(&mut self) {
+            self.0.borrow_mut().calls.push("reload".into());
+        }
+    }
+
+    let shared = Rc::new(RefCell::new(Shared::default()));
+    let id = WidgetId::next();
+    let recording = Recording::default();
+    let web_view = WebView::new(FakeBackend(shared.clone()))
+        .with_url("https://linebender.org")
+        .fix_size(300., 200.)
+        .with_id(id);
+    let widget = Flex::column().with_child(web_view).record(&recording);
+
+    Harness::create_simple((), widget, |harness| {
+        harness.send_initial_events();
+        harness.submit_command(commands::WEBVIEW_GO_BACK.to(id));
+        harness.submit_command(
+            commands::WEBVIEW_EVALUATE_SCRIPT
+                .with("1 + 1".into())
+                .to(id),
+        );
+        assert_eq!(
+            shared.borrow().calls,
+            vec![
+                "load_url https://linebender.org",
+                "go_back",
+                "evaluate 1 + 1"
+            ]
+        );
+
+        recording.clear();
+        let messenger = shared.borrow().messenger.clone().unwrap();
+        messenger.post_message("hello".into());
+        harness.process_commands();
+        let message = recording.drain().find_map(|record| match record {
+            Record::E(Event::Notification(note)) => note.get(commands::WEBVIEW_MESSAGE).cloned(),
+            _ => None,
+        });
+        assert_eq!(message.as_deref(), Some("hello"));
+    });
+}
+
+#[test]
+/// Test that a `Video` shows the frames of its decoder until the end.
+fn video_plays_to_end() {
+    use crate::piet::ImageFormat;
+
+    struct Frames(u64);
+
+    impl VideoDecoder for Frames {
+        fn duration(&self) -> Option<Duration> {
+            Some(Duration::from_millis(20))
+        }
+
+        fn seek(&mut self, position: Duration) {
+            self.0 = position.as_millis() as u64 / 10;
+        }
+
+        fn next_frame(&mut self) -> Option<VideoFrame> {
+            if self.0 >= 3 {
+                return None;
+            }
+            let image = ImageBuf::from_raw(vec![0u8; 2 * 2 * 4], ImageFormat::RgbaSeparate, 2, 2);
+            let timestamp = Duration::from_millis(self.0 * 10);
+            self.0 += 1;
+            Some(VideoFrame { image, timestamp })
+        }
+    }
+
+    let data = Playback {
+        playing: true,
+        ..Default::default()
+    };
+    Harness::create_simple(data, Video::new(Frames(0)), |harness| {
+        harness.send_initial_events();
+        // frames are decoded on another thread, in real time.
+        let give_up = std::time::Instant::now() + Duration::from_secs(5);
+        while harness.data().playing && std::time::Instant::now() < give_up {
+            std::thread::sleep(Duration::from_millis(5));
+            harness.process_commands();
+        }
+
+        let data = harness.data();
+        assert!(!data.playing);
+        assert_eq!(data.position, Duration::from_millis(20));
+        assert_eq!(data.duration, Some(Duration::from_millis(20)));
+    });
+}
+
+#[test]
+/// Test that rendered images can be compared.
+fn render_and_diff_images() {
+    let solid = |color: Color| {
+        Painter::new(move |ctx, _: &(), _| {
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, &color);
+        })
+    };
+    let size = Size::new(20., 10.);
+
+    let red = Harness::render_image((), solid(Color::rgb8(255, 0, 0)), size, 2.0, |_| {});
+    assert_eq!((red.width(), red.height()), (40, 20));
+
+    let same = Harness::render_image((), solid(Color::rgb8(255, 0, 0)), size, 2.0, |_| {});
+    assert_eq!(diff_images(&red, &same, Tolerance::EXACT), None);
+
+    let almost = Harness::render_image((), solid(Color::rgb8(250, 0, 0)), size, 2.0, |_| {});
+    assert!(diff_images(&red, &almost, Tolerance::EXACT).is_some());
+    assert_eq!(diff_images(&red, &almost, Tolerance::channel(5)), None);
+
+    let small = Harness::render_image((), solid(Color::rgb8(255, 0, 0)), size, 1.0, |_| {});
+    assert!(matches!(
+        diff_images(&red, &small, Tolerance::EXACT),
+        Some(ImageDiff::Size { .. })
+    ));
+}
+
+#[test]
+/// Test that capturing a window renders the same image as painting it.
+fn capture_window_image() {
+    let solid = || {
+        Painter::new(|ctx, _: &(), _| {
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, &Color::rgb8(0, 0, 255));
+        })
+    };
+    let size = Size::new(20., 10.);
+
+    let painted = Harness::render_image((), solid(), size, 2.0, |_| {});
+    Harness::create_with_render(
+        (),
+        solid(),
+        size,
+        |harness| {
+            harness.send_initial_events();
+            let captured = harness.capture_image(2.0);
+            assert_eq!(diff_images(&painted, &captured, Tolerance::EXACT), None);
+        },
+        |_| {},
+    );
+}
+
+#[test]
+fn freeze_subtree() {
+    const FREEZE: Selector<bool> = Selector::new("druid-tests.freeze");
+    const REPAINT: Selector = Selector::new("druid-tests.repaint");
+
+    let painted = Rc::new(Cell::new(0));
+    let frozen = Rc::new(Cell::new(None));
+    let leaf = ModularWidget::new((painted.clone(), frozen.clone()))
+        .event_fn(|_, ctx, event, _, _| {
+            if matches!(event, Event::Command(cmd) if cmd.is(REPAINT)) {
+                ctx.request_paint();
+            }
+        })
+        .lifecycle_fn(|(_, frozen), _, event, _, _| {
+            if let LifeCycle::FrozenChanged(is_frozen) = event {
+                frozen.set(Some(*is_frozen));
+            }
+        })
+        .paint_fn(|(painted, _), _, _, _| painted.set(painted.get() + 1));
+
+    let wrapper = ModularWidget::new(WidgetPod::new(leaf))
+        .event_fn(|inner, ctx, event, data, env| {
+            if let Event::Command(cmd) = event {
+                if let Some(freeze) = cmd.get(FREEZE) {
+                    inner.set_frozen(*freeze);
+                    ctx.request_update();
+                }
+            }
+            inner.event(ctx, event, data, env);
+        })
+        .lifecycle_fn(|inner, ctx, event, data, env| inner.lifecycle(ctx, event, data, env))
+        .update_fn(|inner, ctx, _, data, env| inner.update(ctx, data, env))
+        .layout_fn(|inner, ctx, bc, data, env| {
+            let size = inner.layout(ctx, bc, data, env);
+            inner.set_origin(ctx, data, env, Point::ORIGIN);
+            size
+        })
+        .paint_fn(|inner, ctx, data, env| inner.paint(ctx, data, env));
+
+    Harness::create_simple((), wrapper, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.paint();
+        assert_eq!(painted.get(), 1);
+
+        harness.submit_command(FREEZE.with(true));
+        assert_eq!(frozen.get(), Some(true));
+        harness.paint();
+        assert_eq!(painted.get(), 1);
+
+        harness.submit_command(FREEZE.with(false));
+        assert_eq!(frozen.get(), Some(false));
+        harness.paint();
+        assert_eq!(painted.get(), 2);
+
+        // a repaint that is requested while frozen happens once thawed.
+        harness.submit_command(FREEZE.with(true));
+        harness.submit_command(REPAINT);
+        harness.paint_invalid();
+        assert_eq!(painted.get(), 2);
+        harness.submit_command(FREEZE.with(false));
+        harness.paint_invalid();
+        assert_eq!(painted.get(), 3);
+    });
+}
+
+#[test]
+fn widget_registry() {
+    let (flex_id, enabled_id, disabled_id) = (WidgetId::next(), WidgetId::next(), WidgetId::next());
+    let widget = Flex::row()
+        .with_child(SizedBox::empty().fix_size(20., 10.).with_id(enabled_id))
+        .with_child(
+            SizedBox::empty()
+                .fix_size(30., 10.)
+                .disabled_if(|data: &bool, _| *data)
+                .with_id(disabled_id),
+        )
+        .with_id(flex_id)
+        .padding(5.);
+
+    Harness::create_simple(false, widget, |harness| {
+        harness.send_initial_events();
+        assert!(harness.widget_registry().is_empty());
+        harness.just_layout();
+
+        let registry = harness.widget_registry();
+        let enabled = registry.get(enabled_id).unwrap();
+        assert_eq!(enabled.parent, Some(flex_id));
+        assert_eq!(enabled.window_rect.size(), Size::new(20., 10.));
+        assert!(!enabled.is_disabled);
+        assert_eq!(
+            registry
+                .ancestors(enabled_id)
+                .last()
+                .map(|info| info.parent),
+            Some(None)
+        );
+        assert_eq!(registry.of_type::<IdentityWrapper<Flex<bool>>>().count(), 1);
+
+        let disabled = registry.get(disabled_id).unwrap();
+        assert_eq!(disabled.window_rect.x0, enabled.window_rect.x1);
+        assert!(!disabled.is_disabled);
+        assert_eq!(
+            registry
+                .widget_at(disabled.window_rect.center())
+                .unwrap()
+                .id,
+            disabled_id
+        );
+
+        // disabling doesn't need a new layout to show up.
+        harness.update_data(|data| *data = true);
+        assert!(
+            harness
+                .widget_registry()
+                .get(disabled_id)
+                .unwrap()
+                .is_disabled
+        );
+    });
+}
+
+#[test]
+fn viewport_visibility() {
+    let visible = Rc::new(RefCell::new(HashMap::new()));
+    let tracked = |id: WidgetId| {
+        ModularWidget::new(visible.clone())
+            .lifecycle_fn(|visible, ctx, event, _, _| {
+                if let LifeCycle::ViewportVisibilityChanged(is_visible) = event {
+                    visible.borrow_mut().insert(ctx.widget_id(), *is_visible);
+                }
+            })
+            .layout_fn(|_, _, _, _, _| Size::new(100., 100.))
+            .with_id(id)
+    };
+
+    let rows: Vec<_> = (0..6).map(|_| WidgetId::next()).collect();
+    let other = WidgetId::next();
+    let mut column = Flex::column();
+    for id in &rows {
+        column.add_child(tracked(*id));
+    }
+    let widget = Either::new(
+        |show_rows: &bool, _| *show_rows,
+        Scroll::new(column).vertical(),
+        tracked(other),
+    );
+
+    Harness::create_simple(true, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        // the window is 400 high, so only the first four rows can be seen.
+        for id in &rows[..4] {
+            assert_eq!(visible.borrow().get(id), Some(&true));
+        }
+        assert_eq!(visible.borrow().get(&rows[4]), None);
+        assert_eq!(visible.borrow().get(&other), None);
+
+        harness.wheel((50., 50.), (0., 150.));
+        assert_eq!(visible.borrow().get(&rows[0]), Some(&false));
+        assert_eq!(visible.borrow().get(&rows[1]), Some(&true));
+        assert_eq!(visible.borrow().get(&rows[4]), Some(&true));
+        assert_eq!(visible.borrow().get(&rows[5]), Some(&true));
+
+        // the rows are not laid out any more, but still hear about it.
+        harness.update_data(|show_rows| *show_rows = false);
+        harness.just_layout();
+        for id in &rows {
+            assert_eq!(visible.borrow().get(id), Some(&false));
+        }
+        assert_eq!(visible.borrow().get(&other), Some(&true));
+    });
+}
+
+#[test]
+fn removed_widgets_are_told() {
+    const CLEANED_UP: Selector<WidgetId> = Selector::new("druid-tests.cleaned-up");
+
+    let removed = Rc::new(RefCell::new(Vec::new()));
+    let tracked = |id: WidgetId| {
+        ModularWidget::new(removed.clone())
+            .lifecycle_fn(|removed, ctx, event, _, _| {
+                if let LifeCycle::Removed = event {
+                    removed.borrow_mut().push(ctx.widget_id());
+                    ctx.submit_command(CLEANED_UP.with(ctx.widget_id()));
+                }
+            })
+            .with_id(id)
+    };
+
+    // the row is dropped as a whole, with the widgets in it.
+    let (first, second) = (WidgetId::next(), WidgetId::next());
+    let row = Flex::row()
+        .with_child(tracked(first))
+        .with_child(Flex::column().with_child(tracked(second)));
+    let replacer = ReplaceChild::new(row, SizedBox::empty);
+    let delivered = Rc::new(RefCell::new(Vec::new()));
+    let delivered_clone = delivered.clone();
+    let widget = ModularWidget::new(WidgetPod::new(replacer))
+        .event_fn(move |inner, ctx, event, data: &mut (), env| {
+            if let Event::Command(cmd) = event {
+                if let Some(id) = cmd.get(CLEANED_UP) {
+                    delivered_clone.borrow_mut().push(*id);
+                    return;
+                }
+            }
+            inner.event(ctx, event, data, env);
+        })
+        .lifecycle_fn(|inner, ctx, event, data, env| inner.lifecycle(ctx, event, data, env))
+        .update_fn(|inner, ctx, _, data, env| inner.update(ctx, data, env))
+        .layout_fn(|inner, ctx, bc, data, env| {
+            let size = inner.layout(ctx, bc, data, env);
+            inner.set_origin(ctx, data, env, Point::ORIGIN);
+            size
+        });
+
+    Harness::create_simple((), widget, |harness| {
+        harness.send_initial_events();
+        assert!(removed.borrow().is_empty());
+
+        harness.submit_command(REPLACE_CHILD);
+        assert_eq!(*removed.borrow(), vec![first, second]);
+        // the commands they submitted are still delivered.
+        assert_eq!(*delivered.borrow(), vec![first, second]);
+    });
+}
+
+#[test]
+fn view_switcher_retains_children() {
+    let built = Rc::new(Cell::new(0));
+    let seen = Rc::new(Cell::new(0));
+    let (built_clone, seen_clone) = (built.clone(), seen.clone());
+    let widget = ViewSwitcher::new(
+        |data: &u32, _| *data >= 10,
+        move |_, _, _| {
+            built_clone.set(built_clone.get() + 1);
+            ModularWidget::new(seen_clone.clone())
+                .update_fn(|seen, _, _, data, _| seen.set(*data))
+                .boxed()
+        },
+    )
+    .retain_inactive(true);
+
+    Harness::create_simple(1u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.update_data(|data| *data = 2);
+        assert_eq!(seen.get(), 2);
+
+        harness.update_data(|data| *data = 10);
+        assert_eq!(built.get(), 2);
+
+        // switching back reuses the first child, which catches up with the data.
+        harness.update_data(|data| *data = 3);
+        assert_eq!(built.get(), 2);
+        assert_eq!(seen.get(), 3);
+    });
+}
+
+#[test]
+fn view_switcher_limits_retained_children() {
+    let built = Rc::new(RefCell::new(Vec::new()));
+    let built_clone = built.clone();
+    let widget = ViewSwitcher::new(
+        |data: &u32, _| *data,
+        move |key, _, _| {
+            built_clone.borrow_mut().push(*key);
+            SizedBox::empty().boxed()
+        },
+    )
+    .retain_inactive(true)
+    .max_cached(1);
+
+    Harness::create_simple(0u32, widget, |harness| {
+        harness.send_initial_events();
+        harness.update_data(|data| *data = 1);
+        harness.update_data(|data| *data = 0);
+        assert_eq!(*built.borrow(), vec![0, 1]);
+
+        // showing 2 retains 0, which pushes 1 out.
+        harness.update_data(|data| *data = 2);
+        harness.update_data(|data| *data = 0);
+        harness.update_data(|data| *data = 1);
+        assert_eq!(*built.borrow(), vec![0, 1, 2, 1]);
+    });
+}
+
+#[test]
+fn write_data_through_lens() {
+    let id = WidgetId::next();
+    let widget = ModularWidget::new(())
+        .event_fn(|_, ctx, event, _, _| {
+            if let Event::Command(_) = event {
+                ctx.set_handled();
+            }
+        })
+        .with_id(id);
+
+    Harness::create_simple((1u32, false), widget, |harness| {
+        harness.send_initial_events();
+        let write = DataWrite::new(lens!((u32, bool), 0), 7);
+        harness.submit_command(commands::WRITE_DATA.with(write).to(id));
+        assert_eq!(*harness.data(), (7, false));
+
+        // a lens for some other type of data leaves the command to the widget.
+        let write = DataWrite::new(lens!((bool, u32), 1), 9);
+        harness.submit_command(commands::WRITE_DATA.with(write).to(id));
+        assert_eq!(*harness.data(), (7, false));
+    });
+}
+
+#[test]
+fn arrow_keys_move_focus_spatially() {
+    const TAKE_FOCUS: Selector = Selector::new("druid-tests.take-focus");
+
+    let cell = |id: WidgetId| {
+        ModularWidget::new(())
+            .event_fn(|_, ctx, event, _, _| {
+                if let Event::Command(cmd) = event {
+                    if cmd.is(TAKE_FOCUS) {
+                        ctx.request_focus();
+                    }
+                }
+            })
+            .lifecycle_fn(|_, ctx, event, _, _| {
+                if let LifeCycle::WidgetAdded = event {
+                    ctx.register_for_focus();
+                }
+            })
+            .with_id(id)
+            .fix_size(20., 20.)
+    };
+    let row = |left, right| {
+        Flex::row()
+            .with_child(cell(left))
+            .with_spacer(10.)
+            .with_child(cell(right))
+    };
+    let (top_left, top_right, bottom_left) = widget_id3();
+    let bottom_right = WidgetId::next();
+    let grid = Flex::column()
+        .with_child(row(top_left, top_right))
+        .with_spacer(10.)
+        .with_child(row(bottom_left, bottom_right))
+        .controller(SpatialNavigation);
+
+    Harness::create_simple((), grid, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.submit_command(TAKE_FOCUS.to(top_left));
+        assert_eq!(harness.focus(), Some(top_left));
+
+        let mut press = |key| {
+            harness.key_down(KeyEvent::for_test(Modifiers::default(), key));
+            harness.focus()
+        };
+        assert_eq!(press(KbKey::ArrowRight), Some(top_right));
+        assert_eq!(press(KbKey::ArrowDown), Some(bottom_right));
+        assert_eq!(press(KbKey::ArrowLeft), Some(bottom_left));
+        // there is nothing further down, so focus stays.
+        assert_eq!(press(KbKey::ArrowDown), Some(bottom_left));
+    });
+}
+
+#[test]
+/// Test that a `CacheLayer` only repaints its child when the cache is stale.
+fn cache_layer_invalidation() {
+    const REPAINT: Selector = Selector::new("druid-tests.repaint");
+
+    let painted = Rc::new(Cell::new(0));
+    let id = WidgetId::next();
+    let child = ModularWidget::new(painted.clone())
+        .event_fn(|_, ctx, event, _, _| {
+            if let Event::Command(cmd) = event {
+                if cmd.is(REPAINT) {
+                    ctx.request_paint();
+                }
+            }
+        })
+        .layout_fn(|_, _, bc, _, _| bc.constrain((40., 20.)))
+        .paint_fn(|painted, ctx, _: &u32, _| {
+            painted.set(painted.get() + 1);
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, &Color::WHITE);
+        })
+        .with_id(id);
+
+    Harness::create_simple(0u32, CacheLayer::new(child), |harness| {
+        harness.send_initial_events();
+        harness.paint();
+        assert_eq!(painted.get(), 1);
+
+        // painted from the cache
+        harness.paint();
+        assert_eq!(painted.get(), 1);
+
+        harness.submit_command(REPAINT.to(id));
+        harness.paint();
+        assert_eq!(painted.get(), 2);
+        harness.paint();
+        assert_eq!(painted.get(), 2);
+
+        harness.update_data(|data| *data += 1);
+        harness.paint();
+        assert_eq!(painted.get(), 3);
+        harness.paint();
+        assert_eq!(painted.get(), 3);
+    });
+}
+
+#[test]
+/// Test that painting from a `CacheLayer` looks like painting its child directly.
+fn cache_layer_matches_uncached_painting() {
+    let content = || {
+        Painter::new(|ctx, _: &(), _| {
+            let size = ctx.size();
+            ctx.fill(size.to_rect(), &Color::rgb8(0x20, 0x40, 0x80));
+            let circle = kurbo::Circle::new((size.width / 2., size.height / 2.), 8.);
+            ctx.fill(circle, &Color::rgba8(0xff, 0xc0, 0x00, 0xa0));
+            ctx.stroke(kurbo::Line::new((2., 3.), (30., 17.)), &Color::WHITE, 1.5);
+        })
+        .padding(3.)
+    };
+    let size = Size::new(40., 30.);
+
+    let direct = Harness::render_image((), content(), size, 1.0, |_| {});
+    // the first paint fills the cache, the one that is captured uses it.
+    let cached = Harness::render_image((), CacheLayer::new(content()), size, 1.0, |harness| {
+        harness.paint()
+    });
+    assert_eq!(diff_images(&cached, &direct, Tolerance::channel(2)), None);
+}
+
+#[test]
+/// Test that widgets are updated with the changes a delegate makes to the
+/// data, before they handle the command that the delegate saw.
+fn delegate_changes_are_updated_before_commands() {
+    const TRUNCATE: Selector = Selector::new("druid-tests.truncate");
+
+    struct Truncate;
+
+    impl AppDelegate<String> for Truncate {
+        fn command(
+            &mut self,
+            _: &mut DelegateCtx,
+            _: Target,
+            cmd: &Command,
+            data: &mut String,
+            _: &Env,
+        ) -> Handled {
+            if cmd.is(TRUNCATE) {
+                data.truncate(2);
+            }
+            Handled::No
+        }
+    }
+
+    let seen = Rc::new(Cell::new(0));
+    let id = WidgetId::next();
+    // remembers the length of the text it was last updated with, like a
+    // text box remembers its selection.
+    let widget = ModularWidget::new((None, seen.clone()))
+        .lifecycle_fn(|(len, _), _, event, data: &String, _| {
+            if let LifeCycle::WidgetAdded = event {
+                *len = Some(data.len());
+            }
+        })
+        .update_fn(|(len, _), _, _, data, _| *len = Some(data.len()))
+        .event_fn(|(len, seen), _, event, data, _| {
+            if let Event::Command(cmd) = event {
+                if cmd.is(TRUNCATE) {
+                    assert_eq!(*len, Some(data.len()), "handling a command with stale data");
+                    seen.set(seen.get() + 1);
+                }
+            }
+        })
+        .with_id(id);
+
+    Harness::create_simple("some text".to_string(), widget, |harness| {
+        harness.set_delegate(Truncate);
+        harness.send_initial_events();
+        harness.submit_command(TRUNCATE.to(id));
+        assert_eq!(seen.get(), 1);
+        assert_eq!(harness.data(), "so");
+    });
+}
+
+#[test]
+/// Events that don't write to a `Versioned` value keep it the same.
+fn versioned_unchanged_by_events_that_do_not_write() {
+    let widget = ModularWidget::new(())
+        .event_fn(|_, _, event, data: &mut u32, _| {
+            if let Event::Wheel(mouse) = event {
+                *data = mouse.wheel_delta.y as u32;
+            }
+        })
+        .lens(lens::InArc::new::<u32, u32>(lens::Identity));
+
+    Harness::create_simple(Versioned::new(7u32), widget, |harness| {
+        harness.send_initial_events();
+        let original = harness.data().clone();
+
+        harness.event(Event::MouseMove(move_mouse((10., 10.))));
+        assert!(harness.data().same(&original));
+
+        // writing back the same value is not a change either.
+        harness.event(Event::Wheel(scroll_mouse((10., 10.), (0., 7.))));
+        assert!(harness.data().same(&original));
+
+        harness.event(Event::Wheel(scroll_mouse((10., 10.), (0., 3.))));
+        assert!(!harness.data().same(&original));
+        assert_eq!(**harness.data(), 3);
+    });
+}
+
+#[test]
+/// Drags are sent to the widgets under them, in their own coordinates.
+fn drag_events_are_routed_by_position() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let target = |n: u32| {
+        let seen = seen.clone();
+        ModularWidget::new(())
+            .event_fn(move |_, ctx, event, _: &mut (), _| match event {
+                Event::DragOver(drop) => {
+                    seen.borrow_mut().push((n, Some(drop.pos)));
+                    ctx.set_handled();
+                }
+                Event::DragLeave => seen.borrow_mut().push((n, None)),
+                _ => (),
+            })
+            .fix_size(100., 100.)
+    };
+    let widget = Flex::row().with_child(target(0)).with_child(target(1));
+
+    Harness::create_simple((), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.event(Event::DragOver(DropEvent {
+            pos: Point::new(150., 50.),
+            formats: vec!["text/uri-list".into()],
+        }));
+        assert_eq!(seen.borrow().as_slice(), &[(1, Some(Point::new(50., 50.)))]);
+
+        seen.borrow_mut().clear();
+        harness.event(Event::DragLeave);
+        assert_eq!(seen.borrow().as_slice(), &[(0, None), (1, None)]);
+    });
+}
+
+#[test]
+/// Toolbar items that don't fit are moved into the overflow menu, which only
+/// submits commands and so can be shown whatever the application's data is.
+fn toolbar_overflow_menu() {
+    fn toolbar() -> impl Widget<u32> {
+        (1..=5).fold(Toolbar::new(), |toolbar, n| {
+            toolbar.with_button(
+                format!("button-{}", n),
+                format!("Button {}", n),
+                SizedBox::empty().fix_size(20., 20.),
+                move |_, data: &mut u32, _| *data = n,
+            )
+        })
+    }
+    fn shown_menu(recording: &Recording) -> bool {
+        recording.drain().any(|record| match record {
+            Record::E(Event::Command(cmd)) if cmd.is(commands::SHOW_CONTEXT_MENU) => cmd
+                .get_unchecked(commands::SHOW_CONTEXT_MENU)
+                .take()
+                .is_some_and(|menu| menu.is::<crate::menu::ContextMenu<()>>()),
+            _ => false,
+        })
+    }
+
+    let recording = Recording::default();
+    let narrow = Size::new(100., 40.);
+    Harness::create_with_render(
+        0,
+        toolbar().record(&recording),
+        narrow,
+        |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            harness.mouse_click((18., 20.));
+            assert_eq!(*harness.data(), 1);
+
+            recording.clear();
+            harness.mouse_click((93., 20.));
+            assert!(
+                shown_menu(&recording),
+                "the chevron shows the overflow menu"
+            );
+        },
+        |_| {},
+    );
+
+    let recording = Recording::default();
+    let wide = Size::new(400., 40.);
+    Harness::create_with_render(
+        0,
+        toolbar().record(&recording),
+        wide,
+        |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            harness.mouse_click((138., 20.));
+            assert_eq!(*harness.data(), 5);
+
+            recording.clear();
+            harness.mouse_click((393., 20.));
+            assert!(
+                !shown_menu(&recording),
+                "there is no chevron without overflow"
+            );
+        },
+        |_| {},
+    );
+}
+
+#[test]
+/// A middle click in a `TextBox` with no primary selection pastes nothing.
+fn textbox_middle_click_without_primary() {
+    let id = WidgetId::next();
+    let widget = TextBox::new().with_id(id);
+
+    Harness::create_simple("hello".to_string(), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        let pos = harness.window_layout_rect(id).unwrap().center();
+        let mouse = MouseEvent {
+            pos,
+            window_pos: pos,
+            buttons: MouseButtons::default().with(MouseButton::Middle),
+            mods: Modifiers::default(),
+            count: 1,
+            focus: false,
+            button: MouseButton::Middle,
+            wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
+        };
+        harness.event(Event::MouseDown(mouse.clone()));
+        harness.event(Event::MouseUp(mouse));
+        assert_eq!(harness.data(), "hello");
+    });
+}
+
+#[test]
+/// Test that a window is cleared to its own background color if it has one,
+/// and to the theme's `WINDOW_BACKGROUND_COLOR` otherwise.
+fn window_background_color() {
+    let size = Size::new(10., 10.);
+    let corner = |image: &ImageBuf| {
+        let color = image.pixel_colors().next().unwrap().next().unwrap();
+        color.as_rgba8()
+    };
+    let red_theme =
+        || Env::default().adding(theme::WINDOW_BACKGROUND_COLOR, Color::rgb8(255, 0, 0));
+
+    let themed = Harness::render_image((), SizedBox::empty(), size, 1.0, |harness| {
+        harness.set_env(red_theme());
+    });
+    assert_eq!(corner(&themed), (255, 0, 0, 255));
+
+    let own_color = Harness::render_image((), SizedBox::empty(), size, 1.0, |harness| {
+        harness.set_env(red_theme());
+        harness
+            .window_mut()
+            .set_background_color(Color::rgb8(0, 0, 255));
+    });
+    assert_eq!(corner(&own_color), (0, 0, 255, 255));
+}
+
+#[test]
+/// Power events are sent as a global command, so they reach both the
+/// delegate and the widgets.
+fn power_event_reaches_delegate_and_widgets() {
+    struct Record(Rc<RefCell<Vec<PowerEvent>>>);
+
+    impl AppDelegate<()> for Record {
+        fn command(
+            &mut self,
+            _: &mut DelegateCtx,
+            _: Target,
+            cmd: &Command,
+            _: &mut (),
+            _: &Env,
+        ) -> Handled {
+            if let Some(event) = cmd.get(commands::POWER_EVENT) {
+                self.0.borrow_mut().push(*event);
+            }
+            Handled::No
+        }
+    }
+
+    let delegate_seen = Rc::new(RefCell::new(Vec::new()));
+    let widget_seen = Rc::new(RefCell::new(Vec::new()));
+    let widget = ModularWidget::new(widget_seen.clone()).event_fn(|seen, _, event, _, _| {
+        if let Event::Command(cmd) = event {
+            if let Some(event) = cmd.get(commands::POWER_EVENT) {
+                seen.borrow_mut().push(*event);
+            }
+        }
+    });
+
+    Harness::create_simple((), widget, |harness| {
+        harness.set_delegate(Record(delegate_seen.clone()));
+        harness.send_initial_events();
+        harness.submit_command(
+            commands::POWER_EVENT
+                .with(PowerEvent::Suspending)
+                .to(Target::Global),
+        );
+        harness.submit_command(
+            commands::POWER_EVENT
+                .with(PowerEvent::Resumed)
+                .to(Target::Global),
+        );
+    });
+
+    let expected = vec![PowerEvent::Suspending, PowerEvent::Resumed];
+    assert_eq!(*delegate_seen.borrow(), expected);
+    assert_eq!(*widget_seen.borrow(), expected);
+}
+
+#[test]
+/// Starting a file drag hands the mouse to the platform, so the widget
+/// stops being active without waiting for a mouse-up.
+fn start_file_drag_clears_active() {
+    let id = WidgetId::next();
+    let widget = ModularWidget::new(())
+        .event_fn(|_, ctx, event, _, _| match event {
+            Event::MouseDown(_) => ctx.set_active(true),
+            Event::MouseMove(_) if ctx.is_active() => {
+                ctx.start_file_drag(FilePromise::new("notes.txt", |out| out.write_all(b"notes")));
+            }
+            _ => (),
+        })
+        .with_id(id)
+        .fix_size(50., 50.);
+
+    Harness::create_simple((), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.mouse_down((10., 10.));
+        assert!(harness.is_active(id));
+        harness.mouse_move((20., 20.));
+        assert!(!harness.is_active(id));
+    });
+}
+
+#[test]
+/// Clicking and dragging in a `Minimap`'s strip scrolls so that the visible
+/// part is centered on the mouse.
+fn minimap_navigates_scroll() {
+    // the part of the content that was painted last, which is the visible part.
+    let painted = Rc::new(Cell::new(Rect::ZERO));
+    let content = ModularWidget::new(painted.clone())
+        .layout_fn(|_, _, _, _, _| Size::new(400., 4000.))
+        .paint_fn(|painted, ctx, _, _| painted.set(ctx.region().bounding_box()));
+    let minimap = Minimap::new(Scroll::new(content).vertical()).with_width(120.);
+
+    Harness::create_with_render(
+        (),
+        minimap,
+        Size::new(520., 400.),
+        |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            harness.paint();
+            assert_eq!(painted.get().y0, 0.);
+
+            // the strip shows the content at a tenth of its size, minus the
+            // padding, so the middle of the content is at about 200.
+            harness.mouse_down((460., 200.));
+            harness.paint();
+            assert!((painted.get().y0 - 1800.).abs() < 1.);
+
+            // dragging to the bottom stops at the end of the content.
+            harness.mouse_move((460., 399.));
+            harness.mouse_up((460., 399.));
+            harness.paint();
+            assert_eq!(painted.get().y0, 3600.);
+        },
+        |_| {},
+    );
+}
+
+#[test]
+/// Test that the back and forward buttons of the mouse navigate, unless a
+/// widget handles them.
+fn navigation_buttons() {
+    fn press(button: MouseButton) -> Event {
+        let mut mouse = move_mouse((10., 10.));
+        mouse.button = button;
+        mouse.buttons.insert(button);
+        mouse.count = 1;
+        Event::MouseDown(mouse)
+    }
+
+    let path = Arc::new(vec!["home".to_string(), "docs".into(), "druid".into()]);
+    Harness::create_simple(path, Breadcrumbs::new(), |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.event(press(MouseButton::BACK));
+        assert_eq!(**harness.data(), vec!["home", "docs"]);
+    });
+
+    // counts the back and forward commands, and uses the forward button itself.
+    let widget =
+        ModularWidget::new(()).event_fn(|_, ctx, event, data: &mut (u32, u32), _| match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::FORWARD => ctx.set_handled(),
+            Event::Command(cmd) if cmd.is(commands::NAVIGATE_BACK) => data.0 += 1,
+            Event::Command(cmd) if cmd.is(commands::NAVIGATE_FORWARD) => data.1 += 1,
+            _ => (),
+        });
+    Harness::create_simple((0, 0), widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.event(press(MouseButton::BACK));
+        assert_eq!(*harness.data(), (1, 0));
+        harness.event(press(MouseButton::FORWARD));
+        harness.event(press(MouseButton::Middle));
+        assert_eq!(*harness.data(), (1, 0));
+    });
+}
+
+#[test]
+#[cfg(debug_assertions)]
+/// Test that ctrl+shift+I toggles the inspector, unless a widget handles it.
+fn inspector_hotkey() {
+    let hotkey = || KeyEvent::for_test(RawMods::from(SysMods::CmdShift), "I");
+
+    // counts the toggle commands, and uses the hotkey while the data is odd.
+    let widget = ModularWidget::new(()).event_fn(|_, ctx, event, data: &mut u32, _| match event {
+        Event::KeyDown(_) if *data % 2 == 1 => ctx.set_handled(),
+        Event::Command(cmd) if cmd.is(commands::TOGGLE_INSPECTOR) => *data += 1,
+        _ => (),
+    });
+    Harness::create_simple(0, widget, |harness| {
+        harness.send_initial_events();
+        harness.just_layout();
+        harness.key_down(hotkey());
+        assert_eq!(*harness.data(), 1);
+        harness.key_down(hotkey());
+        assert_eq!(*harness.data(), 1);
+    });
+}
@@ -17,8 +17,13 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
+use fluent_bundle::types::{FluentNumber, FluentNumberOptions};
+
 use super::Selection;
-use crate::Data;
+#[cfg(feature = "chrono")]
+use crate::localization::DateSymbols;
+use crate::localization::{parse_locale, NumberSymbols};
+use crate::{Application, Data};
 
 /// A trait for types that create, interpret, and validate textual representations
 /// of values.
@@ -118,6 +123,51 @@ pub struct ParseFormatter<T> {
     fmt_fn: Box<dyn Fn(&T) -> String>,
 }
 
+/// A [`Formatter`] for numbers that follows the conventions of a locale.
+///
+/// Numbers are displayed using the locale's decimal separator and digit
+/// grouping; grouping is omitted while editing. Input is interpreted using
+/// the same conventions, so a German user can type `1.234,5`.
+///
+/// [`PropertyGrid`] uses this to edit `f64` properties.
+///
+/// [`Formatter`]: Formatter
+/// [`PropertyGrid`]: crate::widget::PropertyGrid
+#[derive(Debug, Clone)]
+pub struct NumberFormatter {
+    symbols: NumberSymbols,
+    min_fraction_digits: usize,
+    max_fraction_digits: Option<usize>,
+    use_grouping: bool,
+}
+
+/// A [`Formatter`] for dates that follows the conventions of a locale.
+///
+/// Dates are displayed numerically, in the order and with the separator
+/// used by the locale, for instance `12/31/2021` for `en-US` and
+/// `31.12.2021` for `de-DE`.
+///
+/// [`Formatter`]: Formatter
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone)]
+pub struct DateFormatter {
+    symbols: DateSymbols,
+    pattern: String,
+}
+
+/// An error returned by [`NumberFormatter`] or [`DateFormatter`] when
+/// validating input.
+///
+/// [`NumberFormatter`]: NumberFormatter
+/// [`DateFormatter`]: DateFormatter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocaleValidationError {
+    /// The input contains a character that is not allowed at its position.
+    InvalidChar(char),
+    /// The input has too many digits in some component.
+    TooManyDigits,
+}
+
 impl Validation {
     /// Create a `Validation` indicating succes.
     pub fn success() -> Self {
@@ -208,6 +258,200 @@ where
     }
 }
 
+impl NumberFormatter {
+    /// Create a new `NumberFormatter` for the current system locale.
+    ///
+    /// The separators that the user has chosen in the system's regional
+    /// settings take precedence over those of the locale.
+    pub fn new() -> Self {
+        let mut formatter = NumberFormatter::for_locale(&Application::get_locale());
+        formatter.symbols = formatter
+            .symbols
+            .with_regional_settings(&Application::regional_settings());
+        formatter
+    }
+
+    /// Create a new `NumberFormatter` for the provided locale.
+    ///
+    /// `locale` should be a BCP47 language tag, such as `"de-CH"`.
+    pub fn for_locale(locale: &str) -> Self {
+        NumberFormatter {
+            symbols: NumberSymbols::for_locale(&parse_locale(locale)),
+            min_fraction_digits: 0,
+            max_fraction_digits: None,
+            use_grouping: true,
+        }
+    }
+
+    /// Builder-style method to set the minimum and maximum number of digits
+    /// displayed after the decimal separator.
+    pub fn with_fraction_digits(mut self, min: usize, max: usize) -> Self {
+        self.min_fraction_digits = min;
+        self.max_fraction_digits = Some(max.max(min));
+        self
+    }
+
+    /// Builder-style method to set whether or not digits are grouped,
+    /// as in `1,000,000`. The default is `true`.
+    pub fn with_grouping(mut self, use_grouping: bool) -> Self {
+        self.use_grouping = use_grouping;
+        self
+    }
+
+    fn format_impl(&self, value: f64, use_grouping: bool) -> String {
+        let options = FluentNumberOptions {
+            use_grouping,
+            minimum_fraction_digits: Some(self.min_fraction_digits),
+            maximum_fraction_digits: self.max_fraction_digits,
+            ..Default::default()
+        };
+        self.symbols.format(&FluentNumber::new(value, options))
+    }
+
+    fn is_grouping_char(&self, c: char) -> bool {
+        match self.symbols.grouping {
+            // people can't easily type a non-breaking space, so accept a space too
+            Some('\u{a0}') => c == '\u{a0}' || c == ' ',
+            Some(sep) => c == sep,
+            None => false,
+        }
+    }
+}
+
+impl Formatter<f64> for NumberFormatter {
+    fn format(&self, value: &f64) -> String {
+        self.format_impl(*value, self.use_grouping)
+    }
+
+    fn format_for_editing(&self, value: &f64) -> String {
+        self.format_impl(*value, false)
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        let mut seen_decimal = false;
+        let mut fraction_digits = 0;
+        for (i, c) in input.chars().enumerate() {
+            if c == '-' && i == 0 {
+                continue;
+            } else if c == self.symbols.decimal && !seen_decimal {
+                seen_decimal = true;
+            } else if c.is_ascii_digit() {
+                if seen_decimal {
+                    fraction_digits += 1;
+                }
+            } else if !self.is_grouping_char(c) || seen_decimal {
+                return Validation::failure(LocaleValidationError::InvalidChar(c));
+            }
+        }
+        match self.max_fraction_digits {
+            Some(max) if fraction_digits > max => {
+                Validation::failure(LocaleValidationError::TooManyDigits)
+            }
+            _ => Validation::success(),
+        }
+    }
+
+    fn value(&self, input: &str) -> Result<f64, ValidationError> {
+        let canonical: String = input
+            .trim()
+            .chars()
+            .filter(|c| !self.is_grouping_char(*c))
+            .map(|c| if c == self.symbols.decimal { '.' } else { c })
+            .collect();
+        canonical.parse().map_err(ValidationError::new)
+    }
+}
+
+impl Default for NumberFormatter {
+    fn default() -> Self {
+        NumberFormatter::new()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DateFormatter {
+    /// Create a new `DateFormatter` for the current system locale.
+    ///
+    /// The short date format that the user has chosen in the system's
+    /// regional settings takes precedence over that of the locale, as long as
+    /// it is numeric.
+    pub fn new() -> Self {
+        let settings = Application::regional_settings();
+        match settings
+            .short_date_pattern
+            .as_deref()
+            .and_then(DateSymbols::from_pattern)
+        {
+            Some(symbols) => DateFormatter {
+                pattern: symbols.pattern(),
+                symbols,
+            },
+            None => DateFormatter::for_locale(&Application::get_locale()),
+        }
+    }
+
+    /// Create a new `DateFormatter` for the provided locale.
+    ///
+    /// `locale` should be a BCP47 language tag, such as `"en-GB"`.
+    pub fn for_locale(locale: &str) -> Self {
+        let symbols = DateSymbols::for_locale(&parse_locale(locale));
+        DateFormatter {
+            pattern: symbols.pattern(),
+            symbols,
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Formatter<chrono::NaiveDate> for DateFormatter {
+    fn format(&self, value: &chrono::NaiveDate) -> String {
+        value.format(&self.pattern).to_string()
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        let widths = self.symbols.component_widths();
+        let mut components = input.split(self.symbols.separator);
+        for width in &widths {
+            let component = match components.next() {
+                Some(component) => component,
+                None => break,
+            };
+            if let Some(c) = component.chars().find(|c| !c.is_ascii_digit()) {
+                return Validation::failure(LocaleValidationError::InvalidChar(c));
+            }
+            if component.len() > *width {
+                return Validation::failure(LocaleValidationError::TooManyDigits);
+            }
+        }
+        if components.next().is_some() {
+            return Validation::failure(LocaleValidationError::InvalidChar(self.symbols.separator));
+        }
+        Validation::success()
+    }
+
+    fn value(&self, input: &str) -> Result<chrono::NaiveDate, ValidationError> {
+        chrono::NaiveDate::parse_from_str(input.trim(), &self.pattern).map_err(ValidationError::new)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Default for DateFormatter {
+    fn default() -> Self {
+        DateFormatter::new()
+    }
+}
+
+impl std::fmt::Display for LocaleValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LocaleValidationError::InvalidChar(c) => write!(f, "Invalid character '{}'", c),
+            LocaleValidationError::TooManyDigits => write!(f, "Too many digits"),
+        }
+    }
+}
+
+impl std::error::Error for LocaleValidationError {}
+
 impl std::fmt::Display for ValidationError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", &self.inner)
@@ -225,3 +469,114 @@ impl<T: std::fmt::Display> Default for ParseFormatter<T> {
         ParseFormatter::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use test_env_log::test;
+
+    fn locale_error(validation: Validation) -> Option<LocaleValidationError> {
+        validation
+            .error()?
+            .source()?
+            .downcast_ref::<LocaleValidationError>()
+            .cloned()
+    }
+
+    fn validate<T>(formatter: &impl Formatter<T>, input: &str) -> Option<LocaleValidationError> {
+        locale_error(formatter.validate_partial_input(input, &Selection::caret(input.len())))
+    }
+
+    #[test]
+    fn number_comma_decimal() {
+        let formatter = NumberFormatter::for_locale("de-DE");
+        assert_eq!(formatter.format(&1234.5), "1.234,5");
+        assert_eq!(formatter.format_for_editing(&1234.5), "1234,5");
+        assert_eq!(formatter.value("1.234,5").unwrap(), 1234.5);
+        assert_eq!(formatter.value("1234,5").unwrap(), 1234.5);
+        assert_eq!(validate(&formatter, "1.234,5"), None);
+        assert_eq!(
+            validate(&formatter, "1,234.5"),
+            Some(LocaleValidationError::InvalidChar('.'))
+        );
+        assert_eq!(
+            validate(&formatter, "1,2,3"),
+            Some(LocaleValidationError::InvalidChar(','))
+        );
+    }
+
+    #[test]
+    fn number_space_grouping() {
+        let formatter = NumberFormatter::for_locale("fr-FR");
+        assert_eq!(formatter.format(&1234567.25), "1\u{a0}234\u{a0}567,25");
+        assert_eq!(
+            formatter.value("1\u{a0}234\u{a0}567,25").unwrap(),
+            1234567.25
+        );
+        // a plain space is accepted in place of the non-breaking one
+        assert_eq!(formatter.value("1 234 567,25").unwrap(), 1234567.25);
+        assert_eq!(validate(&formatter, "1 234"), None);
+        assert_eq!(validate(&formatter, "1\u{a0}234"), None);
+    }
+
+    #[test]
+    fn number_negative() {
+        let formatter = NumberFormatter::for_locale("de-DE");
+        assert_eq!(formatter.format(&-1234.5), "-1.234,5");
+        assert_eq!(formatter.value("-1.234,5").unwrap(), -1234.5);
+        assert_eq!(validate(&formatter, "-"), None);
+        assert_eq!(validate(&formatter, "-12,5"), None);
+        assert_eq!(
+            validate(&formatter, "1-2"),
+            Some(LocaleValidationError::InvalidChar('-'))
+        );
+    }
+
+    #[test]
+    fn number_too_many_digits() {
+        let formatter = NumberFormatter::for_locale("en-US").with_fraction_digits(0, 2);
+        assert_eq!(formatter.format(&(2.0 / 3.0)), "0.67");
+        assert_eq!(formatter.format(&1234.5), "1,234.5");
+        assert_eq!(validate(&formatter, "1,234.56"), None);
+        assert_eq!(
+            validate(&formatter, "1,234.567"),
+            Some(LocaleValidationError::TooManyDigits)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn date_round_trip() {
+        let date = chrono::NaiveDate::from_ymd_opt(2021, 12, 31).unwrap();
+        for (locale, text) in [
+            ("en-US", "12/31/2021"),
+            ("de-DE", "31.12.2021"),
+            ("ja", "2021/12/31"),
+        ] {
+            let formatter = DateFormatter::for_locale(locale);
+            assert_eq!(formatter.format(&date), text, "{}", locale);
+            assert_eq!(validate(&formatter, text), None, "{}", locale);
+            assert_eq!(formatter.value(text).unwrap(), date, "{}", locale);
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn date_partial_input() {
+        let formatter = DateFormatter::for_locale("de-DE");
+        assert_eq!(validate(&formatter, "31.1"), None);
+        assert_eq!(
+            validate(&formatter, "31/"),
+            Some(LocaleValidationError::InvalidChar('/'))
+        );
+        assert_eq!(
+            validate(&formatter, "311"),
+            Some(LocaleValidationError::TooManyDigits)
+        );
+        assert_eq!(
+            validate(&formatter, "31.12.2021."),
+            Some(LocaleValidationError::InvalidChar('.'))
+        );
+    }
+}
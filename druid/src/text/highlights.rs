@@ -0,0 +1,216 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Highlighted ranges of text, such as the matches of a search.
+
+use std::ops::Range;
+
+use super::{TextLayout, TextStorage};
+use crate::kurbo::Vec2;
+use crate::piet::RenderContext;
+use crate::{theme, Env, PaintCtx};
+
+/// A set of ranges in a piece of text that should be drawn highlighted.
+///
+/// This is intended for things like find-in-page: the matches are drawn with
+/// the [`SEARCH_HIGHLIGHT_COLOR`] background, and one of them can be marked as
+/// the *current* match, which is drawn with [`SEARCH_HIGHLIGHT_CURRENT_COLOR`].
+///
+/// Highlights can be set on a [`Label`], a [`RawLabel`], or a [`TextBox`].
+///
+/// The ranges are byte offsets into the text, and are kept sorted by their start.
+///
+/// [`SEARCH_HIGHLIGHT_COLOR`]: crate::theme::SEARCH_HIGHLIGHT_COLOR
+/// [`SEARCH_HIGHLIGHT_CURRENT_COLOR`]: crate::theme::SEARCH_HIGHLIGHT_CURRENT_COLOR
+/// [`Label`]: crate::widget::Label
+/// [`RawLabel`]: crate::widget::RawLabel
+/// [`TextBox`]: crate::widget::TextBox
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Highlights {
+    ranges: Vec<Range<usize>>,
+    current: Option<usize>,
+}
+
+impl Highlights {
+    /// Create a new set of highlights from the provided ranges.
+    ///
+    /// Empty ranges are ignored. No match is current.
+    pub fn new(ranges: impl IntoIterator<Item = Range<usize>>) -> Self {
+        let mut ranges: Vec<_> = ranges.into_iter().filter(|r| r.start < r.end).collect();
+        ranges.sort_by_key(|r| (r.start, r.end));
+        Highlights {
+            ranges,
+            current: None,
+        }
+    }
+
+    /// Highlight every non-overlapping occurrence of `needle` in `text`.
+    ///
+    /// The search is case-sensitive; if you need anything more involved,
+    /// compute the ranges yourself and use [`Highlights::new`].
+    pub fn find(text: &str, needle: &str) -> Self {
+        if needle.is_empty() {
+            return Highlights::default();
+        }
+        Highlights::new(
+            text.match_indices(needle)
+                .map(|(start, m)| start..start + m.len()),
+        )
+    }
+
+    /// The highlighted ranges, sorted by their start.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// The number of highlighted ranges.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns `true` if nothing is highlighted.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The index of the current match, if there is one.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// The range of the current match, if there is one.
+    pub fn current_range(&self) -> Option<Range<usize>> {
+        self.current.map(|idx| self.ranges[idx].clone())
+    }
+
+    /// Set the index of the current match.
+    ///
+    /// An index that is out of bounds clears the current match.
+    pub fn set_current(&mut self, current: Option<usize>) {
+        self.current = current.filter(|idx| *idx < self.ranges.len());
+    }
+
+    /// Make the next match current, wrapping around at the end, and return its range.
+    ///
+    /// If there is no current match, this selects the first one.
+    pub fn select_next(&mut self) -> Option<Range<usize>> {
+        if self.ranges.is_empty() {
+            return None;
+        }
+        let next = match self.current {
+            Some(idx) => (idx + 1) % self.ranges.len(),
+            None => 0,
+        };
+        self.current = Some(next);
+        self.current_range()
+    }
+
+    /// Make the previous match current, wrapping around at the start, and return its range.
+    ///
+    /// If there is no current match, this selects the last one.
+    pub fn select_previous(&mut self) -> Option<Range<usize>> {
+        if self.ranges.is_empty() {
+            return None;
+        }
+        let prev = match self.current {
+            Some(0) | None => self.ranges.len() - 1,
+            Some(idx) => idx - 1,
+        };
+        self.current = Some(prev);
+        self.current_range()
+    }
+
+    /// Make the first match that starts at or after `offset` current, wrapping
+    /// around to the first match, and return its range.
+    ///
+    /// This is useful to continue a search from the cursor position.
+    pub fn select_from(&mut self, offset: usize) -> Option<Range<usize>> {
+        if self.ranges.is_empty() {
+            return None;
+        }
+        let idx = self.ranges.iter().position(|r| r.start >= offset);
+        self.current = Some(idx.unwrap_or(0));
+        self.current_range()
+    }
+
+    /// Fill the highlighted ranges of `layout`, offset by `origin`.
+    pub(crate) fn draw<T: TextStorage>(
+        &self,
+        ctx: &mut PaintCtx,
+        layout: &TextLayout<T>,
+        origin: Vec2,
+        env: &Env,
+    ) {
+        if self.ranges.is_empty() {
+            return;
+        }
+        // the text may have changed since the highlights were computed.
+        let len = match layout.text() {
+            Some(text) => text.as_str().len(),
+            None => return,
+        };
+        let color = env.get(theme::SEARCH_HIGHLIGHT_COLOR);
+        let current_color = env.get(theme::SEARCH_HIGHLIGHT_CURRENT_COLOR);
+        for (idx, range) in self.ranges.iter().enumerate() {
+            if range.start >= len {
+                break;
+            }
+            let range = range.start..range.end.min(len);
+            let color = if Some(idx) == self.current {
+                &current_color
+            } else {
+                &color
+            };
+            for rect in layout.rects_for_range(range) {
+                ctx.fill((rect + origin).to_rounded_rect(1.0), color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn navigation_wraps() {
+        let mut highlights = Highlights::find("one two one three one", "one");
+        assert_eq!(highlights.ranges(), &[0..3, 8..11, 18..21]);
+        assert_eq!(highlights.current(), None);
+
+        assert_eq!(highlights.select_next(), Some(0..3));
+        assert_eq!(highlights.select_next(), Some(8..11));
+        assert_eq!(highlights.select_next(), Some(18..21));
+        assert_eq!(highlights.select_next(), Some(0..3));
+        assert_eq!(highlights.select_previous(), Some(18..21));
+
+        assert_eq!(highlights.select_from(4), Some(8..11));
+        assert_eq!(highlights.select_from(19), Some(0..3));
+
+        highlights.set_current(Some(5));
+        assert_eq!(highlights.current(), None);
+        assert_eq!(highlights.select_previous(), Some(18..21));
+
+        let mut empty = Highlights::find("abc", "");
+        assert!(empty.is_empty());
+        assert_eq!(empty.select_next(), None);
+    }
+
+    #[test]
+    fn ranges_are_sorted() {
+        let highlights = Highlights::new(vec![10..12, 2..2, 0..4]);
+        assert_eq!(highlights.ranges(), &[0..4, 10..12]);
+    }
+}
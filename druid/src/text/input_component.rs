@@ -21,8 +21,8 @@ use std::sync::{Arc, Weak};
 use tracing::instrument;
 
 use super::{
-    EditableText, ImeHandlerRef, ImeInvalidation, InputHandler, Movement, Selection, TextAction,
-    TextLayout, TextStorage,
+    EditableText, Highlights, ImeHandlerRef, ImeInvalidation, InputHandler, Movement, Selection,
+    TextAction, TextLayout, TextStorage,
 };
 use crate::kurbo::{Line, Point, Rect, Vec2};
 use crate::piet::TextLayout as _;
@@ -102,6 +102,8 @@ pub struct EditSession<T> {
     alignment_offset: f64,
     /// The portion of the text that is currently marked by the IME.
     composition_range: Option<Range<usize>>,
+    /// Ranges that are drawn highlighted, such as search matches.
+    highlights: Highlights,
     drag_granularity: DragGranularity,
     /// The origin of the textbox, relative to the origin of the window.
     pub origin: Point,
@@ -287,6 +289,16 @@ impl<T: TextStorage + EditableText> Widget<T> for TextComponent<T> {
                     self.borrow_mut()
                         .update_pending_invalidation(ImeInvalidation::Reset);
                 }
+                if mouse.button.is_middle() {
+                    let inval = self.borrow_mut().paste_primary_selection(data, mouse.pos);
+                    if let Some(inval) = inval {
+                        ctx.set_active(false);
+                        ctx.invalidate_text_input(inval);
+                        ctx.request_update();
+                        ctx.request_paint();
+                        return;
+                    }
+                }
                 self.borrow_mut()
                     .do_mouse_down(mouse.pos, mouse.mods, mouse.count);
                 self.borrow_mut()
@@ -314,6 +326,7 @@ impl<T: TextStorage + EditableText> Widget<T> for TextComponent<T> {
             }
             Event::MouseUp(_) if ctx.is_active() => {
                 ctx.set_active(false);
+                self.borrow().set_primary_selection();
                 ctx.request_paint();
             }
             Event::ImeStateChange => {
@@ -456,6 +469,13 @@ impl<T: TextStorage + EditableText> Widget<T> for TextComponent<T> {
         let cursor_color = env.get(theme::CURSOR_COLOR);
         let text_offset = Vec2::new(self.borrow().alignment_offset, 0.0);
 
+        {
+            let session = self.borrow();
+            session
+                .highlights
+                .draw(ctx, &session.layout, text_offset, env);
+        }
+
         let selection = self.borrow().selection();
         let composition = self.borrow().composition_range();
         let sel_rects = self.borrow().layout.rects_for_range(selection.range());
@@ -511,6 +531,25 @@ impl<T> EditSession<T> {
         self.composition_range.clone()
     }
 
+    /// The [`Highlights`] drawn behind the text.
+    pub fn highlights(&self) -> &Highlights {
+        &self.highlights
+    }
+
+    /// A mutable reference to the [`Highlights`] drawn behind the text.
+    ///
+    /// The owning widget must be repainted after these are changed.
+    pub fn highlights_mut(&mut self) -> &mut Highlights {
+        &mut self.highlights
+    }
+
+    /// Set the [`Highlights`] drawn behind the text.
+    ///
+    /// The owning widget must be repainted after these are changed.
+    pub fn set_highlights(&mut self, highlights: Highlights) {
+        self.highlights = highlights;
+    }
+
     /// Sets whether or not this session will allow the insertion of newlines.
     pub fn set_accepts_newlines(&mut self, accepts_newlines: bool) {
         self.accepts_newlines = accepts_newlines;
@@ -605,6 +644,41 @@ impl<T: TextStorage + EditableText> EditSession<T> {
         false
     }
 
+    /// Sets the primary selection to the contents of the current selection, on
+    /// platforms that have one (X11).
+    pub fn set_primary_selection(&self) {
+        if let Some(text) = self
+            .layout
+            .text()
+            .and_then(|txt| txt.slice(self.selection.range()))
+        {
+            if !text.is_empty() {
+                if let Some(mut primary) = primary_selection() {
+                    primary.put_string(text);
+                }
+            }
+        }
+    }
+
+    /// Insert the contents of the primary selection at `point`, as a middle
+    /// click does on X11.
+    ///
+    /// Returns `None`, and does nothing, if there is no primary selection.
+    #[must_use]
+    fn paste_primary_selection(&mut self, data: &mut T, point: Point) -> Option<ImeInvalidation> {
+        let text = primary_selection()?.get_string()?;
+        let text = if self.accepts_newlines {
+            &text
+        } else {
+            text.lines().next().unwrap_or("")
+        };
+        if text.is_empty() {
+            return None;
+        }
+        self.do_mouse_down(point, Modifiers::default(), 1);
+        Some(self.insert_text(data, text))
+    }
+
     fn scroll_to_selection_end(&mut self, after_edit: bool) {
         self.external_scroll_to = Some(after_edit);
     }
@@ -935,6 +1009,7 @@ impl<T> Default for TextComponent<T> {
             pending_ime_invalidation: None,
             selection: Selection::caret(0),
             composition_range: None,
+            highlights: Highlights::default(),
             send_notification_on_return: false,
             send_notification_on_cancel: false,
             accepts_newlines: false,
@@ -952,3 +1027,14 @@ impl<T> Default for TextComponent<T> {
         }
     }
 }
+/// The primary selection, on platforms that have one.
+///
+/// Unit tests have no `Application` (and `try_global` panics off the main
+/// thread), so they always behave as if there is no primary selection.
+fn primary_selection() -> Option<crate::Clipboard> {
+    #[cfg(all(feature = "x11", target_os = "linux", not(test)))]
+    let primary = crate::Application::try_global().and_then(|app| app.primary_selection());
+    #[cfg(not(all(feature = "x11", target_os = "linux", not(test))))]
+    let primary = None;
+    primary
+}
@@ -18,9 +18,12 @@ mod attribute;
 mod backspace;
 mod editable_text;
 mod font_descriptor;
+mod highlights;
 
 #[deprecated(since = "0.8.0", note = "use types from druid::text module instead")]
 #[doc(hidden)]
+// not needed by tests, which would otherwise run twice and use the deprecated module
+#[cfg(not(test))]
 pub mod format;
 // a hack to let us deprecate the format module; we can remove this when we make
 // format private
@@ -43,7 +46,12 @@ pub use self::attribute::{Attribute, AttributeSpans, Link};
 pub use self::backspace::offset_for_delete_backwards;
 pub use self::editable_text::{EditableText, EditableTextCursor, StringCursor};
 pub use self::font_descriptor::FontDescriptor;
-pub use self::format_priv::{Formatter, ParseFormatter, Validation, ValidationError};
+#[cfg(feature = "chrono")]
+pub use self::format_priv::DateFormatter;
+pub use self::format_priv::{
+    Formatter, LocaleValidationError, NumberFormatter, ParseFormatter, Validation, ValidationError,
+};
+pub use self::highlights::Highlights;
 pub use self::layout::{LayoutMetrics, TextLayout};
 pub use self::movement::movement;
 pub use input_component::{EditSession, TextComponent};
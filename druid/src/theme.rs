@@ -17,8 +17,11 @@
 #![allow(missing_docs)]
 
 use crate::piet::Color;
+use crate::shell::{MouseSettings, TextSettings};
 
-use crate::{Env, FontDescriptor, FontFamily, FontStyle, FontWeight, Insets, Key};
+use crate::{
+    Data, Env, FontDescriptor, FontFamily, FontStyle, FontWeight, InputMode, Insets, Key, ValueType,
+};
 
 pub const WINDOW_BACKGROUND_COLOR: Key<Color> =
     Key::new("org.linebender.druid.theme.window_background_color");
@@ -61,7 +64,48 @@ pub const SELECTED_TEXT_INACTIVE_BACKGROUND_COLOR: Key<Color> =
     Key::new("org.linebender.druid.theme.selection_color_inactive");
 pub const SELECTION_TEXT_COLOR: Key<Color> =
     Key::new("org.linebender.druid.theme.selection_text_color");
+/// The background of text ranges marked with [`Highlights`], such as search matches.
+///
+/// [`Highlights`]: crate::text::Highlights
+pub const SEARCH_HIGHLIGHT_COLOR: Key<Color> =
+    Key::new("org.linebender.druid.theme.search_highlight_color");
+/// The background of the current match in a set of [`Highlights`].
+///
+/// [`Highlights`]: crate::text::Highlights
+pub const SEARCH_HIGHLIGHT_CURRENT_COLOR: Key<Color> =
+    Key::new("org.linebender.druid.theme.search_highlight_current_color");
 pub const CURSOR_COLOR: Key<Color> = Key::new("org.linebender.druid.theme.cursor_color");
+/// The width of the text cursor.
+pub const CURSOR_WIDTH: Key<f64> = Key::new("org.linebender.druid.theme.cursor_width");
+/// How long, in milliseconds, the text cursor is shown and then hidden when it
+/// blinks. If this is zero, the cursor does not blink.
+pub const CURSOR_BLINK_INTERVAL: Key<u64> =
+    Key::new("org.linebender.druid.theme.cursor_blink_interval");
+/// The longest time, in milliseconds, between two clicks for them to count as
+/// a double click.
+pub const DOUBLE_CLICK_INTERVAL: Key<u64> =
+    Key::new("org.linebender.druid.theme.double_click_interval");
+/// How far the mouse can move between two clicks for them to count as a
+/// double click.
+pub const DOUBLE_CLICK_DISTANCE: Key<f64> =
+    Key::new("org.linebender.druid.theme.double_click_distance");
+pub const ERROR_COLOR: Key<Color> = Key::new("org.linebender.druid.theme.error_color");
+/// The accent color of an [`Alert`] with [`Severity::Info`].
+///
+/// [`Alert`]: crate::widget::Alert
+/// [`Severity::Info`]: crate::widget::Severity::Info
+pub const ALERT_INFO_COLOR: Key<Color> = Key::new("org.linebender.druid.theme.alert_info_color");
+/// The accent color of an [`Alert`] with [`Severity::Warning`].
+///
+/// [`Alert`]: crate::widget::Alert
+/// [`Severity::Warning`]: crate::widget::Severity::Warning
+pub const ALERT_WARNING_COLOR: Key<Color> =
+    Key::new("org.linebender.druid.theme.alert_warning_color");
+/// The accent color of an [`Alert`] with [`Severity::Error`].
+///
+/// [`Alert`]: crate::widget::Alert
+/// [`Severity::Error`]: crate::widget::Severity::Error
+pub const ALERT_ERROR_COLOR: Key<Color> = Key::new("org.linebender.druid.theme.alert_error_color");
 
 pub const TEXT_SIZE_NORMAL: Key<f64> = Key::new("org.linebender.druid.theme.text_size_normal");
 pub const TEXT_SIZE_LARGE: Key<f64> = Key::new("org.linebender.druid.theme.text_size_large");
@@ -115,6 +159,66 @@ pub const SCROLLBAR_EDGE_WIDTH: Key<f64> =
 /// Minimum length for any scrollbar to be when measured on that
 /// scrollbar's primary axis.
 pub const SCROLLBAR_MIN_SIZE: Key<f64> = Key::new("org.linebender.theme.scrollbar_min_size");
+/// Whether turning the mouse wheel with shift held down scrolls horizontally.
+pub const SCROLL_SHIFT_HORIZONTAL: Key<bool> =
+    Key::new("org.linebender.druid.theme.scroll_shift_horizontal");
+/// The application's UI zoom factor, on top of the platform's scale factor.
+///
+/// The metrics and fonts in the theme are multiplied by it. It is changed with the
+/// [`ZOOM_IN`], [`ZOOM_OUT`], [`RESET_ZOOM`] and [`SET_ZOOM`] commands; setting it in the
+/// env that the application starts with starts the application zoomed.
+///
+/// There are no keyboard shortcuts for zooming unless the application adds the menu items
+/// in [`menu::sys::common`], such as [`zoom_in`].
+///
+/// [`ZOOM_IN`]: crate::commands::ZOOM_IN
+/// [`ZOOM_OUT`]: crate::commands::ZOOM_OUT
+/// [`RESET_ZOOM`]: crate::commands::RESET_ZOOM
+/// [`SET_ZOOM`]: crate::commands::SET_ZOOM
+/// [`menu::sys::common`]: crate::menu::sys::common
+/// [`zoom_in`]: crate::menu::sys::common::zoom_in
+pub const UI_ZOOM: Key<f64> = Key::new("org.linebender.druid.theme.ui_zoom");
+
+/// The kind of input that the user interacted with most recently.
+///
+/// This is kept up to date by druid, in all windows; setting it has no
+/// effect. Widgets can use it to only draw focus rings when the user
+/// navigates with the keyboard, or to make their hit targets larger when the
+/// user uses a touchscreen. Like any other key, a change shows up in
+/// [`update`], through [`UpdateCtx::env_key_changed`].
+///
+/// [`update`]: crate::Widget::update
+/// [`UpdateCtx::env_key_changed`]: crate::UpdateCtx::env_key_changed
+pub const INPUT_MODE: Key<InputMode> = Key::new("org.linebender.druid.theme.input_mode");
+
+/// The zoom levels that [`ZOOM_IN`] and [`ZOOM_OUT`] step through.
+///
+/// [`ZOOM_IN`]: crate::commands::ZOOM_IN
+/// [`ZOOM_OUT`]: crate::commands::ZOOM_OUT
+const ZOOM_LEVELS: &[f64] = &[
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+
+/// The metrics that are scaled by [`UI_ZOOM`].
+///
+/// Border widths are left out, so that hairlines stay hairlines.
+const ZOOMED_METRICS: &[Key<f64>] = &[
+    TEXT_SIZE_NORMAL,
+    TEXT_SIZE_LARGE,
+    BASIC_WIDGET_HEIGHT,
+    WIDE_WIDGET_WIDTH,
+    BORDERED_WIDGET_HEIGHT,
+    PROGRESS_BAR_RADIUS,
+    BUTTON_BORDER_RADIUS,
+    TEXTBOX_BORDER_RADIUS,
+    WIDGET_PADDING_HORIZONTAL,
+    WIDGET_PADDING_VERTICAL,
+    WIDGET_CONTROL_COMPONENT_PADDING,
+    SCROLLBAR_WIDTH,
+    SCROLLBAR_PAD,
+    SCROLLBAR_RADIUS,
+    SCROLLBAR_MIN_SIZE,
+];
 
 /// An initial theme.
 pub(crate) fn add_to_env(env: Env) -> Env {
@@ -145,7 +249,20 @@ pub(crate) fn add_to_env(env: Env) -> Env {
         )
         .adding(SELECTED_TEXT_INACTIVE_BACKGROUND_COLOR, Color::grey8(0x74))
         .adding(SELECTION_TEXT_COLOR, Color::rgb8(0x00, 0x00, 0x00))
+        .adding(SEARCH_HIGHLIGHT_COLOR, Color::rgba8(0xe0, 0xa0, 0x20, 0x60))
+        .adding(
+            SEARCH_HIGHLIGHT_CURRENT_COLOR,
+            Color::rgba8(0xf0, 0xb0, 0x20, 0xc0),
+        )
         .adding(CURSOR_COLOR, Color::WHITE)
+        .adding(CURSOR_WIDTH, 1.0)
+        .adding(CURSOR_BLINK_INTERVAL, 500u64)
+        .adding(DOUBLE_CLICK_INTERVAL, 500u64)
+        .adding(DOUBLE_CLICK_DISTANCE, 4.0)
+        .adding(ERROR_COLOR, Color::rgb8(0xf0, 0x5a, 0x5a))
+        .adding(ALERT_INFO_COLOR, Color::rgb8(0x00, 0x8d, 0xdd))
+        .adding(ALERT_WARNING_COLOR, Color::rgb8(0xe8, 0xa3, 0x3d))
+        .adding(ALERT_ERROR_COLOR, Color::rgb8(0xf0, 0x5a, 0x5a))
         .adding(TEXT_SIZE_NORMAL, 15.0)
         .adding(TEXT_SIZE_LARGE, 24.0)
         .adding(BASIC_WIDGET_HEIGHT, 18.0)
@@ -163,6 +280,9 @@ pub(crate) fn add_to_env(env: Env) -> Env {
         .adding(SCROLLBAR_MIN_SIZE, 45.)
         .adding(SCROLLBAR_RADIUS, 5.)
         .adding(SCROLLBAR_EDGE_WIDTH, 1.)
+        .adding(SCROLL_SHIFT_HORIZONTAL, true)
+        .adding(UI_ZOOM, 1.0)
+        .adding(INPUT_MODE, InputMode::Mouse)
         .adding(WIDGET_PADDING_VERTICAL, 10.0)
         .adding(WIDGET_PADDING_HORIZONTAL, 8.0)
         .adding(WIDGET_CONTROL_COMPONENT_PADDING, 4.0)
@@ -184,7 +304,191 @@ pub(crate) fn add_to_env(env: Env) -> Env {
         )
 }
 
+/// Set the text editing keys from the user's system settings, where there
+/// are any.
+pub(crate) fn add_text_settings(env: &mut Env, settings: &TextSettings) {
+    if let Some(interval) = settings.caret_blink_interval {
+        env.set(CURSOR_BLINK_INTERVAL, interval.as_millis() as u64);
+    }
+    if let Some(width) = settings.caret_width {
+        env.set(CURSOR_WIDTH, width);
+    }
+    if let Some(color) = &settings.selection_color {
+        env.set(SELECTED_TEXT_BACKGROUND_COLOR, color.clone());
+    }
+    if let Some(color) = &settings.selected_text_color {
+        env.set(SELECTION_TEXT_COLOR, color.clone());
+    }
+}
+
+/// Set the mouse keys from the user's system settings, where there are any.
+pub(crate) fn add_mouse_settings(env: &mut Env, settings: &MouseSettings) {
+    if let Some(interval) = settings.double_click_interval {
+        env.set(DOUBLE_CLICK_INTERVAL, interval.as_millis() as u64);
+    }
+    if let Some(distance) = settings.double_click_distance {
+        env.set(DOUBLE_CLICK_DISTANCE, distance);
+    }
+}
+
+/// Apply changed system settings to `env`.
+///
+/// `system` is the env that the application started from, before it was
+/// customized; keys that the application has changed are left alone. It is
+/// updated to the new settings.
+pub(crate) fn update_system_settings(
+    env: &mut Env,
+    system: &mut Env,
+    text: &TextSettings,
+    mouse: &MouseSettings,
+) {
+    fn update<V: ValueType + Data>(key: Key<V>, env: &mut Env, old: &Env, new: &Env) {
+        if env.get(&key).same(&old.get(&key)) {
+            env.set(key.clone(), new.get(&key));
+        }
+    }
+
+    let mut new = system.clone();
+    add_text_settings(&mut new, text);
+    add_mouse_settings(&mut new, mouse);
+    update(CURSOR_BLINK_INTERVAL, env, system, &new);
+    update(CURSOR_WIDTH, env, system, &new);
+    update(SELECTED_TEXT_BACKGROUND_COLOR, env, system, &new);
+    update(SELECTION_TEXT_COLOR, env, system, &new);
+    update(DOUBLE_CLICK_INTERVAL, env, system, &new);
+    update(DOUBLE_CLICK_DISTANCE, env, system, &new);
+    *system = new;
+}
+
+/// Returns `env` with the metrics and fonts of the theme scaled by `zoom`.
+///
+/// `env` is the env at a zoom of `1.0`.
+pub(crate) fn zoomed(env: &Env, zoom: f64) -> Env {
+    let mut env = env.clone();
+    for key in ZOOMED_METRICS {
+        if let Ok(value) = env.try_get(key) {
+            env.set(key.clone(), value * zoom);
+        }
+    }
+    if let Ok(insets) = env.try_get(TEXTBOX_INSETS) {
+        let insets = Insets::new(
+            insets.x0 * zoom,
+            insets.y0 * zoom,
+            insets.x1 * zoom,
+            insets.y1 * zoom,
+        );
+        env.set(TEXTBOX_INSETS, insets);
+    }
+    for key in &[UI_FONT, UI_FONT_BOLD, UI_FONT_ITALIC] {
+        if let Ok(mut font) = env.try_get(key) {
+            font.size *= zoom;
+            env.set(key.clone(), font);
+        }
+    }
+    env.set(UI_ZOOM, zoom);
+    env
+}
+
+/// The next zoom level from `zoom`, in or out.
+///
+/// This is `zoom` itself if there is no level further in that direction.
+pub(crate) fn step_zoom(zoom: f64, zoom_in: bool) -> f64 {
+    // the zoom may have been set to something between the levels.
+    let next = if zoom_in {
+        ZOOM_LEVELS.iter().find(|&&level| level > zoom + 0.001)
+    } else {
+        ZOOM_LEVELS
+            .iter()
+            .rev()
+            .find(|&&level| level < zoom - 0.001)
+    };
+    next.copied().unwrap_or(zoom)
+}
+
+/// Limits `zoom` to the range of the zoom levels.
+pub(crate) fn clamp_zoom(zoom: f64) -> f64 {
+    let min = ZOOM_LEVELS[0];
+    let max = ZOOM_LEVELS[ZOOM_LEVELS.len() - 1];
+    if zoom.is_finite() {
+        zoom.clamp(min, max)
+    } else {
+        1.0
+    }
+}
+
 #[deprecated(since = "0.7.0", note = "use Env::default() instead")]
 pub fn init() -> Env {
     Env::default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use test_env_log::test;
+
+    #[test]
+    fn zoom_scales_metrics_and_fonts() {
+        let env = Env::default();
+        let zoomed = zoomed(&env, 2.0);
+        assert_eq!(zoomed.get(UI_ZOOM), 2.0);
+        assert_eq!(
+            zoomed.get(BASIC_WIDGET_HEIGHT),
+            env.get(BASIC_WIDGET_HEIGHT) * 2.0
+        );
+        assert_eq!(zoomed.get(UI_FONT).size, env.get(UI_FONT).size * 2.0);
+        assert_eq!(
+            zoomed.get(TEXTBOX_INSETS).x0,
+            env.get(TEXTBOX_INSETS).x0 * 2.0
+        );
+        assert_eq!(
+            zoomed.get(BUTTON_BORDER_WIDTH),
+            env.get(BUTTON_BORDER_WIDTH)
+        );
+    }
+
+    #[test]
+    fn zoom_steps() {
+        assert_eq!(step_zoom(1.0, true), 1.1);
+        assert_eq!(step_zoom(1.0, false), 0.9);
+        assert_eq!(step_zoom(1.2, true), 1.25);
+        assert_eq!(step_zoom(1.2, false), 1.1);
+        assert_eq!(step_zoom(3.0, true), 3.0);
+        assert_eq!(step_zoom(0.5, false), 0.5);
+        assert_eq!(clamp_zoom(10.0), 3.0);
+        assert_eq!(clamp_zoom(f64::NAN), 1.0);
+    }
+
+    #[test]
+    fn text_settings_keep_app_overrides() {
+        let mut env = Env::default();
+        add_text_settings(
+            &mut env,
+            &TextSettings {
+                caret_blink_interval: Some(Duration::from_millis(600)),
+                caret_width: Some(2.0),
+                ..Default::default()
+            },
+        );
+        let mut system = env.clone();
+        // the application sets its own caret width.
+        env.set(CURSOR_WIDTH, 3.0);
+
+        let settings = TextSettings {
+            caret_blink_interval: Some(Duration::ZERO),
+            caret_width: Some(1.0),
+            selection_color: Some(Color::rgb8(1, 2, 3)),
+            selected_text_color: None,
+        };
+        update_system_settings(&mut env, &mut system, &settings, &MouseSettings::default());
+        assert_eq!(env.get(CURSOR_BLINK_INTERVAL), 0);
+        assert_eq!(env.get(CURSOR_WIDTH), 3.0);
+        assert!(env
+            .get(SELECTED_TEXT_BACKGROUND_COLOR)
+            .same(&Color::rgb8(1, 2, 3)));
+        assert!(env
+            .get(SELECTION_TEXT_COLOR)
+            .same(&Env::default().get(SELECTION_TEXT_COLOR)));
+        assert_eq!(system.get(CURSOR_WIDTH), 1.0);
+    }
+}
@@ -56,7 +56,6 @@ pub trait ExtendDrain {
 impl<K, V> ExtendDrain for HashMap<K, V>
 where
     K: Eq + Hash + Copy,
-    V: Copy,
 {
     // Benchmarking this vs just extend+drain with a 10k entry map.
     //
@@ -76,6 +75,15 @@ where
     }
 }
 
+/// Strip the module path from a type name, keeping any generic parameters.
+pub(crate) fn short_type_name(name: &str) -> &str {
+    let end = name.find('<').unwrap_or(name.len());
+    match name[..end].rfind("::") {
+        Some(idx) => &name[idx + 2..],
+        None => name,
+    }
+}
+
 /// An enum for specifying whether an event was handled.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Handled {
@@ -0,0 +1,418 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A banner that tells the user about something that happened.
+
+use std::time::Duration;
+
+use crate::kurbo::{BezPath, Circle, Line};
+use crate::widget::prelude::*;
+use crate::widget::{Button, Label, LabelText, LineBreaking};
+use crate::{
+    theme, ArcStr, Color, Data, Key, Lens, LensExt, MouseButton, Point, Rect, TextLayout, WidgetPod,
+};
+use tracing::{instrument, trace};
+
+/// How long it takes to show or hide the alert, in seconds.
+const SHOW_TIME: f64 = 0.2;
+const PADDING: f64 = 8.0;
+const STRIPE_WIDTH: f64 = 4.0;
+const ICON_SIZE: f64 = 18.0;
+const DISMISS_SIZE: f64 = 16.0;
+
+/// How serious the thing an [`Alert`] tells about is.
+///
+/// This picks the color and icon of the alert; the colors are taken from
+/// the [`theme`].
+///
+/// [`theme`]: crate::theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub enum Severity {
+    /// Something the user may want to know.
+    Info,
+    /// Something that may need the user's attention.
+    Warning,
+    /// Something that went wrong.
+    Error,
+}
+
+impl Severity {
+    fn color_key(self) -> Key<Color> {
+        match self {
+            Severity::Info => theme::ALERT_INFO_COLOR,
+            Severity::Warning => theme::ALERT_WARNING_COLOR,
+            Severity::Error => theme::ALERT_ERROR_COLOR,
+        }
+    }
+
+    fn icon_text(self) -> &'static str {
+        match self {
+            Severity::Info => "i",
+            Severity::Warning | Severity::Error => "!",
+        }
+    }
+}
+
+/// A banner with an icon and a message, for things like "Your changes were
+/// saved" or "The connection was lost".
+///
+/// The alert can have an action button, added with [`with_action`]. With
+/// [`dismissible`] it also gets a close button, and whether it is shown is
+/// kept in the data; the alert slides open and closed when that changes.
+///
+/// [`with_action`]: Alert::with_action
+/// [`dismissible`]: Alert::dismissible
+pub struct Alert<T> {
+    severity: Severity,
+    icon: TextLayout<ArcStr>,
+    message: WidgetPod<T, Label<T>>,
+    action: Option<WidgetPod<T, Box<dyn Widget<T>>>>,
+    visible: Option<Box<dyn VisibleStore<T>>>,
+    /// How far the alert is shown, from `0.0` when hidden to `1.0`.
+    openness: f64,
+    /// The height of the alert when it is fully shown.
+    full_height: f64,
+    icon_rect: Rect,
+    /// Where the dismiss button is, if there is one.
+    dismiss_rect: Option<Rect>,
+    dismiss_hot: bool,
+}
+
+/// Where it is kept whether a dismissible alert is shown.
+trait VisibleStore<T> {
+    fn get(&self, data: &T) -> bool;
+    fn put(&self, data: &mut T, visible: bool);
+}
+
+impl<T, L: Lens<T, bool>> VisibleStore<T> for L {
+    fn get(&self, data: &T) -> bool {
+        LensExt::get(self, data)
+    }
+
+    fn put(&self, data: &mut T, visible: bool) {
+        LensExt::put(self, data, visible)
+    }
+}
+
+impl<T: Data> Alert<T> {
+    /// Create a new `Alert` with the given severity and message.
+    pub fn new(severity: Severity, message: impl Into<LabelText<T>>) -> Self {
+        let mut icon = TextLayout::from_text(severity.icon_text());
+        icon.set_font(theme::UI_FONT_BOLD);
+        icon.set_text_color(Color::WHITE);
+        let message = Label::new(message).with_line_break_mode(LineBreaking::WordWrap);
+        Alert {
+            severity,
+            icon,
+            message: WidgetPod::new(message),
+            action: None,
+            visible: None,
+            openness: 1.0,
+            full_height: 0.0,
+            icon_rect: Rect::ZERO,
+            dismiss_rect: None,
+            dismiss_hot: false,
+        }
+    }
+
+    /// Builder-style method to add a button after the message, which calls
+    /// `action` when it is clicked.
+    pub fn with_action(
+        mut self,
+        title: impl Into<LabelText<T>>,
+        action: impl Fn(&mut EventCtx, &mut T, &Env) + 'static,
+    ) -> Self {
+        let button = Button::new(title).on_click(action);
+        self.action = Some(WidgetPod::new(Box::new(button)));
+        self
+    }
+
+    /// Builder-style method to give the alert a close button.
+    ///
+    /// The alert is shown while the value at `visible` is `true`, and the
+    /// close button sets it to `false`.
+    pub fn dismissible(mut self, visible: impl Lens<T, bool> + 'static) -> Self {
+        self.visible = Some(Box::new(visible));
+        self
+    }
+
+    fn is_visible(&self, data: &T) -> bool {
+        self.visible
+            .as_ref()
+            .map(|visible| visible.get(data))
+            .unwrap_or(true)
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.openness <= 0.0
+    }
+}
+
+impl<T: Data> Widget<T> for Alert<T> {
+    #[instrument(name = "Alert", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        let dismiss_rect = self.dismiss_rect;
+        let on_dismiss = |pos: Point| dismiss_rect.is_some_and(|rect| rect.contains(pos));
+        match event {
+            Event::MouseDown(mouse)
+                if mouse.button == MouseButton::Left && on_dismiss(mouse.pos) =>
+            {
+                ctx.set_active(true);
+                ctx.request_paint();
+                return;
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.request_paint();
+                if on_dismiss(mouse.pos) {
+                    if let Some(visible) = &self.visible {
+                        trace!("Alert dismissed");
+                        visible.put(data, false);
+                    }
+                }
+                return;
+            }
+            Event::MouseMove(mouse) => {
+                let hot = on_dismiss(mouse.pos);
+                if hot != self.dismiss_hot {
+                    self.dismiss_hot = hot;
+                    ctx.request_paint();
+                }
+            }
+            Event::AnimFrame(interval) => {
+                let step = Duration::from_nanos(*interval).as_secs_f64() / SHOW_TIME;
+                let target = if self.is_visible(data) { 1.0 } else { 0.0 };
+                self.openness = if target > self.openness {
+                    (self.openness + step).min(target)
+                } else {
+                    (self.openness - step).max(target)
+                };
+                if (self.openness - target).abs() > f64::EPSILON {
+                    ctx.request_anim_frame();
+                }
+                ctx.request_layout();
+            }
+            _ => (),
+        }
+
+        if !self.is_hidden() || event.should_propagate_to_hidden() {
+            self.message.event(ctx, event, data, env);
+            if let Some(action) = &mut self.action {
+                action.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    #[instrument(name = "Alert", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                self.openness = if self.is_visible(data) { 1.0 } else { 0.0 };
+            }
+            LifeCycle::HotChanged(false) => {
+                self.dismiss_hot = false;
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+        self.message.lifecycle(ctx, event, data, env);
+        if let Some(action) = &mut self.action {
+            action.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Alert", level = "trace", skip(self, ctx, old_data, data, env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if self.is_visible(old_data) != self.is_visible(data) {
+            ctx.request_anim_frame();
+        }
+        if ctx.env_changed() && self.icon.needs_rebuild_after_update(ctx) {
+            ctx.request_layout();
+        }
+        self.message.update(ctx, data, env);
+        if let Some(action) = &mut self.action {
+            action.update(ctx, data, env);
+        }
+    }
+
+    #[instrument(name = "Alert", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Alert");
+        self.icon.rebuild_if_needed(ctx.text(), env);
+
+        let action_size = match &mut self.action {
+            Some(action) => action.layout(ctx, &bc.loosen(), data, env),
+            None => Size::ZERO,
+        };
+        let message_x = STRIPE_WIDTH + PADDING * 2.0 + ICON_SIZE;
+        let mut trailing = PADDING;
+        if self.action.is_some() {
+            trailing += action_size.width + PADDING;
+        }
+        if self.visible.is_some() {
+            trailing += DISMISS_SIZE + PADDING;
+        }
+        let message_width = if bc.is_width_bounded() {
+            (bc.max().width - message_x - trailing).max(0.0)
+        } else {
+            f64::INFINITY
+        };
+        let message_bc = BoxConstraints::new(Size::ZERO, Size::new(message_width, f64::INFINITY));
+        let message_size = self.message.layout(ctx, &message_bc, data, env);
+
+        let content_height = message_size
+            .height
+            .max(ICON_SIZE)
+            .max(action_size.height)
+            .max(DISMISS_SIZE);
+        self.full_height = content_height + PADDING * 2.0;
+        let width = bc
+            .constrain(Size::new(message_x + message_size.width + trailing, 0.0))
+            .width;
+        let center_y = |height: f64| PADDING + (content_height - height) / 2.0;
+
+        self.icon_rect = Rect::from_origin_size(
+            (STRIPE_WIDTH + PADDING, center_y(ICON_SIZE)),
+            (ICON_SIZE, ICON_SIZE),
+        );
+        let message_origin = Point::new(message_x, center_y(message_size.height));
+        self.message.set_origin(ctx, data, env, message_origin);
+
+        let mut x = width - PADDING;
+        self.dismiss_rect = if self.visible.is_some() {
+            x -= DISMISS_SIZE;
+            let rect =
+                Rect::from_origin_size((x, center_y(DISMISS_SIZE)), (DISMISS_SIZE, DISMISS_SIZE));
+            x -= PADDING;
+            Some(rect)
+        } else {
+            None
+        };
+        if let Some(action) = &mut self.action {
+            let origin = Point::new(x - action_size.width, center_y(action_size.height));
+            action.set_origin(ctx, data, env, origin);
+        }
+
+        let size = bc.constrain(Size::new(width, self.full_height * self.openness));
+        trace!("Computed layout: size={}, openness={}", size, self.openness);
+        size
+    }
+
+    #[instrument(name = "Alert", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        if self.is_hidden() {
+            return;
+        }
+        let size = ctx.size();
+        let accent = env.get(self.severity.color_key());
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        let rect = Rect::new(0.0, 0.0, size.width, self.full_height);
+
+        ctx.with_save(|ctx| {
+            ctx.clip(size.to_rect());
+            let banner = rect.inset(-0.5).to_rounded_rect(radius);
+            ctx.fill(banner, &accent.clone().with_alpha(0.15));
+            ctx.stroke(banner, &accent, 1.0);
+            let stripe = Rect::new(0.0, 0.0, STRIPE_WIDTH, self.full_height);
+            ctx.fill(stripe.to_rounded_rect((radius, 0.0, 0.0, radius)), &accent);
+
+            // the warning icon is a triangle, the others are circles.
+            let icon = self.icon_rect;
+            if self.severity == Severity::Warning {
+                let mut triangle = BezPath::new();
+                triangle.move_to((icon.center().x, icon.y0));
+                triangle.line_to((icon.x1, icon.y1));
+                triangle.line_to((icon.x0, icon.y1));
+                triangle.close_path();
+                ctx.fill(triangle, &accent);
+            } else {
+                ctx.fill(Circle::new(icon.center(), ICON_SIZE / 2.0), &accent);
+            }
+            let icon_size = self.icon.size();
+            let icon_origin = icon.center() - icon_size.to_vec2() / 2.0;
+            self.icon.draw(ctx, icon_origin);
+
+            self.message.paint(ctx, data, env);
+            if let Some(action) = &mut self.action {
+                action.paint(ctx, data, env);
+            }
+
+            if let Some(dismiss) = self.dismiss_rect {
+                if self.dismiss_hot {
+                    ctx.fill(
+                        dismiss.to_rounded_rect(radius),
+                        &accent.clone().with_alpha(0.3),
+                    );
+                }
+                let cross = dismiss.inset(-DISMISS_SIZE / 4.0);
+                let color = env.get(theme::TEXT_COLOR);
+                ctx.stroke(
+                    Line::new((cross.x0, cross.y0), (cross.x1, cross.y1)),
+                    &color,
+                    1.5,
+                );
+                ctx.stroke(
+                    Line::new((cross.x1, cross.y0), (cross.x0, cross.y1)),
+                    &color,
+                    1.5,
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use crate::widget::Flex;
+    use crate::{WidgetExt, WidgetId};
+    use test_env_log::test;
+
+    #[test]
+    fn action_and_dismissal() {
+        let id = WidgetId::next();
+        // whether the alert is shown, and how often "Undo" was clicked.
+        let alert = Alert::new(Severity::Warning, "Item deleted")
+            .with_action("Undo", |_, data: &mut (bool, u32), _| data.1 += 1)
+            .dismissible(lens!((bool, u32), 0))
+            .with_id(id);
+        let widget = Flex::column().with_child(alert);
+
+        Harness::create_simple((true, 0), widget, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            let rect = harness.window_layout_rect(id).unwrap();
+            assert!(rect.height() > 0.0);
+
+            // the close button is at the end, and the action right before it.
+            let dismiss = Point::new(rect.x1 - PADDING - DISMISS_SIZE / 2.0, rect.center().y);
+            let action = Point::new(dismiss.x - DISMISS_SIZE / 2.0 - PADDING - 4.0, dismiss.y);
+            harness.mouse_click(action);
+            assert_eq!(*harness.data(), (true, 1));
+
+            harness.mouse_click(dismiss);
+            assert_eq!(*harness.data(), (false, 1));
+            harness.advance_time(Duration::from_secs_f64(SHOW_TIME * 2.0));
+            harness.just_layout();
+            assert_eq!(harness.window_layout_rect(id).unwrap().height(), 0.0);
+
+            // showing it again from the data slides it back open.
+            harness.update_data(|data| data.0 = true);
+            harness.advance_time(Duration::from_secs_f64(SHOW_TIME * 2.0));
+            harness.just_layout();
+            assert_eq!(harness.window_layout_rect(id).unwrap(), rect);
+        });
+    }
+}
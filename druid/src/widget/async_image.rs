@@ -0,0 +1,431 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that loads an image in the background.
+
+use crate::image_cache::{ImageCache, ImageSource, ImageState, IMAGE_LOADED};
+use crate::piet::InterpolationMode;
+use crate::widget::prelude::*;
+use crate::widget::{FillStrat, SizedBox, Spinner};
+use crate::{Data, Point, WidgetPod};
+use tracing::{instrument, trace};
+
+/// A widget that loads and displays an image from an [`ImageSource`].
+///
+/// The image is loaded and decoded off the UI thread, through an
+/// [`ImageCache`]. While it is loading a placeholder is shown (a [`Spinner`]
+/// by default), and if it fails to load an error widget is shown instead
+/// (by default, nothing).
+///
+/// Images are loaded from the cache set with [`with_cache`]; if none is set,
+/// the widget creates a cache of its own. Sharing a cache between widgets
+/// means that an image is only decoded once, no matter how many times it is
+/// shown or how often the widgets showing it are rebuilt.
+///
+/// # Examples
+///
+/// ```
+/// # use druid::{Data, Lens, Widget, WidgetExt};
+/// use druid::image_cache::ImageSource;
+/// use druid::widget::{AsyncImage, Label};
+///
+/// #[derive(Clone, Data, Lens)]
+/// struct Photo {
+///     thumbnail: ImageSource,
+/// }
+///
+/// fn photo() -> impl Widget<Photo> {
+///     AsyncImage::new()
+///         .with_error_widget(Label::new("could not load photo"))
+///         .lens(Photo::thumbnail)
+///         .fix_size(64.0, 64.0)
+/// }
+/// ```
+///
+/// [`with_cache`]: AsyncImage::with_cache
+pub struct AsyncImage {
+    cache: Option<ImageCache>,
+    state: ImageState,
+    placeholder: WidgetPod<(), Box<dyn Widget<()>>>,
+    error: WidgetPod<(), Box<dyn Widget<()>>>,
+    fill: FillStrat,
+    interpolation: InterpolationMode,
+}
+
+impl AsyncImage {
+    /// Create a new `AsyncImage`.
+    pub fn new() -> Self {
+        AsyncImage {
+            cache: None,
+            state: ImageState::Loading,
+            placeholder: WidgetPod::new(Box::new(Spinner::new())),
+            error: WidgetPod::new(Box::new(SizedBox::empty())),
+            fill: FillStrat::default(),
+            interpolation: InterpolationMode::Bilinear,
+        }
+    }
+
+    /// Builder-style method to set the [`ImageCache`] used to load images.
+    pub fn with_cache(mut self, cache: ImageCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Builder-style method to set the widget shown while the image is loading.
+    pub fn with_placeholder(mut self, placeholder: impl Widget<()> + 'static) -> Self {
+        self.placeholder = WidgetPod::new(Box::new(placeholder));
+        self
+    }
+
+    /// Builder-style method to set the widget shown if the image fails to load.
+    pub fn with_error_widget(mut self, error: impl Widget<()> + 'static) -> Self {
+        self.error = WidgetPod::new(Box::new(error));
+        self
+    }
+
+    /// Builder-style method for specifying the fill strategy.
+    pub fn fill_mode(mut self, mode: FillStrat) -> Self {
+        self.fill = mode;
+        self
+    }
+
+    /// Modify the widget's fill strategy.
+    pub fn set_fill_mode(&mut self, mode: FillStrat) {
+        self.fill = mode;
+    }
+
+    /// Builder-style method for specifying the interpolation strategy.
+    pub fn interpolation_mode(mut self, interpolation: InterpolationMode) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Modify the widget's interpolation mode.
+    pub fn set_interpolation_mode(&mut self, interpolation: InterpolationMode) {
+        self.interpolation = interpolation;
+    }
+
+    /// Returns the current state of the image.
+    pub fn state(&self) -> &ImageState {
+        &self.state
+    }
+
+    /// Ask the cache for the current state of the image.
+    fn refresh(&mut self, source: &ImageSource) {
+        if let Some(cache) = &self.cache {
+            self.state = cache.get(source);
+        }
+    }
+}
+
+impl Default for AsyncImage {
+    fn default() -> Self {
+        AsyncImage::new()
+    }
+}
+
+impl Widget<ImageSource> for AsyncImage {
+    #[instrument(
+        name = "AsyncImage",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut ImageSource, env: &Env) {
+        if let Event::WindowDisconnected = event {
+            if let Some(cache) = &self.cache {
+                cache.remove_window(ctx.window_id());
+            }
+        }
+        if let Event::Command(cmd) = event {
+            if let Some(source) = cmd.get(IMAGE_LOADED) {
+                if source.same(data) {
+                    trace!("image loaded: {:?}", source);
+                    self.refresh(data);
+                    ctx.request_layout();
+                }
+            }
+        }
+
+        match self.state {
+            ImageState::Loading => self.placeholder.event(ctx, event, &mut (), env),
+            ImageState::Failed(_) => self.error.event(ctx, event, &mut (), env),
+            ImageState::Ready(_) => (),
+        }
+    }
+
+    #[instrument(
+        name = "AsyncImage",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &ImageSource,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            if self.cache.is_none() {
+                self.cache = Some(ImageCache::new(ctx.get_external_handle()));
+            }
+            self.refresh(data);
+        }
+        self.placeholder.lifecycle(ctx, event, &(), env);
+        self.error.lifecycle(ctx, event, &(), env);
+    }
+
+    #[instrument(
+        name = "AsyncImage",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &ImageSource,
+        data: &ImageSource,
+        env: &Env,
+    ) {
+        if !old_data.same(data) {
+            self.refresh(data);
+            ctx.request_layout();
+        }
+        self.placeholder.update(ctx, &(), env);
+        self.error.update(ctx, &(), env);
+    }
+
+    #[instrument(name = "AsyncImage", level = "trace", skip(self, ctx, bc, _data, env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &ImageSource,
+        env: &Env,
+    ) -> Size {
+        bc.debug_check("AsyncImage");
+
+        let child = match &self.state {
+            ImageState::Loading => &mut self.placeholder,
+            ImageState::Failed(_) => &mut self.error,
+            ImageState::Ready(image) => {
+                // Same as `Image`: if only one axis is bounded, keep the aspect
+                // ratio; otherwise use the image's own size.
+                let max = bc.max();
+                let image_size = image.size();
+                let size = if bc.is_width_bounded() && !bc.is_height_bounded() {
+                    let ratio = max.width / image_size.width;
+                    Size::new(max.width, ratio * image_size.height)
+                } else if bc.is_height_bounded() && !bc.is_width_bounded() {
+                    let ratio = max.height / image_size.height;
+                    Size::new(ratio * image_size.width, max.height)
+                } else {
+                    bc.constrain(image_size)
+                };
+                trace!("Computed size: {}", size);
+                return size;
+            }
+        };
+
+        let size = child.layout(ctx, bc, &(), env);
+        child.set_origin(ctx, &(), env, Point::ORIGIN);
+        trace!("Computed size: {}", size);
+        size
+    }
+
+    #[instrument(name = "AsyncImage", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &ImageSource, env: &Env) {
+        let image_size = match &self.state {
+            ImageState::Loading => return self.placeholder.paint(ctx, &(), env),
+            ImageState::Failed(_) => return self.error.paint(ctx, &(), env),
+            ImageState::Ready(image) => image.size(),
+        };
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return,
+        };
+
+        let fill = self.fill;
+        let interpolation = self.interpolation;
+        ctx.with_save(|ctx| {
+            let size = ctx.size();
+            if fill != FillStrat::Contain {
+                ctx.clip(size.to_rect());
+            }
+            ctx.transform(fill.affine_to_fill(size, image_size));
+            // if the image was evicted in the meantime, it is reloaded, and
+            // we will be told when it arrives.
+            cache.draw(ctx, data, image_size.to_rect(), interpolation);
+        });
+    }
+}
+
+#[cfg(all(test, feature = "png"))]
+mod tests {
+    use super::*;
+    use crate::image::{codecs::png::PngEncoder, ColorType};
+    use crate::image_cache::ImageState;
+    use crate::testing::Harness;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::{mpsc, Mutex};
+    use std::time::{Duration, Instant};
+    use test_env_log::test;
+
+    /// A 4x2 PNG.
+    fn png() -> Vec<u8> {
+        let mut data = Vec::new();
+        PngEncoder::new(&mut data)
+            .encode(&[255u8; 4 * 2 * 4], 4, 2, ColorType::Rgba8)
+            .unwrap();
+        data
+    }
+
+    /// Creates the image, with a cache that talks to the harness, once it is
+    /// added, and shares its state and size after each layout with the test.
+    struct Probe {
+        fetcher: Option<Box<dyn FnOnce(ImageCache) -> ImageCache>>,
+        image: Option<AsyncImage>,
+        shown: Rc<RefCell<Vec<(String, Size)>>>,
+    }
+
+    impl Widget<ImageSource> for Probe {
+        fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut ImageSource, env: &Env) {
+            if let Some(image) = &mut self.image {
+                image.event(ctx, event, data, env);
+            }
+        }
+
+        fn lifecycle(
+            &mut self,
+            ctx: &mut LifeCycleCtx,
+            event: &LifeCycle,
+            data: &ImageSource,
+            env: &Env,
+        ) {
+            if let LifeCycle::WidgetAdded = event {
+                let with_fetcher = self.fetcher.take().unwrap();
+                let cache = with_fetcher(ImageCache::new(ctx.get_external_handle()));
+                self.image = Some(
+                    AsyncImage::new()
+                        .with_cache(cache)
+                        .with_placeholder(SizedBox::empty().width(10.).height(10.))
+                        .with_error_widget(SizedBox::empty().width(30.).height(30.)),
+                );
+            }
+            if let Some(image) = &mut self.image {
+                image.lifecycle(ctx, event, data, env);
+            }
+        }
+
+        fn update(
+            &mut self,
+            ctx: &mut UpdateCtx,
+            old_data: &ImageSource,
+            data: &ImageSource,
+            env: &Env,
+        ) {
+            if let Some(image) = &mut self.image {
+                image.update(ctx, old_data, data, env);
+            }
+        }
+
+        fn layout(
+            &mut self,
+            ctx: &mut LayoutCtx,
+            bc: &BoxConstraints,
+            data: &ImageSource,
+            env: &Env,
+        ) -> Size {
+            let image = self.image.as_mut().unwrap();
+            let size = image.layout(ctx, &bc.loosen(), data, env);
+            let state = match image.state() {
+                ImageState::Loading => "loading".to_string(),
+                ImageState::Ready(_) => "ready".to_string(),
+                ImageState::Failed(err) => format!("failed: {}", err),
+            };
+            self.shown.borrow_mut().push((state, size));
+            size
+        }
+
+        fn paint(&mut self, ctx: &mut PaintCtx, data: &ImageSource, env: &Env) {
+            if let Some(image) = &mut self.image {
+                image.paint(ctx, data, env);
+            }
+        }
+    }
+
+    #[test]
+    fn placeholder_then_image_then_error() {
+        // the fetcher waits for the test to let each fetch finish.
+        let (release, gate) = mpsc::channel::<()>();
+        let gate = Mutex::new(gate);
+        let fetcher = move |url: &str| {
+            let _ = gate.lock().unwrap().recv();
+            if url.ends_with("found.png") {
+                Ok(png())
+            } else {
+                Err(format!("{} not found", url))
+            }
+        };
+        let shown = Rc::new(RefCell::new(Vec::new()));
+        let probe = Probe {
+            fetcher: Some(Box::new(move |cache| cache.with_fetcher(fetcher))),
+            image: None,
+            shown: shown.clone(),
+        };
+
+        let found = ImageSource::url("https://example.com/found.png");
+        Harness::create_simple(found, probe, |harness| {
+            // lay out until the image is no longer loading.
+            let wait_for_load = |harness: &mut Harness<ImageSource>| {
+                let start = Instant::now();
+                loop {
+                    harness.process_commands();
+                    harness.just_layout();
+                    if shown.borrow().last().unwrap().0 != "loading" {
+                        break;
+                    }
+                    assert!(start.elapsed() < Duration::from_secs(10), "load timed out");
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                shown.borrow_mut().pop().unwrap()
+            };
+
+            harness.send_initial_events();
+            harness.just_layout();
+            let placeholder = ("loading".to_string(), Size::new(10., 10.));
+            assert_eq!(shown.borrow().last(), Some(&placeholder));
+
+            release.send(()).unwrap();
+            let loaded = wait_for_load(harness);
+            assert_eq!(loaded, ("ready".to_string(), Size::new(4., 2.)));
+
+            let missing = ImageSource::url("https://example.com/missing.png");
+            harness.update_data(|data| *data = missing);
+            harness.just_layout();
+            assert_eq!(shown.borrow().last(), Some(&placeholder));
+
+            release.send(()).unwrap();
+            let failed = wait_for_load(harness);
+            assert_eq!(
+                failed,
+                (
+                    "failed: https://example.com/missing.png not found".to_string(),
+                    Size::new(30., 30.)
+                )
+            );
+        });
+    }
+}
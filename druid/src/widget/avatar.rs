@@ -0,0 +1,233 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A round picture of a person, with their initials when there is no picture.
+
+use crate::kurbo::Circle;
+use crate::widget::prelude::*;
+use crate::widget::{FillStrat, Image, LabelText};
+use crate::{theme, Color, Data, ImageBuf, Point, TextLayout, WidgetPod};
+use tracing::{instrument, trace};
+
+type PresenceFn<T> = Box<dyn Fn(&T, &Env) -> Presence>;
+
+const DEFAULT_DIAMETER: f64 = 32.0;
+
+/// The colors behind the initials; a name always gets the same one.
+const INITIALS_COLORS: [Color; 6] = [
+    Color::rgb8(0x3b, 0x82, 0xc4),
+    Color::rgb8(0x2e, 0x9c, 0x6f),
+    Color::rgb8(0xc4, 0x6a, 0x3b),
+    Color::rgb8(0x8e, 0x5b, 0xc4),
+    Color::rgb8(0xc4, 0x3b, 0x6f),
+    Color::rgb8(0x5b, 0x8e, 0x8e),
+];
+
+/// Whether a person is around, shown as a dot on their [`Avatar`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub enum Presence {
+    /// The person is online.
+    Online,
+    /// The person is online, but hasn't done anything in a while.
+    Away,
+    /// The person doesn't want to be disturbed.
+    Busy,
+    /// The person is not online.
+    Offline,
+}
+
+impl Presence {
+    fn color(self) -> Color {
+        match self {
+            Presence::Online => Color::rgb8(0x3b, 0xb5, 0x5e),
+            Presence::Away => Color::rgb8(0xe8, 0xa3, 0x3d),
+            Presence::Busy => Color::rgb8(0xf0, 0x5a, 0x5a),
+            Presence::Offline => Color::grey8(0x80),
+        }
+    }
+}
+
+/// A round picture of a person.
+///
+/// When there is no picture, the initials of the person's name are shown
+/// instead, on a color that is picked from the name. The avatar can also
+/// show a dot for the person's [`Presence`].
+pub struct Avatar<T> {
+    name: LabelText<T>,
+    initials: TextLayout<String>,
+    image: Option<WidgetPod<T, Image>>,
+    presence: Option<PresenceFn<T>>,
+    diameter: f64,
+}
+
+impl<T: Data> Avatar<T> {
+    /// Create a new `Avatar` for the person with the given name.
+    pub fn new(name: impl Into<LabelText<T>>) -> Self {
+        let mut initials = TextLayout::new();
+        initials.set_font(theme::UI_FONT_BOLD);
+        initials.set_text_color(Color::WHITE);
+        Avatar {
+            name: name.into(),
+            initials,
+            image: None,
+            presence: None,
+            diameter: DEFAULT_DIAMETER,
+        }
+    }
+
+    /// Builder-style method to set the picture of the person.
+    pub fn with_image(mut self, image: ImageBuf) -> Self {
+        self.set_image(Some(image));
+        self
+    }
+
+    /// Set the picture of the person, or remove it to show their initials.
+    pub fn set_image(&mut self, image: Option<ImageBuf>) {
+        self.image =
+            image.map(|image| WidgetPod::new(Image::new(image).fill_mode(FillStrat::Cover)));
+    }
+
+    /// Builder-style method to show the presence of the person, as returned
+    /// by the closure.
+    pub fn with_presence(mut self, presence: impl Fn(&T, &Env) -> Presence + 'static) -> Self {
+        self.presence = Some(Box::new(presence));
+        self
+    }
+
+    /// Builder-style method to set the diameter of the avatar.
+    ///
+    /// The default is 32.
+    pub fn with_diameter(mut self, diameter: f64) -> Self {
+        self.diameter = diameter;
+        self
+    }
+
+    fn update_initials(&mut self) {
+        let initials = self.name.with_display_text(initials);
+        self.initials.set_text(initials);
+        self.initials.set_text_size(self.diameter * 0.4);
+    }
+
+    fn initials_color(&self) -> Color {
+        let sum = self
+            .name
+            .with_display_text(|name| name.chars().map(|c| c as usize).sum::<usize>());
+        INITIALS_COLORS[sum % INITIALS_COLORS.len()].clone()
+    }
+}
+
+/// The first letter of the first and the last word of a name.
+fn initials(name: &str) -> String {
+    let mut words = name.split_whitespace();
+    let first = words.next().and_then(|word| word.chars().next());
+    let last = words.last().and_then(|word| word.chars().next());
+    first
+        .into_iter()
+        .chain(last)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+impl<T: Data> Widget<T> for Avatar<T> {
+    #[instrument(name = "Avatar", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Some(image) = &mut self.image {
+            image.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Avatar", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.name.resolve(data, env);
+            self.update_initials();
+        }
+        if let Some(image) = &mut self.image {
+            image.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Avatar", level = "trace", skip(self, ctx, old_data, data, env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if self.name.resolve(data, env) {
+            self.update_initials();
+            ctx.request_layout();
+        }
+        if ctx.env_changed() && self.initials.needs_rebuild_after_update(ctx) {
+            ctx.request_layout();
+        }
+        if self.presence.is_some() && !old_data.same(data) {
+            ctx.request_paint();
+        }
+        if let Some(image) = &mut self.image {
+            image.update(ctx, data, env);
+        }
+    }
+
+    #[instrument(name = "Avatar", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Avatar");
+        self.initials.rebuild_if_needed(ctx.text(), env);
+        let size = bc.constrain(Size::new(self.diameter, self.diameter));
+        if let Some(image) = &mut self.image {
+            image.layout(ctx, &BoxConstraints::tight(size), data, env);
+            image.set_origin(ctx, data, env, Point::ORIGIN);
+        }
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Avatar", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        let radius = size.min_side() / 2.0;
+        let circle = Circle::new(size.to_rect().center(), radius);
+
+        match &mut self.image {
+            Some(image) => ctx.with_save(|ctx| {
+                ctx.clip(circle);
+                image.paint(ctx, data, env);
+            }),
+            None => {
+                ctx.fill(circle, &self.initials_color());
+                let origin = circle.center - self.initials.size().to_vec2() / 2.0;
+                self.initials.draw(ctx, origin);
+            }
+        }
+
+        if let Some(presence) = &self.presence {
+            // the dot sits on the circle, at the bottom right.
+            let dot_radius = radius * 0.3;
+            let offset = radius * std::f64::consts::FRAC_1_SQRT_2;
+            let dot = Circle::new(circle.center + (offset, offset), dot_radius);
+            ctx.fill(dot, &presence(data, env).color());
+            ctx.stroke(dot, &env.get(theme::WINDOW_BACKGROUND_COLOR), 2.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn initials_of_names() {
+        assert_eq!(initials("Ada Lovelace"), "AL");
+        assert_eq!(initials("Grace Brewster Murray Hopper"), "GH");
+        assert_eq!(initials("plato"), "P");
+        assert_eq!(initials("  émile   zola "), "ÉZ");
+        assert_eq!(initials(""), "");
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A count shown in a bubble on the corner of another widget.
+
+use crate::widget::prelude::*;
+use crate::{theme, Color, Data, KeyOrValue, Point, Rect, TextLayout, WidgetPod};
+use tracing::{instrument, trace};
+
+const DEFAULT_MAX: usize = 99;
+const TEXT_SIZE: f64 = 11.0;
+const BUBBLE_PADDING: f64 = 4.0;
+
+type Count<T> = Box<dyn Fn(&T, &Env) -> usize>;
+
+/// A widget that shows a count in a bubble on the top right corner of its
+/// child, such as the number of unread messages on an icon.
+///
+/// The bubble is drawn over the child and sticks out of it a little; it
+/// is hidden when the count is zero. Counts above the maximum, 99 by
+/// default, are shown as "99+".
+pub struct Badge<T> {
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    count: Count<T>,
+    max: usize,
+    color: KeyOrValue<Color>,
+    text: TextLayout<String>,
+    /// Where the bubble is drawn, if it is shown.
+    bubble: Option<Rect>,
+}
+
+impl<T: Data> Badge<T> {
+    /// Create a new `Badge` on `child`, showing the count returned by the
+    /// closure.
+    pub fn new(
+        child: impl Widget<T> + 'static,
+        count: impl Fn(&T, &Env) -> usize + 'static,
+    ) -> Self {
+        let mut text = TextLayout::new();
+        text.set_font(theme::UI_FONT_BOLD);
+        text.set_text_size(TEXT_SIZE);
+        text.set_text_color(Color::WHITE);
+        Badge {
+            child: WidgetPod::new(Box::new(child)),
+            count: Box::new(count),
+            max: DEFAULT_MAX,
+            color: theme::ERROR_COLOR.into(),
+            text,
+            bubble: None,
+        }
+    }
+
+    /// Builder-style method to set the largest count that is shown as it is;
+    /// larger counts are shown as this number followed by a "+".
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Builder-style method to set the color of the bubble.
+    ///
+    /// The default is [`theme::ERROR_COLOR`].
+    ///
+    /// [`theme::ERROR_COLOR`]: crate::theme::ERROR_COLOR
+    pub fn with_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    fn update_text(&mut self, data: &T, env: &Env) -> bool {
+        let count = (self.count)(data, env);
+        let text = match count {
+            0 => String::new(),
+            count if count > self.max => format!("{}+", self.max),
+            count => count.to_string(),
+        };
+        let changed = self.text.text().map(|old| *old != text).unwrap_or(true);
+        self.text.set_text(text);
+        changed
+    }
+}
+
+impl<T: Data> Widget<T> for Badge<T> {
+    #[instrument(name = "Badge", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.child.event(ctx, event, data, env);
+    }
+
+    #[instrument(name = "Badge", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_text(data, env);
+        }
+        self.child.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(name = "Badge", level = "trace", skip(self, ctx, _old_data, data, env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        if self.update_text(data, env) {
+            ctx.request_layout();
+        }
+        if ctx.env_changed() && self.text.needs_rebuild_after_update(ctx) {
+            ctx.request_layout();
+        }
+        self.child.update(ctx, data, env);
+    }
+
+    #[instrument(name = "Badge", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Badge");
+        let size = self.child.layout(ctx, bc, data, env);
+        self.child.set_origin(ctx, data, env, Point::ORIGIN);
+        self.text.rebuild_if_needed(ctx.text(), env);
+
+        let mut paint_rect = size.to_rect() + self.child.compute_parent_paint_insets(size);
+        self.bubble = if self.text.text().is_none_or(|text| text.is_empty()) {
+            None
+        } else {
+            // the bubble is centered on the top right corner of the child.
+            let text_size = self.text.size();
+            let height = text_size.height + BUBBLE_PADDING;
+            let width = (text_size.width + BUBBLE_PADDING * 2.0).max(height);
+            let bubble = Rect::from_center_size((size.width, 0.0), (width, height));
+            paint_rect = paint_rect.union(bubble);
+            Some(bubble)
+        };
+        ctx.set_paint_insets(paint_rect - size.to_rect());
+
+        trace!("Computed layout: size={}, bubble={:?}", size, self.bubble);
+        size
+    }
+
+    #[instrument(name = "Badge", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.child.paint(ctx, data, env);
+        if let Some(bubble) = self.bubble {
+            let bubble_shape = bubble.to_rounded_rect(bubble.height() / 2.0);
+            ctx.fill(bubble_shape, &self.color.resolve(env));
+            ctx.stroke(bubble_shape, &env.get(theme::WINDOW_BACKGROUND_COLOR), 1.5);
+            let origin = bubble.center() - self.text.size().to_vec2() / 2.0;
+            self.text.draw(ctx, origin);
+        }
+    }
+}
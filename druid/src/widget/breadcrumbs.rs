@@ -0,0 +1,529 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A path of clickable segments, such as the folders leading to a file.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::commands::{BREADCRUMB_SELECTED, NAVIGATE_BACK};
+use crate::widget::prelude::*;
+use crate::{theme, ArcStr, Menu, MenuItem, MouseButton, Point, Rect, Selector, TextLayout};
+use tracing::{instrument, trace};
+
+/// Submitted to the breadcrumbs by their overflow menu, with the index of
+/// the segment.
+const SELECT_SEGMENT: Selector<usize> = Selector::new("druid.builtin.breadcrumbs-select-segment");
+
+const SEGMENT_PADDING: f64 = 4.0;
+const SEPARATOR: &str = "\u{203a}";
+const ELLIPSIS: &str = "\u{2026}";
+
+/// A path of clickable segments, such as the folders leading to a file, or
+/// the pages leading to a setting.
+///
+/// Clicking a segment cuts the path back to that segment, and submits
+/// [`BREADCRUMB_SELECTED`] as a notification with its index, so that the
+/// application can go there. The back button of the mouse, through
+/// [`NAVIGATE_BACK`], selects the segment before the last one.
+///
+/// When there isn't room for the whole path, segments are hidden from the
+/// middle, keeping the first and the last one. The hidden segments can be
+/// reached from a menu behind an ellipsis.
+///
+/// [`BREADCRUMB_SELECTED`]: crate::commands::BREADCRUMB_SELECTED
+/// [`NAVIGATE_BACK`]: crate::commands::NAVIGATE_BACK
+pub struct Breadcrumbs {
+    segments: Vec<TextLayout<ArcStr>>,
+    separator: TextLayout<ArcStr>,
+    ellipsis: TextLayout<ArcStr>,
+    /// The segments that are only in the overflow menu.
+    hidden: Range<usize>,
+    /// Where the parts of the path ended up in the last layout.
+    parts: Vec<(Part, Rect)>,
+    hot: Option<Part>,
+    pressed: Option<Part>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Part {
+    Segment(usize),
+    Ellipsis,
+}
+
+impl Breadcrumbs {
+    /// Create a new `Breadcrumbs` widget.
+    pub fn new() -> Self {
+        Breadcrumbs {
+            segments: Vec::new(),
+            separator: Self::text_layout(SEPARATOR),
+            ellipsis: Self::text_layout(ELLIPSIS),
+            hidden: 0..0,
+            parts: Vec::new(),
+            hot: None,
+            pressed: None,
+        }
+    }
+
+    fn text_layout(text: &str) -> TextLayout<ArcStr> {
+        let mut layout = TextLayout::from_text(text);
+        layout.set_text_color(theme::TEXT_COLOR);
+        layout
+    }
+
+    fn part_at(&self, pos: Point) -> Option<Part> {
+        self.parts
+            .iter()
+            .find(|(_, rect)| rect.contains(pos))
+            .map(|(part, _)| *part)
+    }
+
+    fn select(&mut self, ctx: &mut EventCtx, data: &mut Arc<Vec<String>>, index: usize) {
+        trace!("selected segment {}", index);
+        if index + 1 < data.len() {
+            Arc::make_mut(data).truncate(index + 1);
+        }
+        ctx.submit_notification(BREADCRUMB_SELECTED.with(index));
+    }
+
+    fn show_overflow_menu(&self, ctx: &mut EventCtx, data: &Arc<Vec<String>>) {
+        let mut menu = Menu::<()>::empty();
+        for index in self.hidden.clone() {
+            let item = MenuItem::new(data[index].as_str())
+                .command(SELECT_SEGMENT.with(index).to(ctx.widget_id()));
+            menu = menu.entry(item);
+        }
+        let rect = self
+            .parts
+            .iter()
+            .find(|(part, _)| *part == Part::Ellipsis)
+            .map(|(_, rect)| *rect)
+            .unwrap_or_default();
+        ctx.show_context_menu(menu, ctx.to_window(Point::new(rect.x0, rect.y1)));
+    }
+}
+
+impl Default for Breadcrumbs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<Arc<Vec<String>>> for Breadcrumbs {
+    #[instrument(
+        name = "Breadcrumbs",
+        level = "trace",
+        skip(self, ctx, event, data, _env)
+    )]
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut Arc<Vec<String>>,
+        _env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) => {
+                if let Some(&index) = cmd.get(SELECT_SEGMENT) {
+                    ctx.set_handled();
+                    if index < data.len() {
+                        self.select(ctx, data, index);
+                    }
+                } else if cmd.is(NAVIGATE_BACK) && data.len() > 1 {
+                    ctx.set_handled();
+                    self.select(ctx, data, data.len() - 2);
+                }
+            }
+            Event::MouseMove(mouse) => {
+                let hot = self.part_at(mouse.pos);
+                if hot != self.hot {
+                    self.hot = hot;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                self.pressed = self.part_at(mouse.pos);
+                if self.pressed.is_some() {
+                    ctx.set_active(true);
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.request_paint();
+                let pressed = self.pressed.take();
+                if pressed.is_some() && pressed == self.part_at(mouse.pos) {
+                    match pressed {
+                        Some(Part::Segment(index)) => self.select(ctx, data, index),
+                        Some(Part::Ellipsis) => self.show_overflow_menu(ctx, data),
+                        None => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "Breadcrumbs",
+        level = "trace",
+        skip(self, ctx, event, data, _env)
+    )]
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &Arc<Vec<String>>,
+        _env: &Env,
+    ) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                self.segments = data.iter().map(|s| Self::text_layout(s)).collect();
+            }
+            LifeCycle::HotChanged(false) => {
+                self.hot = None;
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "Breadcrumbs",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &Arc<Vec<String>>,
+        data: &Arc<Vec<String>>,
+        _env: &Env,
+    ) {
+        if !old_data.same(data) {
+            self.segments = data.iter().map(|s| Self::text_layout(s)).collect();
+            ctx.request_layout();
+        }
+        if ctx.env_changed() {
+            let layouts = self
+                .segments
+                .iter_mut()
+                .chain(Some(&mut self.separator))
+                .chain(Some(&mut self.ellipsis));
+            for layout in layouts {
+                if layout.needs_rebuild_after_update(ctx) {
+                    ctx.request_layout();
+                }
+            }
+        }
+    }
+
+    #[instrument(name = "Breadcrumbs", level = "trace", skip(self, ctx, bc, _data, env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Arc<Vec<String>>,
+        env: &Env,
+    ) -> Size {
+        bc.debug_check("Breadcrumbs");
+        for layout in self
+            .segments
+            .iter_mut()
+            .chain(Some(&mut self.separator))
+            .chain(Some(&mut self.ellipsis))
+        {
+            layout.rebuild_if_needed(ctx.text(), env);
+        }
+        let widths: Vec<f64> = self
+            .segments
+            .iter()
+            .map(|segment| segment.size().width + SEGMENT_PADDING * 2.0)
+            .collect();
+        let separator_width = self.separator.size().width;
+        let ellipsis_width = self.ellipsis.size().width + SEGMENT_PADDING * 2.0;
+        let width_of = |widths: &[f64]| {
+            widths.iter().sum::<f64>() + separator_width * (widths.len().max(1) - 1) as f64
+        };
+
+        // hide segments from the middle until the rest fits, but always keep
+        // the first and the last one.
+        let n = widths.len();
+        let mut hidden = 0..0;
+        if width_of(&widths) > bc.max().width && n > 2 {
+            hidden = 1..1;
+            while hidden.end < n - 1 {
+                hidden.end += 1;
+                let shown = widths[..hidden.start].iter().chain(&widths[hidden.end..]);
+                let width = shown.sum::<f64>()
+                    + ellipsis_width
+                    + separator_width * (n - hidden.len()) as f64;
+                if width <= bc.max().width {
+                    break;
+                }
+            }
+        }
+        self.hidden = hidden;
+
+        let height = self
+            .segments
+            .iter()
+            .chain(Some(&self.ellipsis))
+            .map(|layout| layout.size().height)
+            .fold(0.0, f64::max)
+            + SEGMENT_PADDING;
+        self.parts.clear();
+        let mut x = 0.0;
+        for (index, width) in widths.iter().enumerate() {
+            if self.hidden.contains(&index) {
+                if index == self.hidden.start {
+                    self.parts.push((
+                        Part::Ellipsis,
+                        Rect::new(x, 0.0, x + ellipsis_width, height),
+                    ));
+                    x += ellipsis_width + separator_width;
+                }
+                continue;
+            }
+            self.parts
+                .push((Part::Segment(index), Rect::new(x, 0.0, x + width, height)));
+            x += width + separator_width;
+        }
+        let width = (x - separator_width).max(0.0);
+
+        let size = bc.constrain(Size::new(width, height));
+        trace!("Computed layout: size={}, hidden={:?}", size, self.hidden);
+        size
+    }
+
+    #[instrument(name = "Breadcrumbs", level = "trace", skip(self, ctx, _data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Arc<Vec<String>>, env: &Env) {
+        let clip = ctx.size().to_rect();
+        ctx.clip(clip);
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        for (i, (part, rect)) in self.parts.iter().enumerate() {
+            if Some(*part) == self.hot {
+                let color = if Some(*part) == self.pressed {
+                    env.get(theme::BUTTON_DARK)
+                } else {
+                    env.get(theme::BUTTON_LIGHT)
+                };
+                ctx.fill(rect.to_rounded_rect(radius), &color);
+            }
+            let layout = match part {
+                Part::Segment(index) => &self.segments[*index],
+                Part::Ellipsis => &self.ellipsis,
+            };
+            let y = (rect.height() - layout.size().height) / 2.0;
+            layout.draw(ctx, (rect.x0 + SEGMENT_PADDING, y));
+            if i + 1 < self.parts.len() {
+                let y = (rect.height() - self.separator.size().height) / 2.0;
+                self.separator.draw(ctx, (rect.x1, y));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::SHOW_CONTEXT_MENU;
+    use crate::testing::Harness;
+    use crate::WidgetPod;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    /// Shares where the parts of the path are, the selected segments and
+    /// whether a menu was shown with the test.
+    struct Probe {
+        crumbs: WidgetPod<Arc<Vec<String>>, Breadcrumbs>,
+        parts: Rc<RefCell<Vec<(Part, Rect)>>>,
+        selected: Rc<RefCell<Vec<usize>>>,
+        menu_shown: Rc<Cell<bool>>,
+    }
+
+    impl Probe {
+        fn new() -> Self {
+            Probe {
+                crumbs: WidgetPod::new(Breadcrumbs::new()),
+                parts: Default::default(),
+                selected: Default::default(),
+                menu_shown: Default::default(),
+            }
+        }
+    }
+
+    impl Widget<Arc<Vec<String>>> for Probe {
+        fn event(
+            &mut self,
+            ctx: &mut EventCtx,
+            event: &Event,
+            data: &mut Arc<Vec<String>>,
+            env: &Env,
+        ) {
+            match event {
+                Event::Notification(notification) => {
+                    if let Some(index) = notification.get(BREADCRUMB_SELECTED) {
+                        self.selected.borrow_mut().push(*index);
+                    }
+                }
+                Event::Command(cmd) if cmd.is(SHOW_CONTEXT_MENU) => self.menu_shown.set(true),
+                _ => (),
+            }
+            self.crumbs.event(ctx, event, data, env);
+        }
+
+        fn lifecycle(
+            &mut self,
+            ctx: &mut LifeCycleCtx,
+            event: &LifeCycle,
+            data: &Arc<Vec<String>>,
+            env: &Env,
+        ) {
+            self.crumbs.lifecycle(ctx, event, data, env);
+        }
+
+        fn update(
+            &mut self,
+            ctx: &mut UpdateCtx,
+            _old_data: &Arc<Vec<String>>,
+            data: &Arc<Vec<String>>,
+            env: &Env,
+        ) {
+            self.crumbs.update(ctx, data, env);
+        }
+
+        fn layout(
+            &mut self,
+            ctx: &mut LayoutCtx,
+            bc: &BoxConstraints,
+            data: &Arc<Vec<String>>,
+            env: &Env,
+        ) -> Size {
+            let size = self.crumbs.layout(ctx, bc, data, env);
+            self.crumbs.set_origin(ctx, data, env, Point::ORIGIN);
+            *self.parts.borrow_mut() = self.crumbs.widget().parts.clone();
+            size
+        }
+
+        fn paint(&mut self, ctx: &mut PaintCtx, data: &Arc<Vec<String>>, env: &Env) {
+            self.crumbs.paint(ctx, data, env);
+        }
+    }
+
+    fn path() -> Arc<Vec<String>> {
+        let segments = [
+            "Home",
+            "Documents",
+            "Projects",
+            "Druid",
+            "Widgets",
+            "Breadcrumbs",
+        ];
+        Arc::new(segments.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn truncates_middle_when_narrow() {
+        let probe = Probe::new();
+        let parts = probe.parts.clone();
+        let wide = Size::new(2000., 40.);
+        Harness::create_with_render(
+            path(),
+            probe,
+            wide,
+            |harness| {
+                harness.send_initial_events();
+                harness.just_layout();
+                let shown: Vec<_> = parts.borrow().iter().map(|(part, _)| *part).collect();
+                assert_eq!(shown, (0..6).map(Part::Segment).collect::<Vec<_>>());
+            },
+            |_| {},
+        );
+
+        let probe = Probe::new();
+        let crumbs = probe.crumbs.id();
+        let parts = probe.parts.clone();
+        let selected = probe.selected.clone();
+        let menu_shown = probe.menu_shown.clone();
+        let narrow = Size::new(150., 40.);
+        Harness::create_with_render(
+            path(),
+            probe,
+            narrow,
+            |harness| {
+                harness.send_initial_events();
+                harness.just_layout();
+                let shown: Vec<_> = parts.borrow().iter().map(|(part, _)| *part).collect();
+                assert!(shown.len() < 6, "some segments are hidden: {:?}", shown);
+                assert_eq!(shown[0], Part::Segment(0));
+                assert_eq!(shown[1], Part::Ellipsis);
+                assert_eq!(shown.last(), Some(&Part::Segment(5)));
+                // the segments after the ellipsis are the last ones, in order.
+                for pair in shown[2..].windows(2) {
+                    assert!(matches!(pair, [Part::Segment(a), Part::Segment(b)] if a + 1 == *b));
+                }
+
+                let ellipsis = parts.borrow()[1].1.center();
+                harness.mouse_click(ellipsis);
+                assert!(menu_shown.get(), "the ellipsis shows the overflow menu");
+
+                // as if the second segment was picked from the menu.
+                harness.submit_command(SELECT_SEGMENT.with(1).to(crumbs));
+                assert_eq!(harness.data().len(), 2);
+                assert_eq!(selected.take(), [1]);
+            },
+            |_| {},
+        );
+    }
+
+    #[test]
+    fn click_navigates() {
+        let probe = Probe::new();
+        let parts = probe.parts.clone();
+        let selected = probe.selected.clone();
+        let wide = Size::new(2000., 40.);
+        Harness::create_with_render(
+            path(),
+            probe,
+            wide,
+            |harness| {
+                harness.send_initial_events();
+                harness.just_layout();
+                let center = |index: usize| parts.borrow()[index].1.center();
+
+                harness.mouse_click(center(2));
+                assert_eq!(harness.data().len(), 3);
+                assert_eq!(harness.data()[2], "Projects");
+                assert_eq!(selected.take(), [2]);
+
+                harness.just_layout();
+                assert_eq!(parts.borrow().len(), 3);
+                harness.mouse_click(center(2));
+                assert_eq!(
+                    harness.data().len(),
+                    3,
+                    "clicking the last segment keeps it"
+                );
+                assert_eq!(selected.take(), [2]);
+
+                harness.submit_command(NAVIGATE_BACK);
+                assert_eq!(harness.data().len(), 2);
+                assert_eq!(selected.take(), [1]);
+            },
+            |_| {},
+        );
+    }
+}
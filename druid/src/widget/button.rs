@@ -16,7 +16,7 @@
 
 use crate::widget::prelude::*;
 use crate::widget::{Click, ControllerHost, Label, LabelText};
-use crate::{theme, Affine, Data, Insets, LinearGradient, UnitPoint};
+use crate::{theme, Affine, Data, Insets, LinearGradient, StrokeAlignment, UnitPoint};
 use tracing::{instrument, trace};
 
 // the minimum padding added to a button.
@@ -170,12 +170,12 @@ impl<T: Data> Widget<T> for Button<T> {
         let is_active = ctx.is_active() && !ctx.is_disabled();
         let is_hot = ctx.is_hot();
         let size = ctx.size();
-        let stroke_width = env.get(theme::BUTTON_BORDER_WIDTH);
-
-        let rounded_rect = size
-            .to_rect()
-            .inset(-stroke_width / 2.0)
-            .to_rounded_rect(env.get(theme::BUTTON_BORDER_RADIUS));
+        let (rounded_rect, stroke_width) = ctx.snap_stroke(
+            size.to_rect(),
+            env.get(theme::BUTTON_BORDER_WIDTH),
+            StrokeAlignment::Inside,
+        );
+        let rounded_rect = rounded_rect.to_rounded_rect(env.get(theme::BUTTON_BORDER_RADIUS));
 
         let bg_gradient = if ctx.is_disabled() {
             LinearGradient::new(
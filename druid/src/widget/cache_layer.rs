@@ -0,0 +1,335 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that caches the rendering of its child.
+
+use std::mem;
+
+use crate::kurbo::{Affine, Rect};
+use crate::piet::{Device, ImageFormat, InterpolationMode, PietImage};
+use crate::widget::{prelude::*, WidgetWrapper};
+use crate::{Data, Point, Region, WidgetPod};
+
+use tracing::{instrument, trace, warn};
+
+/// The largest cache we will allocate, in device pixels along either axis.
+///
+/// Children that paint outside of this area are painted directly.
+const MAX_CACHE_DIMENSION: f64 = 4096.0;
+
+/// A widget that renders its child into an offscreen image, and then reuses
+/// that image until the child changes.
+///
+/// This is useful for content that is expensive to paint but rarely changes;
+/// for instance a long document inside a [`Scroll`], which would otherwise be
+/// repainted from scratch every time the scroll offset changes.
+///
+/// The cached image is discarded when the data or the [`Env`] changes, when
+/// the child is laid out at a new size, and whenever the child (or any of
+/// its descendants) requests a paint. Content that repaints often gains
+/// nothing from the cache, and only pays for the offscreen image.
+///
+/// If the child is too large to be cached, if it paints anything with a
+/// z-index (which may reach outside of the child), or if offscreen rendering
+/// fails, the child is painted directly.
+///
+/// [`Scroll`]: crate::widget::Scroll
+pub struct CacheLayer<T, W> {
+    child: WidgetPod<T, W>,
+    cache: Option<CachedImage>,
+    /// The device that the cache is rendered with, kept between renders.
+    device: Option<Device>,
+    enabled: bool,
+    /// Set when the child painted z-ordered operations, which can't be cached;
+    /// it is then painted directly until the cache would be discarded.
+    paint_directly: bool,
+}
+
+/// A rendered image of the child, along with the parameters it was rendered with.
+struct CachedImage {
+    image: PietImage,
+    /// The child's paint rect, in our coordinate space.
+    rect: Rect,
+    scale: f64,
+}
+
+impl<T, W: Widget<T>> CacheLayer<T, W> {
+    /// Create a new `CacheLayer` wrapping `child`.
+    pub fn new(child: W) -> Self {
+        CacheLayer {
+            child: WidgetPod::new(child),
+            cache: None,
+            device: None,
+            enabled: true,
+            paint_directly: false,
+        }
+    }
+}
+
+impl<T, W> CacheLayer<T, W> {
+    /// Builder-style method to set whether caching is enabled.
+    ///
+    /// When disabled, the child is painted directly every time.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.set_enabled(enabled);
+        self
+    }
+
+    /// Set whether caching is enabled.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear_cache();
+            self.device = None;
+        }
+    }
+
+    /// Returns `true` if caching is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Discard the cached image, so that the child is repainted the next
+    /// time this widget is painted.
+    pub fn clear_cache(&mut self) {
+        self.cache = None;
+        self.paint_directly = false;
+    }
+
+    /// Returns `true` if there is a cached image of the child.
+    pub fn is_cached(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Returns a reference to the child widget.
+    pub fn child(&self) -> &W {
+        self.child.widget()
+    }
+
+    /// Returns a mutable reference to the child widget.
+    pub fn child_mut(&mut self) -> &mut W {
+        self.child.widget_mut()
+    }
+
+    /// Discard the cache if anything in our subtree requested a paint.
+    ///
+    /// Invalid regions of the child are merged into our own state when a pass
+    /// returns, so we only need to look at our own state here.
+    fn check_invalid(&mut self, invalid: &Region) {
+        if !invalid.is_empty() && (self.cache.is_some() || self.paint_directly) {
+            trace!("child requested paint, discarding cache");
+            self.clear_cache();
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> CacheLayer<T, W> {
+    /// Render the whole child into an image at `scale` device pixels per
+    /// unit, such as for a thumbnail of it. The cache is left alone.
+    pub(crate) fn snapshot(
+        &mut self,
+        ctx: &mut PaintCtx,
+        data: &T,
+        env: &Env,
+        scale: f64,
+    ) -> Option<PietImage> {
+        let rect = self.child.layout_rect().with_origin(Point::ORIGIN);
+        self.render(ctx, data, env, rect, scale, true)
+            .map(|cached| cached.image)
+    }
+
+    /// Render the child into a new offscreen image.
+    ///
+    /// Unless `allow_z_ops` is set, this fails if the child paints any
+    /// z-ordered operations, because they would be clipped to the image.
+    fn render(
+        &mut self,
+        ctx: &mut PaintCtx,
+        data: &T,
+        env: &Env,
+        rect: Rect,
+        scale: f64,
+        allow_z_ops: bool,
+    ) -> Option<CachedImage> {
+        let width = (rect.width() * scale).ceil();
+        let height = (rect.height() * scale).ceil();
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        if width > MAX_CACHE_DIMENSION || height > MAX_CACHE_DIMENSION {
+            trace!("child too large to cache: {}x{}", width, height);
+            return None;
+        }
+
+        if self.device.is_none() {
+            match Device::new() {
+                Ok(device) => self.device = Some(device),
+                Err(e) => {
+                    warn!("CacheLayer failed to create device: {}", e);
+                    self.enabled = false;
+                    return None;
+                }
+            }
+        }
+        let device = self.device.as_mut().unwrap();
+        let mut target = match device.bitmap_target(width as usize, height as usize, scale) {
+            Ok(target) => target,
+            Err(e) => {
+                warn!("CacheLayer failed to create bitmap target: {}", e);
+                self.enabled = false;
+                return None;
+            }
+        };
+
+        {
+            let mut piet = target.render_context();
+            piet.transform(Affine::translate(-rect.origin().to_vec2()));
+            let mut offscreen = PaintCtx {
+                render_ctx: &mut piet,
+                state: ctx.state,
+                widget_state: ctx.widget_state,
+                z_ops: Vec::new(),
+                region: rect.into(),
+                depth: ctx.depth,
+            };
+            self.child.paint_always(&mut offscreen, data, env);
+
+            // z-ordered operations were recorded relative to the offscreen
+            // target, so they have to be run against it as well.
+            let mut z_ops = mem::take(&mut offscreen.z_ops);
+            if !z_ops.is_empty() && !allow_z_ops {
+                trace!("child painted z-ordered operations, painting it directly");
+                self.paint_directly = true;
+                z_ops.clear();
+            }
+            z_ops.sort_by_key(|k| k.z_index);
+            for z_op in z_ops.into_iter() {
+                offscreen.with_save(|ctx| {
+                    ctx.render_ctx.transform(z_op.transform);
+                    (z_op.paint_func)(ctx);
+                });
+            }
+
+            if let Err(e) = piet.finish() {
+                warn!("CacheLayer failed to finish offscreen paint: {}", e);
+                return None;
+            }
+        }
+        if self.paint_directly && !allow_z_ops {
+            return None;
+        }
+
+        let image_buf = match target.to_image_buf(ImageFormat::RgbaPremul) {
+            Ok(buf) => buf,
+            Err(e) => {
+                warn!("CacheLayer failed to read back offscreen image: {}", e);
+                self.enabled = false;
+                return None;
+            }
+        };
+        Some(CachedImage {
+            image: image_buf.to_image(ctx.render_ctx),
+            rect,
+            scale,
+        })
+    }
+}
+
+impl<T, W> WidgetWrapper for CacheLayer<T, W> {
+    widget_wrapper_pod_body!(W, child);
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for CacheLayer<T, W> {
+    #[instrument(
+        name = "CacheLayer",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.child.event(ctx, event, data, env);
+        self.check_invalid(&ctx.widget_state.invalid);
+    }
+
+    #[instrument(
+        name = "CacheLayer",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::FrozenChanged(true) = event {
+            // We won't be painted for a while, so don't hold on to the image.
+            self.clear_cache();
+        }
+        self.child.lifecycle(ctx, event, data, env);
+        self.check_invalid(&ctx.widget_state.invalid);
+    }
+
+    #[instrument(
+        name = "CacheLayer",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if !old_data.same(data) || ctx.env_changed() {
+            self.clear_cache();
+        }
+        self.child.update(ctx, data, env);
+        self.check_invalid(&ctx.widget_state.invalid);
+    }
+
+    #[instrument(name = "CacheLayer", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("CacheLayer");
+        let size = self.child.layout(ctx, bc, data, env);
+        self.child.set_origin(ctx, data, env, Point::ORIGIN);
+        self.check_invalid(&ctx.widget_state.invalid);
+
+        let insets = self.child.compute_parent_paint_insets(size);
+        ctx.set_paint_insets(insets);
+        ctx.set_baseline_offset(self.child.baseline_offset());
+        size
+    }
+
+    #[instrument(name = "CacheLayer", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        // the inspector needs the child to actually be painted in order to see it.
+        if !self.enabled || self.paint_directly || ctx.state.inspector.is_some() {
+            self.child.paint(ctx, data, env);
+            return;
+        }
+
+        let rect = self.child.paint_rect();
+        let scale = ctx
+            .window()
+            .get_scale()
+            .map(|scale| scale.x().max(scale.y()))
+            .unwrap_or(1.0);
+        let is_valid = matches!(
+            &self.cache,
+            Some(cached) if cached.rect == rect && cached.scale == scale
+        );
+        if !is_valid {
+            self.cache = self.render(ctx, data, env, rect, scale, false);
+        }
+
+        match &self.cache {
+            Some(cached) => {
+                // the image is already at device resolution, so there is no
+                // need to interpolate.
+                ctx.draw_image(&cached.image, rect, InterpolationMode::NearestNeighbor);
+            }
+            None => self.child.paint(ctx, data, env),
+        }
+    }
+}
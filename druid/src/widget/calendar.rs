@@ -0,0 +1,496 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A month calendar, with the events of each day.
+
+use std::marker::PhantomData;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::commands::DATE_SELECTED;
+use crate::kurbo::{BezPath, Line};
+use crate::localization::{first_weekday, parse_locale};
+use crate::widget::prelude::*;
+use crate::widget::ListIter;
+use crate::{
+    theme, Application, ArcStr, Data, KbKey, MouseButton, Point, Rect, StrokeAlignment, TextLayout,
+    Weekday,
+};
+use tracing::{instrument, trace};
+
+const DEFAULT_SIZE: Size = Size::new(560.0, 420.0);
+const HEADER_HEIGHT: f64 = 32.0;
+const WEEKDAY_HEIGHT: f64 = 20.0;
+const CELL_PADDING: f64 = 4.0;
+const CHIP_HEIGHT: f64 = 16.0;
+const CHIP_SPACING: f64 = 2.0;
+const SMALL_TEXT_SIZE: f64 = 11.0;
+const ROWS: usize = 6;
+const DAYS: usize = ROWS * 7;
+
+/// A month calendar, showing the events of each day as chips.
+///
+/// The data is a collection of events, such as a `Vector<E>` or an
+/// `Arc<Vec<E>>`; see [`ListIter`]. The closures passed to
+/// [`CalendarView::new`] give the date and the label of each event.
+///
+/// The arrows in the header, or the page up and page down keys, show the
+/// previous and next month. Clicking a day, or moving with the arrow keys,
+/// selects it and submits [`DATE_SELECTED`] as a notification.
+///
+/// Weeks start on the day from the system's regional settings, or else on
+/// the usual day for the current locale; see [`with_first_weekday`].
+///
+/// [`DATE_SELECTED`]: crate::commands::DATE_SELECTED
+/// [`with_first_weekday`]: CalendarView::with_first_weekday
+pub struct CalendarView<E> {
+    event_date: Box<dyn Fn(&E) -> NaiveDate>,
+    event_label: Box<dyn Fn(&E) -> ArcStr>,
+    /// The first day of the month that is shown.
+    month: NaiveDate,
+    selected: Option<NaiveDate>,
+    /// The day weeks start on; `None` until it is looked up.
+    first_weekday: Option<Weekday>,
+    title: TextLayout<String>,
+    weekday_names: Vec<TextLayout<String>>,
+    day_numbers: Vec<TextLayout<String>>,
+    /// The area of the day cells.
+    grid: Rect,
+    phantom: PhantomData<E>,
+}
+
+impl<E: Data> CalendarView<E> {
+    /// Create a new `CalendarView` showing the current month, with the
+    /// functions that give the date and the label of an event.
+    pub fn new(
+        event_date: impl Fn(&E) -> NaiveDate + 'static,
+        event_label: impl Fn(&E) -> ArcStr + 'static,
+    ) -> Self {
+        let today = chrono::Local::now().naive_local().date();
+        let day_numbers = (1..=31)
+            .map(|day| {
+                let mut layout = TextLayout::from_text(day.to_string());
+                layout.set_text_size(SMALL_TEXT_SIZE);
+                layout
+            })
+            .collect();
+        let mut title = TextLayout::new();
+        title.set_font(theme::UI_FONT_BOLD);
+        CalendarView {
+            event_date: Box::new(event_date),
+            event_label: Box::new(event_label),
+            month: first_of_month(today),
+            selected: None,
+            first_weekday: None,
+            title,
+            weekday_names: Vec::new(),
+            day_numbers,
+            grid: Rect::ZERO,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Builder-style method to set the day that weeks start on.
+    pub fn with_first_weekday(mut self, weekday: Weekday) -> Self {
+        self.first_weekday = Some(weekday);
+        self
+    }
+
+    /// Builder-style method to set the month that is shown first.
+    pub fn with_month(mut self, date: NaiveDate) -> Self {
+        self.month = first_of_month(date);
+        self
+    }
+
+    /// The first day of the month that is shown.
+    pub fn month(&self) -> NaiveDate {
+        self.month
+    }
+
+    /// Show the month that contains `date`.
+    pub fn set_month(&mut self, date: NaiveDate) {
+        self.month = first_of_month(date);
+        self.update_texts();
+    }
+
+    /// The selected day, if any.
+    pub fn selected(&self) -> Option<NaiveDate> {
+        self.selected
+    }
+
+    fn week_start(&self) -> Weekday {
+        self.first_weekday.unwrap_or(Weekday::Monday)
+    }
+
+    /// The first day shown in the grid.
+    fn grid_start(&self) -> NaiveDate {
+        grid_start(self.month, self.week_start())
+    }
+
+    fn update_texts(&mut self) {
+        self.title.set_text(self.month.format("%B %Y").to_string());
+        let start = self.grid_start();
+        self.weekday_names = (0..7)
+            .map(|i| {
+                let day = start + Duration::days(i);
+                let mut layout = TextLayout::from_text(day.format("%a").to_string());
+                layout.set_text_size(SMALL_TEXT_SIZE);
+                layout
+            })
+            .collect();
+    }
+
+    fn cell_size(&self) -> Size {
+        Size::new(self.grid.width() / 7.0, self.grid.height() / ROWS as f64)
+    }
+
+    fn cell_rect(&self, index: usize) -> Rect {
+        let cell = self.cell_size();
+        let origin = self.grid.origin()
+            + (
+                (index % 7) as f64 * cell.width,
+                (index / 7) as f64 * cell.height,
+            );
+        Rect::from_origin_size(origin, cell)
+    }
+
+    fn date_at(&self, pos: Point) -> Option<NaiveDate> {
+        if !self.grid.contains(pos) {
+            return None;
+        }
+        let cell = self.cell_size();
+        let column = ((pos.x - self.grid.x0) / cell.width) as i64;
+        let row = ((pos.y - self.grid.y0) / cell.height) as i64;
+        Some(self.grid_start() + Duration::days(row * 7 + column.min(6)))
+    }
+
+    fn prev_rect(&self) -> Rect {
+        Rect::new(0.0, 0.0, HEADER_HEIGHT, HEADER_HEIGHT)
+    }
+
+    fn next_rect(&self) -> Rect {
+        let width = self.grid.width();
+        Rect::new(width - HEADER_HEIGHT, 0.0, width, HEADER_HEIGHT)
+    }
+
+    fn show_month(&mut self, ctx: &mut EventCtx, months: i32) {
+        self.month = add_months(self.month, months);
+        trace!("CalendarView showing {}", self.month);
+        self.update_texts();
+        ctx.request_layout();
+    }
+
+    fn select(&mut self, ctx: &mut EventCtx, date: NaiveDate) {
+        self.selected = Some(date);
+        if first_of_month(date) != self.month {
+            self.month = first_of_month(date);
+            self.update_texts();
+            ctx.request_layout();
+        }
+        ctx.submit_notification(DATE_SELECTED.with(date));
+        ctx.request_paint();
+    }
+}
+
+/// The first day of the month that contains `date`.
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap()
+}
+
+/// The first day of the month `months` after the month that starts on
+/// `month`.
+fn add_months(month: NaiveDate, months: i32) -> NaiveDate {
+    let index = month.year() * 12 + month.month0() as i32 + months;
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1).unwrap()
+}
+
+/// The day that the week that contains the first of `month` starts on.
+fn grid_start(month: NaiveDate, first_weekday: Weekday) -> NaiveDate {
+    let first = match first_weekday {
+        Weekday::Monday => 0,
+        Weekday::Tuesday => 1,
+        Weekday::Wednesday => 2,
+        Weekday::Thursday => 3,
+        Weekday::Friday => 4,
+        Weekday::Saturday => 5,
+        Weekday::Sunday => 6,
+    };
+    let offset = (month.weekday().num_days_from_monday() + 7 - first) % 7;
+    month - Duration::days(offset as i64)
+}
+
+/// A chevron pointing left or right, in `rect`.
+fn chevron(rect: Rect, left: bool) -> BezPath {
+    let center = rect.center();
+    let half = rect.height() / 8.0;
+    let (tip, back) = if left {
+        (center.x - half / 2.0, center.x + half / 2.0)
+    } else {
+        (center.x + half / 2.0, center.x - half / 2.0)
+    };
+    let mut path = BezPath::new();
+    path.move_to((back, center.y - half));
+    path.line_to((tip, center.y));
+    path.line_to((back, center.y + half));
+    path
+}
+
+impl<E: Data, T: ListIter<E>> Widget<T> for CalendarView<E> {
+    #[instrument(
+        name = "CalendarView",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, _env: &Env) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                ctx.request_focus();
+                if self.prev_rect().contains(mouse.pos) {
+                    self.show_month(ctx, -1);
+                } else if self.next_rect().contains(mouse.pos) {
+                    self.show_month(ctx, 1);
+                } else if let Some(date) = self.date_at(mouse.pos) {
+                    self.select(ctx, date);
+                }
+            }
+            Event::KeyDown(key) if ctx.has_focus() => {
+                let selected = self.selected.unwrap_or(self.month);
+                match key.key {
+                    KbKey::ArrowLeft => self.select(ctx, selected - Duration::days(1)),
+                    KbKey::ArrowRight => self.select(ctx, selected + Duration::days(1)),
+                    KbKey::ArrowUp => self.select(ctx, selected - Duration::days(7)),
+                    KbKey::ArrowDown => self.select(ctx, selected + Duration::days(7)),
+                    KbKey::PageUp => self.show_month(ctx, -1),
+                    KbKey::PageDown => self.show_month(ctx, 1),
+                    _ => return,
+                }
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "CalendarView",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, _env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                if self.first_weekday.is_none() {
+                    let settings = Application::regional_settings();
+                    self.first_weekday = Some(settings.first_day_of_week.unwrap_or_else(|| {
+                        first_weekday(&parse_locale(&Application::get_locale()))
+                    }));
+                }
+                self.update_texts();
+                ctx.register_for_focus();
+            }
+            LifeCycle::FocusChanged(_) => ctx.request_paint(),
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "CalendarView",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, _env: &Env) {
+        if !old_data.same(data) {
+            ctx.request_paint();
+        }
+        if ctx.env_changed() {
+            // every layout has to see the change, so this must not stop at
+            // the first one that needs rebuilding.
+            let mut changed = false;
+            let layouts = std::iter::once(&mut self.title)
+                .chain(self.weekday_names.iter_mut())
+                .chain(self.day_numbers.iter_mut());
+            for layout in layouts {
+                changed |= layout.needs_rebuild_after_update(ctx);
+            }
+            if changed {
+                ctx.request_layout();
+            }
+        }
+    }
+
+    #[instrument(
+        name = "CalendarView",
+        level = "trace",
+        skip(self, ctx, bc, _data, env)
+    )]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, env: &Env) -> Size {
+        bc.debug_check("CalendarView");
+        let layouts = std::iter::once(&mut self.title)
+            .chain(self.weekday_names.iter_mut())
+            .chain(self.day_numbers.iter_mut());
+        for layout in layouts {
+            layout.rebuild_if_needed(ctx.text(), env);
+        }
+
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            DEFAULT_SIZE.width
+        };
+        let height = if bc.is_height_bounded() {
+            bc.max().height
+        } else {
+            DEFAULT_SIZE.height
+        };
+        let size = bc.constrain(Size::new(width, height));
+        self.grid = Rect::new(0.0, HEADER_HEIGHT + WEEKDAY_HEIGHT, size.width, size.height);
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "CalendarView", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        let text_color = env.get(theme::TEXT_COLOR);
+        let border_color = env.get(theme::BORDER_DARK);
+        let primary = env.get(theme::PRIMARY_LIGHT);
+
+        // the header, with the month and the arrows to change it.
+        let title_origin = Point::new(
+            (size.width - self.title.size().width) / 2.0,
+            (HEADER_HEIGHT - self.title.size().height) / 2.0,
+        );
+        self.title.draw(ctx, title_origin);
+        ctx.stroke(chevron(self.prev_rect(), true), &text_color, 2.0);
+        ctx.stroke(chevron(self.next_rect(), false), &text_color, 2.0);
+
+        let cell = self.cell_size();
+        for (column, name) in self.weekday_names.iter().enumerate() {
+            let x = column as f64 * cell.width + (cell.width - name.size().width) / 2.0;
+            let y = HEADER_HEIGHT + (WEEKDAY_HEIGHT - name.size().height) / 2.0;
+            name.draw(ctx, (x, y));
+        }
+
+        // the labels of the events of each day in the grid.
+        let start = self.grid_start();
+        let mut events: Vec<Vec<ArcStr>> = vec![Vec::new(); DAYS];
+        data.for_each(|event, _| {
+            let index = ((self.event_date)(event) - start).num_days();
+            if index >= 0 && (index as usize) < DAYS {
+                events[index as usize].push((self.event_label)(event));
+            }
+        });
+
+        let today = chrono::Local::now().naive_local().date();
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        for (index, labels) in events.iter().enumerate() {
+            let date = start + Duration::days(index as i64);
+            let rect = self.cell_rect(index);
+            let in_month = date.month() == self.month.month();
+            if Some(date) == self.selected {
+                ctx.fill(rect, &primary.clone().with_alpha(0.25));
+            }
+
+            let number = &self.day_numbers[date.day0() as usize];
+            let number_origin = Point::new(rect.x0 + CELL_PADDING, rect.y0 + CELL_PADDING);
+            if date == today {
+                let mark = Rect::from_origin_size(number_origin, number.size())
+                    .inflate(2.0, 1.0)
+                    .to_rounded_rect(radius);
+                ctx.stroke(mark, &primary, 1.0);
+            }
+            number.draw(ctx, number_origin);
+
+            // as many chips as fit, with the last one saying how many more
+            // there are if they don't all fit.
+            let mut y = number_origin.y + number.size().height + CELL_PADDING;
+            let fits = ((rect.y1 - CELL_PADDING - y + CHIP_SPACING) / (CHIP_HEIGHT + CHIP_SPACING))
+                .floor()
+                .max(0.0) as usize;
+            for (i, label) in labels.iter().enumerate().take(fits) {
+                let text = if i + 1 == fits && labels.len() > fits {
+                    format!("+{} more", labels.len() - i)
+                } else {
+                    label.to_string()
+                };
+                let chip = Rect::new(
+                    rect.x0 + CELL_PADDING,
+                    y,
+                    rect.x1 - CELL_PADDING,
+                    y + CHIP_HEIGHT,
+                );
+                ctx.fill(chip.to_rounded_rect(radius), &env.get(theme::PRIMARY_DARK));
+                let mut layout = TextLayout::<String>::from_text(text);
+                layout.set_text_size(SMALL_TEXT_SIZE);
+                layout.rebuild_if_needed(ctx.text(), env);
+                let origin = Point::new(
+                    chip.x0 + CELL_PADDING,
+                    chip.center().y - layout.size().height / 2.0,
+                );
+                ctx.with_save(|ctx| {
+                    ctx.clip(chip);
+                    layout.draw(ctx, origin);
+                });
+                y += CHIP_HEIGHT + CHIP_SPACING;
+            }
+
+            if !in_month {
+                ctx.fill(rect, &env.get(theme::BACKGROUND_DARK).with_alpha(0.6));
+            }
+        }
+
+        // the lines between the cells.
+        for row in 0..=ROWS {
+            let y = self.grid.y0 + row as f64 * cell.height;
+            let (line, width) = ctx.snap_line(Line::new((0.0, y), (size.width, y)), 1.0);
+            ctx.stroke(line, &border_color, width);
+        }
+        for column in 1..7 {
+            let x = column as f64 * cell.width;
+            let (line, width) = ctx.snap_line(Line::new((x, self.grid.y0), (x, self.grid.y1)), 1.0);
+            ctx.stroke(line, &border_color, width);
+        }
+
+        if ctx.has_focus() {
+            let (rect, width) = ctx.snap_stroke(self.grid, 1.0, StrokeAlignment::Inside);
+            ctx.stroke(rect, &primary, width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn grid_starts_on_first_weekday() {
+        // the first of october 2026 is a thursday
+        let month = date(2026, 10, 1);
+        assert_eq!(grid_start(month, Weekday::Monday), date(2026, 9, 28));
+        assert_eq!(grid_start(month, Weekday::Sunday), date(2026, 9, 27));
+        assert_eq!(grid_start(month, Weekday::Thursday), month);
+    }
+
+    #[test]
+    fn months_wrap_around_years() {
+        let january = date(2026, 1, 1);
+        assert_eq!(add_months(january, -1), date(2025, 12, 1));
+        assert_eq!(add_months(january, 13), date(2027, 2, 1));
+        assert_eq!(first_of_month(date(2026, 10, 16)), date(2026, 10, 1));
+    }
+}
@@ -16,8 +16,8 @@
 
 use crate::kurbo::{BezPath, Size};
 use crate::piet::{LineCap, LineJoin, LinearGradient, RenderContext, StrokeStyle, UnitPoint};
-use crate::theme;
 use crate::widget::{prelude::*, Label, LabelText};
+use crate::{theme, StrokeAlignment};
 use tracing::{instrument, trace};
 
 /// A checkbox that toggles a `bool`.
@@ -109,12 +109,9 @@ impl Widget<bool> for Checkbox {
     fn paint(&mut self, ctx: &mut PaintCtx, data: &bool, env: &Env) {
         let size = env.get(theme::BASIC_WIDGET_HEIGHT);
         let x_padding = env.get(theme::WIDGET_CONTROL_COMPONENT_PADDING);
-        let border_width = 1.;
-
-        let rect = Size::new(size, size)
-            .to_rect()
-            .inset(-border_width / 2.)
-            .to_rounded_rect(2.);
+        let (rect, border_width) =
+            ctx.snap_stroke(Size::new(size, size).to_rect(), 1., StrokeAlignment::Inside);
+        let rect = rect.to_rounded_rect(2.);
 
         //Paint the background
         let background_gradient = LinearGradient::new(
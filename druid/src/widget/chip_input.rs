@@ -0,0 +1,562 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A field of removable tags, with a text box for adding more.
+
+use crate::im::Vector;
+use crate::kurbo::Line;
+use crate::text::TextComponent;
+use crate::widget::prelude::*;
+use crate::widget::{EnvScope, TextBox};
+use crate::{theme, ArcStr, KbKey, MouseButton, Point, Rect, TextLayout, WidgetPod};
+use tracing::{instrument, trace};
+
+const PADDING: f64 = 4.0;
+const CHIP_PADDING: f64 = 6.0;
+const REMOVE_SIZE: f64 = 8.0;
+const ENTRY_MIN_WIDTH: f64 = 80.0;
+
+type Completions = Box<dyn Fn(&str) -> Vec<String>>;
+
+/// A field that shows a list of tags as chips, with a text box after them
+/// for adding more; for labels, recipients, and the like.
+///
+/// Pressing Enter in the text box adds its text as a tag, and pressing
+/// Backspace while it is empty removes the last tag. Each chip also has a
+/// button to remove it. A tag that is already in the list isn't added again.
+///
+/// With [`with_completions`], suggestions for the text that is being typed
+/// are listed below the field; they can be picked with the mouse, or with
+/// the arrow keys and Enter.
+///
+/// [`with_completions`]: ChipInput::with_completions
+pub struct ChipInput {
+    chips: Vec<Chip>,
+    entry: WidgetPod<String, EnvScope<String, TextBox<String>>>,
+    /// The text in the text box.
+    text: String,
+    completions: Option<Completions>,
+    suggestions: Vec<Suggestion>,
+    selected_suggestion: Option<usize>,
+    hot: Option<Target>,
+    pressed: Option<Target>,
+}
+
+struct Chip {
+    label: TextLayout<ArcStr>,
+    rect: Rect,
+    remove_rect: Rect,
+}
+
+struct Suggestion {
+    text: String,
+    layout: TextLayout<String>,
+    rect: Rect,
+}
+
+/// The parts of the field that can be clicked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Remove(usize),
+    Suggestion(usize),
+}
+
+impl ChipInput {
+    /// Create a new `ChipInput`.
+    pub fn new() -> Self {
+        ChipInput {
+            chips: Vec::new(),
+            entry: Self::entry(TextBox::new()),
+            text: String::new(),
+            completions: None,
+            suggestions: Vec::new(),
+            selected_suggestion: None,
+            hot: None,
+            pressed: None,
+        }
+    }
+
+    /// Builder-style method to set the placeholder text of the text box.
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.entry = Self::entry(TextBox::new().with_placeholder(placeholder));
+        self
+    }
+
+    /// Builder-style method to suggest tags while the user types.
+    ///
+    /// The closure is called with the text in the text box, and returns
+    /// the tags to suggest for it.
+    pub fn with_completions(mut self, completions: impl Fn(&str) -> Vec<String> + 'static) -> Self {
+        self.completions = Some(Box::new(completions));
+        self
+    }
+
+    fn entry(
+        mut text_box: TextBox<String>,
+    ) -> WidgetPod<String, EnvScope<String, TextBox<String>>> {
+        text_box.text_mut().borrow_mut().send_notification_on_return = true;
+        text_box.text_mut().borrow_mut().send_notification_on_cancel = true;
+        // the field draws the border around the chips and the text box.
+        let entry = EnvScope::new(|env, _| env.set(theme::TEXTBOX_BORDER_WIDTH, 0.0), text_box);
+        WidgetPod::new(entry)
+    }
+
+    fn update_chips(&mut self, data: &Vector<String>) {
+        self.chips = data
+            .iter()
+            .map(|tag| {
+                let mut label = TextLayout::from_text(tag.as_str());
+                label.set_text_color(theme::TEXT_COLOR);
+                Chip {
+                    label,
+                    rect: Rect::ZERO,
+                    remove_rect: Rect::ZERO,
+                }
+            })
+            .collect();
+    }
+
+    fn update_suggestions(&mut self, data: &Vector<String>) {
+        let text = self.text.trim();
+        let suggestions = match &self.completions {
+            Some(completions) if !text.is_empty() => completions(text),
+            _ => Vec::new(),
+        };
+        self.suggestions = suggestions
+            .into_iter()
+            .filter(|suggestion| !data.contains(suggestion))
+            .map(|text| {
+                let mut layout = TextLayout::from_text(text.clone());
+                layout.set_text_color(theme::TEXT_COLOR);
+                Suggestion {
+                    text,
+                    layout,
+                    rect: Rect::ZERO,
+                }
+            })
+            .collect();
+        self.selected_suggestion = None;
+    }
+
+    fn target_at(&self, pos: Point) -> Option<Target> {
+        let remove = self
+            .chips
+            .iter()
+            .position(|chip| chip.remove_rect.contains(pos))
+            .map(Target::Remove);
+        let suggestion = || {
+            self.suggestions
+                .iter()
+                .position(|suggestion| suggestion.rect.contains(pos))
+                .map(Target::Suggestion)
+        };
+        remove.or_else(suggestion)
+    }
+
+    fn add_tag(&mut self, ctx: &mut EventCtx, data: &mut Vector<String>, tag: String) {
+        let tag = tag.trim();
+        if !tag.is_empty() && !data.iter().any(|existing| existing == tag) {
+            trace!("ChipInput added tag {:?}", tag);
+            data.push_back(tag.to_owned());
+        }
+        self.text.clear();
+        self.suggestions.clear();
+        self.selected_suggestion = None;
+        ctx.request_update();
+        ctx.request_layout();
+    }
+
+    fn remove_tag(&mut self, ctx: &mut EventCtx, data: &mut Vector<String>, index: usize) {
+        if index < data.len() {
+            trace!("ChipInput removed tag {:?}", data[index]);
+            data.remove(index);
+            ctx.request_layout();
+        }
+    }
+}
+
+impl Default for ChipInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<Vector<String>> for ChipInput {
+    #[instrument(name = "ChipInput", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Vector<String>, env: &Env) {
+        match event {
+            Event::Notification(note) if note.is(TextComponent::RETURN) => {
+                ctx.set_handled();
+                let tag = match self.selected_suggestion {
+                    Some(index) => self.suggestions[index].text.clone(),
+                    None => self.text.clone(),
+                };
+                self.add_tag(ctx, data, tag);
+                return;
+            }
+            Event::Notification(note) if note.is(TextComponent::CANCEL) => {
+                ctx.set_handled();
+                self.suggestions.clear();
+                self.selected_suggestion = None;
+                ctx.request_layout();
+                return;
+            }
+            Event::KeyDown(key) if ctx.has_focus() => {
+                let count = self.suggestions.len();
+                match key.key {
+                    KbKey::Backspace if self.text.is_empty() && !data.is_empty() => {
+                        ctx.set_handled();
+                        self.remove_tag(ctx, data, data.len() - 1);
+                        return;
+                    }
+                    KbKey::ArrowDown if count > 0 => {
+                        ctx.set_handled();
+                        self.selected_suggestion = Some(match self.selected_suggestion {
+                            Some(index) => (index + 1) % count,
+                            None => 0,
+                        });
+                        ctx.request_paint();
+                        return;
+                    }
+                    KbKey::ArrowUp if count > 0 => {
+                        ctx.set_handled();
+                        self.selected_suggestion = Some(match self.selected_suggestion {
+                            Some(index) => (index + count - 1) % count,
+                            None => count - 1,
+                        });
+                        ctx.request_paint();
+                        return;
+                    }
+                    _ => (),
+                }
+            }
+            Event::MouseMove(mouse) => {
+                let hot = self.target_at(mouse.pos);
+                if hot != self.hot {
+                    self.hot = hot;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                self.pressed = self.target_at(mouse.pos);
+                if self.pressed.is_some() {
+                    ctx.set_active(true);
+                    ctx.request_paint();
+                    return;
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.request_paint();
+                let pressed = self.pressed.take();
+                if pressed.is_some() && pressed == self.target_at(mouse.pos) {
+                    match pressed {
+                        Some(Target::Remove(index)) => self.remove_tag(ctx, data, index),
+                        Some(Target::Suggestion(index)) => {
+                            let tag = self.suggestions[index].text.clone();
+                            self.add_tag(ctx, data, tag);
+                        }
+                        None => (),
+                    }
+                }
+                return;
+            }
+            _ => (),
+        }
+
+        let old_text = self.text.clone();
+        self.entry.event(ctx, event, &mut self.text, env);
+        if self.text != old_text {
+            self.update_suggestions(data);
+            ctx.request_update();
+            ctx.request_layout();
+        }
+    }
+
+    #[instrument(name = "ChipInput", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &Vector<String>,
+        env: &Env,
+    ) {
+        match event {
+            LifeCycle::WidgetAdded => self.update_chips(data),
+            LifeCycle::HotChanged(false) => {
+                self.hot = None;
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+        self.entry.lifecycle(ctx, event, &self.text, env);
+    }
+
+    #[instrument(
+        name = "ChipInput",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &Vector<String>,
+        data: &Vector<String>,
+        env: &Env,
+    ) {
+        if !old_data.same(data) {
+            self.update_chips(data);
+            ctx.request_layout();
+        }
+        if ctx.env_changed() {
+            let mut rebuild = false;
+            for chip in &mut self.chips {
+                rebuild |= chip.label.needs_rebuild_after_update(ctx);
+            }
+            for suggestion in &mut self.suggestions {
+                rebuild |= suggestion.layout.needs_rebuild_after_update(ctx);
+            }
+            if rebuild {
+                ctx.request_layout();
+            }
+        }
+        self.entry.update(ctx, &self.text, env);
+    }
+
+    #[instrument(name = "ChipInput", level = "trace", skip(self, ctx, bc, _data, env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Vector<String>,
+        env: &Env,
+    ) -> Size {
+        bc.debug_check("ChipInput");
+        for chip in &mut self.chips {
+            chip.label.rebuild_if_needed(ctx.text(), env);
+        }
+        for suggestion in &mut self.suggestions {
+            suggestion.layout.rebuild_if_needed(ctx.text(), env);
+        }
+
+        let max_width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            f64::INFINITY
+        };
+
+        // the chips flow from left to right, and wrap onto a new line when
+        // they don't fit; the text box takes the rest of the last line.
+        let mut x = PADDING;
+        let mut y = PADDING;
+        let mut line_height: f64 = 0.0;
+        let mut width: f64 = 0.0;
+        // the index of the first chip on the current line.
+        let mut line_start = 0;
+        for (i, chip) in self.chips.iter_mut().enumerate() {
+            let label_size = chip.label.size();
+            let chip_width = label_size.width + REMOVE_SIZE + CHIP_PADDING * 3.0;
+            let chip_height = label_size.height + PADDING;
+            if x > PADDING && x + chip_width + PADDING > max_width {
+                x = PADDING;
+                y += line_height + PADDING;
+                line_height = 0.0;
+                line_start = i;
+            }
+            chip.rect = Rect::from_origin_size((x, y), (chip_width, chip_height));
+            chip.remove_rect = Rect::from_center_size(
+                (
+                    chip.rect.x1 - CHIP_PADDING - REMOVE_SIZE / 2.0,
+                    chip.rect.center().y,
+                ),
+                (REMOVE_SIZE + PADDING, REMOVE_SIZE + PADDING),
+            );
+            x += chip_width + PADDING;
+            width = width.max(x);
+            line_height = line_height.max(chip_height);
+        }
+
+        if x > PADDING && x + ENTRY_MIN_WIDTH + PADDING > max_width {
+            x = PADDING;
+            y += line_height + PADDING;
+            line_height = 0.0;
+            line_start = self.chips.len();
+        }
+        let entry_bc = if max_width.is_finite() {
+            let entry_width = (max_width - x - PADDING).max(0.0);
+            BoxConstraints::new(
+                Size::new(entry_width, 0.0),
+                Size::new(entry_width, f64::INFINITY),
+            )
+        } else {
+            BoxConstraints::new(
+                Size::new(ENTRY_MIN_WIDTH, 0.0),
+                Size::new(f64::INFINITY, f64::INFINITY),
+            )
+        };
+        let entry_size = self.entry.layout(ctx, &entry_bc, &self.text, env);
+        line_height = line_height.max(entry_size.height);
+        self.entry
+            .set_origin(ctx, &self.text, env, Point::new(x, y));
+        // center the chips on the line of the text box
+        for chip in &mut self.chips[line_start..] {
+            let offset = (line_height - chip.rect.height()) / 2.0;
+            chip.rect = chip.rect.with_origin((chip.rect.x0, y + offset));
+            chip.remove_rect = chip
+                .remove_rect
+                .with_origin((chip.remove_rect.x0, chip.remove_rect.y0 + offset));
+        }
+        width = width.max(x + entry_size.width + PADDING);
+        y += line_height + PADDING;
+
+        let field_width = bc.constrain(Size::new(width, 0.0)).width;
+        for suggestion in &mut self.suggestions {
+            let height = suggestion.layout.size().height + PADDING;
+            suggestion.rect = Rect::from_origin_size((0.0, y), (field_width, height));
+            y += height;
+        }
+
+        let size = bc.constrain(Size::new(width, y));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "ChipInput", level = "trace", skip(self, ctx, _data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Vector<String>, env: &Env) {
+        let border_width = env.get(theme::TEXTBOX_BORDER_WIDTH);
+        let radius = env.get(theme::TEXTBOX_BORDER_RADIUS);
+        let field_height = self
+            .suggestions
+            .first()
+            .map(|suggestion| suggestion.rect.y0)
+            .unwrap_or_else(|| ctx.size().height);
+        let field = Rect::new(0.0, 0.0, ctx.size().width, field_height)
+            .inset(-border_width / 2.0)
+            .to_rounded_rect(radius);
+        ctx.fill(field, &env.get(theme::BACKGROUND_LIGHT));
+        let border_color = if ctx.has_focus() {
+            env.get(theme::PRIMARY_LIGHT)
+        } else {
+            env.get(theme::BORDER_DARK)
+        };
+        ctx.stroke(field, &border_color, border_width);
+
+        let text_color = env.get(theme::TEXT_COLOR);
+        for (i, chip) in self.chips.iter().enumerate() {
+            let chip_color = if self.hot == Some(Target::Remove(i)) {
+                env.get(theme::BUTTON_LIGHT)
+            } else {
+                env.get(theme::BACKGROUND_DARK)
+            };
+            let shape = chip.rect.to_rounded_rect(chip.rect.height() / 2.0);
+            ctx.fill(shape, &chip_color);
+            ctx.stroke(shape, &env.get(theme::BORDER_LIGHT), 1.0);
+            let label_y = chip.rect.center().y - chip.label.size().height / 2.0;
+            chip.label.draw(ctx, (chip.rect.x0 + CHIP_PADDING, label_y));
+
+            let cross =
+                Rect::from_center_size(chip.remove_rect.center(), (REMOVE_SIZE, REMOVE_SIZE));
+            ctx.stroke(
+                Line::new((cross.x0, cross.y0), (cross.x1, cross.y1)),
+                &text_color,
+                1.5,
+            );
+            ctx.stroke(
+                Line::new((cross.x1, cross.y0), (cross.x0, cross.y1)),
+                &text_color,
+                1.5,
+            );
+        }
+
+        self.entry.paint(ctx, &self.text, env);
+
+        if let Some(first) = self.suggestions.first() {
+            let last = self.suggestions.last().unwrap_or(first);
+            let list = first.rect.union(last.rect);
+            ctx.fill(list, &env.get(theme::BACKGROUND_DARK));
+            ctx.stroke(list.inset(-0.5), &env.get(theme::BORDER_DARK), 1.0);
+        }
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let highlighted =
+                self.selected_suggestion == Some(i) || self.hot == Some(Target::Suggestion(i));
+            if highlighted {
+                ctx.fill(
+                    suggestion.rect,
+                    &env.get(theme::SELECTED_TEXT_BACKGROUND_COLOR),
+                );
+            }
+            let origin = (
+                suggestion.rect.x0 + PADDING,
+                suggestion.rect.y0 + PADDING / 2.0,
+            );
+            suggestion.layout.draw(ctx, origin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use crate::text::TextAction;
+    use crate::{KeyEvent, Modifiers, WidgetExt, WidgetId};
+    use test_env_log::test;
+
+    const RETURN: TextAction = TextAction::InsertNewLine {
+        ignore_hotkey: false,
+        newline_type: '\n',
+    };
+
+    #[test]
+    fn add_complete_and_remove_tags() {
+        let id = WidgetId::next();
+        let widget = ChipInput::new()
+            .with_completions(|text| {
+                ["rust", "ruby", "python"]
+                    .iter()
+                    .filter(|tag| tag.starts_with(text))
+                    .map(|tag| tag.to_string())
+                    .collect()
+            })
+            .with_id(id);
+        let tags = |harness: &Harness<'_, Vector<String>>| {
+            harness.data().iter().cloned().collect::<Vec<_>>()
+        };
+
+        Harness::create_simple(Vector::new(), widget, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            // with no chips, the text box comes first.
+            let rect = harness.window_layout_rect(id).unwrap();
+            harness.mouse_click((rect.x0 + PADDING * 2.0, rect.y0 + PADDING * 2.0));
+
+            // pick the first suggestion with the keyboard.
+            harness.ime_insert_text("ru");
+            harness.key_down(KeyEvent::for_test(Modifiers::default(), KbKey::ArrowDown));
+            harness.ime_action(RETURN);
+            assert_eq!(tags(harness), vec!["rust"]);
+
+            // the text is trimmed, and tags are only added once.
+            harness.ime_insert_text(" gui ");
+            harness.ime_action(RETURN);
+            harness.ime_insert_text("rust");
+            harness.ime_action(RETURN);
+            assert_eq!(tags(harness), vec!["rust", "gui"]);
+
+            // Backspace in the empty text box removes the last tag.
+            harness.key_down(KeyEvent::for_test(Modifiers::default(), KbKey::Backspace));
+            assert_eq!(tags(harness), vec!["rust"]);
+        });
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A circular progress indicator.
+
+use std::f64::consts::PI;
+use tracing::{instrument, trace};
+
+use crate::kurbo::{Arc, Circle};
+use crate::piet::{LineCap, StrokeStyle};
+use crate::widget::prelude::*;
+use crate::{theme, Color, KeyOrValue, Vec2};
+
+/// How long the indeterminate arc takes for one turn, in seconds.
+const INDETERMINATE_PERIOD: f64 = 1.5;
+
+/// A circular progress indicator.
+///
+/// Like [`ProgressBar`], this impls `Widget<f64>`, expecting a float in the
+/// range `0.0..1.0`, which is shown as an arc growing clockwise from the top.
+///
+/// An [`indeterminate`] indicator ignores its data, and shows a turning arc
+/// instead, for work whose length is not known.
+///
+/// To customize the size, you can place it inside a [`SizedBox`] that has a
+/// fixed width and height.
+///
+/// [`ProgressBar`]: super::ProgressBar
+/// [`indeterminate`]: CircularProgress::indeterminate
+/// [`SizedBox`]: super::SizedBox
+pub struct CircularProgress {
+    indeterminate: bool,
+    /// The phase of the indeterminate animation, in `0.0..1.0`.
+    t: f64,
+    thickness: KeyOrValue<f64>,
+    color: KeyOrValue<Color>,
+    track_color: KeyOrValue<Color>,
+}
+
+impl CircularProgress {
+    /// Create a new `CircularProgress` that shows its data.
+    pub fn new() -> CircularProgress {
+        CircularProgress {
+            indeterminate: false,
+            t: 0.0,
+            thickness: 4.0.into(),
+            color: theme::PRIMARY_LIGHT.into(),
+            track_color: theme::BACKGROUND_LIGHT.into(),
+        }
+    }
+
+    /// Create a new `CircularProgress` that animates without showing a value.
+    pub fn indeterminate() -> CircularProgress {
+        CircularProgress {
+            indeterminate: true,
+            ..CircularProgress::new()
+        }
+    }
+
+    /// Builder-style method for setting the width of the arc.
+    ///
+    /// The argument can be either an `f64` or a [`Key<f64>`].
+    ///
+    /// [`Key<f64>`]: crate::Key
+    pub fn with_thickness(mut self, thickness: impl Into<KeyOrValue<f64>>) -> Self {
+        self.thickness = thickness.into();
+        self
+    }
+
+    /// Builder-style method for setting the color of the arc.
+    ///
+    /// The argument can be either a `Color` or a [`Key<Color>`].
+    ///
+    /// [`Key<Color>`]: crate::Key
+    pub fn with_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Builder-style method for setting the color of the circle behind the arc.
+    ///
+    /// The argument can be either a `Color` or a [`Key<Color>`].
+    ///
+    /// [`Key<Color>`]: crate::Key
+    pub fn with_track_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.track_color = color.into();
+        self
+    }
+
+    /// The start and sweep angle of the arc, in radians.
+    fn arc_angles(&self, data: f64) -> (f64, f64) {
+        if self.indeterminate {
+            // the arc grows and shrinks while it turns
+            let sweep = PI * (0.4 + 0.8 * (PI * self.t).sin());
+            (-PI / 2.0 + 2.0 * PI * self.t, sweep)
+        } else {
+            (-PI / 2.0, 2.0 * PI * data.clamp(0.0, 1.0))
+        }
+    }
+}
+
+impl Default for CircularProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<f64> for CircularProgress {
+    #[instrument(
+        name = "CircularProgress",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut f64, _env: &Env) {
+        if let Event::AnimFrame(interval) = event {
+            if self.indeterminate {
+                self.t += (*interval as f64) * 1e-9 / INDETERMINATE_PERIOD;
+                self.t = self.t.fract();
+                ctx.request_anim_frame();
+                ctx.request_paint();
+            }
+        }
+    }
+
+    #[instrument(
+        name = "CircularProgress",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &f64, _env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            if self.indeterminate {
+                ctx.request_anim_frame();
+            }
+        }
+    }
+
+    #[instrument(
+        name = "CircularProgress",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &f64, data: &f64, _env: &Env) {
+        if !self.indeterminate && !old_data.same(data) {
+            ctx.request_paint();
+        }
+    }
+
+    #[instrument(
+        name = "CircularProgress",
+        level = "trace",
+        skip(self, _ctx, bc, _data, env)
+    )]
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &f64,
+        env: &Env,
+    ) -> Size {
+        bc.debug_check("CircularProgress");
+        let side = env.get(theme::BASIC_WIDGET_HEIGHT) * 2.0;
+        let size = bc.constrain(Size::new(side, side));
+        trace!("Computed size: {}", size);
+        size
+    }
+
+    #[instrument(name = "CircularProgress", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &f64, env: &Env) {
+        let size = ctx.size();
+        let thickness = self.thickness.resolve(env);
+        let center = size.to_rect().center();
+        let radius = (size.width.min(size.height) - thickness) / 2.0;
+        if radius <= 0.0 {
+            return;
+        }
+
+        let track = Circle::new(center, radius);
+        ctx.stroke(track, &self.track_color.resolve(env), thickness);
+
+        let (start_angle, sweep_angle) = self.arc_angles(*data);
+        if sweep_angle > 0.0 {
+            let arc = Arc {
+                center,
+                radii: Vec2::new(radius, radius),
+                start_angle,
+                sweep_angle,
+                x_rotation: 0.0,
+            };
+            let style = StrokeStyle::new().line_cap(LineCap::Round);
+            ctx.stroke_styled(arc, &self.color.resolve(env), thickness, &style);
+        }
+    }
+}
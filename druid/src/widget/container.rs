@@ -16,7 +16,7 @@
 
 use super::BackgroundBrush;
 use crate::widget::prelude::*;
-use crate::{Color, Data, KeyOrValue, Point, WidgetPod};
+use crate::{Color, Data, KeyOrValue, Point, StrokeAlignment, WidgetPod};
 use tracing::{instrument, trace, trace_span};
 
 struct BorderStyle {
@@ -205,12 +205,12 @@ impl<T: Data> Widget<T> for Container<T> {
         }
 
         if let Some(border) = &self.border {
-            let border_width = border.width.resolve(env);
-            let border_rect = ctx
-                .size()
-                .to_rect()
-                .inset(border_width / -2.0)
-                .to_rounded_rect(corner_radius);
+            let (border_rect, border_width) = ctx.snap_stroke(
+                ctx.size().to_rect(),
+                border.width.resolve(env),
+                StrokeAlignment::Inside,
+            );
+            let border_rect = border_rect.to_rounded_rect(corner_radius);
             ctx.stroke(border_rect, &border.color.resolve(env), border_width);
         };
 
@@ -0,0 +1,555 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A selection rectangle drawn over another widget, for cropping.
+
+use tracing::{instrument, trace};
+
+use crate::kurbo::Line;
+use crate::widget::prelude::*;
+use crate::{Color, Cursor, Lens, LensExt, MouseButton, Point, Rect, Vec2, WidgetPod};
+
+const HANDLE_SIZE: f64 = 8.0;
+/// How close to an edge the mouse has to be to grab it.
+const HIT_DISTANCE: f64 = 6.0;
+const DEFAULT_MIN_SIZE: f64 = 16.0;
+const BORDER_COLOR: Color = Color::WHITE;
+const GUIDE_COLOR: Color = Color::rgba8(0xff, 0xff, 0xff, 0x80);
+const HANDLE_OUTLINE_COLOR: Color = Color::rgba8(0, 0, 0, 0xa0);
+const DIM_COLOR: Color = Color::rgba8(0, 0, 0, 0x80);
+
+/// A resizable, movable selection rectangle over a child, such as an image.
+///
+/// The selection is kept in the data as a rectangle that is *normalized* to
+/// the size of the child: `(0, 0)` is its top left corner and `(1, 1)` its
+/// bottom right corner, so the selection stays the same when the child is
+/// resized. An empty rectangle means that nothing is selected.
+///
+/// The selection can be moved by dragging it, and resized by dragging its
+/// edges or handles; dragging outside of it starts a new selection. The area
+/// outside of the selection is dimmed, and the selection shows guides at its
+/// thirds.
+///
+/// The aspect ratio can be locked with [`with_aspect_ratio`]; holding shift
+/// while resizing keeps the current aspect ratio.
+///
+/// [`with_aspect_ratio`]: CropOverlay::with_aspect_ratio
+pub struct CropOverlay<T> {
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    crop: Box<dyn CropStore<T>>,
+    /// The aspect ratio (width / height) of the selection, in the child's coordinates.
+    aspect_ratio: Option<f64>,
+    guides: bool,
+    min_size: f64,
+    drag: Option<Drag>,
+}
+
+/// Where the selection is kept in the data.
+trait CropStore<T> {
+    fn get(&self, data: &T) -> Rect;
+    fn put(&self, data: &mut T, crop: Rect);
+}
+
+impl<T, L: Lens<T, Rect>> CropStore<T> for L {
+    fn get(&self, data: &T) -> Rect {
+        LensExt::get(self, data)
+    }
+
+    fn put(&self, data: &mut T, crop: Rect) {
+        LensExt::put(self, data, crop)
+    }
+}
+
+/// The edges of the selection that are moved by a drag.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Edges {
+    left: bool,
+    top: bool,
+    right: bool,
+    bottom: bool,
+}
+
+impl Edges {
+    fn any(self) -> bool {
+        self.left || self.top || self.right || self.bottom
+    }
+
+    fn cursor(self) -> Cursor {
+        let horizontal = self.left || self.right;
+        let vertical = self.top || self.bottom;
+        match (horizontal, vertical) {
+            (true, false) => Cursor::ResizeLeftRight,
+            (false, true) => Cursor::ResizeUpDown,
+            _ => Cursor::Crosshair,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    Move {
+        start: Point,
+        original: Rect,
+    },
+    Resize {
+        start: Point,
+        original: Rect,
+        edges: Edges,
+    },
+    /// A new selection, from the point where the drag started.
+    Create(Point),
+}
+
+impl<T: Data> CropOverlay<T> {
+    /// Create a new `CropOverlay` over `child`.
+    ///
+    /// The normalized selection is kept in the data, at `crop`.
+    pub fn new(child: impl Widget<T> + 'static, crop: impl Lens<T, Rect> + 'static) -> Self {
+        CropOverlay {
+            child: WidgetPod::new(Box::new(child)),
+            crop: Box::new(crop),
+            aspect_ratio: None,
+            guides: true,
+            min_size: DEFAULT_MIN_SIZE,
+            drag: None,
+        }
+    }
+
+    /// Builder-style method to lock the aspect ratio (width / height) of the selection.
+    ///
+    /// The ratio is in terms of the child's size, not of the normalized rectangle.
+    pub fn with_aspect_ratio(mut self, ratio: f64) -> Self {
+        self.set_aspect_ratio(Some(ratio));
+        self
+    }
+
+    /// Builder-style method to set whether the guides at the thirds of the
+    /// selection are drawn.
+    pub fn with_guides(mut self, guides: bool) -> Self {
+        self.guides = guides;
+        self
+    }
+
+    /// Builder-style method to set the smallest size of the selection, in display points.
+    pub fn with_min_size(mut self, min_size: f64) -> Self {
+        self.min_size = min_size.max(0.0);
+        self
+    }
+
+    /// Lock the aspect ratio (width / height) of the selection, or unlock it with `None`.
+    ///
+    /// This doesn't change the current selection.
+    pub fn set_aspect_ratio(&mut self, ratio: Option<f64>) {
+        self.aspect_ratio = ratio.filter(|ratio| ratio.is_finite() && *ratio > 0.0);
+    }
+
+    /// The selection in our coordinates, or `None` if nothing is selected.
+    fn selection(&self, data: &T, size: Size) -> Option<Rect> {
+        let crop = self.crop.get(data);
+        if crop.area() <= 0.0 {
+            return None;
+        }
+        Some(Rect::new(
+            crop.x0 * size.width,
+            crop.y0 * size.height,
+            crop.x1 * size.width,
+            crop.y1 * size.height,
+        ))
+    }
+
+    fn set_selection(&self, data: &mut T, rect: Rect, size: Size) {
+        if size.is_empty() {
+            return;
+        }
+        let crop = Rect::new(
+            rect.x0 / size.width,
+            rect.y0 / size.height,
+            rect.x1 / size.width,
+            rect.y1 / size.height,
+        );
+        if !self.crop.get(data).same(&crop) {
+            self.crop.put(data, crop);
+        }
+    }
+
+    /// The part of the selection that is under `pos`.
+    fn hit_test(selection: Rect, pos: Point) -> Option<Drag> {
+        let outer = selection.inflate(HIT_DISTANCE, HIT_DISTANCE);
+        if !outer.contains(pos) {
+            return None;
+        }
+        // in a small selection, both edges can be in reach; take the closer one
+        let (left, right) = ((pos.x - selection.x0).abs(), (pos.x - selection.x1).abs());
+        let (top, bottom) = ((pos.y - selection.y0).abs(), (pos.y - selection.y1).abs());
+        let edges = Edges {
+            left: left <= HIT_DISTANCE && left <= right,
+            top: top <= HIT_DISTANCE && top <= bottom,
+            right: right <= HIT_DISTANCE && right < left,
+            bottom: bottom <= HIT_DISTANCE && bottom < top,
+        };
+        if edges.any() {
+            Some(Drag::Resize {
+                start: pos,
+                original: selection,
+                edges,
+            })
+        } else if selection.contains(pos) {
+            Some(Drag::Move {
+                start: pos,
+                original: selection,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn drag_to(&self, drag: Drag, pos: Point, keep_ratio: bool, size: Size) -> Rect {
+        let bounds = size.to_rect();
+        match drag {
+            Drag::Move { start, original } => move_rect(original, pos - start, bounds),
+            Drag::Resize {
+                start,
+                original,
+                edges,
+            } => {
+                let ratio = self
+                    .aspect_ratio
+                    .or_else(|| Some(original.aspect_ratio().recip()).filter(|_| keep_ratio));
+                resize_rect(original, edges, pos - start, ratio, bounds, self.min_size)
+            }
+            Drag::Create(anchor) => {
+                let delta = pos - anchor;
+                let edges = Edges {
+                    left: delta.x < 0.0,
+                    top: delta.y < 0.0,
+                    right: delta.x >= 0.0,
+                    bottom: delta.y >= 0.0,
+                };
+                let original = Rect::from_origin_size(anchor, Size::ZERO);
+                resize_rect(
+                    original,
+                    edges,
+                    delta,
+                    self.aspect_ratio,
+                    bounds,
+                    self.min_size,
+                )
+            }
+        }
+    }
+
+    fn paint_selection(&self, ctx: &mut PaintCtx, selection: Rect) {
+        let size = ctx.size();
+        // dim everything outside of the selection
+        let dimmed = [
+            Rect::new(0.0, 0.0, size.width, selection.y0),
+            Rect::new(0.0, selection.y1, size.width, size.height),
+            Rect::new(0.0, selection.y0, selection.x0, selection.y1),
+            Rect::new(selection.x1, selection.y0, size.width, selection.y1),
+        ];
+        for rect in &dimmed {
+            if rect.area() > 0.0 {
+                ctx.fill(*rect, &DIM_COLOR);
+            }
+        }
+
+        if self.guides {
+            for i in 1..3 {
+                let t = i as f64 / 3.0;
+                let x = selection.x0 + selection.width() * t;
+                let y = selection.y0 + selection.height() * t;
+                let vertical = Line::new((x, selection.y0), (x, selection.y1));
+                let horizontal = Line::new((selection.x0, y), (selection.x1, y));
+                ctx.stroke(vertical, &GUIDE_COLOR, 1.0);
+                ctx.stroke(horizontal, &GUIDE_COLOR, 1.0);
+            }
+        }
+
+        ctx.stroke(selection.inset(-0.5), &BORDER_COLOR, 1.0);
+
+        let center = selection.center();
+        let xs = [selection.x0, center.x, selection.x1];
+        let ys = [selection.y0, center.y, selection.y1];
+        for (i, x) in xs.iter().enumerate() {
+            for (j, y) in ys.iter().enumerate() {
+                if i == 1 && j == 1 {
+                    continue;
+                }
+                let handle = Rect::from_center_size((*x, *y), (HANDLE_SIZE, HANDLE_SIZE));
+                ctx.fill(handle, &BORDER_COLOR);
+                ctx.stroke(handle.inset(-0.5), &HANDLE_OUTLINE_COLOR, 1.0);
+            }
+        }
+    }
+}
+
+/// Moves `rect` by `delta`, keeping it inside of `bounds`.
+fn move_rect(rect: Rect, delta: Vec2, bounds: Rect) -> Rect {
+    let dx = delta.x.max(bounds.x0 - rect.x0).min(bounds.x1 - rect.x1);
+    let dy = delta.y.max(bounds.y0 - rect.y0).min(bounds.y1 - rect.y1);
+    rect + Vec2::new(dx, dy)
+}
+
+/// Moves the `edges` of `rect` by `delta`, keeping it inside of `bounds` and
+/// at least `min` wide and tall.
+///
+/// With an aspect `ratio`, the side that is moved the most decides the size,
+/// and the rectangle grows away from the opposite corner or edge.
+fn resize_rect(
+    rect: Rect,
+    edges: Edges,
+    delta: Vec2,
+    ratio: Option<f64>,
+    bounds: Rect,
+    min: f64,
+) -> Rect {
+    let ratio = match ratio {
+        Some(ratio) if ratio.is_finite() && ratio > 0.0 => ratio,
+        _ => {
+            let mut r = rect;
+            if edges.left {
+                r.x0 = (r.x0 + delta.x).max(bounds.x0).min(r.x1 - min);
+            }
+            if edges.right {
+                r.x1 = (r.x1 + delta.x).min(bounds.x1).max(r.x0 + min);
+            }
+            if edges.top {
+                r.y0 = (r.y0 + delta.y).max(bounds.y0).min(r.y1 - min);
+            }
+            if edges.bottom {
+                r.y1 = (r.y1 + delta.y).min(bounds.y1).max(r.y0 + min);
+            }
+            return r;
+        }
+    };
+
+    let horizontal = edges.left || edges.right;
+    let vertical = edges.top || edges.bottom;
+    let center = rect.center();
+    let mut width = rect.width()
+        + match (edges.left, edges.right) {
+            (true, _) => -delta.x,
+            (_, true) => delta.x,
+            _ => 0.0,
+        };
+    let height = rect.height()
+        + match (edges.top, edges.bottom) {
+            (true, _) => -delta.y,
+            (_, true) => delta.y,
+            _ => 0.0,
+        };
+    // follow the edge that was dragged the furthest, and derive the other
+    // side from it.
+    if vertical && (!horizontal || width / ratio < height) {
+        width = height * ratio;
+    }
+
+    width = width.max(min).max(min * ratio);
+    let mut height = width / ratio;
+
+    // the space between the fixed side and the bounds
+    let max_width = match (edges.left, edges.right) {
+        (true, _) => rect.x1 - bounds.x0,
+        (_, true) => bounds.x1 - rect.x0,
+        _ => 2.0 * (center.x - bounds.x0).min(bounds.x1 - center.x),
+    };
+    let max_height = match (edges.top, edges.bottom) {
+        (true, _) => rect.y1 - bounds.y0,
+        (_, true) => bounds.y1 - rect.y0,
+        _ => 2.0 * (center.y - bounds.y0).min(bounds.y1 - center.y),
+    };
+    let shrink = (max_width / width).min(max_height / height).min(1.0);
+    width *= shrink;
+    height *= shrink;
+
+    let x0 = match (edges.left, edges.right) {
+        (true, _) => rect.x1 - width,
+        (_, true) => rect.x0,
+        _ => center.x - width / 2.0,
+    };
+    let y0 = match (edges.top, edges.bottom) {
+        (true, _) => rect.y1 - height,
+        (_, true) => rect.y0,
+        _ => center.y - height / 2.0,
+    };
+    Rect::from_origin_size((x0, y0), (width, height))
+}
+
+impl<T: Data> Widget<T> for CropOverlay<T> {
+    #[instrument(
+        name = "CropOverlay",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        let size = ctx.size();
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                let hit = self
+                    .selection(data, size)
+                    .and_then(|selection| Self::hit_test(selection, mouse.pos));
+                self.drag = Some(hit.unwrap_or(Drag::Create(mouse.pos)));
+                ctx.set_active(true);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                if let Some(drag) = self.drag {
+                    let rect = self.drag_to(drag, mouse.pos, mouse.mods.shift(), size);
+                    self.set_selection(data, rect, size);
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                if let Some(Drag::Create(anchor)) = self.drag.take() {
+                    // a click without a drag clears the selection
+                    if anchor == mouse.pos {
+                        self.set_selection(data, Rect::ZERO, size);
+                    }
+                }
+                trace!("CropOverlay selection {:?}", self.crop.get(data));
+                ctx.set_active(false);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseMove(mouse) => {
+                let hit = self
+                    .selection(data, size)
+                    .and_then(|selection| Self::hit_test(selection, mouse.pos));
+                match hit {
+                    Some(Drag::Resize { edges, .. }) => ctx.set_cursor(&edges.cursor()),
+                    Some(Drag::Move { .. }) => ctx.set_cursor(&Cursor::Arrow),
+                    _ => ctx.set_cursor(&Cursor::Crosshair),
+                }
+            }
+            _ => (),
+        }
+        self.child.event(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "CropOverlay",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.child.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "CropOverlay",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if !self.crop.get(old_data).same(&self.crop.get(data)) {
+            ctx.request_paint();
+        }
+        self.child.update(ctx, data, env);
+    }
+
+    #[instrument(name = "CropOverlay", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("CropOverlay");
+        let size = self.child.layout(ctx, bc, data, env);
+        self.child.set_origin(ctx, data, env, Point::ORIGIN);
+        trace!("Computed size: {}", size);
+        size
+    }
+
+    #[instrument(name = "CropOverlay", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.child.paint(ctx, data, env);
+        if let Some(selection) = self.selection(data, ctx.size()) {
+            self.paint_selection(ctx, selection);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    const BOUNDS: Rect = Rect::new(0.0, 0.0, 200.0, 100.0);
+
+    fn edges(left: bool, top: bool, right: bool, bottom: bool) -> Edges {
+        Edges {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn move_stays_in_bounds() {
+        let rect = Rect::new(10.0, 10.0, 50.0, 50.0);
+        let moved = move_rect(rect, Vec2::new(-30.0, 20.0), BOUNDS);
+        assert_eq!(moved, Rect::new(0.0, 30.0, 40.0, 70.0));
+        let moved = move_rect(rect, Vec2::new(500.0, 500.0), BOUNDS);
+        assert_eq!(moved, Rect::new(160.0, 60.0, 200.0, 100.0));
+    }
+
+    #[test]
+    fn free_resize() {
+        let rect = Rect::new(50.0, 20.0, 100.0, 60.0);
+        // the bottom right corner
+        let resized = resize_rect(
+            rect,
+            edges(false, false, true, true),
+            Vec2::new(20.0, 100.0),
+            None,
+            BOUNDS,
+            10.0,
+        );
+        assert_eq!(resized, Rect::new(50.0, 20.0, 120.0, 100.0));
+        // the left edge can't pass the right edge
+        let resized = resize_rect(
+            rect,
+            edges(true, false, false, false),
+            Vec2::new(80.0, 0.0),
+            None,
+            BOUNDS,
+            10.0,
+        );
+        assert_eq!(resized, Rect::new(90.0, 20.0, 100.0, 60.0));
+    }
+
+    #[test]
+    fn locked_resize() {
+        let rect = Rect::new(50.0, 20.0, 90.0, 40.0);
+        // dragging the right edge grows the height around the center
+        let resized = resize_rect(
+            rect,
+            edges(false, false, true, false),
+            Vec2::new(20.0, 0.0),
+            Some(2.0),
+            BOUNDS,
+            10.0,
+        );
+        assert_eq!(resized, Rect::new(50.0, 15.0, 110.0, 45.0));
+        // the top left corner is limited by the top of the bounds
+        let resized = resize_rect(
+            rect,
+            edges(true, true, false, false),
+            Vec2::new(-40.0, -10.0),
+            Some(2.0),
+            BOUNDS,
+            10.0,
+        );
+        assert_eq!(resized, Rect::new(10.0, 0.0, 90.0, 40.0));
+    }
+}
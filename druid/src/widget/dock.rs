@@ -0,0 +1,970 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Panels that can be docked at the edges of a window, or floated into
+//! their own windows.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::commands::CLOSE_WINDOW;
+use crate::widget::prelude::*;
+use crate::widget::{Controller, ControllerHost};
+use crate::{
+    theme, ArcStr, Cursor, Data, Lens, MouseButton, Point, Rect, Selector, TextLayout, Vec2,
+    WidgetPod, WindowConfig, WindowId,
+};
+use tracing::{instrument, trace};
+
+/// Submitted to the dock by a floating panel when its window is closed.
+const PANEL_WINDOW_CLOSED: Selector<WindowId> =
+    Selector::new("druid.builtin.dock-panel-window-closed");
+
+const TAB_HEIGHT: f64 = 24.0;
+const TAB_PADDING: f64 = 8.0;
+const SPLITTER_WIDTH: f64 = 4.0;
+/// How close to an edge of the dock a panel has to be dropped to dock there.
+const DROP_MARGIN: f64 = 48.0;
+/// How far a tab has to be dragged before it comes loose.
+const DRAG_THRESHOLD: f64 = 4.0;
+/// The smallest width or height of the center widget.
+const MIN_CENTER: f64 = 60.0;
+const DEFAULT_EXTENT: f64 = 200.0;
+const FLOATING_SIZE: Size = Size::new(300.0, 400.0);
+
+/// An edge of a [`Dock`] that panels can be docked at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub enum DockEdge {
+    /// Along the left edge, above the bottom panels.
+    Left,
+    /// Along the right edge, above the bottom panels.
+    Right,
+    /// Along the bottom edge, across the whole dock.
+    Bottom,
+}
+
+const EDGES: [DockEdge; 3] = [DockEdge::Left, DockEdge::Right, DockEdge::Bottom];
+
+/// Where the panels of a [`Dock`] are.
+///
+/// Each edge has a stack of panels that are shown as tabs, one of which is
+/// shown at a time. Panels can also be floating in their own windows, or
+/// not be in the layout at all, in which case they are hidden. Panels are
+/// referred to by the ids they were added to the dock with.
+///
+/// The layout is changed by the dock when the user moves panels around,
+/// and it can be changed by the application, for example to show a panel
+/// from a menu. It can be saved as a string with `to_string` and restored
+/// with `parse`, so that the layout survives restarts; panel ids must not
+/// contain commas, `@` or whitespace for this to work.
+#[derive(Debug, Clone, PartialEq, Data)]
+pub struct DockLayout {
+    left: DockArea,
+    right: DockArea,
+    bottom: DockArea,
+    floating: Arc<Vec<String>>,
+    /// Where the floating panels were docked before they were floated.
+    float_slots: Arc<Vec<(String, DockEdge, usize)>>,
+}
+
+/// The panels at one edge of a [`DockLayout`].
+#[derive(Debug, Clone, PartialEq, Data)]
+struct DockArea {
+    panels: Arc<Vec<String>>,
+    active: usize,
+    extent: f64,
+}
+
+/// The error returned when parsing a [`DockLayout`] fails.
+#[derive(Debug, Clone)]
+pub struct DockLayoutParseError;
+
+impl DockLayout {
+    /// Create a layout without any panels.
+    pub fn new() -> Self {
+        DockLayout {
+            left: DockArea::new(),
+            right: DockArea::new(),
+            bottom: DockArea::new(),
+            floating: Arc::new(Vec::new()),
+            float_slots: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Builder-style method for docking the panel `id` at `edge`.
+    pub fn with_panel(mut self, edge: DockEdge, id: impl Into<String>) -> Self {
+        self.dock(id, edge);
+        self
+    }
+
+    /// Dock the panel `id` at `edge`, on top of the panels that are already
+    /// there, moving it from wherever it was.
+    pub fn dock(&mut self, id: impl Into<String>, edge: DockEdge) {
+        let id = id.into();
+        self.remove(&id);
+        let area = self.area_mut(edge);
+        let panels = Arc::make_mut(&mut area.panels);
+        panels.push(id);
+        area.active = panels.len() - 1;
+    }
+
+    /// Float the panel `id` in its own window, moving it from wherever it
+    /// was.
+    ///
+    /// If it was docked, its place is remembered for [`redock`].
+    ///
+    /// [`redock`]: DockLayout::redock
+    pub fn float(&mut self, id: impl Into<String>) {
+        let id = id.into();
+        let slot = self.edge(&id).map(|edge| {
+            let index = self.area(edge).panels.iter().position(|panel| *panel == id);
+            (id.clone(), edge, index.unwrap_or(0))
+        });
+        self.remove(&id);
+        if let Some(slot) = slot {
+            Arc::make_mut(&mut self.float_slots).push(slot);
+        }
+        Arc::make_mut(&mut self.floating).push(id);
+    }
+
+    /// Dock the floating panel `id` where it was docked before it was
+    /// floated, and show it there.
+    ///
+    /// A panel that wasn't docked before is hidden instead.
+    pub fn redock(&mut self, id: &str) {
+        let slot = self
+            .float_slots
+            .iter()
+            .find(|(panel, _, _)| panel == id)
+            .map(|(_, edge, index)| (*edge, *index));
+        self.remove(id);
+        if let Some((edge, index)) = slot {
+            let area = self.area_mut(edge);
+            let panels = Arc::make_mut(&mut area.panels);
+            let index = index.min(panels.len());
+            panels.insert(index, id.to_string());
+            area.active = index;
+        }
+    }
+
+    /// Hide the panel `id`.
+    pub fn remove(&mut self, id: &str) {
+        for edge in EDGES.iter() {
+            self.area_mut(*edge).remove(id);
+        }
+        if self.floating.iter().any(|floating| floating == id) {
+            Arc::make_mut(&mut self.floating).retain(|floating| floating != id);
+        }
+        if self.float_slots.iter().any(|(panel, _, _)| panel == id) {
+            Arc::make_mut(&mut self.float_slots).retain(|(panel, _, _)| panel != id);
+        }
+    }
+
+    /// Make the panel `id` the one that is shown at its edge.
+    pub fn show(&mut self, id: &str) {
+        for edge in EDGES.iter() {
+            let area = self.area_mut(*edge);
+            if let Some(index) = area.panels.iter().position(|panel| panel == id) {
+                area.active = index;
+            }
+        }
+    }
+
+    /// The edge the panel `id` is docked at, if it is docked.
+    pub fn edge(&self, id: &str) -> Option<DockEdge> {
+        EDGES
+            .iter()
+            .copied()
+            .find(|edge| self.area(*edge).panels.iter().any(|panel| panel == id))
+    }
+
+    /// Whether the panel `id` is floating in its own window.
+    pub fn is_floating(&self, id: &str) -> bool {
+        self.floating.iter().any(|floating| floating == id)
+    }
+
+    /// The panels docked at `edge`, from the first tab to the last.
+    pub fn panels(&self, edge: DockEdge) -> &[String] {
+        &self.area(edge).panels
+    }
+
+    /// The panels that are floating in their own windows.
+    pub fn floating(&self) -> &[String] {
+        &self.floating
+    }
+
+    /// The panel that is shown at `edge`, if any are docked there.
+    pub fn active(&self, edge: DockEdge) -> Option<&str> {
+        let area = self.area(edge);
+        area.panels.get(area.active).map(String::as_str)
+    }
+
+    /// The width of the left or right edge, or the height of the bottom
+    /// edge, including the tabs.
+    pub fn extent(&self, edge: DockEdge) -> f64 {
+        self.area(edge).extent
+    }
+
+    /// Set the width of the left or right edge, or the height of the bottom
+    /// edge, including the tabs.
+    pub fn set_extent(&mut self, edge: DockEdge, extent: f64) {
+        self.area_mut(edge).extent = extent.max(0.0);
+    }
+
+    fn area(&self, edge: DockEdge) -> &DockArea {
+        match edge {
+            DockEdge::Left => &self.left,
+            DockEdge::Right => &self.right,
+            DockEdge::Bottom => &self.bottom,
+        }
+    }
+
+    fn area_mut(&mut self, edge: DockEdge) -> &mut DockArea {
+        match edge {
+            DockEdge::Left => &mut self.left,
+            DockEdge::Right => &mut self.right,
+            DockEdge::Bottom => &mut self.bottom,
+        }
+    }
+}
+
+impl Default for DockLayout {
+    fn default() -> Self {
+        DockLayout::new()
+    }
+}
+
+impl DockArea {
+    fn new() -> Self {
+        DockArea {
+            panels: Arc::new(Vec::new()),
+            active: 0,
+            extent: DEFAULT_EXTENT,
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(index) = self.panels.iter().position(|panel| panel == id) {
+            Arc::make_mut(&mut self.panels).remove(index);
+            if self.active > index || self.active >= self.panels.len() {
+                self.active = self.active.saturating_sub(1);
+            }
+        }
+    }
+}
+
+impl DockEdge {
+    fn name(self) -> &'static str {
+        match self {
+            DockEdge::Left => "left",
+            DockEdge::Right => "right",
+            DockEdge::Bottom => "bottom",
+        }
+    }
+}
+
+/// Each edge is written as `<edge> <extent> <active> <ids>`, followed by
+/// `floating <ids>`, one per line, with the ids separated by commas. Floating
+/// panels that were docked before are written as `<id>@<edge>:<index>`.
+impl fmt::Display for DockLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for edge in EDGES.iter() {
+            let area = self.area(*edge);
+            writeln!(
+                f,
+                "{} {} {} {}",
+                edge.name(),
+                area.extent,
+                area.active,
+                area.panels.join(",")
+            )?;
+        }
+        let floating: Vec<String> = self
+            .floating
+            .iter()
+            .map(
+                |id| match self.float_slots.iter().find(|(panel, _, _)| panel == id) {
+                    Some((_, edge, index)) => format!("{}@{}:{}", id, edge.name(), index),
+                    None => id.clone(),
+                },
+            )
+            .collect();
+        write!(f, "floating {}", floating.join(","))
+    }
+}
+
+impl FromStr for DockLayout {
+    type Err = DockLayoutParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn ids(ids: Option<&str>) -> Arc<Vec<String>> {
+            let ids = ids.unwrap_or("").split(',').filter(|id| !id.is_empty());
+            Arc::new(ids.map(String::from).collect())
+        }
+
+        let mut layout = DockLayout::new();
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let mut parts = line.split_whitespace();
+            let edge = match parts.next() {
+                Some("floating") => {
+                    let mut floating = Vec::new();
+                    let mut slots = Vec::new();
+                    for entry in ids(parts.next()).iter() {
+                        let mut entry = entry.splitn(2, '@');
+                        let id = entry.next().unwrap_or("").to_string();
+                        if let Some(slot) = entry.next() {
+                            let (edge, index) = slot.split_at(slot.find(':').unwrap_or(0));
+                            let edge = EDGES.iter().copied().find(|e| e.name() == edge);
+                            let index = index.trim_start_matches(':').parse().ok();
+                            match (edge, index) {
+                                (Some(edge), Some(index)) => slots.push((id.clone(), edge, index)),
+                                _ => return Err(DockLayoutParseError),
+                            }
+                        }
+                        floating.push(id);
+                    }
+                    layout.floating = Arc::new(floating);
+                    layout.float_slots = Arc::new(slots);
+                    continue;
+                }
+                Some(name) => EDGES
+                    .iter()
+                    .copied()
+                    .find(|edge| edge.name() == name)
+                    .ok_or(DockLayoutParseError)?,
+                None => continue,
+            };
+            let extent = parts.next().and_then(|extent| extent.parse().ok());
+            let active = parts.next().and_then(|active| active.parse().ok());
+            let area = layout.area_mut(edge);
+            area.extent = extent.ok_or(DockLayoutParseError)?;
+            area.active = active.ok_or(DockLayoutParseError)?;
+            area.panels = ids(parts.next());
+        }
+        Ok(layout)
+    }
+}
+
+impl fmt::Display for DockLayoutParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid dock layout")
+    }
+}
+
+impl std::error::Error for DockLayoutParseError {}
+
+/// A widget surrounded by panels that the user can rearrange.
+///
+/// Panels are docked at the left, right or bottom edge, where the panels at
+/// each edge are stacked as tabs. Dragging a tab moves its panel: dropping
+/// it near an edge or on other panels docks it there, and dropping it over
+/// the center widget floats it in its own window. Closing that window
+/// docks the panel again where it was before it was floated. The edges can
+/// be resized by dragging their inner side.
+///
+/// Where the panels are is kept in a [`DockLayout`] in the data, which the
+/// application can change and save.
+///
+/// Floating panels get a new instance of their widget, created by the
+/// function the panel was added with, and share the dock's data.
+pub struct Dock<T> {
+    center: WidgetPod<T, Box<dyn Widget<T>>>,
+    panels: Vec<Panel<T>>,
+    layout: Box<dyn LayoutStore<T>>,
+    areas: Vec<AreaGeometry>,
+    /// The panels that have a window, with the index of the panel.
+    floating: Vec<(usize, WindowId)>,
+    /// Where the next floating window goes, in screen coordinates.
+    float_origin: Option<Point>,
+    drag: Option<Drag>,
+}
+
+struct Panel<T> {
+    id: String,
+    title: TextLayout<ArcStr>,
+    make_widget: Box<dyn Fn() -> Box<dyn Widget<T>>>,
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+}
+
+/// Where the parts of an edge ended up in the last layout.
+#[derive(Default)]
+struct AreaGeometry {
+    rect: Rect,
+    splitter: Rect,
+    /// The tabs, with the index of their panel.
+    tabs: Vec<(usize, Rect)>,
+    /// The index of the panel that is shown.
+    active: Option<usize>,
+}
+
+enum Drag {
+    /// A tab was pressed, but hasn't been dragged far enough to come loose.
+    Pressed {
+        panel: usize,
+        start: Point,
+    },
+    Tab {
+        panel: usize,
+        pos: Point,
+    },
+    Splitter(DockEdge),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DropTarget {
+    Edge(DockEdge),
+    Float,
+}
+
+/// Where the layout is kept in the data.
+trait LayoutStore<T> {
+    fn get(&self, data: &T) -> DockLayout;
+    fn update(&self, data: &mut T, f: &mut dyn FnMut(&mut DockLayout));
+}
+
+impl<T, L: Lens<T, DockLayout>> LayoutStore<T> for L {
+    fn get(&self, data: &T) -> DockLayout {
+        self.with(data, DockLayout::clone)
+    }
+
+    fn update(&self, data: &mut T, f: &mut dyn FnMut(&mut DockLayout)) {
+        self.with_mut(data, |layout| f(layout))
+    }
+}
+
+/// Tells the dock when the window of a floating panel is closed.
+struct FloatingPanel {
+    dock: WidgetId,
+}
+
+impl<T: Data, W: Widget<T>> Controller<T, W> for FloatingPanel {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::WindowDisconnected = event {
+            ctx.submit_command(PANEL_WINDOW_CLOSED.with(ctx.window_id()).to(self.dock));
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+impl<T: Data> Dock<T> {
+    /// Create a new `Dock` around `center`, with the layout that `lens`
+    /// points to.
+    pub fn new(center: impl Widget<T> + 'static, lens: impl Lens<T, DockLayout> + 'static) -> Self {
+        Dock {
+            center: WidgetPod::new(Box::new(center)),
+            panels: Vec::new(),
+            layout: Box::new(lens),
+            areas: EDGES.iter().map(|_| AreaGeometry::default()).collect(),
+            floating: Vec::new(),
+            float_origin: None,
+            drag: None,
+        }
+    }
+
+    /// Builder-style method for adding a panel, which is shown with the
+    /// title `title` while it is in the layout.
+    ///
+    /// `make_widget` is called once for the docked panel, and again every
+    /// time it is floated.
+    pub fn with_panel<W: Widget<T> + 'static>(
+        mut self,
+        id: impl Into<String>,
+        title: impl Into<ArcStr>,
+        make_widget: impl Fn() -> W + 'static,
+    ) -> Self {
+        let mut title = TextLayout::from_text(title.into());
+        title.set_text_color(theme::TEXT_COLOR);
+        let make_widget = move || -> Box<dyn Widget<T>> { Box::new(make_widget()) };
+        self.panels.push(Panel {
+            id: id.into(),
+            title,
+            child: WidgetPod::new(make_widget()),
+            make_widget: Box::new(make_widget),
+        });
+        self
+    }
+
+    fn panel_index(&self, id: &str) -> Option<usize> {
+        self.panels.iter().position(|panel| panel.id == id)
+    }
+
+    /// The panels that should be floating but don't have a window, and the
+    /// windows of the panels that shouldn't be floating anymore.
+    fn floating_changes(&mut self, data: &T) -> (Vec<usize>, Vec<WindowId>) {
+        let layout = self.layout.get(data);
+        let mut to_close = Vec::new();
+        let panels = &self.panels;
+        self.floating.retain(|(panel, window)| {
+            let keep = layout.is_floating(&panels[*panel].id);
+            if !keep {
+                to_close.push(*window);
+            }
+            keep
+        });
+        let to_open = layout
+            .floating()
+            .iter()
+            .filter_map(|id| self.panel_index(id))
+            .filter(|panel| !self.floating.iter().any(|(open, _)| open == panel))
+            .collect();
+        (to_open, to_close)
+    }
+
+    /// The window config and widget for a floating panel.
+    fn floating_window(
+        &mut self,
+        panel: usize,
+        dock: WidgetId,
+        origin: Point,
+    ) -> (WindowConfig, impl Widget<T>) {
+        let config = WindowConfig::default()
+            .window_size(FLOATING_SIZE)
+            .set_position(origin);
+        let widget =
+            ControllerHost::new((self.panels[panel].make_widget)(), FloatingPanel { dock });
+        // cascade windows that are opened at the same time.
+        self.float_origin = Some(origin + Vec2::new(TAB_HEIGHT, TAB_HEIGHT));
+        (config, widget)
+    }
+
+    fn tab_at(&self, pos: Point) -> Option<usize> {
+        self.areas
+            .iter()
+            .flat_map(|area| area.tabs.iter())
+            .find(|(_, rect)| rect.contains(pos))
+            .map(|(panel, _)| *panel)
+    }
+
+    fn splitter_at(&self, pos: Point) -> Option<DockEdge> {
+        EDGES
+            .iter()
+            .zip(&self.areas)
+            .find(|(_, area)| area.splitter.contains(pos))
+            .map(|(edge, _)| *edge)
+    }
+
+    fn drop_target(&self, pos: Point, size: Size) -> DropTarget {
+        if pos.y > size.height - DROP_MARGIN {
+            DropTarget::Edge(DockEdge::Bottom)
+        } else if pos.x < DROP_MARGIN {
+            DropTarget::Edge(DockEdge::Left)
+        } else if pos.x > size.width - DROP_MARGIN {
+            DropTarget::Edge(DockEdge::Right)
+        } else {
+            EDGES
+                .iter()
+                .zip(&self.areas)
+                .find(|(_, area)| area.rect.contains(pos))
+                .map(|(edge, _)| DropTarget::Edge(*edge))
+                .unwrap_or(DropTarget::Float)
+        }
+    }
+
+    /// The part of the dock that is highlighted while dropping on `target`.
+    fn drop_rect(&self, target: DropTarget, size: Size) -> Rect {
+        match target {
+            DropTarget::Edge(edge) => {
+                let area = &self.areas[edge as usize];
+                if area.rect.area() > 0.0 {
+                    return area.rect;
+                }
+                match edge {
+                    DockEdge::Left => Rect::new(0.0, 0.0, DROP_MARGIN, size.height),
+                    DockEdge::Right => {
+                        Rect::new(size.width - DROP_MARGIN, 0.0, size.width, size.height)
+                    }
+                    DockEdge::Bottom => {
+                        Rect::new(0.0, size.height - DROP_MARGIN, size.width, size.height)
+                    }
+                }
+            }
+            DropTarget::Float => self.center.layout_rect(),
+        }
+    }
+
+    fn is_shown(&self, panel: usize) -> bool {
+        self.areas.iter().any(|area| area.active == Some(panel))
+    }
+}
+
+impl<T: Data> Widget<T> for Dock<T> {
+    #[instrument(name = "Dock", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::WindowConnected => {
+                // restore the floating panels of a saved layout.
+                let (to_open, _) = self.floating_changes(data);
+                for panel in to_open {
+                    let origin = match self.float_origin {
+                        Some(origin) => origin,
+                        None => ctx.window().get_position() + Vec2::new(DROP_MARGIN, DROP_MARGIN),
+                    };
+                    let (config, widget) = self.floating_window(panel, ctx.widget_id(), origin);
+                    let window = ctx.new_sub_window(config, widget, data.clone(), env.clone());
+                    self.floating.push((panel, window));
+                }
+            }
+            Event::Command(cmd) => {
+                if let Some(window) = cmd.get(PANEL_WINDOW_CLOSED) {
+                    ctx.set_handled();
+                    if let Some(index) = self.floating.iter().position(|(_, w)| w == window) {
+                        let (panel, _) = self.floating.remove(index);
+                        let id = self.panels[panel].id.clone();
+                        trace!("the window of {:?} was closed", id);
+                        self.layout.update(data, &mut |layout| layout.redock(&id));
+                    }
+                    return;
+                }
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                if let Some(edge) = self.splitter_at(mouse.pos) {
+                    self.drag = Some(Drag::Splitter(edge));
+                } else if let Some(panel) = self.tab_at(mouse.pos) {
+                    self.drag = Some(Drag::Pressed {
+                        panel,
+                        start: mouse.pos,
+                    });
+                }
+                if self.drag.is_some() {
+                    ctx.set_active(true);
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            Event::MouseMove(mouse) if self.drag.is_some() => {
+                match self.drag.as_mut().unwrap() {
+                    Drag::Pressed { panel, start } => {
+                        if (mouse.pos - *start).hypot() > DRAG_THRESHOLD {
+                            let panel = *panel;
+                            self.drag = Some(Drag::Tab {
+                                panel,
+                                pos: mouse.pos,
+                            });
+                            ctx.request_paint();
+                        }
+                    }
+                    Drag::Tab { pos, .. } => {
+                        *pos = mouse.pos;
+                        ctx.request_paint();
+                    }
+                    Drag::Splitter(edge) => {
+                        let edge = *edge;
+                        let size = ctx.size();
+                        let extent = match edge {
+                            DockEdge::Left => mouse.pos.x,
+                            DockEdge::Right => size.width - mouse.pos.x,
+                            DockEdge::Bottom => size.height - mouse.pos.y,
+                        };
+                        self.layout
+                            .update(data, &mut |layout| layout.set_extent(edge, extent));
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseUp(mouse) if self.drag.is_some() && mouse.button == MouseButton::Left => {
+                ctx.set_active(false);
+                ctx.set_handled();
+                match self.drag.take().unwrap() {
+                    Drag::Pressed { panel, .. } => {
+                        let id = self.panels[panel].id.clone();
+                        self.layout.update(data, &mut |layout| layout.show(&id));
+                    }
+                    Drag::Tab { panel, pos } => {
+                        let id = self.panels[panel].id.clone();
+                        match self.drop_target(pos, ctx.size()) {
+                            DropTarget::Edge(edge) => {
+                                trace!("docking {:?} at {:?}", id, edge);
+                                self.layout
+                                    .update(data, &mut |layout| layout.dock(id.clone(), edge));
+                            }
+                            DropTarget::Float => {
+                                trace!("floating {:?}", id);
+                                let origin = ctx.to_window(pos).to_vec2();
+                                self.float_origin = Some(ctx.window().get_position() + origin);
+                                self.layout
+                                    .update(data, &mut |layout| layout.float(id.clone()));
+                            }
+                        }
+                        ctx.request_paint();
+                    }
+                    Drag::Splitter(_) => (),
+                }
+                return;
+            }
+            Event::MouseMove(mouse) => match self.splitter_at(mouse.pos) {
+                Some(DockEdge::Bottom) => ctx.set_cursor(&Cursor::ResizeUpDown),
+                Some(_) => ctx.set_cursor(&Cursor::ResizeLeftRight),
+                None => ctx.clear_cursor(),
+            },
+            _ => (),
+        }
+
+        self.center.event(ctx, event, data, env);
+        let is_pointer_event = matches!(
+            event,
+            Event::MouseDown(_) | Event::MouseUp(_) | Event::MouseMove(_) | Event::Wheel(_)
+        );
+        for index in 0..self.panels.len() {
+            if !is_pointer_event || self.is_shown(index) {
+                self.panels[index].child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    #[instrument(name = "Dock", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.center.lifecycle(ctx, event, data, env);
+        for panel in &mut self.panels {
+            panel.child.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Dock", level = "trace", skip(self, ctx, old_data, data, env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if !self.layout.get(old_data).same(&self.layout.get(data)) {
+            let (to_open, to_close) = self.floating_changes(data);
+            for window in to_close {
+                ctx.submit_command(CLOSE_WINDOW.to(window));
+            }
+            for panel in to_open {
+                let origin = match self.float_origin {
+                    Some(origin) => origin,
+                    None => ctx.window().get_position() + Vec2::new(DROP_MARGIN, DROP_MARGIN),
+                };
+                let (config, widget) = self.floating_window(panel, ctx.widget_id(), origin);
+                let window = ctx.new_sub_window(config, widget, data.clone(), env.clone());
+                self.floating.push((panel, window));
+            }
+            self.float_origin = None;
+            ctx.request_layout();
+        }
+        if ctx.env_changed() {
+            for panel in &mut self.panels {
+                if panel.title.needs_rebuild_after_update(ctx) {
+                    ctx.request_layout();
+                }
+            }
+        }
+        self.center.update(ctx, data, env);
+        for panel in &mut self.panels {
+            panel.child.update(ctx, data, env);
+        }
+    }
+
+    #[instrument(name = "Dock", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Dock");
+        let size = bc.max();
+        let layout = self.layout.get(data);
+
+        // the panels that are docked at each edge, as indices into `panels`.
+        let docked: Vec<Vec<usize>> = EDGES
+            .iter()
+            .map(|edge| {
+                layout
+                    .panels(*edge)
+                    .iter()
+                    .filter_map(|id| self.panel_index(id))
+                    .collect()
+            })
+            .collect();
+        let extent = |edge: DockEdge, max: f64| {
+            if docked[edge as usize].is_empty() {
+                0.0
+            } else {
+                layout
+                    .extent(edge)
+                    .min(max)
+                    .max(TAB_HEIGHT + SPLITTER_WIDTH)
+            }
+        };
+        let bottom = extent(DockEdge::Bottom, size.height - MIN_CENTER);
+        let left = extent(DockEdge::Left, size.width - MIN_CENTER);
+        let right = extent(DockEdge::Right, size.width - MIN_CENTER - left);
+        let top_height = size.height - bottom;
+
+        let rects = [
+            Rect::new(0.0, 0.0, left, top_height),
+            Rect::new(size.width - right, 0.0, size.width, top_height),
+            Rect::new(0.0, top_height, size.width, size.height),
+        ];
+        let center_rect = Rect::new(left, 0.0, size.width - right, top_height);
+        let center_size = center_rect.size();
+        self.center
+            .layout(ctx, &BoxConstraints::tight(center_size), data, env);
+        self.center.set_origin(ctx, data, env, center_rect.origin());
+
+        for (i, edge) in EDGES.iter().enumerate() {
+            let rect = rects[i];
+            let mut area = AreaGeometry {
+                rect,
+                ..AreaGeometry::default()
+            };
+            if !docked[i].is_empty() {
+                // the splitter is on the side facing the center.
+                let (splitter, content) = match edge {
+                    DockEdge::Left => (
+                        Rect::new(rect.x1 - SPLITTER_WIDTH, rect.y0, rect.x1, rect.y1),
+                        Rect::new(rect.x0, rect.y0, rect.x1 - SPLITTER_WIDTH, rect.y1),
+                    ),
+                    DockEdge::Right => (
+                        Rect::new(rect.x0, rect.y0, rect.x0 + SPLITTER_WIDTH, rect.y1),
+                        Rect::new(rect.x0 + SPLITTER_WIDTH, rect.y0, rect.x1, rect.y1),
+                    ),
+                    DockEdge::Bottom => (
+                        Rect::new(rect.x0, rect.y0, rect.x1, rect.y0 + SPLITTER_WIDTH),
+                        Rect::new(rect.x0, rect.y0 + SPLITTER_WIDTH, rect.x1, rect.y1),
+                    ),
+                };
+                area.splitter = splitter;
+
+                let mut x = content.x0;
+                for &panel in &docked[i] {
+                    let title = &mut self.panels[panel].title;
+                    title.rebuild_if_needed(ctx.text(), env);
+                    let width = title.size().width + TAB_PADDING * 2.0;
+                    let tab = Rect::new(x, content.y0, x + width, content.y0 + TAB_HEIGHT);
+                    area.tabs.push((panel, tab));
+                    x += width;
+                }
+
+                let active = layout.active(*edge).and_then(|id| self.panel_index(id));
+                let active = active.filter(|panel| docked[i].contains(panel));
+                if let Some(panel) = active.or_else(|| docked[i].first().copied()) {
+                    let child_rect =
+                        Rect::new(content.x0, content.y0 + TAB_HEIGHT, content.x1, content.y1);
+                    let child = &mut self.panels[panel].child;
+                    let bc = BoxConstraints::tight(child_rect.size());
+                    child.layout(ctx, &bc, data, env);
+                    child.set_origin(ctx, data, env, child_rect.origin());
+                    area.active = Some(panel);
+                }
+            }
+            self.areas[i] = area;
+        }
+
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Dock", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        self.center.paint(ctx, data, env);
+
+        for area in &self.areas {
+            if area.tabs.is_empty() {
+                continue;
+            }
+            ctx.fill(area.rect, &env.get(theme::BACKGROUND_LIGHT));
+            ctx.fill(area.splitter, &env.get(theme::BORDER_DARK));
+            for (panel, tab) in &area.tabs {
+                if area.active == Some(*panel) {
+                    ctx.fill(*tab, &env.get(theme::BACKGROUND_DARK));
+                }
+                let title = &self.panels[*panel].title;
+                let y = tab.y0 + (TAB_HEIGHT - title.size().height) / 2.0;
+                title.draw(ctx, (tab.x0 + TAB_PADDING, y));
+            }
+        }
+        for index in 0..self.panels.len() {
+            if self.is_shown(index) {
+                self.panels[index].child.paint(ctx, data, env);
+            }
+        }
+
+        if let Some(Drag::Tab { pos, .. }) = &self.drag {
+            let rect = self.drop_rect(self.drop_target(*pos, size), size);
+            let color = env.get(theme::PRIMARY_LIGHT);
+            ctx.fill(rect, &color.clone().with_alpha(0.3));
+            ctx.stroke(rect.inset(-1.0), &color, 2.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn layout_round_trip() {
+        let mut layout = DockLayout::new()
+            .with_panel(DockEdge::Left, "files")
+            .with_panel(DockEdge::Left, "outline")
+            .with_panel(DockEdge::Bottom, "terminal");
+        layout.float("search");
+        layout.show("files");
+        layout.set_extent(DockEdge::Bottom, 150.5);
+
+        let parsed: DockLayout = layout.to_string().parse().unwrap();
+        assert_eq!(parsed, layout);
+        assert_eq!(parsed.active(DockEdge::Left), Some("files"));
+        assert_eq!(parsed.active(DockEdge::Right), None);
+        assert!(parsed.is_floating("search"));
+    }
+
+    #[test]
+    fn dock_moves_panel() {
+        let mut layout = DockLayout::new()
+            .with_panel(DockEdge::Left, "files")
+            .with_panel(DockEdge::Left, "outline");
+        layout.dock("outline", DockEdge::Right);
+        assert_eq!(layout.panels(DockEdge::Left), ["files".to_string()]);
+        assert_eq!(layout.edge("outline"), Some(DockEdge::Right));
+        assert_eq!(layout.active(DockEdge::Left), Some("files"));
+
+        layout.float("files");
+        assert_eq!(layout.edge("files"), None);
+        assert_eq!(layout.active(DockEdge::Left), None);
+    }
+
+    #[test]
+    fn redock_restores_slot() {
+        let mut layout = DockLayout::new()
+            .with_panel(DockEdge::Left, "files")
+            .with_panel(DockEdge::Left, "outline")
+            .with_panel(DockEdge::Left, "history");
+        layout.float("outline");
+        assert_eq!(layout.active(DockEdge::Left), Some("files"));
+
+        // the slot survives saving the layout.
+        let mut layout: DockLayout = layout.to_string().parse().unwrap();
+        layout.redock("outline");
+        assert!(!layout.is_floating("outline"));
+        let panels = ["files", "outline", "history"]
+            .iter()
+            .map(|s| s.to_string());
+        assert_eq!(
+            layout.panels(DockEdge::Left),
+            panels.collect::<Vec<_>>().as_slice()
+        );
+        assert_eq!(layout.active(DockEdge::Left), Some("outline"));
+
+        // a panel that was never docked is hidden.
+        layout.float("search");
+        layout.redock("search");
+        assert!(!layout.is_floating("search"));
+        assert_eq!(layout.edge("search"), None);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!("top 200 0 files".parse::<DockLayout>().is_err());
+        assert!("left wide 0 files".parse::<DockLayout>().is_err());
+        assert!("floating files@top:0".parse::<DockLayout>().is_err());
+    }
+}
@@ -19,6 +19,11 @@ use crate::{Data, Point, WidgetPod};
 use tracing::instrument;
 
 /// A widget that switches between two possible child views.
+///
+/// Both children are kept for as long as the `Either` exists, so switching
+/// back to a child finds it the way it was left, like with its scroll
+/// position or text selection intact. The hidden child doesn't get updates,
+/// and only sees the events that are also sent to hidden widgets.
 pub struct Either<T> {
     closure: Box<dyn Fn(&T, &Env) -> bool>,
     true_branch: WidgetPod<T, Box<dyn Widget<T>>>,
@@ -0,0 +1,176 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that contains panics in its child.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::widget::prelude::*;
+use crate::{theme, Data, Point, TextLayout, WidgetPod};
+use tracing::{error, instrument};
+
+const PADDING: f64 = 4.0;
+const BORDER_WIDTH: f64 = 1.0;
+
+/// A widget that keeps a panic in its child from taking down the window.
+///
+/// If the child (or anything below it) panics during `event`, `lifecycle`,
+/// `update`, `layout` or `paint`, the panic is caught and the child is
+/// replaced by a placeholder showing the panic message. The child is never
+/// called again; the rest of the window keeps working.
+///
+/// This is a last line of defence, not a way to handle errors: the child may
+/// have been interrupted halfway through changing the data, and a panic
+/// during `paint` can leave the render context with an extra clip or
+/// transform for the rest of that frame. Panics can only be caught when the
+/// application is built with `panic = "unwind"`, which is the default except
+/// on wasm.
+pub struct ErrorBoundary<T, W> {
+    child: WidgetPod<T, W>,
+    error: Option<TextLayout<String>>,
+}
+
+impl<T, W: Widget<T>> ErrorBoundary<T, W> {
+    /// Create a new `ErrorBoundary` around `child`.
+    pub fn new(child: W) -> Self {
+        ErrorBoundary {
+            child: WidgetPod::new(child),
+            error: None,
+        }
+    }
+
+    /// The message of the panic that was caught, if any.
+    pub fn error(&self) -> Option<&str> {
+        self.error
+            .as_ref()
+            .and_then(|layout| layout.text())
+            .map(String::as_str)
+    }
+
+    /// Run `f` on the child, unless it has panicked before.
+    ///
+    /// Returns `None` if the child panicked, now or before.
+    fn guard<R>(&mut self, f: impl FnOnce(&mut WidgetPod<T, W>) -> R) -> Option<R> {
+        if self.error.is_some() {
+            return None;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| f(&mut self.child))) {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                let message = panic_message(&*payload);
+                error!("{} panicked: {}", self.child.widget().type_name(), message);
+                self.child.skip_removed();
+                let mut layout = TextLayout::from_text(message);
+                layout.set_text_color(theme::ERROR_COLOR);
+                self.error = Some(layout);
+                None
+            }
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for ErrorBoundary<T, W> {
+    #[instrument(
+        name = "ErrorBoundary",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        let had_error = self.error.is_some();
+        let result = self.guard(|child| child.event(ctx, event, data, env));
+        if result.is_none() && !had_error {
+            ctx.children_changed();
+        }
+    }
+
+    #[instrument(
+        name = "ErrorBoundary",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        let had_error = self.error.is_some();
+        let result = self.guard(|child| child.lifecycle(ctx, event, data, env));
+        if result.is_none() && !had_error {
+            ctx.children_changed();
+        }
+    }
+
+    #[instrument(
+        name = "ErrorBoundary",
+        level = "trace",
+        skip(self, ctx, _old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        let had_error = self.error.is_some();
+        let result = self.guard(|child| child.update(ctx, data, env));
+        if result.is_none() && !had_error {
+            ctx.children_changed();
+        }
+    }
+
+    #[instrument(
+        name = "ErrorBoundary",
+        level = "trace",
+        skip(self, ctx, bc, data, env)
+    )]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let size = self.guard(|child| {
+            let size = child.layout(ctx, bc, data, env);
+            child.set_origin(ctx, data, env, Point::ORIGIN);
+            size
+        });
+        if let Some(size) = size {
+            ctx.set_baseline_offset(self.child.baseline_offset());
+            return size;
+        }
+
+        let layout = self.error.as_mut().unwrap();
+        let inset = PADDING + BORDER_WIDTH;
+        layout.set_wrap_width(bc.max().width - inset * 2.0);
+        layout.rebuild_if_needed(ctx.text(), env);
+        bc.constrain(layout.size() + Size::new(inset * 2.0, inset * 2.0))
+    }
+
+    #[instrument(name = "ErrorBoundary", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        if self.guard(|child| child.paint(ctx, data, env)).is_some() {
+            return;
+        }
+
+        let rect = ctx.size().to_rect().inset(-BORDER_WIDTH / 2.0);
+        ctx.fill(rect, &env.get(theme::BACKGROUND_DARK));
+        ctx.stroke(rect, &env.get(theme::ERROR_COLOR), BORDER_WIDTH);
+        if let Some(layout) = &self.error {
+            let inset = PADDING + BORDER_WIDTH;
+            let clip = ctx.size().to_rect();
+            ctx.with_save(|ctx| {
+                ctx.clip(clip);
+                layout.draw(ctx, (inset, inset));
+            });
+        }
+    }
+}
+
+/// Get the message out of a panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "the widget panicked".into()
+    }
+}
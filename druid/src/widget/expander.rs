@@ -0,0 +1,419 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sections that can be collapsed behind their header.
+
+use std::f64::consts::FRAC_PI_2;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::kurbo::{BezPath, Line};
+use crate::widget::prelude::*;
+use crate::{theme, Affine, ArcStr, Data, Lens, LensExt, MouseButton, Point, Rect, TextLayout};
+use crate::{Vec2, WidgetPod};
+use tracing::{instrument, trace};
+
+/// How long it takes to expand or collapse, in seconds.
+const EXPAND_TIME: f64 = 0.15;
+const HEADER_PADDING: f64 = 6.0;
+const ARROW_SIZE: f64 = 8.0;
+
+/// A section with a header that can be clicked to show or hide its content.
+///
+/// Whether the section is expanded is kept in the data, so that it can be
+/// saved with the rest of the application state. The content slides open
+/// and closed when that changes.
+///
+/// To have a group of sections of which only one is open at a time, use an
+/// [`Accordion`].
+pub struct Expander<T> {
+    title: TextLayout<ArcStr>,
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    expanded: Box<dyn ExpandedStore<T>>,
+    /// How far the content is shown, from `0.0` when collapsed to `1.0` when
+    /// expanded.
+    openness: f64,
+    header_height: f64,
+    header_hot: bool,
+}
+
+/// A group of [`Expander`]s, of which at most one is open at a time.
+///
+/// The index of the open section is kept in the data; opening a section
+/// closes the one that was open before.
+pub struct Accordion<T> {
+    open: Rc<dyn OpenStore<T>>,
+    sections: Vec<WidgetPod<T, Expander<T>>>,
+}
+
+/// Where the state of an expander is kept in the data.
+trait ExpandedStore<T> {
+    fn get(&self, data: &T) -> bool;
+    fn put(&self, data: &mut T, expanded: bool);
+}
+
+/// The state of an expander kept at a lens.
+struct LensStore<L>(L);
+
+impl<T, L: Lens<T, bool>> ExpandedStore<T> for LensStore<L> {
+    fn get(&self, data: &T) -> bool {
+        self.0.get(data)
+    }
+
+    fn put(&self, data: &mut T, expanded: bool) {
+        self.0.put(data, expanded)
+    }
+}
+
+/// Where the open section of an accordion is kept in the data.
+trait OpenStore<T> {
+    fn get(&self, data: &T) -> Option<usize>;
+    fn put(&self, data: &mut T, open: Option<usize>);
+}
+
+impl<T, L: Lens<T, Option<usize>>> OpenStore<T> for L {
+    fn get(&self, data: &T) -> Option<usize> {
+        LensExt::get(self, data)
+    }
+
+    fn put(&self, data: &mut T, open: Option<usize>) {
+        LensExt::put(self, data, open)
+    }
+}
+
+/// The state of one section of an accordion, which is expanded when it is
+/// the open section.
+struct Section<T> {
+    open: Rc<dyn OpenStore<T>>,
+    index: usize,
+}
+
+impl<T> ExpandedStore<T> for Section<T> {
+    fn get(&self, data: &T) -> bool {
+        self.open.get(data) == Some(self.index)
+    }
+
+    fn put(&self, data: &mut T, expanded: bool) {
+        if expanded {
+            self.open.put(data, Some(self.index));
+        } else if self.get(data) {
+            self.open.put(data, None);
+        }
+    }
+}
+
+impl<T: Data> Expander<T> {
+    /// Create a new `Expander` with the given title and content.
+    ///
+    /// Whether it is expanded is kept in the data, at `expanded`.
+    pub fn new(
+        title: impl Into<ArcStr>,
+        child: impl Widget<T> + 'static,
+        expanded: impl Lens<T, bool> + 'static,
+    ) -> Self {
+        Self::with_store(title.into(), Box::new(child), Box::new(LensStore(expanded)))
+    }
+
+    fn with_store(
+        title: ArcStr,
+        child: Box<dyn Widget<T>>,
+        expanded: Box<dyn ExpandedStore<T>>,
+    ) -> Self {
+        let mut title = TextLayout::from_text(title);
+        title.set_text_color(theme::TEXT_COLOR);
+        title.set_font(theme::UI_FONT_BOLD);
+        Expander {
+            title,
+            child: WidgetPod::new(child),
+            expanded,
+            openness: 0.0,
+            header_height: 0.0,
+            header_hot: false,
+        }
+    }
+
+    fn header_rect(&self, size: Size) -> Rect {
+        Rect::new(0.0, 0.0, size.width, self.header_height)
+    }
+
+    fn is_collapsed(&self) -> bool {
+        self.openness <= 0.0
+    }
+}
+
+impl<T: Data> Widget<T> for Expander<T> {
+    #[instrument(name = "Expander", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseDown(mouse)
+                if mouse.button == MouseButton::Left
+                    && self.header_rect(ctx.size()).contains(mouse.pos) =>
+            {
+                ctx.set_active(true);
+                ctx.request_paint();
+                return;
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.request_paint();
+                if self.header_rect(ctx.size()).contains(mouse.pos) {
+                    let expanded = self.expanded.get(data);
+                    trace!("Expander toggled: expanded={}", !expanded);
+                    self.expanded.put(data, !expanded);
+                }
+                return;
+            }
+            Event::MouseMove(mouse) => {
+                let header_hot = self.header_rect(ctx.size()).contains(mouse.pos);
+                if header_hot != self.header_hot {
+                    self.header_hot = header_hot;
+                    ctx.request_paint();
+                }
+            }
+            Event::AnimFrame(interval) => {
+                let step = Duration::from_nanos(*interval).as_secs_f64() / EXPAND_TIME;
+                let target = if self.expanded.get(data) { 1.0 } else { 0.0 };
+                self.openness = if target > self.openness {
+                    (self.openness + step).min(target)
+                } else {
+                    (self.openness - step).max(target)
+                };
+                if (self.openness - target).abs() > f64::EPSILON {
+                    ctx.request_anim_frame();
+                }
+                ctx.request_layout();
+            }
+            _ => (),
+        }
+
+        if !self.is_collapsed() || event.should_propagate_to_hidden() {
+            self.child.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Expander", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                self.openness = if self.expanded.get(data) { 1.0 } else { 0.0 };
+            }
+            LifeCycle::HotChanged(false) => {
+                self.header_hot = false;
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+        self.child.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "Expander",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if self.expanded.get(old_data) != self.expanded.get(data) {
+            ctx.request_anim_frame();
+        }
+        if ctx.env_changed() && self.title.needs_rebuild_after_update(ctx) {
+            ctx.request_layout();
+        }
+        self.child.update(ctx, data, env);
+    }
+
+    #[instrument(name = "Expander", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Expander");
+        self.title.rebuild_if_needed(ctx.text(), env);
+        let title_size = self.title.size();
+        self.header_height = title_size.height.max(ARROW_SIZE) + HEADER_PADDING * 2.0;
+        let header_width = HEADER_PADDING * 3.0 + ARROW_SIZE + title_size.width;
+
+        let child_bc = BoxConstraints::new(
+            Size::new(bc.min().width, 0.0),
+            Size::new(bc.max().width, f64::INFINITY),
+        );
+        let child_size = self.child.layout(ctx, &child_bc, data, env);
+        self.child
+            .set_origin(ctx, data, env, Point::new(0.0, self.header_height));
+
+        let size = bc.constrain(Size::new(
+            header_width.max(child_size.width),
+            self.header_height + child_size.height * self.openness,
+        ));
+        trace!("Computed layout: size={}, openness={}", size, self.openness);
+        size
+    }
+
+    #[instrument(name = "Expander", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        let header = self.header_rect(size);
+        if ctx.is_active() {
+            ctx.fill(header, &env.get(theme::BUTTON_DARK));
+        } else if self.header_hot {
+            ctx.fill(header, &env.get(theme::BACKGROUND_LIGHT));
+        }
+
+        // a triangle that points right when collapsed, and turns to point
+        // down as the content opens.
+        let half = ARROW_SIZE / 2.0;
+        let mut arrow = BezPath::new();
+        arrow.move_to((-half * 0.7, -half));
+        arrow.line_to((half, 0.0));
+        arrow.line_to((-half * 0.7, half));
+        arrow.close_path();
+        let center = Vec2::new(HEADER_PADDING + half, self.header_height / 2.0);
+        let arrow = Affine::translate(center) * Affine::rotate(self.openness * FRAC_PI_2) * arrow;
+        ctx.fill(arrow, &env.get(theme::TEXT_COLOR));
+
+        let title_y = (self.header_height - self.title.size().height) / 2.0;
+        self.title
+            .draw(ctx, (HEADER_PADDING * 2.0 + ARROW_SIZE, title_y));
+
+        if !self.is_collapsed() {
+            let content = Rect::new(0.0, self.header_height, size.width, size.height);
+            ctx.with_save(|ctx| {
+                ctx.clip(content);
+                self.child.paint(ctx, data, env);
+            });
+        }
+    }
+}
+
+impl<T: Data> Accordion<T> {
+    /// Create a new, empty `Accordion`.
+    ///
+    /// The index of the open section is kept in the data, at `open`.
+    pub fn new(open: impl Lens<T, Option<usize>> + 'static) -> Self {
+        Accordion {
+            open: Rc::new(open),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Builder-style method to add a section with the given title and
+    /// content.
+    pub fn with_section(
+        mut self,
+        title: impl Into<ArcStr>,
+        child: impl Widget<T> + 'static,
+    ) -> Self {
+        let section = Section {
+            open: self.open.clone(),
+            index: self.sections.len(),
+        };
+        let expander = Expander::with_store(title.into(), Box::new(child), Box::new(section));
+        self.sections.push(WidgetPod::new(expander));
+        self
+    }
+}
+
+impl<T: Data> Widget<T> for Accordion<T> {
+    #[instrument(name = "Accordion", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        for section in &mut self.sections {
+            section.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Accordion", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        for section in &mut self.sections {
+            section.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(
+        name = "Accordion",
+        level = "trace",
+        skip(self, ctx, _old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        for section in &mut self.sections {
+            section.update(ctx, data, env);
+        }
+    }
+
+    #[instrument(name = "Accordion", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Accordion");
+        // the sections are as wide as the accordion, so the headers line up.
+        let min_width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            bc.min().width
+        };
+        let section_bc = BoxConstraints::new(
+            Size::new(min_width, 0.0),
+            Size::new(bc.max().width, f64::INFINITY),
+        );
+        let mut width: f64 = 0.0;
+        let mut y = 0.0;
+        for section in &mut self.sections {
+            let section_size = section.layout(ctx, &section_bc, data, env);
+            section.set_origin(ctx, data, env, Point::new(0.0, y));
+            width = width.max(section_size.width);
+            y += section_size.height;
+        }
+
+        let size = bc.constrain(Size::new(width, y));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Accordion", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let border = env.get(theme::BORDER_DARK);
+        for (i, section) in self.sections.iter_mut().enumerate() {
+            if i > 0 {
+                let y = section.layout_rect().y0;
+                let (line, width) = ctx.snap_line(Line::new((0.0, y), (ctx.size().width, y)), 1.0);
+                ctx.stroke(line, &border, width);
+            }
+            section.paint(ctx, data, env);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn accordion_sections_are_exclusive() {
+        let open: Rc<dyn OpenStore<Option<usize>>> = Rc::new(crate::lens::Identity);
+        let first = Section {
+            open: open.clone(),
+            index: 0,
+        };
+        let second = Section { open, index: 1 };
+
+        let mut data = None;
+        first.put(&mut data, true);
+        assert!(first.get(&data));
+        assert!(!second.get(&data));
+
+        second.put(&mut data, true);
+        assert!(!first.get(&data));
+        assert!(second.get(&data));
+
+        // collapsing a closed section doesn't close the open one
+        first.put(&mut data, false);
+        assert_eq!(data, Some(1));
+        second.put(&mut data, false);
+        assert_eq!(data, None);
+    }
+}
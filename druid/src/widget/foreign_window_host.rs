@@ -0,0 +1,146 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that hosts a native window created by someone else.
+
+use crate::raw_window_handle::RawWindowHandle;
+use crate::shell::ForeignWindow;
+use crate::widget::prelude::*;
+use crate::widget::{NativeSurface, SurfaceContent, SurfacePlacement};
+use crate::{Data, WindowHandle};
+use tracing::{instrument, warn};
+
+/// Embeds the child window the first time the surface is placed, and keeps it
+/// over the surface after that.
+struct Embedder {
+    child: RawWindowHandle,
+    window: Option<ForeignWindow>,
+    failed: bool,
+}
+
+impl<T> SurfaceContent<T> for Embedder {
+    #[allow(unsafe_code)]
+    fn placed(&mut self, window: &WindowHandle, placement: SurfacePlacement) {
+        if self.window.is_none() && !self.failed {
+            // the caller of `ForeignWindowHost::new` promised that the handle
+            // is valid.
+            match unsafe { window.embed_foreign_window(self.child) } {
+                Ok(foreign) => self.window = Some(foreign),
+                Err(e) => {
+                    warn!("failed to embed foreign window: {}", e);
+                    self.failed = true;
+                }
+            }
+        }
+        if let Some(foreign) = &self.window {
+            foreign.set_frame(placement.rect);
+        }
+    }
+}
+
+/// A widget that embeds a native window that was created outside of druid,
+/// such as the editor of an audio plugin or a legacy control.
+///
+/// The window is made a child of druid's window when the widget is first laid
+/// out, and is then kept over the area of the widget as it moves and changes
+/// size. When the widget gets focus, so does the embedded window. The window
+/// is detached again when the widget is removed from the tree.
+///
+/// `ForeignWindowHost` takes up all the space it is given. It is supported on
+/// Windows, X11 and macOS. The GTK backend can't embed windows, because gtk-rs
+/// only offers `GtkSocket` when built for a single GDK backend, and the web
+/// has no native windows. There a warning is logged, nothing is shown, and
+/// [`is_embedded`] stays `false`.
+///
+/// [`is_embedded`]: ForeignWindowHost::is_embedded
+pub struct ForeignWindowHost {
+    surface: NativeSurface<Embedder>,
+}
+
+impl ForeignWindowHost {
+    /// Create a `ForeignWindowHost` for the window `child`.
+    ///
+    /// # Safety
+    ///
+    /// `child` must be a valid handle to a window of the platform that the
+    /// application runs on, and the window must stay alive for as long as this
+    /// widget.
+    #[allow(unsafe_code)]
+    pub unsafe fn new(child: RawWindowHandle) -> Self {
+        ForeignWindowHost {
+            surface: NativeSurface::new(Embedder {
+                child,
+                window: None,
+                failed: false,
+            }),
+        }
+    }
+
+    /// Whether the window has been embedded.
+    pub fn is_embedded(&self) -> bool {
+        self.surface.content().window.is_some()
+    }
+}
+
+impl<T: Data> Widget<T> for ForeignWindowHost {
+    #[instrument(
+        name = "ForeignWindowHost",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.surface.event(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "ForeignWindowHost",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::FocusChanged(true) = event {
+            if let Some(foreign) = &self.surface.content().window {
+                foreign.focus();
+            }
+        }
+        self.surface.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "ForeignWindowHost",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.surface.update(ctx, old_data, data, env);
+    }
+
+    #[instrument(
+        name = "ForeignWindowHost",
+        level = "trace",
+        skip(self, ctx, bc, data, env)
+    )]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        self.surface.layout(ctx, bc, data, env)
+    }
+
+    #[instrument(
+        name = "ForeignWindowHost",
+        level = "trace",
+        skip(self, ctx, data, env)
+    )]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.surface.paint(ctx, data, env);
+    }
+}
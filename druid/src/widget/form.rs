@@ -0,0 +1,413 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A form of labelled inputs, that keeps track of whether they are valid.
+
+use std::fmt::Debug;
+
+use crate::text::{Formatter, ValidationError};
+use crate::widget::prelude::*;
+use crate::widget::{TextBox, TextBoxEvent, ValidationDelegate};
+use crate::{theme, ArcStr, Lens, LensExt, Point, Selector, TextLayout, WidgetExt, WidgetPod};
+use tracing::{instrument, trace};
+
+/// Submitted as a notification by the text boxes of value fields when their
+/// validation state changes.
+const FIELD_VALIDATED: Selector<Option<ValidationError>> =
+    Selector::new("druid.builtin.form-field-validated");
+
+/// A form, made of inputs with a label in front of them.
+///
+/// The labels are lined up in one column, and the inputs in a second column
+/// that takes up the rest of the width.
+///
+/// Fields added with [`with_value_field`] are edited in a text box using a
+/// [`Formatter`]. While their text is not valid, the error is shown below
+/// them, and the form is not valid. Whether the whole form is valid can be
+/// written to the data with [`with_valid`], for instance to disable the
+/// submit button:
+///
+/// ```
+/// # use druid::widget::{Button, Flex, Form};
+/// # use druid::text::ParseFormatter;
+/// # use druid::{Data, Lens, Widget, WidgetExt};
+/// #[derive(Clone, Data, Lens)]
+/// struct Order {
+///     name: String,
+///     quantity: u32,
+///     valid: bool,
+/// }
+///
+/// fn order_form() -> impl Widget<Order> {
+///     let form = Form::new()
+///         .with_value_field("Name", ParseFormatter::new(), Order::name)
+///         .with_value_field("Quantity", ParseFormatter::new(), Order::quantity)
+///         .with_valid(Order::valid);
+///     let submit = Button::new("Order").disabled_if(|data: &Order, _| !data.valid);
+///     Flex::column().with_child(form).with_child(submit)
+/// }
+/// ```
+///
+/// [`with_value_field`]: Form::with_value_field
+/// [`with_valid`]: Form::with_valid
+/// [`Formatter`]: crate::text::Formatter
+pub struct Form<T> {
+    fields: Vec<Field<T>>,
+    valid_store: Option<Box<dyn ValidStore<T>>>,
+}
+
+struct Field<T> {
+    label: TextLayout<ArcStr>,
+    /// Where the label is drawn, from the last layout.
+    label_origin: Point,
+    input: WidgetPod<T, Box<dyn Widget<T>>>,
+    /// The last validation error reported by the input, if any.
+    error: Option<ValidationError>,
+    error_layout: TextLayout<String>,
+}
+
+/// Where the validity of the form is kept in the data.
+trait ValidStore<T> {
+    fn get(&self, data: &T) -> bool;
+    fn put(&self, data: &mut T, valid: bool);
+}
+
+impl<T, L: Lens<T, bool>> ValidStore<T> for L {
+    fn get(&self, data: &T) -> bool {
+        LensExt::get(self, data)
+    }
+
+    fn put(&self, data: &mut T, valid: bool) {
+        LensExt::put(self, data, valid)
+    }
+}
+
+/// Reports the validation state of a value field's text box to the form.
+struct FieldDelegate;
+
+impl ValidationDelegate for FieldDelegate {
+    fn event(&mut self, ctx: &mut EventCtx, event: TextBoxEvent, _current_text: &str) {
+        let error = match event {
+            TextBoxEvent::Began => return,
+            TextBoxEvent::PartiallyInvalid(err) | TextBoxEvent::Invalid(err) => Some(err),
+            TextBoxEvent::Changed | TextBoxEvent::Complete | TextBoxEvent::Cancel => None,
+        };
+        ctx.submit_notification(FIELD_VALIDATED.with(error));
+    }
+}
+
+impl<T: Data> Form<T> {
+    /// Create a new, empty `Form`.
+    pub fn new() -> Self {
+        Form {
+            fields: Vec::new(),
+            valid_store: None,
+        }
+    }
+
+    /// Builder-style method to add a field with the given label and input
+    /// widget.
+    ///
+    /// The input doesn't take part in the validation of the form.
+    pub fn with_field(mut self, label: impl Into<ArcStr>, input: impl Widget<T> + 'static) -> Self {
+        let mut label = TextLayout::from_text(label);
+        label.set_text_color(theme::TEXT_COLOR);
+        let mut error_layout = TextLayout::new();
+        error_layout.set_text_color(theme::ERROR_COLOR);
+        self.fields.push(Field {
+            label,
+            label_origin: Point::ZERO,
+            input: WidgetPod::new(Box::new(input)),
+            error: None,
+            error_layout,
+        });
+        self
+    }
+
+    /// Builder-style method to add a field that edits the value at `lens`
+    /// in a text box, using the [`Formatter`] to show and validate it.
+    ///
+    /// The form is only valid while the text of all such fields is valid.
+    ///
+    /// [`Formatter`]: crate::text::Formatter
+    pub fn with_value_field<U: Data + Debug>(
+        self,
+        label: impl Into<ArcStr>,
+        formatter: impl Formatter<U> + 'static,
+        lens: impl Lens<T, U> + 'static,
+    ) -> Self {
+        let input = TextBox::new()
+            .with_formatter(formatter)
+            .validate_while_editing(false)
+            .update_data_while_editing(true)
+            .delegate(FieldDelegate)
+            .lens(lens);
+        self.with_field(label, input)
+    }
+
+    /// Builder-style method to keep whether the form is valid in the data,
+    /// at `lens`.
+    ///
+    /// The form is valid when none of its value fields has an error.
+    pub fn with_valid(mut self, lens: impl Lens<T, bool> + 'static) -> Self {
+        self.valid_store = Some(Box::new(lens));
+        self
+    }
+
+    /// Returns `true` if none of the fields has a validation error.
+    pub fn is_valid(&self) -> bool {
+        self.fields.iter().all(|field| field.error.is_none())
+    }
+
+    /// Returns the validation errors of the fields, in the order they were
+    /// added.
+    pub fn errors(&self) -> impl Iterator<Item = Option<&ValidationError>> {
+        self.fields.iter().map(|field| field.error.as_ref())
+    }
+}
+
+impl<T: Data> Default for Form<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data> Widget<T> for Form<T> {
+    #[instrument(name = "Form", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::Notification(note) = event {
+            if let Some(error) = note.get(FIELD_VALIDATED) {
+                ctx.set_handled();
+                let source = note.source();
+                if let Some(field) = self.fields.iter_mut().find(|f| f.input.id() == source) {
+                    trace!("field {:?} validated: {:?}", source, error);
+                    field.error_layout.set_text(
+                        error
+                            .as_ref()
+                            .map(|err| err.to_string())
+                            .unwrap_or_default(),
+                    );
+                    field.error = error.clone();
+                    ctx.request_layout();
+                }
+            }
+        } else {
+            for field in &mut self.fields {
+                field.input.event(ctx, event, data, env);
+            }
+        }
+
+        if let Some(store) = &self.valid_store {
+            let valid = self.is_valid();
+            if store.get(data) != valid {
+                store.put(data, valid);
+            }
+        }
+    }
+
+    #[instrument(name = "Form", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        for field in &mut self.fields {
+            field.input.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Form", level = "trace", skip(self, ctx, _old_data, data, env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        for field in &mut self.fields {
+            field.input.update(ctx, data, env);
+            if ctx.env_changed()
+                && (field.label.needs_rebuild_after_update(ctx)
+                    || field.error_layout.needs_rebuild_after_update(ctx))
+            {
+                ctx.request_layout();
+            }
+        }
+    }
+
+    #[instrument(name = "Form", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Form");
+        let spacing = env.get(theme::WIDGET_PADDING_HORIZONTAL);
+        let row_spacing = env.get(theme::WIDGET_PADDING_VERTICAL);
+
+        for field in &mut self.fields {
+            field.label.rebuild_if_needed(ctx.text(), env);
+            field.error_layout.rebuild_if_needed(ctx.text(), env);
+        }
+        let label_width = self
+            .fields
+            .iter()
+            .map(|field| field.label.size().width)
+            .fold(0.0, f64::max);
+
+        // the inputs fill the rest of the width, if there is a limit to it.
+        let input_x = label_width + spacing;
+        let input_bc = if bc.is_width_bounded() {
+            let width = (bc.max().width - input_x).max(0.0);
+            BoxConstraints::new(Size::new(width, 0.0), Size::new(width, f64::INFINITY))
+        } else {
+            BoxConstraints::UNBOUNDED
+        };
+
+        let mut y = 0.0;
+        let mut width: f64 = 0.0;
+        for (i, field) in self.fields.iter_mut().enumerate() {
+            if i > 0 {
+                y += row_spacing;
+            }
+            let input_size = field.input.layout(ctx, &input_bc, data, env);
+            field
+                .input
+                .set_origin(ctx, data, env, Point::new(input_x, y));
+            let label_size = field.label.size();
+            // right-align the labels, and center them on the input.
+            field.label_origin = Point::new(
+                label_width - label_size.width,
+                y + ((input_size.height - label_size.height) / 2.0).max(0.0),
+            );
+            width = width.max(input_x + input_size.width);
+            y += input_size.height.max(label_size.height);
+            if field.error.is_some() {
+                y += field.error_layout.size().height;
+            }
+        }
+
+        let size = bc.constrain(Size::new(width, y));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Form", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        for field in &mut self.fields {
+            field.label.draw(ctx, field.label_origin);
+            field.input.paint(ctx, data, env);
+            if field.error.is_some() {
+                let input_rect = field.input.layout_rect();
+                field.error_layout.draw(ctx, (input_rect.x0, input_rect.y1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use crate::text::ParseFormatter;
+    use crate::{Data, Lens, Rect};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    #[derive(Clone, Data, Lens)]
+    struct Order {
+        quantity: u32,
+        note: String,
+        valid: bool,
+    }
+
+    /// Shares where the inputs are, and their errors, with the test.
+    struct Probe {
+        form: Form<Order>,
+        inputs: Rc<RefCell<Vec<Rect>>>,
+        errors: Rc<RefCell<Vec<bool>>>,
+    }
+
+    impl Widget<Order> for Probe {
+        fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Order, env: &Env) {
+            self.form.event(ctx, event, data, env);
+            *self.errors.borrow_mut() = self.form.errors().map(|e| e.is_some()).collect();
+        }
+
+        fn lifecycle(
+            &mut self,
+            ctx: &mut LifeCycleCtx,
+            event: &LifeCycle,
+            data: &Order,
+            env: &Env,
+        ) {
+            self.form.lifecycle(ctx, event, data, env);
+        }
+
+        fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Order, data: &Order, env: &Env) {
+            self.form.update(ctx, old_data, data, env);
+        }
+
+        fn layout(
+            &mut self,
+            ctx: &mut LayoutCtx,
+            bc: &BoxConstraints,
+            data: &Order,
+            env: &Env,
+        ) -> Size {
+            let size = self.form.layout(ctx, bc, data, env);
+            *self.inputs.borrow_mut() = self
+                .form
+                .fields
+                .iter()
+                .map(|field| field.input.layout_rect())
+                .collect();
+            size
+        }
+
+        fn paint(&mut self, ctx: &mut PaintCtx, data: &Order, env: &Env) {
+            self.form.paint(ctx, data, env);
+        }
+    }
+
+    #[test]
+    fn invalid_field_makes_form_invalid() {
+        let form = Form::new()
+            .with_value_field("Quantity", ParseFormatter::new(), Order::quantity)
+            .with_value_field("Note", ParseFormatter::new(), Order::note)
+            .with_valid(Order::valid);
+        let inputs = Rc::new(RefCell::new(Vec::new()));
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let probe = Probe {
+            form,
+            inputs: inputs.clone(),
+            errors: errors.clone(),
+        };
+        let data = Order {
+            quantity: 1,
+            note: String::new(),
+            valid: true,
+        };
+
+        Harness::create_simple(data, probe, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            let input = |i: usize| inputs.borrow()[i].center();
+
+            harness.mouse_click(input(0));
+            harness.ime_insert_text("x");
+            assert!(!harness.data().valid);
+            assert_eq!(*errors.borrow(), vec![true, false]);
+
+            // leaving the invalid field throws away the edit.
+            harness.mouse_click(input(1));
+            assert!(harness.data().valid);
+            assert_eq!(*errors.borrow(), vec![false, false]);
+            assert_eq!(harness.data().quantity, 1);
+
+            // while a valid edit is kept.
+            harness.ime_insert_text("gift");
+            harness.mouse_click(input(0));
+            assert!(harness.data().valid);
+            assert_eq!(harness.data().note, "gift");
+        });
+    }
+}
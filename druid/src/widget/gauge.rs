@@ -0,0 +1,414 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A gauge widget, for showing a value on a dial.
+
+use tracing::{instrument, trace};
+
+use crate::kurbo::{Arc, Circle, Line};
+use crate::piet::{LineCap, StrokeStyle};
+use crate::widget::prelude::*;
+use crate::{theme, Color, KeyOrValue, Point, TextLayout, Vec2};
+
+const MAJOR_TICK_LENGTH: f64 = 6.0;
+const MINOR_TICK_LENGTH: f64 = 3.0;
+/// The space between the track, the ticks and the labels.
+const SPACING: f64 = 2.0;
+const THRESHOLD_BAND_WIDTH: f64 = 3.0;
+
+/// A dial that shows a numeric value on an arc, like a speedometer.
+///
+/// This impls `Widget<f64>`. The value is shown by filling the arc from its
+/// start, and optionally with a needle. By default the range is `0.0..1.0` and
+/// the arc is open at the bottom.
+///
+/// Thresholds split the range into bands, which are marked along the inner edge
+/// of the arc; the value is drawn in the color of the band it falls into.
+///
+/// # Examples
+///
+/// ```
+/// use druid::text::{Formatter, NumberFormatter};
+/// use druid::widget::Gauge;
+/// use druid::Color;
+///
+/// let format = NumberFormatter::new().with_fraction_digits(0, 0);
+/// let cpu_temperature = Gauge::new()
+///     .with_range(20.0, 100.0)
+///     .with_ticks(8, 2)
+///     .with_tick_labels(move |value| format.format(&value))
+///     .with_threshold(70.0, Color::rgb8(0xe0, 0xa0, 0x20))
+///     .with_threshold(85.0, Color::rgb8(0xf0, 0x50, 0x50));
+/// ```
+pub struct Gauge {
+    min: f64,
+    max: f64,
+    /// In radians, clockwise from the positive x axis.
+    start_angle: f64,
+    sweep_angle: f64,
+    thickness: KeyOrValue<f64>,
+    color: KeyOrValue<Color>,
+    track_color: KeyOrValue<Color>,
+    tick_color: KeyOrValue<Color>,
+    /// Sorted by value.
+    thresholds: Vec<(f64, KeyOrValue<Color>)>,
+    major_ticks: usize,
+    minor_ticks: usize,
+    tick_labels: Option<Box<dyn Fn(f64) -> String>>,
+    labels: Vec<TextLayout<String>>,
+    needle: bool,
+}
+
+impl Gauge {
+    /// Create a new `Gauge`.
+    pub fn new() -> Gauge {
+        Gauge {
+            min: 0.0,
+            max: 1.0,
+            start_angle: 135f64.to_radians(),
+            sweep_angle: 270f64.to_radians(),
+            thickness: 8.0.into(),
+            color: theme::PRIMARY_LIGHT.into(),
+            track_color: theme::BACKGROUND_LIGHT.into(),
+            tick_color: theme::BORDER_LIGHT.into(),
+            thresholds: Vec::new(),
+            major_ticks: 0,
+            minor_ticks: 1,
+            tick_labels: None,
+            labels: Vec::new(),
+            needle: false,
+        }
+    }
+
+    /// Builder-style method to set the range of values covered by the arc.
+    ///
+    /// Values outside of the range are shown at its ends.
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.min = min;
+        self.max = max;
+        self.labels.clear();
+        self
+    }
+
+    /// Builder-style method to set the arc, in degrees.
+    ///
+    /// The angles go clockwise, starting from the right, so the default of a
+    /// start at 135° and a sweep of 270° leaves a gap at the bottom.
+    pub fn with_arc(mut self, start_degrees: f64, sweep_degrees: f64) -> Self {
+        self.start_angle = start_degrees.to_radians();
+        self.sweep_angle = sweep_degrees.to_radians();
+        self
+    }
+
+    /// Builder-style method for setting the width of the arc.
+    ///
+    /// The argument can be either an `f64` or a [`Key<f64>`].
+    ///
+    /// [`Key<f64>`]: crate::Key
+    pub fn with_thickness(mut self, thickness: impl Into<KeyOrValue<f64>>) -> Self {
+        self.thickness = thickness.into();
+        self
+    }
+
+    /// Builder-style method for setting the color of the value below the first threshold.
+    ///
+    /// The argument can be either a `Color` or a [`Key<Color>`].
+    ///
+    /// [`Key<Color>`]: crate::Key
+    pub fn with_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Builder-style method for setting the color of the arc behind the value.
+    ///
+    /// The argument can be either a `Color` or a [`Key<Color>`].
+    ///
+    /// [`Key<Color>`]: crate::Key
+    pub fn with_track_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.track_color = color.into();
+        self
+    }
+
+    /// Builder-style method for setting the color of the ticks and their labels.
+    ///
+    /// The argument can be either a `Color` or a [`Key<Color>`].
+    ///
+    /// [`Key<Color>`]: crate::Key
+    pub fn with_tick_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.tick_color = color.into();
+        self.labels.clear();
+        self
+    }
+
+    /// Builder-style method to add a threshold.
+    ///
+    /// Values from `value` up to the next threshold are drawn in `color`.
+    pub fn with_threshold(mut self, value: f64, color: impl Into<KeyOrValue<Color>>) -> Self {
+        let idx = self
+            .thresholds
+            .iter()
+            .position(|(v, _)| *v > value)
+            .unwrap_or(self.thresholds.len());
+        self.thresholds.insert(idx, (value, color.into()));
+        self
+    }
+
+    /// Builder-style method to draw ticks outside of the arc.
+    ///
+    /// The range is divided into `major` parts, each of which is divided into
+    /// `minor` parts again. Zero `major` parts disables ticks.
+    pub fn with_ticks(mut self, major: usize, minor: usize) -> Self {
+        self.major_ticks = major;
+        self.minor_ticks = minor.max(1);
+        self.labels.clear();
+        self
+    }
+
+    /// Builder-style method to label the major ticks, with a function that
+    /// formats their value.
+    ///
+    /// Use a [`NumberFormatter`] to format them for the user's locale.
+    ///
+    /// [`NumberFormatter`]: crate::text::NumberFormatter
+    pub fn with_tick_labels(mut self, format: impl Fn(f64) -> String + 'static) -> Self {
+        self.tick_labels = Some(Box::new(format));
+        self.labels.clear();
+        self
+    }
+
+    /// Builder-style method to set whether a needle points at the value.
+    pub fn with_needle(mut self, needle: bool) -> Self {
+        self.needle = needle;
+        self
+    }
+
+    /// The position of `value` along the arc, from `0.0` to `1.0`.
+    fn fraction(&self, value: f64) -> f64 {
+        let span = self.max - self.min;
+        if span == 0.0 || !value.is_finite() {
+            return 0.0;
+        }
+        ((value - self.min) / span).clamp(0.0, 1.0)
+    }
+
+    fn angle(&self, fraction: f64) -> f64 {
+        self.start_angle + self.sweep_angle * fraction
+    }
+
+    /// The index of the threshold whose band `value` falls into.
+    fn threshold_for(&self, value: f64) -> Option<usize> {
+        self.thresholds.iter().rposition(|(v, _)| *v <= value)
+    }
+
+    fn arc(&self, center: Point, radius: f64, from: f64, to: f64) -> Arc {
+        let start_angle = self.angle(from);
+        Arc {
+            center,
+            radii: Vec2::new(radius, radius),
+            start_angle,
+            sweep_angle: self.angle(to) - start_angle,
+            x_rotation: 0.0,
+        }
+    }
+
+    fn rebuild_labels(&mut self) {
+        self.labels.clear();
+        let format = match &self.tick_labels {
+            Some(format) if self.major_ticks > 0 => format,
+            _ => return,
+        };
+        for i in 0..=self.major_ticks {
+            let value = self.min + (self.max - self.min) * i as f64 / self.major_ticks as f64;
+            let mut layout = TextLayout::from_text(format(value));
+            layout.set_text_color(self.tick_color.clone());
+            layout.set_text_size(theme::TEXT_SIZE_NORMAL);
+            self.labels.push(layout);
+        }
+    }
+
+    /// How far the labels stick out from the ticks.
+    fn label_extent(&self) -> f64 {
+        self.labels
+            .iter()
+            .map(|label| {
+                let size = label.size();
+                size.width.max(size.height)
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<f64> for Gauge {
+    #[instrument(name = "Gauge", level = "trace", skip(self, _ctx, _event, _data, _env))]
+    fn event(&mut self, _ctx: &mut EventCtx, _event: &Event, _data: &mut f64, _env: &Env) {}
+
+    #[instrument(name = "Gauge", level = "trace", skip(self, _ctx, _event, _data, _env))]
+    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &f64, _env: &Env) {}
+
+    #[instrument(name = "Gauge", level = "trace", skip(self, ctx, old_data, data, _env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &f64, data: &f64, _env: &Env) {
+        if !old_data.same(data) {
+            ctx.request_paint();
+        }
+        let mut rebuild = false;
+        for label in &mut self.labels {
+            rebuild |= label.needs_rebuild_after_update(ctx);
+        }
+        if rebuild {
+            ctx.request_layout();
+        }
+    }
+
+    #[instrument(name = "Gauge", level = "trace", skip(self, ctx, bc, _data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &f64, env: &Env) -> Size {
+        bc.debug_check("Gauge");
+        if self.labels.is_empty() {
+            self.rebuild_labels();
+        }
+        for label in &mut self.labels {
+            label.rebuild_if_needed(ctx.text(), env);
+        }
+        let side = env.get(theme::WIDE_WIDGET_WIDTH);
+        let size = bc.constrain(Size::new(side, side));
+        trace!("Computed size: {}", size);
+        size
+    }
+
+    #[instrument(name = "Gauge", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &f64, env: &Env) {
+        let size = ctx.size();
+        let center = size.to_rect().center();
+        let thickness = self.thickness.resolve(env);
+
+        let tick_space = if self.major_ticks > 0 {
+            SPACING + MAJOR_TICK_LENGTH
+        } else {
+            0.0
+        };
+        let label_space = if self.labels.is_empty() {
+            0.0
+        } else {
+            SPACING + self.label_extent()
+        };
+        let outer = size.width.min(size.height) / 2.0 - tick_space - label_space;
+        let radius = outer - thickness / 2.0;
+        if radius <= 0.0 {
+            return;
+        }
+
+        ctx.stroke(
+            self.arc(center, radius, 0.0, 1.0),
+            &self.track_color.resolve(env),
+            thickness,
+        );
+
+        // the bands of the thresholds, along the inner edge
+        let band_radius = radius - thickness / 2.0 - SPACING - THRESHOLD_BAND_WIDTH / 2.0;
+        if band_radius > 0.0 {
+            for (i, (value, color)) in self.thresholds.iter().enumerate() {
+                let end = self
+                    .thresholds
+                    .get(i + 1)
+                    .map(|(next, _)| *next)
+                    .unwrap_or(self.max);
+                let (from, to) = (self.fraction(*value), self.fraction(end));
+                if to > from {
+                    let band = self.arc(center, band_radius, from, to);
+                    ctx.stroke(band, &color.resolve(env), THRESHOLD_BAND_WIDTH);
+                }
+            }
+        }
+
+        let value = self.fraction(*data);
+        let color = match self.threshold_for(*data) {
+            Some(idx) => self.thresholds[idx].1.resolve(env),
+            None => self.color.resolve(env),
+        };
+        if value > 0.0 {
+            ctx.stroke(self.arc(center, radius, 0.0, value), &color, thickness);
+        }
+
+        if self.major_ticks > 0 {
+            let tick_color = self.tick_color.resolve(env);
+            let count = self.major_ticks * self.minor_ticks;
+            let tick_start = outer + SPACING;
+            for i in 0..=count {
+                let direction = Vec2::from_angle(self.angle(i as f64 / count as f64));
+                let (length, width) = if i % self.minor_ticks == 0 {
+                    (MAJOR_TICK_LENGTH, 1.5)
+                } else {
+                    (MINOR_TICK_LENGTH, 1.0)
+                };
+                let tick = Line::new(
+                    center + direction * tick_start,
+                    center + direction * (tick_start + length),
+                );
+                ctx.stroke(tick, &tick_color, width);
+            }
+
+            let label_distance = tick_start + MAJOR_TICK_LENGTH + SPACING;
+            for (i, label) in self.labels.iter().enumerate() {
+                let direction = Vec2::from_angle(self.angle(i as f64 / self.major_ticks as f64));
+                let label_size = label.size();
+                // place the label so that its center lies on a circle around the
+                // ticks, at the distance of its own half-extent
+                let half = label_size.to_vec2() / 2.0;
+                let reach = (half.x * direction.x.abs()).max(half.y * direction.y.abs());
+                let label_center = center + direction * (label_distance + reach);
+                label.draw(ctx, label_center - half);
+            }
+        }
+
+        if self.needle {
+            let direction = Vec2::from_angle(self.angle(value));
+            let tip = center + direction * (radius - thickness / 2.0);
+            let style = StrokeStyle::new().line_cap(LineCap::Round);
+            let needle_color = env.get(theme::TEXT_COLOR);
+            ctx.stroke_styled(Line::new(center, tip), &needle_color, 2.0, &style);
+            ctx.fill(Circle::new(center, 4.0), &needle_color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn fractions_and_thresholds() {
+        let gauge = Gauge::new()
+            .with_range(20.0, 100.0)
+            .with_threshold(85.0, Color::RED)
+            .with_threshold(70.0, Color::YELLOW);
+        assert_eq!(gauge.fraction(20.0), 0.0);
+        assert_eq!(gauge.fraction(60.0), 0.5);
+        assert_eq!(gauge.fraction(120.0), 1.0);
+        assert_eq!(gauge.fraction(f64::NAN), 0.0);
+
+        assert_eq!(gauge.threshold_for(50.0), None);
+        assert_eq!(gauge.threshold_for(70.0), Some(0));
+        assert_eq!(gauge.threshold_for(99.0), Some(1));
+
+        let empty = Gauge::new().with_range(1.0, 1.0);
+        assert_eq!(empty.fraction(1.0), 0.0);
+    }
+}
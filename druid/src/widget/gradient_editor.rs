@@ -0,0 +1,380 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An editor for the stops of a gradient.
+
+use std::sync::Arc;
+
+use crate::commands::GRADIENT_STOP_SELECTED;
+use crate::kurbo::BezPath;
+use crate::piet::GradientStop;
+use crate::widget::prelude::*;
+use crate::{theme, Color, KbKey, LinearGradient, MouseButton, Rect, UnitPoint};
+use tracing::{instrument, trace};
+
+const BAR_HEIGHT: f64 = 24.0;
+const HANDLE_SIZE: f64 = 12.0;
+/// How far below the editor a stop has to be dragged to remove it.
+const REMOVE_DISTANCE: f64 = 30.0;
+/// How far the arrow keys move the selected stop.
+const NUDGE: f32 = 0.01;
+const DEFAULT_WIDTH: f64 = 200.0;
+
+/// An editor for the stops of a gradient, as used by [`LinearGradient`] and
+/// [`RadialGradient`].
+///
+/// The gradient is shown in a bar, with a handle below it for each stop.
+/// The handles can be dragged to move the stops; dragging one away below
+/// the editor removes it. Clicking the bar adds a stop there, with the
+/// color the gradient has at that point. A gradient always keeps at least
+/// two stops.
+///
+/// Selecting a stop submits [`GRADIENT_STOP_SELECTED`] as a notification
+/// with its index, so that its color can be edited elsewhere. When the
+/// editor has focus, the arrow keys move the selected stop, and Delete
+/// removes it.
+///
+/// The stops are not sorted by the editor; they are kept in the order they
+/// were added, so that the index of a stop doesn't change while it is
+/// dragged past another one.
+///
+/// [`LinearGradient`]: crate::LinearGradient
+/// [`RadialGradient`]: crate::RadialGradient
+/// [`GRADIENT_STOP_SELECTED`]: crate::commands::GRADIENT_STOP_SELECTED
+pub struct GradientEditor {
+    selected: Option<usize>,
+    drag: Option<Drag>,
+}
+
+/// A stop that is being dragged.
+struct Drag {
+    index: usize,
+    /// Whether the stop will be removed when it is dropped.
+    removing: bool,
+}
+
+impl GradientEditor {
+    /// Create a new `GradientEditor`.
+    pub fn new() -> Self {
+        GradientEditor {
+            selected: None,
+            drag: None,
+        }
+    }
+
+    fn bar_rect(size: Size) -> Rect {
+        Rect::new(
+            HANDLE_SIZE / 2.0,
+            0.0,
+            size.width - HANDLE_SIZE / 2.0,
+            BAR_HEIGHT,
+        )
+    }
+
+    fn handle_rect(size: Size, stop: &GradientStop) -> Rect {
+        let bar = Self::bar_rect(size);
+        let x = bar.x0 + bar.width() * stop.pos as f64;
+        Rect::new(
+            x - HANDLE_SIZE / 2.0,
+            BAR_HEIGHT,
+            x + HANDLE_SIZE / 2.0,
+            BAR_HEIGHT + HANDLE_SIZE * 1.5,
+        )
+    }
+
+    fn pos_at(size: Size, x: f64) -> f32 {
+        let bar = Self::bar_rect(size);
+        if bar.width() <= 0.0 {
+            return 0.0;
+        }
+        ((x - bar.x0) / bar.width()).clamp(0.0, 1.0) as f32
+    }
+
+    fn select(&mut self, ctx: &mut EventCtx, index: usize) {
+        if self.selected != Some(index) {
+            trace!("GradientEditor selected stop {}", index);
+            self.selected = Some(index);
+            ctx.submit_notification(GRADIENT_STOP_SELECTED.with(index));
+        }
+        ctx.request_paint();
+    }
+
+    fn remove(&mut self, ctx: &mut EventCtx, data: &mut Arc<Vec<GradientStop>>, index: usize) {
+        if data.len() > 2 && index < data.len() {
+            trace!("GradientEditor removed stop {}", index);
+            Arc::make_mut(data).remove(index);
+            self.selected = None;
+            ctx.request_paint();
+        }
+    }
+}
+
+impl Default for GradientEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The color of the gradient at `pos`, between the stops around it.
+fn color_at(stops: &[GradientStop], pos: f32) -> Color {
+    let mut sorted = stops.to_vec();
+    sorted.sort_by(|a, b| {
+        a.pos
+            .partial_cmp(&b.pos)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let after = sorted.iter().position(|stop| stop.pos >= pos);
+    match after {
+        None => sorted.last().map(|stop| stop.color.clone()),
+        Some(0) => sorted.first().map(|stop| stop.color.clone()),
+        Some(index) => {
+            let (a, b) = (&sorted[index - 1], &sorted[index]);
+            let t = if b.pos > a.pos {
+                ((pos - a.pos) / (b.pos - a.pos)) as f64
+            } else {
+                0.0
+            };
+            let (r0, g0, b0, a0) = a.color.as_rgba();
+            let (r1, g1, b1, a1) = b.color.as_rgba();
+            let lerp = |from: f64, to: f64| from + (to - from) * t;
+            Some(Color::rgba(
+                lerp(r0, r1),
+                lerp(g0, g1),
+                lerp(b0, b1),
+                lerp(a0, a1),
+            ))
+        }
+    }
+    .unwrap_or(Color::BLACK)
+}
+
+impl Widget<Arc<Vec<GradientStop>>> for GradientEditor {
+    #[instrument(
+        name = "GradientEditor",
+        level = "trace",
+        skip(self, ctx, event, data, _env)
+    )]
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut Arc<Vec<GradientStop>>,
+        _env: &Env,
+    ) {
+        let size = ctx.size();
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                ctx.request_focus();
+                // the handles drawn last are on top
+                let handle = data
+                    .iter()
+                    .rposition(|stop| Self::handle_rect(size, stop).contains(mouse.pos));
+                let index = match handle {
+                    Some(index) => index,
+                    None if Self::bar_rect(size).contains(mouse.pos) => {
+                        let pos = Self::pos_at(size, mouse.pos.x);
+                        let color = color_at(data, pos);
+                        trace!("GradientEditor added stop at {}", pos);
+                        Arc::make_mut(data).push(GradientStop { pos, color });
+                        data.len() - 1
+                    }
+                    None => return,
+                };
+                self.select(ctx, index);
+                self.drag = Some(Drag {
+                    index,
+                    removing: false,
+                });
+                ctx.set_active(true);
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                if let Some(drag) = &mut self.drag {
+                    if let Some(stop) = Arc::make_mut(data).get_mut(drag.index) {
+                        stop.pos = Self::pos_at(size, mouse.pos.x);
+                    }
+                    drag.removing = data.len() > 2 && mouse.pos.y > size.height + REMOVE_DISTANCE;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                if let Some(drag) = self.drag.take() {
+                    if drag.removing {
+                        self.remove(ctx, data, drag.index);
+                    }
+                }
+            }
+            Event::KeyDown(key) if ctx.has_focus() => {
+                let index = match self.selected {
+                    Some(index) if index < data.len() => index,
+                    _ => return,
+                };
+                match key.key {
+                    KbKey::ArrowLeft | KbKey::ArrowRight => {
+                        let nudge = if key.key == KbKey::ArrowLeft {
+                            -NUDGE
+                        } else {
+                            NUDGE
+                        };
+                        let stop = &mut Arc::make_mut(data)[index];
+                        stop.pos = (stop.pos + nudge).clamp(0.0, 1.0);
+                    }
+                    KbKey::Delete | KbKey::Backspace => self.remove(ctx, data, index),
+                    _ => return,
+                }
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "GradientEditor",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        _data: &Arc<Vec<GradientStop>>,
+        _env: &Env,
+    ) {
+        match event {
+            LifeCycle::WidgetAdded => ctx.register_for_focus(),
+            LifeCycle::FocusChanged(_) => ctx.request_paint(),
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "GradientEditor",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &Arc<Vec<GradientStop>>,
+        data: &Arc<Vec<GradientStop>>,
+        _env: &Env,
+    ) {
+        if !old_data.same(data) {
+            if self.selected.is_some_and(|index| index >= data.len()) {
+                self.selected = None;
+            }
+            ctx.request_paint();
+        }
+    }
+
+    #[instrument(
+        name = "GradientEditor",
+        level = "trace",
+        skip(self, _layout_ctx, bc, _data, _env)
+    )]
+    fn layout(
+        &mut self,
+        _layout_ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Arc<Vec<GradientStop>>,
+        _env: &Env,
+    ) -> Size {
+        bc.debug_check("GradientEditor");
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            DEFAULT_WIDTH
+        };
+        let size = bc.constrain(Size::new(width, BAR_HEIGHT + HANDLE_SIZE * 1.5));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "GradientEditor", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Arc<Vec<GradientStop>>, env: &Env) {
+        let size = ctx.size();
+        let bar = Self::bar_rect(size);
+        let mut stops = data.to_vec();
+        stops.sort_by(|a, b| {
+            a.pos
+                .partial_cmp(&b.pos)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        if !stops.is_empty() {
+            let gradient = LinearGradient::new(UnitPoint::LEFT, UnitPoint::RIGHT, stops);
+            ctx.fill(bar, &gradient);
+        }
+        ctx.stroke(bar.inset(-0.5), &env.get(theme::BORDER_DARK), 1.0);
+
+        let removing = self.drag.as_ref().filter(|drag| drag.removing);
+        for (index, stop) in data.iter().enumerate() {
+            if removing.is_some_and(|drag| drag.index == index) {
+                continue;
+            }
+            // a square of the stop's color, with a point up at the bar
+            let handle = Self::handle_rect(size, stop);
+            let mut shape = BezPath::new();
+            shape.move_to((handle.center().x, handle.y0));
+            shape.line_to((handle.x1, handle.y0 + HANDLE_SIZE / 2.0));
+            shape.line_to((handle.x1, handle.y1));
+            shape.line_to((handle.x0, handle.y1));
+            shape.line_to((handle.x0, handle.y0 + HANDLE_SIZE / 2.0));
+            shape.close_path();
+            ctx.fill(shape.clone(), &stop.color);
+            let (border_color, border_width) = if Some(index) == self.selected {
+                let color = if ctx.has_focus() {
+                    env.get(theme::PRIMARY_LIGHT)
+                } else {
+                    env.get(theme::TEXT_COLOR)
+                };
+                (color, 2.0)
+            } else {
+                (env.get(theme::BORDER_LIGHT), 1.0)
+            };
+            ctx.stroke(shape, &border_color, border_width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    fn stop(pos: f32, color: Color) -> GradientStop {
+        GradientStop { pos, color }
+    }
+
+    #[test]
+    fn color_between_stops() {
+        let stops = [
+            stop(1.0, Color::rgb8(200, 0, 0)),
+            stop(0.0, Color::rgb8(0, 0, 100)),
+        ];
+        assert_eq!(color_at(&stops, 0.5).as_rgba8(), (100, 0, 50, 255));
+        assert_eq!(color_at(&stops, 0.0).as_rgba8(), (0, 0, 100, 255));
+        assert_eq!(color_at(&stops, 1.0).as_rgba8(), (200, 0, 0, 255));
+    }
+
+    #[test]
+    fn color_outside_stops() {
+        let stops = [
+            stop(0.25, Color::rgb8(10, 20, 30)),
+            stop(0.75, Color::rgb8(40, 50, 60)),
+        ];
+        assert_eq!(color_at(&stops, 0.0).as_rgba8(), (10, 20, 30, 255));
+        assert_eq!(color_at(&stops, 1.0).as_rgba8(), (40, 50, 60, 255));
+        assert_eq!(color_at(&[], 0.5).as_rgba8(), (0, 0, 0, 255));
+    }
+}
@@ -263,7 +263,7 @@ mod tests {
     /// Painting an empty image shouldn't crash druid.
     #[test]
     fn empty_paint() {
-        use crate::{tests::harness::Harness, WidgetId};
+        use crate::{testing::Harness, WidgetId};
 
         let _id_1 = WidgetId::next();
         let image_data = ImageBuf::empty();
@@ -288,7 +288,7 @@ mod tests {
 
     #[test]
     fn tall_paint() {
-        use crate::{tests::harness::Harness, WidgetId};
+        use crate::{testing::Harness, WidgetId};
 
         let _id_1 = WidgetId::next();
         let image_data = ImageBuf::from_raw(
@@ -337,7 +337,7 @@ mod tests {
 
     #[test]
     fn wide_paint() {
-        use crate::{tests::harness::Harness, WidgetId};
+        use crate::{testing::Harness, WidgetId};
         let _id_1 = WidgetId::next();
         let image_data = ImageBuf::from_raw(
             vec![255, 255, 255, 0, 0, 0, 0, 0, 0, 255, 255, 255],
@@ -389,11 +389,9 @@ mod tests {
     }
 
     #[test]
+    #[cfg(all(feature = "image", feature = "png"))]
     fn into_png() {
-        use crate::{
-            tests::{harness::Harness, temp_dir_for_test},
-            WidgetId,
-        };
+        use crate::{testing::Harness, tests::temp_dir_for_test, WidgetId};
         let _id_1 = WidgetId::next();
         let image_data = ImageBuf::from_raw(
             vec![255, 255, 255, 0, 0, 0, 0, 0, 0, 255, 255, 255],
@@ -424,7 +422,7 @@ mod tests {
     #[test]
     fn width_bound_layout() {
         use crate::{
-            tests::harness::Harness,
+            testing::Harness,
             widget::{Container, Scroll},
             WidgetExt, WidgetId,
         };
@@ -452,7 +450,7 @@ mod tests {
     #[test]
     fn height_bound_layout() {
         use crate::{
-            tests::harness::Harness,
+            testing::Harness,
             widget::{Container, Scroll},
             WidgetExt, WidgetId,
         };
@@ -479,7 +477,7 @@ mod tests {
 
     #[test]
     fn image_clip_area() {
-        use crate::{tests::harness::Harness, WidgetId};
+        use crate::{testing::Harness, WidgetId};
         use std::iter;
 
         let _id_1 = WidgetId::next();
@@ -0,0 +1,359 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget for looking at an image, with pan and zoom.
+
+use tracing::{instrument, trace};
+
+use crate::piet::{ImageBuf, InterpolationMode};
+use crate::widget::prelude::*;
+use crate::widget::{FillStrat, Image};
+use crate::{Color, MouseButton, Point, Rect, Selector, Vec2, WidgetPod};
+
+const MIN_ZOOM: f64 = 0.05;
+const MAX_ZOOM: f64 = 32.0;
+/// How much a wheel delta of one pixel changes the zoom.
+const ZOOM_SPEED: f64 = 0.002;
+const CHECKER_SIZE: f64 = 8.0;
+const CHECKER_LIGHT: Color = Color::grey8(0xcc);
+const CHECKER_DARK: Color = Color::grey8(0x99);
+
+/// How an [`ImageViewer`] chooses its zoom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomMode {
+    /// Scale the image so that all of it is visible, following the size of the viewer.
+    FitToWindow,
+    /// Show one pixel of the image per display point.
+    ActualSize,
+    /// Keep the zoom and position that the user chose, by scrolling or dragging.
+    Manual,
+}
+
+/// A widget that shows an [`Image`] that the user can zoom and pan.
+///
+/// Scrolling zooms around the mouse cursor, and dragging with the left mouse
+/// button moves the image. Double clicking switches between
+/// [`ZoomMode::FitToWindow`] and [`ZoomMode::ActualSize`], which can also be
+/// chosen with the [`FIT_TO_WINDOW`] and [`ACTUAL_SIZE`] commands.
+///
+/// The viewer takes all of the space it is given; when it is unbounded, it
+/// takes the size of the image.
+///
+/// # Examples
+///
+/// ```
+/// use druid::widget::{ImageViewer, ZoomMode};
+/// use druid::piet::ImageBuf;
+///
+/// let viewer = ImageViewer::<()>::new(ImageBuf::empty())
+///     .with_zoom_mode(ZoomMode::ActualSize)
+///     .with_checkerboard(true);
+/// ```
+///
+/// [`FIT_TO_WINDOW`]: ImageViewer::FIT_TO_WINDOW
+/// [`ACTUAL_SIZE`]: ImageViewer::ACTUAL_SIZE
+pub struct ImageViewer<T> {
+    image: WidgetPod<T, Image>,
+    image_size: Size,
+    mode: ZoomMode,
+    zoom: f64,
+    /// The position of the image's origin, in our coordinates.
+    offset: Vec2,
+    checkerboard: bool,
+    /// The last mouse position of a drag.
+    drag: Option<Point>,
+}
+
+impl ImageViewer<()> {
+    /// Scale the image to fit the viewer.
+    pub const FIT_TO_WINDOW: Selector = Selector::new("druid-builtin.image-viewer-fit");
+
+    /// Show the image at its actual size.
+    pub const ACTUAL_SIZE: Selector = Selector::new("druid-builtin.image-viewer-actual-size");
+}
+
+impl<T: Data> ImageViewer<T> {
+    /// Create a new `ImageViewer` for the provided image, fit to the window.
+    pub fn new(image_data: ImageBuf) -> Self {
+        let image_size = image_data.size();
+        let image = Image::new(image_data).fill_mode(FillStrat::Fill);
+        ImageViewer {
+            image: WidgetPod::new(image),
+            image_size,
+            mode: ZoomMode::FitToWindow,
+            zoom: 1.0,
+            offset: Vec2::ZERO,
+            checkerboard: false,
+            drag: None,
+        }
+    }
+
+    /// Builder-style method for setting the initial [`ZoomMode`].
+    pub fn with_zoom_mode(mut self, mode: ZoomMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Builder-style method for drawing a checkerboard behind the image,
+    /// to make transparent parts visible.
+    pub fn with_checkerboard(mut self, checkerboard: bool) -> Self {
+        self.checkerboard = checkerboard;
+        self
+    }
+
+    /// Builder-style method for setting how the image is interpolated when zoomed.
+    pub fn with_interpolation_mode(mut self, interpolation: InterpolationMode) -> Self {
+        self.image
+            .widget_mut()
+            .set_interpolation_mode(interpolation);
+        self
+    }
+
+    /// Set a new image.
+    ///
+    /// This keeps the [`ZoomMode`], unless it is [`ZoomMode::Manual`], in which
+    /// case the new image is fit to the window.
+    pub fn set_image_data(&mut self, image_data: ImageBuf) {
+        self.image_size = image_data.size();
+        self.image.widget_mut().set_image_data(image_data);
+        if self.mode == ZoomMode::Manual {
+            self.mode = ZoomMode::FitToWindow;
+        }
+    }
+
+    /// Set the [`ZoomMode`].
+    ///
+    /// You need to request layout for this to take effect.
+    pub fn set_zoom_mode(&mut self, mode: ZoomMode) {
+        self.mode = mode;
+    }
+
+    /// The current [`ZoomMode`].
+    pub fn zoom_mode(&self) -> ZoomMode {
+        self.mode
+    }
+
+    /// The current zoom factor, where `1.0` is the actual size of the image.
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    fn zoom_around(&mut self, pos: Point, factor: f64, view: Size) {
+        let anchor = (pos.to_vec2() - self.offset) / self.zoom;
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = pos.to_vec2() - anchor * self.zoom;
+        self.offset = clamp_offset(self.offset, self.image_size * self.zoom, view);
+    }
+
+    fn paint_checkerboard(&self, ctx: &mut PaintCtx) {
+        let image_rect =
+            Rect::from_origin_size(self.offset.to_point(), self.image_size * self.zoom);
+        let visible = image_rect.intersect(ctx.size().to_rect());
+        if visible.area() <= 0.0 {
+            return;
+        }
+        ctx.fill(visible, &CHECKER_LIGHT);
+        // the squares stay the same size when zooming, but move with the image
+        let first_col = ((visible.x0 - image_rect.x0) / CHECKER_SIZE).floor() as i64;
+        let first_row = ((visible.y0 - image_rect.y0) / CHECKER_SIZE).floor() as i64;
+        let mut row = first_row;
+        loop {
+            let y0 = image_rect.y0 + row as f64 * CHECKER_SIZE;
+            if y0 >= visible.y1 {
+                break;
+            }
+            let mut col = first_col + (first_col + row).rem_euclid(2);
+            loop {
+                let x0 = image_rect.x0 + col as f64 * CHECKER_SIZE;
+                if x0 >= visible.x1 {
+                    break;
+                }
+                let square = Rect::new(x0, y0, x0 + CHECKER_SIZE, y0 + CHECKER_SIZE);
+                ctx.fill(square.intersect(visible), &CHECKER_DARK);
+                col += 2;
+            }
+            row += 1;
+        }
+    }
+}
+
+/// Keeps as much of the image in view as possible: an image that is smaller than
+/// the view is centered, and a larger one has no gaps at its edges.
+fn clamp_offset(offset: Vec2, image: Size, view: Size) -> Vec2 {
+    let clamp = |offset: f64, image: f64, view: f64| {
+        if image <= view {
+            (view - image) / 2.0
+        } else {
+            offset.max(view - image).min(0.0)
+        }
+    };
+    Vec2::new(
+        clamp(offset.x, image.width, view.width),
+        clamp(offset.y, image.height, view.height),
+    )
+}
+
+impl<T: Data> Widget<T> for ImageViewer<T> {
+    #[instrument(
+        name = "ImageViewer",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.image.event(ctx, event, data, env);
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                if mouse.count == 2 {
+                    self.mode = match self.mode {
+                        ZoomMode::FitToWindow => ZoomMode::ActualSize,
+                        _ => ZoomMode::FitToWindow,
+                    };
+                    ctx.request_layout();
+                } else {
+                    self.drag = Some(mouse.pos);
+                    ctx.set_active(true);
+                }
+                ctx.set_handled();
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                if let Some(last) = self.drag.replace(mouse.pos) {
+                    self.offset += mouse.pos - last;
+                    self.mode = ZoomMode::Manual;
+                    ctx.request_layout();
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                self.drag = None;
+                ctx.set_active(false);
+            }
+            Event::Wheel(mouse) => {
+                let factor = (-mouse.wheel_delta.y * ZOOM_SPEED).exp();
+                self.zoom_around(mouse.pos, factor, ctx.size());
+                self.mode = ZoomMode::Manual;
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(ImageViewer::FIT_TO_WINDOW) => {
+                self.mode = ZoomMode::FitToWindow;
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(ImageViewer::ACTUAL_SIZE) => {
+                self.mode = ZoomMode::ActualSize;
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "ImageViewer",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.image.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "ImageViewer",
+        level = "trace",
+        skip(self, ctx, _old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        self.image.update(ctx, data, env);
+    }
+
+    #[instrument(name = "ImageViewer", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("ImageViewer");
+        let max = bc.max();
+        let size = bc.constrain(Size::new(
+            if bc.is_width_bounded() {
+                max.width
+            } else {
+                self.image_size.width
+            },
+            if bc.is_height_bounded() {
+                max.height
+            } else {
+                self.image_size.height
+            },
+        ));
+
+        match self.mode {
+            ZoomMode::FitToWindow if !self.image_size.is_empty() => {
+                self.zoom = (size.width / self.image_size.width)
+                    .min(size.height / self.image_size.height)
+                    .clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+            ZoomMode::ActualSize => self.zoom = 1.0,
+            _ => (),
+        }
+        let image_size = self.image_size * self.zoom;
+        self.offset = clamp_offset(self.offset, image_size, size);
+
+        self.image
+            .layout(ctx, &BoxConstraints::tight(image_size), data, env);
+        self.image
+            .set_origin(ctx, data, env, self.offset.to_point());
+
+        trace!("Computed size: {}", size);
+        size
+    }
+
+    #[instrument(name = "ImageViewer", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let clip = ctx.size().to_rect();
+        ctx.clip(clip);
+        if self.checkerboard {
+            self.paint_checkerboard(ctx);
+        }
+        self.image.paint(ctx, data, env);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn offsets_are_clamped() {
+        let view = Size::new(100.0, 100.0);
+        // small images are centered
+        let offset = clamp_offset(Vec2::new(-30.0, 70.0), Size::new(50.0, 20.0), view);
+        assert_eq!(offset, Vec2::new(25.0, 40.0));
+        // large images cover the view
+        let offset = clamp_offset(Vec2::new(10.0, -500.0), Size::new(200.0, 300.0), view);
+        assert_eq!(offset, Vec2::new(0.0, -200.0));
+        let offset = clamp_offset(Vec2::new(-40.0, -50.0), Size::new(200.0, 300.0), view);
+        assert_eq!(offset, Vec2::new(-40.0, -50.0));
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let mut viewer = ImageViewer::<()>::new(ImageBuf::empty());
+        viewer.image_size = Size::new(400.0, 400.0);
+        viewer.zoom = 1.0;
+        viewer.offset = Vec2::new(-100.0, -100.0);
+        let view = Size::new(200.0, 200.0);
+
+        viewer.zoom_around(Point::new(50.0, 50.0), 2.0, view);
+        assert_eq!(viewer.zoom, 2.0);
+        // the image point (150, 150) stays at (50, 50)
+        assert_eq!(viewer.offset, Vec2::new(-250.0, -250.0));
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that exposes its data to the widget inspector.
+
+use std::fmt::Debug;
+
+use crate::widget::{prelude::*, WidgetWrapper};
+use crate::Data;
+use tracing::instrument;
+
+/// A widget that shows its data in the widget inspector.
+///
+/// The inspector (toggled with the [`TOGGLE_INSPECTOR`] command) can only
+/// show the data of widgets whose data is `Debug`; wrapping a widget in
+/// `InspectData` opts it in. The data is shown as part of the widget that
+/// contains this one, so when used after a [`lens`], the lensed data is shown.
+///
+/// This widget does nothing while the inspector is closed.
+///
+/// [`TOGGLE_INSPECTOR`]: crate::commands::TOGGLE_INSPECTOR
+/// [`lens`]: super::WidgetExt::lens
+pub struct InspectData<W> {
+    inner: W,
+}
+
+impl<W> InspectData<W> {
+    /// Wrap a widget in an `InspectData`.
+    pub fn new(inner: W) -> Self {
+        InspectData { inner }
+    }
+}
+
+impl<W> WidgetWrapper for InspectData<W> {
+    widget_wrapper_body!(W, inner);
+}
+
+impl<T: Data + Debug, W: Widget<T>> Widget<T> for InspectData<W> {
+    #[instrument(
+        name = "InspectData",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.inner.event(ctx, event, data, env)
+    }
+
+    #[instrument(
+        name = "InspectData",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    #[instrument(
+        name = "InspectData",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.inner.update(ctx, old_data, data, env)
+    }
+
+    #[instrument(name = "InspectData", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        self.inner.layout(ctx, bc, data, env)
+    }
+
+    #[instrument(name = "InspectData", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        if let Some(inspector) = ctx.state.inspector.as_mut() {
+            inspector.set_data(format!("{:?}", data));
+        }
+        self.inner.paint(ctx, data, env)
+    }
+
+    fn id(&self) -> Option<WidgetId> {
+        self.inner.id()
+    }
+}
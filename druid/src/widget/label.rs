@@ -19,11 +19,11 @@ use std::ops::{Deref, DerefMut};
 use druid_shell::Cursor;
 
 use crate::kurbo::Vec2;
-use crate::text::TextStorage;
+use crate::text::{Highlights, TextStorage};
 use crate::widget::prelude::*;
 use crate::{
-    ArcStr, Color, Data, FontDescriptor, KeyOrValue, LocalizedString, Point, TextAlignment,
-    TextLayout,
+    ArcStr, Color, Data, FontDescriptor, InternalLifeCycle, KeyOrValue, LocalizedString, Point,
+    TextAlignment, TextLayout,
 };
 use tracing::{instrument, trace};
 
@@ -99,6 +99,7 @@ pub struct RawLabel<T> {
 
     disabled: bool,
     default_text_color: KeyOrValue<Color>,
+    highlights: Highlights,
 }
 
 /// Options for handling lines that are too wide for the label.
@@ -157,6 +158,7 @@ impl<T: TextStorage> RawLabel<T> {
             line_break_mode: LineBreaking::Overflow,
             disabled: false,
             default_text_color: crate::theme::TEXT_COLOR.into(),
+            highlights: Highlights::default(),
         }
     }
 
@@ -209,6 +211,12 @@ impl<T: TextStorage> RawLabel<T> {
         self
     }
 
+    /// Builder-style method to set the [`Highlights`] drawn behind the text.
+    pub fn with_highlights(mut self, highlights: Highlights) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
     /// Set the text color.
     ///
     /// The argument can be either a `Color` or a [`Key<Color>`].
@@ -273,6 +281,31 @@ impl<T: TextStorage> RawLabel<T> {
         self.layout.set_text_alignment(alignment);
     }
 
+    /// Set the [`Highlights`] drawn behind the text.
+    ///
+    /// If you change this property, you are responsible for calling
+    /// [`request_paint`] to ensure the label is redrawn.
+    ///
+    /// [`request_paint`]: crate::EventCtx::request_paint
+    pub fn set_highlights(&mut self, highlights: Highlights) {
+        self.highlights = highlights;
+    }
+
+    /// The [`Highlights`] drawn behind the text.
+    pub fn highlights(&self) -> &Highlights {
+        &self.highlights
+    }
+
+    /// A mutable reference to the [`Highlights`], for instance to move to the next match.
+    ///
+    /// If you change the highlights, you are responsible for calling
+    /// [`request_paint`] to ensure the label is redrawn.
+    ///
+    /// [`request_paint`]: crate::EventCtx::request_paint
+    pub fn highlights_mut(&mut self) -> &mut Highlights {
+        &mut self.highlights
+    }
+
     /// Draw this label's text at the provided `Point`, without internal padding.
     ///
     /// This is a convenience for widgets that want to use Label as a way
@@ -420,6 +453,14 @@ impl<T: Data> Label<T> {
         self
     }
 
+    /// Builder-style method to set the [`Highlights`] drawn behind the text.
+    ///
+    /// The ranges refer to the resolved text; see [`Label::text`].
+    pub fn with_highlights(mut self, highlights: Highlights) -> Self {
+        self.label.set_highlights(highlights);
+        self
+    }
+
     /// Draw this label's text at the provided `Point`, without internal padding.
     ///
     /// This is a convenience for widgets that want to use Label as a way
@@ -572,6 +613,11 @@ impl<T: TextStorage> Widget<T> for RawLabel<T> {
                 self.layout.set_text_color(color);
                 ctx.request_layout();
             }
+            LifeCycle::Internal(InternalLifeCycle::DebugFindLabel(query))
+                if data.as_str() == query.text() =>
+            {
+                query.found(ctx.widget_id());
+            }
             _ => {}
         }
     }
@@ -613,14 +659,16 @@ impl<T: TextStorage> Widget<T> for RawLabel<T> {
         size
     }
 
-    #[instrument(name = "RawLabel", level = "trace", skip(self, ctx, _data, _env))]
-    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, _env: &Env) {
+    #[instrument(name = "RawLabel", level = "trace", skip(self, ctx, _data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
         let origin = Point::new(LABEL_X_PADDING, 0.0);
         let label_size = ctx.size();
 
         if self.line_break_mode == LineBreaking::Clip {
             ctx.clip(label_size.to_rect());
         }
+        self.highlights
+            .draw(ctx, &self.layout, origin.to_vec2(), env);
         self.draw_at(ctx, origin)
     }
 }
@@ -0,0 +1,124 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A label whose changes are read out by screen readers.
+
+use crate::widget::prelude::*;
+use crate::widget::{Label, LabelText};
+use crate::{Data, Politeness};
+use tracing::{instrument, trace};
+
+/// A label whose changes are announced to screen readers.
+///
+/// This is meant for text that changes without the user moving focus to it,
+/// such as status messages and validation errors. The text is shown like a
+/// [`Label`], and whenever it changes, the new text is announced. The initial
+/// text, and text that becomes empty, are not announced.
+///
+/// By default announcements are [`Politeness::Polite`], so they wait for the
+/// screen reader to finish what it is saying.
+pub struct LiveRegion<T> {
+    label: Label<T>,
+    politeness: Politeness,
+    last_announcement: Option<String>,
+}
+
+impl<T: Data> LiveRegion<T> {
+    /// Create a new `LiveRegion` showing `text`.
+    ///
+    /// This takes the same kinds of text as [`Label::new`].
+    pub fn new(text: impl Into<LabelText<T>>) -> Self {
+        LiveRegion {
+            label: Label::new(text),
+            politeness: Politeness::Polite,
+            last_announcement: None,
+        }
+    }
+
+    /// Create a new `LiveRegion` with text generated from the data.
+    pub fn dynamic(text: impl Fn(&T, &Env) -> String + 'static) -> Self {
+        LiveRegion {
+            label: Label::dynamic(text),
+            politeness: Politeness::Polite,
+            last_announcement: None,
+        }
+    }
+
+    /// Builder-style method for setting how urgent announcements are.
+    pub fn with_politeness(mut self, politeness: Politeness) -> Self {
+        self.politeness = politeness;
+        self
+    }
+
+    /// Set how urgent announcements are.
+    pub fn set_politeness(&mut self, politeness: Politeness) {
+        self.politeness = politeness;
+    }
+
+    /// The text that was announced last, if anything was announced yet.
+    pub fn last_announcement(&self) -> Option<&str> {
+        self.last_announcement.as_deref()
+    }
+
+    /// The label that shows the text, for changing its appearance.
+    pub fn label_mut(&mut self) -> &mut Label<T> {
+        &mut self.label
+    }
+}
+
+impl<T: Data> Widget<T> for LiveRegion<T> {
+    #[instrument(
+        name = "LiveRegion",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.label.event(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "LiveRegion",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.label.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "LiveRegion",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        let old_text = self.label.text();
+        self.label.update(ctx, old_data, data, env);
+        let text = self.label.text();
+        if text != old_text && !text.is_empty() {
+            trace!("announcing {:?}", text);
+            ctx.window().announce(&text, self.politeness);
+            self.last_announcement = Some(text.to_string());
+        }
+    }
+
+    #[instrument(name = "LiveRegion", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        self.label.layout(ctx, bc, data, env)
+    }
+
+    #[instrument(name = "LiveRegion", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.label.paint(ctx, data, env);
+    }
+}
@@ -0,0 +1,507 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A view of log messages that follows new ones as they arrive.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::kurbo::RoundedRect;
+use crate::piet::{FontFamily, Text, TextLayout, TextLayoutBuilder};
+use crate::widget::prelude::*;
+use crate::{theme, ArcStr, Color, Data, Lens, Point, Rect};
+use tracing::{instrument, trace, warn};
+
+const DEFAULT_SIZE: Size = Size::new(400.0, 300.0);
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+const PADDING: f64 = 4.0;
+const SCROLLBAR_MIN_LENGTH: f64 = 16.0;
+
+/// The severity of a [`LogEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Data)]
+pub enum LogLevel {
+    /// Very detailed messages, for following what the program does.
+    Trace,
+    /// Messages that help with finding bugs.
+    Debug,
+    /// Messages about normal operation.
+    Info,
+    /// Messages about something unexpected, that was recovered from.
+    Warn,
+    /// Messages about something that failed.
+    Error,
+}
+
+/// A message in a [`LogBuffer`].
+#[derive(Debug, Clone, PartialEq, Data, Lens)]
+pub struct LogEntry {
+    /// How severe the message is, which decides its color.
+    pub level: LogLevel,
+    /// The text of the message.
+    pub message: ArcStr,
+}
+
+impl LogEntry {
+    /// Create a new `LogEntry`.
+    pub fn new(level: LogLevel, message: impl Into<ArcStr>) -> Self {
+        LogEntry {
+            level,
+            message: message.into(),
+        }
+    }
+}
+
+/// An append-only buffer of log messages, which keeps the most recent ones.
+///
+/// When the buffer is full, adding a message drops the oldest one. Messages
+/// have a position that does not change when older ones are dropped; the
+/// first message that is kept is at [`first_position`].
+///
+/// [`first_position`]: LogBuffer::first_position
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    entries: Arc<VecDeque<LogEntry>>,
+    capacity: usize,
+    /// The number of entries that were dropped to make space.
+    dropped: usize,
+}
+
+impl LogBuffer {
+    /// Create an empty `LogBuffer` that keeps at most `capacity` messages.
+    pub fn new(capacity: usize) -> Self {
+        LogBuffer {
+            entries: Arc::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }
+    }
+
+    /// Add a message, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, entry: LogEntry) {
+        self.extend(std::iter::once(entry));
+    }
+
+    /// Add several messages, dropping the oldest ones if the buffer is full.
+    pub fn extend(&mut self, entries: impl IntoIterator<Item = LogEntry>) {
+        let capacity = self.capacity;
+        let buffer = Arc::make_mut(&mut self.entries);
+        for entry in entries {
+            if buffer.len() == capacity {
+                buffer.pop_front();
+                self.dropped += 1;
+            }
+            buffer.push_back(entry);
+        }
+    }
+
+    /// Remove all messages.
+    pub fn clear(&mut self) {
+        self.dropped += self.entries.len();
+        self.entries = Arc::new(VecDeque::new());
+    }
+
+    /// The maximum number of messages that are kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of messages that are kept.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no messages.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The position of the oldest message that is kept.
+    pub fn first_position(&self) -> usize {
+        self.dropped
+    }
+
+    /// The position that the next message will have.
+    pub fn end_position(&self) -> usize {
+        self.dropped + self.entries.len()
+    }
+
+    /// The message at `position`, if it is still kept.
+    pub fn get(&self, position: usize) -> Option<&LogEntry> {
+        position
+            .checked_sub(self.dropped)
+            .and_then(|idx| self.entries.get(idx))
+    }
+
+    /// Iterate over the messages that are kept, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        LogBuffer::new(10_000)
+    }
+}
+
+impl Data for LogBuffer {
+    fn same(&self, other: &Self) -> bool {
+        self.entries.same(&other.entries)
+            && self.capacity == other.capacity
+            && self.dropped == other.dropped
+    }
+}
+
+/// The data of a [`LogView`]: the messages, the filter and whether the view
+/// follows new messages.
+#[derive(Debug, Clone, Default, Data, Lens)]
+pub struct LogState {
+    /// The messages.
+    pub entries: LogBuffer,
+    /// Only messages that match this are shown; all of them are shown if it
+    /// is empty.
+    ///
+    /// With the `regex` feature this is a regular expression, unless it is not
+    /// a valid one. Otherwise, and in that case, messages that contain the
+    /// text match.
+    pub filter: String,
+    /// Whether the view scrolls to new messages as they arrive.
+    ///
+    /// This is turned off when the view is scrolled up, and back on when it is
+    /// scrolled to the bottom again.
+    pub follow: bool,
+}
+
+impl LogState {
+    /// Create a `LogState` that keeps at most `capacity` messages, and
+    /// follows new ones.
+    pub fn new(capacity: usize) -> Self {
+        LogState {
+            entries: LogBuffer::new(capacity),
+            filter: String::new(),
+            follow: true,
+        }
+    }
+}
+
+/// Decides which messages are shown.
+enum Filter {
+    All,
+    Contains(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Filter {
+    fn new(pattern: &str) -> Self {
+        if pattern.is_empty() {
+            return Filter::All;
+        }
+        #[cfg(feature = "regex")]
+        {
+            if let Ok(regex) = regex::Regex::new(pattern) {
+                return Filter::Regex(regex);
+            }
+        }
+        Filter::Contains(pattern.to_owned())
+    }
+
+    fn matches(&self, message: &str) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Contains(text) => message.contains(text.as_str()),
+            #[cfg(feature = "regex")]
+            Filter::Regex(regex) => regex.is_match(message),
+        }
+    }
+}
+
+/// A view of log messages, such as the output of a background task.
+///
+/// Each message is a line, colored by its [`LogLevel`]. Only the lines that
+/// are visible are laid out and painted, so the view stays fast with many
+/// messages. The [`LogState::filter`] is applied as it changes.
+///
+/// While [`LogState::follow`] is set, the view scrolls to new messages as they
+/// arrive. Scrolling up with the mouse wheel turns it off, so that the lines
+/// being read stay in place; scrolling back to the bottom turns it on.
+pub struct LogView {
+    font_size: f64,
+    line_height: f64,
+    filter: Filter,
+    /// The positions of the messages that match the filter, in order.
+    matches: VecDeque<usize>,
+    /// The position up to which messages have been matched against the filter.
+    matched_to: usize,
+    /// The distance from the top of the first line to the top of the view.
+    scroll_offset: f64,
+}
+
+impl LogView {
+    /// Create a new `LogView`.
+    pub fn new() -> Self {
+        LogView {
+            font_size: DEFAULT_FONT_SIZE,
+            line_height: 0.0,
+            filter: Filter::All,
+            matches: VecDeque::new(),
+            matched_to: 0,
+            scroll_offset: 0.0,
+        }
+    }
+
+    /// Builder-style method to set the size of the text.
+    ///
+    /// The default is 12.
+    pub fn with_font_size(mut self, size: f64) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    /// The number of messages that match the filter.
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Bring the list of matching messages up to date with `entries`.
+    fn sync_matches(&mut self, entries: &LogBuffer) {
+        let first = entries.first_position();
+        while self.matches.front().is_some_and(|&pos| pos < first) {
+            self.matches.pop_front();
+        }
+        for position in self.matched_to.max(first)..entries.end_position() {
+            if let Some(entry) = entries.get(position) {
+                if self.filter.matches(&entry.message) {
+                    self.matches.push_back(position);
+                }
+            }
+        }
+        self.matched_to = entries.end_position();
+    }
+
+    fn rematch(&mut self, data: &LogState) {
+        self.filter = Filter::new(&data.filter);
+        self.matches.clear();
+        self.matched_to = 0;
+        self.sync_matches(&data.entries);
+    }
+
+    fn content_height(&self) -> f64 {
+        self.matches.len() as f64 * self.line_height + PADDING * 2.0
+    }
+
+    fn max_offset(&self, viewport: Size) -> f64 {
+        (self.content_height() - viewport.height).max(0.0)
+    }
+
+    fn scroll_to_end(&mut self, viewport: Size) {
+        self.scroll_offset = self.max_offset(viewport);
+    }
+
+    fn line_color(level: LogLevel, env: &Env) -> Color {
+        match level {
+            LogLevel::Error => Color::rgb8(0xf1, 0x4c, 0x4c),
+            LogLevel::Warn => Color::rgb8(0xe5, 0xc0, 0x40),
+            LogLevel::Info => env.get(theme::TEXT_COLOR),
+            LogLevel::Debug | LogLevel::Trace => env.get(theme::DISABLED_TEXT_COLOR),
+        }
+    }
+}
+
+impl Default for LogView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<LogState> for LogView {
+    #[instrument(name = "LogView", level = "trace", skip(self, ctx, event, data, _env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut LogState, _env: &Env) {
+        if let Event::Wheel(mouse) = event {
+            let viewport = ctx.size();
+            let max = self.max_offset(viewport);
+            let offset = (self.scroll_offset + mouse.wheel_delta.y).max(0.0).min(max);
+            if offset != self.scroll_offset {
+                self.scroll_offset = offset;
+                ctx.request_paint();
+            }
+            // follow again once the bottom is reached.
+            let at_end = offset >= max;
+            if data.follow != at_end {
+                trace!("LogView follow set to {}", at_end);
+                data.follow = at_end;
+            }
+            ctx.set_handled();
+        }
+    }
+
+    #[instrument(name = "LogView", level = "trace", skip(self, _ctx, event, data, _env))]
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &LogState,
+        _env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rematch(data);
+        }
+    }
+
+    #[instrument(
+        name = "LogView",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &LogState, data: &LogState, _env: &Env) {
+        if old_data.filter != data.filter || data.entries.capacity() != old_data.entries.capacity()
+        {
+            self.rematch(data);
+        } else if !old_data.entries.same(&data.entries) {
+            // a cleared buffer starts over at a later position.
+            if data.entries.end_position() < self.matched_to {
+                self.rematch(data);
+            } else {
+                self.sync_matches(&data.entries);
+            }
+        } else if old_data.follow == data.follow {
+            return;
+        }
+
+        let viewport = ctx.size();
+        if data.follow {
+            self.scroll_to_end(viewport);
+        } else {
+            self.scroll_offset = self.scroll_offset.min(self.max_offset(viewport));
+        }
+        ctx.request_paint();
+    }
+
+    #[instrument(name = "LogView", level = "trace", skip(self, ctx, bc, data, _env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &LogState,
+        _env: &Env,
+    ) -> Size {
+        bc.debug_check("LogView");
+        match ctx
+            .text()
+            .new_text_layout("M")
+            .font(FontFamily::MONOSPACE, self.font_size)
+            .build()
+        {
+            Ok(layout) => self.line_height = layout.size().height,
+            Err(e) => warn!("failed to measure log font: {}", e),
+        }
+
+        let size = bc.constrain(DEFAULT_SIZE);
+        if data.follow {
+            self.scroll_to_end(size);
+        } else {
+            self.scroll_offset = self.scroll_offset.min(self.max_offset(size));
+        }
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "LogView", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &LogState, env: &Env) {
+        let size = ctx.size();
+        ctx.fill(size.to_rect(), &env.get(theme::BACKGROUND_DARK));
+        if self.line_height <= 0.0 || self.matches.is_empty() {
+            return;
+        }
+
+        // only the lines in the view are laid out.
+        let line_height = self.line_height;
+        let top = self.scroll_offset - PADDING;
+        let first = (top / line_height).floor().max(0.0) as usize;
+        let count = (size.height / line_height).ceil() as usize + 1;
+        ctx.with_save(|ctx| {
+            ctx.clip(size.to_rect());
+            for (idx, &position) in self.matches.iter().enumerate().skip(first).take(count) {
+                let entry = match data.entries.get(position) {
+                    Some(entry) => entry,
+                    None => continue,
+                };
+                let layout = ctx
+                    .text()
+                    .new_text_layout(entry.message.clone())
+                    .font(FontFamily::MONOSPACE, self.font_size)
+                    .text_color(Self::line_color(entry.level, env))
+                    .build();
+                match layout {
+                    Ok(layout) => {
+                        let y = idx as f64 * line_height - top;
+                        ctx.draw_text(&layout, Point::new(PADDING, y));
+                    }
+                    Err(e) => warn!("failed to build log line: {}", e),
+                }
+            }
+        });
+
+        // a thumb that shows which part of the messages is visible.
+        let content = self.content_height();
+        if content > size.height {
+            let width = env.get(theme::SCROLLBAR_WIDTH);
+            let pad = env.get(theme::SCROLLBAR_PAD);
+            let track = size.height - pad * 2.0;
+            let length = (track * size.height / content).max(SCROLLBAR_MIN_LENGTH);
+            let y = pad + (track - length) * self.scroll_offset / self.max_offset(size);
+            let x = size.width - width - pad;
+            let thumb = RoundedRect::from_rect(
+                Rect::new(x, y, x + width, y + length),
+                env.get(theme::SCROLLBAR_RADIUS),
+            );
+            ctx.fill(thumb, &env.get(theme::SCROLLBAR_COLOR));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn ring_buffer_keeps_positions() {
+        let mut buffer = LogBuffer::new(3);
+        buffer.extend((0..5).map(|i| LogEntry::new(LogLevel::Info, i.to_string())));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.first_position(), 2);
+        assert_eq!(buffer.end_position(), 5);
+        assert_eq!(buffer.get(1), None);
+        assert_eq!(&*buffer.get(4).unwrap().message, "4");
+    }
+
+    #[test]
+    fn matches_follow_the_buffer() {
+        let mut data = LogState::new(4);
+        let mut view = LogView::new();
+        data.filter = "b".into();
+        view.rematch(&data);
+        for message in &["a", "b1", "c", "b2", "b3", "d"] {
+            data.entries.push(LogEntry::new(LogLevel::Warn, *message));
+            view.sync_matches(&data.entries);
+        }
+        // "a" and "b1" were dropped.
+        assert_eq!(view.matches, vec![3, 4]);
+
+        data.filter = String::new();
+        view.rematch(&data);
+        assert_eq!(view.match_count(), 4);
+    }
+}
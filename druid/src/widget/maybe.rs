@@ -23,10 +23,19 @@ use druid::widget::SizedBox;
 
 /// A widget that switches between two possible child views, for `Data` that
 /// is `Option<T>`.
+///
+/// By default the child is rebuilt every time the data changes between `Some`
+/// and `None`; see [`retain_inactive`] to keep it instead.
+///
+/// [`retain_inactive`]: Maybe::retain_inactive
 pub struct Maybe<T> {
     some_maker: Box<dyn Fn() -> Box<dyn Widget<T>>>,
     none_maker: Box<dyn Fn() -> Box<dyn Widget<()>>>,
     widget: MaybeWidget<T>,
+    retain_inactive: bool,
+    /// The child that was switched away from, if it is retained, along with
+    /// the data it last saw if it is the `Some` child.
+    inactive: Option<(MaybeWidget<T>, Option<T>)>,
 }
 
 /// Internal widget, which is either the `Some` variant, or the `None` variant.
@@ -53,6 +62,8 @@ impl<T: Data> Maybe<T> {
             some_maker: Box::new(move || some_maker().boxed()),
             none_maker: Box::new(move || none_maker().boxed()),
             widget,
+            retain_inactive: false,
+            inactive: None,
         }
     }
 
@@ -61,13 +72,36 @@ impl<T: Data> Maybe<T> {
         Self::new(some_maker, SizedBox::empty)
     }
 
-    /// Re-create the internal widget, usually in response to the optional going `Some` -> `None`
+    /// Builder-style method to keep the child that is switched away from,
+    /// instead of dropping it and building a new one when it is needed again.
+    ///
+    /// This keeps the state of the child, like a scroll position or a text
+    /// selection, for the price of keeping both children around. While it is
+    /// not shown, the child doesn't get updates, and only sees the events that
+    /// are also sent to hidden widgets, with the data it was last shown with;
+    /// any changes it makes to that data are dropped.
+    pub fn retain_inactive(mut self, retain: bool) -> Self {
+        self.retain_inactive = retain;
+        self
+    }
+
+    /// Switch the internal widget, usually in response to the optional going `Some` -> `None`
     /// or the reverse.
-    fn rebuild_widget(&mut self, is_some: bool) {
-        if is_some {
-            self.widget = MaybeWidget::Some(WidgetPod::new((self.some_maker)()));
-        } else {
-            self.widget = MaybeWidget::None(WidgetPod::new((self.none_maker)()));
+    ///
+    /// `last_data` is the data the current child was last shown with, if known.
+    fn switch_widget(&mut self, is_some: bool, last_data: Option<&T>) {
+        if self.widget.is_some() == is_some {
+            return;
+        }
+        let widget = match self.inactive.take() {
+            Some((widget, _)) if widget.is_some() == is_some => widget,
+            _ if is_some => MaybeWidget::Some(WidgetPod::new((self.some_maker)())),
+            _ => MaybeWidget::None(WidgetPod::new((self.none_maker)())),
+        };
+        let previous = std::mem::replace(&mut self.widget, widget);
+        if self.retain_inactive && previous.is_initialized() {
+            let last_data = last_data.filter(|_| previous.is_some()).cloned();
+            self.inactive = Some((previous, last_data));
         }
     }
 }
@@ -80,6 +114,14 @@ impl<T: Data> Widget<Option<T>> for Maybe<T> {
                 None => self.widget.with_none(|w| w.event(ctx, event, &mut (), env)),
             };
         }
+        if event.should_propagate_to_hidden() {
+            if let Some((widget, last_data)) = self.inactive.as_mut() {
+                match last_data {
+                    Some(d) => widget.with_some(|w| w.event(ctx, event, &mut d.clone(), env)),
+                    None => widget.with_none(|w| w.event(ctx, event, &mut (), env)),
+                };
+            }
+        }
     }
 
     fn lifecycle(
@@ -92,19 +134,41 @@ impl<T: Data> Widget<Option<T>> for Maybe<T> {
         if data.is_some() != self.widget.is_some() {
             // possible if getting lifecycle after an event that changed the data,
             // or on WidgetAdded
-            self.rebuild_widget(data.is_some());
+            self.switch_widget(data.is_some(), None);
         }
         assert_eq!(data.is_some(), self.widget.is_some(), "{:?}", event);
         match data.as_ref() {
             Some(d) => self.widget.with_some(|w| w.lifecycle(ctx, event, d, env)),
             None => self.widget.with_none(|w| w.lifecycle(ctx, event, &(), env)),
         };
+        if event.should_propagate_to_hidden() {
+            if let Some((widget, last_data)) = self.inactive.as_mut() {
+                match last_data {
+                    Some(d) => widget.with_some(|w| w.lifecycle(ctx, event, d, env)),
+                    None => widget.with_none(|w| w.lifecycle(ctx, event, &(), env)),
+                };
+            }
+        }
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Option<T>, data: &Option<T>, env: &Env) {
         if old_data.is_some() != data.is_some() {
-            self.rebuild_widget(data.is_some());
+            self.switch_widget(data.is_some(), old_data.as_ref());
             ctx.children_changed();
+            // a retained child has already been added, and needs to catch up
+            // with the data; a new one gets the current data when it is added.
+            match data {
+                Some(new) => self.widget.with_some(|w| {
+                    if w.is_initialized() {
+                        w.update(ctx, new, env)
+                    }
+                }),
+                None => self.widget.with_none(|w| {
+                    if w.is_initialized() {
+                        w.update(ctx, &(), env)
+                    }
+                }),
+            };
         } else {
             match data {
                 Some(new) => self.widget.with_some(|w| w.update(ctx, new, env)),
@@ -152,6 +216,14 @@ impl<T> MaybeWidget<T> {
         }
     }
 
+    /// Whether the widget has been added to the tree.
+    fn is_initialized(&self) -> bool {
+        match self {
+            Self::Some(widget) => widget.is_initialized(),
+            Self::None(widget) => widget.is_initialized(),
+        }
+    }
+
     /// Lens to the `Some` variant.
     fn with_some<R, F: FnOnce(&mut WidgetPod<T, Box<dyn Widget<T>>>) -> R>(
         &mut self,
@@ -0,0 +1,220 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A scaled-down overview of a scroll area, for navigating it.
+
+use crate::kurbo::Line;
+use crate::piet::{InterpolationMode, PietImage};
+use crate::widget::prelude::*;
+use crate::widget::Scroll;
+use crate::{theme, Data, MouseButton, Point, Rect, Vec2, WidgetPod};
+use tracing::{instrument, trace};
+
+const DEFAULT_WIDTH: f64 = 120.0;
+const STRIP_PADDING: f64 = 4.0;
+
+/// A [`Scroll`] with an overview of its content next to it, such as for a
+/// node editor or a long document.
+///
+/// The overview is a strip on the right that shows a scaled-down snapshot
+/// of the whole content, with the visible part outlined. Clicking or
+/// dragging in the strip scrolls so that the visible part is centered on
+/// the mouse.
+///
+/// The snapshot is cached, and taken again when the data, the environment
+/// or the size of the content changes.
+pub struct Minimap<T, W> {
+    scroll: WidgetPod<T, Scroll<T, W>>,
+    width: f64,
+    snapshot: Option<PietImage>,
+    /// The size of the content when the snapshot was taken.
+    snapshot_size: Size,
+    /// The area of the strip, on the right of the scroll area.
+    strip: Rect,
+    /// The area within the strip where the content is drawn.
+    content_rect: Rect,
+    /// The size of the overview relative to the content.
+    scale: f64,
+}
+
+impl<T: Data, W: Widget<T>> Minimap<T, W> {
+    /// Create a new `Minimap` showing the content of `scroll`.
+    pub fn new(scroll: Scroll<T, W>) -> Self {
+        Minimap {
+            scroll: WidgetPod::new(scroll),
+            width: DEFAULT_WIDTH,
+            snapshot: None,
+            snapshot_size: Size::ZERO,
+            strip: Rect::ZERO,
+            content_rect: Rect::ZERO,
+            scale: 1.0,
+        }
+    }
+
+    /// Builder-style method to set the width of the overview strip.
+    ///
+    /// The default is 120.
+    pub fn with_width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Returns a reference to the scroll area.
+    pub fn scroll(&self) -> &Scroll<T, W> {
+        self.scroll.widget()
+    }
+
+    /// Returns a mutable reference to the scroll area.
+    pub fn scroll_mut(&mut self) -> &mut Scroll<T, W> {
+        self.scroll.widget_mut()
+    }
+
+    /// The outline of the visible part of the content, in the strip.
+    fn viewport_outline(&self) -> Rect {
+        let viewport = self.scroll.widget().viewport_rect();
+        let origin = self.content_rect.origin() + viewport.origin().to_vec2() * self.scale;
+        Rect::from_origin_size(origin, viewport.size() * self.scale).intersect(self.content_rect)
+    }
+
+    /// Scroll so that the visible part is centered on `pos`, in the strip.
+    fn navigate(&mut self, ctx: &mut EventCtx, pos: Point) {
+        if self.scale <= 0.0 {
+            return;
+        }
+        let target = ((pos - self.content_rect.origin()) / self.scale).to_point();
+        let viewport = self.scroll.widget().viewport_rect();
+        let delta: Vec2 = target - viewport.center();
+        if self.scroll.widget_mut().scroll_by(delta) {
+            trace!("Minimap scrolled by {}", delta);
+            ctx.request_paint();
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for Minimap<T, W> {
+    #[instrument(name = "Minimap", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseDown(mouse)
+                if mouse.button == MouseButton::Left && self.strip.contains(mouse.pos) =>
+            {
+                ctx.set_active(true);
+                self.navigate(ctx, mouse.pos);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                self.navigate(ctx, mouse.pos);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.set_handled();
+                return;
+            }
+            _ => (),
+        }
+        self.scroll.event(ctx, event, data, env);
+    }
+
+    #[instrument(name = "Minimap", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.scroll.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "Minimap",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if !old_data.same(data) || ctx.env_changed() {
+            self.snapshot = None;
+            ctx.request_paint();
+        }
+        self.scroll.update(ctx, data, env);
+    }
+
+    #[instrument(name = "Minimap", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Minimap");
+        let scroll_bc = bc.shrink((self.width, 0.0));
+        let scroll_size = self.scroll.layout(ctx, &scroll_bc, data, env);
+        self.scroll.set_origin(ctx, data, env, Point::ORIGIN);
+        let size = bc.constrain(Size::new(
+            scroll_size.width + self.width,
+            scroll_size.height,
+        ));
+
+        self.strip = Rect::new(scroll_size.width, 0.0, size.width, size.height);
+        let available = self.strip.inset(-STRIP_PADDING);
+        let content_size = self.scroll.widget().child_size();
+        self.scale = if content_size.width > 0.0 && content_size.height > 0.0 {
+            (available.width() / content_size.width)
+                .min(available.height() / content_size.height)
+                .max(0.0)
+        } else {
+            0.0
+        };
+        // the content is centered horizontally and starts at the top.
+        let content_width = content_size.width * self.scale;
+        let origin = Point::new(available.center().x - content_width / 2.0, available.y0);
+        self.content_rect =
+            Rect::from_origin_size(origin, (content_width, content_size.height * self.scale));
+
+        if content_size != self.snapshot_size {
+            self.snapshot = None;
+        }
+
+        trace!("Computed layout: size={}, scale={}", size, self.scale);
+        size
+    }
+
+    #[instrument(name = "Minimap", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.scroll.paint(ctx, data, env);
+
+        ctx.fill(self.strip, &env.get(theme::BACKGROUND_DARK));
+        let border_x = self.strip.x0 + 0.5;
+        let border = Line::new((border_x, 0.0), (border_x, self.strip.y1));
+        ctx.stroke(border, &env.get(theme::BORDER_DARK), 1.0);
+
+        if self.scale <= 0.0 {
+            return;
+        }
+
+        if self.snapshot.is_none() {
+            let device_scale = ctx
+                .window()
+                .get_scale()
+                .map(|scale| scale.x().max(scale.y()))
+                .unwrap_or(1.0);
+            let scale = self.scale * device_scale;
+            self.snapshot = self
+                .scroll
+                .widget_mut()
+                .content_snapshot(ctx, data, env, scale);
+            self.snapshot_size = self.scroll.widget().child_size();
+        }
+        if let Some(snapshot) = &self.snapshot {
+            ctx.draw_image(snapshot, self.content_rect, InterpolationMode::Bilinear);
+        }
+
+        let outline = self.viewport_outline();
+        let color = env.get(theme::PRIMARY_LIGHT);
+        ctx.fill(outline, &color.clone().with_alpha(0.2));
+        ctx.stroke(outline.inset(-0.5), &color, 1.0);
+    }
+}
@@ -19,89 +19,175 @@
 mod widget_wrapper;
 
 mod added;
+mod alert;
 mod align;
 mod aspect_ratio_box;
+#[cfg(feature = "image")]
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+mod async_image;
+mod avatar;
+mod badge;
+mod breadcrumbs;
 mod button;
+mod cache_layer;
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+mod calendar;
 mod checkbox;
+#[cfg(feature = "im")]
+mod chip_input;
+mod circular_progress;
 mod click;
 mod clip_box;
 mod common;
 mod container;
 mod controller;
+mod crop_overlay;
 mod disable_if;
+mod dock;
 mod either;
 mod env_scope;
+mod error_boundary;
+mod expander;
 mod flex;
+#[cfg(feature = "raw-win-handle")]
+mod foreign_window_host;
+mod form;
+mod gauge;
+mod gradient_editor;
 mod identity_wrapper;
 mod image;
+mod image_viewer;
+mod inspect_data;
 mod invalidation;
 mod label;
 mod lens_wrap;
 mod list;
+mod live_region;
+mod log_view;
 mod maybe;
+mod minimap;
+mod native_surface;
+mod node_graph;
 mod padding;
 mod painter;
 mod parse;
 mod progress_bar;
+mod property_grid;
 mod radio;
+mod rating;
 mod scope;
 mod scroll;
 mod sized_box;
 mod slider;
+mod spatial_navigation;
 mod spinner;
 mod split;
+mod status_bar;
 mod stepper;
+mod steps;
 #[cfg(feature = "svg")]
 #[cfg_attr(docsrs, doc(cfg(feature = "svg")))]
 mod svg;
+mod swatch_grid;
 mod switch;
 mod tabs;
+mod terminal;
 mod textbox;
+mod timeline;
+mod toolbar;
+mod tour;
 mod value_textbox;
+#[cfg(not(target_arch = "wasm32"))]
+mod video;
 mod view_switcher;
+mod web_view;
 #[allow(clippy::module_inception)]
 mod widget;
 mod widget_ext;
 
 pub use self::image::Image;
 pub use added::Added;
+pub use alert::{Alert, Severity};
 pub use align::Align;
 pub use aspect_ratio_box::AspectRatioBox;
+#[cfg(feature = "image")]
+pub use async_image::AsyncImage;
+pub use avatar::{Avatar, Presence};
+pub use badge::Badge;
+pub use breadcrumbs::Breadcrumbs;
 pub use button::Button;
+pub use cache_layer::CacheLayer;
+#[cfg(feature = "chrono")]
+pub use calendar::CalendarView;
 pub use checkbox::Checkbox;
+#[cfg(feature = "im")]
+pub use chip_input::ChipInput;
+pub use circular_progress::CircularProgress;
 pub use click::Click;
 pub use clip_box::{ClipBox, Viewport};
 pub use common::FillStrat;
 pub use container::Container;
 pub use controller::{Controller, ControllerHost};
+pub use crop_overlay::CropOverlay;
 pub use disable_if::DisabledIf;
+pub use dock::{Dock, DockEdge, DockLayout, DockLayoutParseError};
 pub use either::Either;
 pub use env_scope::EnvScope;
+pub use error_boundary::ErrorBoundary;
+pub use expander::{Accordion, Expander};
 pub use flex::{Axis, CrossAxisAlignment, Flex, FlexParams, MainAxisAlignment};
+#[cfg(feature = "raw-win-handle")]
+pub use foreign_window_host::ForeignWindowHost;
+pub use form::Form;
+pub use gauge::Gauge;
+pub use gradient_editor::GradientEditor;
 pub use identity_wrapper::IdentityWrapper;
+pub use image_viewer::{ImageViewer, ZoomMode};
+pub use inspect_data::InspectData;
 pub use label::{Label, LabelText, LineBreaking, RawLabel};
 pub use lens_wrap::LensWrap;
 pub use list::{List, ListIter};
+pub use live_region::LiveRegion;
+pub use log_view::{LogBuffer, LogEntry, LogLevel, LogState, LogView};
 pub use maybe::Maybe;
+pub use minimap::Minimap;
+pub use native_surface::{NativeSurface, SurfaceContent, SurfacePlacement};
+pub use node_graph::{Connection, Graph, GraphNode, NodeGraph, PortId};
 pub use padding::Padding;
 pub use painter::{BackgroundBrush, Painter};
 pub use parse::Parse;
 pub use progress_bar::ProgressBar;
+pub use property_grid::{PropertyGrid, PropertyValue};
 pub use radio::{Radio, RadioGroup};
+pub use rating::{Rating, RatingValue};
 pub use scope::{DefaultScopePolicy, LensScopeTransfer, Scope, ScopePolicy, ScopeTransfer};
 pub use scroll::Scroll;
 pub use sized_box::SizedBox;
 pub use slider::Slider;
+pub use spatial_navigation::SpatialNavigation;
 pub use spinner::Spinner;
 pub use split::Split;
+pub use status_bar::{StatusBar, StatusMessage};
 pub use stepper::Stepper;
+pub use steps::Steps;
 #[cfg(feature = "svg")]
 pub use svg::{Svg, SvgData};
+pub use swatch_grid::SwatchGrid;
 pub use switch::Switch;
 pub use tabs::{TabInfo, Tabs, TabsEdge, TabsPolicy, TabsState, TabsTransition};
+pub use terminal::{PtyBackend, TerminalView};
 pub use textbox::TextBox;
+pub use timeline::{Timeline, TimelineData, TimelineItem};
+pub use toolbar::Toolbar;
+pub use tour::{TourController, TourStep};
 pub use value_textbox::{TextBoxEvent, ValidationDelegate, ValueTextBox};
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "image", feature = "gif"))]
+pub use video::GifDecoder;
+pub use video::{Playback, Video, VideoDecoder, VideoFrame};
 pub use view_switcher::ViewSwitcher;
+pub use web_view::{WebView, WebViewBackend, WebViewMessenger};
 #[doc(hidden)]
 pub use widget::{Widget, WidgetId};
 #[doc(hidden)]
@@ -0,0 +1,210 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A region of the window that is rendered by something other than piet.
+
+use crate::widget::prelude::*;
+use crate::{Data, InternalLifeCycle, Rect, Scalable, Scale, WindowHandle};
+use tracing::{instrument, warn};
+
+const DEFAULT_SIZE: Size = Size::new(300.0, 150.0);
+
+/// Where a [`NativeSurface`] is in its window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfacePlacement {
+    /// The area covered by the surface, in window coordinates (display points).
+    pub rect: Rect,
+    /// The scale of the window.
+    pub scale: Scale,
+}
+
+impl SurfacePlacement {
+    /// The area covered by the surface, in window coordinates (pixels).
+    pub fn pixel_rect(&self) -> Rect {
+        self.rect.to_px(self.scale)
+    }
+}
+
+/// The content of a [`NativeSurface`].
+///
+/// This is where a renderer that druid doesn't know about (such as wgpu or
+/// OpenGL) gets hooked up: it is told where the surface is, gets the input
+/// for it, and can paint a fallback with piet.
+pub trait SurfaceContent<T> {
+    /// Called when the surface is first placed in the window, and whenever it
+    /// moves, changes size, or the scale of the window changes.
+    ///
+    /// The [`WindowHandle`] implements `HasRawWindowHandle` when the
+    /// `raw-win-handle` feature is enabled, which is what most graphics
+    /// libraries need to create a surface or a child window. With that
+    /// feature, `WindowHandle::create_render_surface` creates a child window
+    /// that druid never paints over; move it to `placement.rect` here with
+    /// `RenderSurface::set_frame`.
+    fn placed(&mut self, window: &WindowHandle, placement: SurfacePlacement);
+
+    /// Handle an event. Mouse positions are relative to the surface.
+    ///
+    /// The surface becomes active when it is clicked, so that it keeps
+    /// getting mouse events while the button is held down, and it takes
+    /// focus, so that it gets keyboard events.
+    #[allow(unused_variables)]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {}
+
+    /// Called when the data changes.
+    #[allow(unused_variables)]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {}
+
+    /// Paint with piet, below whatever the native renderer draws.
+    ///
+    /// This can be used to draw something while the native surface is not
+    /// ready yet. By default nothing is painted.
+    #[allow(unused_variables)]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {}
+}
+
+/// A widget that reserves part of the window for a native renderer.
+///
+/// `NativeSurface` takes up its preferred size, within the constraints it
+/// is given, and keeps its [`SurfaceContent`] informed of where that space is in the window, in both
+/// display points and pixels. Input within the surface is passed on to the
+/// content.
+///
+/// Druid does not composite native surfaces itself: the content is
+/// responsible for presenting its frames in the given area, for instance by
+/// presenting into a `RenderSurface` at that position, or by drawing into
+/// the window after druid has painted it.
+pub struct NativeSurface<C> {
+    content: C,
+    preferred_size: Size,
+    placement: Option<SurfacePlacement>,
+}
+
+impl<C> NativeSurface<C> {
+    /// Create a new `NativeSurface` with the given content.
+    pub fn new(content: C) -> Self {
+        NativeSurface {
+            content,
+            preferred_size: DEFAULT_SIZE,
+            placement: None,
+        }
+    }
+
+    /// Builder-style method for setting the size the surface would like to
+    /// have.
+    ///
+    /// The default is 300 by 150. Use `expand` to make the surface fill all
+    /// the space it is given instead.
+    pub fn with_preferred_size(mut self, size: impl Into<Size>) -> Self {
+        self.preferred_size = size.into();
+        self
+    }
+
+    /// The content of the surface.
+    pub fn content(&self) -> &C {
+        &self.content
+    }
+
+    /// The content of the surface, mutably.
+    pub fn content_mut(&mut self) -> &mut C {
+        &mut self.content
+    }
+
+    /// Where the surface was last placed, if it has been placed yet.
+    pub fn placement(&self) -> Option<SurfacePlacement> {
+        self.placement
+    }
+
+    /// Record where the surface is, returning the new placement if it changed.
+    fn set_placement(&mut self, window: &WindowHandle, rect: Rect) -> Option<SurfacePlacement> {
+        let scale = match window.get_scale() {
+            Ok(scale) => scale,
+            Err(e) => {
+                warn!("could not get the scale of the window: {}", e);
+                Scale::default()
+            }
+        };
+        let placement = SurfacePlacement { rect, scale };
+        if self.placement == Some(placement) {
+            return None;
+        }
+        self.placement = Some(placement);
+        Some(placement)
+    }
+}
+
+impl<T: Data, C: SurfaceContent<T>> Widget<T> for NativeSurface<C> {
+    #[instrument(
+        name = "NativeSurface",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseDown(_) => {
+                ctx.set_active(true);
+                ctx.request_focus();
+            }
+            Event::MouseUp(_) => ctx.set_active(false),
+            _ => (),
+        }
+        self.content.event(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "NativeSurface",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, _env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => ctx.register_for_focus(),
+            LifeCycle::Size(_) | LifeCycle::Internal(InternalLifeCycle::ParentWindowOrigin) => {
+                let rect = Rect::from_origin_size(ctx.window_origin(), ctx.size());
+                if let Some(placement) = self.set_placement(ctx.window(), rect) {
+                    self.content.placed(ctx.window(), placement);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "NativeSurface",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.content.update(ctx, old_data, data, env);
+    }
+
+    #[instrument(
+        name = "NativeSurface",
+        level = "trace",
+        skip(self, _ctx, bc, _data, _env)
+    )]
+    fn layout(&mut self, _ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, _env: &Env) -> Size {
+        bc.constrain(self.preferred_size)
+    }
+
+    #[instrument(name = "NativeSurface", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        // the scale is not part of the layout, so a change in scale is only
+        // noticed here.
+        let rect = Rect::from_origin_size(ctx.window_origin(), ctx.size());
+        if let Some(placement) = self.set_placement(ctx.window(), rect) {
+            self.content.placed(ctx.window(), placement);
+        }
+        self.content.paint(ctx, data, env);
+    }
+}
@@ -0,0 +1,670 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An editor for graphs of nodes connected by wires.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::commands::{CONNECT_PORTS, DISCONNECT_PORTS};
+use crate::core::WidgetState;
+use crate::kurbo::{Affine, Circle, CubicBez};
+use crate::widget::prelude::*;
+use crate::{theme, Data, MouseButton, Point, Rect, Vec2, WidgetPod};
+use tracing::{instrument, trace};
+
+const NODE_PADDING: f64 = 8.0;
+const PORT_RADIUS: f64 = 5.0;
+/// How far from a port a click still hits it.
+const PORT_HIT_RADIUS: f64 = 8.0;
+/// The smallest horizontal distance of the control points of a wire.
+const MIN_WIRE_HANDLE: f64 = 40.0;
+const MIN_ZOOM: f64 = 0.25;
+const MAX_ZOOM: f64 = 4.0;
+/// How much the zoom changes per unit of wheel delta.
+const ZOOM_SPEED: f64 = 0.002;
+const DEFAULT_SIZE: Size = Size::new(400.0, 300.0);
+
+/// A port of a node in a [`Graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Data)]
+pub struct PortId {
+    /// The index of the node.
+    pub node: usize,
+    /// The index of the input or output on the node.
+    pub port: usize,
+}
+
+/// A wire from an output of one node to an input of another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Data)]
+pub struct Connection {
+    /// The output the wire starts at.
+    pub from: PortId,
+    /// The input the wire ends at.
+    pub to: PortId,
+}
+
+/// A node in a [`Graph`].
+#[derive(Debug, Clone, Data)]
+pub struct GraphNode<N> {
+    /// The position of the top left corner of the node.
+    pub position: Point,
+    /// The number of inputs, shown on the left of the node.
+    pub inputs: usize,
+    /// The number of outputs, shown on the right of the node.
+    pub outputs: usize,
+    /// Whether the node is selected.
+    pub selected: bool,
+    /// The data of the node widget.
+    pub data: N,
+}
+
+impl<N> GraphNode<N> {
+    /// Create a new node at `position`, without inputs or outputs.
+    pub fn new(position: impl Into<Point>, data: N) -> Self {
+        GraphNode {
+            position: position.into(),
+            inputs: 0,
+            outputs: 0,
+            selected: false,
+            data,
+        }
+    }
+
+    /// Builder-style method to set the number of inputs.
+    pub fn with_inputs(mut self, inputs: usize) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Builder-style method to set the number of outputs.
+    pub fn with_outputs(mut self, outputs: usize) -> Self {
+        self.outputs = outputs;
+        self
+    }
+}
+
+/// The nodes and connections shown by a [`NodeGraph`].
+#[derive(Debug, Clone, Data)]
+pub struct Graph<N> {
+    /// The nodes; later nodes are drawn on top of earlier ones.
+    pub nodes: Arc<Vec<GraphNode<N>>>,
+    /// The connections between the nodes.
+    pub connections: Arc<Vec<Connection>>,
+}
+
+impl<N: Clone> Graph<N> {
+    /// Create a new, empty `Graph`.
+    pub fn new() -> Self {
+        Graph {
+            nodes: Arc::new(Vec::new()),
+            connections: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Add a node, returning its index.
+    pub fn add_node(&mut self, node: GraphNode<N>) -> usize {
+        let nodes = Arc::make_mut(&mut self.nodes);
+        nodes.push(node);
+        nodes.len() - 1
+    }
+
+    /// Remove the node at `index`, along with its connections.
+    ///
+    /// The nodes after it move down by one, and the connections are updated
+    /// to match.
+    pub fn remove_node(&mut self, index: usize) {
+        Arc::make_mut(&mut self.nodes).remove(index);
+        let connections = Arc::make_mut(&mut self.connections);
+        connections.retain(|c| c.from.node != index && c.to.node != index);
+        for connection in connections.iter_mut() {
+            for port in &mut [&mut connection.from, &mut connection.to] {
+                if port.node > index {
+                    port.node -= 1;
+                }
+            }
+        }
+    }
+
+    /// Add a connection, unless it already exists.
+    ///
+    /// This can be called when handling [`CONNECT_PORTS`].
+    ///
+    /// [`CONNECT_PORTS`]: crate::commands::CONNECT_PORTS
+    pub fn connect(&mut self, connection: Connection) {
+        if !self.connections.contains(&connection) {
+            Arc::make_mut(&mut self.connections).push(connection);
+        }
+    }
+
+    /// Remove a connection.
+    ///
+    /// This can be called when handling [`DISCONNECT_PORTS`].
+    ///
+    /// [`DISCONNECT_PORTS`]: crate::commands::DISCONNECT_PORTS
+    pub fn disconnect(&mut self, connection: Connection) {
+        if self.connections.contains(&connection) {
+            Arc::make_mut(&mut self.connections).retain(|c| *c != connection);
+        }
+    }
+
+    /// The indices of the selected nodes.
+    pub fn selected(&self) -> impl Iterator<Item = usize> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.selected)
+            .map(|(index, _)| index)
+    }
+}
+
+impl<N: Clone> Default for Graph<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the mouse is doing while it is pressed.
+enum Drag {
+    /// Panning the view; the last mouse position, in view coordinates.
+    Pan(Point),
+    /// Moving the selected nodes; the last mouse position, in graph
+    /// coordinates.
+    Move(Point),
+    /// Selecting nodes in a rectangle, in view coordinates.
+    Select { start: Point, end: Point },
+    /// Dragging a wire from an output to the mouse, in graph coordinates.
+    Connect { from: PortId, to: Point },
+}
+
+/// An editor for a [`Graph`] of nodes connected by wires, such as a shader
+/// or audio graph.
+///
+/// Each node is shown by a widget built by the closure passed to
+/// [`NodeGraph::new`], in a frame with its inputs on the left and its
+/// outputs on the right.
+///
+/// - Dragging a node moves it, along with the other selected nodes.
+///   Clicking a node selects it; shift-clicking adds it to the selection.
+/// - Dragging on the background selects the nodes in a rectangle.
+/// - Dragging with the middle button, or scrolling, pans the view;
+///   scrolling with ctrl held zooms around the mouse.
+/// - Dragging from an output to an input submits [`CONNECT_PORTS`], and
+///   dragging a wire away from its input submits [`DISCONNECT_PORTS`].
+///
+/// The editor does not change the connections itself: handle these
+/// notifications in a [`Controller`], for example with
+/// [`Graph::connect`] and [`Graph::disconnect`], so that connections
+/// can be checked first.
+///
+/// [`CONNECT_PORTS`]: crate::commands::CONNECT_PORTS
+/// [`DISCONNECT_PORTS`]: crate::commands::DISCONNECT_PORTS
+/// [`Controller`]: crate::widget::Controller
+pub struct NodeGraph<N> {
+    closure: Box<dyn Fn() -> Box<dyn Widget<N>>>,
+    nodes: Vec<WidgetPod<N, Box<dyn Widget<N>>>>,
+    pan: Vec2,
+    zoom: f64,
+    drag: Option<Drag>,
+}
+
+impl<N: Data> NodeGraph<N> {
+    /// Create a new `NodeGraph`. The closure is called to build the widget
+    /// for each node.
+    pub fn new<W: Widget<N> + 'static>(closure: impl Fn() -> W + 'static) -> Self {
+        NodeGraph {
+            closure: Box::new(move || Box::new(closure())),
+            nodes: Vec::new(),
+            pan: Vec2::ZERO,
+            zoom: 1.0,
+            drag: None,
+        }
+    }
+
+    /// The current zoom factor.
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Set the zoom factor, keeping the center of the view in place.
+    pub fn set_zoom(&mut self, ctx: &mut EventCtx, zoom: f64) {
+        let center = ctx.size().to_rect().center();
+        self.zoom_around(center, zoom / self.zoom);
+        ctx.request_paint();
+    }
+
+    /// The offset of the graph in the view, in view coordinates.
+    pub fn pan(&self) -> Vec2 {
+        self.pan
+    }
+
+    /// Set the offset of the graph in the view.
+    pub fn set_pan(&mut self, ctx: &mut EventCtx, pan: Vec2) {
+        self.pan = pan;
+        ctx.request_paint();
+    }
+
+    /// Create or remove node widgets to match the data.
+    ///
+    /// Returns `true` if widgets were added or removed.
+    fn update_child_count(&mut self, data: &Graph<N>) -> bool {
+        let len = self.nodes.len();
+        match len.cmp(&data.nodes.len()) {
+            Ordering::Greater => self.nodes.truncate(data.nodes.len()),
+            Ordering::Less => {
+                for _ in len..data.nodes.len() {
+                    self.nodes.push(WidgetPod::new((self.closure)()));
+                }
+            }
+            Ordering::Equal => (),
+        }
+        len != data.nodes.len()
+    }
+
+    /// The transform from graph to view coordinates.
+    fn transform(&self) -> Affine {
+        Affine::translate(self.pan) * Affine::scale(self.zoom)
+    }
+
+    fn to_graph(&self, pos: Point) -> Point {
+        ((pos - self.pan).to_vec2() / self.zoom).to_point()
+    }
+
+    fn zoom_around(&mut self, pos: Point, factor: f64) {
+        let anchor = self.to_graph(pos);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan = (pos - anchor.to_vec2() * self.zoom).to_vec2();
+    }
+
+    /// Map the mouse position of an event into graph coordinates.
+    fn to_graph_event(&self, event: &Event) -> Event {
+        let mut event = event.clone();
+        match &mut event {
+            Event::MouseDown(mouse)
+            | Event::MouseUp(mouse)
+            | Event::MouseMove(mouse)
+            | Event::Wheel(mouse) => mouse.pos = self.to_graph(mouse.pos),
+            _ => (),
+        }
+        event
+    }
+
+    /// The frame of the node at `index`, in graph coordinates.
+    fn frame_rect(&self, index: usize) -> Rect {
+        self.nodes[index].layout_rect().inset(NODE_PADDING)
+    }
+
+    /// The position of a port, in graph coordinates.
+    fn port_position(&self, data: &Graph<N>, port: PortId, input: bool) -> Option<Point> {
+        let node = data.nodes.get(port.node)?;
+        let child = self.nodes.get(port.node)?;
+        let count = if input { node.inputs } else { node.outputs };
+        if port.port >= count {
+            return None;
+        }
+        let frame = child.layout_rect().inset(NODE_PADDING);
+        let y = frame.y0 + frame.height() * (port.port + 1) as f64 / (count + 1) as f64;
+        let x = if input { frame.x0 } else { frame.x1 };
+        Some(Point::new(x, y))
+    }
+
+    /// The input or output at `pos`, in graph coordinates.
+    fn port_at(&self, data: &Graph<N>, pos: Point, input: bool) -> Option<PortId> {
+        let len = self.nodes.len().min(data.nodes.len());
+        (0..len).rev().find_map(|node| {
+            let count = if input {
+                data.nodes[node].inputs
+            } else {
+                data.nodes[node].outputs
+            };
+            (0..count).map(|port| PortId { node, port }).find(|&port| {
+                self.port_position(data, port, input)
+                    .is_some_and(|center| center.distance(pos) <= PORT_HIT_RADIUS)
+            })
+        })
+    }
+
+    /// The topmost node at `pos`, in graph coordinates.
+    fn node_at(&self, data: &Graph<N>, pos: Point) -> Option<usize> {
+        let len = self.nodes.len().min(data.nodes.len());
+        (0..len)
+            .rev()
+            .find(|&index| self.frame_rect(index).contains(pos))
+    }
+}
+
+/// Set the selection state of every node, leaving the data untouched if
+/// nothing changes.
+fn select_nodes<N: Data>(data: &mut Graph<N>, select: impl Fn(usize, &GraphNode<N>) -> bool) {
+    let changed = data
+        .nodes
+        .iter()
+        .enumerate()
+        .any(|(index, node)| select(index, node) != node.selected);
+    if changed {
+        for (index, node) in Arc::make_mut(&mut data.nodes).iter_mut().enumerate() {
+            node.selected = select(index, node);
+        }
+    }
+}
+
+/// The wire between two ports, in graph coordinates.
+fn wire(from: Point, to: Point) -> CubicBez {
+    let handle = Vec2::new(((to.x - from.x).abs() / 2.0).max(MIN_WIRE_HANDLE), 0.0);
+    CubicBez::new(from, from + handle, to - handle, to)
+}
+
+/// The node widgets don't know that they are panned and zoomed, so the
+/// areas they invalidate are in graph coordinates; repaint the whole view
+/// instead.
+fn repaint_if_invalid(state: &mut WidgetState) {
+    if !state.invalid.is_empty() {
+        let rect = state.size().to_rect();
+        state.invalid.set_rect(rect);
+    }
+}
+
+impl<N: Data> Widget<Graph<N>> for NodeGraph<N> {
+    #[instrument(name = "NodeGraph", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Graph<N>, env: &Env) {
+        if self.drag.is_none() {
+            let graph_event = self.to_graph_event(event);
+            for (index, child) in self.nodes.iter_mut().enumerate() {
+                if let Some(node) = data.nodes.get(index) {
+                    let mut node_data = node.data.clone();
+                    child.event(ctx, &graph_event, &mut node_data, env);
+                    if !node.data.same(&node_data) {
+                        Arc::make_mut(&mut data.nodes)[index].data = node_data;
+                    }
+                }
+            }
+            repaint_if_invalid(ctx.widget_state);
+            if ctx.is_handled() || self.nodes.iter().any(|child| child.has_active()) {
+                return;
+            }
+        }
+
+        match event {
+            Event::MouseDown(mouse) => {
+                let pos = self.to_graph(mouse.pos);
+                self.drag = if mouse.button == MouseButton::Middle {
+                    Some(Drag::Pan(mouse.pos))
+                } else if mouse.button != MouseButton::Left {
+                    None
+                } else if let Some(from) = self.port_at(data, pos, false) {
+                    Some(Drag::Connect { from, to: pos })
+                } else if let Some(to) = self.port_at(data, pos, true) {
+                    // pick up the wire that ends at this input, if any.
+                    let connection = data.connections.iter().rev().find(|c| c.to == to).copied();
+                    connection.map(|connection| {
+                        ctx.submit_notification(DISCONNECT_PORTS.with(connection));
+                        Drag::Connect {
+                            from: connection.from,
+                            to: pos,
+                        }
+                    })
+                } else if let Some(index) = self.node_at(data, pos) {
+                    if mouse.mods.shift() {
+                        select_nodes(data, |i, node| node.selected != (i == index));
+                    } else if !data.nodes[index].selected {
+                        select_nodes(data, |i, _| i == index);
+                    }
+                    Some(Drag::Move(pos))
+                } else {
+                    if !mouse.mods.shift() {
+                        select_nodes(data, |_, _| false);
+                    }
+                    Some(Drag::Select {
+                        start: mouse.pos,
+                        end: mouse.pos,
+                    })
+                };
+                if self.drag.is_some() {
+                    ctx.set_active(true);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                let pos = self.to_graph(mouse.pos);
+                match &mut self.drag {
+                    Some(Drag::Pan(last)) => {
+                        self.pan += mouse.pos - *last;
+                        *last = mouse.pos;
+                    }
+                    Some(Drag::Move(last)) => {
+                        let delta = pos - *last;
+                        *last = pos;
+                        for node in Arc::make_mut(&mut data.nodes).iter_mut() {
+                            if node.selected {
+                                node.position += delta;
+                            }
+                        }
+                        ctx.request_layout();
+                    }
+                    Some(Drag::Select { end, .. }) => *end = mouse.pos,
+                    Some(Drag::Connect { to, .. }) => *to = pos,
+                    None => (),
+                }
+                ctx.request_paint();
+            }
+            Event::MouseUp(mouse) if ctx.is_active() => {
+                let pos = self.to_graph(mouse.pos);
+                match self.drag.take() {
+                    Some(Drag::Select { start, end }) => {
+                        let band = Rect::from_points(self.to_graph(start), self.to_graph(end));
+                        let len = self.nodes.len().min(data.nodes.len());
+                        let hits: Vec<bool> = (0..len)
+                            .map(|index| self.frame_rect(index).intersect(band).area() > 0.0)
+                            .collect();
+                        select_nodes(data, |index, node| {
+                            node.selected || hits.get(index).copied().unwrap_or(false)
+                        });
+                    }
+                    Some(Drag::Connect { from, .. }) => {
+                        if let Some(to) = self.port_at(data, pos, true) {
+                            if to.node != from.node {
+                                trace!("NodeGraph connecting {:?} to {:?}", from, to);
+                                ctx.submit_notification(
+                                    CONNECT_PORTS.with(Connection { from, to }),
+                                );
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                ctx.set_active(false);
+                ctx.request_paint();
+            }
+            Event::Wheel(mouse) => {
+                if mouse.mods.ctrl() {
+                    self.zoom_around(mouse.pos, (-mouse.wheel_delta.y * ZOOM_SPEED).exp());
+                } else {
+                    self.pan -= mouse.scroll_delta(env);
+                }
+                ctx.set_handled();
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(name = "NodeGraph", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &Graph<N>, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            if self.update_child_count(data) {
+                ctx.children_changed();
+            }
+        }
+        for (child, node) in self.nodes.iter_mut().zip(data.nodes.iter()) {
+            child.lifecycle(ctx, event, &node.data, env);
+        }
+        repaint_if_invalid(ctx.widget_state);
+    }
+
+    #[instrument(
+        name = "NodeGraph",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Graph<N>, data: &Graph<N>, env: &Env) {
+        for (child, node) in self.nodes.iter_mut().zip(data.nodes.iter()) {
+            child.update(ctx, &node.data, env);
+        }
+        if self.update_child_count(data) {
+            ctx.children_changed();
+        }
+        if !old_data.same(data) {
+            ctx.request_layout();
+        }
+        repaint_if_invalid(ctx.widget_state);
+    }
+
+    #[instrument(name = "NodeGraph", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &Graph<N>,
+        env: &Env,
+    ) -> Size {
+        bc.debug_check("NodeGraph");
+        for (child, node) in self.nodes.iter_mut().zip(data.nodes.iter()) {
+            child.layout(ctx, &BoxConstraints::UNBOUNDED, &node.data, env);
+            let origin = node.position + Vec2::new(NODE_PADDING, NODE_PADDING);
+            child.set_origin(ctx, &node.data, env, origin);
+        }
+
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            DEFAULT_SIZE.width
+        };
+        let height = if bc.is_height_bounded() {
+            bc.max().height
+        } else {
+            DEFAULT_SIZE.height
+        };
+        let size = bc.constrain(Size::new(width, height));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "NodeGraph", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Graph<N>, env: &Env) {
+        let view = ctx.size().to_rect();
+        ctx.clip(view);
+        ctx.fill(view, &env.get(theme::BACKGROUND_DARK));
+
+        let transform = self.transform();
+        let visible = transform
+            .inverse()
+            .transform_rect_bbox(ctx.region().bounding_box());
+        let wire_color = env.get(theme::BORDER_LIGHT);
+        let selected_color = env.get(theme::PRIMARY_LIGHT);
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        ctx.with_save(|ctx| {
+            ctx.transform(transform);
+            ctx.with_child_ctx(visible, |ctx| {
+                for connection in data.connections.iter() {
+                    let from = self.port_position(data, connection.from, false);
+                    let to = self.port_position(data, connection.to, true);
+                    if let (Some(from), Some(to)) = (from, to) {
+                        ctx.stroke(wire(from, to), &wire_color, 2.0);
+                    }
+                }
+
+                for (child, node) in self.nodes.iter_mut().zip(data.nodes.iter()) {
+                    let frame = child.layout_rect().inset(NODE_PADDING);
+                    let frame_shape = frame.to_rounded_rect(radius);
+                    ctx.fill(frame_shape, &env.get(theme::BACKGROUND_LIGHT));
+                    if node.selected {
+                        ctx.stroke(frame_shape, &selected_color, 2.0);
+                    } else {
+                        ctx.stroke(frame_shape, &env.get(theme::BORDER_DARK), 1.0);
+                    }
+                    child.paint(ctx, &node.data, env);
+
+                    for (count, x) in &[(node.inputs, frame.x0), (node.outputs, frame.x1)] {
+                        for port in 0..*count {
+                            let y =
+                                frame.y0 + frame.height() * (port + 1) as f64 / (count + 1) as f64;
+                            let circle = Circle::new((*x, y), PORT_RADIUS);
+                            ctx.fill(circle, &wire_color);
+                            ctx.stroke(circle, &env.get(theme::BORDER_DARK), 1.0);
+                        }
+                    }
+                }
+
+                if let Some(Drag::Connect { from, to }) = &self.drag {
+                    if let Some(from) = self.port_position(data, *from, false) {
+                        ctx.stroke(wire(from, *to), &selected_color, 2.0);
+                    }
+                }
+            });
+        });
+
+        if let Some(Drag::Select { start, end }) = &self.drag {
+            let band = Rect::from_points(*start, *end);
+            ctx.fill(band, &selected_color.clone().with_alpha(0.15));
+            ctx.stroke(band, &selected_color, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn remove_node_updates_connections() {
+        let mut graph = Graph::new();
+        for i in 0..3 {
+            let node = GraphNode::new((i as f64 * 100.0, 0.0), i)
+                .with_inputs(1)
+                .with_outputs(1);
+            graph.add_node(node);
+        }
+        let port = |node| PortId { node, port: 0 };
+        graph.connect(Connection {
+            from: port(0),
+            to: port(1),
+        });
+        graph.connect(Connection {
+            from: port(1),
+            to: port(2),
+        });
+        graph.connect(Connection {
+            from: port(0),
+            to: port(2),
+        });
+        // connecting twice has no effect
+        graph.connect(Connection {
+            from: port(0),
+            to: port(2),
+        });
+        assert_eq!(graph.connections.len(), 3);
+
+        graph.remove_node(1);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes[1].data, 2);
+        assert_eq!(
+            graph.connections.as_slice(),
+            &[Connection {
+                from: port(0),
+                to: port(1),
+            }]
+        );
+    }
+}
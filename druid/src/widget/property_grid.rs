@@ -0,0 +1,519 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A two-column grid of named properties and their editors.
+
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use crate::kurbo::Line;
+use crate::piet::{FontFamily, FontWeight};
+use crate::text::{
+    Formatter, NumberFormatter, ParseFormatter, Selection, Validation, ValidationError,
+};
+use crate::widget::prelude::*;
+use crate::widget::{Checkbox, Flex, LabelText, Painter, RadioGroup, SizedBox, TextBox};
+use crate::{
+    theme, ArcStr, Color, FontDescriptor, Lens, Point, Rect, TextLayout, WidgetExt, WidgetPod,
+};
+use tracing::{instrument, trace};
+
+/// The width of the editor column when the grid is not given a width.
+const DEFAULT_EDITOR_WIDTH: f64 = 200.0;
+const COLOR_SWATCH_SIZE: f64 = 20.0;
+
+/// A value that has a default editor in a [`PropertyGrid`].
+///
+/// This is implemented for `bool` (a checkbox), `String` (a text box), the
+/// number types (a text box that only accepts numbers) and [`Color`] (a
+/// swatch and a text box for its hex code). Enums can be edited with
+/// [`PropertyGrid::with_choice`].
+pub trait PropertyValue: Data {
+    /// Create the widget that edits a value of this type.
+    fn editor() -> Box<dyn Widget<Self>>;
+}
+
+/// An inspector of properties, with their names in the first column and
+/// editors for their values in the second.
+///
+/// The properties are described by adding them in order; the editor of each
+/// one is picked by the type of its value, see [`PropertyValue`].
+///
+/// ```
+/// # use druid::widget::PropertyGrid;
+/// # use druid::{Color, Data, Lens, Widget};
+/// #[derive(Clone, Data, PartialEq)]
+/// enum Shape {
+///     Circle,
+///     Square,
+/// }
+///
+/// #[derive(Clone, Data, Lens)]
+/// struct Node {
+///     name: String,
+///     visible: bool,
+///     opacity: f64,
+///     fill: Color,
+///     shape: Shape,
+/// }
+///
+/// fn inspector() -> impl Widget<Node> {
+///     PropertyGrid::new()
+///         .with_section("General")
+///         .with_property("Name", Node::name)
+///         .with_property("Visible", Node::visible)
+///         .with_section("Appearance")
+///         .with_property("Opacity", Node::opacity)
+///         .with_property("Fill", Node::fill)
+///         .with_choice(
+///             "Shape",
+///             vec![("Circle", Shape::Circle), ("Square", Shape::Square)],
+///             Node::shape,
+///         )
+/// }
+/// ```
+pub struct PropertyGrid<T> {
+    rows: Vec<Row<T>>,
+    /// The width of the name column, from the last layout.
+    name_width: f64,
+}
+
+struct Row<T> {
+    label: TextLayout<ArcStr>,
+    /// `None` for section headers.
+    editor: Option<WidgetPod<T, Box<dyn Widget<T>>>>,
+    /// The area of the row, from the last layout.
+    rect: Rect,
+}
+
+impl<T: Data> PropertyGrid<T> {
+    /// Create a new, empty `PropertyGrid`.
+    pub fn new() -> Self {
+        PropertyGrid {
+            rows: Vec::new(),
+            name_width: 0.0,
+        }
+    }
+
+    /// Builder-style method to add a property that edits the value at `lens`
+    /// with the default editor for its type.
+    pub fn with_property<U: PropertyValue>(
+        self,
+        name: impl Into<ArcStr>,
+        lens: impl Lens<T, U> + 'static,
+    ) -> Self {
+        self.with_editor(name, U::editor().lens(lens))
+    }
+
+    /// Builder-style method to add a property that is one of `choices`,
+    /// such as the variants of an enum.
+    pub fn with_choice<U: Data + PartialEq>(
+        self,
+        name: impl Into<ArcStr>,
+        choices: impl IntoIterator<Item = (impl Into<LabelText<U>> + 'static, U)> + 'static,
+        lens: impl Lens<T, U> + 'static,
+    ) -> Self {
+        self.with_editor(name, RadioGroup::new(choices).lens(lens))
+    }
+
+    /// Builder-style method to add a property with a custom editor.
+    pub fn with_editor(
+        mut self,
+        name: impl Into<ArcStr>,
+        editor: impl Widget<T> + 'static,
+    ) -> Self {
+        let mut label = TextLayout::from_text(name);
+        label.set_text_color(theme::TEXT_COLOR);
+        self.rows.push(Row {
+            label,
+            editor: Some(WidgetPod::new(Box::new(editor))),
+            rect: Rect::ZERO,
+        });
+        self
+    }
+
+    /// Builder-style method to start a section of properties, with a header
+    /// that spans both columns.
+    pub fn with_section(mut self, title: impl Into<ArcStr>) -> Self {
+        let mut label = TextLayout::from_text(title);
+        label.set_text_color(theme::TEXT_COLOR);
+        label.set_font(FontDescriptor::new(FontFamily::SYSTEM_UI).with_weight(FontWeight::BOLD));
+        self.rows.push(Row {
+            label,
+            editor: None,
+            rect: Rect::ZERO,
+        });
+        self
+    }
+}
+
+impl<T: Data> Default for PropertyGrid<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data> Widget<T> for PropertyGrid<T> {
+    #[instrument(
+        name = "PropertyGrid",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        for editor in self.rows.iter_mut().filter_map(|row| row.editor.as_mut()) {
+            editor.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(
+        name = "PropertyGrid",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        for editor in self.rows.iter_mut().filter_map(|row| row.editor.as_mut()) {
+            editor.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(
+        name = "PropertyGrid",
+        level = "trace",
+        skip(self, ctx, _old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        for row in &mut self.rows {
+            if let Some(editor) = row.editor.as_mut() {
+                editor.update(ctx, data, env);
+            }
+            if ctx.env_changed() && row.label.needs_rebuild_after_update(ctx) {
+                ctx.request_layout();
+            }
+        }
+    }
+
+    #[instrument(name = "PropertyGrid", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("PropertyGrid");
+        let pad_x = env.get(theme::WIDGET_PADDING_HORIZONTAL);
+        let pad_y = env.get(theme::WIDGET_PADDING_VERTICAL);
+
+        for row in &mut self.rows {
+            row.label.rebuild_if_needed(ctx.text(), env);
+        }
+        // section headers span both columns, so they don't make the names wider.
+        self.name_width = self
+            .rows
+            .iter()
+            .filter(|row| row.editor.is_some())
+            .map(|row| row.label.size().width)
+            .fold(0.0, f64::max)
+            + 2.0 * pad_x;
+
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            self.name_width + DEFAULT_EDITOR_WIDTH + 2.0 * pad_x
+        };
+        let editor_width = (width - self.name_width - 2.0 * pad_x).max(0.0);
+        let editor_bc = BoxConstraints::new(
+            Size::new(editor_width, 0.0),
+            Size::new(editor_width, f64::INFINITY),
+        );
+
+        let mut y = 0.0;
+        for row in &mut self.rows {
+            let mut height = row.label.size().height;
+            if let Some(editor) = row.editor.as_mut() {
+                let editor_size = editor.layout(ctx, &editor_bc, data, env);
+                editor.set_origin(
+                    ctx,
+                    data,
+                    env,
+                    Point::new(self.name_width + pad_x, y + pad_y),
+                );
+                height = height.max(editor_size.height);
+            }
+            let height = height + 2.0 * pad_y;
+            row.rect = Rect::new(0.0, y, width, y + height);
+            y += height;
+        }
+
+        let size = bc.constrain(Size::new(width, y));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "PropertyGrid", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let pad_x = env.get(theme::WIDGET_PADDING_HORIZONTAL);
+        let line_color = env.get(theme::BORDER_DARK);
+        let header_color = env.get(theme::BACKGROUND_LIGHT);
+
+        for row in &mut self.rows {
+            let label_y = row.rect.y0 + (row.rect.height() - row.label.size().height) / 2.0;
+            match row.editor.as_mut() {
+                Some(editor) => {
+                    let divider_x = self.name_width - 0.5;
+                    ctx.stroke(
+                        Line::new((divider_x, row.rect.y0), (divider_x, row.rect.y1)),
+                        &line_color,
+                        1.0,
+                    );
+                    row.label.draw(ctx, (pad_x, label_y));
+                    editor.paint(ctx, data, env);
+                }
+                None => {
+                    ctx.fill(row.rect, &header_color);
+                    row.label.draw(ctx, (pad_x, label_y));
+                }
+            }
+            let line_y = row.rect.y1 - 0.5;
+            ctx.stroke(
+                Line::new((row.rect.x0, line_y), (row.rect.x1, line_y)),
+                &line_color,
+                1.0,
+            );
+        }
+    }
+}
+
+impl PropertyValue for bool {
+    fn editor() -> Box<dyn Widget<Self>> {
+        Box::new(Checkbox::new(""))
+    }
+}
+
+impl PropertyValue for String {
+    fn editor() -> Box<dyn Widget<Self>> {
+        Box::new(TextBox::new())
+    }
+}
+
+/// A text box that only accepts valid numbers.
+fn number_editor<N>() -> Box<dyn Widget<N>>
+where
+    N: Data + Debug + Display + FromStr,
+    N::Err: std::error::Error + 'static,
+{
+    Box::new(
+        TextBox::new()
+            .with_formatter(ParseFormatter::new())
+            .validate_while_editing(false)
+            .update_data_while_editing(true),
+    )
+}
+
+macro_rules! impl_property_value_for_numbers {
+    ($($ty:ty),*) => {
+        $(
+            impl PropertyValue for $ty {
+                fn editor() -> Box<dyn Widget<Self>> {
+                    number_editor()
+                }
+            }
+        )*
+    };
+}
+
+impl_property_value_for_numbers!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32);
+
+impl PropertyValue for f64 {
+    /// Uses the decimal and grouping separators of the current locale.
+    fn editor() -> Box<dyn Widget<Self>> {
+        Box::new(
+            TextBox::new()
+                .with_formatter(NumberFormatter::new())
+                .validate_while_editing(false)
+                .update_data_while_editing(true),
+        )
+    }
+}
+
+impl PropertyValue for Color {
+    fn editor() -> Box<dyn Widget<Self>> {
+        let swatch = Painter::new(|ctx, color: &Color, env| {
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, color);
+            ctx.stroke(rect.inset(-0.5), &env.get(theme::BORDER_DARK), 1.0);
+        });
+        let hex = TextBox::new()
+            .with_formatter(HexFormatter)
+            .validate_while_editing(false)
+            .update_data_while_editing(true);
+        Box::new(
+            Flex::row()
+                .with_child(
+                    SizedBox::new(swatch)
+                        .width(COLOR_SWATCH_SIZE)
+                        .height(COLOR_SWATCH_SIZE),
+                )
+                .with_default_spacer()
+                .with_flex_child(hex, 1.0),
+        )
+    }
+}
+
+/// Shows colors as `#rrggbb`, or `#rrggbbaa` if they are not opaque.
+struct HexFormatter;
+
+impl Formatter<Color> for HexFormatter {
+    fn format(&self, value: &Color) -> String {
+        let (r, g, b, a) = value.as_rgba8();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        match Color::from_hex_str(input) {
+            Ok(_) => Validation::success(),
+            Err(e) => Validation::failure(e),
+        }
+    }
+
+    fn value(&self, input: &str) -> Result<Color, ValidationError> {
+        Color::from_hex_str(input).map_err(ValidationError::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    #[derive(Clone, Data, PartialEq)]
+    enum Shape {
+        Circle,
+        Square,
+    }
+
+    #[derive(Clone, Data, Lens)]
+    struct Node {
+        visible: bool,
+        opacity: f64,
+        fill: Color,
+        shape: Shape,
+    }
+
+    /// Shares where the editors are with the test.
+    struct Probe {
+        grid: PropertyGrid<Node>,
+        editors: Rc<RefCell<Vec<Rect>>>,
+    }
+
+    impl Widget<Node> for Probe {
+        fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Node, env: &Env) {
+            self.grid.event(ctx, event, data, env);
+        }
+
+        fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &Node, env: &Env) {
+            self.grid.lifecycle(ctx, event, data, env);
+        }
+
+        fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Node, data: &Node, env: &Env) {
+            self.grid.update(ctx, old_data, data, env);
+        }
+
+        fn layout(
+            &mut self,
+            ctx: &mut LayoutCtx,
+            bc: &BoxConstraints,
+            data: &Node,
+            env: &Env,
+        ) -> Size {
+            let size = self.grid.layout(ctx, bc, data, env);
+            *self.editors.borrow_mut() = self
+                .grid
+                .rows
+                .iter()
+                .filter_map(|row| row.editor.as_ref())
+                .map(|editor| editor.layout_rect())
+                .collect();
+            size
+        }
+
+        fn paint(&mut self, ctx: &mut PaintCtx, data: &Node, env: &Env) {
+            self.grid.paint(ctx, data, env);
+        }
+    }
+
+    #[test]
+    fn editors_change_their_properties() {
+        let grid = PropertyGrid::new()
+            .with_section("Node")
+            .with_property("Visible", Node::visible)
+            .with_property("Opacity", Node::opacity)
+            .with_property("Fill", Node::fill)
+            .with_choice(
+                "Shape",
+                vec![("Circle", Shape::Circle), ("Square", Shape::Square)],
+                Node::shape,
+            );
+        let editors = Rc::new(RefCell::new(Vec::new()));
+        let probe = Probe {
+            grid,
+            editors: editors.clone(),
+        };
+        let data = Node {
+            visible: false,
+            opacity: 1.0,
+            fill: Color::BLACK,
+            shape: Shape::Square,
+        };
+
+        Harness::create_simple(data, probe, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            // the section header has no editor.
+            assert_eq!(editors.borrow().len(), 4);
+            let editor = |i: usize| editors.borrow()[i];
+
+            harness.mouse_click(editor(0).center());
+            assert!(harness.data().visible);
+
+            // numbers are updated while typing, as long as they are valid.
+            harness.mouse_click(editor(1).center());
+            harness.ime_insert_text("5");
+            assert_eq!(harness.data().opacity, 15.0);
+            harness.ime_insert_text("x");
+            assert_eq!(harness.data().opacity, 15.0);
+
+            harness.mouse_click(editor(2).center());
+            harness.ime_insert_text("80");
+            assert_eq!(harness.data().fill, Color::rgba8(0, 0, 0, 0x80));
+
+            // the first choice is at the top.
+            harness.mouse_click(editor(3).origin() + (5.0, 5.0));
+            assert!(harness.data().shape == Shape::Circle);
+        });
+    }
+
+    #[test]
+    fn hex_colors_round_trip() {
+        let opaque = Color::rgb8(0x12, 0xab, 0xff);
+        assert_eq!(HexFormatter.format(&opaque), "#12abff");
+        assert_eq!(HexFormatter.value("#12abff").unwrap(), opaque);
+
+        let translucent = Color::rgba8(0, 0, 0, 0x80);
+        assert_eq!(HexFormatter.format(&translucent), "#00000080");
+        assert_eq!(HexFormatter.value("#00000080").unwrap(), translucent);
+        assert!(HexFormatter.value("#12abzz").is_err());
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A row of stars for giving or showing a rating.
+
+use crate::widget::prelude::*;
+use crate::{theme, ArcStr, Color, Data, KbKey, KeyOrValue, MouseButton, Point, Rect, TextLayout};
+use tracing::{instrument, trace};
+
+const DEFAULT_STARS: u8 = 5;
+const STAR_SIZE: f64 = 20.0;
+const FILLED_STAR: &str = "\u{2605}";
+const EMPTY_STAR: &str = "\u{2606}";
+
+/// A value that a [`Rating`] can edit.
+///
+/// This is implemented for `f64`, and for `u8`, which only holds whole
+/// stars.
+pub trait RatingValue: Data {
+    /// Whether the value can hold half stars.
+    const HALF_STEPS: bool;
+    /// The number of stars.
+    fn to_stars(&self) -> f64;
+    /// The value for a number of stars.
+    fn from_stars(stars: f64) -> Self;
+}
+
+impl RatingValue for f64 {
+    const HALF_STEPS: bool = true;
+
+    fn to_stars(&self) -> f64 {
+        *self
+    }
+
+    fn from_stars(stars: f64) -> Self {
+        stars
+    }
+}
+
+impl RatingValue for u8 {
+    const HALF_STEPS: bool = false;
+
+    fn to_stars(&self) -> f64 {
+        *self as f64
+    }
+
+    fn from_stars(stars: f64) -> Self {
+        stars.round().max(0.0).min(u8::MAX as f64) as u8
+    }
+}
+
+/// A row of stars, for rating something.
+///
+/// Clicking a star sets the rating to it, and hovering shows what the
+/// rating would be. When it has focus, the arrow keys change the rating by
+/// one step, and Home and End set it to none or all of the stars.
+///
+/// This implements `Widget<f64>` and `Widget<u8>`; see [`RatingValue`].
+pub struct Rating {
+    stars: u8,
+    half_steps: bool,
+    read_only: bool,
+    color: KeyOrValue<Color>,
+    filled: TextLayout<ArcStr>,
+    empty: TextLayout<ArcStr>,
+    /// The rating under the mouse, shown instead of the actual rating.
+    preview: Option<f64>,
+}
+
+impl Rating {
+    /// Create a new `Rating` with five stars.
+    pub fn new() -> Self {
+        let mut rating = Rating {
+            stars: DEFAULT_STARS,
+            half_steps: false,
+            read_only: false,
+            color: theme::PRIMARY_LIGHT.into(),
+            filled: TextLayout::new(),
+            empty: TextLayout::new(),
+            preview: None,
+        };
+        rating.set_glyphs(FILLED_STAR, EMPTY_STAR);
+        rating
+    }
+
+    /// Builder-style method to set the number of stars.
+    pub fn with_stars(mut self, stars: u8) -> Self {
+        self.stars = stars;
+        self
+    }
+
+    /// Builder-style method to allow ratings in half stars.
+    ///
+    /// This has no effect when the rating is kept in a `u8`.
+    pub fn with_half_steps(mut self, half_steps: bool) -> Self {
+        self.half_steps = half_steps;
+        self
+    }
+
+    /// Builder-style method to only show the rating, without letting the
+    /// user change it.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Builder-style method to set the color of the filled stars.
+    ///
+    /// The default is [`theme::PRIMARY_LIGHT`].
+    ///
+    /// [`theme::PRIMARY_LIGHT`]: crate::theme::PRIMARY_LIGHT
+    pub fn with_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.color = color.into();
+        self.filled.set_text_color(self.color.clone());
+        self
+    }
+
+    /// Builder-style method to draw something other than stars.
+    ///
+    /// `filled` is drawn for the part of the rating that is given, and
+    /// `empty` for the rest.
+    pub fn with_glyphs(mut self, filled: impl Into<ArcStr>, empty: impl Into<ArcStr>) -> Self {
+        self.set_glyphs(filled, empty);
+        self
+    }
+
+    fn set_glyphs(&mut self, filled: impl Into<ArcStr>, empty: impl Into<ArcStr>) {
+        self.filled = TextLayout::from_text(filled);
+        self.filled.set_text_size(STAR_SIZE);
+        self.filled.set_text_color(self.color.clone());
+        self.empty = TextLayout::from_text(empty);
+        self.empty.set_text_size(STAR_SIZE);
+        self.empty.set_text_color(theme::TEXT_COLOR);
+    }
+
+    /// Whether ratings can be given in half stars, for values of type `V`.
+    fn half_steps<V: RatingValue>(&self) -> bool {
+        self.half_steps && V::HALF_STEPS
+    }
+
+    fn step<V: RatingValue>(&self) -> f64 {
+        if self.half_steps::<V>() {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    fn star_width(&self) -> f64 {
+        self.filled.size().width.max(self.empty.size().width)
+    }
+
+    /// The rating that clicking at `x` would give.
+    fn rating_at<V: RatingValue>(&self, x: f64) -> f64 {
+        let width = self.star_width();
+        if width <= 0.0 {
+            return 0.0;
+        }
+        let stars = x / width;
+        let rating = if self.half_steps::<V>() {
+            (stars * 2.0).floor() / 2.0 + 0.5
+        } else {
+            stars.floor() + 1.0
+        };
+        rating.max(self.step::<V>()).min(self.stars as f64)
+    }
+
+    fn set_rating<V: RatingValue>(&self, ctx: &mut EventCtx, data: &mut V, rating: f64) {
+        let rating = rating.max(0.0).min(self.stars as f64);
+        trace!("Rating set to {}", rating);
+        *data = V::from_stars(rating);
+        ctx.request_paint();
+    }
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: RatingValue> Widget<V> for Rating {
+    #[instrument(name = "Rating", level = "trace", skip(self, ctx, event, data, _env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut V, _env: &Env) {
+        if self.read_only || ctx.is_disabled() {
+            return;
+        }
+        match event {
+            Event::MouseMove(mouse) => {
+                let preview = if ctx.is_hot() {
+                    Some(self.rating_at::<V>(mouse.pos.x))
+                } else {
+                    None
+                };
+                if preview != self.preview {
+                    self.preview = preview;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                ctx.set_active(true);
+                ctx.request_focus();
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                if ctx.is_hot() {
+                    self.set_rating(ctx, data, self.rating_at::<V>(mouse.pos.x));
+                }
+            }
+            Event::KeyDown(key) if ctx.has_focus() => {
+                let rating = data.to_stars();
+                let new_rating = match key.key {
+                    KbKey::ArrowRight | KbKey::ArrowUp => rating + self.step::<V>(),
+                    KbKey::ArrowLeft | KbKey::ArrowDown => rating - self.step::<V>(),
+                    KbKey::Home => 0.0,
+                    KbKey::End => self.stars as f64,
+                    _ => return,
+                };
+                ctx.set_handled();
+                self.set_rating(ctx, data, new_rating);
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(name = "Rating", level = "trace", skip(self, ctx, event, _data, _env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &V, _env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded if !self.read_only => ctx.register_for_focus(),
+            LifeCycle::HotChanged(false) => {
+                self.preview = None;
+                ctx.request_paint();
+            }
+            LifeCycle::FocusChanged(_) | LifeCycle::DisabledChanged(_) => ctx.request_paint(),
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "Rating",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &V, data: &V, _env: &Env) {
+        if !old_data.same(data) {
+            ctx.request_paint();
+        }
+        if ctx.env_changed()
+            && (self.filled.needs_rebuild_after_update(ctx)
+                || self.empty.needs_rebuild_after_update(ctx))
+        {
+            ctx.request_layout();
+        }
+    }
+
+    #[instrument(name = "Rating", level = "trace", skip(self, ctx, bc, _data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &V, env: &Env) -> Size {
+        bc.debug_check("Rating");
+        self.filled.rebuild_if_needed(ctx.text(), env);
+        self.empty.rebuild_if_needed(ctx.text(), env);
+        let height = self.filled.size().height.max(self.empty.size().height);
+        let size = bc.constrain(Size::new(self.star_width() * self.stars as f64, height));
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Rating", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &V, env: &Env) {
+        let rating = self.preview.unwrap_or_else(|| data.to_stars());
+        let width = self.star_width();
+        let height = ctx.size().height;
+        for i in 0..self.stars {
+            let x = width * i as f64;
+            let origin = Point::new(x, 0.0);
+            // how much of this star is filled, from 0.0 to 1.0.
+            let fill = (rating - i as f64).clamp(0.0, 1.0);
+            if fill < 1.0 {
+                self.empty.draw(ctx, origin);
+            }
+            if fill > 0.0 {
+                let filled = &self.filled;
+                ctx.with_save(|ctx| {
+                    ctx.clip(Rect::new(x, 0.0, x + width * fill, height));
+                    filled.draw(ctx, origin);
+                });
+            }
+        }
+
+        if ctx.has_focus() {
+            let focus_rect = ctx.size().to_rect().inset(-0.5);
+            ctx.stroke(focus_rect, &env.get(theme::PRIMARY_LIGHT), 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use crate::widget::Flex;
+    use crate::{KeyEvent, Modifiers, WidgetExt, WidgetId};
+    use test_env_log::test;
+
+    /// Where clicking gives `stars`, with the stars at `rect`.
+    fn at_stars(rect: Rect, stars: f64) -> Point {
+        Point::new(
+            rect.x0 + rect.width() / DEFAULT_STARS as f64 * stars,
+            rect.center().y,
+        )
+    }
+
+    #[test]
+    fn half_steps() {
+        let id = WidgetId::next();
+        let widget = Flex::row().with_child(WidgetExt::<f64>::with_id(
+            Rating::new().with_half_steps(true),
+            id,
+        ));
+
+        Harness::create_simple(0.0f64, widget, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            let rect = harness.window_layout_rect(id).unwrap();
+
+            harness.mouse_click(at_stars(rect, 2.2));
+            assert_eq!(*harness.data(), 2.5);
+
+            let mut press = |key| {
+                harness.key_down(KeyEvent::for_test(Modifiers::default(), key));
+                *harness.data()
+            };
+            assert_eq!(press(KbKey::End), 5.0);
+            assert_eq!(press(KbKey::ArrowRight), 5.0);
+            assert_eq!(press(KbKey::ArrowLeft), 4.5);
+            assert_eq!(press(KbKey::Home), 0.0);
+            assert_eq!(press(KbKey::ArrowDown), 0.0);
+        });
+    }
+
+    #[test]
+    fn whole_stars() {
+        let id = WidgetId::next();
+        // half steps have no effect on a u8.
+        let widget = Flex::row().with_child(WidgetExt::<u8>::with_id(
+            Rating::new().with_half_steps(true),
+            id,
+        ));
+
+        Harness::create_simple(0u8, widget, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            let rect = harness.window_layout_rect(id).unwrap();
+
+            harness.mouse_click(at_stars(rect, 2.2));
+            assert_eq!(*harness.data(), 3);
+            harness.key_down(KeyEvent::for_test(Modifiers::default(), KbKey::ArrowUp));
+            assert_eq!(*harness.data(), 4);
+        });
+    }
+
+    #[test]
+    fn read_only() {
+        let id = WidgetId::next();
+        let widget =
+            Flex::row().with_child(WidgetExt::<u8>::with_id(Rating::new().read_only(true), id));
+
+        Harness::create_simple(2u8, widget, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            let rect = harness.window_layout_rect(id).unwrap();
+
+            harness.mouse_click(at_stars(rect, 4.5));
+            assert_eq!(*harness.data(), 2);
+            assert_eq!(harness.focus(), None);
+        });
+    }
+}
@@ -14,29 +14,37 @@
 
 //! A container that scrolls its contents.
 
+use crate::piet::PietImage;
 use crate::widget::prelude::*;
-use crate::widget::{Axis, ClipBox};
+use crate::widget::{Axis, CacheLayer, ClipBox};
 use crate::{scroll_component::*, Data, Rect, Vec2};
 use tracing::{instrument, trace};
 
 /// A container that scrolls its contents.
 ///
 /// This container holds a single child, and uses the wheel to scroll it
-/// when the child's bounds are larger than the viewport.
+/// when the child's bounds are larger than the viewport. Turning the wheel
+/// with shift held down scrolls horizontally, unless
+/// [`SCROLL_SHIFT_HORIZONTAL`] is turned off.
 ///
 /// The child is laid out with completely unconstrained layout bounds by
 /// default. Restrict to a specific axis with [`vertical`] or [`horizontal`].
 /// When restricted to scrolling on a specific axis the child's size is
 /// locked on the opposite axis.
 ///
+/// The child's rendering can be cached in a [`CacheLayer`], so that scrolling
+/// mostly-static content does not require repainting it; see [`cache_content`].
+///
 /// [`vertical`]: struct.Scroll.html#method.vertical
 /// [`horizontal`]: struct.Scroll.html#method.horizontal
+/// [`cache_content`]: Scroll::cache_content
+/// [`SCROLL_SHIFT_HORIZONTAL`]: crate::theme::SCROLL_SHIFT_HORIZONTAL
 pub struct Scroll<T, W> {
-    clip: ClipBox<T, W>,
+    clip: ClipBox<T, CacheLayer<T, W>>,
     scroll_component: ScrollComponent,
 }
 
-impl<T, W: Widget<T>> Scroll<T, W> {
+impl<T: Data, W: Widget<T>> Scroll<T, W> {
     /// Create a new scroll container.
     ///
     /// This method will allow scrolling in all directions if child's bounds
@@ -44,7 +52,7 @@ impl<T, W: Widget<T>> Scroll<T, W> {
     /// [horizontal](#method.horizontal) methods to limit scrolling to a specific axis.
     pub fn new(child: W) -> Scroll<T, W> {
         Scroll {
-            clip: ClipBox::new(child),
+            clip: ClipBox::new(CacheLayer::new(child).with_enabled(false)),
             scroll_component: ScrollComponent::new(),
         }
     }
@@ -99,6 +107,27 @@ impl<T, W> Scroll<T, W> {
         self
     }
 
+    /// Builder-style method to set whether the rendering of the child is cached.
+    ///
+    /// This is `false` by default. The cache is an offscreen image of the
+    /// child, which costs memory and is thrown away whenever the child
+    /// repaints, so it only pays off for large content that rarely changes.
+    ///
+    /// See [`CacheLayer`] for more details.
+    pub fn cache_content(mut self, cache: bool) -> Self {
+        self.set_cache_content(cache);
+        self
+    }
+
+    /// Set whether the rendering of the child is cached.
+    ///
+    /// See [`cache_content`] for more details.
+    ///
+    /// [`cache_content`]: Scroll::cache_content
+    pub fn set_cache_content(&mut self, cache: bool) {
+        self.clip.child_mut().set_enabled(cache);
+    }
+
     /// Disable both scrollbars
     pub fn disable_scrollbars(mut self) -> Self {
         self.scroll_component.enabled = ScrollbarsEnabled::None;
@@ -141,12 +170,12 @@ impl<T, W> Scroll<T, W> {
 
     /// Returns a reference to the child widget.
     pub fn child(&self) -> &W {
-        self.clip.child()
+        self.clip.child().child()
     }
 
     /// Returns a mutable reference to the child widget.
     pub fn child_mut(&mut self) -> &mut W {
-        self.clip.child_mut()
+        self.clip.child_mut().child_mut()
     }
 
     /// Returns the size of the child widget.
@@ -172,6 +201,20 @@ impl<T, W> Scroll<T, W> {
     }
 }
 
+impl<T: Data, W: Widget<T>> Scroll<T, W> {
+    /// Render the whole content into an image at `scale` device pixels per
+    /// unit, for an overview of it.
+    pub(crate) fn content_snapshot(
+        &mut self,
+        ctx: &mut PaintCtx,
+        data: &T,
+        env: &Env,
+        scale: f64,
+    ) -> Option<PietImage> {
+        self.clip.child_mut().snapshot(ctx, data, env, scale)
+    }
+}
+
 impl<T: Data, W: Widget<T>> Widget<T> for Scroll<T, W> {
     #[instrument(name = "Scroll", level = "trace", skip(self, ctx, event, data, env))]
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
@@ -0,0 +1,66 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A controller that moves focus with the arrow keys.
+
+use crate::widget::prelude::*;
+use crate::widget::Controller;
+use crate::{FocusDirection, KbKey};
+
+/// A [`Controller`] that moves focus to the nearest focusable widget in the
+/// direction of an arrow key.
+///
+/// Only arrow keys that are pressed without Shift, Ctrl, Alt or Meta, and
+/// that the focused widget doesn't handle itself, move focus; a [`TextBox`]
+/// keeps using them to move its cursor, for instance. This is meant to wrap
+/// the root widget of a window, for keyboard-first interfaces and interfaces
+/// that are used from across the room with a remote control.
+///
+/// ```
+/// use druid::widget::{Button, Flex, SpatialNavigation};
+/// use druid::WidgetExt;
+///
+/// let grid = Flex::row()
+///     .with_child(Button::<()>::new("Play"))
+///     .with_child(Button::new("Settings"))
+///     .controller(SpatialNavigation);
+/// ```
+///
+/// [`TextBox`]: crate::widget::TextBox
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SpatialNavigation;
+
+impl<T, W: Widget<T>> Controller<T, W> for SpatialNavigation {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        child.event(ctx, event, data, env);
+        if let Event::KeyDown(key) = event {
+            let mods = key.mods;
+            if ctx.is_handled() || mods.shift() || mods.ctrl() || mods.alt() || mods.meta() {
+                return;
+            }
+            if !ctx.has_focus() {
+                return;
+            }
+            let direction = match key.key {
+                KbKey::ArrowLeft => FocusDirection::Left,
+                KbKey::ArrowRight => FocusDirection::Right,
+                KbKey::ArrowUp => FocusDirection::Up,
+                KbKey::ArrowDown => FocusDirection::Down,
+                _ => return,
+            };
+            ctx.focus_in_direction(direction);
+            ctx.set_handled();
+        }
+    }
+}
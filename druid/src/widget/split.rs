@@ -288,8 +288,10 @@ impl<T> Split<T> {
             ),
         };
         let splitter_color = self.bar_color(env);
-        ctx.stroke(line1, &splitter_color, line_width);
-        ctx.stroke(line2, &splitter_color, line_width);
+        let (line1, width) = ctx.snap_line(line1, line_width);
+        ctx.stroke(line1, &splitter_color, width);
+        let (line2, width) = ctx.snap_line(line2, line_width);
+        ctx.stroke(line2, &splitter_color, width);
     }
 }
 
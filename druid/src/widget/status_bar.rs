@@ -0,0 +1,382 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A bar along the bottom of a window.
+
+use std::time::Duration;
+
+use crate::commands::{CLEAR_STATUS_MESSAGE, SHOW_STATUS_MESSAGE};
+use crate::kurbo::Line;
+use crate::widget::prelude::*;
+use crate::{theme, ArcStr, Data, Point, Rect, TextLayout, TimerToken, WidgetPod};
+use tracing::{instrument, trace};
+
+const VERTICAL_PADDING: f64 = 2.0;
+const BORDER_WIDTH: f64 = 1.0;
+
+/// A message shown in a [`StatusBar`] with [`SHOW_STATUS_MESSAGE`].
+///
+/// [`SHOW_STATUS_MESSAGE`]: crate::commands::SHOW_STATUS_MESSAGE
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusMessage {
+    text: ArcStr,
+    timeout: Option<Duration>,
+}
+
+impl StatusMessage {
+    /// Create a message that is shown until it is replaced or cleared.
+    pub fn new(text: impl Into<ArcStr>) -> Self {
+        StatusMessage {
+            text: text.into(),
+            timeout: None,
+        }
+    }
+
+    /// Builder-style method for hiding the message again after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The text of the message.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// How long the message is shown for, if it is hidden on its own.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// A bar with widgets on its left, in its center and on its right, such as
+/// the one along the bottom of most document windows.
+///
+/// The left and right widgets get their preferred width first, the right one
+/// before the left one. The center widget is centered in the bar if there is
+/// room, and gets whatever space is left otherwise. Widgets that don't fit in
+/// the space they get are clipped.
+///
+/// Short-lived messages, such as "Saved" or "3 files copied", are shown in
+/// place of the left widget, which doesn't get any input while it is
+/// hidden. They are shown by submitting [`SHOW_STATUS_MESSAGE`], and hidden
+/// again after their timeout or with [`CLEAR_STATUS_MESSAGE`]. Submitted from
+/// a widget, these commands reach every status bar in its window.
+///
+/// [`SHOW_STATUS_MESSAGE`]: crate::commands::SHOW_STATUS_MESSAGE
+/// [`CLEAR_STATUS_MESSAGE`]: crate::commands::CLEAR_STATUS_MESSAGE
+pub struct StatusBar<T> {
+    left: Option<Slot<T>>,
+    center: Option<Slot<T>>,
+    right: Option<Slot<T>>,
+    message: Option<TextLayout<ArcStr>>,
+    message_rect: Rect,
+    message_timer: TimerToken,
+}
+
+/// A child of the bar, and the part of the bar it is clipped to.
+struct Slot<T> {
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    clip: Rect,
+}
+
+impl<T: Data> StatusBar<T> {
+    /// Create a new, empty `StatusBar`.
+    pub fn new() -> Self {
+        StatusBar {
+            left: None,
+            center: None,
+            right: None,
+            message: None,
+            message_rect: Rect::ZERO,
+            message_timer: TimerToken::INVALID,
+        }
+    }
+
+    /// Builder-style method for setting the widget on the left.
+    pub fn with_left(mut self, child: impl Widget<T> + 'static) -> Self {
+        self.left = Some(Slot::new(child));
+        self
+    }
+
+    /// Builder-style method for setting the widget in the center.
+    pub fn with_center(mut self, child: impl Widget<T> + 'static) -> Self {
+        self.center = Some(Slot::new(child));
+        self
+    }
+
+    /// Builder-style method for setting the widget on the right.
+    pub fn with_right(mut self, child: impl Widget<T> + 'static) -> Self {
+        self.right = Some(Slot::new(child));
+        self
+    }
+
+    /// The text of the message that is currently shown, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .and_then(|layout| layout.text())
+            .map(|text| &**text)
+    }
+
+    fn show_message(&mut self, ctx: &mut EventCtx, message: &StatusMessage) {
+        trace!("showing {:?}", message.text);
+        let mut layout = TextLayout::from_text(message.text.clone());
+        layout.set_text_color(theme::TEXT_COLOR);
+        self.message = Some(layout);
+        self.message_timer = match message.timeout {
+            Some(timeout) => ctx.request_timer(timeout),
+            None => TimerToken::INVALID,
+        };
+        ctx.request_layout();
+    }
+
+    fn clear_message(&mut self, ctx: &mut EventCtx) {
+        if self.message.take().is_some() {
+            self.message_timer = TimerToken::INVALID;
+            ctx.request_layout();
+        }
+    }
+
+    fn slots_mut(&mut self) -> impl Iterator<Item = &mut Slot<T>> {
+        self.left
+            .iter_mut()
+            .chain(self.center.iter_mut())
+            .chain(self.right.iter_mut())
+    }
+}
+
+impl<T: Data> Default for StatusBar<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data> Slot<T> {
+    fn new(child: impl Widget<T> + 'static) -> Self {
+        Slot {
+            child: WidgetPod::new(Box::new(child)),
+            clip: Rect::ZERO,
+        }
+    }
+
+    /// Lay out the child with at most `max_width`, returning its size.
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        max_width: f64,
+        max_height: f64,
+        data: &T,
+        env: &Env,
+    ) -> Size {
+        let bc = BoxConstraints::new(Size::ZERO, Size::new(max_width, max_height));
+        self.child.layout(ctx, &bc, data, env)
+    }
+
+    /// Place the child at `x`, centered vertically below the border, and clip
+    /// it to `width`.
+    fn place(&mut self, ctx: &mut LayoutCtx, x: f64, width: f64, height: f64, data: &T, env: &Env) {
+        let child_height = self.child.layout_rect().height();
+        let y = BORDER_WIDTH + ((height - child_height) / 2.0).max(0.0);
+        self.child.set_origin(ctx, data, env, Point::new(x, y));
+        self.clip = Rect::new(x, BORDER_WIDTH, x + width, BORDER_WIDTH + height);
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let clip = self.clip;
+        ctx.with_save(|ctx| {
+            ctx.clip(clip);
+            self.child.paint(ctx, data, env);
+        });
+    }
+}
+
+impl<T: Data> Widget<T> for StatusBar<T> {
+    #[instrument(name = "StatusBar", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::Command(cmd) => {
+                if let Some(message) = cmd.get(SHOW_STATUS_MESSAGE) {
+                    self.show_message(ctx, message);
+                } else if cmd.is(CLEAR_STATUS_MESSAGE) {
+                    self.clear_message(ctx);
+                }
+            }
+            Event::Timer(token) if *token == self.message_timer => {
+                self.clear_message(ctx);
+                return;
+            }
+            _ => (),
+        }
+        // the left child is hidden behind the message, and shouldn't get the
+        // input that is meant for it.
+        let left_hidden = self.message.is_some() && !event.should_propagate_to_hidden();
+        if let Some(left) = &mut self.left {
+            if !left_hidden {
+                left.child.event(ctx, event, data, env);
+            }
+        }
+        for slot in self.center.iter_mut().chain(self.right.iter_mut()) {
+            slot.child.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "StatusBar", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        for slot in self.slots_mut() {
+            slot.child.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(
+        name = "StatusBar",
+        level = "trace",
+        skip(self, ctx, _old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        if let Some(message) = &mut self.message {
+            if ctx.env_changed() && message.needs_rebuild_after_update(ctx) {
+                ctx.request_layout();
+            }
+        }
+        for slot in self.slots_mut() {
+            slot.child.update(ctx, data, env);
+        }
+    }
+
+    #[instrument(name = "StatusBar", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("StatusBar");
+        let padding = env.get(theme::WIDGET_PADDING_HORIZONTAL);
+        let max_height = (bc.max().height - VERTICAL_PADDING * 2.0 - BORDER_WIDTH).max(0.0);
+        // with unbounded width, every child gets what it wants.
+        let available = (bc.max().width - padding * 2.0).max(0.0);
+        let gap = |size: Size| if size.width > 0.0 { padding } else { 0.0 };
+
+        let right_size = match &mut self.right {
+            Some(right) => right.layout(ctx, available, max_height, data, env),
+            None => Size::ZERO,
+        };
+        let left_available = (available - right_size.width - gap(right_size)).max(0.0);
+        let left_size = match &mut self.left {
+            Some(left) => left.layout(ctx, left_available, max_height, data, env),
+            None => Size::ZERO,
+        };
+        // the message takes the place of the left child.
+        let message_size = match &mut self.message {
+            Some(message) => {
+                message.rebuild_if_needed(ctx.text(), env);
+                let size = message.size();
+                Size::new(size.width.min(left_available), size.height)
+            }
+            None => Size::ZERO,
+        };
+        let start_size = if self.message.is_some() {
+            message_size
+        } else {
+            left_size
+        };
+
+        let center_available =
+            (available - start_size.width - gap(start_size) - right_size.width - gap(right_size))
+                .max(0.0);
+        let center_size = match &mut self.center {
+            Some(center) => center.layout(ctx, center_available, max_height, data, env),
+            None => Size::ZERO,
+        };
+        let center_width = center_size.width.min(center_available);
+
+        let content_height = [left_size, center_size, right_size, message_size]
+            .iter()
+            .map(|size| size.height)
+            .fold(0.0, f64::max);
+        let natural_width = padding * 2.0
+            + start_size.width
+            + gap(start_size)
+            + center_width
+            + gap(right_size)
+            + right_size.width;
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            natural_width
+        };
+        let size = bc.constrain(Size::new(
+            width,
+            content_height + VERTICAL_PADDING * 2.0 + BORDER_WIDTH,
+        ));
+        // the part below the border, where the children are.
+        let height = size.height - BORDER_WIDTH;
+
+        let left_width = left_size.width.min(left_available);
+        if let Some(left) = &mut self.left {
+            left.place(ctx, padding, left_width, height, data, env);
+        }
+        self.message_rect = Rect::from_origin_size(
+            (
+                padding,
+                BORDER_WIDTH + ((height - message_size.height) / 2.0).max(0.0),
+            ),
+            message_size,
+        );
+        let right_width = right_size.width.min(available);
+        if let Some(right) = &mut self.right {
+            let x = size.width - padding - right_width;
+            right.place(ctx, x, right_width, height, data, env);
+        }
+        if let Some(center) = &mut self.center {
+            // centered in the whole bar, unless that would overlap the others.
+            let min_x = padding + start_size.width + gap(start_size);
+            let max_x = size.width - padding - right_width - gap(right_size) - center_width;
+            let x = ((size.width - center_width) / 2.0).min(max_x).max(min_x);
+            center.place(ctx, x, center_width, height, data, env);
+        }
+
+        let insets = self
+            .slots_mut()
+            .map(|slot| slot.child.paint_rect())
+            .fold(Rect::ZERO.with_size(size), |rect, paint| rect.union(paint));
+        ctx.set_paint_insets(insets - size.to_rect());
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "StatusBar", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        ctx.fill(size.to_rect(), &env.get(theme::BACKGROUND_LIGHT));
+        let y = BORDER_WIDTH / 2.0;
+        ctx.stroke(
+            Line::new((0.0, y), (size.width, y)),
+            &env.get(theme::BORDER_DARK),
+            BORDER_WIDTH,
+        );
+
+        if let Some(message) = &self.message {
+            let rect = self.message_rect;
+            ctx.with_save(|ctx| {
+                ctx.clip(rect);
+                message.draw(ctx, rect.origin());
+            });
+        } else if let Some(left) = &mut self.left {
+            left.paint(ctx, data, env);
+        }
+        if let Some(center) = &mut self.center {
+            center.paint(ctx, data, env);
+        }
+        if let Some(right) = &mut self.right {
+            right.paint(ctx, data, env);
+        }
+    }
+}
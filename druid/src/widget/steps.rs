@@ -0,0 +1,581 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A sequence of pages that are filled in one after the other.
+
+use crate::kurbo::{Line, RoundedRect};
+use crate::widget::prelude::*;
+use crate::widget::TabsTransition;
+use crate::{theme, Affine, ArcStr, Data, MouseButton, Point, Rect, TextLayout, Vec2, WidgetPod};
+use tracing::{instrument, trace};
+
+const HEADER_HEIGHT: f64 = 32.0;
+const PROGRESS_HEIGHT: f64 = 3.0;
+const FOOTER_HEIGHT: f64 = 40.0;
+const BUTTON_PADDING: f64 = 12.0;
+const BUTTON_HEIGHT: f64 = 24.0;
+
+type Validate<T> = Box<dyn Fn(&T, &Env) -> bool>;
+type Finish<T> = Box<dyn Fn(&mut EventCtx, &mut T, &Env)>;
+
+/// A sequence of pages, such as the steps of a setup assistant, that are
+/// filled in one after the other.
+///
+/// Below the current page are buttons for going back and forth, and above it
+/// are its title and a bar showing how far along the sequence it is. On the
+/// last page the "Next" button turns into a "Finish" button, which runs the
+/// action given to [`on_finish`].
+///
+/// A page can have a validation function, which keeps the user from going
+/// past it until it returns `true`, for example until all required fields
+/// are filled in.
+///
+/// [`on_finish`]: Steps::on_finish
+pub struct Steps<T> {
+    steps: Vec<Step<T>>,
+    current: usize,
+    on_finish: Option<Finish<T>>,
+    transition: TabsTransition,
+    transition_state: Option<TransitionState>,
+    header: TextLayout<String>,
+    back: NavButton,
+    next: NavButton,
+    finish: NavButton,
+}
+
+struct Step<T> {
+    title: ArcStr,
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    validate: Option<Validate<T>>,
+}
+
+/// A button drawn by the `Steps` widget itself, so that it can be disabled
+/// depending on the current page.
+struct NavButton {
+    label: TextLayout<ArcStr>,
+    rect: Rect,
+    hot: bool,
+    pressed: bool,
+}
+
+struct TransitionState {
+    previous: usize,
+    elapsed: u64,
+    duration: u64,
+    forward: bool,
+}
+
+impl<T: Data> Steps<T> {
+    /// Create a new `Steps` widget without any pages.
+    pub fn new() -> Self {
+        let mut header = TextLayout::new();
+        header.set_text_color(theme::TEXT_COLOR);
+        header.set_font(theme::UI_FONT_BOLD);
+        Steps {
+            steps: Vec::new(),
+            current: 0,
+            on_finish: None,
+            transition: TabsTransition::default(),
+            transition_state: None,
+            header,
+            back: NavButton::new("Back"),
+            next: NavButton::new("Next"),
+            finish: NavButton::new("Finish"),
+        }
+    }
+
+    /// Builder-style method for adding a page with the title `title`.
+    pub fn with_step(mut self, title: impl Into<ArcStr>, child: impl Widget<T> + 'static) -> Self {
+        self.steps.push(Step {
+            title: title.into(),
+            child: WidgetPod::new(Box::new(child)),
+            validate: None,
+        });
+        self
+    }
+
+    /// Builder-style method for adding a page with the title `title`, which
+    /// the user can only go past while `validate` returns `true`.
+    pub fn with_validated_step(
+        mut self,
+        title: impl Into<ArcStr>,
+        child: impl Widget<T> + 'static,
+        validate: impl Fn(&T, &Env) -> bool + 'static,
+    ) -> Self {
+        self.steps.push(Step {
+            title: title.into(),
+            child: WidgetPod::new(Box::new(child)),
+            validate: Some(Box::new(validate)),
+        });
+        self
+    }
+
+    /// Builder-style method for setting what happens when the "Finish"
+    /// button is clicked on the last page.
+    pub fn on_finish(mut self, action: impl Fn(&mut EventCtx, &mut T, &Env) + 'static) -> Self {
+        self.on_finish = Some(Box::new(action));
+        self
+    }
+
+    /// Builder-style method for setting how pages change.
+    pub fn with_transition(mut self, transition: TabsTransition) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// The index of the page that is shown.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    fn is_last(&self) -> bool {
+        self.current + 1 >= self.steps.len()
+    }
+
+    /// Whether the user may leave the current page forwards.
+    fn can_advance(&self, data: &T, env: &Env) -> bool {
+        match self
+            .steps
+            .get(self.current)
+            .and_then(|step| step.validate.as_ref())
+        {
+            Some(validate) => validate(data, env),
+            None => true,
+        }
+    }
+
+    fn forward_button(&mut self) -> &mut NavButton {
+        if self.is_last() {
+            &mut self.finish
+        } else {
+            &mut self.next
+        }
+    }
+
+    fn go_to(&mut self, ctx: &mut EventCtx, index: usize) {
+        trace!("going from step {} to step {}", self.current, index);
+        if let TabsTransition::Slide(duration) = self.transition {
+            self.transition_state = Some(TransitionState {
+                previous: self.current,
+                elapsed: 0,
+                duration,
+                forward: index > self.current,
+            });
+            ctx.request_anim_frame();
+        }
+        self.current = index;
+        self.update_header();
+        ctx.request_layout();
+    }
+
+    fn update_header(&mut self) {
+        if let Some(step) = self.steps.get(self.current) {
+            self.header.set_text(format!(
+                "{} ({}/{})",
+                step.title,
+                self.current + 1,
+                self.steps.len()
+            ));
+        }
+    }
+}
+
+impl<T: Data> Default for Steps<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NavButton {
+    fn new(label: &str) -> Self {
+        let mut layout = TextLayout::from_text(label);
+        layout.set_text_color(theme::TEXT_COLOR);
+        NavButton {
+            label: layout,
+            rect: Rect::ZERO,
+            hot: false,
+            pressed: false,
+        }
+    }
+
+    fn width(&self) -> f64 {
+        self.label.size().width + BUTTON_PADDING * 2.0
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx, enabled: bool, env: &Env) {
+        let background = if !enabled {
+            env.get(theme::DISABLED_BUTTON_DARK)
+        } else if self.pressed && self.hot {
+            env.get(theme::BUTTON_DARK)
+        } else {
+            env.get(theme::BUTTON_LIGHT)
+        };
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        let rounded = RoundedRect::from_rect(self.rect, radius);
+        ctx.fill(rounded, &background);
+        ctx.stroke(
+            rounded,
+            &env.get(theme::BORDER_DARK),
+            env.get(theme::BUTTON_BORDER_WIDTH),
+        );
+        let size = self.label.size();
+        let origin = self.rect.center() - Vec2::new(size.width / 2.0, size.height / 2.0);
+        if enabled {
+            self.label.draw(ctx, origin);
+        } else {
+            ctx.with_save(|ctx| {
+                // there's no disabled text color on the layout, so fade it.
+                ctx.clip(self.rect);
+                self.label.draw(ctx, origin);
+                ctx.fill(
+                    self.rect,
+                    &env.get(theme::DISABLED_BUTTON_DARK).with_alpha(0.5),
+                );
+            });
+        }
+    }
+}
+
+impl TransitionState {
+    fn fraction(&self) -> f64 {
+        (self.elapsed as f64 / self.duration as f64).min(1.0)
+    }
+}
+
+impl<T: Data> Widget<T> for Steps<T> {
+    #[instrument(name = "Steps", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseMove(mouse) => {
+                let back_hot = self.back.rect.contains(mouse.pos);
+                let forward_hot = self.forward_button().rect.contains(mouse.pos);
+                if back_hot != self.back.hot || forward_hot != self.forward_button().hot {
+                    self.back.hot = back_hot;
+                    self.forward_button().hot = forward_hot;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                if self.back.rect.contains(mouse.pos) && self.current > 0 {
+                    self.back.pressed = true;
+                } else if self.forward_button().rect.contains(mouse.pos)
+                    && self.can_advance(data, env)
+                {
+                    self.forward_button().pressed = true;
+                }
+                if self.back.pressed || self.forward_button().pressed {
+                    ctx.set_active(true);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                    return;
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.set_handled();
+                ctx.request_paint();
+                let back = std::mem::take(&mut self.back.pressed);
+                let forward = std::mem::take(&mut self.forward_button().pressed);
+                if back && self.back.rect.contains(mouse.pos) {
+                    self.go_to(ctx, self.current - 1);
+                } else if forward
+                    && self.forward_button().rect.contains(mouse.pos)
+                    && self.can_advance(data, env)
+                {
+                    if self.is_last() {
+                        if let Some(on_finish) = &self.on_finish {
+                            on_finish(ctx, data, env);
+                        }
+                    } else {
+                        self.go_to(ctx, self.current + 1);
+                    }
+                }
+                return;
+            }
+            Event::AnimFrame(interval) => {
+                if let Some(state) = &mut self.transition_state {
+                    state.elapsed += *interval;
+                    if state.elapsed < state.duration {
+                        ctx.request_anim_frame();
+                    } else {
+                        self.transition_state = None;
+                    }
+                    ctx.request_paint();
+                }
+            }
+            _ => (),
+        }
+
+        if event.should_propagate_to_hidden() {
+            for step in &mut self.steps {
+                step.child.event(ctx, event, data, env);
+            }
+        } else if let Some(step) = self.steps.get_mut(self.current) {
+            step.child.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Steps", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => self.update_header(),
+            LifeCycle::HotChanged(false) => {
+                self.back.hot = false;
+                self.next.hot = false;
+                self.finish.hot = false;
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+        if event.should_propagate_to_hidden() {
+            for step in &mut self.steps {
+                step.child.lifecycle(ctx, event, data, env);
+            }
+        } else if let Some(step) = self.steps.get_mut(self.current) {
+            step.child.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Steps", level = "trace", skip(self, ctx, _old_data, data, env))]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        if ctx.env_changed() {
+            let mut layouts = [
+                &mut self.back.label,
+                &mut self.next.label,
+                &mut self.finish.label,
+            ];
+            let mut needs_layout = self.header.needs_rebuild_after_update(ctx);
+            for layout in layouts.iter_mut() {
+                needs_layout |= layout.needs_rebuild_after_update(ctx);
+            }
+            if needs_layout {
+                ctx.request_layout();
+            }
+        }
+        // the buttons depend on whether the page is valid.
+        ctx.request_paint();
+        for step in &mut self.steps {
+            step.child.update(ctx, data, env);
+        }
+    }
+
+    #[instrument(name = "Steps", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Steps");
+        self.header.rebuild_if_needed(ctx.text(), env);
+        for button in [&mut self.back, &mut self.next, &mut self.finish].iter_mut() {
+            button.label.rebuild_if_needed(ctx.text(), env);
+        }
+
+        let chrome = HEADER_HEIGHT + FOOTER_HEIGHT;
+        let body_bc = bc.shrink((0.0, chrome)).loosen();
+        // laying out all pages, so that events can be delivered to them.
+        let mut body_size = Size::ZERO;
+        for step in &mut self.steps {
+            let size = step.child.layout(ctx, &body_bc, data, env);
+            step.child
+                .set_origin(ctx, data, env, Point::new(0.0, HEADER_HEIGHT));
+            body_size = Size::new(
+                body_size.width.max(size.width),
+                body_size.height.max(size.height),
+            );
+        }
+        let size = bc.constrain(Size::new(body_size.width, body_size.height + chrome));
+
+        let padding = env.get(theme::WIDGET_PADDING_HORIZONTAL);
+        let y = size.height - FOOTER_HEIGHT + (FOOTER_HEIGHT - BUTTON_HEIGHT) / 2.0;
+        let forward_width = self.next.width().max(self.finish.width());
+        let forward = Rect::new(
+            size.width - padding - forward_width,
+            y,
+            size.width - padding,
+            y + BUTTON_HEIGHT,
+        );
+        self.next.rect = forward;
+        self.finish.rect = forward;
+        let back_x = forward.x0 - padding - self.back.width();
+        self.back.rect = Rect::new(back_x, y, forward.x0 - padding, y + BUTTON_HEIGHT);
+
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Steps", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        let padding = env.get(theme::WIDGET_PADDING_HORIZONTAL);
+
+        // the title, and how far along we are.
+        let header_y = (HEADER_HEIGHT - PROGRESS_HEIGHT - self.header.size().height) / 2.0;
+        self.header.draw(ctx, (padding, header_y));
+        let track = Rect::new(
+            0.0,
+            HEADER_HEIGHT - PROGRESS_HEIGHT,
+            size.width,
+            HEADER_HEIGHT,
+        );
+        ctx.fill(track, &env.get(theme::BACKGROUND_DARK));
+        if !self.steps.is_empty() {
+            let done = (self.current + 1) as f64 / self.steps.len() as f64;
+            let bar = Rect::new(0.0, track.y0, size.width * done, track.y1);
+            ctx.fill(bar, &env.get(theme::PRIMARY_LIGHT));
+        }
+
+        // the page, sliding in if it just changed.
+        let body = Rect::new(0.0, HEADER_HEIGHT, size.width, size.height - FOOTER_HEIGHT);
+        let current = self.current;
+        let transition = &self.transition_state;
+        let steps = &mut self.steps;
+        ctx.with_save(|ctx| {
+            ctx.clip(body);
+            match transition {
+                Some(state) => {
+                    let offset = size.width * state.fraction();
+                    let (previous_x, current_x) = if state.forward {
+                        (-offset, size.width - offset)
+                    } else {
+                        (offset, offset - size.width)
+                    };
+                    if let Some(previous) = steps.get_mut(state.previous) {
+                        ctx.with_save(|ctx| {
+                            ctx.transform(Affine::translate((previous_x, 0.0)));
+                            previous.child.paint(ctx, data, env);
+                        });
+                    }
+                    if let Some(step) = steps.get_mut(current) {
+                        ctx.with_save(|ctx| {
+                            ctx.transform(Affine::translate((current_x, 0.0)));
+                            step.child.paint(ctx, data, env);
+                        });
+                    }
+                }
+                None => {
+                    if let Some(step) = steps.get_mut(current) {
+                        step.child.paint(ctx, data, env);
+                    }
+                }
+            }
+        });
+
+        let y = size.height - FOOTER_HEIGHT;
+        ctx.stroke(
+            Line::new((0.0, y + 0.5), (size.width, y + 0.5)),
+            &env.get(theme::BORDER_LIGHT),
+            1.0,
+        );
+        self.back.paint(ctx, self.current > 0, env);
+        let can_advance = self.can_advance(data, env);
+        let is_last = self.is_last();
+        let forward = if is_last { &self.finish } else { &self.next };
+        forward.paint(
+            ctx,
+            can_advance && (!is_last || self.on_finish.is_some()),
+            env,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use crate::widget::SizedBox;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    /// The buttons are drawn by `Steps` itself, so this shares where they
+    /// are, and which page is shown, with the test.
+    struct Probe<T> {
+        steps: Steps<T>,
+        buttons: Rc<Cell<(Rect, Rect)>>,
+        current: Rc<Cell<usize>>,
+    }
+
+    impl<T: Data> Widget<T> for Probe<T> {
+        fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+            self.steps.event(ctx, event, data, env);
+            self.current.set(self.steps.current());
+        }
+
+        fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+            self.steps.lifecycle(ctx, event, data, env);
+        }
+
+        fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+            self.steps.update(ctx, old_data, data, env);
+        }
+
+        fn layout(
+            &mut self,
+            ctx: &mut LayoutCtx,
+            bc: &BoxConstraints,
+            data: &T,
+            env: &Env,
+        ) -> Size {
+            let size = self.steps.layout(ctx, bc, data, env);
+            self.buttons
+                .set((self.steps.back.rect, self.steps.forward_button().rect));
+            size
+        }
+
+        fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+            self.steps.paint(ctx, data, env);
+        }
+    }
+
+    #[test]
+    fn validation_blocks_next_and_finish_runs() {
+        // whether the second page is filled in, and how often we finished.
+        let steps = Steps::new()
+            .with_step("Welcome", SizedBox::empty())
+            .with_validated_step("Details", SizedBox::empty(), |data: &(bool, u32), _| data.0)
+            .with_step("Done", SizedBox::empty())
+            .on_finish(|_, data: &mut (bool, u32), _| data.1 += 1)
+            .with_transition(TabsTransition::Instant);
+        let buttons = Rc::new(Cell::new((Rect::ZERO, Rect::ZERO)));
+        let current = Rc::new(Cell::new(0));
+        let probe = Probe {
+            steps,
+            buttons: buttons.clone(),
+            current: current.clone(),
+        };
+
+        Harness::create_simple((false, 0), probe, |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            let click_back = |harness: &mut Harness<'_, (bool, u32)>| {
+                harness.mouse_click(buttons.get().0.center());
+                current.get()
+            };
+            let click_forward = |harness: &mut Harness<'_, (bool, u32)>| {
+                harness.mouse_click(buttons.get().1.center());
+                current.get()
+            };
+
+            // there's nothing before the first page.
+            assert_eq!(click_back(harness), 0);
+            assert_eq!(click_forward(harness), 1);
+            // the second page isn't filled in yet.
+            assert_eq!(click_forward(harness), 1);
+            harness.update_data(|data| data.0 = true);
+            assert_eq!(click_forward(harness), 2);
+            assert_eq!(click_back(harness), 1);
+            assert_eq!(click_forward(harness), 2);
+            // "Finish" stays on the last page.
+            assert_eq!(click_forward(harness), 2);
+            assert_eq!(*harness.data(), (true, 1));
+        });
+    }
+}
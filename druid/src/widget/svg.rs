@@ -427,7 +427,7 @@ mod tests {
 
     #[test]
     fn translate() {
-        use crate::tests::harness::Harness;
+        use crate::testing::Harness;
 
         let svg_data = SvgData::from_str(
             "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 2 2'>
@@ -483,7 +483,7 @@ mod tests {
 
     #[test]
     fn scale() {
-        use crate::tests::harness::Harness;
+        use crate::testing::Harness;
 
         let svg_data = SvgData::from_str(
             "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 2 2'>
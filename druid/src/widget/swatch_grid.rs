@@ -0,0 +1,203 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A grid of color swatches to pick from.
+
+use std::sync::Arc;
+
+use crate::commands::COLOR_SELECTED;
+use crate::widget::prelude::*;
+use crate::{theme, Color, Insets, MouseButton, Point, Rect};
+use tracing::{instrument, trace};
+
+const DEFAULT_SWATCH_SIZE: f64 = 20.0;
+const SPACING: f64 = 4.0;
+const OUTLINE_WIDTH: f64 = 2.0;
+
+/// A grid of color swatches, such as a palette of recently used colors.
+///
+/// The colors are the data of the widget. Clicking a swatch submits
+/// [`COLOR_SELECTED`] as a notification with its color; the swatch that was
+/// clicked last is outlined.
+///
+/// The swatches fill the rows from left to right, with as many columns as
+/// fit in the width.
+///
+/// [`COLOR_SELECTED`]: crate::commands::COLOR_SELECTED
+pub struct SwatchGrid {
+    swatch_size: f64,
+    columns: usize,
+    hot: Option<usize>,
+    selected: Option<usize>,
+}
+
+impl SwatchGrid {
+    /// Create a new `SwatchGrid`.
+    pub fn new() -> Self {
+        SwatchGrid {
+            swatch_size: DEFAULT_SWATCH_SIZE,
+            columns: 1,
+            hot: None,
+            selected: None,
+        }
+    }
+
+    /// Builder-style method to set the size of the swatches.
+    ///
+    /// The default is 20.
+    pub fn with_swatch_size(mut self, size: f64) -> Self {
+        self.swatch_size = size;
+        self
+    }
+
+    fn swatch_rect(&self, index: usize) -> Rect {
+        let column = index % self.columns;
+        let row = index / self.columns;
+        let step = self.swatch_size + SPACING;
+        Rect::from_origin_size(
+            (column as f64 * step, row as f64 * step),
+            (self.swatch_size, self.swatch_size),
+        )
+    }
+
+    fn swatch_at(&self, pos: Point, count: usize) -> Option<usize> {
+        (0..count).find(|&index| self.swatch_rect(index).contains(pos))
+    }
+}
+
+impl Default for SwatchGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<Arc<Vec<Color>>> for SwatchGrid {
+    #[instrument(
+        name = "SwatchGrid",
+        level = "trace",
+        skip(self, ctx, event, data, _env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Arc<Vec<Color>>, _env: &Env) {
+        match event {
+            Event::MouseMove(mouse) => {
+                let hot = self.swatch_at(mouse.pos, data.len());
+                if hot != self.hot {
+                    self.hot = hot;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(mouse)
+                if mouse.button == MouseButton::Left
+                    && self.swatch_at(mouse.pos, data.len()).is_some() =>
+            {
+                ctx.set_active(true);
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                if let Some(index) = self.swatch_at(mouse.pos, data.len()) {
+                    trace!("SwatchGrid selected swatch {}", index);
+                    self.selected = Some(index);
+                    ctx.submit_notification(COLOR_SELECTED.with(data[index].clone()));
+                    ctx.request_paint();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "SwatchGrid",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        _data: &Arc<Vec<Color>>,
+        _env: &Env,
+    ) {
+        if let LifeCycle::HotChanged(false) = event {
+            self.hot = None;
+            ctx.request_paint();
+        }
+    }
+
+    #[instrument(
+        name = "SwatchGrid",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &Arc<Vec<Color>>,
+        data: &Arc<Vec<Color>>,
+        _env: &Env,
+    ) {
+        if !old_data.same(data) {
+            if old_data.len() != data.len() {
+                self.selected = None;
+                ctx.request_layout();
+            }
+            ctx.request_paint();
+        }
+    }
+
+    #[instrument(name = "SwatchGrid", level = "trace", skip(self, ctx, bc, data, _env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &Arc<Vec<Color>>,
+        _env: &Env,
+    ) -> Size {
+        bc.debug_check("SwatchGrid");
+        let step = self.swatch_size + SPACING;
+        self.columns = if bc.is_width_bounded() {
+            (((bc.max().width + SPACING) / step).floor() as usize).max(1)
+        } else {
+            data.len().max(1)
+        };
+        let columns = self.columns.min(data.len());
+        let rows = data.len().div_ceil(self.columns);
+        let width = (columns as f64 * step - SPACING).max(0.0);
+        let height = (rows as f64 * step - SPACING).max(0.0);
+        let size = bc.constrain(Size::new(width, height));
+        // the outlines are drawn around the swatches
+        ctx.set_paint_insets(Insets::uniform(OUTLINE_WIDTH * 1.5));
+        trace!("Computed layout: size={}, columns={}", size, self.columns);
+        size
+    }
+
+    #[instrument(name = "SwatchGrid", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Arc<Vec<Color>>, env: &Env) {
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        for (index, color) in data.iter().enumerate() {
+            let swatch = self.swatch_rect(index).to_rounded_rect(radius);
+            ctx.fill(swatch, color);
+            ctx.stroke(swatch, &env.get(theme::BORDER_DARK), 1.0);
+            if Some(index) == self.selected {
+                let outline = self
+                    .swatch_rect(index)
+                    .inset(OUTLINE_WIDTH * 0.75)
+                    .to_rounded_rect(radius);
+                ctx.stroke(outline, &env.get(theme::PRIMARY_LIGHT), OUTLINE_WIDTH);
+            } else if Some(index) == self.hot {
+                let outline = self.swatch_rect(index).inset(1.0).to_rounded_rect(radius);
+                ctx.stroke(outline, &env.get(theme::BORDER_LIGHT), 1.0);
+            }
+        }
+    }
+}
@@ -538,29 +538,44 @@ struct TabsBody<TP: TabsPolicy> {
     axis: Axis,
     transition: TabsTransition,
     transition_state: Option<TabsTransitionState>,
+    defer_updates: bool,
     phantom_tp: PhantomData<TP>,
 }
 
 impl<TP: TabsPolicy> TabsBody<TP> {
-    fn new(axis: Axis, transition: TabsTransition) -> TabsBody<TP> {
+    fn new(axis: Axis, transition: TabsTransition, defer_updates: bool) -> TabsBody<TP> {
         TabsBody {
             children: vec![],
             axis,
             transition,
             transition_state: None,
+            defer_updates,
             phantom_tp: Default::default(),
         }
     }
 
     fn make_tabs(&mut self, data: &TabsState<TP>) -> Vec<usize> {
+        let defer_updates = self.defer_updates;
         ensure_for_tabs(
             &mut self.children,
             &data.policy,
             &data.inner,
-            |policy, key| WidgetPod::new(policy.tab_body(key, &data.inner)),
+            |policy, key| {
+                let mut pod = WidgetPod::new(policy.tab_body(key, &data.inner));
+                pod.set_defer_updates(defer_updates);
+                pod
+            },
         )
     }
 
+    /// Freeze the tabs that are not shown, so that they don't use any time.
+    fn freeze_hidden(&mut self, selected: TabIndex) {
+        let previous = self.transition_state.as_ref().map(|t| t.previous_idx);
+        for (idx, (_, child)) in self.children.iter_mut().enumerate() {
+            child.set_frozen(idx != selected && Some(idx) != previous);
+        }
+    }
+
     fn active_child(&mut self, state: &TabsState<TP>) -> Option<&mut TabBodyPod<TP>> {
         Self::child(&mut self.children, state.selected)
     }
@@ -595,6 +610,8 @@ impl<TP: TabsPolicy> Widget<TabsState<TP>> for TabsBody<TP> {
                 ctx.request_anim_frame();
             } else {
                 self.transition_state = None;
+                self.freeze_hidden(data.selected);
+                ctx.request_update();
             }
             ctx.request_paint();
         }
@@ -610,6 +627,7 @@ impl<TP: TabsPolicy> Widget<TabsState<TP>> for TabsBody<TP> {
     ) {
         if let LifeCycle::WidgetAdded = event {
             self.make_tabs(data);
+            self.freeze_hidden(data.selected);
             ctx.children_changed();
             ctx.request_layout();
         }
@@ -654,6 +672,9 @@ impl<TP: TabsPolicy> Widget<TabsState<TP>> for TabsBody<TP> {
                 ctx.request_anim_frame();
             }
         }
+        if init.is_some() || old_data.selected != data.selected {
+            self.freeze_hidden(data.selected);
+        }
 
         // Make sure to only pass events to initialised children
         if let Some(init) = init {
@@ -836,6 +857,7 @@ pub struct Tabs<TP: TabsPolicy> {
     axis: Axis,
     edge: TabsEdge,
     transition: TabsTransition,
+    defer_hidden_updates: bool,
     content: TabsContent<TP>,
 }
 
@@ -859,6 +881,7 @@ impl<TP: TabsPolicy> Tabs<TP> {
             axis: Axis::Horizontal,
             edge: Default::default(),
             transition: Default::default(),
+            defer_hidden_updates: false,
             content,
         }
     }
@@ -899,6 +922,18 @@ impl<TP: TabsPolicy> Tabs<TP> {
         self
     }
 
+    /// Builder-style method to defer the updates of tabs that are not
+    /// selected, until they are selected again.
+    ///
+    /// Tabs that are not selected are always frozen, so they are not painted
+    /// and don't animate; see [`WidgetPod::set_frozen`]. Deferring their
+    /// updates as well saves more time when the data changes often, but the
+    /// events they get (such as commands) come with data they haven't seen.
+    pub fn with_deferred_updates(mut self, defer: bool) -> Self {
+        self.defer_hidden_updates = defer;
+        self
+    }
+
     /// Available when the policy implements AddTab - e.g StaticTabs.
     /// Return this Tabs widget with the named tab added.
     pub fn with_tab(
@@ -931,7 +966,7 @@ impl<TP: TabsPolicy> Tabs<TP> {
 
     fn make_scope(&self, tabs_from_data: TP) -> WidgetPod<TP::Input, TabsScope<TP>> {
         let tabs_bar = TabBar::new(self.axis, self.edge);
-        let tabs_body = TabsBody::new(self.axis, self.transition)
+        let tabs_body = TabsBody::new(self.axis, self.transition, self.defer_hidden_updates)
             .padding(5.)
             .border(theme::BORDER_DARK, 0.5);
         let mut layout: Flex<TabsState<TP>> = Flex::for_axis(self.axis.cross());
@@ -0,0 +1,1144 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that shows the output of a terminal program.
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::process::Child;
+
+use crate::piet::{
+    FontFamily, FontStyle, FontWeight, Text, TextAttribute, TextLayout, TextLayoutBuilder,
+};
+use crate::widget::prelude::*;
+use crate::{
+    theme, Application, Color, ExtEventSink, HotKey, KbKey, KeyEvent, MouseButton, Point, Rect,
+    Selector, SysMods, Target,
+};
+use tracing::{instrument, trace, warn};
+
+// sent by the reading thread to the widget.
+const OUTPUT: Selector<Vec<u8>> = Selector::new("druid.builtin.terminal-output");
+
+const DEFAULT_FONT_SIZE: f64 = 13.0;
+const DEFAULT_SCROLLBACK: usize = 10_000;
+const DEFAULT_COLS: usize = 80;
+const DEFAULT_ROWS: usize = 24;
+const TAB_WIDTH: usize = 8;
+
+/// The 16 standard colors, used for the basic and bright ANSI colors.
+const PALETTE: [Color; 16] = [
+    Color::rgb8(0x00, 0x00, 0x00),
+    Color::rgb8(0xcd, 0x31, 0x31),
+    Color::rgb8(0x0d, 0xbc, 0x79),
+    Color::rgb8(0xe5, 0xe5, 0x10),
+    Color::rgb8(0x24, 0x72, 0xc8),
+    Color::rgb8(0xbc, 0x3f, 0xbc),
+    Color::rgb8(0x11, 0xa8, 0xcd),
+    Color::rgb8(0xe5, 0xe5, 0xe5),
+    Color::rgb8(0x66, 0x66, 0x66),
+    Color::rgb8(0xf1, 0x4c, 0x4c),
+    Color::rgb8(0x23, 0xd1, 0x8b),
+    Color::rgb8(0xf5, 0xf5, 0x43),
+    Color::rgb8(0x3b, 0x8e, 0xea),
+    Color::rgb8(0xd6, 0x70, 0xd6),
+    Color::rgb8(0x29, 0xb8, 0xdb),
+    Color::rgb8(0xff, 0xff, 0xff),
+];
+
+/// A program that runs in a [`TerminalView`], usually through a
+/// pseudo-terminal.
+///
+/// Druid does not create pseudo-terminals itself; implementations of this
+/// trait wrap a library that does, or a plain child process. There is an
+/// implementation for [`std::process::Child`], for showing the output of a
+/// command; its stdout must be piped.
+pub trait PtyBackend: 'static {
+    /// Returns the reader for the output of the program.
+    ///
+    /// This is called once, when the `TerminalView` is added to the widget
+    /// tree, and the reader is moved to a background thread. Reading should
+    /// block until there is output, and return `0` once the program is done.
+    fn take_reader(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// Send input to the program, such as typed keys or pasted text.
+    fn write(&mut self, bytes: &[u8]);
+
+    /// Called when the number of columns and rows that fit in the
+    /// `TerminalView` changes.
+    fn resize(&mut self, _cols: usize, _rows: usize) {}
+}
+
+impl PtyBackend for Child {
+    fn take_reader(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout
+            .take()
+            .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if let Some(stdin) = &mut self.stdin {
+            if let Err(e) = stdin.write_all(bytes) {
+                warn!("failed to write to child process: {}", e);
+            }
+        }
+    }
+}
+
+/// A color, as set by an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TermColor {
+    /// The default foreground or background color.
+    Default,
+    /// One of the 256 colors of the xterm palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl TermColor {
+    fn resolve(self, default: &Color) -> Color {
+        match self {
+            TermColor::Default => default.clone(),
+            TermColor::Indexed(idx) => indexed_color(idx),
+            TermColor::Rgb(r, g, b) => Color::rgb8(r, g, b),
+        }
+    }
+}
+
+/// The color with `idx` in the xterm palette: the 16 standard colors, a 6x6x6
+/// color cube and a ramp of greys.
+fn indexed_color(idx: u8) -> Color {
+    match idx {
+        0..=15 => PALETTE[idx as usize].clone(),
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let idx = idx - 16;
+            Color::rgb8(level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+        }
+        _ => {
+            let grey = 8 + (idx - 232) * 10;
+            Color::rgb8(grey, grey, grey)
+        }
+    }
+}
+
+/// The attributes of a cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Style {
+    fg: TermColor,
+    bg: TermColor,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    inverse: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fg: TermColor::Default,
+            bg: TermColor::Default,
+            bold: false,
+            italic: false,
+            underline: false,
+            inverse: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+impl Cell {
+    fn is_blank(self) -> bool {
+        self.ch == ' ' && self.style == Style::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParseState {
+    Ground,
+    Escape,
+    /// After `ESC (` and similar, which are followed by one more byte.
+    Charset,
+    Csi,
+    Osc,
+    /// An `ESC` within an operating system command, which may start the
+    /// string terminator.
+    OscEscape,
+}
+
+/// The lines of a terminal, and the parser that writes to them.
+///
+/// This understands the common subset of the VT100 and xterm escape
+/// sequences: cursor movement, erasing and colors and attributes. Other
+/// sequences are skipped.
+struct Screen {
+    /// The scrollback, followed by the `rows` lines of the screen.
+    ///
+    /// Lines are as long as their last written cell.
+    lines: VecDeque<Vec<Cell>>,
+    /// The number of lines that were dropped from the start of the
+    /// scrollback, so that positions can refer to lines independently of that.
+    dropped: usize,
+    cols: usize,
+    rows: usize,
+    max_scrollback: usize,
+    /// The row of the cursor, within the screen.
+    cursor_row: usize,
+    cursor_col: usize,
+    cursor_visible: bool,
+    style: Style,
+    state: ParseState,
+    params: Vec<u16>,
+    /// Whether the control sequence starts with `?`.
+    private: bool,
+    /// The bytes of a UTF-8 sequence that is not complete yet.
+    utf8: Vec<u8>,
+}
+
+impl Screen {
+    fn new(cols: usize, rows: usize, max_scrollback: usize) -> Self {
+        Screen {
+            lines: (0..rows).map(|_| Vec::new()).collect(),
+            dropped: 0,
+            cols,
+            rows,
+            max_scrollback,
+            cursor_row: 0,
+            cursor_col: 0,
+            cursor_visible: true,
+            style: Style::default(),
+            state: ParseState::Ground,
+            params: Vec::new(),
+            private: false,
+            utf8: Vec::new(),
+        }
+    }
+
+    /// The number of lines in the scrollback.
+    fn scrollback_len(&self) -> usize {
+        self.lines.len() - self.rows
+    }
+
+    fn line_mut(&mut self, row: usize) -> &mut Vec<Cell> {
+        let idx = self.scrollback_len() + row;
+        &mut self.lines[idx]
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        if rows > self.rows {
+            let extra = rows - self.rows;
+            self.lines.extend((0..extra).map(|_| Vec::new()));
+        } else {
+            let mut excess = self.rows - rows;
+            // drop blank lines below the cursor, before pushing lines into
+            // the scrollback.
+            while excess > 0
+                && self.cursor_row + 1 < self.rows
+                && self
+                    .lines
+                    .back()
+                    .is_some_and(|l| l.iter().all(|cell| cell.is_blank()))
+            {
+                self.lines.pop_back();
+                self.rows -= 1;
+                excess -= 1;
+            }
+            self.cursor_row = self.cursor_row.saturating_sub(excess);
+        }
+        self.rows = rows;
+        self.cols = cols;
+        self.cursor_row = self.cursor_row.min(rows - 1);
+        self.cursor_col = self.cursor_col.min(cols);
+        self.trim_scrollback();
+    }
+
+    fn trim_scrollback(&mut self) {
+        while self.scrollback_len() > self.max_scrollback {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.advance(byte);
+        }
+    }
+
+    fn advance(&mut self, byte: u8) {
+        match self.state {
+            ParseState::Ground => self.ground(byte),
+            ParseState::Escape => match byte {
+                b'[' => {
+                    self.state = ParseState::Csi;
+                    self.params.clear();
+                    self.private = false;
+                }
+                b']' => self.state = ParseState::Osc,
+                b'(' | b')' | b'*' | b'+' => self.state = ParseState::Charset,
+                b'M' => {
+                    self.cursor_row = self.cursor_row.saturating_sub(1);
+                    self.state = ParseState::Ground;
+                }
+                _ => self.state = ParseState::Ground,
+            },
+            ParseState::Charset => self.state = ParseState::Ground,
+            ParseState::Csi => match byte {
+                b'0'..=b'9' => {
+                    if self.params.is_empty() {
+                        self.params.push(0);
+                    }
+                    let param = self.params.last_mut().unwrap();
+                    *param = param
+                        .saturating_mul(10)
+                        .saturating_add((byte - b'0') as u16);
+                }
+                b';' => {
+                    if self.params.is_empty() {
+                        self.params.push(0);
+                    }
+                    self.params.push(0);
+                }
+                b'?' => self.private = true,
+                // other intermediate and parameter bytes
+                0x20..=0x3f => (),
+                0x40..=0x7e => {
+                    self.state = ParseState::Ground;
+                    self.csi(byte);
+                }
+                // a control character cancels the sequence
+                _ => self.state = ParseState::Ground,
+            },
+            // the window title and similar are ignored.
+            ParseState::Osc => match byte {
+                0x07 => self.state = ParseState::Ground,
+                0x1b => self.state = ParseState::OscEscape,
+                _ => (),
+            },
+            ParseState::OscEscape => {
+                self.state = if byte == b'\\' {
+                    ParseState::Ground
+                } else {
+                    ParseState::Osc
+                };
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        if !self.utf8.is_empty() || byte >= 0x80 {
+            self.utf8.push(byte);
+            let expected = match self.utf8[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if self.utf8.len() >= expected {
+                let ch = std::str::from_utf8(&self.utf8)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .unwrap_or(char::REPLACEMENT_CHARACTER);
+                self.utf8.clear();
+                self.put(ch);
+            }
+            return;
+        }
+        match byte {
+            0x1b => self.state = ParseState::Escape,
+            // output that does not come through a pty, such as that of a
+            // piped command, has no carriage returns; a bare line feed also
+            // starts a new line.
+            b'\n' | 0x0b | 0x0c => {
+                self.line_feed();
+                self.cursor_col = 0;
+            }
+            b'\r' => self.cursor_col = 0,
+            0x08 => self.cursor_col = self.cursor_col.min(self.cols - 1).saturating_sub(1),
+            b'\t' => {
+                let next = (self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor_col = next.min(self.cols - 1);
+            }
+            0x00..=0x1f | 0x7f => (),
+            _ => self.put(byte as char),
+        }
+    }
+
+    fn put(&mut self, ch: char) {
+        // the cursor stays after the last column until the next character,
+        // which then wraps.
+        if self.cursor_col >= self.cols {
+            self.cursor_col = 0;
+            self.line_feed();
+        }
+        let (col, cell) = (
+            self.cursor_col,
+            Cell {
+                ch,
+                style: self.style,
+            },
+        );
+        let line = self.line_mut(self.cursor_row);
+        if line.len() <= col {
+            line.resize(col, Cell::default());
+            line.push(cell);
+        } else {
+            line[col] = cell;
+        }
+        self.cursor_col += 1;
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor_row + 1 < self.rows {
+            self.cursor_row += 1;
+        } else {
+            self.lines.push_back(Vec::new());
+            self.trim_scrollback();
+        }
+    }
+
+    /// The parameter at `idx`, or `default` if it is missing or zero.
+    fn param(&self, idx: usize, default: usize) -> usize {
+        match self.params.get(idx) {
+            Some(&p) if p != 0 => p as usize,
+            _ => default,
+        }
+    }
+
+    fn csi(&mut self, action: u8) {
+        if self.private {
+            // show or hide the cursor; other private modes are ignored.
+            if self.params.first() == Some(&25) {
+                match action {
+                    b'h' => self.cursor_visible = true,
+                    b'l' => self.cursor_visible = false,
+                    _ => (),
+                }
+            }
+            return;
+        }
+        let n = self.param(0, 1);
+        let last_row = self.rows - 1;
+        let last_col = self.cols - 1;
+        match action {
+            b'A' => self.cursor_row = self.cursor_row.saturating_sub(n),
+            b'B' => self.cursor_row = (self.cursor_row + n).min(last_row),
+            b'C' => self.cursor_col = (self.cursor_col + n).min(last_col),
+            b'D' => self.cursor_col = self.cursor_col.min(last_col).saturating_sub(n),
+            b'E' => {
+                self.cursor_row = (self.cursor_row + n).min(last_row);
+                self.cursor_col = 0;
+            }
+            b'F' => {
+                self.cursor_row = self.cursor_row.saturating_sub(n);
+                self.cursor_col = 0;
+            }
+            b'G' => self.cursor_col = (n - 1).min(last_col),
+            b'd' => self.cursor_row = (n - 1).min(last_row),
+            b'H' | b'f' => {
+                self.cursor_row = (n - 1).min(last_row);
+                self.cursor_col = (self.param(1, 1) - 1).min(last_col);
+            }
+            b'J' => self.erase_display(self.param(0, 0)),
+            b'K' => {
+                let (row, col) = (self.cursor_row, self.cursor_col);
+                self.erase_line(row, col, self.param(0, 0));
+            }
+            b'X' => {
+                let (row, col) = (self.cursor_row, self.cursor_col);
+                let line = self.line_mut(row);
+                let end = (col + n).min(line.len());
+                for cell in line.iter_mut().take(end).skip(col) {
+                    *cell = Cell::default();
+                }
+            }
+            b'm' => self.select_graphic_rendition(),
+            _ => trace!("unhandled control sequence {:?}", action as char),
+        }
+    }
+
+    /// Erase part of the line at `row`: after `col` for mode 0, up to it for
+    /// mode 1, and all of it otherwise.
+    fn erase_line(&mut self, row: usize, col: usize, mode: usize) {
+        let line = self.line_mut(row);
+        match mode {
+            0 => line.truncate(col),
+            1 => {
+                let end = (col + 1).min(line.len());
+                for cell in &mut line[..end] {
+                    *cell = Cell::default();
+                }
+            }
+            _ => line.clear(),
+        }
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        match mode {
+            0 => {
+                self.erase_line(row, col, 0);
+                for below in row + 1..self.rows {
+                    self.line_mut(below).clear();
+                }
+            }
+            1 => {
+                self.erase_line(row, col, 1);
+                for above in 0..row {
+                    self.line_mut(above).clear();
+                }
+            }
+            _ => {
+                for row in 0..self.rows {
+                    self.line_mut(row).clear();
+                }
+            }
+        }
+    }
+
+    fn select_graphic_rendition(&mut self) {
+        if self.params.is_empty() {
+            self.style = Style::default();
+            return;
+        }
+        let params = std::mem::take(&mut self.params);
+        let mut iter = params.iter().copied();
+        while let Some(param) = iter.next() {
+            let style = &mut self.style;
+            match param {
+                0 => *style = Style::default(),
+                1 => style.bold = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                7 => style.inverse = true,
+                22 => style.bold = false,
+                23 => style.italic = false,
+                24 => style.underline = false,
+                27 => style.inverse = false,
+                30..=37 => style.fg = TermColor::Indexed((param - 30) as u8),
+                38 => style.fg = extended_color(&mut iter),
+                39 => style.fg = TermColor::Default,
+                40..=47 => style.bg = TermColor::Indexed((param - 40) as u8),
+                48 => style.bg = extended_color(&mut iter),
+                49 => style.bg = TermColor::Default,
+                90..=97 => style.fg = TermColor::Indexed((param - 90 + 8) as u8),
+                100..=107 => style.bg = TermColor::Indexed((param - 100 + 8) as u8),
+                _ => (),
+            }
+        }
+        self.params = params;
+    }
+}
+
+/// Parse the rest of a `38` or `48` attribute: `5;n` for the palette, or
+/// `2;r;g;b`.
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> TermColor {
+    let mut next = || params.next().unwrap_or(0).min(255) as u8;
+    match next() {
+        5 => TermColor::Indexed(next()),
+        2 => {
+            let (r, g, b) = (next(), next(), next());
+            TermColor::Rgb(r, g, b)
+        }
+        _ => TermColor::Default,
+    }
+}
+
+/// A position between two cells, in absolute lines that do not change when
+/// the scrollback is trimmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct GridPos {
+    line: usize,
+    col: usize,
+}
+
+/// A widget that shows the output of a terminal program, such as a shell or
+/// a build command.
+///
+/// Text is laid out on a monospace grid, with the colors and attributes set
+/// by ANSI escape sequences. Lines that scroll off the top are kept in the
+/// scrollback, which can be scrolled with the mouse wheel.
+///
+/// The program is a [`PtyBackend`]; its output is read on a background
+/// thread, and keys typed into the focused widget are sent to it. The grid
+/// fills the widget, and the backend is told when its size changes.
+///
+/// Text can be selected with the mouse, and copied with the copy command, or
+/// with ctrl+shift+C (cmd+C on macOS). Ctrl+shift+V (cmd+V) pastes.
+pub struct TerminalView {
+    backend: Option<Box<dyn PtyBackend>>,
+    screen: Screen,
+    font_size: f64,
+    cell_size: Size,
+    /// How many lines up from the bottom the view is scrolled.
+    scroll_offset: usize,
+    /// The anchor and the moving end of the selection.
+    selection: Option<(GridPos, GridPos)>,
+}
+
+impl TerminalView {
+    /// Create a `TerminalView` that shows the output of `backend`.
+    pub fn new(backend: impl PtyBackend) -> Self {
+        TerminalView {
+            backend: Some(Box::new(backend)),
+            screen: Screen::new(DEFAULT_COLS, DEFAULT_ROWS, DEFAULT_SCROLLBACK),
+            font_size: DEFAULT_FONT_SIZE,
+            cell_size: Size::ZERO,
+            scroll_offset: 0,
+            selection: None,
+        }
+    }
+
+    /// Builder-style method to set the size of the text.
+    ///
+    /// The default is 13.
+    pub fn with_font_size(mut self, size: f64) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    /// Builder-style method to set how many lines are kept in the scrollback.
+    ///
+    /// The default is 10,000.
+    pub fn with_scrollback(mut self, lines: usize) -> Self {
+        self.screen.max_scrollback = lines;
+        self.screen.trim_scrollback();
+        self
+    }
+
+    /// The number of columns and rows of the grid.
+    pub fn grid_size(&self) -> (usize, usize) {
+        (self.screen.cols, self.screen.rows)
+    }
+
+    /// The selected text, with one line per row.
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.ordered_selection()?;
+        let mut text = String::new();
+        for line in start.line..=end.line {
+            let cells = match self.line(line) {
+                Some(cells) => cells,
+                None => continue,
+            };
+            let from = if line == start.line { start.col } else { 0 };
+            let to = if line == end.line {
+                end.col
+            } else {
+                cells.len()
+            };
+            let row: String = cells
+                .iter()
+                .take(to.min(cells.len()))
+                .skip(from)
+                .map(|cell| cell.ch)
+                .collect();
+            if line != start.line {
+                text.push('\n');
+            }
+            text.push_str(row.trim_end());
+        }
+        Some(text)
+    }
+
+    fn ordered_selection(&self) -> Option<(GridPos, GridPos)> {
+        match self.selection {
+            Some((a, b)) if a != b => Some((a.min(b), a.max(b))),
+            _ => None,
+        }
+    }
+
+    /// The cells of an absolute line, if it is still kept.
+    fn line(&self, line: usize) -> Option<&Vec<Cell>> {
+        line.checked_sub(self.screen.dropped)
+            .and_then(|idx| self.screen.lines.get(idx))
+    }
+
+    /// The absolute line at the top of the view.
+    fn first_visible_line(&self) -> usize {
+        self.screen.dropped + self.screen.scrollback_len() - self.scroll_offset
+    }
+
+    fn pos_at(&self, point: Point) -> GridPos {
+        if self.cell_size.is_empty() {
+            return GridPos { line: 0, col: 0 };
+        }
+        let row = (point.y / self.cell_size.height)
+            .floor()
+            .max(0.0)
+            .min((self.screen.rows - 1) as f64) as usize;
+        let col = (point.x / self.cell_size.width)
+            .round()
+            .max(0.0)
+            .min(self.screen.cols as f64) as usize;
+        GridPos {
+            line: self.first_visible_line() + row,
+            col,
+        }
+    }
+
+    fn send(&mut self, bytes: &[u8]) {
+        if let Some(backend) = &mut self.backend {
+            backend.write(bytes);
+        }
+    }
+
+    /// Show the output of the program, keeping the view on the same lines if
+    /// it is scrolled up.
+    fn receive(&mut self, bytes: &[u8]) {
+        let before = self.screen.dropped + self.screen.lines.len();
+        self.screen.feed(bytes);
+        if self.scroll_offset > 0 {
+            let added = self.screen.dropped + self.screen.lines.len() - before;
+            self.scroll_offset = (self.scroll_offset + added).min(self.screen.scrollback_len());
+        }
+    }
+
+    fn scroll_lines(&mut self, ctx: &mut EventCtx, lines: isize) {
+        let max = self.screen.scrollback_len() as isize;
+        let offset = (self.scroll_offset as isize + lines).max(0).min(max) as usize;
+        if offset != self.scroll_offset {
+            self.scroll_offset = offset;
+            ctx.request_paint();
+        }
+    }
+
+    fn copy_selection(&self) {
+        if let Some(text) = self.selected_text() {
+            if !text.is_empty() {
+                Application::global().clipboard().put_string(text);
+            }
+        }
+    }
+
+    fn measure_cell(&mut self, ctx: &mut LayoutCtx) {
+        match ctx
+            .text()
+            .new_text_layout("M")
+            .font(FontFamily::MONOSPACE, self.font_size)
+            .build()
+        {
+            Ok(layout) => self.cell_size = layout.size(),
+            Err(e) => warn!("failed to measure terminal font: {}", e),
+        }
+    }
+
+    fn paint_run(
+        &self,
+        ctx: &mut PaintCtx,
+        origin: Point,
+        cells: &[Cell],
+        selected: bool,
+        env: &Env,
+    ) {
+        let style = cells[0].style;
+        let default_fg = env.get(theme::TEXT_COLOR);
+        let default_bg = env.get(theme::BACKGROUND_DARK);
+        let mut fg = style.fg.resolve(&default_fg);
+        let mut bg = match style.bg {
+            TermColor::Default => None,
+            color => Some(color.resolve(&default_bg)),
+        };
+        if style.inverse {
+            bg = Some(fg);
+            fg = style.bg.resolve(&default_bg);
+        }
+        if selected {
+            bg = Some(env.get(theme::SELECTED_TEXT_BACKGROUND_COLOR));
+        }
+        if let Some(bg) = bg {
+            let width = cells.len() as f64 * self.cell_size.width;
+            let rect = Rect::from_origin_size(origin, (width, self.cell_size.height));
+            ctx.fill(rect, &bg);
+        }
+        if cells.iter().all(|cell| cell.ch == ' ') && !style.underline {
+            return;
+        }
+
+        let text: String = cells.iter().map(|cell| cell.ch).collect();
+        let weight = if style.bold {
+            FontWeight::BOLD
+        } else {
+            FontWeight::REGULAR
+        };
+        let font_style = if style.italic {
+            FontStyle::Italic
+        } else {
+            FontStyle::Regular
+        };
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .font(FontFamily::MONOSPACE, self.font_size)
+            .default_attribute(weight)
+            .default_attribute(font_style)
+            .default_attribute(TextAttribute::Underline(style.underline))
+            .text_color(fg)
+            .build();
+        match layout {
+            Ok(layout) => ctx.draw_text(&layout, origin),
+            Err(e) => warn!("failed to build terminal text: {}", e),
+        }
+    }
+}
+
+impl<T: Data> Widget<T> for TerminalView {
+    #[instrument(
+        name = "TerminalView",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, _env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(OUTPUT) => {
+                let bytes = cmd.get_unchecked(OUTPUT);
+                self.receive(bytes);
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if ctx.is_focused() && cmd.is(crate::commands::COPY) => {
+                self.copy_selection();
+                ctx.set_handled();
+            }
+            Event::Paste(item) if ctx.is_focused() => {
+                if let Some(text) = item.get_string() {
+                    self.send(text.as_bytes());
+                }
+                ctx.set_handled();
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                ctx.request_focus();
+                ctx.set_active(true);
+                let pos = self.pos_at(mouse.pos);
+                self.selection = Some((pos, pos));
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                let pos = self.pos_at(mouse.pos);
+                if let Some((_, head)) = &mut self.selection {
+                    if *head != pos {
+                        *head = pos;
+                        ctx.request_paint();
+                    }
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.set_handled();
+            }
+            Event::Wheel(mouse) => {
+                if self.cell_size.height > 0.0 {
+                    let lines = (mouse.wheel_delta.y / self.cell_size.height).round() as isize;
+                    let lines = if lines == 0 {
+                        -(mouse.wheel_delta.y.signum() as isize)
+                    } else {
+                        -lines
+                    };
+                    self.scroll_lines(ctx, lines);
+                }
+                ctx.set_handled();
+            }
+            Event::KeyDown(key) if ctx.is_focused() => {
+                let mods = if cfg!(target_os = "macos") {
+                    SysMods::Cmd
+                } else {
+                    SysMods::CmdShift
+                };
+                if HotKey::new(mods, "C").matches(key) || HotKey::new(mods, "c").matches(key) {
+                    self.copy_selection();
+                } else if HotKey::new(mods, "V").matches(key) || HotKey::new(mods, "v").matches(key)
+                {
+                    // the window turns this into a `Paste` event.
+                    ctx.submit_command(crate::commands::PASTE.to(ctx.window_id()));
+                } else if let Some(bytes) = key_input(key) {
+                    self.send(&bytes);
+                    self.selection = None;
+                    self.scroll_offset = 0;
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "TerminalView",
+        level = "trace",
+        skip(self, ctx, event, _data, _env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, _env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                ctx.register_for_focus();
+                let reader = self.backend.as_mut().and_then(|b| b.take_reader());
+                if let Some(reader) = reader {
+                    let sink = ctx.get_external_handle();
+                    let widget = ctx.widget_id();
+                    std::thread::spawn(move || read_output(reader, sink, widget));
+                }
+            }
+            LifeCycle::FocusChanged(_) => ctx.request_paint(),
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "TerminalView",
+        level = "trace",
+        skip(self, ctx, _old_data, _data, _env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, _data: &T, _env: &Env) {
+        if ctx.env_changed() {
+            ctx.request_layout();
+        }
+    }
+
+    #[instrument(
+        name = "TerminalView",
+        level = "trace",
+        skip(self, ctx, bc, _data, _env)
+    )]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, _env: &Env) -> Size {
+        bc.debug_check("TerminalView");
+        self.measure_cell(ctx);
+        let cell = self.cell_size;
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            DEFAULT_COLS as f64 * cell.width
+        };
+        let height = if bc.is_height_bounded() {
+            bc.max().height
+        } else {
+            DEFAULT_ROWS as f64 * cell.height
+        };
+        let size = bc.constrain(Size::new(width, height));
+
+        if !cell.is_empty() {
+            let cols = ((size.width / cell.width).floor() as usize).max(1);
+            let rows = ((size.height / cell.height).floor() as usize).max(1);
+            if (cols, rows) != self.grid_size() {
+                self.screen.resize(cols, rows);
+                self.scroll_offset = self.scroll_offset.min(self.screen.scrollback_len());
+                if let Some(backend) = &mut self.backend {
+                    backend.resize(cols, rows);
+                }
+            }
+        }
+
+        trace!(
+            "Computed layout: size={}, grid={:?}",
+            size,
+            self.grid_size()
+        );
+        size
+    }
+
+    #[instrument(name = "TerminalView", level = "trace", skip(self, ctx, _data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
+        let size = ctx.size();
+        ctx.fill(size.to_rect(), &env.get(theme::BACKGROUND_DARK));
+        if self.cell_size.is_empty() {
+            return;
+        }
+        let cell = self.cell_size;
+        let selection = self.ordered_selection();
+        let first = self.first_visible_line();
+        ctx.with_save(|ctx| {
+            ctx.clip(size.to_rect());
+            for row in 0..self.screen.rows {
+                let line = first + row;
+                let cells = match self.line(line) {
+                    Some(cells) => cells,
+                    None => continue,
+                };
+                let cells = &cells[..cells.len().min(self.screen.cols)];
+                let is_selected = |col: usize| match selection {
+                    Some((start, end)) => {
+                        let pos = GridPos { line, col };
+                        start <= pos && pos < end
+                    }
+                    None => false,
+                };
+                // draw runs of cells that look the same.
+                let mut start = 0;
+                while start < cells.len() {
+                    let selected = is_selected(start);
+                    let mut end = start + 1;
+                    while end < cells.len()
+                        && cells[end].style == cells[start].style
+                        && is_selected(end) == selected
+                    {
+                        end += 1;
+                    }
+                    let origin = Point::new(start as f64 * cell.width, row as f64 * cell.height);
+                    self.paint_run(ctx, origin, &cells[start..end], selected, env);
+                    start = end;
+                }
+                // selected space after the end of the line.
+                if let Some((start, end)) = selection {
+                    let from = if line == start.line { start.col } else { 0 };
+                    let to = if line == end.line {
+                        end.col
+                    } else if line < end.line {
+                        self.screen.cols
+                    } else {
+                        0
+                    };
+                    let from = from.max(cells.len());
+                    if line >= start.line && to > from {
+                        let rect = Rect::new(
+                            from as f64 * cell.width,
+                            row as f64 * cell.height,
+                            to as f64 * cell.width,
+                            (row + 1) as f64 * cell.height,
+                        );
+                        ctx.fill(rect, &env.get(theme::SELECTED_TEXT_BACKGROUND_COLOR));
+                    }
+                }
+            }
+
+            if self.screen.cursor_visible && self.scroll_offset == 0 {
+                let col = self.screen.cursor_col.min(self.screen.cols - 1);
+                let rect = Rect::from_origin_size(
+                    (
+                        col as f64 * cell.width,
+                        self.screen.cursor_row as f64 * cell.height,
+                    ),
+                    cell,
+                );
+                let color = env.get(theme::CURSOR_COLOR);
+                if ctx.is_focused() {
+                    ctx.fill(rect, &color.with_alpha(0.6));
+                } else {
+                    ctx.stroke(rect.inset(-0.5), &color, 1.0);
+                }
+            }
+        });
+    }
+}
+
+/// The bytes that a terminal sends for a key, if any.
+fn key_input(key: &KeyEvent) -> Option<Vec<u8>> {
+    let bytes: &[u8] = match &key.key {
+        KbKey::Character(text) => {
+            let mut bytes = text.as_bytes().to_vec();
+            if key.mods.ctrl() && bytes.len() == 1 && bytes[0].is_ascii_alphabetic() {
+                bytes[0] = bytes[0].to_ascii_uppercase() & 0x1f;
+            }
+            if key.mods.alt() {
+                bytes.insert(0, 0x1b);
+            }
+            return Some(bytes);
+        }
+        KbKey::Enter => b"\r",
+        KbKey::Backspace => b"\x7f",
+        KbKey::Tab => b"\t",
+        KbKey::Escape => b"\x1b",
+        KbKey::ArrowUp => b"\x1b[A",
+        KbKey::ArrowDown => b"\x1b[B",
+        KbKey::ArrowRight => b"\x1b[C",
+        KbKey::ArrowLeft => b"\x1b[D",
+        KbKey::Home => b"\x1b[H",
+        KbKey::End => b"\x1b[F",
+        KbKey::Insert => b"\x1b[2~",
+        KbKey::Delete => b"\x1b[3~",
+        KbKey::PageUp => b"\x1b[5~",
+        KbKey::PageDown => b"\x1b[6~",
+        _ => return None,
+    };
+    Some(bytes.to_vec())
+}
+
+/// Read the output of the program on a background thread, and send it to the
+/// widget.
+fn read_output(mut reader: Box<dyn Read + Send>, sink: ExtEventSink, widget: WidgetId) {
+    let mut buf = [0; 4096];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return,
+            Ok(n) => {
+                let output = Box::new(buf[..n].to_vec());
+                if let Err(e) = sink.submit_command(OUTPUT, output, Target::Widget(widget)) {
+                    warn!("failed to deliver terminal output: {}", e);
+                    return;
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => {
+                warn!("failed to read terminal output: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    fn text(screen: &Screen, row: usize) -> String {
+        let idx = screen.scrollback_len() + row;
+        screen.lines[idx].iter().map(|cell| cell.ch).collect()
+    }
+
+    #[test]
+    fn parse_colors_and_cursor_movement() {
+        let mut screen = Screen::new(10, 3, 100);
+        screen.feed(b"ab\x1b[1;31mcd\x1b[0me\r\n\x1b[38;5;200mx\x1b[2;4Hy");
+        assert_eq!(text(&screen, 0), "abcde");
+        let cells = &screen.lines[0];
+        assert_eq!(cells[1].style, Style::default());
+        assert!(cells[2].style.bold);
+        assert_eq!(cells[3].style.fg, TermColor::Indexed(1));
+        assert_eq!(cells[4].style, Style::default());
+        assert_eq!(text(&screen, 1), "x  y");
+        assert_eq!(screen.lines[1][0].style.fg, TermColor::Indexed(200));
+
+        screen.feed("\x1b[2J\x1b[Hé".as_bytes());
+        assert_eq!(text(&screen, 0), "é");
+        assert_eq!(text(&screen, 1), "");
+    }
+
+    #[test]
+    fn wrap_and_scrollback() {
+        let mut screen = Screen::new(4, 2, 2);
+        screen.feed(b"abcdef\nghi\njkl\nmno");
+        assert_eq!(screen.scrollback_len(), 2);
+        assert_eq!(screen.dropped, 1);
+        assert_eq!(text(&screen, 0), "jkl");
+        assert_eq!(text(&screen, 1), "mno");
+        assert_eq!(
+            screen.lines[0].iter().map(|c| c.ch).collect::<String>(),
+            "ef"
+        );
+    }
+}
@@ -20,16 +20,15 @@ use tracing::{instrument, trace};
 use crate::kurbo::Insets;
 use crate::piet::TextLayout as _;
 use crate::text::{
-    EditableText, ImeInvalidation, Selection, TextComponent, TextLayout, TextStorage,
+    EditableText, Highlights, ImeInvalidation, Selection, TextComponent, TextLayout, TextStorage,
 };
 use crate::widget::prelude::*;
 use crate::widget::{Padding, Scroll, WidgetWrapper};
 use crate::{
-    theme, Color, Command, FontDescriptor, HotKey, KeyEvent, KeyOrValue, Point, Rect, SysMods,
-    TextAlignment, TimerToken, Vec2,
+    theme, Color, Command, FontDescriptor, HotKey, KeyEvent, KeyOrValue, Point, Rect, Selector,
+    SysMods, TextAlignment, TimerToken, Vec2,
 };
 
-const CURSOR_BLINK_DURATION: Duration = Duration::from_millis(500);
 const MAC_OR_LINUX: bool = cfg!(any(target_os = "macos", target_os = "linux"));
 
 /// When we scroll after editing or movement, we show a little extra of the document.
@@ -44,6 +43,13 @@ const SCROLL_TO_INSETS: Insets = Insets::uniform_xy(40.0, 0.0);
 /// [`Formatter`]. You can create a [`ValueTextBox`] by passing the appropriate
 /// [`Formatter`] to [`TextBox::with_formatter`].
 ///
+/// # Primary selection
+///
+/// On X11, selecting text with the mouse also sets the primary selection, and
+/// a middle click pastes the primary selection at the clicked position. On
+/// other platforms there is no primary selection and middle clicks behave like
+/// any other click.
+///
 /// [`Formatter`]: crate::text::format::Formatter
 /// [`ValueTextBox`]: super::ValueTextBox
 pub struct TextBox<T> {
@@ -67,6 +73,23 @@ pub struct TextBox<T> {
     text_pos: Point,
 }
 
+impl TextBox<()> {
+    /// Replace the [`Highlights`] drawn behind the text, such as the matches of a search.
+    pub const SET_HIGHLIGHTS: Selector<Highlights> =
+        Selector::new("druid-builtin.textbox-set-highlights");
+
+    /// Make the next highlighted match current, select it, and scroll it into view.
+    ///
+    /// This wraps around to the first match.
+    pub const SELECT_NEXT_MATCH: Selector = Selector::new("druid-builtin.textbox-next-match");
+
+    /// Make the previous highlighted match current, select it, and scroll it into view.
+    ///
+    /// This wraps around to the last match.
+    pub const SELECT_PREVIOUS_MATCH: Selector =
+        Selector::new("druid-builtin.textbox-previous-match");
+}
+
 impl<T: EditableText + TextStorage> TextBox<T> {
     /// Create a new TextBox widget.
     pub fn new() -> Self {
@@ -292,9 +315,11 @@ impl<T> TextBox<T> {
         self.inner.child_mut().wrapped_mut()
     }
 
-    fn reset_cursor_blink(&mut self, token: TimerToken) {
+    /// Show the cursor, and start the timer for hiding it again, if the
+    /// cursor blinks.
+    fn reset_cursor_blink(&mut self, token: Option<TimerToken>) {
         self.cursor_on = true;
-        self.cursor_timer = token;
+        self.cursor_timer = token.unwrap_or(TimerToken::INVALID);
     }
 
     fn should_draw_cursor(&self) -> bool {
@@ -307,6 +332,43 @@ impl<T> TextBox<T> {
 }
 
 impl<T: TextStorage + EditableText> TextBox<T> {
+    /// Set the [`Highlights`] drawn behind the text.
+    ///
+    /// If you change this property, you are responsible for calling
+    /// [`request_paint`] to ensure the textbox is redrawn. From outside the
+    /// widget, you can submit [`TextBox::SET_HIGHLIGHTS`] instead.
+    ///
+    /// [`request_paint`]: crate::EventCtx::request_paint
+    pub fn set_highlights(&mut self, highlights: Highlights) {
+        if self.text().can_write() {
+            self.text_mut().borrow_mut().set_highlights(highlights);
+        }
+    }
+
+    /// Move to the next or previous highlighted match, selecting it.
+    fn select_match(&mut self, ctx: &mut EventCtx, data: &T, forward: bool) {
+        if !self.text().can_write() {
+            return;
+        }
+        let range = {
+            let mut session = self.text_mut().borrow_mut();
+            let highlights = session.highlights_mut();
+            if forward {
+                highlights.select_next()
+            } else {
+                highlights.select_previous()
+            }
+        };
+        if let Some(range) = range {
+            let selection = Selection::new(range.start, range.end).constrained(data.as_str());
+            if let Some(inval) = self.text_mut().borrow_mut().set_selection(selection) {
+                ctx.invalidate_text_input(inval);
+            }
+            self.scroll_to_selection_end();
+        }
+        ctx.request_paint();
+    }
+
     fn rect_for_selection_end(&self) -> Rect {
         let text = self.text().borrow();
         let layout = text.layout.layout().unwrap();
@@ -404,7 +466,9 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
                     if !mouse.focus {
                         ctx.request_focus();
                         self.was_focused_from_click = true;
-                        self.reset_cursor_blink(ctx.request_timer(CURSOR_BLINK_DURATION));
+                        self.reset_cursor_blink(
+                            cursor_blink_interval(env).map(|d| ctx.request_timer(d)),
+                        );
                     } else {
                         ctx.set_handled();
                     }
@@ -413,9 +477,14 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
             Event::Timer(id) => {
                 if !ctx.is_disabled() {
                     if *id == self.cursor_timer && ctx.has_focus() {
-                        self.cursor_on = !self.cursor_on;
+                        match cursor_blink_interval(env) {
+                            Some(interval) => {
+                                self.cursor_on = !self.cursor_on;
+                                self.cursor_timer = ctx.request_timer(interval);
+                            }
+                            None => self.reset_cursor_blink(None),
+                        }
                         ctx.request_paint();
-                        self.cursor_timer = ctx.request_timer(CURSOR_BLINK_DURATION);
                     }
                 } else if self.cursor_on {
                     self.cursor_on = false;
@@ -423,7 +492,7 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
                 }
             }
             Event::ImeStateChange => {
-                self.reset_cursor_blink(ctx.request_timer(CURSOR_BLINK_DURATION));
+                self.reset_cursor_blink(cursor_blink_interval(env).map(|d| ctx.request_timer(d)));
             }
             Event::Command(ref cmd)
                 if !self.text().is_composing()
@@ -444,6 +513,20 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
                 }
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(TextBox::SET_HIGHLIGHTS) => {
+                let highlights = cmd.get_unchecked(TextBox::SET_HIGHLIGHTS).clone();
+                self.set_highlights(highlights);
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(TextBox::SELECT_NEXT_MATCH) => {
+                self.select_match(ctx, data, true);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(TextBox::SELECT_PREVIOUS_MATCH) => {
+                self.select_match(ctx, data, false);
+                ctx.set_handled();
+            }
             Event::Paste(ref item) if self.text().can_write() => {
                 if let Some(string) = item.get_string() {
                     let text = if self.multiline {
@@ -479,7 +562,7 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
                     ctx.invalidate_text_input(ImeInvalidation::SelectionChanged);
                 }
                 self.text_mut().has_focus = true;
-                self.reset_cursor_blink(ctx.request_timer(CURSOR_BLINK_DURATION));
+                self.reset_cursor_blink(cursor_blink_interval(env).map(|d| ctx.request_timer(d)));
                 self.was_focused_from_click = false;
                 ctx.request_paint();
             }
@@ -509,6 +592,10 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
         if ctx.env_changed() && self.placeholder.needs_rebuild_after_update(ctx) {
             ctx.request_layout();
         }
+        if ctx.env_key_changed(&theme::CURSOR_BLINK_INTERVAL) && ctx.has_focus() {
+            self.reset_cursor_blink(cursor_blink_interval(env).map(|d| ctx.request_timer(d)));
+            ctx.request_paint();
+        }
         if self.text().can_write() {
             if let Some(ime_invalidation) = self.text_mut().borrow_mut().pending_ime_invalidation()
             {
@@ -564,6 +651,7 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
         let size = ctx.size();
         let background_color = env.get(theme::BACKGROUND_LIGHT);
         let cursor_color = env.get(theme::CURSOR_COLOR);
+        let cursor_width = env.get(theme::CURSOR_WIDTH);
         let border_width = env.get(theme::TEXTBOX_BORDER_WIDTH);
         let textbox_insets = env.get(theme::TEXTBOX_INSETS);
 
@@ -614,7 +702,7 @@ impl<T: TextStorage + EditableText> Widget<T> for TextBox<T> {
 
             ctx.with_save(|ctx| {
                 ctx.clip(clip_rect);
-                ctx.stroke(cursor, &cursor_color, 1.);
+                ctx.stroke(cursor, &cursor_color, cursor_width);
             })
         }
 
@@ -628,3 +716,11 @@ impl<T: TextStorage + EditableText> Default for TextBox<T> {
         TextBox::new()
     }
 }
+
+/// How long the cursor is shown and hidden for, or `None` if it doesn't blink.
+fn cursor_blink_interval(env: &Env) -> Option<Duration> {
+    match env.get(theme::CURSOR_BLINK_INTERVAL) {
+        0 => None,
+        millis => Some(Duration::from_millis(millis)),
+    }
+}
@@ -0,0 +1,605 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A timeline of items on tracks, for scheduling and editing media.
+
+use std::sync::Arc;
+
+use crate::kurbo::Line;
+use crate::widget::prelude::*;
+use crate::{theme, ArcStr, Cursor, Data, MouseButton, Point, Rect, TextLayout};
+use tracing::{instrument, trace};
+
+const DEFAULT_HEADER_WIDTH: f64 = 100.0;
+const DEFAULT_WIDTH: f64 = 600.0;
+const RULER_HEIGHT: f64 = 24.0;
+const TRACK_HEIGHT: f64 = 32.0;
+const ITEM_MARGIN: f64 = 4.0;
+/// How close to the edge of an item the mouse has to be to resize it.
+const EDGE_GRAB: f64 = 5.0;
+/// The smallest distance between two labels on the ruler.
+const MIN_LABEL_SPACING: f64 = 60.0;
+/// The smallest distance between two ticks on the ruler.
+const MIN_TICK_SPACING: f64 = 8.0;
+const LABEL_TEXT_SIZE: f64 = 11.0;
+const DEFAULT_ZOOM_LEVELS: &[f64] = &[5.0, 10.0, 20.0, 50.0, 100.0, 200.0];
+const DEFAULT_ZOOM: usize = 2;
+
+/// An item on a [`Timeline`].
+#[derive(Debug, Clone, Data, PartialEq)]
+pub struct TimelineItem {
+    /// The index of the track the item is on.
+    pub track: usize,
+    /// The time the item starts at.
+    pub start: f64,
+    /// The time the item ends at.
+    pub end: f64,
+    /// The text shown on the item.
+    pub label: ArcStr,
+}
+
+impl TimelineItem {
+    /// Create a new item.
+    pub fn new(track: usize, start: f64, end: f64, label: impl Into<ArcStr>) -> Self {
+        TimelineItem {
+            track,
+            start,
+            end,
+            label: label.into(),
+        }
+    }
+}
+
+/// The items and current time shown by a [`Timeline`].
+#[derive(Debug, Clone, Data)]
+pub struct TimelineData {
+    /// The items; later items are drawn on top of earlier ones.
+    pub items: Arc<Vec<TimelineItem>>,
+    /// The time the cursor is at.
+    pub time: f64,
+}
+
+/// Which part of an item is being dragged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Grab {
+    Move,
+    Start,
+    End,
+}
+
+enum Drag {
+    /// Dragging an item; the mouse position and the item when the drag
+    /// started.
+    Item {
+        index: usize,
+        grab: Grab,
+        origin: Point,
+        original: TimelineItem,
+    },
+    /// Moving the current-time cursor along the ruler.
+    Cursor,
+}
+
+/// A horizontally scrolling timeline, with items on tracks, such as for
+/// scheduling or editing media.
+///
+/// Times are in whatever unit the application uses, such as seconds or
+/// days; the zoom level sets how many pixels there are per unit.
+///
+/// Items can be dragged to move them in time and between tracks, and their
+/// edges can be dragged to resize them; the times snap to a grid, set
+/// with [`with_grid`]. Clicking or dragging on the ruler at the top moves
+/// the current-time cursor.
+///
+/// Scrolling the mouse wheel scrolls the timeline, and scrolling with ctrl
+/// held steps through the zoom levels.
+///
+/// [`with_grid`]: Timeline::with_grid
+pub struct Timeline {
+    tracks: Vec<TextLayout<ArcStr>>,
+    header_width: f64,
+    zoom_levels: Vec<f64>,
+    zoom: usize,
+    grid: f64,
+    /// The horizontal scroll offset, in pixels.
+    scroll: f64,
+    labels: Vec<TextLayout<ArcStr>>,
+    time_format: Box<dyn Fn(f64) -> String>,
+    drag: Option<Drag>,
+}
+
+impl Timeline {
+    /// Create a new `Timeline`, without named tracks.
+    pub fn new() -> Self {
+        Timeline {
+            tracks: Vec::new(),
+            header_width: DEFAULT_HEADER_WIDTH,
+            zoom_levels: DEFAULT_ZOOM_LEVELS.to_vec(),
+            zoom: DEFAULT_ZOOM,
+            grid: 1.0,
+            scroll: 0.0,
+            labels: Vec::new(),
+            time_format: Box::new(|time| format!("{}", (time * 100.0).round() / 100.0)),
+            drag: None,
+        }
+    }
+
+    /// Builder-style method to add a track, with a name shown on its left.
+    ///
+    /// Tracks that items are on are shown even if they were not added.
+    pub fn with_track(mut self, name: impl Into<ArcStr>) -> Self {
+        let mut layout = TextLayout::from_text(name);
+        layout.set_text_color(theme::TEXT_COLOR);
+        self.tracks.push(layout);
+        self
+    }
+
+    /// Builder-style method to set the width of the track names.
+    ///
+    /// The default is 100.
+    pub fn with_header_width(mut self, width: f64) -> Self {
+        self.header_width = width;
+        self
+    }
+
+    /// Builder-style method to set the grid that item times snap to.
+    ///
+    /// The default is 1.0; a grid of 0.0 turns snapping off.
+    pub fn with_grid(mut self, grid: f64) -> Self {
+        self.grid = grid.max(0.0);
+        self
+    }
+
+    /// Builder-style method to set the zoom levels, in pixels per unit of
+    /// time, and the index of the initial one.
+    ///
+    /// The default levels are 5, 10, 20, 50, 100 and 200, starting at 20.
+    pub fn with_zoom_levels(mut self, levels: impl Into<Vec<f64>>, initial: usize) -> Self {
+        let levels = levels.into();
+        if !levels.is_empty() {
+            self.zoom = initial.min(levels.len() - 1);
+            self.zoom_levels = levels;
+        }
+        self
+    }
+
+    /// Builder-style method to set how times are shown on the ruler.
+    pub fn with_time_format(mut self, format: impl Fn(f64) -> String + 'static) -> Self {
+        self.time_format = Box::new(format);
+        self
+    }
+
+    /// Set the zoom level, by its index.
+    pub fn set_zoom(&mut self, zoom: usize) {
+        self.zoom = zoom.min(self.zoom_levels.len() - 1);
+    }
+
+    /// The current zoom level, in pixels per unit of time.
+    pub fn scale(&self) -> f64 {
+        self.zoom_levels[self.zoom]
+    }
+
+    fn time_to_x(&self, time: f64) -> f64 {
+        self.header_width + time * self.scale() - self.scroll
+    }
+
+    fn x_to_time(&self, x: f64) -> f64 {
+        (x - self.header_width + self.scroll) / self.scale()
+    }
+
+    fn snap(&self, time: f64) -> f64 {
+        if self.grid > 0.0 {
+            (time / self.grid).round() * self.grid
+        } else {
+            time
+        }
+    }
+
+    fn track_count(&self, data: &TimelineData) -> usize {
+        let used = data.items.iter().map(|item| item.track + 1).max();
+        used.unwrap_or(0).max(self.tracks.len()).max(1)
+    }
+
+    fn item_rect(&self, item: &TimelineItem) -> Rect {
+        let y = RULER_HEIGHT + item.track as f64 * TRACK_HEIGHT;
+        Rect::new(
+            self.time_to_x(item.start),
+            y + ITEM_MARGIN,
+            self.time_to_x(item.end),
+            y + TRACK_HEIGHT - ITEM_MARGIN,
+        )
+    }
+
+    /// The topmost item at `pos`, and which part of it.
+    fn item_at(&self, data: &TimelineData, pos: Point) -> Option<(usize, Grab)> {
+        if pos.x < self.header_width {
+            return None;
+        }
+        let (index, item) = data
+            .items
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, item)| self.item_rect(item).contains(pos))?;
+        let rect = self.item_rect(item);
+        // narrow items can always be moved.
+        let grab = if rect.width() < EDGE_GRAB * 3.0 {
+            Grab::Move
+        } else if pos.x - rect.x0 <= EDGE_GRAB {
+            Grab::Start
+        } else if rect.x1 - pos.x <= EDGE_GRAB {
+            Grab::End
+        } else {
+            Grab::Move
+        };
+        Some((index, grab))
+    }
+
+    /// Keep the scroll offset between the start and the end of the items.
+    fn clamp_scroll(&mut self, data: &TimelineData, width: f64) {
+        let end = data
+            .items
+            .iter()
+            .map(|item| item.end)
+            .fold(data.time, f64::max);
+        let visible = (width - self.header_width).max(0.0);
+        let max_scroll = (end * self.scale() - visible / 2.0).max(0.0);
+        self.scroll = self.scroll.max(0.0).min(max_scroll);
+    }
+
+    /// Change the zoom level by `steps`, keeping the time at `x` in place.
+    fn zoom_around(&mut self, x: f64, steps: isize) {
+        let time = self.x_to_time(x);
+        let zoom = self.zoom as isize + steps;
+        self.zoom = zoom.max(0).min(self.zoom_levels.len() as isize - 1) as usize;
+        self.scroll = time * self.scale() - (x - self.header_width);
+    }
+
+    fn drag_item(&self, data: &mut TimelineData, pos: Point) {
+        let (index, grab, origin, original) = match &self.drag {
+            Some(Drag::Item {
+                index,
+                grab,
+                origin,
+                original,
+            }) => (*index, *grab, *origin, original),
+            _ => return,
+        };
+        let delta = (pos.x - origin.x) / self.scale();
+        let min_length = if self.grid > 0.0 { self.grid } else { 0.0 };
+        let mut item = original.clone();
+        match grab {
+            Grab::Move => {
+                let start = self.snap(original.start + delta).max(0.0);
+                item.end = start + (original.end - original.start);
+                item.start = start;
+                let track = ((pos.y - RULER_HEIGHT) / TRACK_HEIGHT).floor();
+                item.track = track.max(0.0).min(self.track_count(data) as f64 - 1.0) as usize;
+            }
+            Grab::Start => {
+                item.start = self
+                    .snap(original.start + delta)
+                    .max(0.0)
+                    .min(original.end - min_length);
+            }
+            Grab::End => {
+                item.end = self
+                    .snap(original.end + delta)
+                    .max(original.start + min_length);
+            }
+        }
+        if data.items[index] != item {
+            trace!("Timeline item {} moved to {:?}", index, item);
+            Arc::make_mut(&mut data.items)[index] = item;
+        }
+    }
+
+    /// Create or update the layouts of the item labels.
+    fn update_labels(&mut self, data: &TimelineData) {
+        self.labels.truncate(data.items.len());
+        for (index, item) in data.items.iter().enumerate() {
+            if let Some(label) = self.labels.get_mut(index) {
+                label.set_text(item.label.clone());
+            } else {
+                let mut label = TextLayout::from_text(item.label.clone());
+                label.set_text_size(LABEL_TEXT_SIZE);
+                label.set_text_color(theme::TEXT_COLOR);
+                self.labels.push(label);
+            }
+        }
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<TimelineData> for Timeline {
+    #[instrument(name = "Timeline", level = "trace", skip(self, ctx, event, data, _env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut TimelineData, _env: &Env) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                if mouse.pos.y < RULER_HEIGHT && mouse.pos.x >= self.header_width {
+                    data.time = self.x_to_time(mouse.pos.x).max(0.0);
+                    self.drag = Some(Drag::Cursor);
+                } else if let Some((index, grab)) = self.item_at(data, mouse.pos) {
+                    self.drag = Some(Drag::Item {
+                        index,
+                        grab,
+                        origin: mouse.pos,
+                        original: data.items[index].clone(),
+                    });
+                }
+                if self.drag.is_some() {
+                    ctx.set_active(true);
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                match self.drag {
+                    Some(Drag::Cursor) => {
+                        data.time = self.x_to_time(mouse.pos.x).max(0.0);
+                    }
+                    Some(Drag::Item { .. }) => self.drag_item(data, mouse.pos),
+                    None => (),
+                }
+                ctx.request_paint();
+            }
+            Event::MouseMove(mouse) => match self.item_at(data, mouse.pos) {
+                Some((_, Grab::Start)) | Some((_, Grab::End)) => {
+                    ctx.set_cursor(&Cursor::ResizeLeftRight)
+                }
+                _ => ctx.clear_cursor(),
+            },
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                self.drag = None;
+                ctx.request_paint();
+            }
+            Event::Wheel(mouse) => {
+                if mouse.mods.ctrl() {
+                    let steps = if mouse.wheel_delta.y < 0.0 { 1 } else { -1 };
+                    self.zoom_around(mouse.pos.x, steps);
+                } else if mouse.wheel_delta.x != 0.0 {
+                    self.scroll += mouse.wheel_delta.x;
+                } else {
+                    self.scroll += mouse.wheel_delta.y;
+                }
+                self.clamp_scroll(data, ctx.size().width);
+                ctx.set_handled();
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+    }
+
+    #[instrument(
+        name = "Timeline",
+        level = "trace",
+        skip(self, _ctx, event, data, _env)
+    )]
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &TimelineData,
+        _env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_labels(data);
+        }
+    }
+
+    #[instrument(
+        name = "Timeline",
+        level = "trace",
+        skip(self, ctx, old_data, data, _env)
+    )]
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &TimelineData,
+        data: &TimelineData,
+        _env: &Env,
+    ) {
+        if !old_data.items.same(&data.items) {
+            self.update_labels(data);
+            ctx.request_layout();
+        } else if !old_data.same(data) {
+            ctx.request_paint();
+        }
+        if ctx.env_changed() {
+            // every layout has to see the change, so this must not stop at
+            // the first one that needs rebuilding.
+            let mut changed = false;
+            for layout in self.tracks.iter_mut().chain(self.labels.iter_mut()) {
+                changed |= layout.needs_rebuild_after_update(ctx);
+            }
+            if changed {
+                ctx.request_layout();
+            }
+        }
+    }
+
+    #[instrument(name = "Timeline", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &TimelineData,
+        env: &Env,
+    ) -> Size {
+        bc.debug_check("Timeline");
+        for layout in self.tracks.iter_mut().chain(self.labels.iter_mut()) {
+            layout.rebuild_if_needed(ctx.text(), env);
+        }
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            DEFAULT_WIDTH
+        };
+        let height = RULER_HEIGHT + self.track_count(data) as f64 * TRACK_HEIGHT;
+        let size = bc.constrain(Size::new(width, height));
+        self.clamp_scroll(data, size.width);
+        trace!("Computed layout: size={}", size);
+        size
+    }
+
+    #[instrument(name = "Timeline", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &TimelineData, env: &Env) {
+        let size = ctx.size();
+        let border_color = env.get(theme::BORDER_DARK);
+        let text_color = env.get(theme::TEXT_COLOR);
+        ctx.fill(size.to_rect(), &env.get(theme::BACKGROUND_DARK));
+
+        // the tracks, with their names on the left.
+        for track in 0..self.track_count(data) {
+            let y = RULER_HEIGHT + track as f64 * TRACK_HEIGHT;
+            let row = Rect::new(0.0, y, size.width, y + TRACK_HEIGHT);
+            if track % 2 == 1 {
+                ctx.fill(row, &env.get(theme::BACKGROUND_LIGHT).with_alpha(0.3));
+            }
+            let line_y = y + TRACK_HEIGHT - 0.5;
+            ctx.stroke(
+                Line::new((0.0, line_y), (size.width, line_y)),
+                &border_color,
+                1.0,
+            );
+            if let Some(name) = self.tracks.get(track) {
+                let origin = Point::new(
+                    ITEM_MARGIN * 2.0,
+                    y + (TRACK_HEIGHT - name.size().height) / 2.0,
+                );
+                let header = Rect::new(0.0, y, self.header_width, y + TRACK_HEIGHT);
+                ctx.with_save(|ctx| {
+                    ctx.clip(header);
+                    name.draw(ctx, origin);
+                });
+            }
+        }
+
+        let content = Rect::new(self.header_width, 0.0, size.width, size.height);
+        let header_x = self.header_width - 0.5;
+        ctx.stroke(
+            Line::new((header_x, 0.0), (header_x, size.height)),
+            &border_color,
+            1.0,
+        );
+
+        ctx.with_save(|ctx| {
+            ctx.clip(content);
+
+            // the ruler, with a label every few ticks.
+            let scale = self.scale();
+            let tick = if self.grid > 0.0 { self.grid } else { 1.0 };
+            let tick = tick * (MIN_TICK_SPACING / (tick * scale)).max(1.0).ceil();
+            let label_every = (MIN_LABEL_SPACING / (tick * scale)).max(1.0).ceil() as i64;
+            let first = (self.x_to_time(self.header_width) / tick).floor() as i64;
+            let last = (self.x_to_time(size.width) / tick).ceil() as i64;
+            for i in first.max(0)..=last {
+                let time = i as f64 * tick;
+                let x = self.time_to_x(time).round() + 0.5;
+                let is_label = i % label_every == 0;
+                let tick_height = if is_label {
+                    RULER_HEIGHT / 2.0
+                } else {
+                    RULER_HEIGHT / 4.0
+                };
+                let line = Line::new((x, RULER_HEIGHT - tick_height), (x, RULER_HEIGHT));
+                ctx.stroke(line, &text_color, 1.0);
+                if is_label {
+                    let mut label = TextLayout::<String>::from_text((self.time_format)(time));
+                    label.set_text_size(LABEL_TEXT_SIZE);
+                    label.set_text_color(theme::TEXT_COLOR);
+                    label.rebuild_if_needed(ctx.text(), env);
+                    label.draw(ctx, (x + 2.0, 0.0));
+                }
+            }
+            let ruler_y = RULER_HEIGHT - 0.5;
+            ctx.stroke(
+                Line::new((0.0, ruler_y), (size.width, ruler_y)),
+                &border_color,
+                1.0,
+            );
+
+            // the items, with their labels clipped to them.
+            let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+            let active = match &self.drag {
+                Some(Drag::Item { index, .. }) => Some(*index),
+                _ => None,
+            };
+            for (index, item) in data.items.iter().enumerate() {
+                let rect = self.item_rect(item);
+                let shape = rect.to_rounded_rect(radius);
+                ctx.fill(shape, &env.get(theme::PRIMARY_DARK));
+                if Some(index) == active {
+                    ctx.stroke(shape, &env.get(theme::PRIMARY_LIGHT), 2.0);
+                } else {
+                    ctx.stroke(shape, &border_color, 1.0);
+                }
+                if let Some(label) = self.labels.get(index) {
+                    let origin = Point::new(
+                        rect.x0 + ITEM_MARGIN,
+                        rect.center().y - label.size().height / 2.0,
+                    );
+                    ctx.with_save(|ctx| {
+                        ctx.clip(rect);
+                        label.draw(ctx, origin);
+                    });
+                }
+            }
+
+            // the current-time cursor.
+            let x = self.time_to_x(data.time).round() + 0.5;
+            let cursor_color = env.get(theme::PRIMARY_LIGHT);
+            ctx.stroke(Line::new((x, 0.0), (x, size.height)), &cursor_color, 1.0);
+            let handle = Rect::from_center_size((x, RULER_HEIGHT / 2.0), (7.0, RULER_HEIGHT));
+            ctx.fill(handle.to_rounded_rect(2.0), &cursor_color);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Harness;
+    use test_env_log::test;
+
+    #[test]
+    fn drag_snaps_to_grid() {
+        let data = TimelineData {
+            items: Arc::new(vec![TimelineItem::new(0, 1.0, 3.0, "item")]),
+            time: 0.0,
+        };
+        // at the default zoom an item starts at 100 + 20 * start.
+        Harness::create_simple(data, Timeline::new(), |harness| {
+            harness.send_initial_events();
+            harness.just_layout();
+            harness.mouse_down((140.0, 40.0));
+            harness.mouse_move((151.0, 40.0));
+            harness.mouse_up((151.0, 40.0));
+            let item = &harness.data().items[0];
+            assert_eq!((item.start, item.end), (2.0, 4.0));
+
+            // resizing the end
+            harness.mouse_down((178.0, 40.0));
+            harness.mouse_move((205.0, 40.0));
+            harness.mouse_up((205.0, 40.0));
+            let item = &harness.data().items[0];
+            assert_eq!((item.start, item.end), (2.0, 5.0));
+        });
+    }
+}
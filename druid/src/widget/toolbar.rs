@@ -0,0 +1,648 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A bar of buttons and other widgets along the top of a window.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::kurbo::Line;
+use crate::widget::prelude::*;
+use crate::widget::{Label, SizedBox};
+use crate::{
+    theme, ArcStr, Data, Lens, LensExt, Menu, MenuItem, MouseButton, Point, Rect, Selector,
+    WidgetPod,
+};
+use tracing::{instrument, trace};
+
+/// Submitted as a notification by the chevron button.
+const SHOW_OVERFLOW: Selector = Selector::new("druid.builtin.toolbar-show-overflow");
+/// Submitted to the toolbar by its overflow menu, with the index of the item.
+const ACTIVATE_ITEM: Selector<usize> = Selector::new("druid.builtin.toolbar-activate-item");
+
+const PADDING: f64 = 4.0;
+const ITEM_SPACING: f64 = 2.0;
+const BUTTON_PADDING: f64 = 4.0;
+const SEPARATOR_WIDTH: f64 = 9.0;
+const BORDER_WIDTH: f64 = 1.0;
+
+type Action<T> = Rc<dyn Fn(&mut EventCtx, &mut T, &Env)>;
+type Selected<T> = Rc<dyn Fn(&T) -> bool>;
+
+/// A bar of buttons, toggle buttons, separators and other widgets, such as
+/// the one along the top of most document windows.
+///
+/// Items are laid out from left to right. When the toolbar is too narrow to
+/// show all of them, the ones that don't fit are moved into a menu behind a
+/// chevron button at the right end of the toolbar. Buttons and toggle
+/// buttons can be used from that menu; other widgets are listed there, but
+/// can only be used once there is room for them again.
+///
+/// The overflow menu is a `Menu<()>` whose entries only submit commands, so
+/// unlike other context menus it can be shown whatever the application's
+/// root `Data` type is; see [`EventCtx::show_context_menu`].
+///
+/// Each item has an id, which is used to let users reorder the items: with
+/// [`with_order`], items can be dragged to a new place while holding the
+/// `Alt` key, and the new order is stored in the data as a list of ids.
+///
+/// [`with_order`]: Toolbar::with_order
+pub struct Toolbar<T> {
+    items: Vec<Item<T>>,
+    chevron: WidgetPod<T, ToolButton<T>>,
+    order_store: Option<Box<dyn OrderStore<T>>>,
+    /// Indices into `items`, in the order they are shown.
+    order: Vec<usize>,
+    /// How many of the items in `order` fit in the toolbar.
+    visible: usize,
+    drag: Option<Drag>,
+}
+
+struct Item<T> {
+    id: String,
+    title: ArcStr,
+    /// What the item does when it is clicked, for buttons and toggle buttons.
+    action: Option<Action<T>>,
+    /// Whether the item is checked, for toggle buttons.
+    selected: Option<Selected<T>>,
+    is_separator: bool,
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+}
+
+/// An item that is being dragged to a new place.
+struct Drag {
+    /// The position of the item in the visible items.
+    from: usize,
+    /// The position it would be dropped at.
+    to: usize,
+}
+
+/// Where the order of the items is kept in the data.
+trait OrderStore<T> {
+    fn get(&self, data: &T) -> Arc<Vec<String>>;
+    fn put(&self, data: &mut T, order: Arc<Vec<String>>);
+}
+
+impl<T, L: Lens<T, Arc<Vec<String>>>> OrderStore<T> for L {
+    fn get(&self, data: &T) -> Arc<Vec<String>> {
+        LensExt::get(self, data)
+    }
+
+    fn put(&self, data: &mut T, order: Arc<Vec<String>>) {
+        LensExt::put(self, data, order)
+    }
+}
+
+/// A button in the toolbar, which shows its icon and highlights when it is
+/// hovered, pressed or checked.
+struct ToolButton<T> {
+    icon: WidgetPod<T, Box<dyn Widget<T>>>,
+    action: Action<T>,
+    selected: Option<Selected<T>>,
+}
+
+impl<T: Data> Toolbar<T> {
+    /// Create a new, empty `Toolbar`.
+    pub fn new() -> Self {
+        let chevron = ToolButton::new(
+            Label::new("\u{bb}"),
+            Rc::new(|ctx: &mut EventCtx, _: &mut T, _: &Env| {
+                ctx.submit_notification(SHOW_OVERFLOW)
+            }),
+            None,
+        );
+        Toolbar {
+            items: Vec::new(),
+            chevron: WidgetPod::new(chevron),
+            order_store: None,
+            order: Vec::new(),
+            visible: 0,
+            drag: None,
+        }
+    }
+
+    /// Builder-style method for adding a button showing `icon`, which calls
+    /// `action` when it is clicked.
+    ///
+    /// `title` is shown in the overflow menu.
+    pub fn with_button(
+        mut self,
+        id: impl Into<String>,
+        title: impl Into<ArcStr>,
+        icon: impl Widget<T> + 'static,
+        action: impl Fn(&mut EventCtx, &mut T, &Env) + 'static,
+    ) -> Self {
+        let action: Action<T> = Rc::new(action);
+        let button = ToolButton::new(icon, action.clone(), None);
+        self.items.push(Item {
+            id: id.into(),
+            title: title.into(),
+            action: Some(action),
+            selected: None,
+            is_separator: false,
+            child: WidgetPod::new(Box::new(button)),
+        });
+        self
+    }
+
+    /// Builder-style method for adding a toggle button showing `icon`, which
+    /// is checked while the `bool` that `lens` points to is `true`, and flips
+    /// it when it is clicked.
+    ///
+    /// `title` is shown in the overflow menu.
+    pub fn with_toggle(
+        mut self,
+        id: impl Into<String>,
+        title: impl Into<ArcStr>,
+        icon: impl Widget<T> + 'static,
+        lens: impl Lens<T, bool> + 'static,
+    ) -> Self {
+        let lens = Rc::new(lens);
+        let selected: Selected<T> = {
+            let lens = lens.clone();
+            Rc::new(move |data: &T| lens.with(data, |checked| *checked))
+        };
+        let action: Action<T> = Rc::new(move |_ctx: &mut EventCtx, data: &mut T, _env: &Env| {
+            lens.with_mut(data, |checked| *checked = !*checked)
+        });
+        let button = ToolButton::new(icon, action.clone(), Some(selected.clone()));
+        self.items.push(Item {
+            id: id.into(),
+            title: title.into(),
+            action: Some(action),
+            selected: Some(selected),
+            is_separator: false,
+            child: WidgetPod::new(Box::new(button)),
+        });
+        self
+    }
+
+    /// Builder-style method for adding a separator.
+    pub fn with_separator(mut self) -> Self {
+        self.items.push(Item {
+            id: format!("separator-{}", self.items.len()),
+            title: "".into(),
+            action: None,
+            selected: None,
+            is_separator: true,
+            child: WidgetPod::new(Box::new(SizedBox::empty().width(SEPARATOR_WIDTH))),
+        });
+        self
+    }
+
+    /// Builder-style method for adding an arbitrary widget, such as a search
+    /// field.
+    ///
+    /// `title` is shown in the overflow menu.
+    pub fn with_widget(
+        mut self,
+        id: impl Into<String>,
+        title: impl Into<ArcStr>,
+        widget: impl Widget<T> + 'static,
+    ) -> Self {
+        self.items.push(Item {
+            id: id.into(),
+            title: title.into(),
+            action: None,
+            selected: None,
+            is_separator: false,
+            child: WidgetPod::new(Box::new(widget)),
+        });
+        self
+    }
+
+    /// Builder-style method for letting users reorder the items, keeping the
+    /// order in the data that `lens` points to.
+    ///
+    /// The order is a list of item ids. Ids that don't belong to an item are
+    /// ignored, and items that are missing from the list are shown after the
+    /// others, in the order they were added in. An empty list therefore means
+    /// the default order.
+    ///
+    /// Separators get the ids `separator-0`, `separator-1` and so on, counting
+    /// all items that were added before them.
+    pub fn with_order(mut self, lens: impl Lens<T, Arc<Vec<String>>> + 'static) -> Self {
+        self.order_store = Some(Box::new(lens));
+        self
+    }
+
+    fn resolve_order(&mut self, data: &T) {
+        let mut order = Vec::with_capacity(self.items.len());
+        if let Some(store) = &self.order_store {
+            for id in store.get(data).iter() {
+                if let Some(index) = self.items.iter().position(|item| item.id == *id) {
+                    if !order.contains(&index) {
+                        order.push(index);
+                    }
+                }
+            }
+        }
+        for index in 0..self.items.len() {
+            if !order.contains(&index) {
+                order.push(index);
+            }
+        }
+        self.order = order;
+    }
+
+    fn has_overflow(&self) -> bool {
+        self.visible < self.order.len()
+    }
+
+    /// The position among the visible items that an item dropped at `x`
+    /// would get.
+    fn drop_position(&self, x: f64) -> usize {
+        self.order[..self.visible]
+            .iter()
+            .filter(|&&index| self.items[index].child.layout_rect().center().x < x)
+            .count()
+    }
+
+    fn finish_drag(&mut self, drag: Drag, data: &mut T) {
+        let to = if drag.to > drag.from {
+            drag.to - 1
+        } else {
+            drag.to
+        };
+        if to == drag.from {
+            return;
+        }
+        let index = self.order.remove(drag.from);
+        self.order.insert(to, index);
+        if let Some(store) = &self.order_store {
+            let ids = self
+                .order
+                .iter()
+                .map(|&index| self.items[index].id.clone())
+                .collect();
+            store.put(data, Arc::new(ids));
+        }
+    }
+
+    fn show_overflow_menu(&self, ctx: &mut EventCtx, data: &T) {
+        let mut menu = Menu::<()>::empty();
+        let mut after_separator = true;
+        for &index in &self.order[self.visible..] {
+            let item = &self.items[index];
+            if item.is_separator {
+                if !after_separator {
+                    menu = menu.separator();
+                    after_separator = true;
+                }
+                continue;
+            }
+            let mut entry = MenuItem::new(item.title.clone());
+            if item.action.is_some() {
+                entry = entry.command(ACTIVATE_ITEM.with(index).to(ctx.widget_id()));
+            } else {
+                entry = entry.enabled(false);
+            }
+            if let Some(selected) = &item.selected {
+                entry = entry.selected(selected(data));
+            }
+            menu = menu.entry(entry);
+            after_separator = false;
+        }
+        let chevron = self.chevron.layout_rect();
+        let location = ctx.to_window(Point::new(chevron.x0, chevron.y1));
+        ctx.show_context_menu(menu, location);
+    }
+}
+
+impl<T: Data> Default for Toolbar<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data> ToolButton<T> {
+    fn new(
+        icon: impl Widget<T> + 'static,
+        action: Action<T>,
+        selected: Option<Selected<T>>,
+    ) -> Self {
+        ToolButton {
+            icon: WidgetPod::new(Box::new(icon)),
+            action,
+            selected,
+        }
+    }
+}
+
+impl<T: Data> Widget<T> for ToolButton<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left && !ctx.is_disabled() => {
+                ctx.set_active(true);
+                ctx.request_paint();
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+                ctx.set_active(false);
+                if ctx.is_hot() && !ctx.is_disabled() {
+                    (self.action)(ctx, data, env);
+                }
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+        self.icon.event(ctx, event, data, env);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::HotChanged(_) | LifeCycle::DisabledChanged(_) = event {
+            ctx.request_paint();
+        }
+        self.icon.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if let Some(selected) = &self.selected {
+            if selected(old_data) != selected(data) {
+                ctx.request_paint();
+            }
+        }
+        self.icon.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let padding = Size::new(BUTTON_PADDING * 2.0, BUTTON_PADDING * 2.0);
+        let icon_bc = bc.loosen().shrink(padding);
+        let icon_size = self.icon.layout(ctx, &icon_bc, data, env);
+        self.icon
+            .set_origin(ctx, data, env, Point::new(BUTTON_PADDING, BUTTON_PADDING));
+        bc.constrain(icon_size + padding)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let checked = self
+            .selected
+            .as_ref()
+            .is_some_and(|selected| selected(data));
+        let background = if ctx.is_active() || checked {
+            Some(env.get(theme::BUTTON_DARK))
+        } else if ctx.is_hot() && !ctx.is_disabled() {
+            Some(env.get(theme::BUTTON_LIGHT))
+        } else {
+            None
+        };
+        if let Some(background) = background {
+            let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+            let rect = ctx.size().to_rect().to_rounded_rect(radius);
+            ctx.fill(rect, &background);
+        }
+        self.icon.paint(ctx, data, env);
+    }
+}
+
+impl<T: Data> Widget<T> for Toolbar<T> {
+    #[instrument(name = "Toolbar", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::Notification(note) if note.is(SHOW_OVERFLOW) => {
+                ctx.set_handled();
+                self.show_overflow_menu(ctx, data);
+                return;
+            }
+            Event::Command(cmd) => {
+                if let Some(&index) = cmd.get(ACTIVATE_ITEM) {
+                    ctx.set_handled();
+                    if let Some(action) = self.items.get(index).and_then(|it| it.action.clone()) {
+                        trace!(
+                            "activating {:?} from the overflow menu",
+                            self.items[index].id
+                        );
+                        action(ctx, data, env);
+                    }
+                    return;
+                }
+            }
+            Event::MouseDown(mouse)
+                if self.order_store.is_some()
+                    && mouse.button == MouseButton::Left
+                    && mouse.mods.alt() =>
+            {
+                let under_mouse = self.order[..self.visible]
+                    .iter()
+                    .position(|&index| self.items[index].child.layout_rect().contains(mouse.pos));
+                if let Some(from) = under_mouse {
+                    self.drag = Some(Drag { from, to: from });
+                    ctx.set_active(true);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                    return;
+                }
+            }
+            Event::MouseMove(mouse) if self.drag.is_some() => {
+                let to = self.drop_position(mouse.pos.x);
+                if let Some(drag) = &mut self.drag {
+                    drag.to = to;
+                }
+                ctx.request_paint();
+                return;
+            }
+            Event::MouseUp(mouse) if self.drag.is_some() && mouse.button == MouseButton::Left => {
+                let drag = self.drag.take().unwrap();
+                ctx.set_active(false);
+                self.finish_drag(drag, data);
+                ctx.request_layout();
+                return;
+            }
+            _ => (),
+        }
+
+        let is_pointer_event = matches!(
+            event,
+            Event::MouseDown(_) | Event::MouseUp(_) | Event::MouseMove(_) | Event::Wheel(_)
+        );
+        for (position, &index) in self.order.iter().enumerate() {
+            // items in the overflow menu don't get the mouse.
+            if position < self.visible || !is_pointer_event {
+                self.items[index].child.event(ctx, event, data, env);
+            }
+        }
+        if self.has_overflow() || !is_pointer_event {
+            self.chevron.event(ctx, event, data, env);
+        }
+    }
+
+    #[instrument(name = "Toolbar", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.resolve_order(data);
+            self.visible = self.order.len();
+        }
+        for item in &mut self.items {
+            item.child.lifecycle(ctx, event, data, env);
+        }
+        self.chevron.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "Toolbar",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if let Some(store) = &self.order_store {
+            if !store.get(old_data).same(&store.get(data)) {
+                self.resolve_order(data);
+                ctx.request_layout();
+            }
+        }
+        for item in &mut self.items {
+            item.child.update(ctx, data, env);
+        }
+        self.chevron.update(ctx, data, env);
+    }
+
+    #[instrument(name = "Toolbar", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("Toolbar");
+        let max_height = (bc.max().height - PADDING * 2.0 - BORDER_WIDTH).max(0.0);
+        let child_bc = BoxConstraints::new(Size::ZERO, Size::new(f64::INFINITY, max_height));
+
+        let mut sizes = vec![Size::ZERO; self.items.len()];
+        for &index in &self.order {
+            sizes[index] = self.items[index].child.layout(ctx, &child_bc, data, env);
+        }
+        let chevron_size = self.chevron.layout(ctx, &child_bc, data, env);
+
+        let total_width = self
+            .order
+            .iter()
+            .map(|&index| sizes[index].width + ITEM_SPACING)
+            .sum::<f64>()
+            - ITEM_SPACING;
+        let available = bc.max().width - PADDING * 2.0;
+        self.visible = if total_width <= available {
+            self.order.len()
+        } else {
+            // make room for the chevron, and fit as many items as possible.
+            let available = available - chevron_size.width - ITEM_SPACING;
+            let mut width = 0.0;
+            let mut visible = 0;
+            for &index in &self.order {
+                width += sizes[index].width;
+                if width > available {
+                    break;
+                }
+                width += ITEM_SPACING;
+                visible += 1;
+            }
+            // don't end with a separator.
+            while visible > 0 && self.items[self.order[visible - 1]].is_separator {
+                visible -= 1;
+            }
+            visible
+        };
+
+        let mut content_height = self.order[..self.visible]
+            .iter()
+            .map(|&index| sizes[index].height)
+            .fold(0.0, f64::max);
+        if self.has_overflow() {
+            content_height = content_height.max(chevron_size.height);
+        }
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            total_width + PADDING * 2.0
+        };
+        let size = bc.constrain(Size::new(
+            width,
+            content_height + PADDING * 2.0 + BORDER_WIDTH,
+        ));
+        let height = size.height - BORDER_WIDTH;
+
+        let mut x = PADDING;
+        for (position, &index) in self.order.iter().enumerate() {
+            let child = &mut self.items[index].child;
+            let child_size = sizes[index];
+            if position < self.visible {
+                let y = ((height - child_size.height) / 2.0).max(0.0);
+                child.set_origin(ctx, data, env, Point::new(x, y));
+                x += child_size.width + ITEM_SPACING;
+            } else {
+                // out of sight, these are only reachable from the menu.
+                child.set_origin(ctx, data, env, Point::new(size.width, 0.0));
+            }
+        }
+        let chevron_x = if self.has_overflow() {
+            size.width - PADDING - chevron_size.width
+        } else {
+            size.width
+        };
+        let chevron_y = ((height - chevron_size.height) / 2.0).max(0.0);
+        self.chevron
+            .set_origin(ctx, data, env, Point::new(chevron_x, chevron_y));
+
+        trace!(
+            "Computed layout: size={}, visible={}/{}",
+            size,
+            self.visible,
+            self.order.len()
+        );
+        size
+    }
+
+    #[instrument(name = "Toolbar", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let size = ctx.size();
+        ctx.fill(size.to_rect(), &env.get(theme::BACKGROUND_LIGHT));
+        let y = size.height - BORDER_WIDTH / 2.0;
+        ctx.stroke(
+            Line::new((0.0, y), (size.width, y)),
+            &env.get(theme::BORDER_DARK),
+            BORDER_WIDTH,
+        );
+
+        let content = Rect::new(
+            0.0,
+            PADDING,
+            size.width,
+            size.height - BORDER_WIDTH - PADDING,
+        );
+        for &index in &self.order[..self.visible] {
+            let item = &mut self.items[index];
+            if item.is_separator {
+                let x = item.child.layout_rect().center().x.round() + 0.5;
+                ctx.stroke(
+                    Line::new((x, content.y0), (x, content.y1)),
+                    &env.get(theme::BORDER_LIGHT),
+                    1.0,
+                );
+            } else {
+                item.child.paint(ctx, data, env);
+            }
+        }
+        if self.has_overflow() {
+            self.chevron.paint(ctx, data, env);
+        }
+
+        if let Some(drag) = &self.drag {
+            let x = match self.order[..self.visible].get(drag.to) {
+                Some(&index) => self.items[index].child.layout_rect().x0 - ITEM_SPACING / 2.0,
+                None => match self.order[..self.visible].last() {
+                    Some(&index) => self.items[index].child.layout_rect().x1 + ITEM_SPACING / 2.0,
+                    None => PADDING,
+                },
+            };
+            ctx.stroke(
+                Line::new((x, content.y0), (x, content.y1)),
+                &env.get(theme::PRIMARY_LIGHT),
+                2.0,
+            );
+        }
+    }
+}
@@ -0,0 +1,488 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Guided tours that point out parts of the interface, one at a time.
+
+use tracing::{instrument, trace};
+
+use crate::command::sys::{LOCATE_WIDGET, WIDGET_LOCATED};
+use crate::kurbo::{BezPath, Shape};
+use crate::widget::prelude::*;
+use crate::{theme, Color, KbKey, Lens, LensExt, MouseButton, Point, Rect, Selector};
+use crate::{TextLayout, WidgetPod};
+
+const DIM_COLOR: Color = Color::rgba8(0, 0, 0, 0x99);
+/// How far the highlight extends beyond the target widget.
+const HIGHLIGHT_PADDING: f64 = 4.0;
+const HIGHLIGHT_RADIUS: f64 = 4.0;
+const POPOVER_WIDTH: f64 = 280.0;
+const POPOVER_PADDING: f64 = 12.0;
+const POPOVER_RADIUS: f64 = 6.0;
+/// The space between the highlight and the popover, and between the parts
+/// of the popover.
+const SPACING: f64 = 8.0;
+const BUTTON_PADDING_H: f64 = 10.0;
+const BUTTON_PADDING_V: f64 = 4.0;
+
+/// One stop of a tour: a widget to point out, and what to say about it.
+pub struct TourStep {
+    target: WidgetId,
+    title: TextLayout<String>,
+    text: TextLayout<String>,
+}
+
+/// A guided tour over a child, for introducing new users to an interface.
+///
+/// The tour goes through a list of [`TourStep`]s. Each one highlights a
+/// widget, dims the rest of the window, and explains the widget in a popover
+/// next to it, with buttons to go to the next step or to skip the tour.
+/// While the tour runs, clicks outside the highlighted widget and the popover
+/// are ignored. Escape skips the tour, and Enter goes to the next step.
+///
+/// Whether the tour has been completed (or skipped) is kept in the data, so
+/// that it can be saved with the rest of the application state. The tour
+/// starts by itself when it has not been completed yet; it can be started
+/// again with the [`START`] command.
+///
+/// The widgets are found by their [`WidgetId`], so they need to have a known
+/// id, which can be given with [`WidgetExt::with_id`]. The controller should
+/// wrap the whole content of the window, so that all of the window is dimmed.
+///
+/// [`START`]: TourController::START
+/// [`WidgetExt::with_id`]: super::WidgetExt::with_id
+pub struct TourController<T> {
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    steps: Vec<TourStep>,
+    completed: Box<dyn CompletedStore<T>>,
+    /// The index of the current step, while the tour runs.
+    current: Option<usize>,
+    /// The layout rect of the current target, in our coordinates, once it
+    /// has been located.
+    target_rect: Option<Rect>,
+    skip_label: TextLayout<String>,
+    next_label: TextLayout<String>,
+    /// Where the popover and its buttons were last painted.
+    popover: Rect,
+    skip_button: Rect,
+    next_button: Rect,
+    hot_button: Option<TourButton>,
+    pressed_button: Option<TourButton>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TourButton {
+    Skip,
+    Next,
+}
+
+/// Where the completion of a tour is kept in the data.
+trait CompletedStore<T> {
+    fn get(&self, data: &T) -> bool;
+    fn put(&self, data: &mut T, completed: bool);
+}
+
+impl<T, L: Lens<T, bool>> CompletedStore<T> for L {
+    fn get(&self, data: &T) -> bool {
+        LensExt::get(self, data)
+    }
+
+    fn put(&self, data: &mut T, completed: bool) {
+        LensExt::put(self, data, completed)
+    }
+}
+
+impl TourStep {
+    /// Create a step that highlights the widget with the id `target`.
+    pub fn new(target: WidgetId, title: impl Into<String>, text: impl Into<String>) -> Self {
+        let mut title_layout = TextLayout::from_text(title.into());
+        title_layout.set_font(theme::UI_FONT_BOLD);
+        TourStep {
+            target,
+            title: title_layout,
+            text: TextLayout::from_text(text.into()),
+        }
+    }
+}
+
+impl TourController<()> {
+    /// Start the tour from its first step, even if it was completed before.
+    pub const START: Selector = Selector::new("druid-builtin.tour-start");
+}
+
+impl<T: Data> TourController<T> {
+    /// Create a new tour over `child`.
+    ///
+    /// `completed` points to the `bool` in the data that records whether the
+    /// tour has been completed.
+    pub fn new(child: impl Widget<T> + 'static, completed: impl Lens<T, bool> + 'static) -> Self {
+        TourController {
+            child: WidgetPod::new(child).boxed(),
+            steps: Vec::new(),
+            completed: Box::new(completed),
+            current: None,
+            target_rect: None,
+            skip_label: TextLayout::from_text("Skip".to_string()),
+            next_label: TextLayout::new(),
+            popover: Rect::ZERO,
+            skip_button: Rect::ZERO,
+            next_button: Rect::ZERO,
+            hot_button: None,
+            pressed_button: None,
+        }
+    }
+
+    /// Builder-style method for adding a step to the tour.
+    pub fn with_step(mut self, step: TourStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Returns `true` if the tour is running.
+    pub fn is_running(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Go to the step at `index`, or end the tour if there is no such step.
+    fn go_to(&mut self, ctx: &mut EventCtx, data: &mut T, index: usize) {
+        self.hot_button = None;
+        self.pressed_button = None;
+        if index < self.steps.len() {
+            self.show_step(index);
+            ctx.submit_command(LOCATE_WIDGET.to(self.steps[index].target));
+        } else {
+            self.current = None;
+            self.completed.put(data, true);
+        }
+        ctx.request_paint();
+    }
+
+    fn show_step(&mut self, index: usize) {
+        self.current = Some(index);
+        self.target_rect = None;
+        let next = if index + 1 == self.steps.len() {
+            "Done"
+        } else {
+            "Next"
+        };
+        self.next_label.set_text(next.to_string());
+    }
+
+    fn advance(&mut self, ctx: &mut EventCtx, data: &mut T) {
+        if let Some(index) = self.current {
+            self.go_to(ctx, data, index + 1);
+        }
+    }
+
+    fn skip(&mut self, ctx: &mut EventCtx, data: &mut T) {
+        self.go_to(ctx, data, self.steps.len());
+    }
+
+    fn current_target(&self) -> Option<WidgetId> {
+        self.current.map(|index| self.steps[index].target)
+    }
+
+    fn button_at(&self, pos: Point) -> Option<TourButton> {
+        if self.skip_button.contains(pos) {
+            Some(TourButton::Skip)
+        } else if self.next_button.contains(pos) {
+            Some(TourButton::Next)
+        } else {
+            None
+        }
+    }
+
+    fn highlight(&self) -> Option<Rect> {
+        self.target_rect
+            .map(|rect| rect.inflate(HIGHLIGHT_PADDING, HIGHLIGHT_PADDING))
+    }
+
+    /// Whether the tour handles a mouse event at `pos` itself, instead of the
+    /// child.
+    fn captures(&self, ctx: &EventCtx, pos: Point) -> bool {
+        ctx.is_active()
+            || self.popover.contains(pos)
+            || !self.highlight().is_some_and(|rect| rect.contains(pos))
+    }
+}
+
+impl<T: Data> Widget<T> for TourController<T> {
+    #[instrument(
+        name = "TourController",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(TourController::START) => {
+                self.go_to(ctx, data, 0);
+                ctx.set_handled();
+                return;
+            }
+            Event::Notification(note) if note.is(WIDGET_LOCATED) => {
+                if Some(note.source()) == self.current_target() {
+                    let rect = *note.get(WIDGET_LOCATED).unwrap();
+                    self.target_rect = Some(rect - ctx.window_origin().to_vec2());
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+
+        if self.is_running() {
+            match event {
+                Event::MouseDown(mouse) if self.captures(ctx, mouse.pos) => {
+                    if mouse.button == MouseButton::Left && self.popover.contains(mouse.pos) {
+                        self.pressed_button = self.button_at(mouse.pos);
+                        ctx.set_active(true);
+                    }
+                    ctx.set_handled();
+                    return;
+                }
+                Event::MouseUp(mouse) if self.captures(ctx, mouse.pos) => {
+                    if ctx.is_active() {
+                        ctx.set_active(false);
+                        let pressed = self.pressed_button.take();
+                        if pressed.is_some() && pressed == self.button_at(mouse.pos) {
+                            match pressed {
+                                Some(TourButton::Skip) => self.skip(ctx, data),
+                                _ => self.advance(ctx, data),
+                            }
+                        }
+                    }
+                    ctx.set_handled();
+                    return;
+                }
+                Event::Wheel(mouse) if self.captures(ctx, mouse.pos) => {
+                    ctx.set_handled();
+                    return;
+                }
+                Event::MouseMove(mouse) => {
+                    let hot = self.button_at(mouse.pos);
+                    if hot != self.hot_button {
+                        self.hot_button = hot;
+                        ctx.request_paint();
+                    }
+                }
+                Event::KeyDown(key) if key.key == KbKey::Escape => {
+                    self.skip(ctx, data);
+                    ctx.set_handled();
+                    return;
+                }
+                Event::KeyDown(key) if key.key == KbKey::Enter => {
+                    self.advance(ctx, data);
+                    ctx.set_handled();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        self.child.event(ctx, event, data, env);
+
+        // The target may have moved without us being laid out again.
+        if let Event::Wheel(_) = event {
+            if let Some(target) = self.current_target() {
+                ctx.submit_command(LOCATE_WIDGET.to(target));
+            }
+        }
+    }
+
+    #[instrument(
+        name = "TourController",
+        level = "trace",
+        skip(self, ctx, event, data, env)
+    )]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            if !self.completed.get(data) && !self.steps.is_empty() {
+                self.show_step(0);
+            }
+        }
+        self.child.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "TourController",
+        level = "trace",
+        skip(self, ctx, _old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        if self.is_running() && self.completed.get(data) {
+            // The tour was marked as completed from elsewhere.
+            self.current = None;
+            ctx.request_paint();
+        }
+        self.child.update(ctx, data, env);
+    }
+
+    #[instrument(
+        name = "TourController",
+        level = "trace",
+        skip(self, ctx, bc, data, env)
+    )]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        bc.debug_check("TourController");
+        let size = self.child.layout(ctx, bc, data, env);
+        self.child.set_origin(ctx, data, env, Point::ORIGIN);
+        ctx.set_paint_insets(self.child.paint_insets());
+
+        if let Some(target) = self.current_target() {
+            ctx.submit_command(LOCATE_WIDGET.to(target));
+        }
+        trace!("Computed size: {}", size);
+        size
+    }
+
+    #[instrument(name = "TourController", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.child.paint(ctx, data, env);
+
+        let index = match self.current {
+            Some(index) => index,
+            None => return,
+        };
+        let size = ctx.size();
+        let highlight = self.highlight();
+
+        // Dim everything but the highlight.
+        let mut dim = BezPath::new();
+        dim.extend(size.to_rect().path_elements(0.1));
+        if let Some(rect) = highlight {
+            dim.extend(rect.to_rounded_rect(HIGHLIGHT_RADIUS).path_elements(0.1));
+        }
+        ctx.fill_even_odd(dim, &DIM_COLOR);
+        if let Some(rect) = highlight {
+            let outline = rect.to_rounded_rect(HIGHLIGHT_RADIUS);
+            ctx.stroke(outline, &env.get(theme::PRIMARY_LIGHT), 2.0);
+        }
+
+        let step = &mut self.steps[index];
+        let text_width = POPOVER_WIDTH - 2.0 * POPOVER_PADDING;
+        step.title.set_wrap_width(text_width);
+        step.text.set_wrap_width(text_width);
+        step.title.rebuild_if_needed(ctx.text(), env);
+        step.text.rebuild_if_needed(ctx.text(), env);
+        self.skip_label.rebuild_if_needed(ctx.text(), env);
+        self.next_label.rebuild_if_needed(ctx.text(), env);
+
+        let title_height = step.title.size().height;
+        let text_height = step.text.size().height;
+        let button_padding = Size::new(2.0 * BUTTON_PADDING_H, 2.0 * BUTTON_PADDING_V);
+        let skip_size = self.skip_label.size() + button_padding;
+        let next_size = self.next_label.size() + button_padding;
+        let button_height = skip_size.height.max(next_size.height);
+        let popover_size = Size::new(
+            POPOVER_WIDTH,
+            2.0 * POPOVER_PADDING + title_height + text_height + button_height + 2.0 * SPACING,
+        );
+        let origin = popover_origin(highlight, popover_size, size);
+        self.popover = Rect::from_origin_size(origin, popover_size);
+
+        let buttons_y = self.popover.y1 - POPOVER_PADDING - button_height;
+        self.skip_button =
+            Rect::from_origin_size((origin.x + POPOVER_PADDING, buttons_y), skip_size);
+        self.next_button = Rect::from_origin_size(
+            (
+                self.popover.x1 - POPOVER_PADDING - next_size.width,
+                buttons_y,
+            ),
+            next_size,
+        );
+
+        let popover = self.popover.to_rounded_rect(POPOVER_RADIUS);
+        ctx.fill(popover, &env.get(theme::BACKGROUND_LIGHT));
+        ctx.stroke(popover, &env.get(theme::BORDER_DARK), 1.0);
+        let text_origin = origin + (POPOVER_PADDING, POPOVER_PADDING);
+        step.title.draw(ctx, text_origin);
+        step.text
+            .draw(ctx, text_origin + (0.0, title_height + SPACING));
+
+        let radius = env.get(theme::BUTTON_BORDER_RADIUS);
+        if self.hot_button == Some(TourButton::Skip) {
+            let button = self.skip_button.to_rounded_rect(radius);
+            ctx.fill(button, &env.get(theme::BACKGROUND_DARK));
+        }
+        let next_color = if self.hot_button == Some(TourButton::Next) {
+            theme::PRIMARY_LIGHT
+        } else {
+            theme::PRIMARY_DARK
+        };
+        let button = self.next_button.to_rounded_rect(radius);
+        ctx.fill(button, &env.get(next_color));
+        let label_offset = (BUTTON_PADDING_H, BUTTON_PADDING_V);
+        self.skip_label
+            .draw(ctx, self.skip_button.origin() + label_offset);
+        self.next_label
+            .draw(ctx, self.next_button.origin() + label_offset);
+    }
+}
+
+/// Where to put a popover of the size `popover` for the highlighted rect
+/// `target`, inside `bounds`.
+///
+/// The popover goes below the target if there is room, and above it
+/// otherwise, centered horizontally. Without a target, it is centered in the
+/// bounds.
+fn popover_origin(target: Option<Rect>, popover: Size, bounds: Size) -> Point {
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let center = bounds.to_rect().center();
+            return center - popover.to_vec2() / 2.0;
+        }
+    };
+
+    let below = target.y1 + SPACING;
+    let above = target.y0 - SPACING - popover.height;
+    let y = if below + popover.height <= bounds.height || above < 0.0 {
+        below
+    } else {
+        above
+    };
+    let x = target.center().x - popover.width / 2.0;
+
+    let max_x = (bounds.width - popover.width).max(0.0);
+    let max_y = (bounds.height - popover.height).max(0.0);
+    Point::new(x.max(0.0).min(max_x), y.max(0.0).min(max_y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn popover_placement() {
+        let bounds = Size::new(400.0, 300.0);
+        let popover = Size::new(100.0, 50.0);
+
+        let top = Rect::new(150.0, 20.0, 250.0, 40.0);
+        let origin = popover_origin(Some(top), popover, bounds);
+        assert_eq!(origin, Point::new(150.0, 40.0 + SPACING));
+
+        let bottom = Rect::new(150.0, 260.0, 250.0, 280.0);
+        let origin = popover_origin(Some(bottom), popover, bounds);
+        assert_eq!(origin, Point::new(150.0, 260.0 - SPACING - 50.0));
+
+        // pushed back inside at the left edge
+        let left = Rect::new(0.0, 20.0, 20.0, 40.0);
+        let origin = popover_origin(Some(left), popover, bounds);
+        assert_eq!(origin.x, 0.0);
+
+        let origin = popover_origin(None, popover, bounds);
+        assert_eq!(origin, Point::new(150.0, 125.0));
+    }
+}
@@ -0,0 +1,456 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that plays video.
+
+use std::any::Any;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use crate::commands::{VIDEO_ENDED, VIDEO_POSITION};
+#[cfg(all(feature = "image", feature = "gif"))]
+use crate::image::{codecs::gif, AnimationDecoder};
+#[cfg(all(feature = "image", feature = "gif"))]
+use crate::piet::ImageFormat;
+use crate::piet::{ImageBuf, InterpolationMode, PietImage};
+use crate::widget::prelude::*;
+use crate::widget::FillStrat;
+use crate::{Data, ExtEventSink, Lens, Selector, Target};
+use tracing::{instrument, warn};
+
+// sent by the decoding thread to the widget.
+const FRAME: Selector<VideoFrame> = Selector::new("druid.builtin.video-frame");
+const ENDED: Selector = Selector::new("druid.builtin.video-ended");
+const DURATION: Selector<Option<Duration>> = Selector::new("druid.builtin.video-duration");
+
+/// A decoded frame of video.
+#[derive(Clone)]
+pub struct VideoFrame {
+    /// The image.
+    pub image: ImageBuf,
+    /// When the frame should be shown, relative to the start of the video.
+    pub timestamp: Duration,
+}
+
+/// A source of decoded video frames, for the [`Video`] widget.
+///
+/// Druid only includes `GifDecoder`, for animated GIFs. For other formats,
+/// implementations of this trait wrap a platform decoder, such as gstreamer,
+/// Media Foundation or AVFoundation.
+///
+/// The decoder is moved to a background thread when the `Video` is added to
+/// the widget tree, and all methods are called on that thread.
+pub trait VideoDecoder: Send + 'static {
+    /// The length of the video, if it is known.
+    fn duration(&self) -> Option<Duration>;
+
+    /// Move to `position`, so that the next frame is the one that should be
+    /// shown at that time.
+    fn seek(&mut self, position: Duration);
+
+    /// Decode the next frame, or return `None` at the end of the video.
+    fn next_frame(&mut self) -> Option<VideoFrame>;
+}
+
+/// The state of playback of a [`Video`], which is its data.
+///
+/// Setting `playing` starts and stops playback, and changing `position`
+/// seeks. While the video plays, `position` is kept up to date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Data, Lens)]
+pub struct Playback {
+    /// Whether the video is playing.
+    pub playing: bool,
+    /// The timestamp of the frame that is being shown.
+    pub position: Duration,
+    /// The length of the video, once it is known.
+    pub duration: Option<Duration>,
+}
+
+/// A [`VideoDecoder`] that plays an animated GIF.
+///
+/// All the frames are decoded up front, when the decoder is created.
+#[cfg(all(feature = "image", feature = "gif"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "image", feature = "gif"))))]
+pub struct GifDecoder {
+    frames: Vec<VideoFrame>,
+    duration: Duration,
+    /// The index of the next frame.
+    next: usize,
+}
+
+/// Messages from the widget to the decoding thread.
+enum Control {
+    Play,
+    Pause,
+    Seek(Duration),
+}
+
+/// A widget that plays video from a [`VideoDecoder`].
+///
+/// The decoder is supplied by the application. Druid includes a decoder for
+/// animated GIFs, `GifDecoder`, when the `image` and `gif` features are
+/// enabled.
+///
+/// Frames are decoded on a background thread, and shown when they are due.
+/// Playback is controlled through the [`Playback`] data. While playing, a
+/// [`VIDEO_POSITION`] notification is sent for every frame that is shown,
+/// and [`VIDEO_ENDED`] when the end is reached.
+///
+/// [`VIDEO_POSITION`]: crate::commands::VIDEO_POSITION
+/// [`VIDEO_ENDED`]: crate::commands::VIDEO_ENDED
+pub struct Video {
+    decoder: Option<Box<dyn VideoDecoder>>,
+    control: Option<Sender<Control>>,
+    frame: Option<ImageBuf>,
+    paint_data: Option<PietImage>,
+    fill: FillStrat,
+    /// The last position that we know about, to tell seeks apart from
+    /// playback.
+    position: Duration,
+}
+
+impl Video {
+    /// Create a `Video` that shows the frames produced by `decoder`.
+    pub fn new(decoder: impl VideoDecoder) -> Self {
+        Video {
+            decoder: Some(Box::new(decoder)),
+            control: None,
+            frame: None,
+            paint_data: None,
+            fill: FillStrat::Contain,
+            position: Duration::ZERO,
+        }
+    }
+
+    /// Builder-style method for specifying how frames fill the widget.
+    ///
+    /// The default is [`FillStrat::Contain`].
+    pub fn fill_mode(mut self, fill: FillStrat) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Set how frames fill the widget.
+    pub fn set_fill_mode(&mut self, fill: FillStrat) {
+        self.fill = fill;
+    }
+
+    fn send(&self, control: Control) {
+        if let Some(sender) = &self.control {
+            // this only fails if the thread is gone, and then there is nothing
+            // left to control.
+            let _ = sender.send(control);
+        }
+    }
+}
+
+#[cfg(all(feature = "image", feature = "gif"))]
+impl GifDecoder {
+    /// Decode an animated GIF from its bytes.
+    pub fn from_data(data: &[u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut timestamp = Duration::ZERO;
+        let mut frames = Vec::new();
+        for frame in gif::GifDecoder::new(data)?.into_frames() {
+            let frame = frame?;
+            let delay = Duration::from(frame.delay());
+            let buffer = frame.into_buffer();
+            let (width, height) = buffer.dimensions();
+            let image = ImageBuf::from_raw(
+                buffer.into_raw(),
+                ImageFormat::RgbaSeparate,
+                width as usize,
+                height as usize,
+            );
+            frames.push(VideoFrame { image, timestamp });
+            timestamp += delay;
+        }
+        Ok(GifDecoder {
+            frames,
+            duration: timestamp,
+            next: 0,
+        })
+    }
+
+    /// Decode an animated GIF from a file.
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        GifDecoder::from_data(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(all(feature = "image", feature = "gif"))]
+impl VideoDecoder for GifDecoder {
+    fn duration(&self) -> Option<Duration> {
+        Some(self.duration)
+    }
+
+    fn seek(&mut self, position: Duration) {
+        // the last frame that starts at or before `position`.
+        self.next = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.timestamp <= position)
+            .unwrap_or(0);
+    }
+
+    fn next_frame(&mut self) -> Option<VideoFrame> {
+        let frame = self.frames.get(self.next)?.clone();
+        self.next += 1;
+        Some(frame)
+    }
+}
+
+impl Widget<Playback> for Video {
+    #[instrument(name = "Video", level = "trace", skip(self, ctx, event, data, _env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Playback, _env: &Env) {
+        if let Event::Command(cmd) = event {
+            if let Some(frame) = cmd.get(FRAME) {
+                let size = frame.image.size();
+                if self.frame.as_ref().map(|old| old.size()) != Some(size) {
+                    ctx.request_layout();
+                }
+                self.frame = Some(frame.image.clone());
+                self.paint_data = None;
+                ctx.request_paint();
+
+                self.position = frame.timestamp;
+                data.position = frame.timestamp;
+                ctx.submit_notification(VIDEO_POSITION.with(frame.timestamp));
+                ctx.set_handled();
+            } else if cmd.is(ENDED) {
+                data.playing = false;
+                ctx.submit_notification(VIDEO_ENDED);
+                ctx.set_handled();
+            } else if let Some(duration) = cmd.get(DURATION) {
+                data.duration = *duration;
+                ctx.set_handled();
+            }
+        }
+    }
+
+    #[instrument(name = "Video", level = "trace", skip(self, ctx, event, data, _env))]
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &Playback,
+        _env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            if let Some(decoder) = self.decoder.take() {
+                let (sender, receiver) = mpsc::channel();
+                let sink = ctx.get_external_handle();
+                let widget = ctx.widget_id();
+                std::thread::spawn(move || decode(decoder, receiver, sink, widget));
+                self.control = Some(sender);
+
+                if data.position != Duration::ZERO {
+                    self.position = data.position;
+                    self.send(Control::Seek(data.position));
+                }
+                if data.playing {
+                    self.send(Control::Play);
+                }
+            }
+        }
+    }
+
+    #[instrument(
+        name = "Video",
+        level = "trace",
+        skip(self, _ctx, old_data, data, _env)
+    )]
+    fn update(&mut self, _ctx: &mut UpdateCtx, old_data: &Playback, data: &Playback, _env: &Env) {
+        if data.position != self.position {
+            self.position = data.position;
+            self.send(Control::Seek(data.position));
+        }
+        if data.playing != old_data.playing {
+            self.send(if data.playing {
+                Control::Play
+            } else {
+                Control::Pause
+            });
+        }
+    }
+
+    #[instrument(name = "Video", level = "trace", skip(self, _ctx, bc, _data, _env))]
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Playback,
+        _env: &Env,
+    ) -> Size {
+        bc.debug_check("Video");
+        let size = self.frame.as_ref().map(ImageBuf::size).unwrap_or_default();
+        bc.constrain(size)
+    }
+
+    #[instrument(name = "Video", level = "trace", skip(self, ctx, _data, _env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Playback, _env: &Env) {
+        let frame = match &self.frame {
+            Some(frame) => frame,
+            None => return,
+        };
+        let transform = self.fill.affine_to_fill(ctx.size(), frame.size());
+        let paint_data = &mut self.paint_data;
+        let clip = ctx.size().to_rect();
+        ctx.with_save(|ctx| {
+            let image = paint_data.get_or_insert_with(|| frame.to_image(ctx.render_ctx));
+            ctx.clip(clip);
+            ctx.transform(transform);
+            ctx.draw_image(image, frame.size().to_rect(), InterpolationMode::Bilinear);
+        });
+    }
+}
+
+/// Decode frames on a background thread, and send them to the widget when
+/// they are due.
+fn decode(
+    mut decoder: Box<dyn VideoDecoder>,
+    control: Receiver<Control>,
+    sink: ExtEventSink,
+    widget: WidgetId,
+) {
+    let submit_frame = |frame| submit(&sink, widget, FRAME, frame);
+    submit(&sink, widget, DURATION, decoder.duration());
+
+    let mut playing = false;
+    // the next frame to show.
+    let mut pending: Option<VideoFrame> = None;
+    // a point in time, and the timestamp that was due then.
+    let mut clock: Option<(Instant, Duration)> = None;
+    loop {
+        if playing && clock.is_none() {
+            if let Some(frame) = &pending {
+                clock = Some((Instant::now(), frame.timestamp));
+            }
+        }
+        let timeout = match (&pending, clock) {
+            _ if !playing => None,
+            (Some(frame), Some((start, base))) => {
+                let due = start + frame.timestamp.checked_sub(base).unwrap_or_default();
+                Some(due.saturating_duration_since(Instant::now()))
+            }
+            _ => Some(Duration::ZERO),
+        };
+        let message = match timeout {
+            Some(timeout) => match control.recv_timeout(timeout) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            },
+            None => match control.recv() {
+                Ok(message) => Some(message),
+                Err(_) => return,
+            },
+        };
+
+        match message {
+            Some(Control::Play) => {
+                playing = true;
+                clock = None;
+            }
+            Some(Control::Pause) => playing = false,
+            Some(Control::Seek(position)) => {
+                decoder.seek(position);
+                clock = None;
+                pending = decoder.next_frame();
+                // show the new position right away, even when paused.
+                if !playing {
+                    if let Some(frame) = pending.take() {
+                        submit_frame(frame);
+                    }
+                }
+            }
+            // the pending frame is due, or there is none yet.
+            None => {
+                if let Some(frame) = pending.take() {
+                    submit_frame(frame);
+                }
+                match decoder.next_frame() {
+                    Some(frame) => {
+                        if clock.is_none() {
+                            clock = Some((Instant::now(), frame.timestamp));
+                        }
+                        pending = Some(frame);
+                    }
+                    None => {
+                        playing = false;
+                        submit(&sink, widget, ENDED, ());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn submit<T: Any + Send>(sink: &ExtEventSink, widget: WidgetId, selector: Selector<T>, payload: T) {
+    if let Err(e) = sink.submit_command(selector, Box::new(payload), Target::Widget(widget)) {
+        warn!("failed to deliver video event: {}", e);
+    }
+}
+
+#[cfg(all(test, feature = "image", feature = "gif"))]
+mod tests {
+    use super::*;
+    use crate::image::{Delay, Frame, Rgba, RgbaImage};
+    use test_env_log::test;
+
+    /// Encode a GIF with 2x2 frames that last `delays` milliseconds.
+    fn encode_gif(delays: &[u32]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let frames = delays.iter().map(|ms| {
+            let image = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+            Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(*ms, 1))
+        });
+        // the encoder finishes the file when it is dropped.
+        gif::GifEncoder::new(&mut data)
+            .encode_frames(frames)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn gif_frames() {
+        let mut decoder = GifDecoder::from_data(&encode_gif(&[100, 200, 100])).unwrap();
+        assert_eq!(decoder.duration(), Some(Duration::from_millis(400)));
+
+        let frames: Vec<_> = std::iter::from_fn(|| decoder.next_frame()).collect();
+        let timestamps: Vec<_> = frames.iter().map(|frame| frame.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            [0, 100, 300].map(Duration::from_millis).to_vec()
+        );
+        assert_eq!(frames[0].image.size(), Size::new(2.0, 2.0));
+    }
+
+    #[test]
+    fn gif_seek() {
+        let mut decoder = GifDecoder::from_data(&encode_gif(&[100, 200, 100])).unwrap();
+        decoder.seek(Duration::from_millis(250));
+        let next = decoder.next_frame().map(|frame| frame.timestamp);
+        assert_eq!(next, Some(Duration::from_millis(100)));
+
+        decoder.seek(Duration::from_millis(300));
+        assert!(decoder.next_frame().is_some());
+        assert!(decoder.next_frame().is_none());
+
+        decoder.seek(Duration::ZERO);
+        let next = decoder.next_frame().map(|frame| frame.timestamp);
+        assert_eq!(next, Some(Duration::ZERO));
+    }
+}
@@ -20,13 +20,23 @@ use tracing::instrument;
 
 type ChildPicker<T, U> = dyn Fn(&T, &Env) -> U;
 type ChildBuilder<T, U> = dyn Fn(&U, &T, &Env) -> Box<dyn Widget<T>>;
+type Child<T> = WidgetPod<T, Box<dyn Widget<T>>>;
 
 /// A widget that switches dynamically between multiple children.
+///
+/// By default a child is dropped when it is switched away from, and built
+/// again if it is picked later; see [`retain_inactive`] to keep it instead.
+///
+/// [`retain_inactive`]: ViewSwitcher::retain_inactive
 pub struct ViewSwitcher<T, U> {
     child_picker: Box<ChildPicker<T, U>>,
     child_builder: Box<ChildBuilder<T, U>>,
-    active_child: Option<WidgetPod<T, Box<dyn Widget<T>>>>,
+    active_child: Option<Child<T>>,
     active_child_id: Option<U>,
+    retain_inactive: bool,
+    max_cached: usize,
+    /// The retained children, least recently shown first.
+    inactive_children: Vec<(U, Child<T>)>,
 }
 
 impl<T: Data, U: Data> ViewSwitcher<T, U> {
@@ -48,8 +58,36 @@ impl<T: Data, U: Data> ViewSwitcher<T, U> {
             child_builder: Box::new(child_builder),
             active_child: None,
             active_child_id: None,
+            retain_inactive: false,
+            max_cached: usize::MAX,
+            inactive_children: Vec::new(),
         }
     }
+
+    /// Builder-style method to keep the children that are switched away from,
+    /// instead of dropping them and calling the `child_builder` again when
+    /// they are picked later.
+    ///
+    /// This keeps the state of the children, like a scroll position or a
+    /// text selection, for the price of keeping every child that has been
+    /// shown around. While it is not shown, a child doesn't get updates, and
+    /// only sees the events that are also sent to hidden widgets.
+    pub fn retain_inactive(mut self, retain: bool) -> Self {
+        self.retain_inactive = retain;
+        self
+    }
+
+    /// Builder-style method to limit the number of children that are retained
+    /// while they are not shown, with [`retain_inactive`].
+    ///
+    /// When there are more, the one that was shown least recently is dropped.
+    /// By default, every child that has been shown is retained.
+    ///
+    /// [`retain_inactive`]: ViewSwitcher::retain_inactive
+    pub fn max_cached(mut self, max_cached: usize) -> Self {
+        self.max_cached = max_cached;
+        self
+    }
 }
 
 impl<T: Data, U: Data> Widget<T> for ViewSwitcher<T, U> {
@@ -62,6 +100,11 @@ impl<T: Data, U: Data> Widget<T> for ViewSwitcher<T, U> {
         if let Some(child) = self.active_child.as_mut() {
             child.event(ctx, event, data, env);
         }
+        if event.should_propagate_to_hidden() {
+            for (_, child) in &mut self.inactive_children {
+                child.event(ctx, event, data, env);
+            }
+        }
     }
 
     #[instrument(
@@ -78,6 +121,11 @@ impl<T: Data, U: Data> Widget<T> for ViewSwitcher<T, U> {
         if let Some(child) = self.active_child.as_mut() {
             child.lifecycle(ctx, event, data, env);
         }
+        if event.should_propagate_to_hidden() {
+            for (_, child) in &mut self.inactive_children {
+                child.lifecycle(ctx, event, data, env);
+            }
+        }
     }
 
     #[instrument(
@@ -89,10 +137,30 @@ impl<T: Data, U: Data> Widget<T> for ViewSwitcher<T, U> {
         let child_id = (self.child_picker)(data, env);
         // Safe to unwrap because self.active_child_id should not be empty
         if !child_id.same(self.active_child_id.as_ref().unwrap()) {
-            self.active_child = Some(WidgetPod::new((self.child_builder)(&child_id, data, env)));
-            self.active_child_id = Some(child_id);
+            if let (true, Some(id), Some(child)) = (
+                self.retain_inactive,
+                self.active_child_id.take(),
+                self.active_child.take(),
+            ) {
+                self.inactive_children.push((id, child));
+            }
+            let retained = self
+                .inactive_children
+                .iter()
+                .position(|(id, _)| id.same(&child_id))
+                .map(|idx| self.inactive_children.remove(idx).1);
+            let excess = self.inactive_children.len().saturating_sub(self.max_cached);
+            self.inactive_children.drain(..excess);
+            let mut child = retained
+                .unwrap_or_else(|| WidgetPod::new((self.child_builder)(&child_id, data, env)));
             ctx.children_changed();
-        // Because the new child has not yet been initialized, we have to skip the update after switching.
+            // A retained child has to catch up with the data. Because a new child has not
+            // yet been initialized, we have to skip its update after switching.
+            if child.is_initialized() {
+                child.update(ctx, data, env);
+            }
+            self.active_child = Some(child);
+            self.active_child_id = Some(child_id);
         } else if let Some(child) = self.active_child.as_mut() {
             child.update(ctx, data, env);
         }
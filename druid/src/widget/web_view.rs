@@ -0,0 +1,253 @@
+// Copyright 2021 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that shows web content.
+//!
+//! This only provides the widget and the [`WebViewBackend`] trait. Druid does
+//! not depend on any webview library, so an application that uses a `WebView`
+//! has to implement the trait on top of the webview of each platform it
+//! supports.
+
+use crate::commands::{
+    NAVIGATE_BACK, NAVIGATE_FORWARD, WEBVIEW_EVALUATE_SCRIPT, WEBVIEW_GO_BACK, WEBVIEW_GO_FORWARD,
+    WEBVIEW_LOAD_HTML, WEBVIEW_LOAD_URL, WEBVIEW_MESSAGE, WEBVIEW_NAVIGATED, WEBVIEW_RELOAD,
+};
+use crate::widget::prelude::*;
+use crate::widget::{NativeSurface, SurfaceContent, SurfacePlacement};
+use crate::{Data, ExtEventSink, Selector, Target, WindowHandle};
+use tracing::{instrument, warn};
+
+// sent by the backend, and turned into notifications by the widget.
+const RECEIVED_MESSAGE: Selector<String> = Selector::new("druid.builtin.webview-received-message");
+const DID_NAVIGATE: Selector<String> = Selector::new("druid.builtin.webview-did-navigate");
+
+/// A platform webview that can be hosted by a [`WebView`].
+///
+/// Druid does not include a webview itself; implementations of this trait
+/// wrap one (such as WebView2, WKWebView or WebKitGTK), usually in a child
+/// window that is kept over the area of the `WebView` widget.
+///
+/// The methods that load content may be called before the webview has been
+/// placed for the first time, in which case they should take effect once it
+/// has been created.
+pub trait WebViewBackend {
+    /// Called when the `WebView` is added to the widget tree, with a way to
+    /// send messages and navigation events back to it.
+    fn connect(&mut self, messenger: WebViewMessenger);
+
+    /// Called when the webview is first placed in the window, and whenever
+    /// it moves or changes size.
+    fn set_bounds(&mut self, window: &WindowHandle, placement: SurfacePlacement);
+
+    /// Load the page at `url`.
+    fn load_url(&mut self, url: &str);
+
+    /// Show `html` as the content of the page.
+    fn load_html(&mut self, html: &str);
+
+    /// Run JavaScript in the current page.
+    fn evaluate_script(&mut self, script: &str);
+
+    /// Go back one page in the history.
+    fn go_back(&mut self);
+
+    /// Go forward one page in the history.
+    fn go_forward(&mut self);
+
+    /// Reload the current page.
+    fn reload(&mut self);
+}
+
+/// A handle that a [`WebViewBackend`] uses to talk to its [`WebView`].
+///
+/// This can be moved between threads, and cloned.
+#[derive(Clone)]
+pub struct WebViewMessenger {
+    sink: ExtEventSink,
+    widget: WidgetId,
+}
+
+impl WebViewMessenger {
+    /// Deliver a message from the page, which the `WebView` sends on as a
+    /// [`WEBVIEW_MESSAGE`] notification.
+    ///
+    /// [`WEBVIEW_MESSAGE`]: crate::commands::WEBVIEW_MESSAGE
+    pub fn post_message(&self, message: String) {
+        self.submit(RECEIVED_MESSAGE, message);
+    }
+
+    /// Report that the webview has navigated to `url`, which the `WebView`
+    /// sends on as a [`WEBVIEW_NAVIGATED`] notification.
+    ///
+    /// [`WEBVIEW_NAVIGATED`]: crate::commands::WEBVIEW_NAVIGATED
+    pub fn navigated(&self, url: String) {
+        self.submit(DID_NAVIGATE, url);
+    }
+
+    fn submit(&self, selector: Selector<String>, payload: String) {
+        if let Err(e) = self
+            .sink
+            .submit_command(selector, payload, Target::Widget(self.widget))
+        {
+            warn!("failed to deliver webview event: {}", e);
+        }
+    }
+}
+
+/// What to show when the `WebView` is created.
+enum Source {
+    Url(String),
+    Html(String),
+}
+
+/// Keeps the backend informed about where the `WebView` is.
+struct Host<B>(B);
+
+impl<T, B: WebViewBackend> SurfaceContent<T> for Host<B> {
+    fn placed(&mut self, window: &WindowHandle, placement: SurfacePlacement) {
+        self.0.set_bounds(window, placement);
+    }
+}
+
+/// A widget that shows web content using a platform webview.
+///
+/// The webview itself is provided by a [`WebViewBackend`], which the
+/// application supplies: druid has no built-in backend on any platform. The
+/// `WebView` takes up all the space it is given, and keeps the backend's
+/// webview over that area.
+///
+/// It is controlled with commands targeted at its id: [`WEBVIEW_LOAD_URL`],
+/// [`WEBVIEW_LOAD_HTML`], [`WEBVIEW_EVALUATE_SCRIPT`], [`WEBVIEW_GO_BACK`],
+/// [`WEBVIEW_GO_FORWARD`] and [`WEBVIEW_RELOAD`]. Messages from the page and
+/// navigation are reported to the `WebView`'s ancestors as
+/// [`WEBVIEW_MESSAGE`] and [`WEBVIEW_NAVIGATED`] notifications, which can be
+/// handled with a [`Controller`].
+///
+/// The back and forward buttons of the mouse go back and forward in the
+/// history, through [`NAVIGATE_BACK`] and [`NAVIGATE_FORWARD`].
+///
+/// [`WEBVIEW_LOAD_URL`]: crate::commands::WEBVIEW_LOAD_URL
+/// [`WEBVIEW_LOAD_HTML`]: crate::commands::WEBVIEW_LOAD_HTML
+/// [`WEBVIEW_EVALUATE_SCRIPT`]: crate::commands::WEBVIEW_EVALUATE_SCRIPT
+/// [`WEBVIEW_GO_BACK`]: crate::commands::WEBVIEW_GO_BACK
+/// [`WEBVIEW_GO_FORWARD`]: crate::commands::WEBVIEW_GO_FORWARD
+/// [`WEBVIEW_RELOAD`]: crate::commands::WEBVIEW_RELOAD
+/// [`NAVIGATE_BACK`]: crate::commands::NAVIGATE_BACK
+/// [`NAVIGATE_FORWARD`]: crate::commands::NAVIGATE_FORWARD
+/// [`WEBVIEW_MESSAGE`]: crate::commands::WEBVIEW_MESSAGE
+/// [`WEBVIEW_NAVIGATED`]: crate::commands::WEBVIEW_NAVIGATED
+/// [`Controller`]: super::Controller
+pub struct WebView<B> {
+    surface: NativeSurface<Host<B>>,
+    initial: Option<Source>,
+}
+
+impl<B: WebViewBackend> WebView<B> {
+    /// Create a new `WebView`, showing an empty page.
+    pub fn new(backend: B) -> Self {
+        WebView {
+            surface: NativeSurface::new(Host(backend)),
+            initial: None,
+        }
+    }
+
+    /// Builder-style method for loading a URL when the `WebView` is created.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.initial = Some(Source::Url(url.into()));
+        self
+    }
+
+    /// Builder-style method for showing HTML when the `WebView` is created.
+    pub fn with_html(mut self, html: impl Into<String>) -> Self {
+        self.initial = Some(Source::Html(html.into()));
+        self
+    }
+
+    /// The backend of this `WebView`.
+    pub fn backend(&self) -> &B {
+        &self.surface.content().0
+    }
+
+    /// The backend of this `WebView`, mutably.
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.surface.content_mut().0
+    }
+}
+
+impl<T: Data, B: WebViewBackend> Widget<T> for WebView<B> {
+    #[instrument(name = "WebView", level = "trace", skip(self, ctx, event, data, env))]
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::Command(cmd) = event {
+            let backend = self.backend_mut();
+            if let Some(url) = cmd.get(WEBVIEW_LOAD_URL) {
+                backend.load_url(url);
+            } else if let Some(html) = cmd.get(WEBVIEW_LOAD_HTML) {
+                backend.load_html(html);
+            } else if let Some(script) = cmd.get(WEBVIEW_EVALUATE_SCRIPT) {
+                backend.evaluate_script(script);
+            } else if cmd.is(WEBVIEW_GO_BACK) || cmd.is(NAVIGATE_BACK) {
+                backend.go_back();
+            } else if cmd.is(WEBVIEW_GO_FORWARD) || cmd.is(NAVIGATE_FORWARD) {
+                backend.go_forward();
+            } else if cmd.is(WEBVIEW_RELOAD) {
+                backend.reload();
+            } else if let Some(message) = cmd.get(RECEIVED_MESSAGE) {
+                ctx.submit_notification(WEBVIEW_MESSAGE.with(message.clone()));
+            } else if let Some(url) = cmd.get(DID_NAVIGATE) {
+                ctx.submit_notification(WEBVIEW_NAVIGATED.with(url.clone()));
+            } else {
+                return;
+            }
+            ctx.set_handled();
+            return;
+        }
+        self.surface.event(ctx, event, data, env);
+    }
+
+    #[instrument(name = "WebView", level = "trace", skip(self, ctx, event, data, env))]
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            let messenger = WebViewMessenger {
+                sink: ctx.get_external_handle(),
+                widget: ctx.widget_id(),
+            };
+            self.backend_mut().connect(messenger);
+            match self.initial.take() {
+                Some(Source::Url(url)) => self.backend_mut().load_url(&url),
+                Some(Source::Html(html)) => self.backend_mut().load_html(&html),
+                None => (),
+            }
+        }
+        self.surface.lifecycle(ctx, event, data, env);
+    }
+
+    #[instrument(
+        name = "WebView",
+        level = "trace",
+        skip(self, ctx, old_data, data, env)
+    )]
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.surface.update(ctx, old_data, data, env);
+    }
+
+    #[instrument(name = "WebView", level = "trace", skip(self, ctx, bc, data, env))]
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        self.surface.layout(ctx, bc, data, env)
+    }
+
+    #[instrument(name = "WebView", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.surface.paint(ctx, data, env);
+    }
+}
@@ -17,7 +17,7 @@
 use super::invalidation::DebugInvalidation;
 use super::{
     Added, Align, BackgroundBrush, Click, Container, Controller, ControllerHost, EnvScope,
-    IdentityWrapper, LensWrap, Padding, Parse, SizedBox, WidgetId,
+    ErrorBoundary, IdentityWrapper, InspectData, LensWrap, Padding, Parse, SizedBox, WidgetId,
 };
 use crate::widget::{DisabledIf, Scroll};
 use crate::{
@@ -221,6 +221,17 @@ pub trait WidgetExt<T: Data>: Widget<T> + Sized + 'static {
         DebugInvalidation::new(self)
     }
 
+    /// Wrap this widget in an [`InspectData`], so that its data is shown in
+    /// the widget inspector.
+    ///
+    /// [`InspectData`]: super::InspectData
+    fn inspect_data(self) -> InspectData<Self>
+    where
+        T: std::fmt::Debug,
+    {
+        InspectData::new(self)
+    }
+
     /// Set the [`DEBUG_WIDGET`] env variable for this widget (and its descendants).
     ///
     /// This does nothing by default, but you can use this variable while
@@ -285,6 +296,14 @@ pub trait WidgetExt<T: Data>: Widget<T> + Sized + 'static {
     fn disabled_if(self, disabled_if: impl Fn(&T, &Env) -> bool + 'static) -> DisabledIf<T, Self> {
         DisabledIf::new(self, disabled_if)
     }
+
+    /// Wrap this widget in an [`ErrorBoundary`], which replaces it with an
+    /// error message if it panics.
+    ///
+    /// [`ErrorBoundary`]: crate::widget::ErrorBoundary
+    fn error_boundary(self) -> ErrorBoundary<T, Self> {
+        ErrorBoundary::new(self)
+    }
 }
 
 impl<T: Data, W: Widget<T> + 'static> WidgetExt<T> for W {}
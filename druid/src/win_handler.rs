@@ -18,26 +18,34 @@ use std::any::{Any, TypeId};
 use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
+use std::time::Duration;
+
+use instant::Instant;
 
 use crate::kurbo::Size;
 use crate::piet::Piet;
 use crate::shell::{
-    text::InputHandler, Application, FileDialogToken, FileInfo, IdleToken, MouseEvent, Region,
-    Scale, TextFieldToken, WinHandler, WindowHandle,
+    text::InputHandler, Application, Clipboard, DropEvent, FileDialogToken, FileInfo, IdleToken,
+    MouseEvent, PowerEvent, PowerStatus, Region, Scale, TextFieldToken, WinHandler, WindowHandle,
 };
 
 use crate::app_delegate::{AppDelegate, DelegateCtx};
 use crate::core::CommandQueue;
+#[cfg(feature = "data-diff")]
+use crate::data_diff::DiffLog;
 use crate::ext_event::{ExtEventHost, ExtEventSink};
-use crate::menu::{ContextMenu, MenuItemId, MenuManager};
+use crate::menu::{AppMenu, ContextMenu, MenuItemId, MenuManager};
+use crate::mouse::ClickCounter;
+use crate::registry::{Registries, WidgetInfo, WidgetRegistry};
 use crate::window::{ImeUpdateFn, Window};
 use crate::{
-    Command, Data, Env, Event, Handled, InternalEvent, KeyEvent, PlatformError, Selector, Target,
-    TimerToken, WidgetId, WindowDesc, WindowId,
+    theme, Command, Data, Env, Event, Handled, InputMode, InternalEvent, KeyEvent, PlatformError,
+    Selector, Target, TimerToken, WidgetId, WindowDesc, WindowId,
 };
 
 use crate::app::{PendingWindow, WindowConfig};
 use crate::command::sys as sys_cmd;
+use crate::command::ScheduledCommand;
 use druid_shell::WindowBuilder;
 
 pub(crate) const RUN_COMMANDS_TOKEN: IdleToken = IdleToken::new(1);
@@ -56,6 +64,7 @@ pub struct DruidHandler<T> {
     app_state: AppState<T>,
     /// The id for the current window.
     window_id: WindowId,
+    click_counter: ClickCounter,
 }
 
 /// The top level event handler.
@@ -63,7 +72,8 @@ pub struct DruidHandler<T> {
 /// This corresponds to the `AppHandler` trait in druid-shell, which is only
 /// used to handle events that are not associated with a window.
 ///
-/// Currently, this means only menu items on macOS when no window is open.
+/// Currently, this means menu items on macOS when no window is open, and
+/// power events.
 pub(crate) struct AppHandler<T> {
     app_state: AppState<T>,
 }
@@ -94,13 +104,26 @@ struct Inner<T> {
     /// the application-level menu, only set on macos and only if there
     /// are no open windows.
     root_menu: Option<MenuManager<T>>,
+    /// The menu shared by all windows that don't have their own.
+    app_menu: Option<AppMenu<T>>,
     /// The id of the most-recently-focused window that has a menu. On macOS, this
     /// is the window that's currently in charge of the app menu.
     #[allow(unused_variables)]
     menu_window: Option<WindowId>,
     pub(crate) env: Env,
+    /// The env as the application customized it, before it was zoomed.
+    unzoomed_env: Env,
+    /// The env before the application customized it, with the current
+    /// system settings.
+    system_env: Env,
     pub(crate) data: T,
     ime_focus_change: Option<Box<dyn Fn()>>,
+    /// The most recent mouse move that has not been delivered yet, and the
+    /// window it belongs to. See [`AppState::mouse_move`].
+    pending_mouse_move: Option<(WindowId, MouseEvent)>,
+    /// Logs the changes of `data` before every update, if it was asked for.
+    #[cfg(feature = "data-diff")]
+    data_diff: Option<DiffLog<T>>,
 }
 
 /// All active windows.
@@ -144,6 +167,18 @@ impl<T> Windows<T> {
     }
 }
 
+impl<T> Registries for Windows<T> {
+    fn registry(&self, window: WindowId) -> Option<&WidgetRegistry> {
+        self.windows.get(&window).map(Window::registry)
+    }
+
+    fn find(&self, widget: WidgetId) -> Option<(WindowId, &WidgetInfo)> {
+        self.windows
+            .iter()
+            .find_map(|(id, win)| Some((*id, win.registry().get(widget)?)))
+    }
+}
+
 impl<T> AppHandler<T> {
     pub(crate) fn new(app_state: AppState<T>) -> Self {
         Self { app_state }
@@ -155,21 +190,32 @@ impl<T> AppState<T> {
         app: Application,
         data: T,
         env: Env,
+        system_env: Env,
         delegate: Option<Box<dyn AppDelegate<T>>>,
         ext_event_host: ExtEventHost,
     ) -> Self {
+        // the application may start zoomed.
+        let zoom = theme::clamp_zoom(env.get(theme::UI_ZOOM));
+        let unzoomed_env = env.adding(theme::UI_ZOOM, 1.0);
+        let env = theme::zoomed(&unzoomed_env, zoom);
         let inner = Rc::new(RefCell::new(Inner {
             app,
             delegate,
             command_queue: VecDeque::new(),
             file_dialogs: HashMap::new(),
             root_menu: None,
+            app_menu: None,
             menu_window: None,
             ext_event_host,
             data,
             env,
+            unzoomed_env,
+            system_env,
             windows: Windows::default(),
             ime_focus_change: None,
+            pending_mouse_move: None,
+            #[cfg(feature = "data-diff")]
+            data_diff: None,
         }));
 
         AppState { inner }
@@ -178,6 +224,10 @@ impl<T> AppState<T> {
     pub(crate) fn app(&self) -> Application {
         self.inner.borrow().app.clone()
     }
+
+    pub(crate) fn set_app_menu(&mut self, menu: Option<AppMenu<T>>) {
+        self.inner.borrow_mut().app_menu = menu;
+    }
 }
 
 impl<T: Data> Inner<T> {
@@ -214,12 +264,14 @@ impl<T: Data> Inner<T> {
             ref mut data,
             ref ext_event_host,
             ref env,
+            ref windows,
             ..
         } = self;
         let mut ctx = DelegateCtx {
             command_queue,
             app_data_type: TypeId::of::<T>(),
             ext_event_host,
+            registries: windows,
         };
         delegate
             .as_deref_mut()
@@ -258,17 +310,30 @@ impl<T: Data> Inner<T> {
     /// We clean up resources and notifiy the delegate, if necessary.
     fn remove_window(&mut self, window_id: WindowId) {
         self.with_delegate(|del, data, env, ctx| del.window_removed(window_id, data, env, ctx));
+        if matches!(&self.pending_mouse_move, Some((id, _)) if *id == window_id) {
+            self.pending_mouse_move = None;
+        }
         // when closing the last window:
         if let Some(mut win) = self.windows.remove(window_id) {
             if self.windows.windows.is_empty() {
                 // on mac we need to keep the menu around
-                self.root_menu = win.menu.take();
+                self.root_menu = match &self.app_menu {
+                    // the window's menu may have its own additions.
+                    Some(app_menu) => {
+                        let mut root_menu = app_menu.manager(None);
+                        let menu = root_menu.initialize(None, &self.data, &self.env);
+                        self.app.set_menu(menu);
+                        Some(root_menu)
+                    }
+                    None => win.menu.take(),
+                };
                 // If there are even no pending windows, we quit the run loop.
                 if self.windows.count() == 0 {
                     #[cfg(any(target_os = "windows", feature = "x11"))]
                     self.app.quit();
                 }
             }
+            self.command_queue.append(&mut win.remove_widgets());
         }
 
         // if we are closing the window that is currently responsible for
@@ -325,6 +390,31 @@ impl<T: Data> Inner<T> {
     fn configure_window(&mut self, config: &WindowConfig, id: WindowId) {
         if let Some(win) = self.windows.get_mut(id) {
             config.apply_to_handle(&mut win.handle);
+            if let Some(color) = &config.background_color {
+                win.set_background_color(color.clone());
+            }
+        }
+    }
+
+    fn toggle_frame_stats(&mut self, id: WindowId) {
+        if let Some(win) = self.windows.get_mut(id) {
+            win.toggle_frame_stats();
+        }
+    }
+
+    fn toggle_inspector(&mut self, id: WindowId) {
+        if let Some(win) = self.windows.get_mut(id) {
+            win.toggle_inspector();
+        }
+    }
+
+    fn capture_image(&mut self, id: WindowId) {
+        if let Some(win) = self.windows.get_mut(id) {
+            let scale = win.handle.get_scale().map(|scale| scale.x()).unwrap_or(1.0);
+            match win.capture_image(&mut self.command_queue, &self.data, &self.env, scale) {
+                Ok(image) => self.append_command(sys_cmd::WINDOW_IMAGE_CAPTURED.with(image).to(id)),
+                Err(e) => tracing::warn!("failed to capture window image: {}", e),
+            }
         }
     }
 
@@ -333,6 +423,9 @@ impl<T: Data> Inner<T> {
             win.prepare_paint(&mut self.command_queue, &mut self.data, &self.env);
         }
         self.do_update();
+        if let Some(win) = self.windows.get_mut(window_id) {
+            win.layout_if_needed(&mut self.command_queue, &self.data, &self.env);
+        }
     }
 
     fn paint(&mut self, window_id: WindowId, piet: &mut Piet, invalid: &Region) {
@@ -347,12 +440,27 @@ impl<T: Data> Inner<T> {
         }
     }
 
+    /// Dispatch a command to the delegate and then to the windows.
+    ///
+    /// If the delegate changes the data, `update` is run before the command
+    /// goes to the windows, so that widgets never handle a command with data
+    /// that they haven't been updated with. Otherwise `update` is left to the
+    /// caller, which runs it once the whole command queue has been processed.
     fn dispatch_cmd(&mut self, cmd: Command) -> Handled {
+        let old_data = self.delegate.as_ref().map(|_| self.data.clone());
         let handled = self.delegate_cmd(&cmd);
-        self.do_update();
         if handled.is_handled() {
             return handled;
         }
+        if matches!(&old_data, Some(old_data) if !old_data.same(&self.data)) {
+            self.do_update();
+        }
+
+        if let Some(write) = cmd.get(sys_cmd::WRITE_DATA) {
+            if !matches!(cmd.target(), Target::Widget(_)) && write.apply(&mut self.data) {
+                return Handled::Yes;
+            }
+        }
 
         match cmd.target() {
             Target::Window(id) => {
@@ -424,6 +532,11 @@ impl<T: Data> Inner<T> {
             _ => (),
         }
 
+        // widgets see the new input mode while handling the event that changed it.
+        if let Some(mode) = InputMode::for_event(&event) {
+            self.set_input_mode(mode);
+        }
+
         // if the event was swallowed by the delegate we consider it handled?
         let event = match self.delegate_event(source_id, event) {
             Some(event) => event,
@@ -439,11 +552,22 @@ impl<T: Data> Inner<T> {
 
     fn show_context_menu(&mut self, window_id: WindowId, cmd: &Command) {
         if let Some(win) = self.windows.get_mut(window_id) {
-            match cmd
+            // menus that don't use any data can be shown with any application state.
+            let menu = cmd
                 .get_unchecked(sys_cmd::SHOW_CONTEXT_MENU)
                 .take()
-                .and_then(|b| b.downcast::<ContextMenu<T>>().ok())
-            {
+                .and_then(|b| {
+                    b.downcast::<ContextMenu<T>>()
+                        .map(|menu| *menu)
+                        .or_else(|b| {
+                            b.downcast::<ContextMenu<()>>().map(|menu| ContextMenu {
+                                menu: menu.menu.with_data(),
+                                location: menu.location,
+                            })
+                        })
+                        .ok()
+                });
+            match menu {
                 Some(menu) => {
                     win.show_context_menu(menu.menu, menu.location, &self.data, &self.env)
                 }
@@ -455,7 +579,59 @@ impl<T: Data> Inner<T> {
         }
     }
 
+    /// Re-read the system settings, and update the env if they changed.
+    fn system_settings_changed(&mut self) {
+        let text = Application::get_text_settings();
+        let mouse = Application::get_mouse_settings();
+        let old_env = self.env.clone();
+        theme::update_system_settings(&mut self.unzoomed_env, &mut self.system_env, &text, &mouse);
+        self.env = theme::zoomed(&self.unzoomed_env, self.env.get(theme::UI_ZOOM));
+        if !self.env.same(&old_env) {
+            self.do_update();
+        }
+    }
+
+    /// Hand a scheduled command to the window that it targets, or to any
+    /// window, whose timer will submit it.
+    fn schedule_command(&mut self, command: ScheduledCommand) {
+        let window = match command.command.target() {
+            Target::Window(id) if self.windows.windows.contains_key(&id) => {
+                self.windows.get_mut(id)
+            }
+            _ => self.windows.windows.values_mut().next(),
+        };
+        match window {
+            Some(window) => window.schedule_command(command),
+            None => tracing::warn!("no window to schedule {:?} on.", command.command),
+        }
+    }
+
+    /// Change the zoom of the UI, in all windows.
+    fn set_zoom(&mut self, zoom: f64) {
+        let zoom = theme::clamp_zoom(zoom);
+        if zoom != self.env.get(theme::UI_ZOOM) {
+            self.env = theme::zoomed(&self.unzoomed_env, zoom);
+            // widgets relayout when the zoom changes, see `WidgetPod::update`.
+            self.do_update();
+        }
+    }
+
+    /// Change the input mode, in all windows.
+    ///
+    /// Widgets see the change in the update that follows every event.
+    fn set_input_mode(&mut self, mode: InputMode) {
+        if mode != self.env.get(theme::INPUT_MODE) {
+            self.unzoomed_env.set(theme::INPUT_MODE, mode);
+            self.env.set(theme::INPUT_MODE, mode);
+        }
+    }
+
     fn do_update(&mut self) {
+        #[cfg(feature = "data-diff")]
+        if let Some(log) = &mut self.data_diff {
+            log.log(&self.data);
+        }
+
         // we send `update` to all windows, not just the active one:
         for window in self.windows.iter_mut() {
             window.update(&mut self.command_queue, &self.data, &self.env);
@@ -542,11 +718,18 @@ impl<T: Data> DruidHandler<T> {
         DruidHandler {
             app_state,
             window_id,
+            click_counter: ClickCounter::default(),
         }
     }
 }
 
 impl<T: Data> AppState<T> {
+    #[cfg(feature = "data-diff")]
+    pub(crate) fn set_data_diff(&mut self, diff: Option<fn(&T, &T) -> crate::DataDiff>) {
+        let mut inner = self.inner.borrow_mut();
+        inner.data_diff = diff.map(|diff| DiffLog::new(&inner.data, diff));
+    }
+
     pub(crate) fn data(&self) -> T {
         self.inner.borrow().data.clone()
     }
@@ -571,6 +754,10 @@ impl<T: Data> AppState<T> {
         self.inner.borrow_mut().window_got_focus(window_id)
     }
 
+    fn system_settings_changed(&mut self) {
+        self.inner.borrow_mut().system_settings_changed()
+    }
+
     /// Send an event to the widget hierarchy.
     ///
     /// Returns `true` if the event produced an action.
@@ -578,6 +765,13 @@ impl<T: Data> AppState<T> {
     /// This is principally because in certain cases (such as keydown on Windows)
     /// the OS needs to know if an event was handled.
     fn do_window_event(&mut self, event: Event, window_id: WindowId) -> Handled {
+        // a pending mouse move happened before this event, so it has to be
+        // delivered first.
+        self.flush_mouse_move();
+        self.dispatch_window_event(event, window_id)
+    }
+
+    fn dispatch_window_event(&mut self, event: Event, window_id: WindowId) -> Handled {
         let result = self.inner.borrow_mut().do_window_event(window_id, event);
         self.process_commands();
         self.inner.borrow_mut().do_update();
@@ -588,7 +782,42 @@ impl<T: Data> AppState<T> {
         result
     }
 
+    /// Handle a mouse move.
+    ///
+    /// Pointer devices can report movement far more often than we can paint,
+    /// and every move costs a full event and update pass, so moves are not
+    /// delivered immediately. Instead we hold on to the most recent one and
+    /// deliver it at the start of the next frame, or before the next event,
+    /// whichever comes first.
+    fn mouse_move(&mut self, event: &MouseEvent, window_id: WindowId) {
+        let other_window = matches!(
+            &self.inner.borrow().pending_mouse_move,
+            Some((id, _)) if *id != window_id
+        );
+        if other_window {
+            self.flush_mouse_move();
+        }
+
+        let mut inner = self.inner.borrow_mut();
+        let prev = inner.pending_mouse_move.replace((window_id, event.clone()));
+        if prev.is_none() {
+            if let Some(win) = inner.windows.get_mut(window_id) {
+                win.handle.request_anim_frame();
+            }
+        }
+    }
+
+    /// Deliver the pending mouse move, if there is one.
+    fn flush_mouse_move(&mut self) {
+        let pending = self.inner.borrow_mut().pending_mouse_move.take();
+        if let Some((window_id, event)) = pending {
+            let event = Event::MouseMove(event.into());
+            self.dispatch_window_event(event, window_id);
+        }
+    }
+
     fn prepare_paint_window(&mut self, window_id: WindowId) {
+        self.flush_mouse_move();
         self.inner.borrow_mut().prepare_paint(window_id);
     }
 
@@ -597,6 +826,7 @@ impl<T: Data> AppState<T> {
     }
 
     fn idle(&mut self, token: IdleToken) {
+        self.flush_mouse_move();
         match token {
             RUN_COMMANDS_TOKEN => {
                 self.process_commands();
@@ -622,13 +852,44 @@ impl<T: Data> AppState<T> {
     }
 
     fn process_ext_events(&mut self) {
-        loop {
-            let ext_cmd = self.inner.borrow_mut().ext_event_host.recv();
-            match ext_cmd {
-                Some(cmd) => self.handle_cmd(cmd),
-                None => break,
+        // commands that arrive while we handle these get their own idle callback.
+        let commands = self.inner.borrow_mut().ext_event_host.recv_pending();
+        for cmd in commands {
+            self.handle_cmd(cmd);
+        }
+    }
+
+    /// Tell the application that the system or its displays went to sleep or
+    /// woke up.
+    fn power_event(&mut self, event: PowerEvent) {
+        let cmd = sys_cmd::POWER_EVENT.with(event).to(Target::Global);
+        self.inner.borrow_mut().append_command(cmd);
+        self.process_commands();
+        let mut inner = self.inner.borrow_mut();
+        if let PowerEvent::Resumed | PowerEvent::DisplayOn = event {
+            for win in inner.windows.iter_mut() {
+                win.handle.invalidate();
             }
         }
+        inner.do_update();
+    }
+
+    /// Tell the application that the power status has changed.
+    fn power_status_changed(&mut self, status: PowerStatus) {
+        let cmd = sys_cmd::POWER_STATUS_CHANGED
+            .with(status)
+            .to(Target::Global);
+        self.inner.borrow_mut().append_command(cmd);
+        self.process_commands();
+        self.inner.borrow_mut().do_update();
+    }
+
+    /// Tell the application that the locale settings have changed.
+    fn locale_changed(&mut self) {
+        let cmd = sys_cmd::LOCALE_CHANGED.to(Target::Global);
+        self.inner.borrow_mut().append_command(cmd);
+        self.process_commands();
+        self.inner.borrow_mut().do_update();
     }
 
     /// Handle a 'command' message from druid-shell. These map to  an item
@@ -665,6 +926,11 @@ impl<T: Data> AppState<T> {
                 }
             }
             _ if cmd.is(sys_cmd::CLOSE_ALL_WINDOWS) => self.request_close_all_windows(),
+            _ if cmd.is(sys_cmd::SCHEDULE_COMMAND) => self.schedule_command(cmd),
+            _ if cmd.is(sys_cmd::ZOOM_IN) => self.step_zoom(true),
+            _ if cmd.is(sys_cmd::ZOOM_OUT) => self.step_zoom(false),
+            _ if cmd.is(sys_cmd::RESET_ZOOM) => self.set_zoom(1.0),
+            _ if cmd.is(sys_cmd::SET_ZOOM) => self.set_zoom(*cmd.get_unchecked(sys_cmd::SET_ZOOM)),
             T::Window(id) if cmd.is(sys_cmd::INVALIDATE_IME) => self.invalidate_ime(cmd, id),
             // these should come from a window
             // FIXME: we need to be able to open a file without a window handle
@@ -677,6 +943,9 @@ impl<T: Data> AppState<T> {
                 }
             }
             T::Window(id) if cmd.is(sys_cmd::SHOW_WINDOW) => self.show_window(id),
+            T::Window(id) if cmd.is(sys_cmd::TOGGLE_FRAME_STATS) => self.toggle_frame_stats(id),
+            T::Window(id) if cmd.is(sys_cmd::TOGGLE_INSPECTOR) => self.toggle_inspector(id),
+            T::Window(id) if cmd.is(sys_cmd::CAPTURE_WINDOW_IMAGE) => self.capture_image(id),
             T::Window(id) if cmd.is(sys_cmd::PASTE) => self.do_paste(id),
             _ if cmd.is(sys_cmd::CLOSE_WINDOW) => {
                 tracing::warn!("CLOSE_WINDOW command must target a window.")
@@ -684,6 +953,15 @@ impl<T: Data> AppState<T> {
             _ if cmd.is(sys_cmd::SHOW_WINDOW) => {
                 tracing::warn!("SHOW_WINDOW command must target a window.")
             }
+            _ if cmd.is(sys_cmd::TOGGLE_FRAME_STATS) => {
+                tracing::warn!("TOGGLE_FRAME_STATS command must target a window.")
+            }
+            _ if cmd.is(sys_cmd::TOGGLE_INSPECTOR) => {
+                tracing::warn!("TOGGLE_INSPECTOR command must target a window.")
+            }
+            _ if cmd.is(sys_cmd::CAPTURE_WINDOW_IMAGE) => {
+                tracing::warn!("CAPTURE_WINDOW_IMAGE command must target a window.")
+            }
             _ if cmd.is(sys_cmd::SHOW_OPEN_PANEL) => {
                 tracing::warn!("SHOW_OPEN_PANEL command must target a window.")
             }
@@ -748,14 +1026,20 @@ impl<T: Data> AppState<T> {
     }
 
     fn handle_dialog_response(&mut self, token: FileDialogToken, file_info: Option<FileInfo>) {
+        self.handle_dialog_files(token, file_info.into_iter().collect())
+    }
+
+    /// Send the accept command once for each file, or the cancel command if
+    /// there are none.
+    fn handle_dialog_files(&mut self, token: FileDialogToken, files: Vec<FileInfo>) {
         let mut inner = self.inner.borrow_mut();
         if let Some(dialog_info) = inner.file_dialogs.remove(&token) {
-            let cmd = if let Some(info) = file_info {
-                dialog_info.accept_cmd.with(info).to(dialog_info.id)
-            } else {
-                dialog_info.cancel_cmd.to(dialog_info.id)
-            };
-            inner.append_command(cmd);
+            if files.is_empty() {
+                inner.append_command(dialog_info.cancel_cmd.to(dialog_info.id));
+            }
+            for info in files {
+                inner.append_command(dialog_info.accept_cmd.with(info).to(dialog_info.id));
+            }
         } else {
             tracing::error!("unknown dialog token");
         }
@@ -813,6 +1097,33 @@ impl<T: Data> AppState<T> {
         }
     }
 
+    fn toggle_frame_stats(&mut self, id: WindowId) {
+        self.inner.borrow_mut().toggle_frame_stats(id);
+    }
+
+    fn toggle_inspector(&mut self, id: WindowId) {
+        self.inner.borrow_mut().toggle_inspector(id);
+    }
+
+    fn capture_image(&mut self, id: WindowId) {
+        self.inner.borrow_mut().capture_image(id);
+    }
+
+    fn schedule_command(&mut self, cmd: Command) {
+        if let Some(command) = cmd.get_unchecked(sys_cmd::SCHEDULE_COMMAND).take() {
+            self.inner.borrow_mut().schedule_command(command);
+        }
+    }
+
+    fn step_zoom(&mut self, zoom_in: bool) {
+        let zoom = self.inner.borrow().env.get(theme::UI_ZOOM);
+        self.set_zoom(theme::step_zoom(zoom, zoom_in));
+    }
+
+    fn set_zoom(&mut self, zoom: f64) {
+        self.inner.borrow_mut().set_zoom(zoom);
+    }
+
     fn do_paste(&mut self, window_id: WindowId) {
         let event = Event::Paste(self.inner.borrow().app.clipboard());
         self.inner.borrow_mut().do_window_event(window_id, event);
@@ -861,9 +1172,23 @@ impl<T: Data> AppState<T> {
         let env = self.env();
 
         pending.size_policy = config.size_policy;
+        pending.background_color = config.background_color.clone();
+        pending.placement = config.deferred_placement();
+        if config.background_color.is_none() && !pending.transparent {
+            // so that the window doesn't flash in the platform's default color
+            // before we paint it.
+            builder.set_background_color(env.get(theme::WINDOW_BACKGROUND_COLOR));
+        }
         pending.title.resolve(&data, &env);
         builder.set_title(pending.title.display_text().to_string());
 
+        if pending.menu.is_none() {
+            let app_menu = self.inner.borrow().app_menu.clone();
+            pending.menu = match app_menu {
+                Some(app_menu) => Some(app_menu.manager(pending.customize_menu.take())),
+                None => MenuManager::platform_default(),
+            };
+        }
         let platform_menu = pending
             .menu
             .as_mut()
@@ -884,6 +1209,18 @@ impl<T: Data> crate::shell::AppHandler for AppHandler<T> {
     fn command(&mut self, id: u32) {
         self.app_state.handle_system_cmd(id, None)
     }
+
+    fn power_event(&mut self, event: PowerEvent) {
+        self.app_state.power_event(event)
+    }
+
+    fn power_status_changed(&mut self, status: PowerStatus) {
+        self.app_state.power_status_changed(status)
+    }
+
+    fn locale_changed(&mut self) {
+        self.app_state.locale_changed()
+    }
 }
 
 impl<T: Data> WinHandler for DruidHandler<T> {
@@ -924,10 +1261,24 @@ impl<T: Data> WinHandler for DruidHandler<T> {
         self.app_state.handle_dialog_response(token, file_info);
     }
 
+    fn open_files(&mut self, token: FileDialogToken, files: Vec<FileInfo>) {
+        self.app_state.handle_dialog_files(token, files);
+    }
+
     fn mouse_down(&mut self, event: &MouseEvent) {
-        // TODO: double-click detection (or is this done in druid-shell?)
-        let event = Event::MouseDown(event.clone().into());
-        self.app_state.do_window_event(event, self.window_id);
+        // We count the clicks ourselves, because not every platform does, and those that do
+        // don't agree on what happens after a triple click.
+        let mut event: crate::MouseEvent = event.clone().into();
+        let env = self.app_state.env();
+        event.count = self.click_counter.count_for_click(
+            event.pos,
+            event.button,
+            Instant::now(),
+            Duration::from_millis(env.get(theme::DOUBLE_CLICK_INTERVAL)),
+            env.get(theme::DOUBLE_CLICK_DISTANCE),
+        );
+        self.app_state
+            .do_window_event(Event::MouseDown(event), self.window_id);
     }
 
     fn mouse_up(&mut self, event: &MouseEvent) {
@@ -936,8 +1287,7 @@ impl<T: Data> WinHandler for DruidHandler<T> {
     }
 
     fn mouse_move(&mut self, event: &MouseEvent) {
-        let event = Event::MouseMove(event.clone().into());
-        self.app_state.do_window_event(event, self.window_id);
+        self.app_state.mouse_move(event, self.window_id);
     }
 
     fn mouse_leave(&mut self) {
@@ -966,6 +1316,24 @@ impl<T: Data> WinHandler for DruidHandler<T> {
         self.app_state.do_window_event(event, self.window_id);
     }
 
+    fn drag_over(&mut self, event: &DropEvent) -> bool {
+        self.app_state
+            .do_window_event(Event::DragOver(event.clone()), self.window_id)
+            .is_handled()
+    }
+
+    fn drag_leave(&mut self) {
+        self.app_state
+            .do_window_event(Event::DragLeave, self.window_id);
+    }
+
+    fn dropped(&mut self, event: &DropEvent, data: &Clipboard) -> bool {
+        let event = Event::Dropped(event.clone(), data.clone());
+        self.app_state
+            .do_window_event(event, self.window_id)
+            .is_handled()
+    }
+
     fn got_focus(&mut self) {
         self.app_state.window_got_focus(self.window_id);
     }
@@ -975,6 +1343,20 @@ impl<T: Data> WinHandler for DruidHandler<T> {
             .do_window_event(Event::Timer(token), self.window_id);
     }
 
+    fn suspended(&mut self) {
+        self.app_state
+            .do_window_event(Event::WindowSuspended, self.window_id);
+    }
+
+    fn resumed(&mut self) {
+        self.app_state
+            .do_window_event(Event::WindowResumed, self.window_id);
+    }
+
+    fn system_settings_changed(&mut self) {
+        self.app_state.system_settings_changed();
+    }
+
     fn idle(&mut self, token: IdleToken) {
         self.app_state.idle(token);
     }
@@ -16,18 +16,26 @@
 
 use std::collections::{HashMap, VecDeque};
 use std::mem;
+use std::rc::Rc;
 use tracing::{error, info, info_span};
 
 // Automatically defaults to std::time::Instant on non Wasm platforms
 use instant::Instant;
 
-use crate::piet::{Color, Piet, RenderContext};
-use crate::shell::{text::InputHandler, Counter, Cursor, Region, TextFieldToken, WindowHandle};
+use crate::piet::{self, Color, Device, ImageBuf, ImageFormat, Piet, RenderContext};
+use crate::shell::{
+    text::InputHandler, Counter, Cursor, HotKey, MouseButton, Region, SysMods, TextFieldToken,
+    WindowHandle,
+};
 
-use crate::app::{PendingWindow, WindowSizePolicy};
-use crate::contexts::ContextState;
-use crate::core::{CommandQueue, FocusChange, WidgetState};
-use crate::menu::{MenuItemId, MenuManager};
+use crate::app::{PendingWindow, Placement, WindowSizePolicy};
+use crate::command::{sys as sys_cmd, ScheduledCommand};
+use crate::contexts::{ContextState, RemovalContext};
+use crate::core::{CommandQueue, FocusChange, TimerRequest, WidgetState};
+use crate::frame_stats::{FrameStats, Pass, OVERLAY_RECT};
+use crate::inspector::Inspector;
+use crate::menu::{MenuChanges, MenuItemId, MenuManager};
+use crate::registry::WidgetRegistry;
 use crate::text::TextFieldRegistration;
 use crate::util::ExtendDrain;
 use crate::widget::LabelText;
@@ -40,6 +48,9 @@ use crate::{
 
 pub type ImeUpdateFn = dyn FnOnce(crate::shell::text::Event);
 
+/// If more than this many rects are invalid, we invalidate the whole window instead.
+const MAX_INVALID_RECTS: usize = 64;
+
 /// A unique identifier for a window.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct WindowId(u64);
@@ -51,6 +62,8 @@ pub struct Window<T> {
     pub(crate) title: LabelText<T>,
     size_policy: WindowSizePolicy,
     size: Size,
+    /// Where to put the window at the next layout.
+    placement: Option<Placement>,
     invalid: Region,
     pub(crate) menu: Option<MenuManager<T>>,
     pub(crate) context_menu: Option<(MenuManager<T>, Point)>,
@@ -59,11 +72,24 @@ pub struct Window<T> {
     pub(crate) last_mouse_pos: Option<Point>,
     pub(crate) focus: Option<WidgetId>,
     pub(crate) handle: WindowHandle,
-    pub(crate) timers: HashMap<TimerToken, WidgetId>,
+    pub(crate) timers: HashMap<TimerToken, TimerRequest>,
     pub(crate) transparent: bool,
+    /// The color the window is cleared to; if `None`, the theme's
+    /// `WINDOW_BACKGROUND_COLOR` is used.
+    background_color: Option<Color>,
     pub(crate) ime_handlers: Vec<(TextFieldToken, TextFieldRegistration)>,
     ext_handle: ExtEventSink,
     pub(crate) ime_focus_change: Option<Option<TextFieldToken>>,
+    frame_stats: FrameStats,
+    inspector: Inspector,
+    /// The widgets of the window, as of the last layout.
+    registry: WidgetRegistry,
+    /// Kept by every widget to tell it when it is removed; made on the first
+    /// lifecycle pass.
+    removal: Option<Rc<RemovalContext>>,
+    /// Whether the platform has suspended the window, in which case it can't
+    /// be painted.
+    suspended: bool,
 }
 
 impl<T> Window<T> {
@@ -78,9 +104,11 @@ impl<T> Window<T> {
             root: WidgetPod::new(pending.root),
             size_policy: pending.size_policy,
             size: Size::ZERO,
+            placement: pending.placement,
             invalid: Region::EMPTY,
             title: pending.title,
             transparent: pending.transparent,
+            background_color: pending.background_color,
             menu: pending.menu,
             context_menu: None,
             last_anim: None,
@@ -91,8 +119,51 @@ impl<T> Window<T> {
             ext_handle,
             ime_handlers: Vec::new(),
             ime_focus_change: None,
+            frame_stats: FrameStats::default(),
+            inspector: Inspector::default(),
+            registry: WidgetRegistry::default(),
+            removal: None,
+            suspended: false,
         }
     }
+
+    /// The widgets of the window.
+    pub(crate) fn registry(&self) -> &WidgetRegistry {
+        &self.registry
+    }
+
+    /// Drop the widgets of the window, returning the commands that they
+    /// submitted when they were told that they were removed.
+    pub(crate) fn remove_widgets(self) -> CommandQueue {
+        let Window { root, removal, .. } = self;
+        drop(root);
+        removal
+            .map(|removal| removal.take_commands())
+            .unwrap_or_default()
+    }
+
+    /// Turn the frame timing overlay on or off.
+    pub(crate) fn toggle_frame_stats(&mut self) {
+        self.frame_stats.toggle();
+        self.handle.invalidate();
+    }
+
+    /// Change the color that the window is cleared to.
+    ///
+    /// This doesn't change the platform's background color, which is done by
+    /// [`WindowConfig::apply_to_handle`].
+    ///
+    /// [`WindowConfig::apply_to_handle`]: crate::WindowConfig::apply_to_handle
+    pub(crate) fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+        self.handle.invalidate();
+    }
+
+    /// Open or close the widget inspector.
+    pub(crate) fn toggle_inspector(&mut self) {
+        self.inspector.toggle();
+        self.handle.invalidate();
+    }
 }
 
 impl<T: Data> Window<T> {
@@ -152,6 +223,11 @@ impl<T: Data> Window<T> {
         env: &Env,
         process_commands: bool,
     ) {
+        // widgets that were dropped may have submitted commands.
+        if let Some(removal) = &self.removal {
+            queue.append(&mut removal.take_commands());
+        }
+
         // If children are changed during the handling of an event,
         // we need to send RouteWidgetAdded now, so that they are ready for update/layout.
         if widget_state.children_changed {
@@ -205,7 +281,7 @@ impl<T: Data> Window<T> {
         self.timers.extend_drain(&mut widget_state.timers);
 
         // If we need a new paint pass, make sure druid-shell knows it.
-        if self.wants_animation_frame() {
+        if self.wants_animation_frame() && !self.suspended {
             self.handle.request_anim_frame();
         }
         self.invalid.union_with(&widget_state.invalid);
@@ -240,18 +316,40 @@ impl<T: Data> Window<T> {
                 self.last_mouse_pos = Some(e.pos)
             }
             Event::Internal(InternalEvent::MouseLeave) => self.last_mouse_pos = None,
+            Event::WindowSuspended => self.suspended = true,
+            Event::WindowResumed => {
+                self.suspended = false;
+                // the time spent suspended shouldn't show up as one long
+                // animation frame.
+                self.last_anim = None;
+                self.handle.invalidate();
+            }
             _ => (),
         }
 
+        if self.inspector.is_enabled()
+            && matches!(
+                event,
+                Event::MouseMove(_) | Event::Internal(InternalEvent::MouseLeave)
+            )
+        {
+            // the highlighted widget follows the cursor.
+            self.handle.invalidate();
+        }
         let event = match event {
-            Event::Timer(token) => {
-                if let Some(widget_id) = self.timers.get(&token) {
-                    Event::Internal(InternalEvent::RouteTimer(token, *widget_id))
-                } else {
+            Event::Timer(token) => match self.timers.get(&token) {
+                Some(request) if request.command.is_some() => {
+                    if let Some(command) = self.timers.remove(&token).and_then(|r| r.command) {
+                        self.submit_scheduled_command(queue, command);
+                    }
+                    return Handled::Yes;
+                }
+                Some(request) => Event::Internal(InternalEvent::RouteTimer(token, request.widget)),
+                None => {
                     error!("No widget found for timer {:?}", token);
                     return Handled::No;
                 }
-            }
+            },
             other => other,
         };
 
@@ -266,7 +364,7 @@ impl<T: Data> Window<T> {
         }
 
         let mut widget_state = WidgetState::new(self.root.id(), Some(self.size));
-        let is_handled = {
+        let mut is_handled = {
             let mut state =
                 ContextState::new::<T>(queue, &self.ext_handle, &self.handle, self.id, self.focus);
             let mut notifications = VecDeque::new();
@@ -281,7 +379,11 @@ impl<T: Data> Window<T> {
             {
                 let _span = info_span!("event");
                 let _span = _span.enter();
+                let start = Instant::now();
                 self.root.event(&mut ctx, &event, data, env);
+                if self.frame_stats.is_enabled() {
+                    self.frame_stats.record_pass(Pass::Event, start.elapsed());
+                }
             }
 
             if !ctx.notifications.is_empty() {
@@ -293,6 +395,23 @@ impl<T: Data> Window<T> {
             Handled::from(ctx.is_handled)
         };
 
+        if let (Event::MouseDown(mouse), Handled::No) = (&event, is_handled) {
+            // the back and forward buttons navigate, unless a widget uses them.
+            match mouse.button {
+                MouseButton::BACK => queue.push_back(sys_cmd::NAVIGATE_BACK.to(self.id)),
+                MouseButton::FORWARD => queue.push_back(sys_cmd::NAVIGATE_FORWARD.to(self.id)),
+                _ => (),
+            }
+        }
+        if let (Event::KeyDown(key), Handled::No) = (&event, is_handled) {
+            // in debug builds, ctrl+shift+i toggles the inspector, unless a
+            // widget uses it.
+            if cfg!(debug_assertions) && HotKey::new(SysMods::CmdShift, "I").matches(key) {
+                queue.push_back(sys_cmd::TOGGLE_INSPECTOR.to(self.id));
+                is_handled = Handled::Yes;
+            }
+        }
+
         // Clean up the timer token and do it immediately after the event handling
         // because the token may be reused and re-added in a lifecycle pass below.
         if let Event::Internal(InternalEvent::RouteTimer(token, _)) = event {
@@ -322,6 +441,29 @@ impl<T: Data> Window<T> {
         is_handled
     }
 
+    /// Wait for the delay of a scheduled command, and then submit it.
+    pub(crate) fn schedule_command(&mut self, command: ScheduledCommand) {
+        let token = self.handle.request_timer(command.delay);
+        let request = TimerRequest {
+            widget: self.root.id(),
+            delay: command.delay,
+            command: Some(command),
+        };
+        self.timers.insert(token, request);
+    }
+
+    /// Submit a scheduled command whose timer fired, unless it was cancelled,
+    /// and wait for the next time if it repeats.
+    fn submit_scheduled_command(&mut self, queue: &mut CommandQueue, command: ScheduledCommand) {
+        if command.timer.is_cancelled() {
+            return;
+        }
+        queue.push_back(command.command.clone());
+        if command.repeat {
+            self.schedule_command(command);
+        }
+    }
+
     pub(crate) fn lifecycle(
         &mut self,
         queue: &mut CommandQueue,
@@ -330,9 +472,24 @@ impl<T: Data> Window<T> {
         env: &Env,
         process_commands: bool,
     ) {
+        if self.removal.is_none() {
+            let removal =
+                RemovalContext::new::<T>(self.id, self.handle.clone(), self.ext_handle.clone());
+            self.removal = Some(Rc::new(removal));
+        }
         let mut widget_state = WidgetState::new(self.root.id(), Some(self.size));
         let mut state =
             ContextState::new::<T>(queue, &self.ext_handle, &self.handle, self.id, self.focus);
+        state.removal = self.removal.as_ref();
+        // every widget that is shown gets its window origin, so this is when
+        // the registry is rebuilt.
+        let previous = match event {
+            LifeCycle::Internal(InternalLifeCycle::ParentWindowOrigin) => {
+                Some(mem::take(&mut self.registry))
+            }
+            _ => None,
+        };
+        state.registry = Some(&mut self.registry);
         let mut ctx = LifeCycleCtx {
             state: &mut state,
             widget_state: &mut widget_state,
@@ -342,6 +499,17 @@ impl<T: Data> Window<T> {
             let _span = info_span!("lifecycle");
             let _span = _span.enter();
             self.root.lifecycle(&mut ctx, event, data, env);
+
+            // widgets that were visible and are no longer shown at all, like the
+            // pages of a `Tabs` that was switched, weren't reached by the pass.
+            if let (Some(previous), Some(registry)) = (previous, ctx.state.registry.as_deref()) {
+                let widgets = registry.hidden_since(&previous);
+                if !widgets.is_empty() {
+                    let event =
+                        LifeCycle::Internal(InternalLifeCycle::RouteViewportHidden { widgets });
+                    self.root.lifecycle(&mut ctx, &event, data, env);
+                }
+            }
         }
 
         self.post_event_processing(&mut widget_state, queue, data, env, process_commands);
@@ -363,7 +531,11 @@ impl<T: Data> Window<T> {
         {
             let _span = info_span!("update");
             let _span = _span.enter();
+            let start = Instant::now();
             self.root.update(&mut update_ctx, data, env);
+            if self.frame_stats.is_enabled() {
+                self.frame_stats.record_pass(Pass::Update, start.elapsed());
+            }
         }
 
         if let Some(cursor) = &widget_state.cursor {
@@ -374,24 +546,56 @@ impl<T: Data> Window<T> {
     }
 
     pub(crate) fn invalidate_and_finalize(&mut self) {
+        if self.suspended {
+            // everything is repainted on resume.
+            self.invalid.clear();
+            return;
+        }
         if self.root.state().needs_layout {
+            // We don't know which parts of the window will change until we
+            // have done layout, so we just ask for a paint cycle; the damaged
+            // regions are invalidated in `layout_if_needed`.
+            self.handle.request_anim_frame();
+        }
+        if self.invalid.rects().len() > MAX_INVALID_RECTS {
             self.handle.invalidate();
         } else {
             for rect in self.invalid.rects() {
                 self.handle.invalidate_rect(*rect);
             }
         }
+        if self.frame_stats.is_enabled() && !self.invalid.is_empty() {
+            // the overlay shows the stats of the previous frame, so it has to
+            // be repainted along with everything else.
+            self.handle.invalidate_rect(OVERLAY_RECT);
+        }
+        if self.inspector.is_enabled() && !self.invalid.is_empty() {
+            // the inspector only sees the widgets that are painted, so paint
+            // all of them.
+            self.handle.invalidate();
+        }
         self.invalid.clear();
     }
 
+    /// Do a layout pass if one is needed, and invalidate the regions of the
+    /// window that changed as a result.
+    ///
+    /// This is called while preparing to paint.
+    pub(crate) fn layout_if_needed(&mut self, queue: &mut CommandQueue, data: &T, env: &Env) {
+        if self.root.state().needs_layout {
+            let damage = self.layout(queue, data, env);
+            self.invalid.union_with(&damage);
+            self.invalidate_and_finalize();
+        }
+    }
+
     #[cfg(test)]
     #[allow(dead_code)]
     pub(crate) fn invalid(&self) -> &Region {
         &self.invalid
     }
 
-    #[cfg(test)]
-    #[allow(dead_code)]
+    #[cfg(not(target_arch = "wasm32"))]
     pub(crate) fn invalid_mut(&mut self) -> &mut Region {
         &mut self.invalid
     }
@@ -424,23 +628,73 @@ impl<T: Data> Window<T> {
             self.layout(queue, data, env);
         }
 
+        let background = self.background(env);
         for &r in invalid.rects() {
-            piet.clear(
-                Some(r),
-                if self.transparent {
-                    Color::TRANSPARENT
-                } else {
-                    env.get(crate::theme::WINDOW_BACKGROUND_COLOR)
-                },
-            );
+            piet.clear(Some(r), background.clone());
         }
         self.paint(piet, invalid, queue, data, env);
     }
 
-    fn layout(&mut self, queue: &mut CommandQueue, data: &T, env: &Env) {
+    /// Paint the whole window offscreen into an image of the window's size
+    /// times `scale` pixels.
+    ///
+    /// The inspector and frame statistics overlays are left out.
+    pub(crate) fn capture_image(
+        &mut self,
+        queue: &mut CommandQueue,
+        data: &T,
+        env: &Env,
+        scale: f64,
+    ) -> Result<ImageBuf, piet::Error> {
+        if self.root.state().needs_layout {
+            self.layout(queue, data, env);
+        }
+        let pixel_size = (self.size * scale).expand();
+        let mut device = Device::new()?;
+        let mut target =
+            device.bitmap_target(pixel_size.width as usize, pixel_size.height as usize, scale)?;
+        {
+            let mut piet = target.render_context();
+            piet.clear(None, self.background(env));
+            let region = self.size.to_rect().into();
+            let widget_state = WidgetState::new(self.root.id(), Some(self.size));
+            let mut state =
+                ContextState::new::<T>(queue, &self.ext_handle, &self.handle, self.id, self.focus);
+            Self::paint_root(
+                &mut self.root,
+                &mut piet,
+                &region,
+                &mut state,
+                &widget_state,
+                data,
+                env,
+            );
+            piet.finish()?;
+        }
+        target.to_image_buf(ImageFormat::RgbaPremul)
+    }
+
+    /// The color that the window is cleared to before painting.
+    fn background(&self, env: &Env) -> Color {
+        if self.transparent {
+            Color::TRANSPARENT
+        } else if let Some(color) = &self.background_color {
+            color.clone()
+        } else {
+            env.get(crate::theme::WINDOW_BACKGROUND_COLOR)
+        }
+    }
+
+    /// Do a layout pass, returning the region that was damaged by widgets
+    /// moving or changing size.
+    fn layout(&mut self, queue: &mut CommandQueue, data: &T, env: &Env) -> Region {
         let mut widget_state = WidgetState::new(self.root.id(), Some(self.size));
         let mut state =
             ContextState::new::<T>(queue, &self.ext_handle, &self.handle, self.id, self.focus);
+        let stats_enabled = self.frame_stats.is_enabled();
+        if stats_enabled {
+            state.frame_stats = Some(&mut self.frame_stats);
+        }
         let mut layout_ctx = LayoutCtx {
             state: &mut state,
             widget_state: &mut widget_state,
@@ -451,6 +705,7 @@ impl<T: Data> Window<T> {
             WindowSizePolicy::Content => BoxConstraints::UNBOUNDED,
         };
 
+        let start = Instant::now();
         let content_size = {
             let _span = info_span!("layout");
             let _span = _span.enter();
@@ -465,8 +720,15 @@ impl<T: Data> Window<T> {
                 self.handle.set_size(full_size)
             }
         }
+        let size = self.size;
+        if let Some(position) = self.placement.take().and_then(|p| p.position(size)) {
+            self.handle.set_position(position);
+        }
         self.root
             .set_origin(&mut layout_ctx, data, env, Point::ORIGIN);
+        if stats_enabled {
+            self.frame_stats.record_pass(Pass::Layout, start.elapsed());
+        }
         self.lifecycle(
             queue,
             &LifeCycle::Internal(InternalLifeCycle::ParentWindowOrigin),
@@ -474,13 +736,14 @@ impl<T: Data> Window<T> {
             env,
             false,
         );
+        let damage = mem::replace(&mut widget_state.invalid, Region::EMPTY);
         self.post_event_processing(&mut widget_state, queue, data, env, true);
+        damage
     }
 
-    /// only expose `layout` for testing; normally it is called as part of `do_paint`
+    /// only expose `layout` for testing; normally it is called as part of `prepare_paint`
     #[cfg(not(target_arch = "wasm32"))]
-    #[cfg(test)]
-    pub(crate) fn just_layout(&mut self, queue: &mut CommandQueue, data: &T, env: &Env) {
+    pub(crate) fn just_layout(&mut self, queue: &mut CommandQueue, data: &T, env: &Env) -> Region {
         self.layout(queue, data, env)
     }
 
@@ -495,16 +758,61 @@ impl<T: Data> Window<T> {
         let widget_state = WidgetState::new(self.root.id(), Some(self.size));
         let mut state =
             ContextState::new::<T>(queue, &self.ext_handle, &self.handle, self.id, self.focus);
+        let stats_enabled = self.frame_stats.is_enabled();
+        if stats_enabled {
+            state.frame_stats = Some(&mut self.frame_stats);
+        }
+        let inspector_enabled = self.inspector.is_enabled();
+        if inspector_enabled {
+            self.inspector.begin_frame();
+            state.inspector = Some(&mut self.inspector);
+        }
+        let start = Instant::now();
+        Self::paint_root(
+            &mut self.root,
+            piet,
+            invalid,
+            &mut state,
+            &widget_state,
+            data,
+            env,
+        );
+
+        if inspector_enabled {
+            self.inspector
+                .paint_overlay(piet, self.last_mouse_pos, self.size);
+        }
+        if stats_enabled {
+            self.frame_stats.record_pass(Pass::Paint, start.elapsed());
+            self.frame_stats.paint_overlay(piet);
+            self.frame_stats.finish_frame();
+        }
+
+        if self.wants_animation_frame() {
+            self.handle.request_anim_frame();
+        }
+    }
+
+    /// Paint the root widget, followed by the z-ordered operations that were
+    /// recorded while painting it.
+    fn paint_root(
+        root: &mut WidgetPod<T, Box<dyn Widget<T>>>,
+        piet: &mut Piet,
+        invalid: &Region,
+        state: &mut ContextState,
+        widget_state: &WidgetState,
+        data: &T,
+        env: &Env,
+    ) {
         let mut ctx = PaintCtx {
             render_ctx: piet,
-            state: &mut state,
-            widget_state: &widget_state,
+            state,
+            widget_state,
             z_ops: Vec::new(),
             region: invalid.clone(),
             depth: 0,
         };
 
-        let root = &mut self.root;
         info_span!("paint").in_scope(|| {
             ctx.with_child_ctx(invalid.clone(), |ctx| root.paint_raw(ctx, data, env));
         });
@@ -520,10 +828,6 @@ impl<T: Data> Window<T> {
                 });
             });
         }
-
-        if self.wants_animation_frame() {
-            self.handle.request_anim_frame();
-        }
     }
 
     pub(crate) fn update_title(&mut self, data: &T, env: &Env) {
@@ -534,8 +838,20 @@ impl<T: Data> Window<T> {
 
     pub(crate) fn update_menu(&mut self, data: &T, env: &Env) {
         if let Some(menu) = &mut self.menu {
-            if let Some(new_menu) = menu.update(Some(self.id), data, env) {
-                self.handle.set_menu(new_menu);
+            match menu.update_items(Some(self.id), data, env) {
+                Some(MenuChanges::Replace(new_menu)) => self.handle.set_menu(new_menu),
+                Some(MenuChanges::Items(items)) => {
+                    for (id, item) in items {
+                        self.handle.update_menu_item(
+                            id,
+                            &item.title,
+                            item.hotkey.as_ref(),
+                            item.enabled,
+                            item.selected,
+                        );
+                    }
+                }
+                None => (),
             }
         }
         if let Some((menu, point)) = &mut self.context_menu {
@@ -572,6 +888,7 @@ impl<T: Data> Window<T> {
                 let event = LifeCycle::Internal(InternalLifeCycle::RouteFocusChanged { old, new });
                 self.lifecycle(queue, &event, data, env, false);
                 self.focus = new;
+                self.registry.set_focus(old, new);
                 // check if the newly focused widget has an IME session, and
                 // notify the system if so.
                 //
@@ -634,6 +951,12 @@ impl<T: Data> Window<T> {
             FocusChange::Focus(id) => Some(id),
             FocusChange::Next => self.widget_from_focus_chain(true),
             FocusChange::Previous => self.widget_from_focus_chain(false),
+            // stay put when there is nothing in that direction.
+            FocusChange::Direction(direction) => self.focus.map(|focus| {
+                self.registry
+                    .nearest_in_direction(focus, direction, self.focus_chain().iter().copied())
+                    .unwrap_or(focus)
+            }),
         }
     }
 